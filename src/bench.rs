@@ -0,0 +1,272 @@
+use crate::cli::BenchArgs;
+use roblox_rs::options::{ScriptKind, Target};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+// Runs each benchmark once to warm up, then as many times as fit in the time budget
+const HARNESS: &str = r#"
+local function __bench(name: string, run: () -> ())
+    run()
+    local iterations = 0
+    local start = os.clock()
+    local elapsed = 0
+    repeat
+        run()
+        iterations += 1
+        elapsed = os.clock() - start
+    until elapsed >= __benchSeconds
+    print(`__bench\t{name}\t{elapsed / iterations * 1e9}\t{iterations}`)
+end
+"#;
+
+struct Measurement {
+    name: String,
+    nanos: f64,
+    iterations: u64,
+}
+
+pub fn run(args: &BenchArgs) {
+    if let Err(error) = bench(args) {
+        eprintln!("error: {}", error);
+        process::exit(1);
+    }
+}
+
+fn bench(args: &BenchArgs) -> Result<(), String> {
+    let file_contents = fs::read_to_string(&args.file)
+        .map_err(|error| format!("Failed to read {}: {}", args.file, error))?;
+    let mut syntax_tree = syn::parse_file(&file_contents)
+        .map_err(|error| format!("Failed to parse {}: {}", args.file, error))?;
+
+    // The harness calls the benchmarks instead of `main`
+    syntax_tree
+        .items
+        .retain(|item| !matches!(item, syn::Item::Fn(item_fn) if item_fn.sig.ident == "main"));
+
+    let benches = bench_functions(&syntax_tree)?;
+    if benches.is_empty() {
+        return Err(format!("{} has no `#[bench]` functions", args.file));
+    }
+
+    let stem = Path::new(&args.file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut messages: Vec<String> = Vec::new();
+    let mut results = Vec::new();
+    #[cfg(feature = "luau")]
+    let mut embedded_notified = false;
+
+    for &level in &args.opt_levels {
+        let mut options = crate::transpile_options(&args.transpile, &args.file);
+        options.target = Target::Lune;
+        options.kind = Some(ScriptKind::Script);
+        options.opt_level = level;
+
+        let transpiled =
+            roblox_rs::transpile_tree(&args.file, &file_contents, syntax_tree.clone(), options)
+                .unwrap_or_else(|roblox_rs::UnsupportedConstruct(diagnostic)| {
+                    eprintln!("{}", diagnostic.render(&args.file));
+                    std::process::exit(1);
+                });
+        for message in transpiled.messages {
+            if !messages.contains(&message) {
+                eprintln!("{}", message);
+                messages.push(message);
+            }
+        }
+
+        let script = harness(&transpiled.output, &benches, args.time);
+        let path = std::env::temp_dir().join(format!("roblox-rs-bench-{}-O{}.luau", stem, level));
+        fs::write(&path, &script).expect("Failed to write benchmark script");
+
+        let output = process::Command::new(&args.lune)
+            .arg("run")
+            .arg(&path)
+            .output();
+        let _ = fs::remove_file(&path);
+
+        let stdout = match output {
+            Ok(output) if output.status.success() => {
+                String::from_utf8_lossy(&output.stdout).to_string()
+            }
+            Ok(output) => {
+                return Err(format!(
+                    "Benchmarks failed at --opt-level {}: {}",
+                    level,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                ))
+            }
+            #[cfg(not(feature = "luau"))]
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                return Err(format!(
+                    "`{}` wasn't found; install Lune, pass `--lune <path>`, or rebuild with `--features luau` to run the benchmarks in the embedded Luau VM",
+                    args.lune
+                ))
+            }
+            #[cfg(feature = "luau")]
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+                if !embedded_notified {
+                    eprintln!(
+                        "note: `{}` wasn't found; running the benchmarks in the embedded Luau VM",
+                        args.lune
+                    );
+                    embedded_notified = true;
+                }
+                run_embedded(&script).map_err(|error| {
+                    format!("Benchmarks failed at --opt-level {}: {}", level, error)
+                })?
+            }
+            Err(error) => return Err(format!("Failed to run `{}`: {}", args.lune, error)),
+        };
+
+        results.push((level, measurements(&stdout)));
+    }
+
+    print!("{}", report(&benches, &results));
+    Ok(())
+}
+
+/// Runs the benchmark script in the embedded Luau VM for when Lune isn't installed,
+/// returning what it prints.
+#[cfg(feature = "luau")]
+fn run_embedded(script: &str) -> Result<String, String> {
+    let lua = mlua::Lua::new();
+    let printed = std::rc::Rc::new(std::cell::RefCell::new(String::new()));
+
+    let output = printed.clone();
+    let print = lua
+        .create_function(move |_, values: mlua::Variadic<mlua::Value>| {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| value.to_string().unwrap_or_else(|_| "?".to_string()))
+                .collect();
+            writeln!(output.borrow_mut(), "{}", values.join("\t")).unwrap();
+            Ok(())
+        })
+        .map_err(|error| error.to_string())?;
+    lua.globals()
+        .set("print", print)
+        .map_err(|error| error.to_string())?;
+
+    lua.load(script).exec().map_err(|error| error.to_string())?;
+    Ok(printed.take())
+}
+
+/// The names of the zero-argument `#[bench]` functions at the top level of the file.
+fn bench_functions(file: &syn::File) -> Result<Vec<String>, String> {
+    file.items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Fn(item_fn)
+                if item_fn
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("bench")) =>
+            {
+                if !item_fn.sig.inputs.is_empty() {
+                    return Some(Err(format!(
+                        "`#[bench]` function `{}` must take no arguments",
+                        item_fn.sig.ident
+                    )));
+                }
+
+                Some(Ok(item_fn.sig.ident.to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn harness(output: &str, benches: &[String], seconds: f64) -> String {
+    let mut script = output.to_string();
+    writeln!(script, "\nlocal __benchSeconds = {}", seconds).unwrap();
+    script.push_str(HARNESS);
+
+    for bench in benches {
+        writeln!(script, "__bench(\"{}\", {})", bench, bench).unwrap();
+    }
+
+    script
+}
+
+fn measurements(stdout: &str) -> Vec<Measurement> {
+    stdout
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.strip_prefix("__bench\t")?.split('\t');
+            Some(Measurement {
+                name: fields.next()?.to_string(),
+                nanos: fields.next()?.parse().ok()?,
+                iterations: fields.next()?.parse().ok()?,
+            })
+        })
+        .collect()
+}
+
+/// A table with one row per benchmark and one column per level, with the speedup over the
+/// first level.
+fn report(benches: &[String], results: &[(u8, Vec<Measurement>)]) -> String {
+    let name_width = benches.iter().map(String::len).max().unwrap_or(0).max(5);
+
+    let mut report = format!("{:<width$}", "bench", width = name_width);
+    for (level, _) in results {
+        write!(report, "  {:>22}", format!("-O{}", level)).unwrap();
+    }
+    report.push('\n');
+
+    for bench in benches {
+        write!(report, "{:<width$}", bench, width = name_width).unwrap();
+
+        let baseline = results
+            .first()
+            .and_then(|(_, measurements)| find(measurements, bench))
+            .map(|measurement| measurement.nanos);
+
+        for (index, (_, measurements)) in results.iter().enumerate() {
+            let cell = match find(measurements, bench) {
+                Some(measurement) => {
+                    let speedup = match baseline {
+                        Some(baseline) if index > 0 => {
+                            format!(" ({:.2}x)", baseline / measurement.nanos)
+                        }
+                        _ => String::new(),
+                    };
+                    format!(
+                        "{}{} [{}]",
+                        duration(measurement.nanos),
+                        speedup,
+                        measurement.iterations
+                    )
+                }
+                None => "-".to_string(),
+            };
+
+            write!(report, "  {:>22}", cell).unwrap();
+        }
+        report.push('\n');
+    }
+
+    report
+}
+
+fn find<'a>(measurements: &'a [Measurement], name: &str) -> Option<&'a Measurement> {
+    measurements
+        .iter()
+        .find(|measurement| measurement.name == name)
+}
+
+fn duration(nanos: f64) -> String {
+    if nanos >= 1e9 {
+        format!("{:.2} s", nanos / 1e9)
+    } else if nanos >= 1e6 {
+        format!("{:.2} ms", nanos / 1e6)
+    } else if nanos >= 1e3 {
+        format!("{:.2} µs", nanos / 1e3)
+    } else {
+        format!("{:.0} ns", nanos)
+    }
+}
@@ -0,0 +1,22 @@
+//! `cargo roblox build|check|watch`: cargo runs `cargo-roblox roblox <args>`, which is
+//! forwarded to the `roblox-rs` binary installed next to this one.
+
+use std::process::Command;
+
+fn main() {
+    let mut args = std::env::args_os().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "roblox") {
+        args.next();
+    }
+
+    let roblox_rs = std::env::current_exe()
+        .expect("Failed to locate the cargo-roblox executable")
+        .with_file_name(format!("roblox-rs{}", std::env::consts::EXE_SUFFIX));
+
+    let status = Command::new(&roblox_rs)
+        .args(args)
+        .status()
+        .unwrap_or_else(|error| panic!("Failed to run {}: {}", roblox_rs.display(), error));
+
+    std::process::exit(status.code().unwrap_or(1));
+}
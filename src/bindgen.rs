@@ -0,0 +1,1066 @@
+use crate::cli::BindgenArgs;
+use roblox_rs::roblox_api::MemberKind;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+pub const API_DUMP_URL: &str =
+    "https://raw.githubusercontent.com/MaximumADHD/Roblox-Client-Tracker/roblox/Full-API-Dump.json";
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop", "macro",
+    "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self", "static",
+    "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+// Types the generated crate defines by hand instead of as opaque data types
+const PRELUDE: &str = r#"use std::collections::HashMap;
+use std::marker::PhantomData;
+
+/// Any Luau value.
+pub struct Variant {
+    _opaque: (),
+}
+
+pub struct RBXScriptConnection {
+    _opaque: (),
+}
+
+impl RBXScriptConnection {
+    pub fn disconnect(&self) {
+        unimplemented!()
+    }
+
+    pub fn connected(&self) -> bool {
+        unimplemented!()
+    }
+}
+
+/// A Roblox event whose listeners receive `Args`.
+pub struct RBXScriptSignal<Args = ()> {
+    _args: PhantomData<Args>,
+}
+
+impl RBXScriptSignal<()> {
+    pub fn connect(&self, callback: impl FnMut() + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn once(&self, callback: impl FnOnce() + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn wait(&self) {
+        unimplemented!()
+    }
+}
+"#;
+
+const GLOBALS: &str = r#"
+pub fn game() -> DataModel {
+    unimplemented!()
+}
+
+pub fn workspace() -> Workspace {
+    unimplemented!()
+}
+
+pub fn script() -> LuaSourceContainer {
+    unimplemented!()
+}
+"#;
+
+// `Tween::new` takes its property goals as a `props!` table and its timing as a plain
+// struct, lowered to `TweenService:Create` and `TweenInfo.new`
+const TWEEN: &str = r#"
+/// The timing of a tween; omitted fields take the `TweenInfo.new` defaults.
+pub struct TweenInfo {
+    pub time: f64,
+    pub easing_style: enums::EasingStyle,
+    pub easing_direction: enums::EasingDirection,
+    pub repeat_count: i32,
+    pub reverses: bool,
+    pub delay_time: f64,
+}
+
+impl Default for TweenInfo {
+    fn default() -> Self {
+        TweenInfo {
+            time: 1.0,
+            easing_style: enums::EasingStyle::Quad,
+            easing_direction: enums::EasingDirection::Out,
+            repeat_count: 0,
+            reverses: false,
+            delay_time: 0.0,
+        }
+    }
+}
+
+/// The property goals of a tween, built with `props! { transparency: 1.0 }`.
+pub struct Properties {
+    _opaque: (),
+}
+
+impl Properties {
+    #[doc(hidden)]
+    pub fn new() -> Self {
+        Properties { _opaque: () }
+    }
+}
+
+#[macro_export]
+macro_rules! props {
+    ($($name:ident: $value:expr),* $(,)?) => {{
+        $(let _ = &$value;)*
+        $crate::Properties::new()
+    }};
+}
+"#;
+
+/// A hand-written module of free functions over one or more services, emitted when the
+/// dump has every class it needs and lowered through the `functions` templates.
+struct HelperModule {
+    requires: &'static [&'static str],
+    source: &'static str,
+    functions: &'static [(&'static str, &'static str, Option<&'static str>)],
+}
+
+const HELPER_MODULES: &[HelperModule] = &[
+    HelperModule {
+        requires: &[],
+        source: TASK,
+        functions: &[],
+    },
+    HelperModule {
+        requires: &["RunService"],
+        source: RUN_SERVICE,
+        functions: RUN_SERVICE_FUNCTIONS,
+    },
+    HelperModule {
+        requires: &["UserInputService", "ContextActionService", "InputObject"],
+        source: INPUT,
+        functions: INPUT_FUNCTIONS,
+    },
+    HelperModule {
+        requires: &["CollectionService"],
+        source: COLLECTION,
+        functions: COLLECTION_FUNCTIONS,
+    },
+];
+
+// The Luau `task` library needs no templates, `task::spawn(f)` is already `task.spawn(f)`
+const TASK: &str = r#"
+/// A coroutine scheduled through the `task` library, stopped with `cancel`.
+pub struct Thread {
+    _opaque: (),
+}
+
+impl Thread {
+    pub fn cancel(&self) {
+        unimplemented!()
+    }
+}
+
+/// Scheduling through the Luau `task` library.
+pub mod task {
+    use super::*;
+
+    pub fn spawn(callback: impl FnOnce() + 'static) -> Thread {
+        unimplemented!()
+    }
+
+    pub fn defer(callback: impl FnOnce() + 'static) -> Thread {
+        unimplemented!()
+    }
+
+    pub fn delay(seconds: f64, callback: impl FnOnce() + 'static) -> Thread {
+        unimplemented!()
+    }
+
+    pub fn wait(seconds: f64) -> f64 {
+        unimplemented!()
+    }
+
+    pub fn cancel(thread: &Thread) {
+        unimplemented!()
+    }
+
+    /// Resumes in parallel; only in an `#![actor]` script.
+    pub fn desynchronize() {
+        unimplemented!()
+    }
+
+    pub fn synchronize() {
+        unimplemented!()
+    }
+}
+"#;
+
+const COLLECTION: &str = r#"
+/// Tag queries and tag signals through `CollectionService`.
+pub mod collection {
+    use super::*;
+
+    pub fn get_tagged(tag: &str) -> Vec<Instance> {
+        unimplemented!()
+    }
+
+    pub fn on_tag_added(tag: &str, callback: impl FnMut(Instance) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn on_tag_removed(tag: &str, callback: impl FnMut(Instance) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn add_tag(instance: &Instance, tag: &str) {
+        unimplemented!()
+    }
+
+    pub fn remove_tag(instance: &Instance, tag: &str) {
+        unimplemented!()
+    }
+
+    pub fn has_tag(instance: &Instance, tag: &str) -> bool {
+        unimplemented!()
+    }
+}
+"#;
+
+const COLLECTION_FUNCTIONS: &[(&str, &str, Option<&str>)] = &[
+    (
+        "collection::get_tagged",
+        "game:GetService(\"CollectionService\"):GetTagged({0})",
+        Some("Vec<Instance>"),
+    ),
+    (
+        "collection::on_tag_added",
+        "game:GetService(\"CollectionService\"):GetInstanceAddedSignal({0}):Connect({1})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "collection::on_tag_removed",
+        "game:GetService(\"CollectionService\"):GetInstanceRemovedSignal({0}):Connect({1})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "collection::add_tag",
+        "game:GetService(\"CollectionService\"):AddTag({0}, {1})",
+        None,
+    ),
+    (
+        "collection::remove_tag",
+        "game:GetService(\"CollectionService\"):RemoveTag({0}, {1})",
+        None,
+    ),
+    (
+        "collection::has_tag",
+        "game:GetService(\"CollectionService\"):HasTag({0}, {1})",
+        None,
+    ),
+];
+
+const RUN_SERVICE: &str = r#"
+/// Frame-loop callbacks on `RunService`.
+pub mod run_service {
+    use super::*;
+
+    pub fn on_heartbeat(callback: impl FnMut(f64) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn on_render_stepped(callback: impl FnMut(f64) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn on_stepped(callback: impl FnMut(f64, f64) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn bind_to_render_step(
+        name: &str,
+        priority: enums::RenderPriority,
+        callback: impl FnMut(f64) + 'static,
+    ) {
+        unimplemented!()
+    }
+
+    pub fn unbind_from_render_step(name: &str) {
+        unimplemented!()
+    }
+}
+"#;
+
+const RUN_SERVICE_FUNCTIONS: &[(&str, &str, Option<&str>)] = &[
+    (
+        "run_service::on_heartbeat",
+        "game:GetService(\"RunService\").Heartbeat:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "run_service::on_render_stepped",
+        "game:GetService(\"RunService\").RenderStepped:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "run_service::on_stepped",
+        "game:GetService(\"RunService\").Stepped:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "run_service::bind_to_render_step",
+        "game:GetService(\"RunService\"):BindToRenderStep({0}, {1}.Value, {2})",
+        None,
+    ),
+    (
+        "run_service::unbind_from_render_step",
+        "game:GetService(\"RunService\"):UnbindFromRenderStep({0})",
+        None,
+    ),
+];
+
+const INPUT: &str = r#"
+/// Client input through `UserInputService` and `ContextActionService`.
+pub mod input {
+    use super::*;
+
+    pub fn on_input_began(callback: impl FnMut(InputObject, bool) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn on_input_ended(callback: impl FnMut(InputObject, bool) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn on_input_changed(callback: impl FnMut(InputObject, bool) + 'static) -> RBXScriptConnection {
+        unimplemented!()
+    }
+
+    pub fn is_key_down(key: enums::KeyCode) -> bool {
+        unimplemented!()
+    }
+
+    pub fn bind_action(
+        name: &str,
+        callback: impl FnMut(String, enums::UserInputState, InputObject) + 'static,
+        create_touch_button: bool,
+        inputs: &[enums::KeyCode],
+    ) {
+        unimplemented!()
+    }
+
+    pub fn unbind_action(name: &str) {
+        unimplemented!()
+    }
+}
+"#;
+
+const INPUT_FUNCTIONS: &[(&str, &str, Option<&str>)] = &[
+    (
+        "input::on_input_began",
+        "game:GetService(\"UserInputService\").InputBegan:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "input::on_input_ended",
+        "game:GetService(\"UserInputService\").InputEnded:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "input::on_input_changed",
+        "game:GetService(\"UserInputService\").InputChanged:Connect({0})",
+        Some("RBXScriptConnection"),
+    ),
+    (
+        "input::is_key_down",
+        "game:GetService(\"UserInputService\"):IsKeyDown({0})",
+        None,
+    ),
+    (
+        "input::bind_action",
+        "game:GetService(\"ContextActionService\"):BindAction({0}, {1}, {2}, table.unpack({3}))",
+        None,
+    ),
+    (
+        "input::unbind_action",
+        "game:GetService(\"ContextActionService\"):UnbindAction({0})",
+        None,
+    ),
+];
+
+pub fn run(args: &BindgenArgs) {
+    let dump = read_dump(&args.dump);
+    let dump: serde_json::Value = serde_json::from_str(&dump).expect("Failed to parse API dump");
+
+    let classes = dump["Classes"].as_array().expect("API dump has no Classes");
+    let enums = dump["Enums"].as_array().expect("API dump has no Enums");
+
+    let class_names: HashSet<&str> = classes
+        .iter()
+        .filter_map(|class| class["Name"].as_str())
+        .collect();
+
+    let mut generator = Generator {
+        class_names,
+        data_types: BTreeSet::new(),
+        lib: String::new(),
+        bindings: serde_json::Map::new(),
+    };
+
+    let tween = generator.class_names.contains("Tween");
+    let modules: Vec<&HelperModule> = HELPER_MODULES
+        .iter()
+        .filter(|module| {
+            module
+                .requires
+                .iter()
+                .all(|class| generator.class_names.contains(class))
+        })
+        .collect();
+    if tween {
+        generator.bindings.insert(
+            "TweenInfo".to_string(),
+            serde_json::json!({ "superclass": null, "members": {} }),
+        );
+    }
+
+    for class in classes {
+        generator.class(class);
+    }
+
+    generator.signal_arities();
+    generator.data_types();
+
+    let mut enums_rs = String::from(
+        "//! Roblox enums, lowered to `Enum.Name.Item`.\n#![allow(non_camel_case_types)]\n",
+    );
+    let mut enum_names = Vec::new();
+
+    for item in enums {
+        let Some(name) = item["Name"].as_str() else {
+            continue;
+        };
+
+        enum_names.push(name.to_string());
+        writeln!(
+            enums_rs,
+            "\n#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]\npub enum {} {{",
+            name
+        )
+        .unwrap();
+
+        let mut seen = HashSet::new();
+        for enum_item in item["Items"].as_array().into_iter().flatten() {
+            let Some(item_name) = enum_item["Name"].as_str() else {
+                continue;
+            };
+
+            let variant = if item_name.starts_with(|c: char| c.is_ascii_digit()) {
+                format!("_{}", item_name)
+            } else {
+                item_name.to_string()
+            };
+
+            if seen.insert(variant.clone()) {
+                writeln!(enums_rs, "    {},", variant).unwrap();
+            }
+        }
+
+        enums_rs.push_str("}\n");
+    }
+
+    let out_dir = Path::new(&args.out_dir);
+    fs::create_dir_all(out_dir.join("src")).expect("Failed to create output directory");
+
+    let manifest = format!(
+        "[package]\nname = \"{}\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[dependencies]\n",
+        args.name
+    );
+    fs::write(out_dir.join("Cargo.toml"), manifest).expect("Failed to write Cargo.toml");
+
+    let lib = format!(
+        "//! Roblox API bindings generated by `roblox-rs bindgen`; do not edit.\n\
+         #![allow(non_camel_case_types, non_snake_case, unused_imports, unused_variables, clippy::all)]\n\n\
+         pub mod enums;\n\n{}{}{}{}{}",
+        PRELUDE,
+        GLOBALS,
+        if tween { TWEEN } else { "" },
+        modules
+            .iter()
+            .map(|module| module.source)
+            .collect::<String>(),
+        generator.lib
+    );
+    fs::write(out_dir.join("src").join("lib.rs"), lib).expect("Failed to write lib.rs");
+    fs::write(out_dir.join("src").join("enums.rs"), enums_rs).expect("Failed to write enums.rs");
+
+    let functions: serde_json::Map<String, serde_json::Value> = modules
+        .iter()
+        .flat_map(|module| module.functions)
+        .map(|(path, template, ty)| {
+            (
+                path.to_string(),
+                serde_json::json!({ "luau": template, "type": ty }),
+            )
+        })
+        .collect();
+
+    let bindings = serde_json::json!({
+        "classes": generator.bindings,
+        "enums": enum_names,
+        "globals": {
+            "game": "DataModel",
+            "workspace": "Workspace",
+            "script": "LuaSourceContainer",
+        },
+        "functions": functions,
+    });
+    fs::write(
+        out_dir.join("bindings.json"),
+        serde_json::to_string_pretty(&bindings).unwrap(),
+    )
+    .expect("Failed to write bindings.json");
+}
+
+fn read_dump(source: &str) -> String {
+    if !source.starts_with("http://") && !source.starts_with("https://") {
+        return fs::read_to_string(source).expect("Failed to read API dump");
+    }
+
+    let output = process::Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", source])
+        .output()
+        .expect("Failed to run curl to download the API dump");
+
+    if !output.status.success() {
+        panic!(
+            "Failed to download the API dump from {}: {}",
+            source,
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).expect("API dump is not valid UTF-8")
+}
+
+// Yielding data store calls that can fail, bound as `Result`s and lowered through `pcall`
+const PCALL_METHODS: &[(&str, &str, &str)] = &[
+    (
+        "get_async",
+        "GetAsync",
+        "<T>(&self, key: &str) -> Result<Option<T>, String>",
+    ),
+    (
+        "set_async",
+        "SetAsync",
+        "<T>(&self, key: &str, value: T) -> Result<(), String>",
+    ),
+    (
+        "update_async",
+        "UpdateAsync",
+        "<T>(&self, key: &str, transform: impl FnMut(Option<T>) -> Option<T> + 'static) -> Result<Option<T>, String>",
+    ),
+    (
+        "remove_async",
+        "RemoveAsync",
+        "<T>(&self, key: &str) -> Result<Option<T>, String>",
+    ),
+    (
+        "increment_async",
+        "IncrementAsync",
+        "(&self, key: &str, delta: i64) -> Result<i64, String>",
+    ),
+];
+
+// Attributes are typed by the caller instead of coming back as `Variant`
+#[allow(clippy::type_complexity)]
+const ATTRIBUTE_METHODS: &[(&str, &str, &str, MemberKind, Option<&str>)] = &[
+    (
+        "get_attribute",
+        "GetAttribute",
+        "<T>(&self, name: &str) -> Option<T>",
+        MemberKind::Attribute,
+        None,
+    ),
+    (
+        "set_attribute",
+        "SetAttribute",
+        "<T>(&self, name: &str, value: T)",
+        MemberKind::Method,
+        None,
+    ),
+    (
+        "attribute_changed_signal",
+        "GetAttributeChangedSignal",
+        "(&self, name: &str) -> RBXScriptSignal",
+        MemberKind::Method,
+        Some("RBXScriptSignal"),
+    ),
+];
+
+struct Generator<'a> {
+    class_names: HashSet<&'a str>,
+    data_types: BTreeSet<String>,
+    lib: String,
+    bindings: serde_json::Map<String, serde_json::Value>,
+}
+
+struct Method {
+    kind: MemberKind,
+    rust_name: String,
+    luau_name: String,
+    signature: String,
+    ty: Option<String>,
+}
+
+impl Generator<'_> {
+    fn class(&mut self, class: &serde_json::Value) {
+        let Some(name) = class["Name"].as_str() else {
+            return;
+        };
+
+        let class_tags = tags(class);
+        let superclass = class["Superclass"]
+            .as_str()
+            .filter(|superclass| self.class_names.contains(superclass));
+
+        let mut methods = Vec::new();
+
+        if !class_tags.contains(&"NotCreatable") && !class_tags.contains(&"Service") {
+            methods.push(Method {
+                kind: MemberKind::Constructor,
+                rust_name: "new".to_string(),
+                luau_name: name.to_string(),
+                signature: format!("() -> {}", name),
+                ty: Some(name.to_string()),
+            });
+        }
+
+        if class_tags.contains(&"Service") {
+            methods.push(Method {
+                kind: MemberKind::Service,
+                rust_name: "service".to_string(),
+                luau_name: name.to_string(),
+                signature: format!("() -> {}", name),
+                ty: Some(name.to_string()),
+            });
+        }
+
+        if name == "Instance" {
+            methods.push(Method {
+                kind: MemberKind::Cast,
+                rust_name: "cast".to_string(),
+                luau_name: String::new(),
+                signature: "<T>(&self) -> T".to_string(),
+                ty: None,
+            });
+        }
+
+        let data_store = matches!(name, "GlobalDataStore" | "DataStore" | "OrderedDataStore");
+
+        let declares = |luau_name: &str| {
+            class["Members"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .any(|member| member["Name"] == luau_name)
+        };
+
+        for (rust_name, luau_name, signature) in PCALL_METHODS.iter().filter(|_| data_store) {
+            if declares(luau_name) {
+                methods.push(Method {
+                    kind: MemberKind::Pcall,
+                    rust_name: rust_name.to_string(),
+                    luau_name: luau_name.to_string(),
+                    signature: signature.to_string(),
+                    ty: None,
+                });
+            }
+        }
+
+        for (rust_name, luau_name, signature, kind, ty) in ATTRIBUTE_METHODS {
+            if declares(luau_name) {
+                methods.push(Method {
+                    kind: *kind,
+                    rust_name: rust_name.to_string(),
+                    luau_name: luau_name.to_string(),
+                    signature: signature.to_string(),
+                    ty: ty.map(str::to_string),
+                });
+            }
+        }
+
+        if name == "Tween" {
+            methods.push(Method {
+                kind: MemberKind::Tween,
+                rust_name: "new".to_string(),
+                luau_name: "Create".to_string(),
+                signature:
+                    "(instance: &Instance, info: TweenInfo, properties: Properties) -> Tween"
+                        .to_string(),
+                ty: Some(name.to_string()),
+            });
+        }
+
+        // Deprecated camelCase aliases come last so the current name wins a snake_case clash
+        let mut members: Vec<&serde_json::Value> = class["Members"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|member| is_scriptable(member))
+            .collect();
+        members.sort_by_key(|member| tags(member).contains(&"Deprecated"));
+
+        for member in members {
+            self.member(member, &mut methods);
+        }
+
+        let mut seen = HashSet::new();
+        methods.retain(|method| seen.insert(method.rust_name.clone()));
+
+        writeln!(self.lib, "\n/// The Roblox `{}` class.", name).unwrap();
+        writeln!(self.lib, "pub struct {} {{\n    _opaque: (),\n}}", name).unwrap();
+
+        if let Some(superclass) = superclass {
+            writeln!(
+                self.lib,
+                "\nimpl std::ops::Deref for {} {{\n    type Target = {};\n\n    fn deref(&self) -> &{} {{\n        unimplemented!()\n    }}\n}}",
+                name, superclass, superclass
+            )
+            .unwrap();
+        }
+
+        let mut members = serde_json::Map::new();
+
+        if !methods.is_empty() {
+            writeln!(self.lib, "\nimpl {} {{", name).unwrap();
+
+            for (index, method) in methods.iter().enumerate() {
+                if index > 0 {
+                    self.lib.push('\n');
+                }
+
+                writeln!(
+                    self.lib,
+                    "    pub fn {}{} {{\n        unimplemented!()\n    }}",
+                    method.rust_name, method.signature
+                )
+                .unwrap();
+
+                let mut binding = serde_json::json!({
+                    "kind": method.kind.name(),
+                    "name": method.luau_name,
+                });
+                if let Some(ty) = &method.ty {
+                    binding["type"] = ty.clone().into();
+                }
+
+                members.insert(method.rust_name.clone(), binding);
+            }
+
+            self.lib.push_str("}\n");
+        }
+
+        self.bindings.insert(
+            name.to_string(),
+            serde_json::json!({
+                "superclass": superclass,
+                "members": members,
+            }),
+        );
+    }
+
+    fn member(&mut self, member: &serde_json::Value, methods: &mut Vec<Method>) {
+        let Some(name) = member["Name"].as_str().filter(|name| is_identifier(name)) else {
+            return;
+        };
+
+        let rust_name = snake_case(name);
+
+        match member["MemberType"].as_str() {
+            Some("Property") => {
+                let value_type = &member["ValueType"];
+                let ty = self.rust_type(value_type, Position::Value);
+
+                methods.push(Method {
+                    kind: MemberKind::Getter,
+                    rust_name: escape_keyword(&rust_name),
+                    luau_name: name.to_string(),
+                    signature: format!("(&self) -> {}", ty),
+                    ty: self.bound_type(value_type),
+                });
+
+                let read_only = tags(member).contains(&"ReadOnly")
+                    || member["Security"]["Write"]
+                        .as_str()
+                        .is_some_and(|security| security != "None");
+
+                if !read_only {
+                    methods.push(Method {
+                        kind: MemberKind::Setter,
+                        rust_name: format!("set_{}", rust_name),
+                        luau_name: name.to_string(),
+                        signature: format!(
+                            "(&self, value: {})",
+                            self.rust_type(value_type, Position::Param)
+                        ),
+                        ty: None,
+                    });
+                }
+            }
+
+            Some("Function") => {
+                let params = self.params(member, Position::Param);
+                let return_type = &member["ReturnType"];
+                let returns = match self.rust_type(return_type, Position::Value).as_str() {
+                    "()" => String::new(),
+                    ty => format!(" -> {}", ty),
+                };
+
+                methods.push(Method {
+                    kind: MemberKind::Method,
+                    rust_name: escape_keyword(&rust_name),
+                    luau_name: name.to_string(),
+                    signature: format!("(&self{}){}", params, returns),
+                    ty: self.bound_type(return_type),
+                });
+            }
+
+            Some("Event") => {
+                let args: Vec<String> = member["Parameters"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|param| self.rust_type(&param["Type"], Position::Argument))
+                    .collect();
+
+                let args = match args.len() {
+                    0 => String::new(),
+                    1 => format!("<({},)>", args[0]),
+                    _ => format!("<({})>", args.join(", ")),
+                };
+
+                methods.push(Method {
+                    kind: MemberKind::Event,
+                    rust_name: escape_keyword(&rust_name),
+                    luau_name: name.to_string(),
+                    signature: format!("(&self) -> RBXScriptSignal{}", args),
+                    ty: Some("RBXScriptSignal".to_string()),
+                });
+            }
+
+            _ => {}
+        }
+    }
+
+    fn params(&mut self, member: &serde_json::Value, position: Position) -> String {
+        let mut seen = HashSet::new();
+
+        member["Parameters"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|param| {
+                let mut name = escape_keyword(&snake_case(param["Name"].as_str().unwrap_or("arg")));
+                while !seen.insert(name.clone()) {
+                    name.push('_');
+                }
+
+                format!(", {}: {}", name, self.rust_type(&param["Type"], position))
+            })
+            .collect()
+    }
+
+    /// The class or data type a member evaluates to, used to type method-call chains.
+    fn bound_type(&self, value_type: &serde_json::Value) -> Option<String> {
+        let name = value_type["Name"].as_str()?;
+
+        match value_type["Category"].as_str()? {
+            "Class" if self.class_names.contains(name) => Some(name.to_string()),
+            "DataType" if is_identifier(name) && special_type(name).is_none() => {
+                Some(name.to_string())
+            }
+            "Group" if name == "Objects" => Some("Vec<Instance>".to_string()),
+            _ => None,
+        }
+    }
+
+    fn rust_type(&mut self, value_type: &serde_json::Value, position: Position) -> String {
+        let name = value_type["Name"].as_str().unwrap_or("Variant");
+        let category = value_type["Category"].as_str().unwrap_or("");
+
+        let ty = match (category, name) {
+            ("Primitive", "bool") => "bool".to_string(),
+            ("Primitive", "int") => "i32".to_string(),
+            ("Primitive", "int64") => "i64".to_string(),
+            ("Primitive", "float") => "f32".to_string(),
+            ("Primitive", "double") => "f64".to_string(),
+            ("Primitive", "string") if position == Position::Param => "&str".to_string(),
+            ("Primitive", "string") => "String".to_string(),
+            ("Primitive", "void") | ("Primitive", "null") => "()".to_string(),
+
+            ("Class", class) if self.class_names.contains(class) => match position {
+                Position::Param => format!("&{}", class),
+                Position::Value => format!("Option<{}>", class),
+                Position::Argument => class.to_string(),
+            },
+
+            ("Enum", name) => format!("enums::{}", name),
+
+            (_, name) if special_type(name).is_some() => special_type(name).unwrap().to_string(),
+
+            ("DataType", name) if is_identifier(name) => {
+                self.data_types.insert(name.to_string());
+                name.to_string()
+            }
+
+            _ => "Variant".to_string(),
+        };
+
+        if ty == "String" && position == Position::Param {
+            "&str".to_string()
+        } else {
+            ty
+        }
+    }
+
+    fn signal_arities(&mut self) {
+        for arity in 1..=6 {
+            let params: Vec<String> = (0..arity).map(|index| format!("A{}", index)).collect();
+            let params = params.join(", ");
+            let args = if arity == 1 {
+                format!("({},)", params)
+            } else {
+                format!("({})", params)
+            };
+
+            writeln!(
+                self.lib,
+                "\nimpl<{params}> RBXScriptSignal<{args}> {{\n    \
+                 pub fn connect(&self, callback: impl FnMut({params}) + 'static) -> RBXScriptConnection {{\n        unimplemented!()\n    }}\n\n    \
+                 pub fn once(&self, callback: impl FnOnce({params}) + 'static) -> RBXScriptConnection {{\n        unimplemented!()\n    }}\n\n    \
+                 pub fn wait(&self) -> {args} {{\n        unimplemented!()\n    }}\n}}",
+            )
+            .unwrap();
+        }
+
+        let signal_members = serde_json::json!({
+            "connect": { "kind": "method", "name": "Connect", "type": "RBXScriptConnection" },
+            "once": { "kind": "method", "name": "Once", "type": "RBXScriptConnection" },
+            "wait": { "kind": "method", "name": "Wait" },
+        });
+        let connection_members = serde_json::json!({
+            "disconnect": { "kind": "method", "name": "Disconnect" },
+            "connected": { "kind": "getter", "name": "Connected" },
+        });
+
+        self.bindings.insert(
+            "RBXScriptSignal".to_string(),
+            serde_json::json!({ "superclass": null, "members": signal_members }),
+        );
+        self.bindings.insert(
+            "RBXScriptConnection".to_string(),
+            serde_json::json!({ "superclass": null, "members": connection_members }),
+        );
+    }
+
+    fn data_types(&mut self) {
+        for name in std::mem::take(&mut self.data_types) {
+            if self.class_names.contains(name.as_str()) || self.bindings.contains_key(&name) {
+                continue;
+            }
+
+            writeln!(self.lib, "\n/// The Roblox `{}` data type.", name).unwrap();
+            writeln!(self.lib, "pub struct {} {{\n    _opaque: (),\n}}", name).unwrap();
+
+            self.bindings.insert(
+                name,
+                serde_json::json!({ "superclass": null, "members": {} }),
+            );
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Position {
+    Param,
+    Value,
+    Argument,
+}
+
+fn special_type(name: &str) -> Option<&'static str> {
+    match name {
+        "Array" => Some("Vec<Variant>"),
+        "Dictionary" | "Map" => Some("HashMap<String, Variant>"),
+        "Objects" => Some("Vec<Instance>"),
+        "Tuple" | "Variant" | "Function" => Some("Variant"),
+        "Content" | "ContentId" | "ProtectedString" | "BinaryString" => Some("String"),
+        "RBXScriptSignal" => Some("RBXScriptSignal"),
+        "RBXScriptConnection" => Some("RBXScriptConnection"),
+        _ => None,
+    }
+}
+
+fn tags(value: &serde_json::Value) -> Vec<&str> {
+    value["Tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag.as_str())
+        .collect()
+}
+
+// Members only plugins or Roblox's own scripts may touch are left out of the bindings
+fn is_scriptable(member: &serde_json::Value) -> bool {
+    let tags = tags(member);
+    if tags.contains(&"Hidden") || tags.contains(&"NotScriptable") {
+        return false;
+    }
+
+    match &member["Security"] {
+        serde_json::Value::String(security) => security == "None",
+        security => security["Read"].as_str().is_none_or(|read| read == "None"),
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn escape_keyword(name: &str) -> String {
+    if RUST_KEYWORDS.contains(&name) {
+        format!("{}_", name)
+    } else {
+        name.to_string()
+    }
+}
+
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_ascii_uppercase() && index > 0 {
+            let previous = chars[index - 1];
+            let next_is_lower = chars
+                .get(index + 1)
+                .is_some_and(|next| next.is_ascii_lowercase());
+
+            if previous.is_ascii_lowercase()
+                || previous.is_ascii_digit()
+                || (previous.is_ascii_uppercase() && next_is_lower)
+            {
+                snake.push('_');
+            }
+        }
+
+        snake.push(c.to_ascii_lowercase());
+    }
+
+    snake
+}
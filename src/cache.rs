@@ -0,0 +1,90 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+pub struct CachedModule {
+    pub output: String,
+    pub declarations: Option<String>,
+    pub messages: Vec<String>,
+}
+
+pub struct Cache {
+    dir: PathBuf,
+}
+
+impl Cache {
+    pub fn open(crate_dir: &Path) -> Self {
+        let dir = crate_dir.join("target").join("roblox-rs");
+        fs::create_dir_all(&dir).expect("Failed to create cache directory");
+
+        Self { dir }
+    }
+
+    /// A module's output depends on its own source, its path, the options it was transpiled
+    /// with and, through glob imports, on what the modules it `use`s export, so the key also
+    /// covers the hashes of those modules' sources.
+    pub fn key(source: &Path, contents: &str, dependencies: &[u64], options: &impl Hash) -> String {
+        let mut hasher = DefaultHasher::new();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+        source.hash(&mut hasher);
+        contents.hash(&mut hasher);
+        dependencies.hash(&mut hasher);
+        options.hash(&mut hasher);
+
+        format!("{:016x}", hasher.finish())
+    }
+
+    pub fn source_hash(contents: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        contents.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    pub fn load(&self, key: &str) -> Option<CachedModule> {
+        let entry = fs::read_to_string(self.entry_path(key)).ok()?;
+        let entry: serde_json::Value = serde_json::from_str(&entry).ok()?;
+
+        Some(CachedModule {
+            output: entry["output"].as_str()?.to_string(),
+            declarations: entry["declarations"].as_str().map(str::to_string),
+            messages: entry["messages"]
+                .as_array()?
+                .iter()
+                .filter_map(|message| message.as_str().map(str::to_string))
+                .collect(),
+        })
+    }
+
+    pub fn store(&self, key: &str, module: &CachedModule) {
+        let entry = serde_json::json!({
+            "output": module.output,
+            "declarations": module.declarations,
+            "messages": module.messages,
+        });
+
+        fs::write(self.entry_path(key), entry.to_string()).expect("Failed to write cache entry");
+    }
+
+    pub fn prune(&self, keep: &HashSet<String>) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let is_stale = path
+                .file_stem()
+                .is_some_and(|stem| !keep.contains(stem.to_string_lossy().as_ref()));
+
+            if is_stale && path.extension().is_some_and(|ext| ext == "json") {
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
@@ -0,0 +1,153 @@
+use crate::diagnostics::Diagnostic;
+use crate::transpiler::UnsupportedConstruct;
+use std::collections::HashSet;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+#[derive(Default, Clone)]
+pub struct CfgSet {
+    flags: HashSet<String>,
+    values: HashSet<(String, String)>,
+}
+
+impl CfgSet {
+    pub fn from_specs(specs: &[String]) -> Self {
+        let mut cfg = Self::default();
+
+        for spec in specs {
+            cfg.insert(spec);
+        }
+
+        cfg
+    }
+
+    pub fn insert(&mut self, spec: &str) {
+        match spec.split_once('=') {
+            Some((key, value)) => {
+                let value = value.trim().trim_matches('"');
+                self.values
+                    .insert((key.trim().to_string(), value.to_string()));
+            }
+
+            None => {
+                self.flags.insert(spec.trim().to_string());
+            }
+        }
+    }
+
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.flags.contains(flag)
+    }
+
+    /// Whether items with `attrs` are compiled in. Predicates are checked up front by
+    /// [`check_predicates`], so one that can't be evaluated only counts as disabled here.
+    pub fn is_enabled(&self, attrs: &[syn::Attribute]) -> bool {
+        attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("cfg"))
+            .all(|attr| self.eval_attr(attr).unwrap_or(false))
+    }
+
+    pub fn eval_macro(&self, mac: &syn::Macro) -> bool {
+        self.eval_cfg_macro(mac).unwrap_or(false)
+    }
+
+    fn eval_attr(&self, attr: &syn::Attribute) -> Result<bool, String> {
+        let predicate = attr
+            .parse_args::<syn::Meta>()
+            .map_err(|_| "Failed to parse cfg predicate".to_string())?;
+
+        self.eval(&predicate)
+    }
+
+    fn eval_cfg_macro(&self, mac: &syn::Macro) -> Result<bool, String> {
+        let predicate = mac
+            .parse_body::<syn::Meta>()
+            .map_err(|_| "Failed to parse cfg! predicate".to_string())?;
+
+        self.eval(&predicate)
+    }
+
+    fn eval(&self, predicate: &syn::Meta) -> Result<bool, String> {
+        Ok(match predicate {
+            syn::Meta::Path(path) => path
+                .get_ident()
+                .is_some_and(|ident| self.flags.contains(&ident.to_string())),
+
+            syn::Meta::NameValue(name_value) => {
+                let key = name_value
+                    .path
+                    .get_ident()
+                    .map(|ident| ident.to_string())
+                    .unwrap_or_default();
+
+                if let syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(value),
+                    ..
+                }) = &name_value.value
+                {
+                    self.values.contains(&(key, value.value()))
+                } else {
+                    return Err(format!("Unsupported cfg value for `{}`", key));
+                }
+            }
+
+            syn::Meta::List(list) => {
+                let nested = list
+                    .parse_args_with(Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated)
+                    .map_err(|_| "Failed to parse nested cfg predicates".to_string())?;
+                let values = nested
+                    .iter()
+                    .map(|meta| self.eval(meta))
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                if list.path.is_ident("all") {
+                    values.iter().all(|value| *value)
+                } else if list.path.is_ident("any") {
+                    values.iter().any(|value| *value)
+                } else if list.path.is_ident("not") {
+                    !values.iter().any(|value| *value)
+                } else {
+                    return Err("Unsupported cfg predicate".to_string());
+                }
+            }
+        })
+    }
+}
+
+/// Fails on the first `#[cfg(...)]` or `cfg!(...)` predicate in `file` that can't be
+/// evaluated, rather than leaving the item it guards out.
+pub fn check_predicates(file: &syn::File) -> Result<(), UnsupportedConstruct> {
+    let mut check = Check::default();
+    check.visit_file(file);
+    check.failure.map_or(Ok(()), Err)
+}
+
+#[derive(Default)]
+struct Check {
+    failure: Option<UnsupportedConstruct>,
+}
+
+impl Check {
+    fn record(&mut self, result: Result<bool, String>, span: proc_macro2::Span) {
+        if let (Err(message), None) = (result, &self.failure) {
+            let diagnostic = Diagnostic::error("unsupported", message, span);
+            self.failure = Some(UnsupportedConstruct(diagnostic));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Check {
+    fn visit_attribute(&mut self, attr: &'ast syn::Attribute) {
+        if attr.path().is_ident("cfg") {
+            self.record(CfgSet::default().eval_attr(attr), attr.span());
+        }
+    }
+
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        if mac.path.is_ident("cfg") {
+            self.record(CfgSet::default().eval_cfg_macro(mac), mac.span());
+        }
+    }
+}
@@ -1,7 +1,551 @@
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use roblox_rs::diagnostics::MessageFormat;
+use roblox_rs::options::{
+    Formatter, IntSemantics, Optimization, RequireStyle, ScriptKind, Target, TestFramework,
+    TraitDispatch, Unsupported,
+};
 
 #[derive(Parser)]
 #[command(author = "glomdom", version = "0.0.0a", about = "Rust to Luau compiler.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[arg(required = true)]
+    pub file: Option<String>,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Write the output into DIR using the file-name suffix Rojo expects for the script kind"
+    )]
+    pub out_dir: Option<String>,
+
+    #[arg(
+        long,
+        help = "Emit a `.d.luau` declaration file with only the exported types and signatures"
+    )]
+    pub declarations: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FRAMEWORK",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "testez",
+        requires = "out_dir",
+        help = "Also write the file's `#[test]` functions as a `.spec.luau` file for TestEZ (`--emit-tests` or `--emit-tests=testez`) or Jest-Lua (`--emit-tests=jest`), with `assert!`, `assert_eq!` and `assert_ne!` lowered to `expect` calls"
+    )]
+    pub emit_tests: Option<TestFramework>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a table of the constructs each file uses and whether they lowered cleanly, degraded or failed to PATH, as HTML if it ends in `.html` and Markdown otherwise"
+    )]
+    pub report: Option<String>,
+
+    #[arg(
+        long,
+        help = "Transpile the file twice and fail if the output or diagnostics differ, for CI checks that output is reproducible"
+    )]
+    pub verify_deterministic: bool,
+
+    #[arg(
+        long,
+        requires = "out_dir",
+        help = "Keep hand-edits to the output file: write it with a checksum header, and if it was edited since, merge the edits into the new output, or leave it unchanged and show the conflicts"
+    )]
+    pub merge: bool,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "luau-analyze",
+        help = "Typecheck the output in strict mode with luau-analyze from PATH, or COMMAND like `--typecheck=\"luau-lsp analyze --defs=globalTypes.d.luau\"`, reporting its errors at the Rust lines they came from"
+    )]
+    pub typecheck: Option<String>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "luau",
+        help = "Stage to print: the parsed syntax tree (`ast`) or the generated Luau"
+    )]
+    pub emit: Emit,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Emit {
+    Ast,
+    Luau,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Transpile a library crate into a Wally package")]
+    Package(PackageArgs),
+
+    #[command(about = "Generate a Rust bindings crate from the Roblox API dump")]
+    Bindgen(BindgenArgs),
+
+    #[command(about = "Time `#[bench]` functions in Lune at each optimization level")]
+    Bench(BenchArgs),
+
+    #[command(about = "Transpile a file and run it in an embedded Luau VM")]
+    Run(RunArgs),
+
+    #[command(
+        about = "Transpile generated programs and a corpus, checking the transpiler doesn't panic and its output is valid Luau"
+    )]
+    Fuzz(FuzzArgs),
+
+    #[command(
+        about = "Run a language server reporting transpile diagnostics and the generated Luau"
+    )]
+    Lsp(LspArgs),
+
+    #[command(about = "Transpile every crate of a Cargo project into its output directory")]
+    Build(ProjectArgs),
+
+    #[command(
+        about = "Report the diagnostics of every crate of a Cargo project without writing output"
+    )]
+    Check(ProjectArgs),
+
+    #[command(about = "Build a Cargo project, then rebuild it whenever a source file changes")]
+    Watch(ProjectArgs),
+}
+
+#[derive(Args)]
+pub struct ProjectArgs {
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "Cargo.toml",
+        help = "Path to the Cargo.toml of the crate or workspace"
+    )]
+    pub manifest_path: String,
+
+    #[arg(
+        long,
+        short,
+        value_name = "NAME",
+        help = "Only build this workspace member; can be given more than once"
+    )]
+    pub package: Vec<String>,
+
+    #[arg(
+        long,
+        short = 'F',
+        value_name = "FEATURES",
+        value_delimiter = ',',
+        help = "Comma-separated features to enable, as `feature = \"...\"` cfg flags"
+    )]
+    pub features: Vec<String>,
+
+    #[arg(long, help = "Enable every feature of each crate")]
+    pub all_features: bool,
+
+    #[arg(long, help = "Do not enable the `default` feature")]
+    pub no_default_features: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Directory to write the output into; defaults to `out-dir` in `[package.metadata.roblox-rs]`, or `out`"
+    )]
+    pub out_dir: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Write a table of the constructs each file uses and whether they lowered cleanly, degraded or failed to PATH, as HTML if it ends in `.html` and Markdown otherwise"
+    )]
+    pub report: Option<String>,
+
+    #[arg(
+        long,
+        help = "Transpile each file twice and fail if the output or diagnostics differ, for CI checks that output is reproducible"
+    )]
+    pub verify_deterministic: bool,
+
+    #[arg(
+        long,
+        help = "Keep hand-edits to output files: write them with a checksum header, and if one was edited since, merge the edits into the new output, or leave it unchanged and show the conflicts"
+    )]
+    pub merge: bool,
+
+    #[arg(
+        long,
+        value_name = "COMMAND",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "luau-analyze",
+        help = "Typecheck the output in strict mode with luau-analyze from PATH, or COMMAND like `--typecheck=\"luau-lsp analyze --defs=globalTypes.d.luau\"`, reporting its errors at the Rust lines they came from"
+    )]
+    pub typecheck: Option<String>,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args)]
+pub struct LspArgs {
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args)]
+pub struct RunArgs {
+    #[arg(help = "Rust file to run")]
+    pub file: String,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args)]
+pub struct FuzzArgs {
+    #[arg(
+        long,
+        default_value_t = 1000,
+        help = "Number of random programs to generate"
+    )]
+    pub cases: u64,
+
+    #[arg(
+        long,
+        help = "Seed for the generated programs, to reproduce a run; defaults to the current time"
+    )]
+    pub seed: Option<u64>,
+
+    #[arg(
+        long,
+        default_value_t = 3,
+        help = "How deeply expressions and blocks are nested"
+    )]
+    pub depth: usize,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Also transpile every `.rs` file under DIR"
+    )]
+    pub corpus: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "fuzz-failures",
+        help = "Directory to save the programs that fail into"
+    )]
+    pub failures: String,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args)]
+pub struct BenchArgs {
+    #[arg(help = "Rust file containing the `#[bench]` functions")]
     pub file: String,
+
+    #[arg(
+        long,
+        value_name = "LEVELS",
+        value_delimiter = ',',
+        default_value = "0,1,2",
+        value_parser = clap::value_parser!(u8).range(0..=2),
+        help = "Comma-separated optimization levels to compare"
+    )]
+    pub opt_levels: Vec<u8>,
+
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 1.0,
+        help = "How long to run each benchmark for"
+    )]
+    pub time: f64,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "lune",
+        help = "Lune executable to run the benchmarks with"
+    )]
+    pub lune: String,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args)]
+pub struct BindgenArgs {
+    #[arg(
+        long,
+        value_name = "PATH|URL",
+        default_value = crate::bindgen::API_DUMP_URL,
+        help = "Full API dump JSON to read, either a file or a URL to download"
+    )]
+    pub dump: String,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "roblox-api",
+        help = "Directory to write the bindings crate into"
+    )]
+    pub out_dir: String,
+
+    #[arg(
+        long,
+        default_value = "roblox-api",
+        help = "Name of the generated crate"
+    )]
+    pub name: String,
+}
+
+#[derive(Args)]
+pub struct PackageArgs {
+    #[arg(default_value = ".", help = "Path to the crate containing Cargo.toml")]
+    pub crate_dir: String,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "wally-package",
+        help = "Directory to write the package into"
+    )]
+    pub out_dir: String,
+
+    #[arg(long, help = "Wally scope; defaults to package.metadata.wally.scope")]
+    pub scope: Option<String>,
+
+    #[arg(
+        long,
+        default_value = "shared",
+        help = "Wally realm (`shared` or `server`)"
+    )]
+    pub realm: String,
+
+    #[arg(
+        long,
+        help = "Also emit a `.d.luau` declaration file next to every module"
+    )]
+    pub declarations: bool,
+
+    #[arg(
+        long,
+        short,
+        value_name = "N",
+        help = "Number of files to transpile in parallel; defaults to the number of CPUs"
+    )]
+    pub jobs: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Transpile every module even if it is unchanged since the last run"
+    )]
+    pub no_cache: bool,
+
+    #[command(flatten)]
+    pub transpile: TranspileArgs,
+}
+
+#[derive(Args, Clone, Hash)]
+pub struct TranspileArgs {
+    #[arg(
+        long = "cfg",
+        value_name = "SPEC",
+        help = "Enable a cfg flag, e.g. `debug_assertions` or `feature=\"net\"`"
+    )]
+    pub cfg: Vec<String>,
+
+    #[arg(long, help = "Do not carry Rust comments over into the generated Luau")]
+    pub strip_comments: bool,
+
+    #[arg(long, help = "Generate moonwave documentation annotations")]
+    pub moonwave: bool,
+
+    #[arg(
+        long,
+        help = "Mark locals that are never reassigned with a `-- const` comment"
+    )]
+    pub annotate_const: bool,
+
+    #[arg(
+        long,
+        help = "Wrap each function body in `debug.profilebegin`/`debug.profileend` for the microprofiler"
+    )]
+    pub profile: bool,
+
+    #[arg(
+        long,
+        help = "Start the output with an `@generated` header naming the roblox-rs version, the source file and its hash, and when it was generated"
+    )]
+    pub header: bool,
+
+    #[arg(
+        long,
+        requires = "header",
+        help = "Leave the generation time out of the `--header`, so unchanged sources regenerate identical files"
+    )]
+    pub no_timestamp: bool,
+
+    #[arg(
+        long,
+        help = "Wrap the output of each item in `--#region`/`--#endregion` markers naming the Rust item and its line"
+    )]
+    pub regions: bool,
+
+    #[arg(
+        long,
+        help = "Specialize generic functions and structs per instantiation instead of emitting Luau generics"
+    )]
+    pub monomorphize: bool,
+
+    #[arg(
+        long,
+        help = "Emit the body of `fn main()` as top-level statements at the end of the output"
+    )]
+    pub script: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "script",
+        help = "Append a `main()` call at the end of the output"
+    )]
+    pub call_main: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Kind of Roblox script to emit; overrides a `#![kind = \"...\"]` attribute in the file"
+    )]
+    pub kind: Option<ScriptKind>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "roblox",
+        help = "Runtime to emit for: Roblox, or Lune with `std::fs`, `env`, `process` and `net` mapped to its builtin libraries"
+    )]
+    pub target: Target,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "STYLE",
+        help = "Write `require`s with string paths like `require(\"./util\")` (the default for `--target lune`) or instance paths like `require(script.Parent.util)` (the default for Roblox), translating `#[require]` paths between the two"
+    )]
+    pub require_style: Option<RequireStyle>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "exact",
+        help = "Overflow of arithmetic on 8-, 16- and 32-bit integers: left unbounded as Luau numbers, wrapped to the type's range as in release builds, or checked for overflow as in debug builds"
+    )]
+    pub int_semantics: IntSemantics,
+
+    #[arg(
+        long,
+        value_name = "LEVEL",
+        default_value_t = 0,
+        value_parser = clap::value_parser!(u8).range(0..=2),
+        help = "Optimization level: 0 keeps the output close to the Rust source, 1 rewrites slow Luau idioms like string concatenation in loops, 2 also inlines small functions"
+    )]
+    pub opt_level: u8,
+
+    #[arg(
+        long,
+        value_name = "PASS",
+        value_delimiter = ',',
+        help = "Comma-separated optimizations to enable on top of --opt-level; `tailrec` turns functions that call themselves in tail position into loops"
+    )]
+    pub opt: Vec<Optimization>,
+
+    #[arg(
+        long,
+        value_enum,
+        value_name = "MODE",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "stub",
+        conflicts_with = "strict_unsupported",
+        help = "Keep going at constructs that can't be lowered, with a warning: `--lenient` or `--lenient=stub` emits an `error(\"roblox-rs: unsupported: ...\")` stub in their place, `--lenient=skip` leaves them out"
+    )]
+    pub lenient: Option<Unsupported>,
+
+    #[arg(
+        long,
+        help = "Stop with an error at the first construct that can't be lowered, the default; overrides `lenient` in the project metadata"
+    )]
+    pub strict_unsupported: bool,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        help = "Lower calls on Roblox types using the bindings crate generated by `bindgen`"
+    )]
+    pub bindings: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Luau path to `require` React-lua from in files with `#[component]` functions; defaults to `game:GetService(\"ReplicatedStorage\").Packages.React`"
+    )]
+    pub react: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Luau path to `require` a logger module from, whose `info(message, fields)` and other level functions `log`/`tracing` macros call instead of printing"
+    )]
+    pub logger: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "LUAU",
+        help = "Luau expression `std::env::args()` lowers to, such as a table of arguments passed in by the host; defaults to `{}`"
+    )]
+    pub env_args: Option<String>,
+
+    #[arg(
+        long,
+        help = "Require `extern \"luau\"` modules through `TS.import` and register `#[flamework(service)]`/`#[flamework(controller)]` structs with Flamework, for use from a roblox-ts project"
+    )]
+    pub roblox_ts: bool,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "metatable",
+        help = "Lower trait methods onto metatable-backed method tables, or pass trait implementations as explicit dictionary tables"
+    )]
+    pub trait_dispatch: TraitDispatch,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Run the generated Luau through a formatter, wrapping long lines"
+    )]
+    pub format: Option<Formatter>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value = "human",
+        help = "Print diagnostics as human-readable text or as one cargo-style JSON message per line"
+    )]
+    pub message_format: MessageFormat,
 }
@@ -0,0 +1,152 @@
+pub struct Comment {
+    pub line: usize,
+    pub text: String,
+    pub block: bool,
+}
+
+pub fn extract_comments(source: &str) -> Vec<Comment> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut comments = Vec::new();
+    let mut line = 1;
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+
+            '/' if chars.get(i + 1) == Some(&'/') => {
+                let start = i;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+
+                let text: String = chars[start..i].iter().collect();
+                let is_doc = (text.starts_with("///") && !text.starts_with("////"))
+                    || text.starts_with("//!");
+
+                if !is_doc {
+                    comments.push(Comment {
+                        line,
+                        text: text[2..].trim().to_string(),
+                        block: false,
+                    });
+                }
+            }
+
+            '/' if chars.get(i + 1) == Some(&'*') => {
+                let start = i;
+                let start_line = line;
+                let mut depth = 0;
+
+                while i < chars.len() {
+                    if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+                        depth += 1;
+                        i += 2;
+                    } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+                        depth -= 1;
+                        i += 2;
+
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        if chars[i] == '\n' {
+                            line += 1;
+                        }
+
+                        i += 1;
+                    }
+                }
+
+                let text: String = chars[start..i].iter().collect();
+                let is_doc = (text.starts_with("/**") && !text.starts_with("/**/"))
+                    || text.starts_with("/*!");
+
+                if !is_doc {
+                    let inner = text.trim_start_matches("/*").trim_end_matches("*/");
+                    comments.push(Comment {
+                        line: start_line,
+                        text: inner.trim().to_string(),
+                        block: true,
+                    });
+                }
+            }
+
+            'r' if is_raw_string_start(&chars, i) => {
+                i += 1;
+
+                let mut hashes = 0;
+                while chars.get(i) == Some(&'#') {
+                    hashes += 1;
+                    i += 1;
+                }
+
+                i += 1;
+                while i < chars.len() {
+                    if chars[i] == '"' && (1..=hashes).all(|n| chars.get(i + n) == Some(&'#')) {
+                        i += 1 + hashes;
+                        break;
+                    }
+
+                    if chars[i] == '\n' {
+                        line += 1;
+                    }
+
+                    i += 1;
+                }
+            }
+
+            '"' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '"' {
+                    if chars[i] == '\\' {
+                        i += 1;
+                    }
+
+                    if chars.get(i) == Some(&'\n') {
+                        line += 1;
+                    }
+
+                    i += 1;
+                }
+
+                i += 1;
+            }
+
+            '\'' => {
+                if chars.get(i + 1) == Some(&'\\') {
+                    i += 2;
+                    while i < chars.len() && chars[i] != '\'' {
+                        i += 1;
+                    }
+
+                    i += 1;
+                } else if chars.get(i + 2) == Some(&'\'') {
+                    i += 3;
+                } else {
+                    i += 1;
+                }
+            }
+
+            _ => i += 1,
+        }
+    }
+
+    comments
+}
+
+fn is_raw_string_start(chars: &[char], i: usize) -> bool {
+    if i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_') {
+        return false;
+    }
+
+    let mut j = i + 1;
+    while chars.get(j) == Some(&'#') {
+        j += 1;
+    }
+
+    chars.get(j) == Some(&'"')
+}
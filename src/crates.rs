@@ -0,0 +1,414 @@
+use crate::diagnostics::Diagnostic;
+use crate::options::CrateModules;
+use crate::transpiler::UnsupportedConstruct;
+use proc_macro2::{Group, Punct, Spacing, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet};
+use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
+
+/// The modules of other workspace crates that a file uses.
+#[derive(Default, Clone)]
+pub struct CrateImports {
+    /// Each module's local and `require` argument, in the order they're first used
+    pub requires: Vec<(String, String)>,
+    /// Names brought into scope by `use`, with the module local and the item's path in it
+    pub imports: Vec<(String, String, String)>,
+}
+
+impl CrateImports {
+    pub fn requires_module(&self, local: &str) -> bool {
+        self.requires.iter().any(|(module, _)| module == local)
+    }
+
+    /// Whether `name` is an imported type, going by Rust's naming conventions: `Point` is a
+    /// type, but `SCALE` is a constant.
+    pub fn imports_type(&self, name: &str) -> bool {
+        self.imports.iter().any(|(import, _, item)| {
+            import == name
+                && !item.contains('.')
+                && item.starts_with(|c: char| c.is_ascii_uppercase())
+                && item.contains(|c: char| c.is_ascii_lowercase())
+        })
+    }
+}
+
+/// Rewrites paths into workspace crates, like `shared::math::lerp`, to start with a local
+/// for the module they name (`SharedMath::lerp`), and collects the `require` of each module
+/// and the items the file's top-level `use` declarations import from them.
+pub fn resolve_crate_paths(
+    file: &mut syn::File,
+    crates: &[CrateModules],
+) -> Result<CrateImports, UnsupportedConstruct> {
+    let mut resolver = Resolver::new(crates, CrateImports::default());
+
+    if crates.is_empty() {
+        return Ok(resolver.imports);
+    }
+
+    // Named imports shadow glob ones, so they're bound first
+    let mut leaves = Vec::new();
+    for item in &file.items {
+        if let syn::Item::Use(item_use) = item {
+            let mut item_leaves = Vec::new();
+            use_leaves(&item_use.tree, &mut Vec::new(), &mut item_leaves);
+            leaves.extend(item_leaves.into_iter().map(|leaf| (leaf, item_use.span())));
+        }
+    }
+    leaves.sort_by_key(|((_, name), _)| name == "*");
+
+    let declared = declared_names(&file.items);
+    for ((path, name), span) in leaves {
+        resolver.import(&path, name, &declared).map_err(|message| {
+            let diagnostic = Diagnostic::error("unsupported", message, span);
+            UnsupportedConstruct(diagnostic)
+        })?;
+    }
+
+    resolver.visit_file_mut(file);
+    Ok(resolver.imports)
+}
+
+struct Resolver<'a> {
+    crates: &'a [CrateModules],
+    /// Names paths can start with: the crates themselves and the modules imported from them
+    aliases: HashMap<String, (usize, Vec<String>)>,
+    imports: CrateImports,
+}
+
+/// The local and remaining segments a path into a workspace crate or the crate's own modules
+/// is rewritten to, like `["Util", "clamp"]` for `util::clamp`, adding the module's `require`
+/// to `imports` on first use.
+pub fn rewrite_crate_path(
+    path: &[String],
+    crates: &[CrateModules],
+    imports: &mut CrateImports,
+) -> Option<Vec<String>> {
+    let mut resolver = Resolver::new(crates, std::mem::take(imports));
+    let rewritten = resolver.resolve(path).map(|(krate, module, rest)| {
+        std::iter::once(resolver.require(krate, &module))
+            .chain(rest)
+            .collect()
+    });
+
+    *imports = resolver.imports;
+    rewritten
+}
+
+impl<'a> Resolver<'a> {
+    fn new(crates: &'a [CrateModules], imports: CrateImports) -> Self {
+        Resolver {
+            crates,
+            aliases: crates
+                .iter()
+                .enumerate()
+                .map(|(index, krate)| (krate.name.clone(), (index, Vec::new())))
+                .collect(),
+            imports,
+        }
+    }
+
+    fn import(
+        &mut self,
+        path: &[String],
+        name: String,
+        declared: &HashSet<String>,
+    ) -> Result<(), String> {
+        let Some((krate, module, rest)) = self.resolve(path) else {
+            return Ok(());
+        };
+
+        // A glob over a module imports everything its file exports
+        if name == "*" {
+            if !rest.is_empty() {
+                return Err(format!(
+                    "Glob imports of items in other files are not supported; import `{}` items by name",
+                    path.join("::")
+                ));
+            }
+
+            let exports = self.crates[krate]
+                .exports
+                .get(&module)
+                .cloned()
+                .unwrap_or_default();
+            let local = self.require(krate, &module);
+            for export in exports {
+                // A module in a file of its own is reached through its own `require`
+                let mut child = module.clone();
+                child.push(export.clone());
+                if self.crates[krate]
+                    .modules
+                    .iter()
+                    .any(|(path, _)| *path == child)
+                {
+                    self.aliases.entry(export).or_insert((krate, child));
+                    continue;
+                }
+
+                self.bind(export.clone(), &local, export, declared, true)?;
+            }
+            return Ok(());
+        }
+
+        if rest.is_empty() {
+            self.aliases.insert(name, (krate, module));
+            return Ok(());
+        }
+
+        let local = self.require(krate, &module);
+        self.bind(name, &local, rest.join("."), declared, false)
+    }
+
+    /// Binds `name` to `item` of a module's local. A name the file declares or already
+    /// imports from elsewhere can't be imported by name again, and isn't imported by a glob.
+    fn bind(
+        &mut self,
+        name: String,
+        local: &str,
+        item: String,
+        declared: &HashSet<String>,
+        glob: bool,
+    ) -> Result<(), String> {
+        let existing = self
+            .imports
+            .imports
+            .iter()
+            .find(|(import, _, _)| *import == name);
+        if existing.is_some_and(|(_, module, path)| module == local && *path == item) {
+            return Ok(());
+        }
+
+        if existing.is_some() || declared.contains(&name) {
+            if glob {
+                return Ok(());
+            }
+            return Err(format!("`{}` is defined more than once in this file", name));
+        }
+
+        self.imports.imports.push((name, local.to_string(), item));
+        Ok(())
+    }
+
+    /// The crate a path starting with an alias points into, the longest module of that crate
+    /// the path names, and the segments after it.
+    fn resolve(&self, path: &[String]) -> Option<(usize, Vec<String>, Vec<String>)> {
+        let (first, rest) = path.split_first()?;
+        let (krate, prefix) = self.aliases.get(first)?;
+
+        let full: Vec<String> = prefix.iter().chain(rest).cloned().collect();
+        let module = self.crates[*krate]
+            .modules
+            .iter()
+            .map(|(module, _)| module)
+            .filter(|module| full.starts_with(module))
+            .max_by_key(|module| module.len())?
+            .clone();
+
+        let rest = full[module.len()..].to_vec();
+        Some((*krate, module, rest))
+    }
+
+    /// The local a module is required into, adding its `require` on first use.
+    fn require(&mut self, krate: usize, module: &[String]) -> String {
+        let krate = &self.crates[krate];
+        let local: String = std::iter::once(&krate.name)
+            .chain(module)
+            .flat_map(|name| name.split('_'))
+            .map(|part| {
+                let mut chars = part.chars();
+                chars.next().map_or(String::new(), |first| {
+                    first.to_uppercase().chain(chars).collect()
+                })
+            })
+            .collect();
+
+        if self.imports.requires.iter().any(|(name, _)| *name == local) {
+            return local;
+        }
+
+        let path = krate
+            .modules
+            .iter()
+            .find(|(path, _)| path == module)
+            .map(|(_, require)| require.clone())
+            .expect("Module was resolved from the crate's modules");
+        // `util::f` and `crate::util::f` are the same module
+        if let Some((required, _)) = self
+            .imports
+            .requires
+            .iter()
+            .find(|(_, required)| *required == path)
+        {
+            return required.clone();
+        }
+
+        self.imports.requires.push((local.clone(), path));
+        local
+    }
+
+    /// The same rewrite as for parsed paths, over the tokens of a macro's arguments.
+    fn rewrite_tokens(&mut self, tokens: TokenStream) -> TokenStream {
+        let trees: Vec<TokenTree> = tokens.into_iter().collect();
+        let mut rewritten = Vec::new();
+        let mut index = 0;
+
+        while index < trees.len() {
+            if let TokenTree::Group(group) = &trees[index] {
+                let mut inner = Group::new(group.delimiter(), self.rewrite_tokens(group.stream()));
+                inner.set_span(group.span());
+                rewritten.push(TokenTree::Group(inner));
+                index += 1;
+                continue;
+            }
+
+            // Only a path's first segment can name a crate, so `a::shared::b` is left alone
+            let after_path = index >= 2 && is_path_separator(&trees, index - 2);
+            let mut segments = Vec::new();
+            let mut end = index;
+            while let Some(TokenTree::Ident(ident)) = trees.get(end).filter(|_| !after_path) {
+                segments.push(ident.clone());
+                end += 1;
+                if is_path_separator(&trees, end) {
+                    end += 2;
+                } else {
+                    break;
+                }
+            }
+
+            let names: Vec<String> = segments.iter().map(ToString::to_string).collect();
+            let resolved = (segments.len() >= 2)
+                .then(|| self.resolve(&names))
+                .flatten();
+
+            let Some((krate, module, rest)) = resolved else {
+                rewritten.push(trees[index].clone());
+                index += 1;
+                continue;
+            };
+
+            let local = self.require(krate, &module);
+            let span = segments[0].span();
+            rewritten.push(TokenTree::Ident(proc_macro2::Ident::new(&local, span)));
+            for name in &rest {
+                rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
+                rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+                rewritten.push(TokenTree::Ident(proc_macro2::Ident::new(name, span)));
+            }
+
+            // The segments consumed are the leading ones of the rewritten path
+            index += segments.len() * 3 - 2;
+        }
+
+        rewritten.into_iter().collect()
+    }
+}
+
+impl VisitMut for Resolver<'_> {
+    fn visit_item_use_mut(&mut self, _: &mut syn::ItemUse) {}
+
+    fn visit_path_mut(&mut self, i: &mut syn::Path) {
+        for argument in &mut i.segments {
+            self.visit_path_arguments_mut(&mut argument.arguments);
+        }
+
+        if i.segments.len() < 2 {
+            return;
+        }
+
+        let names: Vec<String> = i
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect();
+        let Some((krate, module, rest)) = self.resolve(&names) else {
+            return;
+        };
+
+        let first = &i.segments[0];
+        let local = syn::Ident::new(&self.require(krate, &module), first.ident.span());
+
+        // The segments after the module keep their generic arguments
+        let kept: Vec<syn::PathSegment> = i
+            .segments
+            .iter()
+            .skip(i.segments.len() - rest.len())
+            .cloned()
+            .collect();
+
+        i.leading_colon = None;
+        i.segments = std::iter::once(syn::PathSegment::from(local))
+            .chain(kept)
+            .collect();
+    }
+
+    fn visit_macro_mut(&mut self, i: &mut syn::Macro) {
+        self.visit_path_mut(&mut i.path);
+        i.tokens = self.rewrite_tokens(std::mem::take(&mut i.tokens));
+    }
+}
+
+/// The names the file's own items declare, which imports can't reuse.
+fn declared_names(items: &[syn::Item]) -> HashSet<String> {
+    items
+        .iter()
+        .filter_map(|item| match item {
+            syn::Item::Const(item) => Some(&item.ident),
+            syn::Item::Enum(item) => Some(&item.ident),
+            syn::Item::Fn(item) => Some(&item.sig.ident),
+            syn::Item::Static(item) => Some(&item.ident),
+            syn::Item::Struct(item) => Some(&item.ident),
+            syn::Item::Trait(item) => Some(&item.ident),
+            syn::Item::Type(item) => Some(&item.ident),
+            _ => None,
+        })
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn is_path_separator(trees: &[TokenTree], index: usize) -> bool {
+    matches!(
+        (trees.get(index), trees.get(index + 1)),
+        (Some(TokenTree::Punct(first)), Some(TokenTree::Punct(second)))
+            if first.as_char() == ':' && first.spacing() == Spacing::Joint && second.as_char() == ':'
+    )
+}
+
+/// The full path and local name of each name a `use` tree imports.
+pub fn use_leaves(
+    tree: &syn::UseTree,
+    prefix: &mut Vec<String>,
+    leaves: &mut Vec<(Vec<String>, String)>,
+) {
+    match tree {
+        syn::UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            use_leaves(&path.tree, prefix, leaves);
+            prefix.pop();
+        }
+        syn::UseTree::Name(name) if name.ident == "self" => {
+            if let Some(last) = prefix.last() {
+                leaves.push((prefix.clone(), last.clone()));
+            }
+        }
+        syn::UseTree::Name(name) => {
+            let mut path = prefix.clone();
+            path.push(name.ident.to_string());
+            leaves.push((path, name.ident.to_string()));
+        }
+        syn::UseTree::Rename(rename) => {
+            let mut path = prefix.clone();
+            if rename.ident != "self" {
+                path.push(rename.ident.to_string());
+            }
+            leaves.push((path, rename.rename.to_string()));
+        }
+        syn::UseTree::Glob(_) => {
+            leaves.push((prefix.clone(), "*".to_string()));
+        }
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                use_leaves(tree, prefix, leaves);
+            }
+        }
+    }
+}
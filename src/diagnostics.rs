@@ -0,0 +1,147 @@
+use clap::ValueEnum;
+use proc_macro2::Span;
+use std::fmt;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum MessageFormat {
+    #[default]
+    Human,
+    Json,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: &'static str,
+    pub message: String,
+    pub line: usize,
+    pub column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+    pub bytes: std::ops::Range<usize>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: &'static str, message: String, span: Span) -> Self {
+        let start = span.start();
+        let end = span.end();
+
+        Self {
+            severity,
+            code,
+            message,
+            line: start.line,
+            column: start.column + 1,
+            end_line: end.line,
+            end_column: end.column + 1,
+            bytes: span.byte_range(),
+        }
+    }
+
+    pub fn warning(code: &'static str, message: String, span: Span) -> Self {
+        Self::new(Severity::Warning, code, message, span)
+    }
+
+    pub fn error(code: &'static str, message: String, span: Span) -> Self {
+        Self::new(Severity::Error, code, message, span)
+    }
+
+    pub fn note(code: &'static str, message: String, span: Span) -> Self {
+        Self::new(Severity::Note, code, message, span)
+    }
+
+    pub fn render(&self, file: &str) -> String {
+        format!(
+            "{}[{}]: {}\n  --> {}:{}:{}",
+            self.severity, self.code, self.message, file, self.line, self.column
+        )
+    }
+
+    pub fn to_json(&self, file: &str) -> String {
+        let span = serde_json::json!({
+            "file_name": file,
+            "byte_start": self.bytes.start,
+            "byte_end": self.bytes.end,
+            "line_start": self.line,
+            "line_end": self.end_line,
+            "column_start": self.column,
+            "column_end": self.end_column,
+            "is_primary": true,
+            "text": [],
+            "label": null,
+            "suggested_replacement": null,
+            "suggestion_applicability": null,
+            "expansion": null,
+        });
+
+        compiler_message(
+            self.severity,
+            Some(self.code),
+            &self.message,
+            vec![span],
+            self.render(file),
+        )
+    }
+}
+
+pub fn error_json(message: &str) -> String {
+    compiler_message(
+        Severity::Error,
+        None,
+        message,
+        Vec::new(),
+        format!("{}: {}", Severity::Error, message),
+    )
+}
+
+/// A note about a whole file, like a count of its diagnostics.
+pub fn file_note(code: &str, message: &str, file: &str, format: MessageFormat) -> String {
+    let rendered = format!("{}[{}]: {}\n  --> {}", Severity::Note, code, message, file);
+    match format {
+        MessageFormat::Human => rendered,
+        MessageFormat::Json => {
+            compiler_message(Severity::Note, Some(code), message, Vec::new(), rendered)
+        }
+    }
+}
+
+fn compiler_message(
+    severity: Severity,
+    code: Option<&str>,
+    message: &str,
+    spans: Vec<serde_json::Value>,
+    rendered: String,
+) -> String {
+    let code = code.map(|code| serde_json::json!({ "code": code, "explanation": null }));
+
+    serde_json::json!({
+        "reason": "compiler-message",
+        "message": {
+            "$message_type": "diagnostic",
+            "message": message,
+            "code": code,
+            "level": severity.to_string(),
+            "spans": spans,
+            "children": [],
+            "rendered": rendered,
+        }
+    })
+    .to_string()
+}
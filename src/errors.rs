@@ -0,0 +1,126 @@
+use crate::diagnostics::Diagnostic;
+use crate::transpiler::UnsupportedConstruct;
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::visit_mut::VisitMut;
+
+/// Rewrites anyhow's macros into the `Err` and `format!` they stand for, since an anyhow
+/// error is its message once lowered: `bail!("no {}", key)` becomes
+/// `return Err(format!("no {}", key))`, `ensure!(cond, ...)` an `if !(cond)` around that, and
+/// `anyhow!(...)` just the `format!`.
+pub fn desugar_error_macros(file: &mut syn::File) -> Result<(), UnsupportedConstruct> {
+    let mut macros = ErrorMacros::default();
+    macros.visit_file_mut(file);
+    macros.failure.map_or(Ok(()), Err)
+}
+
+#[derive(Default)]
+struct ErrorMacros {
+    /// The first macro that couldn't be desugared, which is left as it was
+    failure: Option<UnsupportedConstruct>,
+}
+
+impl ErrorMacros {
+    fn desugar(&mut self, mac: &syn::Macro) -> Option<syn::Expr> {
+        match desugar(mac) {
+            Ok(expr) => expr,
+            Err(message) => {
+                if self.failure.is_none() {
+                    let diagnostic = Diagnostic::error("unsupported", message, mac.span());
+                    self.failure = Some(UnsupportedConstruct(diagnostic));
+                }
+                None
+            }
+        }
+    }
+}
+
+impl VisitMut for ErrorMacros {
+    fn visit_stmt_mut(&mut self, stmt: &mut syn::Stmt) {
+        if let syn::Stmt::Macro(stmt_macro) = stmt {
+            if let Some(expr) = self.desugar(&stmt_macro.mac) {
+                *stmt = syn::Stmt::Expr(expr, stmt_macro.semi_token);
+            }
+        }
+
+        syn::visit_mut::visit_stmt_mut(self, stmt);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Macro(expr_macro) = expr {
+            if let Some(desugared) = self.desugar(&expr_macro.mac) {
+                *expr = desugared;
+            }
+        }
+
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+}
+
+fn desugar(mac: &syn::Macro) -> Result<Option<syn::Expr>, String> {
+    let Some(name) = mac
+        .path
+        .segments
+        .last()
+        .map(|segment| segment.ident.to_string())
+    else {
+        return Ok(None);
+    };
+    let is_anyhow = mac.path.segments.len() == 1
+        || mac
+            .path
+            .segments
+            .first()
+            .is_some_and(|segment| segment.ident == "anyhow");
+    if !is_anyhow || !matches!(name.as_str(), "bail" | "ensure" | "anyhow") {
+        return Ok(None);
+    }
+
+    let span = mac.span();
+    let mut args: Vec<syn::Expr> = mac
+        .parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+        .map_err(|_| format!("Failed to parse `{}!` arguments", name))?
+        .into_iter()
+        .collect();
+
+    // `ensure!` takes the condition first, with anyhow's message when there's no other
+    let condition = if name == "ensure" {
+        if args.is_empty() {
+            return Err("`ensure!` expects a condition".to_string());
+        }
+
+        let condition = args.remove(0);
+        if args.is_empty() {
+            let source = condition.to_token_stream().to_string();
+            let message = format!("Condition failed: `{}`", source)
+                .replace('{', "{{")
+                .replace('}', "}}");
+            args.push(syn::parse_quote_spanned!(span=> #message));
+        }
+        Some(condition)
+    } else {
+        None
+    };
+    let args: Punctuated<syn::Expr, syn::Token![,]> = args.into_iter().collect();
+
+    // A lone error value is formatted with its `Display`
+    let message: syn::Expr = match args.first() {
+        Some(syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(_),
+            ..
+        })) => syn::parse_quote_spanned!(span=> format!(#args)),
+        Some(_) => syn::parse_quote_spanned!(span=> format!("{}", #args)),
+        None => return Err(format!("`{}!` expects a message", name)),
+    };
+
+    Ok(Some(match (name.as_str(), condition) {
+        ("anyhow", _) => message,
+        (_, Some(condition)) => syn::parse_quote_spanned! {span=>
+            if !(#condition) {
+                return Err(#message);
+            }
+        },
+        _ => syn::parse_quote_spanned!(span=> return Err(#message)),
+    }))
+}
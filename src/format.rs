@@ -0,0 +1,13 @@
+use stylua_lib::{Config, IndentType, LuaVersion, OutputVerification};
+
+pub fn stylua(source: &str) -> Result<String, String> {
+    let config = Config {
+        syntax: LuaVersion::Luau,
+        indent_type: IndentType::Spaces,
+        indent_width: 4,
+        ..Config::default()
+    };
+
+    stylua_lib::format_code(source, config, None, OutputVerification::None)
+        .map_err(|error| error.to_string())
+}
@@ -0,0 +1,235 @@
+/// A piece of a `format!`-style string: literal text or a `{...}` placeholder.
+pub enum Piece {
+    Literal(String),
+    Placeholder { arg: ArgRef, spec: String },
+}
+
+pub enum ArgRef {
+    Next,
+    Index(usize),
+    Name(String),
+}
+
+pub fn parse(format: &str) -> Result<Vec<Piece>, String> {
+    let mut pieces = Vec::new();
+    let mut literal = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut inner = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => inner.push(c),
+                        None => {
+                            return Err(format!("Unterminated `{{` in format string {:?}", format))
+                        }
+                    }
+                }
+
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+
+                let (arg, spec) = inner.split_once(':').unwrap_or((&inner, ""));
+                let arg = arg.trim();
+                let arg = if arg.is_empty() {
+                    ArgRef::Next
+                } else if let Ok(index) = arg.parse() {
+                    ArgRef::Index(index)
+                } else {
+                    ArgRef::Name(arg.to_string())
+                };
+
+                pieces.push(Piece::Placeholder {
+                    arg,
+                    spec: spec.to_string(),
+                });
+            }
+            '}' => return Err(format!("Unmatched `}}` in format string {:?}", format)),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        pieces.push(Piece::Literal(literal));
+    }
+
+    Ok(pieces)
+}
+
+/// What a formatted value is known to be, which decides its `string.format` conversion.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    Int,
+    Float,
+    Str,
+    Unknown,
+}
+
+/// The `string.format` pattern for a `{:...}` spec, like `%8.2f` for `{:>8.2}`, or `None` for
+/// a spec that formats like `{}`. Specs with no `string.format` equivalent are an `Err`
+/// saying why.
+pub fn printf_pattern(spec: &str, kind: ValueKind) -> Result<Option<String>, String> {
+    let mut chars = spec.chars().peekable();
+
+    // `[[fill]align]`, where only the default space fill has a `string.format` equivalent
+    let leading: Vec<char> = spec.chars().take(2).collect();
+    let align = match leading.as_slice() {
+        [fill, align @ ('<' | '>' | '^')] => {
+            if *fill != ' ' {
+                return Err(format!("the fill character `{}` isn't supported", fill));
+            }
+            chars.nth(1);
+            Some(*align)
+        }
+        [align @ ('<' | '>' | '^'), ..] => {
+            chars.next();
+            Some(*align)
+        }
+        _ => None,
+    };
+    if align == Some('^') {
+        return Err("centering with `^` isn't supported".to_string());
+    }
+
+    let sign = chars.next_if_eq(&'+').is_some();
+    if chars.peek() == Some(&'-') {
+        return Err("the `-` flag isn't supported".to_string());
+    }
+    let alternate = chars.next_if_eq(&'#').is_some();
+    let zero = chars.next_if_eq(&'0').is_some();
+
+    let mut width = String::new();
+    while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+        width.push(digit);
+    }
+    let precision = chars.next_if_eq(&'.').map(|_| {
+        let mut precision = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            precision.push(digit);
+        }
+        precision
+    });
+
+    let conversion: String = chars.collect();
+    if conversion.contains('$') || conversion.contains('*') || precision.as_deref() == Some("") {
+        return Err("widths and precisions taken from arguments aren't supported".to_string());
+    }
+
+    match conversion.as_str() {
+        "" | "?" => {}
+        "x" | "X" | "o" => {
+            if kind == ValueKind::Float || kind == ValueKind::Str {
+                return Err(format!("`{}` only formats integers", conversion));
+            }
+
+            // `{:#x}` always prefixes `0x`, where `%#x` leaves out a zero's
+            if alternate {
+                if !width.is_empty() {
+                    return Err("`#` with a width isn't supported".to_string());
+                }
+                let prefix = if conversion == "o" { "0o" } else { "0x" };
+                return Ok(Some(format!("{}%{}", prefix, conversion)));
+            }
+
+            let flag = if align == Some('<') {
+                "-"
+            } else if zero {
+                "0"
+            } else {
+                ""
+            };
+            return Ok(Some(format!("%{}{}{}", flag, width, conversion)));
+        }
+        "b" => return Err("binary formatting isn't supported".to_string()),
+        "e" | "E" => return Err("exponent formatting isn't supported".to_string()),
+        other => return Err(format!("the format type `{}` isn't supported", other)),
+    }
+    if alternate && conversion.is_empty() {
+        return Err("`#` only applies to `x`, `o` and `?`".to_string());
+    }
+
+    // Rust ignores the precision of integers
+    let precision = precision.filter(|_| kind != ValueKind::Int);
+    if !sign && !zero && width.is_empty() && precision.is_none() {
+        return Ok(None);
+    }
+
+    // Strings are aligned left by default and numbers right, zero padding overriding both padding overrides the alignment
+    let left = !zero
+        && !width.is_empty()
+        && (align == Some('<') || (align.is_none() && kind == ValueKind::Str));
+    let mut pattern = String::from("%");
+    if left {
+        pattern.push('-');
+    }
+    if sign {
+        pattern.push('+');
+    }
+    if zero {
+        pattern.push('0');
+    }
+    pattern.push_str(&width);
+
+    match (precision, kind) {
+        (Some(precision), ValueKind::Str) => pattern.push_str(&format!(".{}s", precision)),
+        (Some(precision), _) => pattern.push_str(&format!(".{}f", precision)),
+        (None, ValueKind::Str) if sign || zero => {
+            return Err("`+` and `0` only apply to numbers".to_string());
+        }
+        (None, ValueKind::Float) if sign || zero => {
+            return Err("`+` and `0` need a precision on floats".to_string());
+        }
+        (None, _) if sign || zero => pattern.push('d'),
+        (None, _) => pattern.push('s'),
+    }
+
+    Ok(Some(pattern))
+}
+
+/// Escapes text for a Luau `"..."` string.
+pub fn quoted(text: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '"' => escaped.push_str("\\\""),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped.push('"');
+    escaped
+}
+
+/// Escapes text for the literal parts of a Luau `` `...` `` interpolated string.
+pub fn interpolated(text: &str) -> String {
+    let mut escaped = String::new();
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '`' => escaped.push_str("\\`"),
+            '{' => escaped.push_str("\\{"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
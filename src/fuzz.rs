@@ -0,0 +1,568 @@
+use crate::cli::FuzzArgs;
+use roblox_rs::LUAU_KEYWORDS;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+enum Outcome {
+    Ok,
+    /// A located error for a construct the transpiler rejects on purpose
+    Unsupported,
+    Failed(String),
+}
+
+pub fn run(args: &FuzzArgs) {
+    let seed = args.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+    });
+    println!("fuzzing {} cases with --seed {}", args.cases, seed);
+    if !cfg!(feature = "luau") {
+        eprintln!("note: outputs aren't checked to be valid Luau without `--features luau`");
+    }
+
+    let mut cases: Vec<(String, String)> = Vec::new();
+    if let Some(corpus) = &args.corpus {
+        for path in rust_files(Path::new(corpus)) {
+            let source = fs::read_to_string(&path).expect("Failed to read corpus file");
+            cases.push((path.display().to_string(), source));
+        }
+    }
+    for index in 0..args.cases {
+        let mut rng = Rng::new(seed.wrapping_add(index));
+        let name = format!("case-{}-{}.rs", seed, index);
+        cases.push((name, Generator::new(&mut rng, args.depth).program()));
+    }
+
+    let mut unsupported = 0;
+    let mut failures = 0;
+    for (name, source) in &cases {
+        match check(args, name, source) {
+            Outcome::Ok => {}
+            Outcome::Unsupported => unsupported += 1,
+            Outcome::Failed(message) => {
+                failures += 1;
+                eprintln!("error: {}: {}", name, message);
+
+                let file = Path::new(name).file_name().unwrap_or_default();
+                let path = Path::new(&args.failures).join(file);
+                fs::create_dir_all(&args.failures).expect("Failed to create failures directory");
+                fs::write(&path, source).expect("Failed to write failing case");
+                eprintln!("  --> saved to {}", path.display());
+            }
+        }
+    }
+
+    println!(
+        "{} cases, {} failed, {} rejected as unsupported",
+        cases.len(),
+        failures,
+        unsupported
+    );
+    if failures > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn check(args: &FuzzArgs, name: &str, source: &str) -> Outcome {
+    let options = crate::transpile_options(&args.transpile, name);
+
+    // Unsupported constructs come back as errors, so any panic is a bug in the transpiler
+    let result = std::panic::catch_unwind(|| roblox_rs::transpile_source(name, source, options));
+    match result {
+        Ok(Ok(transpiled)) => match luau_syntax(name, &transpiled.output) {
+            Ok(()) => Outcome::Ok,
+            Err(error) => Outcome::Failed(format!("output is not valid Luau: {}", error)),
+        },
+        Ok(Err(_)) => Outcome::Unsupported,
+        Err(payload) => Outcome::Failed(format!(
+            "transpiler panicked: {}",
+            roblox_rs::panic_message(&*payload)
+        )),
+    }
+}
+
+/// Compiles the output without running it.
+#[cfg(feature = "luau")]
+fn luau_syntax(name: &str, source: &str) -> Result<(), String> {
+    mlua::Lua::new()
+        .load(source)
+        .set_name(format!(
+            "@{}",
+            Path::new(name).with_extension("luau").display()
+        ))
+        .into_function()
+        .map(|_| ())
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "luau"))]
+fn luau_syntax(_name: &str, _source: &str) -> Result<(), String> {
+    Ok(())
+}
+
+fn rust_files(dir: &Path) -> Vec<std::path::PathBuf> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir).expect("Failed to read corpus directory") {
+        let path = entry.expect("Failed to read corpus directory").path();
+        if path.is_dir() {
+            files.extend(rust_files(&path));
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    files
+}
+
+/// SplitMix64, so a seed always generates the same cases.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn chance(&mut self, percent: usize) -> bool {
+        self.below(100) < percent
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Ty {
+    Int,
+    Float,
+    Bool,
+    Str,
+}
+
+impl Ty {
+    const ALL: [Ty; 4] = [Ty::Int, Ty::Float, Ty::Bool, Ty::Str];
+
+    fn name(self) -> &'static str {
+        match self {
+            Ty::Int => "i32",
+            Ty::Float => "f64",
+            Ty::Bool => "bool",
+            Ty::Str => "String",
+        }
+    }
+}
+
+struct Binding {
+    name: String,
+    ty: Ty,
+    mutable: bool,
+}
+
+struct Function {
+    name: String,
+    params: Vec<Ty>,
+    ret: Ty,
+}
+
+/// String literals with escapes, quotes and braces, which each need escaping in Luau.
+const STRINGS: [&str; 6] = [
+    r#""say \"hi\"""#,
+    r#""tab\tline\n""#,
+    r#""C:\\dir""#,
+    r#""{brace} `tick`""#,
+    r#""\u{e9}\x41""#,
+    r#""\0""#,
+];
+
+const CHARS: [&str; 6] = ["'a'", r"'\''", r"'\\'", r"'\n'", "'\"'", "'{'"];
+
+/// Generates programs that type check: functions over `i32`, `f64`, `bool` and `String`
+/// with bindings, loops, `if`, `match` on ranges and tuples, `Option`s, casts, escaped
+/// string and char literals and a struct, called from a `main` that prints their results.
+/// Some bindings are named after Luau keywords.
+struct Generator<'r> {
+    rng: &'r mut Rng,
+    max_depth: usize,
+    scope: Vec<Binding>,
+    functions: Vec<Function>,
+    next_name: usize,
+    keywords_used: usize,
+}
+
+impl<'r> Generator<'r> {
+    fn new(rng: &'r mut Rng, max_depth: usize) -> Self {
+        Self {
+            rng,
+            max_depth,
+            scope: Vec::new(),
+            functions: Vec::new(),
+            next_name: 0,
+            keywords_used: 0,
+        }
+    }
+
+    fn program(&mut self) -> String {
+        let mut source =
+            String::from("struct Point {\n    x: i32,\n    y: f64,\n    on: bool,\n}\n\n");
+
+        for index in 0..1 + self.rng.below(4) {
+            let params: Vec<Ty> = (0..self.rng.below(4)).map(|_| self.ty()).collect();
+            let ret = self.ty();
+            let name = format!("f{}", index);
+
+            self.scope.clear();
+            let signature: Vec<String> = params
+                .iter()
+                .enumerate()
+                .map(|(index, ty)| {
+                    let name = format!("p{}", index);
+                    self.scope.push(Binding {
+                        name: name.clone(),
+                        ty: *ty,
+                        mutable: false,
+                    });
+                    format!("{}: {}", name, ty.name())
+                })
+                .collect();
+
+            writeln!(
+                source,
+                "fn {}({}) -> {} {{",
+                name,
+                signature.join(", "),
+                ret.name()
+            )
+            .unwrap();
+            for _ in 0..self.rng.below(5) {
+                let stmt = self.stmt(1);
+                writeln!(source, "    {}", stmt).unwrap();
+            }
+            let value = self.expr(ret, 0);
+            writeln!(source, "    {}\n}}\n", value).unwrap();
+
+            self.functions.push(Function { name, params, ret });
+        }
+
+        self.scope.clear();
+        source.push_str("fn main() {\n");
+        for index in 0..self.functions.len() {
+            let args: Vec<String> = self.functions[index]
+                .params
+                .clone()
+                .into_iter()
+                .map(|ty| self.expr(ty, 1))
+                .collect();
+            let call = format!("{}({})", self.functions[index].name, args.join(", "));
+            writeln!(source, "    println!(\"{{}}\", {});", call).unwrap();
+        }
+        source.push_str("}\n");
+
+        source
+    }
+
+    fn ty(&mut self) -> Ty {
+        Ty::ALL[self.rng.below(Ty::ALL.len())]
+    }
+
+    fn fresh(&mut self) -> String {
+        // Each keyword is used once, so two bindings in one pattern never share a name
+        if self.keywords_used < LUAU_KEYWORDS.len() && self.rng.chance(15) {
+            self.keywords_used += 1;
+            return LUAU_KEYWORDS[self.keywords_used - 1].to_string();
+        }
+
+        self.next_name += 1;
+        format!("v{}", self.next_name)
+    }
+
+    fn variable(&mut self, ty: Ty, mutable: bool) -> Option<String> {
+        let candidates: Vec<&Binding> = self
+            .scope
+            .iter()
+            .filter(|binding| binding.ty == ty && (binding.mutable || !mutable))
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+
+        let index = self.rng.below(candidates.len());
+        Some(candidates[index].name.clone())
+    }
+
+    /// Runs `body` with the bindings it declares dropped afterwards, like a block.
+    fn scoped(&mut self, body: impl FnOnce(&mut Self) -> String) -> String {
+        let depth = self.scope.len();
+        let code = body(self);
+        self.scope.truncate(depth);
+        code
+    }
+
+    fn block(&mut self, depth: usize) -> String {
+        self.scoped(|this| {
+            let stmts: Vec<String> = (0..1 + this.rng.below(3))
+                .map(|_| this.stmt(depth + 1))
+                .collect();
+            format!("{{ {} }}", stmts.join(" "))
+        })
+    }
+
+    fn stmt(&mut self, depth: usize) -> String {
+        let nested = depth < self.max_depth;
+
+        match self.rng.below(10) {
+            0 | 1 => {
+                let ty = self.ty();
+                let mutable = self.rng.chance(50);
+                let value = self.expr(ty, depth);
+                let name = self.fresh();
+                self.scope.push(Binding {
+                    name: name.clone(),
+                    ty,
+                    mutable,
+                });
+                let keyword = if mutable { "let mut" } else { "let" };
+                format!("{} {}: {} = {};", keyword, name, ty.name(), value)
+            }
+            2 => match self.variable(Ty::Int, true) {
+                Some(name) => {
+                    let op = ["+=", "-=", "*="][self.rng.below(3)];
+                    format!("{} {} {};", name, op, self.expr(Ty::Int, depth))
+                }
+                None => format!("let _ = {};", self.expr(Ty::Bool, depth)),
+            },
+            3 if nested => {
+                let condition = self.expr(Ty::Bool, depth);
+                let then = self.block(depth);
+                if self.rng.chance(50) {
+                    format!("if {} {} else {}", condition, then, self.block(depth))
+                } else {
+                    format!("if {} {}", condition, then)
+                }
+            }
+            4 if nested => {
+                let counter = self.fresh();
+                let limit = self.rng.below(4);
+                let body = self.block(depth);
+                format!(
+                    "let mut {c} = 0; while {c} < {} {{ {c} += 1; {} }}",
+                    limit,
+                    body,
+                    c = counter
+                )
+            }
+            5 if nested => {
+                let index = self.fresh();
+                let end = self.rng.below(5);
+                let body = self.scoped(|this| {
+                    this.scope.push(Binding {
+                        name: index.clone(),
+                        ty: Ty::Int,
+                        mutable: false,
+                    });
+                    this.block(depth)
+                });
+                format!("for {} in 0..{} {}", index, end, body)
+            }
+            6 if nested => {
+                let value = self.fresh();
+                let some = self.expr(Ty::Int, depth);
+                let option = if self.rng.chance(50) {
+                    format!("Some({})", some)
+                } else {
+                    "None".to_string()
+                };
+                let body = self.scoped(|this| {
+                    this.scope.push(Binding {
+                        name: value.clone(),
+                        ty: Ty::Int,
+                        mutable: false,
+                    });
+                    this.block(depth)
+                });
+                format!(
+                    "let {o}: Option<i32> = {}; if let Some({}) = {o} {}",
+                    option,
+                    value,
+                    body,
+                    o = self.fresh()
+                )
+            }
+            7 => {
+                let (a, b) = (self.fresh(), self.fresh());
+                let left = self.expr(Ty::Int, depth);
+                let right = self.expr(Ty::Bool, depth);
+                self.scope.push(Binding {
+                    name: a.clone(),
+                    ty: Ty::Int,
+                    mutable: false,
+                });
+                self.scope.push(Binding {
+                    name: b.clone(),
+                    ty: Ty::Bool,
+                    mutable: false,
+                });
+                format!("let ({}, {}) = ({}, {});", a, b, left, right)
+            }
+            8 => {
+                let point = self.point(depth);
+                let (x, on) = (self.fresh(), self.fresh());
+                self.scope.push(Binding {
+                    name: x.clone(),
+                    ty: Ty::Int,
+                    mutable: false,
+                });
+                self.scope.push(Binding {
+                    name: on.clone(),
+                    ty: Ty::Bool,
+                    mutable: false,
+                });
+                format!("let Point {{ x: {}, on: {}, .. }} = {};", x, on, point)
+            }
+            _ => format!("println!(\"{{}}\", {});", self.expr(Ty::Int, depth)),
+        }
+    }
+
+    fn expr(&mut self, ty: Ty, depth: usize) -> String {
+        if depth >= self.max_depth || self.rng.chance(30) {
+            return self.leaf(ty);
+        }
+        let depth = depth + 1;
+
+        match (ty, self.rng.below(6)) {
+            (_, 0) => {
+                let condition = self.expr(Ty::Bool, depth);
+                let then = self.expr(ty, depth);
+                let otherwise = self.expr(ty, depth);
+                format!("if {} {{ {} }} else {{ {} }}", condition, then, otherwise)
+            }
+            (_, 1) => {
+                let scrutinee = self.expr(Ty::Int, depth);
+                let arms = [
+                    format!("0 => {}", self.expr(ty, depth)),
+                    format!("1..=3 => {}", self.expr(ty, depth)),
+                    format!("-5..0 => {}", self.expr(ty, depth)),
+                    format!("_ => {}", self.expr(ty, depth)),
+                ];
+                format!("match {} {{ {} }}", scrutinee, arms.join(", "))
+            }
+            (_, 2) => {
+                let (a, b) = (self.expr(Ty::Int, depth), self.expr(Ty::Bool, depth));
+                let arms = [
+                    format!("(0, true) => {}", self.expr(ty, depth)),
+                    format!("(_, false) => {}", self.expr(ty, depth)),
+                    format!("_ => {}", self.expr(ty, depth)),
+                ];
+                format!("match ({}, {}) {{ {} }}", a, b, arms.join(", "))
+            }
+            (_, 3) => {
+                let candidates: Vec<usize> = (0..self.functions.len())
+                    .filter(|index| self.functions[*index].ret == ty)
+                    .collect();
+                if candidates.is_empty() {
+                    return self.leaf(ty);
+                }
+                let index = candidates[self.rng.below(candidates.len())];
+                let args: Vec<String> = self.functions[index]
+                    .params
+                    .clone()
+                    .into_iter()
+                    .map(|param| self.expr(param, depth))
+                    .collect();
+                format!("{}({})", self.functions[index].name, args.join(", "))
+            }
+            (Ty::Int, 4) => match self.rng.below(4) {
+                0 => format!("({} as i32)", self.expr(Ty::Float, depth)),
+                1 => format!("({} as i16 as u8 as i32)", self.expr(Ty::Int, depth)),
+                2 => format!("({} as i32)", self.expr(Ty::Bool, depth)),
+                _ => format!("({} as u32 as i32)", CHARS[self.rng.below(CHARS.len())]),
+            },
+            (Ty::Int, 5) => {
+                // A literal divisor, so the program can't divide by zero
+                let op = ["/", "%"][self.rng.below(2)];
+                let divisor = 1 + self.rng.below(9);
+                format!("({} {} {})", self.expr(Ty::Int, depth), op, divisor)
+            }
+            (Ty::Int, _) => {
+                let op = ["+", "-", "*"][self.rng.below(3)];
+                format!(
+                    "({} {} {})",
+                    self.expr(Ty::Int, depth),
+                    op,
+                    self.expr(Ty::Int, depth)
+                )
+            }
+            (Ty::Float, 4) => format!("({} as f64)", self.expr(Ty::Int, depth)),
+            (Ty::Float, 5) => format!("({} as f32 as f64)", self.expr(Ty::Float, depth)),
+            (Ty::Float, _) => {
+                let op = ["+", "-", "*", "/", "%"][self.rng.below(5)];
+                let (left, right) = (self.expr(Ty::Float, depth), self.expr(Ty::Float, depth));
+                format!("({} {} {})", left, op, right)
+            }
+            (Ty::Bool, 4) => {
+                let op = ["<", "<=", "==", "!=", ">"][self.rng.below(5)];
+                format!(
+                    "({} {} {})",
+                    self.expr(Ty::Int, depth),
+                    op,
+                    self.expr(Ty::Int, depth)
+                )
+            }
+            (Ty::Bool, _) => {
+                let op = ["&&", "||"][self.rng.below(2)];
+                let (left, right) = (self.expr(Ty::Bool, depth), self.expr(Ty::Bool, depth));
+                format!("(!{} {} {})", left, op, right)
+            }
+            (Ty::Str, 4) => {
+                let point = self.point(depth);
+                format!("{{ let p = {}; format!(\"{{}}/{{}}\", p.x, p.on) }}", point)
+            }
+            (Ty::Str, 5) => format!("String::from({})", CHARS[self.rng.below(CHARS.len())]),
+            (Ty::Str, _) => {
+                let (left, right) = (self.expr(Ty::Int, depth), self.expr(Ty::Str, depth));
+                format!("format!(\"{{}}-{{}}\", {}, {})", left, right)
+            }
+        }
+    }
+
+    fn point(&mut self, depth: usize) -> String {
+        format!(
+            "Point {{ x: {}, y: {}, on: {} }}",
+            self.expr(Ty::Int, depth),
+            self.expr(Ty::Float, depth),
+            self.expr(Ty::Bool, depth)
+        )
+    }
+
+    fn leaf(&mut self, ty: Ty) -> String {
+        if self.rng.chance(60) {
+            if let Some(name) = self.variable(ty, false) {
+                return if ty == Ty::Str {
+                    format!("{}.clone()", name)
+                } else {
+                    name
+                };
+            }
+        }
+
+        match ty {
+            Ty::Int => (self.rng.below(20) as i32 - 5).to_string(),
+            Ty::Float => format!("{}.5", self.rng.below(10)),
+            Ty::Bool => ["true", "false"][self.rng.below(2)].to_string(),
+            Ty::Str if self.rng.chance(30) => {
+                format!("String::from({})", STRINGS[self.rng.below(STRINGS.len())])
+            }
+            Ty::Str => format!("String::from(\"s{}\")", self.rng.below(10)),
+        }
+    }
+}
@@ -0,0 +1,55 @@
+/// The comment a file starts with under `--header`: the `@generated` marker that code review
+/// tools collapse generated files by, the version and source it was generated with, and
+/// when, unless `timestamp` is `None`.
+pub fn header(file: &str, source: &str, timestamp: Option<u64>) -> String {
+    let mut header = format!(
+        "-- @generated by roblox-rs {} from {}; edits are lost when it is regenerated\n-- source hash: {:016x}\n",
+        env!("CARGO_PKG_VERSION"),
+        file.replace('\\', "/"),
+        source_hash(source)
+    );
+
+    if let Some(timestamp) = timestamp {
+        header += &format!("-- generated at: {}\n", utc_datetime(timestamp));
+    }
+
+    header
+}
+
+/// FNV-1a, which is the same on every platform and Rust release.
+fn source_hash(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// Seconds since the Unix epoch as an RFC 3339 UTC time, converting days to a date with
+/// Howard Hinnant's `civil_from_days`.
+fn utc_datetime(timestamp: u64) -> String {
+    let days = (timestamp / 86400) as i64 + 719468;
+    let seconds = timestamp % 86400;
+
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
@@ -0,0 +1,145 @@
+use syn::visit_mut::VisitMut;
+
+/// Rewrites each `impl Trait` parameter type into a type parameter of the function with the
+/// same bounds, which is what it means in Rust, so `fn total(shape: impl Shape)` is lowered
+/// like `fn total<T: Shape>(shape: T)`, trait dictionaries included. `impl Fn(i32)` parameters
+/// and `impl Trait` return types are left for the transpiler to type.
+pub fn desugar_impl_params(file: &mut syn::File) {
+    ImplParams.visit_file_mut(file);
+}
+
+struct ImplParams;
+
+impl VisitMut for ImplParams {
+    fn visit_signature_mut(&mut self, sig: &mut syn::Signature) {
+        let mut taken: Vec<String> = sig
+            .generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect();
+
+        for input in &mut sig.inputs {
+            if let syn::FnArg::Typed(pat_type) = input {
+                let mut params = Vec::new();
+                replace_impl_types(&mut pat_type.ty, &mut taken, &mut params);
+
+                for param in params {
+                    sig.generics.params.push(syn::GenericParam::Type(param));
+                }
+            }
+        }
+
+        if !sig.generics.params.is_empty() && sig.generics.lt_token.is_none() {
+            sig.generics.lt_token = Some(Default::default());
+            sig.generics.gt_token = Some(Default::default());
+        }
+    }
+}
+
+/// Replaces the `impl Trait` types in `ty` with fresh type parameters, collecting them.
+fn replace_impl_types(
+    ty: &mut syn::Type,
+    taken: &mut Vec<String>,
+    params: &mut Vec<syn::TypeParam>,
+) {
+    struct Replace<'a> {
+        taken: &'a mut Vec<String>,
+        params: &'a mut Vec<syn::TypeParam>,
+    }
+
+    impl VisitMut for Replace<'_> {
+        fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+            // A closure parameter is typed as a Luau function type instead
+            let syn::Type::ImplTrait(impl_trait) = ty else {
+                return syn::visit_mut::visit_type_mut(self, ty);
+            };
+            if fn_bound(&impl_trait.bounds).is_some() {
+                return syn::visit_mut::visit_type_mut(self, ty);
+            }
+
+            let name = fresh_param(self.taken);
+            let ident = syn::Ident::new(&name, impl_trait.impl_token.span);
+            self.params.push(syn::TypeParam {
+                attrs: Vec::new(),
+                ident: ident.clone(),
+                colon_token: Some(Default::default()),
+                bounds: impl_trait.bounds.clone(),
+                eq_token: None,
+                default: None,
+            });
+
+            *ty = syn::Type::Path(syn::TypePath {
+                qself: None,
+                path: ident.into(),
+            });
+        }
+
+        // A nested function's own `impl Trait` parameters belong to it
+        fn visit_type_bare_fn_mut(&mut self, _: &mut syn::TypeBareFn) {}
+    }
+
+    Replace { taken, params }.visit_type_mut(ty);
+}
+
+fn fresh_param(taken: &mut Vec<String>) -> String {
+    let name = ["T", "U", "V"]
+        .into_iter()
+        .map(str::to_string)
+        .chain((1..).map(|index| format!("T{}", index)))
+        .find(|name| !taken.contains(name))
+        .expect("There are always more names");
+
+    taken.push(name.clone());
+    name
+}
+
+/// The arguments of an `Fn`, `FnMut` or `FnOnce` bound among `bounds`, like `(i32) -> i32`.
+pub fn fn_bound<P>(
+    bounds: &syn::punctuated::Punctuated<syn::TypeParamBound, P>,
+) -> Option<&syn::ParenthesizedGenericArguments> {
+    bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(trait_bound) = bound else {
+            return None;
+        };
+        let segment = trait_bound.path.segments.last()?;
+
+        match &segment.arguments {
+            syn::PathArguments::Parenthesized(args)
+                if matches!(
+                    segment.ident.to_string().as_str(),
+                    "Fn" | "FnMut" | "FnOnce"
+                ) =>
+            {
+                Some(args)
+            }
+            _ => None,
+        }
+    })
+}
+
+/// The type parameters of `generics` bounded by a closure trait, inline or in the `where`
+/// clause, with the bound's arguments.
+pub fn fn_bounded_params(
+    generics: &syn::Generics,
+) -> Vec<(String, &syn::ParenthesizedGenericArguments)> {
+    generics
+        .type_params()
+        .filter_map(|param| {
+            let where_bounds = generics
+                .where_clause
+                .iter()
+                .flat_map(|where_clause| &where_clause.predicates)
+                .filter_map(|predicate| match predicate {
+                    syn::WherePredicate::Type(predicate)
+                        if matches!(&predicate.bounded_ty, syn::Type::Path(path) if path.path.is_ident(&param.ident)) =>
+                    {
+                        fn_bound(&predicate.bounds)
+                    }
+                    _ => None,
+                });
+            let args = fn_bound(&param.bounds).into_iter().chain(where_bounds).next()?;
+
+            Some((param.ident.to_string(), args))
+        })
+        .collect()
+}
@@ -0,0 +1,644 @@
+use crate::crates::{rewrite_crate_path, use_leaves, CrateImports};
+use crate::options::CrateModules;
+use proc_macro2::{Delimiter, Punct, Spacing, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet};
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ItemKind {
+    Module,
+    Function,
+    /// A `const` or `static`
+    Value,
+    Enum,
+    Variant,
+    /// A struct, trait or type alias
+    Type,
+    /// An item of a module `require`d into a local, by its path through the local
+    Required,
+}
+
+/// An item a `pub use` adds to its module's table, or to the file's exports.
+pub struct ReExport {
+    /// The inline module the `use` is in, empty at the top of the file
+    pub module: Vec<String>,
+    pub name: String,
+    /// The item's path from the crate root
+    pub path: Vec<String>,
+    pub kind: ItemKind,
+}
+
+/// Rewrites paths through names that `use` declarations bring into scope in the file's own
+/// modules, like `Round` from `use shapes::Circle as Round` or `deep` from a glob, to the
+/// `crate::` path of the item they name, and aliases of items from outside the file, like
+/// `Map` from `use std::collections::HashMap as Map`, to the item's own name. Names imported
+/// from workspace crates or the crate's other files are rewritten to their path through the
+/// module's `require`, which is added to `crate_imports`, unless [`crate::crates`] already
+/// bound them at the top of the file. Returns the items `pub use` re-exports.
+pub fn resolve_imports(
+    file: &mut syn::File,
+    crates: &[CrateModules],
+    crate_imports: &mut CrateImports,
+) -> Vec<ReExport> {
+    let mut tree = Tree {
+        modules: HashMap::new(),
+        variants: HashMap::new(),
+        crates: crates.iter().map(|krate| krate.name.clone()).collect(),
+        // The crate's own modules include its root unless the file is the root
+        nested: crates.iter().any(|krate| {
+            krate.name == "crate" && krate.modules.iter().any(|(path, _)| path.is_empty())
+        }),
+    };
+    tree.collect(&file.items, &mut Vec::new());
+
+    let mut paths: Vec<&Vec<String>> = tree.modules.keys().collect();
+    paths.sort();
+    let mut re_exports = Vec::new();
+    let mut aliases = HashMap::new();
+    for path in paths {
+        let module = &tree.modules[path];
+        let mut module_aliases = tree.aliases(path, &module.uses);
+        // The file's own `use`s of other files are bound to locals by `crates`
+        if path.is_empty() {
+            module_aliases.retain(|_, target| !matches!(target, Target::Crate(_)));
+        }
+        aliases.insert(path.clone(), module_aliases);
+
+        for (name, target) in tree.imports(path, &module.uses, true) {
+            match target {
+                Target::Local(item, kind) => re_exports.push(ReExport {
+                    module: path.clone(),
+                    name,
+                    path: item,
+                    kind,
+                }),
+                Target::Crate(_) if path.is_empty() => re_exports.push(ReExport {
+                    module: path.clone(),
+                    path: vec![name.clone()],
+                    name,
+                    kind: ItemKind::Required,
+                }),
+                Target::Crate(item) => {
+                    if let Some(item) = rewrite_crate_path(&item, crates, crate_imports) {
+                        re_exports.push(ReExport {
+                            module: path.clone(),
+                            name,
+                            path: item,
+                            kind: ItemKind::Required,
+                        });
+                    }
+                }
+                Target::External(_) => {}
+            }
+        }
+    }
+
+    // A `pub mod` in a file of its own is exported through its `require`
+    for item in &file.items {
+        let syn::Item::Mod(item_mod) = item else {
+            continue;
+        };
+        if item_mod.content.is_some() || !matches!(item_mod.vis, syn::Visibility::Public(_)) {
+            continue;
+        }
+
+        let name = item_mod.ident.to_string();
+        if let Some(path) = rewrite_crate_path(std::slice::from_ref(&name), crates, crate_imports) {
+            re_exports.push(ReExport {
+                module: Vec::new(),
+                name,
+                path,
+                kind: ItemKind::Required,
+            });
+        }
+    }
+
+    Rewriter {
+        tree: &tree,
+        crates,
+        crate_imports,
+        module_aliases: aliases,
+        module: Vec::new(),
+        scopes: Vec::new(),
+        bound: HashSet::new(),
+        callee: false,
+    }
+    .visit_file_mut(file);
+
+    re_exports
+}
+
+#[derive(Clone, Debug)]
+enum Target {
+    Local(Vec<String>, ItemKind),
+    /// An item from outside the file, by its own name
+    External(String),
+    /// An item of a workspace crate or another file of the crate, by its path
+    Crate(Vec<String>),
+}
+
+/// A `use` leaf: the path it names, the name it brings in or `*`, and whether it's `pub`.
+type UseLeaf = (Vec<String>, String, bool);
+
+#[derive(Default)]
+struct Module {
+    items: HashMap<String, ItemKind>,
+    uses: Vec<UseLeaf>,
+}
+
+struct Tree {
+    /// The file's inline modules by path, with the file itself at `[]`
+    modules: HashMap<Vec<String>, Module>,
+    /// The variants of each enum, by the enum's path
+    variants: HashMap<Vec<String>, Vec<String>>,
+    /// The names paths into workspace crates and the crate's own modules start with
+    crates: HashSet<String>,
+    /// Whether the file is a module of its crate rather than its root, so `crate::` leaves it
+    nested: bool,
+}
+
+/// How many `use` declarations a name is followed through before it's left as it is.
+const MAX_DEPTH: usize = 16;
+
+impl Tree {
+    fn collect(&mut self, items: &[syn::Item], path: &mut Vec<String>) {
+        let mut module = Module::default();
+
+        for item in items {
+            let (ident, kind) = match item {
+                syn::Item::Fn(item_fn) => (&item_fn.sig.ident, ItemKind::Function),
+                syn::Item::Const(item_const) => (&item_const.ident, ItemKind::Value),
+                syn::Item::Static(item_static) => (&item_static.ident, ItemKind::Value),
+                syn::Item::Struct(item_struct) => (&item_struct.ident, ItemKind::Type),
+                syn::Item::Trait(item_trait) => (&item_trait.ident, ItemKind::Type),
+                syn::Item::Type(item_type) => (&item_type.ident, ItemKind::Type),
+                syn::Item::Enum(item_enum) => {
+                    let mut enum_path = path.clone();
+                    enum_path.push(item_enum.ident.to_string());
+                    let variants = item_enum
+                        .variants
+                        .iter()
+                        .map(|variant| variant.ident.to_string())
+                        .collect();
+                    self.variants.insert(enum_path, variants);
+                    (&item_enum.ident, ItemKind::Enum)
+                }
+                syn::Item::Mod(item_mod) => {
+                    let Some((_, items)) = &item_mod.content else {
+                        continue;
+                    };
+                    path.push(item_mod.ident.to_string());
+                    self.collect(items, path);
+                    path.pop();
+                    (&item_mod.ident, ItemKind::Module)
+                }
+                syn::Item::Use(item_use) => {
+                    module.uses.extend(leaves(item_use));
+                    continue;
+                }
+                _ => continue,
+            };
+
+            module.items.insert(ident.to_string(), kind);
+        }
+
+        self.modules.insert(path.clone(), module);
+    }
+
+    /// The names `uses` bring into `module`, other than ones it declares itself. `exported`
+    /// keeps only the `pub` ones.
+    fn imports(
+        &self,
+        module: &[String],
+        uses: &[UseLeaf],
+        exported: bool,
+    ) -> Vec<(String, Target)> {
+        let own = &self.modules[module].items;
+        let mut imports = Vec::new();
+
+        for (path, name, is_pub) in uses {
+            if exported && !is_pub {
+                continue;
+            }
+
+            if name != "*" {
+                if let Some(target) = self.resolve(module, path, 0) {
+                    imports.push((name.clone(), target));
+                }
+                continue;
+            }
+
+            match self.resolve(module, path, 0) {
+                Some(Target::Local(glob, ItemKind::Module)) => {
+                    let inner = &self.modules[&glob];
+                    let mut names: Vec<&String> = inner
+                        .items
+                        .keys()
+                        .chain(
+                            inner
+                                .uses
+                                .iter()
+                                .filter(|(_, name, is_pub)| *is_pub && name != "*")
+                                .map(|(_, name, _)| name),
+                        )
+                        .collect();
+                    names.sort();
+
+                    for name in names {
+                        if let Some(target) = self.name_in(&glob, name, 1) {
+                            imports.push((name.clone(), target));
+                        }
+                    }
+                }
+                Some(Target::Local(glob, ItemKind::Enum)) => {
+                    for variant in &self.variants[&glob] {
+                        let mut path = glob.clone();
+                        path.push(variant.clone());
+                        imports.push((variant.clone(), Target::Local(path, ItemKind::Variant)));
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        imports.retain(|(name, _)| !own.contains_key(name));
+        imports
+    }
+
+    /// What each imported name is rewritten to, leaving out the ones that already are it.
+    fn aliases(&self, module: &[String], uses: &[UseLeaf]) -> HashMap<String, Target> {
+        self.imports(module, uses, false)
+            .into_iter()
+            .filter(|(name, target)| match target {
+                Target::External(original) => original != name,
+                Target::Local(..) | Target::Crate(_) => true,
+            })
+            .collect()
+    }
+
+    /// The item a `use` path names from `module`, following `crate::`, `self::` and `super::`
+    /// and the `use` declarations of the modules it passes through.
+    fn resolve(&self, module: &[String], path: &[String], depth: usize) -> Option<Target> {
+        let (first, rest) = path.split_first()?;
+        if depth > MAX_DEPTH {
+            return None;
+        }
+
+        // Paths the file's own items don't resolve name a module declared in a file of its own
+        let other_file = || {
+            self.crates
+                .contains(first)
+                .then(|| Target::Crate(path.to_vec()))
+        };
+
+        match first.as_str() {
+            "crate" if self.nested => other_file(),
+            "crate" => self.lookup(&[], rest, depth).or_else(other_file),
+            "self" => self.lookup(module, rest, depth).or_else(other_file),
+            "super" => {
+                let mut base = module.to_vec();
+                let mut rest = path;
+                while rest.first().is_some_and(|segment| segment == "super") {
+                    if base.pop().is_none() {
+                        return other_file();
+                    }
+                    rest = &rest[1..];
+                }
+                self.lookup(&base, rest, depth)
+            }
+            _ if self.name_in(module, first, depth).is_some() => self.lookup(module, path, depth),
+            _ => other_file().or_else(|| Some(Target::External(path.last()?.clone()))),
+        }
+    }
+
+    fn lookup(&self, module: &[String], path: &[String], depth: usize) -> Option<Target> {
+        let Some((first, rest)) = path.split_first() else {
+            return Some(Target::Local(module.to_vec(), ItemKind::Module));
+        };
+
+        let target = self.name_in(module, first, depth)?;
+        if rest.is_empty() {
+            return Some(target);
+        }
+
+        match target {
+            Target::Local(path, ItemKind::Module) => self.lookup(&path, rest, depth),
+            Target::Local(mut path, ItemKind::Enum)
+                if rest.len() == 1 && self.variants[&path].contains(&rest[0]) =>
+            {
+                path.push(rest[0].clone());
+                Some(Target::Local(path, ItemKind::Variant))
+            }
+            Target::External(_) => Some(Target::External(rest.last()?.clone())),
+            _ => None,
+        }
+    }
+
+    /// What `name` means in `module`: an item it declares, or one it imports.
+    fn name_in(&self, module: &[String], name: &str, depth: usize) -> Option<Target> {
+        let declared = self.modules.get(module)?;
+        if let Some(kind) = declared.items.get(name) {
+            let mut path = module.to_vec();
+            path.push(name.to_string());
+            return Some(Target::Local(path, *kind));
+        }
+
+        if let Some((path, _, _)) = declared.uses.iter().find(|(_, local, _)| local == name) {
+            return self.resolve(module, path, depth + 1);
+        }
+
+        declared
+            .uses
+            .iter()
+            .filter(|(_, local, _)| local == "*")
+            .find_map(
+                |(path, _, _)| match self.resolve(module, path, depth + 1)? {
+                    Target::Local(glob, ItemKind::Module) => self.name_in(&glob, name, depth + 1),
+                    Target::Local(mut glob, ItemKind::Enum)
+                        if self.variants[&glob].iter().any(|variant| variant == name) =>
+                    {
+                        glob.push(name.to_string());
+                        Some(Target::Local(glob, ItemKind::Variant))
+                    }
+                    _ => None,
+                },
+            )
+    }
+}
+
+fn leaves(item_use: &syn::ItemUse) -> Vec<UseLeaf> {
+    let mut leaves = Vec::new();
+    use_leaves(&item_use.tree, &mut Vec::new(), &mut leaves);
+
+    let is_pub = matches!(item_use.vis, syn::Visibility::Public(_));
+    leaves
+        .into_iter()
+        .map(|(path, name)| (path, name, is_pub))
+        .collect()
+}
+
+struct Rewriter<'a> {
+    tree: &'a Tree,
+    crates: &'a [CrateModules],
+    crate_imports: &'a mut CrateImports,
+    module_aliases: HashMap<Vec<String>, HashMap<String, Target>>,
+    module: Vec<String>,
+    /// The aliases of the `use` declarations in the blocks being visited, innermost last
+    scopes: Vec<HashMap<String, Target>>,
+    /// Names bound by patterns in the function being visited, which shadow imports
+    bound: HashSet<String>,
+    /// Whether the path being visited is the function of a call
+    callee: bool,
+}
+
+impl Rewriter<'_> {
+    fn alias(&self, name: &str) -> Option<&Target> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.module_aliases.get(&self.module)?.get(name))
+    }
+
+    /// The segments a path starting with the alias `name` starts with instead. A local
+    /// of the same name is read instead if the path is `shadowable`, being just the name
+    /// and not called; locals holding closures aren't told apart from the import.
+    fn replacement(&mut self, name: &str, shadowable: bool) -> Option<Vec<String>> {
+        match self.alias(name)? {
+            Target::Crate(path) => {
+                let path = path.clone();
+                rewrite_crate_path(&path, self.crates, self.crate_imports)
+            }
+            Target::Local(_, ItemKind::Function | ItemKind::Value)
+                if shadowable && self.bound.contains(name) =>
+            {
+                None
+            }
+            // Types, constants and variants keep their file-level local wherever they're declared
+            Target::Local(path, ItemKind::Type | ItemKind::Enum | ItemKind::Value) => {
+                Some(path[path.len() - 1..].to_vec())
+            }
+            Target::Local(path, ItemKind::Variant) => Some(path[path.len() - 2..].to_vec()),
+            Target::Local(path, _) if path.len() == 1 => Some(path.clone()),
+            Target::Local(path, _) => Some(
+                std::iter::once("crate".to_string())
+                    .chain(path.iter().cloned())
+                    .collect(),
+            ),
+            Target::External(original) => Some(vec![original.clone()]),
+        }
+    }
+
+    /// Whether a pattern binding `name` instead matches the imported constant or variant.
+    fn is_imported_constant(&self, name: &str) -> bool {
+        matches!(
+            self.alias(name),
+            Some(Target::Local(_, ItemKind::Value | ItemKind::Variant))
+        )
+    }
+
+    fn visit_function<T>(
+        &mut self,
+        visit: impl FnOnce(&mut Self) -> T,
+        bound: HashSet<String>,
+    ) -> T {
+        let outer = std::mem::replace(&mut self.bound, bound);
+        let result = visit(self);
+        self.bound = outer;
+        result
+    }
+
+    fn bindings(&self, node: impl FnOnce(&mut Bindings)) -> HashSet<String> {
+        let mut bindings = Bindings {
+            rewriter: self,
+            names: HashSet::new(),
+        };
+        node(&mut bindings);
+        bindings.names
+    }
+
+    /// The same rewrite over the tokens of a macro's arguments, for names that aren't a
+    /// field, a method or a later segment of a path.
+    fn rewrite_tokens(&mut self, tokens: TokenStream) -> TokenStream {
+        let trees: Vec<TokenTree> = tokens.into_iter().collect();
+        let mut rewritten = Vec::new();
+
+        for (index, tree) in trees.iter().enumerate() {
+            match tree {
+                TokenTree::Group(group) => {
+                    let mut inner = proc_macro2::Group::new(
+                        group.delimiter(),
+                        self.rewrite_tokens(group.stream()),
+                    );
+                    inner.set_span(group.span());
+                    rewritten.push(TokenTree::Group(inner));
+                }
+                TokenTree::Ident(ident) => {
+                    let after_dot = index >= 1
+                        && matches!(&trees[index - 1], TokenTree::Punct(punct) if punct.as_char() == '.');
+                    let after_path = index >= 2
+                        && matches!(
+                            (&trees[index - 2], &trees[index - 1]),
+                            (TokenTree::Punct(first), TokenTree::Punct(second))
+                                if first.as_char() == ':' && second.as_char() == ':'
+                        );
+                    let shadowable = !matches!(
+                        trees.get(index + 1),
+                        Some(TokenTree::Punct(punct)) if punct.as_char() == ':'
+                    ) && !matches!(
+                        trees.get(index + 1),
+                        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Parenthesis
+                    );
+                    let replacement = (!after_dot && !after_path)
+                        .then(|| self.replacement(&ident.to_string(), shadowable))
+                        .flatten();
+
+                    let Some(segments) = replacement else {
+                        rewritten.push(tree.clone());
+                        continue;
+                    };
+                    for (position, segment) in segments.iter().enumerate() {
+                        if position > 0 {
+                            rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
+                            rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+                        }
+                        rewritten.push(TokenTree::Ident(proc_macro2::Ident::new(
+                            segment,
+                            ident.span(),
+                        )));
+                    }
+                }
+                _ => rewritten.push(tree.clone()),
+            }
+        }
+
+        rewritten.into_iter().collect()
+    }
+}
+
+impl VisitMut for Rewriter<'_> {
+    fn visit_item_use_mut(&mut self, _: &mut syn::ItemUse) {}
+
+    fn visit_attribute_mut(&mut self, _: &mut syn::Attribute) {}
+
+    fn visit_item_mod_mut(&mut self, i: &mut syn::ItemMod) {
+        self.module.push(i.ident.to_string());
+        syn::visit_mut::visit_item_mod_mut(self, i);
+        self.module.pop();
+    }
+
+    fn visit_item_fn_mut(&mut self, i: &mut syn::ItemFn) {
+        let bound = self.bindings(|bindings| bindings.visit_item_fn(i));
+        self.visit_function(
+            |rewriter| syn::visit_mut::visit_item_fn_mut(rewriter, i),
+            bound,
+        );
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, i: &mut syn::ImplItemFn) {
+        let bound = self.bindings(|bindings| bindings.visit_impl_item_fn(i));
+        self.visit_function(
+            |rewriter| syn::visit_mut::visit_impl_item_fn_mut(rewriter, i),
+            bound,
+        );
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, i: &mut syn::TraitItemFn) {
+        let bound = self.bindings(|bindings| bindings.visit_trait_item_fn(i));
+        self.visit_function(
+            |rewriter| syn::visit_mut::visit_trait_item_fn_mut(rewriter, i),
+            bound,
+        );
+    }
+
+    fn visit_block_mut(&mut self, i: &mut syn::Block) {
+        let uses: Vec<UseLeaf> = i
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                syn::Stmt::Item(syn::Item::Use(item_use)) => Some(leaves(item_use)),
+                _ => None,
+            })
+            .flatten()
+            .collect();
+        if uses.is_empty() {
+            return syn::visit_mut::visit_block_mut(self, i);
+        }
+
+        let aliases = self.tree.aliases(&self.module, &uses);
+        self.scopes.push(aliases);
+        syn::visit_mut::visit_block_mut(self, i);
+        self.scopes.pop();
+    }
+
+    fn visit_pat_mut(&mut self, i: &mut syn::Pat) {
+        if let syn::Pat::Ident(pat_ident) = i {
+            let plain = pat_ident.by_ref.is_none()
+                && pat_ident.mutability.is_none()
+                && pat_ident.subpat.is_none();
+            if plain && self.is_imported_constant(&pat_ident.ident.to_string()) {
+                *i = syn::Pat::Path(syn::ExprPath {
+                    attrs: Vec::new(),
+                    qself: None,
+                    path: syn::Path::from(pat_ident.ident.clone()),
+                });
+            }
+        }
+
+        syn::visit_mut::visit_pat_mut(self, i);
+    }
+
+    fn visit_expr_call_mut(&mut self, i: &mut syn::ExprCall) {
+        self.callee = matches!(&*i.func, syn::Expr::Path(_));
+        syn::visit_mut::visit_expr_call_mut(self, i);
+    }
+
+    fn visit_path_mut(&mut self, i: &mut syn::Path) {
+        let shadowable = i.segments.len() == 1 && !std::mem::take(&mut self.callee);
+        syn::visit_mut::visit_path_mut(self, i);
+
+        let Some(first) = i.segments.first().filter(|_| i.leading_colon.is_none()) else {
+            return;
+        };
+        let Some(replacement) = self.replacement(&first.ident.to_string(), shadowable) else {
+            return;
+        };
+
+        // The last segment of the replacement keeps the alias's generic arguments
+        let span = first.ident.span();
+        let arguments = first.arguments.clone();
+        let mut segments: Vec<syn::PathSegment> = replacement
+            .iter()
+            .map(|segment| syn::PathSegment::from(syn::Ident::new(segment, span)))
+            .collect();
+        if let Some(last) = segments.last_mut() {
+            last.arguments = arguments;
+        }
+
+        i.segments = segments
+            .into_iter()
+            .chain(i.segments.iter().skip(1).cloned())
+            .collect();
+    }
+
+    fn visit_macro_mut(&mut self, i: &mut syn::Macro) {
+        i.tokens = self.rewrite_tokens(std::mem::take(&mut i.tokens));
+    }
+}
+
+/// Collects the names a function's parameters and patterns bind.
+struct Bindings<'a, 'b> {
+    rewriter: &'a Rewriter<'b>,
+    names: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for Bindings<'_, '_> {
+    fn visit_pat_ident(&mut self, i: &'ast syn::PatIdent) {
+        let name = i.ident.to_string();
+        if !self.rewriter.is_imported_constant(&name) {
+            self.names.insert(name);
+        }
+        syn::visit::visit_pat_ident(self, i);
+    }
+}
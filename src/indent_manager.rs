@@ -21,6 +21,14 @@ impl IndentManager {
         }
     }
 
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    pub fn set_level(&mut self, level: usize) {
+        self.level = level;
+    }
+
     pub fn get_indent(&self) -> String {
         self.indent_str.repeat(self.level)
     }
@@ -0,0 +1,569 @@
+use crate::type_registry::{self, TypeRegistry};
+use proc_macro2::TokenTree;
+use std::collections::{HashMap, HashSet};
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+/// The most expressions a function's body can have to be inlined.
+const MAX_SIZE: usize = 12;
+
+/// The most places a function can be named in and still be inlined, so the output doesn't
+/// grow by more than the definitions it saves.
+const MAX_USES: usize = 3;
+
+/// A function whose body is one small expression, inlined at `--opt-level 2`.
+pub struct InlineFn {
+    receiver: bool,
+    params: Vec<String>,
+    /// How many times the body uses each parameter, and `self`
+    uses: HashMap<String, usize>,
+    body: syn::Expr,
+    /// Names the body uses that aren't parameters, such as constants, which a local at the
+    /// call site could shadow
+    free_names: Vec<String>,
+}
+
+/// The functions of a file that can be inlined: free functions by name, and the methods and
+/// associated functions of inherent impls by type and name.
+#[derive(Default)]
+pub struct Inlines {
+    fns: HashMap<(Option<String>, String), InlineFn>,
+    /// Private free functions that every use inlines, so they aren't emitted
+    removed: HashSet<String>,
+}
+
+impl Inlines {
+    pub fn collect(file: &syn::File, types: &TypeRegistry) -> Self {
+        let mut names = NameCounter::default();
+        names.visit_file(file);
+
+        let mut inlines = Inlines::default();
+        let mut private = Vec::new();
+
+        for item in &file.items {
+            match item {
+                syn::Item::Fn(item_fn) if item_fn.sig.ident != "main" => {
+                    let name = item_fn.sig.ident.to_string();
+                    if names.fns.get(&name) != Some(&1) || item_fn.sig.receiver().is_some() {
+                        continue;
+                    }
+
+                    if let Some(inline_fn) =
+                        inline_fn(&item_fn.attrs, &item_fn.sig, &item_fn.block, None, types)
+                    {
+                        if !matches!(item_fn.vis, syn::Visibility::Public(_)) {
+                            private.push(name.clone());
+                        }
+                        inlines.fns.insert((None, name), inline_fn);
+                    }
+                }
+
+                syn::Item::Impl(item_impl)
+                    if item_impl.trait_.is_none() && item_impl.generics.params.is_empty() =>
+                {
+                    let Some(type_name) = type_registry::type_name(&item_impl.self_ty) else {
+                        continue;
+                    };
+
+                    for impl_item in &item_impl.items {
+                        let syn::ImplItem::Fn(impl_fn) = impl_item else {
+                            continue;
+                        };
+
+                        if let Some(inline_fn) = inline_fn(
+                            &impl_fn.attrs,
+                            &impl_fn.sig,
+                            &impl_fn.block,
+                            Some(&type_name),
+                            types,
+                        ) {
+                            let key = (Some(type_name.clone()), impl_fn.sig.ident.to_string());
+                            inlines.fns.insert(key, inline_fn);
+                        }
+                    }
+                }
+
+                _ => {}
+            }
+        }
+
+        inlines.fns.retain(|(_, name), inline_fn| {
+            names.uses.get(name).copied().unwrap_or(0) <= MAX_USES
+                && !inline_fn
+                    .free_names
+                    .iter()
+                    .any(|free| names.bound.contains(free))
+        });
+
+        for name in private {
+            let Some(inline_fn) = inlines.fns.get(&(None, name.clone())) else {
+                continue;
+            };
+
+            let mut uses = RemovalCheck {
+                name: &name,
+                inline_fn,
+                removable: !names.bound.contains(&name),
+            };
+            uses.visit_file(file);
+
+            if uses.removable {
+                inlines.removed.insert(name);
+            }
+        }
+
+        inlines
+    }
+
+    pub fn get(&self, owner: Option<&str>, name: &str) -> Option<&InlineFn> {
+        self.fns.get(&(owner.map(str::to_string), name.to_string()))
+    }
+
+    pub fn is_removed(&self, name: &str) -> bool {
+        self.removed.contains(name)
+    }
+}
+
+impl InlineFn {
+    /// Whether a call with these arguments can be replaced by the body: each argument is
+    /// free of side effects, and one the body uses more than once is cheap to repeat.
+    pub fn accepts<'a>(
+        &self,
+        receiver: Option<&syn::Expr>,
+        args: impl ExactSizeIterator<Item = &'a syn::Expr>,
+    ) -> bool {
+        let accepts = |param: &str, arg: &syn::Expr| match self.uses.get(param) {
+            Some(uses) if *uses > 1 => is_atomic(arg),
+            _ => is_pure(arg),
+        };
+
+        args.len() == self.params.len()
+            && receiver.is_none_or(|receiver| accepts("self", receiver))
+            && self
+                .params
+                .iter()
+                .zip(args)
+                .all(|(param, arg)| accepts(param, arg))
+    }
+
+    pub fn has_receiver(&self) -> bool {
+        self.receiver
+    }
+
+    pub fn free_names(&self) -> &[String] {
+        &self.free_names
+    }
+
+    /// The body with the arguments in place of the parameters and `Self` naming `owner`,
+    /// parenthesized unless it's a single atom so it keeps its precedence in the call's place.
+    pub fn expand<'a>(
+        &self,
+        owner: Option<&str>,
+        receiver: Option<&syn::Expr>,
+        args: impl Iterator<Item = &'a syn::Expr>,
+    ) -> syn::Expr {
+        let mut values: HashMap<String, syn::Expr> = self
+            .params
+            .iter()
+            .cloned()
+            .zip(args.map(parenthesized))
+            .collect();
+        if let Some(receiver) = receiver {
+            values.insert("self".to_string(), parenthesized(receiver));
+        }
+
+        let mut body = self.body.clone();
+        Substitute { values, owner }.visit_expr_mut(&mut body);
+        parenthesized(&body)
+    }
+}
+
+fn inline_fn(
+    attrs: &[syn::Attribute],
+    sig: &syn::Signature,
+    block: &syn::Block,
+    owner: Option<&str>,
+    types: &TypeRegistry,
+) -> Option<InlineFn> {
+    let never = attrs.iter().any(|attr| {
+        attr.path().is_ident("inline")
+            && attr
+                .parse_args::<syn::Ident>()
+                .is_ok_and(|arg| arg == "never")
+    });
+    let other_attrs = attrs
+        .iter()
+        .any(|attr| !attr.path().is_ident("doc") && !attr.path().is_ident("inline"));
+    if never
+        || other_attrs
+        || sig.asyncness.is_some()
+        || !sig.generics.params.is_empty()
+        || matches!(sig.output, syn::ReturnType::Default)
+    {
+        return None;
+    }
+
+    let [syn::Stmt::Expr(body, None)] = &block.stmts[..] else {
+        return None;
+    };
+
+    // The call would have destroyed what it took ownership of
+    let owned = |ty: Option<String>| {
+        ty.map(|ty| match (ty.as_str(), owner) {
+            ("Self", Some(owner)) => owner.to_string(),
+            _ => ty,
+        })
+        .is_some_and(|ty| types.needs_destroy(&ty))
+    };
+
+    let mut params = Vec::new();
+    for arg in &sig.inputs {
+        match arg {
+            syn::FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none() && owned(owner.map(str::to_string)) {
+                    return None;
+                }
+            }
+            syn::FnArg::Typed(pat_type) => {
+                let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                    return None;
+                };
+                if pat_ident.by_ref.is_some()
+                    || pat_ident.subpat.is_some()
+                    || owned(type_registry::type_name(&pat_type.ty))
+                {
+                    return None;
+                }
+                params.push(pat_ident.ident.to_string());
+            }
+        }
+    }
+
+    let mut shape = BodyShape {
+        types,
+        params: &params,
+        size: 0,
+        inlinable: true,
+        uses: HashMap::new(),
+        free_names: Vec::new(),
+    };
+    shape.visit_expr(body);
+    let (inlinable, uses, free_names) = (
+        shape.inlinable && shape.size <= MAX_SIZE,
+        shape.uses,
+        shape.free_names,
+    );
+
+    inlinable.then(|| InlineFn {
+        receiver: sig.receiver().is_some(),
+        params,
+        uses,
+        body: body.clone(),
+        free_names,
+    })
+}
+
+/// Whether an expression can be evaluated any number of times, or not at all, without
+/// changing what the program does.
+fn is_pure(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Unary(unary) => is_pure(&unary.expr),
+        syn::Expr::Binary(binary) => {
+            !is_compound_assign(&binary.op) && is_pure(&binary.left) && is_pure(&binary.right)
+        }
+        syn::Expr::Cast(cast) => is_pure(&cast.expr),
+        syn::Expr::Paren(paren) => is_pure(&paren.expr),
+        _ => is_atomic(expr),
+    }
+}
+
+/// Whether an expression is a name, a literal or a field of one, which costs no more to
+/// repeat than a local would.
+fn is_atomic(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(_) | syn::Expr::Path(_) => true,
+        syn::Expr::Field(field) => is_atomic(&field.base),
+        syn::Expr::Reference(reference) => is_atomic(&reference.expr),
+        syn::Expr::Paren(paren) => is_atomic(&paren.expr),
+        _ => false,
+    }
+}
+
+fn parenthesized(expr: &syn::Expr) -> syn::Expr {
+    match expr {
+        syn::Expr::Lit(_)
+        | syn::Expr::Path(_)
+        | syn::Expr::Field(_)
+        | syn::Expr::Paren(_)
+        | syn::Expr::Call(_)
+        | syn::Expr::MethodCall(_)
+        | syn::Expr::Index(_)
+        | syn::Expr::Struct(_)
+        | syn::Expr::Macro(_) => expr.clone(),
+        _ => syn::parse_quote!((#expr)),
+    }
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::AddAssign(_)
+            | syn::BinOp::SubAssign(_)
+            | syn::BinOp::MulAssign(_)
+            | syn::BinOp::DivAssign(_)
+            | syn::BinOp::RemAssign(_)
+            | syn::BinOp::BitXorAssign(_)
+            | syn::BinOp::BitAndAssign(_)
+            | syn::BinOp::BitOrAssign(_)
+            | syn::BinOp::ShlAssign(_)
+            | syn::BinOp::ShrAssign(_)
+    )
+}
+
+/// Measures a body and checks it only computes a value: no control flow, assignments,
+/// closures or macros, and no calls to free functions, which may themselves be inlined away.
+struct BodyShape<'a> {
+    types: &'a TypeRegistry,
+    params: &'a [String],
+    size: usize,
+    inlinable: bool,
+    uses: HashMap<String, usize>,
+    free_names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for BodyShape<'_> {
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        self.size += 1;
+
+        match i {
+            syn::Expr::Lit(_)
+            | syn::Expr::Field(_)
+            | syn::Expr::Unary(_)
+            | syn::Expr::Paren(_)
+            | syn::Expr::Reference(_)
+            | syn::Expr::Cast(_)
+            | syn::Expr::Struct(_)
+            | syn::Expr::Tuple(_)
+            | syn::Expr::Index(_)
+            | syn::Expr::MethodCall(_) => {}
+
+            syn::Expr::Binary(binary) if !is_compound_assign(&binary.op) => {}
+
+            syn::Expr::Path(path) => {
+                if let Some(ident) = path.path.get_ident() {
+                    let name = ident.to_string();
+                    if name == "self" || self.params.contains(&name) {
+                        *self.uses.entry(name).or_default() += 1;
+                    } else {
+                        self.free_names.push(name);
+                    }
+                }
+            }
+
+            syn::Expr::Call(call) => {
+                let constructor = match &*call.func {
+                    syn::Expr::Path(path) => match path.path.get_ident() {
+                        Some(ident) => {
+                            ident == "Self"
+                                || ["Some", "Ok", "Err"].iter().any(|variant| ident == variant)
+                                || self.types.is_struct(&ident.to_string())
+                        }
+                        None => true,
+                    },
+                    _ => false,
+                };
+
+                if !constructor {
+                    self.inlinable = false;
+                    return;
+                }
+
+                // The callee's a path to a type or variant, not a use of a local
+                for arg in &call.args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+
+            _ => {
+                self.inlinable = false;
+                return;
+            }
+        }
+
+        syn::visit::visit_expr(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        if i.turbofish.is_some() {
+            self.inlinable = false;
+        }
+        syn::visit::visit_expr_method_call(self, i);
+    }
+}
+
+/// How many times each name is used across the file, the names patterns bind and how many
+/// functions of each name there are, in any module.
+#[derive(Default)]
+struct NameCounter {
+    uses: HashMap<String, usize>,
+    bound: HashSet<String>,
+    fns: HashMap<String, usize>,
+}
+
+impl<'ast> Visit<'ast> for NameCounter {
+    fn visit_path(&mut self, i: &'ast syn::Path) {
+        if let Some(last) = i.segments.last() {
+            *self.uses.entry(last.ident.to_string()).or_default() += 1;
+        }
+        syn::visit::visit_path(self, i);
+    }
+
+    fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+        *self.uses.entry(i.method.to_string()).or_default() += 1;
+        syn::visit::visit_expr_method_call(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        count_tokens(i.tokens.clone(), &mut self.uses);
+        syn::visit::visit_macro(self, i);
+    }
+
+    fn visit_pat_ident(&mut self, i: &'ast syn::PatIdent) {
+        self.bound.insert(i.ident.to_string());
+        syn::visit::visit_pat_ident(self, i);
+    }
+
+    fn visit_signature(&mut self, i: &'ast syn::Signature) {
+        *self.fns.entry(i.ident.to_string()).or_default() += 1;
+        syn::visit::visit_signature(self, i);
+    }
+
+    fn visit_foreign_item_fn(&mut self, i: &'ast syn::ForeignItemFn) {
+        *self.fns.entry(i.sig.ident.to_string()).or_default() += 1;
+    }
+}
+
+fn count_tokens(tokens: proc_macro2::TokenStream, uses: &mut HashMap<String, usize>) {
+    for tree in tokens {
+        match tree {
+            TokenTree::Ident(ident) => *uses.entry(ident.to_string()).or_default() += 1,
+            TokenTree::Group(group) => count_tokens(group.stream(), uses),
+            _ => {}
+        }
+    }
+}
+
+/// Checks that every use of a private free function is a call the transpiler inlines, in
+/// an expression whose value is used: a call made for its effects is emitted as a call.
+struct RemovalCheck<'a> {
+    name: &'a str,
+    inline_fn: &'a InlineFn,
+    removable: bool,
+}
+
+impl<'ast> Visit<'ast> for RemovalCheck<'_> {
+    fn visit_stmt(&mut self, i: &'ast syn::Stmt) {
+        if let syn::Stmt::Expr(syn::Expr::Call(call), Some(_)) = i {
+            if let syn::Expr::Path(path) = &*call.func {
+                if path.path.is_ident(self.name) {
+                    self.removable = false;
+                }
+            }
+        }
+        syn::visit::visit_stmt(self, i);
+    }
+
+    fn visit_expr_call(&mut self, i: &'ast syn::ExprCall) {
+        if let syn::Expr::Path(path) = &*i.func {
+            if path.path.is_ident(self.name) && path.qself.is_none() {
+                if !self.inline_fn.accepts(None, i.args.iter()) {
+                    self.removable = false;
+                }
+
+                for arg in &i.args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+        }
+        syn::visit::visit_expr_call(self, i);
+    }
+
+    fn visit_path(&mut self, i: &'ast syn::Path) {
+        if i.segments
+            .last()
+            .is_some_and(|last| last.ident == self.name)
+        {
+            self.removable = false;
+        }
+        syn::visit::visit_path(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        let mut uses = HashMap::new();
+        count_tokens(i.tokens.clone(), &mut uses);
+        if uses.contains_key(self.name) {
+            self.removable = false;
+        }
+        syn::visit::visit_macro(self, i);
+    }
+
+    fn visit_use_tree(&mut self, i: &'ast syn::UseTree) {
+        let ident = match i {
+            syn::UseTree::Path(path) => &path.ident,
+            syn::UseTree::Name(name) => &name.ident,
+            syn::UseTree::Rename(rename) => &rename.ident,
+            _ => return syn::visit::visit_use_tree(self, i),
+        };
+        if ident == self.name {
+            self.removable = false;
+        }
+        syn::visit::visit_use_tree(self, i);
+    }
+
+    // The definition itself
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if i.sig.ident != self.name {
+            syn::visit::visit_item_fn(self, i);
+        }
+    }
+}
+
+struct Substitute<'a> {
+    values: HashMap<String, syn::Expr>,
+    owner: Option<&'a str>,
+}
+
+impl VisitMut for Substitute<'_> {
+    fn visit_expr_mut(&mut self, i: &mut syn::Expr) {
+        if let syn::Expr::Path(path) = i {
+            if let Some(value) = path
+                .path
+                .get_ident()
+                .and_then(|ident| self.values.get(&ident.to_string()))
+            {
+                *i = value.clone();
+                return;
+            }
+        }
+        syn::visit_mut::visit_expr_mut(self, i);
+    }
+
+    // `Point { x, y }` needs its values spelled out once they're replaced
+    fn visit_field_value_mut(&mut self, i: &mut syn::FieldValue) {
+        if i.colon_token.is_none() {
+            i.colon_token = Some(Default::default());
+        }
+        syn::visit_mut::visit_field_value_mut(self, i);
+    }
+
+    fn visit_path_mut(&mut self, i: &mut syn::Path) {
+        if let (Some(owner), Some(first)) = (self.owner, i.segments.first_mut()) {
+            if first.ident == "Self" {
+                first.ident = syn::Ident::new(owner, first.ident.span());
+            }
+        }
+        syn::visit_mut::visit_path_mut(self, i);
+    }
+}
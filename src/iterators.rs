@@ -0,0 +1,404 @@
+const COUNTER: &str = "\u{0}";
+
+#[derive(Clone, PartialEq)]
+pub struct Linear {
+    terms: Vec<(String, i64)>,
+    constant: i64,
+}
+
+impl Linear {
+    pub fn constant(value: i64) -> Self {
+        Self {
+            terms: Vec::new(),
+            constant: value,
+        }
+    }
+
+    pub fn atom(atom: &str) -> Self {
+        Self {
+            terms: vec![(atom.to_string(), 1)],
+            constant: 0,
+        }
+    }
+
+    pub fn counter() -> Self {
+        Self::atom(COUNTER)
+    }
+
+    pub fn as_constant(&self) -> Option<i64> {
+        self.terms.is_empty().then_some(self.constant)
+    }
+
+    pub fn add(&self, other: &Linear) -> Linear {
+        let mut result = self.clone();
+
+        for (atom, coefficient) in &other.terms {
+            match result
+                .terms
+                .iter_mut()
+                .find(|(existing, _)| existing == atom)
+            {
+                Some((_, existing)) => *existing += coefficient,
+                None => result.terms.push((atom.clone(), *coefficient)),
+            }
+        }
+
+        result.terms.retain(|(_, coefficient)| *coefficient != 0);
+        result.constant += other.constant;
+        result
+    }
+
+    pub fn sub(&self, other: &Linear) -> Linear {
+        self.add(&other.scale(-1))
+    }
+
+    pub fn scale(&self, factor: i64) -> Linear {
+        if factor == 0 {
+            return Linear::constant(0);
+        }
+
+        Linear {
+            terms: self
+                .terms
+                .iter()
+                .map(|(atom, coefficient)| (atom.clone(), coefficient * factor))
+                .collect(),
+            constant: self.constant * factor,
+        }
+    }
+
+    pub fn mul(&self, other: &Linear) -> Linear {
+        match (self.as_constant(), other.as_constant()) {
+            (Some(factor), _) => other.scale(factor),
+            (_, Some(factor)) => self.scale(factor),
+            _ => Linear::atom(&format!(
+                "{} * {}",
+                self.render_grouped(),
+                other.render_grouped()
+            )),
+        }
+    }
+
+    pub fn floor_div(&self, divisor: &Linear) -> Linear {
+        match (self.as_constant(), divisor.as_constant()) {
+            (_, Some(1)) => self.clone(),
+            (_, Some(-1)) => self.scale(-1),
+            (Some(value), Some(divisor)) if divisor != 0 => {
+                Linear::constant(value.div_euclid(divisor))
+            }
+            _ => Linear::atom(&format!(
+                "{} // {}",
+                self.render_grouped(),
+                divisor.render_grouped()
+            )),
+        }
+    }
+
+    fn render_grouped(&self) -> String {
+        let rendered = self.render(COUNTER);
+
+        if has_top_level_space(&rendered) {
+            format!("({})", rendered)
+        } else {
+            rendered
+        }
+    }
+
+    fn has_nested_counter(&self) -> bool {
+        self.terms
+            .iter()
+            .any(|(atom, _)| atom != COUNTER && atom.contains(COUNTER))
+    }
+
+    fn counter_coefficient(&self) -> i64 {
+        self.terms
+            .iter()
+            .find(|(atom, _)| atom == COUNTER)
+            .map_or(0, |(_, coefficient)| *coefficient)
+    }
+
+    fn without_counter(&self) -> Linear {
+        let mut result = self.clone();
+        result.terms.retain(|(atom, _)| atom != COUNTER);
+        result
+    }
+
+    fn substitute_counter(&self, replacement: &Linear) -> Linear {
+        self.without_counter()
+            .add(&replacement.scale(self.counter_coefficient()))
+    }
+
+    pub fn render(&self, counter: &str) -> String {
+        let mut out = String::new();
+        let positive = self
+            .terms
+            .iter()
+            .filter(|(_, coefficient)| *coefficient > 0);
+        let negative = self
+            .terms
+            .iter()
+            .filter(|(_, coefficient)| *coefficient < 0);
+
+        for (atom, coefficient) in positive.chain(negative) {
+            let atom = atom.replace(COUNTER, counter);
+            let atom = if *coefficient != 1 && has_top_level_space(&atom) {
+                format!("({})", atom)
+            } else {
+                atom.to_string()
+            };
+
+            let magnitude = match coefficient.abs() {
+                1 => atom,
+                factor => format!("{} * {}", factor, atom),
+            };
+
+            match (out.is_empty(), *coefficient < 0) {
+                (true, false) => out.push_str(&magnitude),
+                (true, true) => out.push_str(&format!("-{}", magnitude)),
+                (false, false) => out.push_str(&format!(" + {}", magnitude)),
+                (false, true) => out.push_str(&format!(" - {}", magnitude)),
+            }
+        }
+
+        match (out.is_empty(), self.constant) {
+            (true, constant) => constant.to_string(),
+            (false, 0) => out,
+            (false, constant) if constant < 0 => format!("{} - {}", out, -constant),
+            (false, constant) => format!("{} + {}", out, constant),
+        }
+    }
+}
+
+fn has_top_level_space(expr: &str) -> bool {
+    let mut depth = 0;
+
+    for c in expr.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            ' ' if depth == 0 => return true,
+            _ => {}
+        }
+    }
+
+    false
+}
+
+#[derive(Clone)]
+pub enum LoopValue {
+    Number(Linear),
+    Index(String, Linear),
+    Tuple(Vec<LoopValue>),
+    Element(Box<LoopValue>, usize),
+}
+
+impl LoopValue {
+    fn substitute_counter(&self, replacement: &Linear) -> LoopValue {
+        match self {
+            LoopValue::Number(linear) => LoopValue::Number(linear.substitute_counter(replacement)),
+            LoopValue::Index(base, linear) => {
+                LoopValue::Index(base.clone(), linear.substitute_counter(replacement))
+            }
+            LoopValue::Tuple(values) => LoopValue::Tuple(
+                values
+                    .iter()
+                    .map(|value| value.substitute_counter(replacement))
+                    .collect(),
+            ),
+            LoopValue::Element(value, index) => {
+                LoopValue::Element(Box::new(value.substitute_counter(replacement)), *index)
+            }
+        }
+    }
+
+    fn has_nested_counter(&self) -> bool {
+        match self {
+            LoopValue::Number(linear) | LoopValue::Index(_, linear) => linear.has_nested_counter(),
+            LoopValue::Tuple(values) => values.iter().any(LoopValue::has_nested_counter),
+            LoopValue::Element(value, _) => value.has_nested_counter(),
+        }
+    }
+
+    pub fn render(&self, counter: &str) -> String {
+        match self {
+            LoopValue::Number(linear) => linear.render(counter),
+            LoopValue::Index(base, linear) => format!("{}[{}]", base, linear.render(counter)),
+            LoopValue::Tuple(values) => {
+                let values: Vec<String> =
+                    values.iter().map(|value| value.render(counter)).collect();
+                format!("{{{}}}", values.join(", "))
+            }
+            LoopValue::Element(value, index) => format!("{}[{}]", value.render(counter), index + 1),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LoopIter {
+    pub start: Linear,
+    pub bound: Linear,
+    pub step: Linear,
+    pub exact: bool,
+    pub value: LoopValue,
+    pub sequence: Option<String>,
+    pub open_end: Option<Linear>,
+}
+
+impl LoopIter {
+    pub fn range(start: Linear, bound: Linear) -> Self {
+        Self {
+            start,
+            bound,
+            step: Linear::constant(1),
+            exact: true,
+            value: LoopValue::Number(Linear::counter()),
+            sequence: None,
+            open_end: None,
+        }
+    }
+
+    pub fn float_range(start: Linear, end: Linear) -> Self {
+        Self {
+            open_end: Some(end),
+            ..Self::range(start, Linear::constant(0))
+        }
+    }
+
+    pub fn sequence(base: &str) -> Self {
+        Self {
+            start: Linear::constant(1),
+            bound: Linear::atom(&format!("#{}", base)),
+            step: Linear::constant(1),
+            exact: true,
+            value: LoopValue::Index(base.to_string(), Linear::counter()),
+            sequence: Some(base.to_string()),
+            open_end: None,
+        }
+    }
+
+    pub fn close(self) -> Self {
+        let Some(end) = &self.open_end else {
+            return self;
+        };
+
+        let count = Linear::atom(&format!(
+            "math.ceil({} / {})",
+            end.sub(&self.start).render_grouped(),
+            self.step.render_grouped()
+        ));
+
+        Self {
+            bound: count.sub(&Linear::constant(1)),
+            value: LoopValue::Number(self.start.add(&self.step.mul(&Linear::counter()))),
+            start: Linear::constant(0),
+            step: Linear::constant(1),
+            exact: true,
+            sequence: None,
+            open_end: None,
+        }
+    }
+
+    pub fn rev(self) -> Self {
+        let iter = self.close();
+
+        let start = if iter.exact {
+            iter.bound.clone()
+        } else {
+            let steps = iter.bound.sub(&iter.start).floor_div(&iter.step);
+            iter.start.add(&iter.step.mul(&steps))
+        };
+
+        Self {
+            start,
+            bound: iter.start.clone(),
+            step: iter.step.scale(-1),
+            exact: true,
+            sequence: None,
+            ..iter
+        }
+    }
+
+    pub fn step_by(self, step: Linear) -> Result<Self, String> {
+        if step.as_constant().is_some_and(|step| step <= 0) {
+            return Err("`step_by()` requires a positive step".to_string());
+        }
+
+        let exact = self.exact && step.as_constant() == Some(1);
+
+        Ok(Self {
+            step: self.step.mul(&step),
+            exact,
+            sequence: None,
+            ..self
+        }
+        .close())
+    }
+
+    pub fn enumerate(self) -> Self {
+        let iter = self.close();
+        let position = iter.position();
+
+        Self {
+            value: LoopValue::Tuple(vec![LoopValue::Number(position), iter.value.clone()]),
+            sequence: None,
+            ..iter
+        }
+    }
+
+    pub fn zip(self, other: LoopIter) -> Self {
+        let iter = self.close();
+        let other = other.close();
+
+        let count = match (iter.count().as_constant(), other.count().as_constant()) {
+            (Some(left), Some(right)) => Linear::constant(left.min(right)),
+            _ => Linear::atom(&format!(
+                "math.min({}, {})",
+                iter.count().render(COUNTER),
+                other.count().render(COUNTER)
+            )),
+        };
+
+        let other_counter = other.start.add(&other.step.mul(&iter.position()));
+        let other_value = other.value.substitute_counter(&other_counter);
+        let last = count.sub(&Linear::constant(1));
+
+        Self {
+            bound: iter.start.add(&iter.step.mul(&last)),
+            value: LoopValue::Tuple(vec![iter.value.clone(), other_value]),
+            exact: true,
+            sequence: None,
+            ..iter
+        }
+    }
+
+    fn position(&self) -> Linear {
+        Linear::counter().sub(&self.start).floor_div(&self.step)
+    }
+
+    pub fn count(&self) -> Linear {
+        self.bound
+            .sub(&self.start)
+            .floor_div(&self.step)
+            .add(&Linear::constant(1))
+    }
+
+    pub fn rebase_onto(&self, value: &Linear) -> Option<LoopIter> {
+        let coefficient = value.counter_coefficient();
+        if coefficient.abs() != 1 || value.has_nested_counter() || self.value.has_nested_counter() {
+            return None;
+        }
+
+        let offset = value.without_counter();
+        let old_counter = Linear::counter().sub(&offset).scale(coefficient);
+
+        Some(Self {
+            start: value.substitute_counter(&self.start),
+            bound: value.substitute_counter(&self.bound),
+            step: self.step.scale(coefficient),
+            value: self.value.substitute_counter(&old_counter),
+            sequence: None,
+            exact: self.exact,
+            open_end: None,
+        })
+    }
+}
@@ -0,0 +1,338 @@
+pub mod cfg;
+mod comments;
+mod crates;
+pub mod diagnostics;
+mod errors;
+mod format;
+mod format_string;
+mod header;
+mod impl_trait;
+mod imports;
+mod indent_manager;
+mod inline;
+mod iterators;
+mod logging;
+mod moonwave;
+mod name_gen;
+mod normalize;
+pub mod options;
+mod rename;
+pub mod report;
+mod require_path;
+pub mod roblox_api;
+mod runtime;
+mod scope;
+mod statics;
+mod transpiler;
+mod type_registry;
+
+use crate::cfg::CfgSet;
+use crate::diagnostics::{Diagnostic, MessageFormat};
+use crate::indent_manager::IndentManager;
+use crate::options::{
+    Formatter, IntSemantics, Optimization, RequireStyle, ScriptKind, Target, TestFramework,
+    TraitDispatch, TranspilerOptions, Unsupported,
+};
+use crate::transpiler::LuauTranspiler;
+use clap::ValueEnum;
+use std::path::Path;
+
+pub struct Transpiled {
+    pub output: String,
+    pub kind: Option<ScriptKind>,
+    pub messages: Vec<String>,
+    pub diagnostics: Vec<Diagnostic>,
+    /// Whether the file is an `#![actor]` script, which is written into a folder that Rojo
+    /// turns into an Actor with [`ACTOR_META`] as its `init.meta.json`
+    pub actor: bool,
+    /// The Rust line each line of `output` was lowered from, if any; empty if it was
+    /// formatted, which moves lines around
+    pub source_map: Vec<Option<usize>>,
+}
+
+pub use crate::rename::LUAU_KEYWORDS;
+pub use crate::transpiler::UnsupportedConstruct;
+
+pub const ACTOR_META: &str = "{\n    \"className\": \"Actor\"\n}\n";
+
+/// Transpiles an already parsed file; `file_contents` is its source, for comments and spans.
+/// Fails with the first construct that can't be lowered.
+pub fn transpile_tree(
+    file: &str,
+    file_contents: &str,
+    mut syntax_tree: syn::File,
+    mut options: TranspilerOptions,
+) -> Result<Transpiled, UnsupportedConstruct> {
+    if options.tests.is_some() {
+        options.cfg.insert("test");
+    }
+
+    cfg::check_predicates(&syntax_tree)?;
+    statics::desugar_lazy_statics(&mut syntax_tree)?;
+    let thread_locals = statics::desugar_thread_locals(&mut syntax_tree)?;
+    normalize::normalize(&mut syntax_tree);
+    let mut crate_imports = crates::resolve_crate_paths(&mut syntax_tree, &options.crates)?;
+    let re_exports =
+        imports::resolve_imports(&mut syntax_tree, &options.crates, &mut crate_imports);
+    let renames = rename::rename_reserved(&mut syntax_tree);
+    impl_trait::desugar_impl_params(&mut syntax_tree);
+    errors::desugar_error_macros(&mut syntax_tree)?;
+
+    let header = options
+        .header
+        .then(|| header::header(file, file_contents, options.timestamp));
+
+    // StyLua can't parse `declare` statements, so declaration files are left as emitted
+    let formatter = options.format.filter(|_| !options.declarations);
+    let message_format = options.message_format;
+    let mut indent_manager = IndentManager::new("    ");
+    let mut transpiler = LuauTranspiler::new(&mut indent_manager, file_contents, options);
+    transpiler.import_crates(crate_imports);
+    transpiler.re_export(re_exports);
+    transpiler.export_renamed(renames.exports);
+
+    transpiler.visit_file(&syntax_tree)?;
+
+    let mut diagnostics = renames.diagnostics;
+    diagnostics.extend(thread_locals);
+    diagnostics.extend(transpiler.take_diagnostics());
+    let mut messages: Vec<String> = diagnostics
+        .iter()
+        .map(|diagnostic| match message_format {
+            MessageFormat::Human => diagnostic.render(file),
+            MessageFormat::Json => diagnostic.to_json(file),
+        })
+        .collect();
+
+    let fallbacks = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.code == "any")
+        .count();
+    if fallbacks > 0 {
+        let types = if fallbacks == 1 { "type" } else { "types" };
+        messages.push(diagnostics::file_note(
+            "any",
+            &format!("{} {} fell back to `any`", fallbacks, types),
+            file,
+            message_format,
+        ));
+    }
+
+    let kind = transpiler.kind();
+    let actor = transpiler.is_actor();
+    let (mut output, mut source_map) = transpiler.render();
+
+    // Formatting moves lines around, so a formatted file has no source map
+    if formatter == Some(Formatter::Stylua) {
+        match format::stylua(&output) {
+            Ok(formatted) => {
+                output = formatted;
+                source_map.clear();
+            }
+            Err(error) => messages.push(format!(
+                "warning[format]: StyLua failed, leaving output unformatted: {}\n  --> {}",
+                error, file
+            )),
+        }
+    }
+
+    if let Some(header) = header {
+        output.insert_str(0, &header);
+        if !source_map.is_empty() {
+            source_map.splice(0..0, header.lines().map(|_| None));
+        }
+    }
+
+    Ok(Transpiled {
+        output,
+        kind,
+        messages,
+        diagnostics,
+        actor,
+        source_map,
+    })
+}
+
+/// Transpiles `source`, reporting a parse error or a construct the transpiler can't lower
+/// as an `Err` with its location.
+pub fn transpile_source(
+    file: &str,
+    source: &str,
+    options: TranspilerOptions,
+) -> Result<Transpiled, String> {
+    let syntax_tree = syn::parse_file(source).map_err(|error| {
+        let start = error.span().start();
+        format!("{}:{}:{}: {}", file, start.line, start.column + 1, error)
+    })?;
+
+    transpile_tree(file, source, syntax_tree, options).map_err(
+        |UnsupportedConstruct(diagnostic)| {
+            format!(
+                "{}:{}:{}: {}",
+                file, diagnostic.line, diagnostic.column, diagnostic.message
+            )
+        },
+    )
+}
+
+/// The entry point for the web playground: transpiles `source` with the options in
+/// `options_json` (named like the CLI flags, e.g. `{ "kind": "module", "opt_level": 1 }`) and
+/// returns `{ "ok": true, "output": ..., "kind": ..., "diagnostics": [...] }` with cargo-style
+/// diagnostics, or `{ "ok": false, "error": ... }`.
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen::prelude::wasm_bindgen)]
+pub fn transpile(source: &str, options_json: &str) -> String {
+    let result = options_from_json(options_json)
+        .and_then(|(file, options)| transpile_source(&file, source, options));
+
+    let json = match result {
+        Ok(transpiled) => {
+            let diagnostics: Vec<serde_json::Value> = transpiled
+                .messages
+                .into_iter()
+                .map(|message| {
+                    serde_json::from_str(&message).unwrap_or(serde_json::Value::String(message))
+                })
+                .collect();
+
+            serde_json::json!({
+                "ok": true,
+                "output": transpiled.output,
+                "kind": transpiled.kind.and_then(|kind| {
+                    kind.to_possible_value().map(|value| value.get_name().to_string())
+                }),
+                "diagnostics": diagnostics,
+            })
+        }
+
+        Err(error) => serde_json::json!({ "ok": false, "error": error }),
+    };
+
+    json.to_string()
+}
+
+/// The file name for diagnostics and the options in a playground options object. Roblox API
+/// bindings can't be loaded without a file system, so there is no `bindings` option.
+fn options_from_json(options_json: &str) -> Result<(String, TranspilerOptions), String> {
+    let json: serde_json::Value = if options_json.trim().is_empty() {
+        serde_json::json!({})
+    } else {
+        serde_json::from_str(options_json).map_err(|error| format!("Invalid options: {}", error))?
+    };
+    let object = json
+        .as_object()
+        .ok_or_else(|| "Options must be a JSON object".to_string())?;
+
+    let mut file = "main.rs".to_string();
+    let mut options = TranspilerOptions {
+        message_format: MessageFormat::Json,
+        ..Default::default()
+    };
+
+    for (key, value) in object {
+        let flag = || match value.as_bool() {
+            Some(flag) => Ok(flag),
+            None => Err(format!("Option `{}` must be a boolean", key)),
+        };
+        let text = || match value.as_str() {
+            Some(text) => Ok(text.to_string()),
+            None => Err(format!("Option `{}` must be a string", key)),
+        };
+
+        match key.as_str() {
+            "file" => file = text()?,
+            "cfg" => {
+                let specs: Option<Vec<String>> = value.as_array().and_then(|specs| {
+                    specs
+                        .iter()
+                        .map(|spec| spec.as_str().map(str::to_string))
+                        .collect()
+                });
+                let specs = specs.ok_or("Option `cfg` must be an array of strings")?;
+                options.cfg = CfgSet::from_specs(&specs);
+            }
+            "strip_comments" => options.strip_comments = flag()?,
+            "moonwave" => options.moonwave = flag()?,
+            "annotate_const" => options.annotate_const = flag()?,
+            "profile" => options.profile = flag()?,
+            "header" => options.header = flag()?,
+            "regions" => options.regions = flag()?,
+            "timestamp" => {
+                options.timestamp = Some(
+                    value
+                        .as_u64()
+                        .ok_or("Option `timestamp` must be a number of seconds")?,
+                )
+            }
+            "monomorphize" => options.monomorphize = flag()?,
+            "script" => options.script = flag()?,
+            "call_main" => options.call_main = flag()?,
+            "roblox_ts" => options.roblox_ts = flag()?,
+            "declarations" => options.declarations = flag()?,
+            "kind" => options.kind = Some(value_enum::<ScriptKind>(key, &text()?)?),
+            "target" => options.target = value_enum::<Target>(key, &text()?)?,
+            "require_style" => {
+                options.require_style = Some(value_enum::<RequireStyle>(key, &text()?)?)
+            }
+            "int_semantics" => options.int_semantics = value_enum::<IntSemantics>(key, &text()?)?,
+            "trait_dispatch" => {
+                options.trait_dispatch = value_enum::<TraitDispatch>(key, &text()?)?
+            }
+            "format" => options.format = Some(value_enum::<Formatter>(key, &text()?)?),
+            "opt_level" => {
+                options.opt_level = value
+                    .as_u64()
+                    .filter(|level| *level <= 2)
+                    .ok_or("Option `opt_level` must be 0, 1 or 2")?
+                    as u8
+            }
+            "opt" => {
+                let names: Option<Vec<&str>> = value
+                    .as_array()
+                    .and_then(|names| names.iter().map(|name| name.as_str()).collect());
+                let names = names.ok_or("Option `opt` must be an array of strings")?;
+                options.optimizations = names
+                    .into_iter()
+                    .map(|name| value_enum::<Optimization>(key, name))
+                    .collect::<Result<_, _>>()?;
+            }
+            "lenient" => options.unsupported = value_enum::<Unsupported>(key, &text()?)?,
+            "react" => options.react = Some(text()?),
+            "logger" => options.logger = Some(text()?),
+            "tests" => options.tests = Some(value_enum::<TestFramework>(key, &text()?)?),
+            "env_args" => options.env_args = Some(text()?),
+            _ => return Err(format!("Unknown option `{}`", key)),
+        }
+    }
+
+    options.module_name = module_name(&file);
+    Ok((file, options))
+}
+
+fn value_enum<T: ValueEnum>(key: &str, name: &str) -> Result<T, String> {
+    T::from_str(name, true).map_err(|_| format!("Invalid value `{}` for option `{}`", name, key))
+}
+
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "transpilation panicked".to_string())
+}
+
+pub fn module_name(path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    stem.split(['_', '-'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars.next().map_or(String::new(), |first| {
+                first.to_uppercase().chain(chars).collect()
+            })
+        })
+        .collect()
+}
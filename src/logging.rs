@@ -0,0 +1,143 @@
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+
+const LEVELS: &[&str] = &["trace", "debug", "info", "warn", "error"];
+
+/// The level of a `log` or `tracing` macro like `info!` or `log::warn!`, which are lowered
+/// alike.
+pub fn log_level(mac: &syn::Macro) -> Option<&'static str> {
+    let segments = &mac.path.segments;
+    let name = segments.last()?.ident.to_string();
+    let is_logger = segments.len() == 1
+        || (segments.len() == 2
+            && matches!(segments[0].ident.to_string().as_str(), "log" | "tracing"));
+
+    LEVELS
+        .iter()
+        .copied()
+        .find(|level| is_logger && *level == name)
+}
+
+/// How a structured field is formatted: `?value` with `Debug`, `%value` with `Display`, and
+/// anything else like tracing's recorded values, which `Debug` matches.
+#[derive(Clone, Copy, PartialEq)]
+pub enum FieldFormat {
+    Debug,
+    Display,
+}
+
+pub struct LogField {
+    pub name: String,
+    pub format: FieldFormat,
+    pub value: syn::Expr,
+}
+
+pub struct LogArgs {
+    pub fields: Vec<LogField>,
+    /// The format string and its arguments, which a tracing event may leave out
+    pub message: Vec<syn::Expr>,
+}
+
+/// Parses `log`'s `target: "net", "sent {}", n` as well as tracing's
+/// `user = ?id, %addr, count, "sent {}", n`; a `target:`, `parent:` or `name:` is dropped.
+pub fn parse_log_args(mac: &syn::Macro) -> LogArgs {
+    let parser = |input: ParseStream| {
+        let mut fields = Vec::new();
+
+        while !input.is_empty() && !input.peek(syn::LitStr) {
+            if input.peek(syn::Ident)
+                && input.peek2(syn::Token![:])
+                && !input.peek2(syn::Token![::])
+            {
+                input.parse::<syn::Ident>()?;
+                input.parse::<syn::Token![:]>()?;
+                input.parse::<syn::Expr>()?;
+            } else {
+                fields.push(parse_field(input)?);
+            }
+
+            if !input.is_empty() {
+                input.parse::<syn::Token![,]>()?;
+            }
+        }
+
+        let message = Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated(input)?;
+        Ok(LogArgs {
+            fields,
+            message: message.into_iter().collect(),
+        })
+    };
+
+    parser.parse2(mac.tokens.clone()).unwrap_or_else(|error| {
+        panic!(
+            "Failed to parse `{}!` arguments: {}",
+            mac.path.segments.last().unwrap().ident,
+            error
+        )
+    })
+}
+
+fn parse_field(input: ParseStream) -> syn::Result<LogField> {
+    if let Some(format) = parse_sigil(input)? {
+        let value: syn::Expr = input.parse()?;
+        let name = field_name(&value).ok_or_else(|| {
+            input.error("A field without a name must be a variable or a field, like `?user.id`")
+        })?;
+        return Ok(LogField {
+            name,
+            format,
+            value,
+        });
+    }
+
+    let mut name = vec![input.parse::<syn::Ident>()?];
+    while input.peek(syn::Token![.]) {
+        input.parse::<syn::Token![.]>()?;
+        name.push(input.parse()?);
+    }
+    let dotted = name
+        .iter()
+        .map(|ident| ident.to_string())
+        .collect::<Vec<_>>()
+        .join(".");
+
+    if !input.peek(syn::Token![=]) {
+        return Ok(LogField {
+            name: dotted,
+            format: FieldFormat::Debug,
+            value: syn::parse_quote!(#(#name).*),
+        });
+    }
+
+    input.parse::<syn::Token![=]>()?;
+    let format = parse_sigil(input)?.unwrap_or(FieldFormat::Debug);
+    Ok(LogField {
+        name: dotted,
+        format,
+        value: input.parse()?,
+    })
+}
+
+fn parse_sigil(input: ParseStream) -> syn::Result<Option<FieldFormat>> {
+    if input.peek(syn::Token![?]) {
+        input.parse::<syn::Token![?]>()?;
+        Ok(Some(FieldFormat::Debug))
+    } else if input.peek(syn::Token![%]) {
+        input.parse::<syn::Token![%]>()?;
+        Ok(Some(FieldFormat::Display))
+    } else {
+        Ok(None)
+    }
+}
+
+/// The name tracing gives `?user.id`, the place as written.
+fn field_name(value: &syn::Expr) -> Option<String> {
+    match value {
+        syn::Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+        syn::Expr::Field(field) => match &field.member {
+            syn::Member::Named(ident) => Some(format!("{}.{}", field_name(&field.base)?, ident)),
+            syn::Member::Unnamed(_) => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,476 @@
+use crate::cli::LspArgs;
+use roblox_rs::{Transpiled, UnsupportedConstruct};
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+// JSON-RPC error code for requests the server doesn't implement
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A language server on stdin/stdout that reports transpile diagnostics when a file is opened
+/// or saved, shows the generated Luau for the item under the cursor on hover (and through the
+/// `roblox-rs/generatedLuau` request), and jumps to it with go-to-definition.
+pub fn run(args: &LspArgs) {
+    let mut server = Server {
+        args,
+        documents: HashMap::new(),
+    };
+
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    while let Some(message) = read_message(&mut input) {
+        let method = message["method"].as_str().unwrap_or_default();
+        let params = &message["params"];
+
+        let result = match method {
+            "initialize" => Some(capabilities()),
+            "shutdown" => Some(Value::Null),
+            "exit" => return,
+
+            "textDocument/didOpen" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                server.documents.insert(uri.to_string(), text.to_string());
+                server.publish_diagnostics(uri);
+                None
+            }
+
+            "textDocument/didChange" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                let changes = params["contentChanges"].as_array();
+                if let Some(text) = changes
+                    .and_then(|changes| changes.last())
+                    .and_then(|change| change["text"].as_str())
+                {
+                    server.documents.insert(uri.to_string(), text.to_string());
+                }
+                None
+            }
+
+            "textDocument/didSave" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                if let Some(text) = params["text"].as_str() {
+                    server.documents.insert(uri.to_string(), text.to_string());
+                }
+                server.publish_diagnostics(uri);
+                None
+            }
+
+            "textDocument/didClose" => {
+                let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+                server.documents.remove(uri);
+                notify(
+                    "textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": [] }),
+                );
+                None
+            }
+
+            "textDocument/hover" => Some(server.hover(params)),
+            "textDocument/definition" => Some(server.definition(params)),
+            "roblox-rs/generatedLuau" => Some(server.generated_luau(params)),
+
+            _ => {
+                if let Some(id) = message.get("id") {
+                    send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("Unsupported method `{}`", method),
+                        },
+                    }));
+                }
+                None
+            }
+        };
+
+        if let (Some(result), Some(id)) = (result, message.get("id")) {
+            send(json!({ "jsonrpc": "2.0", "id": id, "result": result }));
+        }
+    }
+}
+
+fn capabilities() -> Value {
+    json!({
+        "capabilities": {
+            "textDocumentSync": {
+                "openClose": true,
+                "change": 1,
+                "save": { "includeText": true },
+            },
+            "hoverProvider": true,
+            "definitionProvider": true,
+        },
+        "serverInfo": { "name": "roblox-rs", "version": env!("CARGO_PKG_VERSION") },
+    })
+}
+
+struct Server<'a> {
+    args: &'a LspArgs,
+    documents: HashMap<String, String>,
+}
+
+impl Server<'_> {
+    /// Transpiles a document that parses, failing with the construct it can't lower.
+    fn transpile(&self, uri: &str) -> Option<Result<Transpiled, UnsupportedConstruct>> {
+        let text = self.documents.get(uri)?;
+        let syntax_tree = syn::parse_file(text).ok()?;
+        let path = uri_to_path(uri);
+        let file = path.to_string_lossy();
+
+        let mut options = crate::transpile_options(&self.args.transpile, &file);
+        options.message_format = roblox_rs::diagnostics::MessageFormat::Json;
+        Some(roblox_rs::transpile_tree(&file, text, syntax_tree, options))
+    }
+
+    fn publish_diagnostics(&self, uri: &str) {
+        let Some(text) = self.documents.get(uri) else {
+            return;
+        };
+
+        let diagnostics = match syn::parse_file(text) {
+            Err(error) => {
+                let start = error.span().start();
+                let end = error.span().end();
+                vec![json!({
+                    "range": range(start.line, start.column, end.line, end.column),
+                    "severity": 1,
+                    "source": "roblox-rs",
+                    "message": error.to_string(),
+                })]
+            }
+
+            Ok(_) => match self.transpile(uri) {
+                Some(Ok(transpiled)) => transpiled
+                    .messages
+                    .iter()
+                    .map(|message| lsp_diagnostic(message))
+                    .collect(),
+                Some(Err(UnsupportedConstruct(diagnostic))) => vec![json!({
+                    "range": range(
+                        diagnostic.line,
+                        diagnostic.column - 1,
+                        diagnostic.end_line,
+                        diagnostic.end_column - 1,
+                    ),
+                    "severity": 1,
+                    "code": diagnostic.code,
+                    "source": "roblox-rs",
+                    "message": diagnostic.message,
+                })],
+                None => Vec::new(),
+            },
+        };
+
+        notify(
+            "textDocument/publishDiagnostics",
+            json!({ "uri": uri, "diagnostics": diagnostics }),
+        );
+    }
+
+    /// The output and the item under the cursor's lines in it.
+    fn generated_item(&self, params: &Value) -> Option<(String, std::ops::Range<usize>)> {
+        let uri = params["textDocument"]["uri"].as_str()?;
+        let line = params["position"]["line"].as_u64()? as usize + 1;
+        let column = params["position"]["character"].as_u64()? as usize;
+
+        let syntax_tree = syn::parse_file(self.documents.get(uri)?).ok()?;
+        let item = item_at(&syntax_tree.items, None, line, column)?;
+        let output = self.transpile(uri)?.ok()?.output;
+        let lines = generated_lines(&output, &item)?;
+
+        Some((output, lines))
+    }
+
+    fn hover(&self, params: &Value) -> Value {
+        let Some((output, lines)) = self.generated_item(params) else {
+            return Value::Null;
+        };
+
+        let luau: Vec<&str> = output.lines().skip(lines.start).take(lines.len()).collect();
+        json!({
+            "contents": {
+                "kind": "markdown",
+                "value": format!("```luau\n{}\n```", luau.join("\n")),
+            },
+        })
+    }
+
+    /// Writes the output to a temporary file and points at the item in it. The file is named
+    /// after a hash of the document's path too, so documents with the same name don't
+    /// overwrite each other's output.
+    fn definition(&self, params: &Value) -> Value {
+        let Some((output, lines)) = self.generated_item(params) else {
+            return Value::Null;
+        };
+        let Some(uri) = params["textDocument"]["uri"].as_str() else {
+            return Value::Null;
+        };
+
+        let source = uri_to_path(uri);
+        let stem = source
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut hasher = DefaultHasher::new();
+        source.hash(&mut hasher);
+        let dir = std::env::temp_dir().join("roblox-rs-lsp");
+        let path = dir.join(format!("{}-{:016x}.luau", stem, hasher.finish()));
+        if std::fs::create_dir_all(&dir).is_err() || std::fs::write(&path, &output).is_err() {
+            return Value::Null;
+        }
+
+        let last = output.lines().nth(lines.end - 1).map_or(0, str::len);
+        json!({
+            "uri": format!("file://{}", path.display()),
+            "range": range(lines.start + 1, 0, lines.end, last),
+        })
+    }
+
+    /// `{ "luau": ... }` with the generated Luau for the item at `position`, or for the whole
+    /// file if no position is given.
+    fn generated_luau(&self, params: &Value) -> Value {
+        if params.get("position").is_some() {
+            return match self.generated_item(params) {
+                Some((output, lines)) => {
+                    let luau: Vec<&str> =
+                        output.lines().skip(lines.start).take(lines.len()).collect();
+                    json!({ "luau": luau.join("\n") })
+                }
+                None => json!({ "luau": null }),
+            };
+        }
+
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+        match self.transpile(uri) {
+            Some(Ok(transpiled)) => json!({ "luau": transpiled.output }),
+            _ => json!({ "luau": null }),
+        }
+    }
+}
+
+/// A top-level item, a method of an `impl` on `owner`, or an item in an inline module.
+struct ItemName {
+    owner: Option<String>,
+    name: String,
+    is_type: bool,
+}
+
+fn item_at(
+    items: &[syn::Item],
+    owner: Option<&str>,
+    line: usize,
+    column: usize,
+) -> Option<ItemName> {
+    use syn::spanned::Spanned;
+
+    let contains = |span: proc_macro2::Span| {
+        let (start, end) = (span.start(), span.end());
+        (start.line, start.column) <= (line, column) && (line, column) <= (end.line, end.column)
+    };
+
+    let item = items.iter().find(|item| contains(item.span()))?;
+    let owner = owner.map(str::to_string);
+
+    match item {
+        syn::Item::Fn(item_fn) => Some(ItemName {
+            owner,
+            name: item_fn.sig.ident.to_string(),
+            is_type: false,
+        }),
+
+        syn::Item::Struct(item_struct) => Some(ItemName {
+            owner,
+            name: item_struct.ident.to_string(),
+            is_type: true,
+        }),
+
+        syn::Item::Enum(item_enum) => Some(ItemName {
+            owner,
+            name: item_enum.ident.to_string(),
+            is_type: true,
+        }),
+
+        syn::Item::Type(item_type) => Some(ItemName {
+            owner,
+            name: item_type.ident.to_string(),
+            is_type: true,
+        }),
+
+        syn::Item::Impl(item_impl) => {
+            let syn::Type::Path(self_type) = &*item_impl.self_ty else {
+                return None;
+            };
+            let type_name = self_type.path.segments.last()?.ident.to_string();
+
+            item_impl
+                .items
+                .iter()
+                .find_map(|impl_item| match impl_item {
+                    syn::ImplItem::Fn(method) if contains(method.span()) => Some(ItemName {
+                        owner: Some(type_name.clone()),
+                        name: method.sig.ident.to_string(),
+                        is_type: false,
+                    }),
+                    _ => None,
+                })
+        }
+
+        syn::Item::Mod(item_mod) => {
+            let (_, items) = item_mod.content.as_ref()?;
+            item_at(items, Some(&item_mod.ident.to_string()), line, column)
+        }
+
+        _ => None,
+    }
+}
+
+/// The 0-based lines of `output` that declare `item`: a function through its `end`, or a
+/// type alias through its closing brace.
+fn generated_lines(output: &str, item: &ItemName) -> Option<std::ops::Range<usize>> {
+    let lines: Vec<&str> = output.lines().collect();
+
+    let start = lines.iter().position(|line| {
+        let line = line.trim_start();
+        if item.is_type {
+            let declaration = line.strip_prefix("export ").unwrap_or(line);
+            return declaration
+                .strip_prefix("type ")
+                .and_then(|rest| rest.strip_prefix(item.name.as_str()))
+                .is_some_and(|rest| rest.starts_with([' ', '<']));
+        }
+
+        let declaration = line.strip_prefix("local ").unwrap_or(line);
+        let Some(path) = declaration
+            .strip_prefix("function ")
+            .and_then(|rest| rest.split('(').next())
+        else {
+            return false;
+        };
+
+        match &item.owner {
+            Some(owner) => {
+                path == format!("{}.{}", owner, item.name)
+                    || path == format!("{}:{}", owner, item.name)
+            }
+            None => path == item.name,
+        }
+    })?;
+
+    let indent = lines[start].len() - lines[start].trim_start().len();
+    let closing = if item.is_type { "}" } else { "end" };
+    if item.is_type && !lines[start].trim_end().ends_with('{') {
+        return Some(start..start + 1);
+    }
+
+    let end = lines[start + 1..]
+        .iter()
+        .position(|line| {
+            line.len() - line.trim_start().len() == indent && line.trim_start().starts_with(closing)
+        })
+        .map_or(lines.len(), |offset| start + 1 + offset + 1);
+
+    Some(start..end)
+}
+
+/// Converts a cargo-style JSON diagnostic into an LSP one.
+fn lsp_diagnostic(message: &str) -> Value {
+    let Ok(json) = serde_json::from_str::<Value>(message) else {
+        return json!({
+            "range": range(1, 0, 1, 0),
+            "severity": 2,
+            "source": "roblox-rs",
+            "message": message,
+        });
+    };
+
+    let message = &json["message"];
+    let span = &message["spans"][0];
+    let position = |key: &str| span[key].as_u64().unwrap_or(1) as usize;
+    let severity = match message["level"].as_str() {
+        Some("error") => 1,
+        Some("warning") => 2,
+        _ => 3,
+    };
+
+    json!({
+        "range": range(
+            position("line_start"),
+            position("column_start") - 1,
+            position("line_end"),
+            position("column_end") - 1,
+        ),
+        "severity": severity,
+        "code": message["code"]["code"],
+        "source": "roblox-rs",
+        "message": message["message"],
+    })
+}
+
+/// An LSP range from 1-based lines and 0-based columns.
+fn range(start_line: usize, start_column: usize, end_line: usize, end_column: usize) -> Value {
+    json!({
+        "start": { "line": start_line.saturating_sub(1), "character": start_column },
+        "end": { "line": end_line.saturating_sub(1), "character": end_column },
+    })
+}
+
+fn uri_to_path(uri: &str) -> PathBuf {
+    let path = uri.strip_prefix("file://").unwrap_or(uri);
+
+    let mut bytes = Vec::new();
+    let mut chars = path.bytes();
+    while let Some(byte) = chars.next() {
+        if byte == b'%' {
+            let hex: Vec<u8> = chars.by_ref().take(2).collect();
+            if let Some(decoded) = std::str::from_utf8(&hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                bytes.push(decoded);
+                continue;
+            }
+        }
+        bytes.push(byte);
+    }
+
+    Path::new(&String::from_utf8_lossy(&bytes).to_string()).to_path_buf()
+}
+
+fn read_message(input: &mut impl BufRead) -> Option<Value> {
+    let mut length = None;
+    loop {
+        let mut header = String::new();
+        if input.read_line(&mut header).ok()? == 0 {
+            return None;
+        }
+
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length:") {
+            length = value.trim().parse().ok();
+        }
+    }
+
+    let mut body = vec![0; length?];
+    input.read_exact(&mut body).ok()?;
+    serde_json::from_slice(&body).ok()
+}
+
+fn notify(method: &str, params: Value) {
+    send(json!({ "jsonrpc": "2.0", "method": method, "params": params }));
+}
+
+fn send(message: Value) {
+    let body = message.to_string();
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body)
+        .and_then(|_| stdout.flush())
+        .expect("Failed to write to stdout");
+}
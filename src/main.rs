@@ -1,25 +1,322 @@
+mod bench;
+mod bindgen;
+mod cache;
 mod cli;
-mod indent_manager;
-mod transpiler;
+mod fuzz;
+mod lsp;
+mod merge;
+mod package;
+mod project;
+mod run;
+mod typecheck;
 
-use crate::cli::Cli;
-use crate::indent_manager::IndentManager;
-use crate::transpiler::LuauTranspiler;
-use clap::Parser;
+use crate::cli::{Cli, Command, Emit, TranspileArgs};
+use clap::{CommandFactory, FromArgMatches};
+use roblox_rs::cfg::CfgSet;
+use roblox_rs::diagnostics::{self, MessageFormat};
+use roblox_rs::options::TranspilerOptions;
+use roblox_rs::report::{self, FileReport};
+use roblox_rs::roblox_api::RobloxApi;
+use roblox_rs::{module_name, transpile_source, Transpiled};
+use std::path::Path;
+use std::sync::Arc;
 use syn::parse_file;
-use syn::visit::Visit;
 
 fn main() {
-    let cli = Cli::parse();
-
-    let file_contents = std::fs::read_to_string(&cli.file).expect("Failed to read file");
-    let syntax_tree = parse_file(&file_contents).expect("Failed to parse file contents");
-
-    
-    let mut indent_manager = IndentManager::new("    ");
-    let mut transpiler = LuauTranspiler::new(&mut indent_manager);
-    transpiler.visit_file(&syntax_tree);
-    
-    // println!("{:#?}", syntax_tree);
-    println!("{}", transpiler.render());
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
+
+    let message_format = match &cli.command {
+        Some(Command::Package(args)) => args.transpile.message_format,
+        Some(Command::Bench(args)) => args.transpile.message_format,
+        Some(Command::Run(args)) => args.transpile.message_format,
+        Some(Command::Fuzz(args)) => args.transpile.message_format,
+        Some(Command::Lsp(_)) => MessageFormat::Human,
+        Some(Command::Build(args) | Command::Check(args) | Command::Watch(args)) => {
+            args.transpile.message_format
+        }
+        Some(Command::Bindgen(_)) => MessageFormat::Human,
+        None => cli.transpile.message_format,
+    };
+
+    if message_format == MessageFormat::Json {
+        std::panic::set_hook(Box::new(|info| {
+            let message = roblox_rs::panic_message(info.payload());
+            eprintln!("{}", diagnostics::error_json(&message));
+        }));
+    }
+
+    match &cli.command {
+        Some(Command::Package(args)) => package::run(args),
+        Some(Command::Bench(args)) => bench::run(args),
+        Some(Command::Run(args)) => run::run(args),
+        Some(Command::Fuzz(args)) => fuzz::run(args),
+        Some(Command::Lsp(args)) => lsp::run(args),
+        Some(Command::Build(args)) => project::build(args, subcommand_matches(&matches)),
+        Some(Command::Check(args)) => project::check(args, subcommand_matches(&matches)),
+        Some(Command::Watch(args)) => project::watch(args, subcommand_matches(&matches)),
+        Some(Command::Bindgen(args)) => bindgen::run(args),
+
+        None => {
+            let file = cli.file.as_deref().expect("An input file is required");
+
+            match cli.emit {
+                Emit::Ast => {
+                    let file_contents = std::fs::read_to_string(file).expect("Failed to read file");
+                    let syntax_tree =
+                        parse_file(&file_contents).expect("Failed to parse file contents");
+
+                    println!("{:#?}", syntax_tree);
+                    return;
+                }
+
+                Emit::Luau => {}
+            }
+
+            let mut options = transpile_options(&cli.transpile, file);
+            options.declarations = cli.declarations;
+            options.roblox_api = cli.transpile.bindings.as_deref().map(roblox_api_for);
+
+            if let Some(path) = &cli.report {
+                let source = std::fs::read_to_string(file).expect("Failed to read file");
+                write_report(path, &[report::report(file, &source, options.clone())]);
+            }
+
+            let spec_options = cli.emit_tests.map(|framework| TranspilerOptions {
+                tests: Some(framework),
+                declarations: false,
+                ..options.clone()
+            });
+
+            let verify_options = cli.verify_deterministic.then(|| options.clone());
+            let transpiled = transpile_file(file, options);
+            for message in &transpiled.messages {
+                eprintln!("{}", message);
+            }
+
+            if let Some(command) = &cli.typecheck {
+                let (messages, failed) =
+                    typecheck::typecheck(command, file, &transpiled, message_format);
+                for message in &messages {
+                    eprintln!("{}", message);
+                }
+                if failed {
+                    std::process::exit(1);
+                }
+            }
+
+            if let Some(options) = verify_options {
+                let source = std::fs::read_to_string(file).expect("Failed to read file");
+                if let Some(difference) = nondeterminism(file, &source, options, &transpiled) {
+                    match message_format {
+                        MessageFormat::Human => eprintln!("error: {}", difference),
+                        MessageFormat::Json => {
+                            eprintln!("{}", diagnostics::error_json(&difference))
+                        }
+                    }
+                    std::process::exit(1);
+                }
+            }
+
+            match &cli.out_dir {
+                Some(out_dir) => {
+                    let stem = Path::new(file)
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let suffix = if cli.declarations {
+                        ".d.luau"
+                    } else {
+                        transpiled.kind.map_or(".luau", |kind| kind.suffix())
+                    };
+                    let mut dir = Path::new(out_dir).to_path_buf();
+                    if transpiled.actor {
+                        dir.push(&stem);
+                    }
+                    let path = dir.join(format!("{}{}", stem, suffix));
+
+                    std::fs::create_dir_all(&dir).expect("Failed to create output directory");
+                    if transpiled.actor {
+                        std::fs::write(dir.join("init.meta.json"), roblox_rs::ACTOR_META)
+                            .expect("Failed to write output file");
+                    }
+                    if cli.merge {
+                        let base_dir = merge::base_dir(Path::new(""));
+                        match merge::write(&path, &base_dir, &transpiled.output) {
+                            Ok(true) => eprintln!(
+                                "{}",
+                                diagnostics::file_note(
+                                    "merge",
+                                    merge::MERGED,
+                                    &path.to_string_lossy(),
+                                    message_format
+                                )
+                            ),
+                            Ok(false) => {}
+                            Err(error) => {
+                                match message_format {
+                                    MessageFormat::Human => eprintln!("error: {}", error),
+                                    MessageFormat::Json => {
+                                        eprintln!("{}", diagnostics::error_json(&error))
+                                    }
+                                }
+                                std::process::exit(1);
+                            }
+                        }
+                    } else {
+                        std::fs::write(&path, transpiled.output)
+                            .expect("Failed to write output file");
+                    }
+
+                    // The spec is the file again with `cfg(test)`, so only new messages are shown
+                    if let Some(spec_options) = spec_options {
+                        let spec = transpile_file(file, spec_options);
+                        for message in &spec.messages {
+                            if !transpiled.messages.contains(message) {
+                                eprintln!("{}", message);
+                            }
+                        }
+
+                        let path = Path::new(out_dir).join(format!("{}.spec.luau", stem));
+                        std::fs::write(&path, spec.output).expect("Failed to write output file");
+                    }
+                }
+
+                None => println!("{}", transpiled.output),
+            }
+        }
+    }
+}
+
+fn subcommand_matches(matches: &clap::ArgMatches) -> &clap::ArgMatches {
+    matches
+        .subcommand()
+        .map(|(_, matches)| matches)
+        .expect("Expected a subcommand")
+}
+
+pub fn transpile_options(args: &TranspileArgs, file: &str) -> TranspilerOptions {
+    TranspilerOptions {
+        cfg: CfgSet::from_specs(&args.cfg),
+        strip_comments: args.strip_comments,
+        moonwave: args.moonwave,
+        annotate_const: args.annotate_const,
+        profile: args.profile,
+        header: args.header,
+        timestamp: (args.header && !args.no_timestamp).then(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .expect("The system clock is before 1970")
+                .as_secs()
+        }),
+        regions: args.regions,
+        monomorphize: args.monomorphize,
+        script: args.script,
+        call_main: args.call_main,
+        kind: args.kind,
+        target: args.target,
+        require_style: args.require_style,
+        int_semantics: args.int_semantics,
+        opt_level: args.opt_level,
+        optimizations: args.opt.clone(),
+        unsupported: args
+            .lenient
+            .filter(|_| !args.strict_unsupported)
+            .unwrap_or_default(),
+        trait_dispatch: args.trait_dispatch,
+        declarations: false,
+        format: args.format,
+        message_format: args.message_format,
+        roblox_api: None,
+        react: args.react.clone(),
+        logger: args.logger.clone(),
+        tests: None,
+        env_args: args.env_args.clone(),
+        roblox_ts: args.roblox_ts,
+        module_name: module_name(file),
+        crates: Vec::new(),
+    }
+}
+
+pub fn roblox_api_for(bindings: &str) -> Arc<RobloxApi> {
+    let path = Path::new(bindings);
+    let path = if path.is_dir() {
+        path.join("bindings.json")
+    } else {
+        path.to_path_buf()
+    };
+
+    let contents = std::fs::read_to_string(&path)
+        .unwrap_or_else(|_| panic!("Failed to read bindings from {}", path.display()));
+    Arc::new(RobloxApi::from_json(&contents))
+}
+
+pub fn write_report(path: &str, reports: &[FileReport]) {
+    let rendered = if path.ends_with(".html") {
+        report::render_html(reports)
+    } else {
+        report::render_markdown(reports)
+    };
+
+    std::fs::write(path, rendered).expect("Failed to write report");
+}
+
+/// Transpiles `source` again and describes where the result differs from `first`, if it does.
+/// Each run seeds the transpiler's hash maps anew, so output that depends on their iteration
+/// order shows up here.
+pub fn nondeterminism(
+    file: &str,
+    source: &str,
+    options: TranspilerOptions,
+    first: &Transpiled,
+) -> Option<String> {
+    let second = match transpile_source(file, source, options) {
+        Ok(second) => second,
+        Err(error) => {
+            return Some(format!(
+                "{} failed to transpile a second time: {}",
+                file, error
+            ))
+        }
+    };
+
+    if second.messages != first.messages {
+        return Some(format!(
+            "diagnostics for {} differ between two transpiles",
+            file
+        ));
+    }
+
+    let mut first_lines = first.output.lines();
+    let mut second_lines = second.output.lines();
+    let mut line = 1;
+    loop {
+        match (first_lines.next(), second_lines.next()) {
+            (None, None) => return None,
+            (first, second) if first != second => {
+                return Some(format!(
+                    "output of {} differs between two transpiles at line {}:\n  first:  {}\n  second: {}",
+                    file,
+                    line,
+                    first.unwrap_or("<end of output>"),
+                    second.unwrap_or("<end of output>")
+                ));
+            }
+            _ => line += 1,
+        }
+    }
+}
+
+/// Transpiles a file, exiting with the error if it can't be parsed or transpiled.
+pub fn transpile_file(file: &str, options: TranspilerOptions) -> Transpiled {
+    let file_contents = std::fs::read_to_string(file).expect("Failed to read file");
+    let message_format = options.message_format;
+
+    transpile_source(file, &file_contents, options).unwrap_or_else(|error| {
+        match message_format {
+            MessageFormat::Human => eprintln!("error: {}", error),
+            MessageFormat::Json => eprintln!("{}", diagnostics::error_json(&error)),
+        }
+        std::process::exit(1);
+    })
 }
@@ -0,0 +1,186 @@
+use similar::{Algorithm, DiffTag, TextDiff};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const CHECKSUM_HEADER: &str = "-- roblox-rs checksum: ";
+
+pub const MERGED: &str = "the file was edited since it was generated; the edits were kept and merged into the new output";
+
+/// Where the output each file was last generated with is kept, as the base of the merge.
+pub fn base_dir(crate_dir: &Path) -> PathBuf {
+    crate_dir.join("target").join("roblox-rs").join("merge")
+}
+
+/// Writes `generated` to `path` behind a header with its checksum. If the file there has
+/// been edited since it was generated, the edits are merged into `generated` against the
+/// output it was generated with, returning `Ok(true)`; edits that overlap a change to the
+/// output, or that can't be merged without it, leave the file as it is and are returned
+/// as the error. A file without the header wasn't written by `--merge` and is overwritten.
+pub fn write(path: &Path, base_dir: &Path, generated: &str) -> Result<bool, String> {
+    let base_path = base_dir.join(format!("{:016x}.luau", checksum(&path.to_string_lossy())));
+    let existing = fs::read_to_string(path).ok();
+
+    let (contents, merged) = match existing.as_deref().and_then(split_header) {
+        Some((recorded, body)) if recorded != checksum(body) => {
+            let base = fs::read_to_string(&base_path)
+                .ok()
+                .filter(|base| checksum(base) == recorded)
+                .ok_or_else(|| {
+                    format!(
+                        "{} was edited since it was generated, and the output it was generated with is gone, so the edits can't be merged; it was left unchanged. It differs from the new output by:\n{}",
+                        path.display(),
+                        TextDiff::from_lines(generated, body)
+                            .unified_diff()
+                            .header("generated", "edited")
+                            .to_string()
+                            .trim_end()
+                    )
+                })?;
+
+            let merged = merge(&base, body, generated).map_err(|conflicts| {
+                format!(
+                    "{} was edited since it was generated, and the edits overlap changes to the output; it was left unchanged:\n{}",
+                    path.display(),
+                    conflicts.join("\n").trim_end()
+                )
+            })?;
+            (merged, true)
+        }
+
+        _ => (generated.to_string(), false),
+    };
+
+    let contents = format!(
+        "{}{:016x}\n{}",
+        CHECKSUM_HEADER,
+        checksum(generated),
+        contents
+    );
+    if existing.as_deref() != Some(contents.as_str()) {
+        fs::write(path, contents).expect("Failed to write output file");
+    }
+
+    fs::create_dir_all(base_dir).expect("Failed to create merge base directory");
+    fs::write(base_path, generated).expect("Failed to write merge base");
+    Ok(merged)
+}
+
+/// The checksum recorded in a file's header and the rest of the file.
+fn split_header(contents: &str) -> Option<(u64, &str)> {
+    let (header, body) = contents.split_once('\n')?;
+    let checksum = header.strip_prefix(CHECKSUM_HEADER)?;
+    Some((u64::from_str_radix(checksum, 16).ok()?, body))
+}
+
+/// FNV-1a, which unlike `DefaultHasher` is the same in every Rust release.
+fn checksum(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// The changed ranges of `base` and the lines of `other` that replace them.
+fn changes(base: &[&str], other: &[&str]) -> Vec<(Range<usize>, Range<usize>)> {
+    similar::capture_diff_slices(Algorithm::Myers, base, other)
+        .into_iter()
+        .filter(|op| op.tag() != DiffTag::Equal)
+        .map(|op| (op.old_range(), op.new_range()))
+        .collect()
+}
+
+/// A three-way merge by line: each run of `base` that the edits or the new output change
+/// takes the side that changed it, or either if both changed it alike, and is a conflict
+/// otherwise. The conflicts are rendered with markers, at their line in the file.
+fn merge(base: &str, edited: &str, generated: &str) -> Result<String, Vec<String>> {
+    let base: Vec<&str> = base.split_inclusive('\n').collect();
+    let edited: Vec<&str> = edited.split_inclusive('\n').collect();
+    let generated: Vec<&str> = generated.split_inclusive('\n').collect();
+    let ours = changes(&base, &edited);
+    let theirs = changes(&base, &generated);
+
+    let mut lines: Vec<&str> = Vec::new();
+    let mut conflicts = Vec::new();
+    let (mut i, mut j, mut position) = (0, 0, 0);
+    // The checksum header is the file's first line
+    let mut offset = 1isize;
+
+    while i < ours.len() || j < theirs.len() {
+        let start = ours
+            .get(i)
+            .into_iter()
+            .chain(theirs.get(j))
+            .map(|(old, _)| old.start)
+            .min()
+            .unwrap();
+        let edited_line = start as isize + offset + 1;
+        lines.extend(&base[position..start]);
+
+        // Changes that touch or overlap each other are merged as one
+        let (first_ours, first_theirs) = (i, j);
+        let mut end = start;
+        loop {
+            if let Some((old, new)) = ours.get(i).filter(|(old, _)| old.start <= end) {
+                end = end.max(old.end);
+                offset += new.len() as isize - old.len() as isize;
+                i += 1;
+            } else if let Some((old, _)) = theirs.get(j).filter(|(old, _)| old.start <= end) {
+                end = end.max(old.end);
+                j += 1;
+            } else {
+                break;
+            }
+        }
+
+        let unchanged = &base[start..end];
+        let ours = apply(&base, &edited, &ours[first_ours..i], start..end);
+        let theirs = apply(&base, &generated, &theirs[first_theirs..j], start..end);
+        if ours == unchanged || ours == theirs {
+            lines.extend(&theirs);
+        } else if theirs == unchanged {
+            lines.extend(&ours);
+        } else {
+            conflicts.push(format!(
+                "<<<<<<< edited, line {}\n{}=======\n{}>>>>>>> generated\n",
+                edited_line,
+                with_newline(&ours),
+                with_newline(&theirs)
+            ));
+        }
+        position = end;
+    }
+
+    lines.extend(&base[position..]);
+    if conflicts.is_empty() {
+        Ok(lines.concat())
+    } else {
+        Err(conflicts)
+    }
+}
+
+/// The lines `range` of `base` becomes with `changes`, which all fall within it.
+fn apply<'a>(
+    base: &[&'a str],
+    other: &[&'a str],
+    changes: &[(Range<usize>, Range<usize>)],
+    range: Range<usize>,
+) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut position = range.start;
+    for (old, new) in changes {
+        lines.extend(&base[position..old.start]);
+        lines.extend(&other[new.clone()]);
+        position = old.end;
+    }
+
+    lines.extend(&base[position..range.end]);
+    lines
+}
+
+fn with_newline(lines: &[&str]) -> String {
+    let mut text = lines.concat();
+    if !text.is_empty() && !text.ends_with('\n') {
+        text.push('\n');
+    }
+    text
+}
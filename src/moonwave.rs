@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+
+pub struct FunctionDocs<'a> {
+    pub within: &'a str,
+    pub name: &'a str,
+    pub params: &'a [(String, String)],
+    pub return_type: Option<&'a str>,
+}
+
+pub struct InterfaceDocs<'a> {
+    pub within: &'a str,
+    pub name: &'a str,
+    pub fields: &'a [(String, String)],
+}
+
+pub fn class_docs(class: &str, docs: Vec<String>) -> Vec<String> {
+    let mut lines = vec![format!("@class {}", class)];
+    lines.extend(docs);
+    lines
+}
+
+pub fn function_docs(function: &FunctionDocs, docs: Vec<String>) -> Vec<String> {
+    let (mut lines, param_docs, return_docs) = split_sections(docs);
+
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+
+    lines.push(format!("@function {}", function.name));
+    lines.push(format!("@within {}", function.within));
+
+    for (name, ty) in function.params {
+        match param_docs.get(name) {
+            Some(description) => lines.push(format!("@param {} {} -- {}", name, ty, description)),
+            None => lines.push(format!("@param {} {}", name, ty)),
+        }
+    }
+
+    if let Some(ty) = function.return_type {
+        match &return_docs {
+            Some(description) => lines.push(format!("@return {} -- {}", ty, description)),
+            None => lines.push(format!("@return {}", ty)),
+        }
+    }
+
+    lines
+}
+
+pub fn interface_docs(interface: &InterfaceDocs, mut docs: Vec<String>) -> Vec<String> {
+    if !docs.is_empty() {
+        docs.push(String::new());
+    }
+
+    docs.push(format!("@interface {}", interface.name));
+    docs.push(format!("@within {}", interface.within));
+
+    for (name, ty) in interface.fields {
+        docs.push(format!(".{} {}", name, ty));
+    }
+
+    docs
+}
+
+fn split_sections(docs: Vec<String>) -> (Vec<String>, HashMap<String, String>, Option<String>) {
+    let mut body = Vec::new();
+    let mut params = HashMap::new();
+    let mut returns: Vec<String> = Vec::new();
+    let mut section = None;
+
+    for line in docs {
+        let trimmed = line.trim();
+
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            section = Some(heading.trim().to_lowercase());
+            continue;
+        }
+
+        match section.as_deref() {
+            Some("arguments") | Some("parameters") => {
+                if let Some((name, description)) = parse_param_bullet(trimmed) {
+                    params.insert(name, description);
+                }
+            }
+
+            Some("returns") => {
+                if !trimmed.is_empty() {
+                    returns.push(trimmed.to_string());
+                }
+            }
+
+            _ => body.push(line),
+        }
+    }
+
+    while body.last().is_some_and(|line| line.is_empty()) {
+        body.pop();
+    }
+
+    let returns = (!returns.is_empty()).then(|| returns.join(" "));
+    (body, params, returns)
+}
+
+fn parse_param_bullet(line: &str) -> Option<(String, String)> {
+    let rest = line
+        .strip_prefix("* ")
+        .or_else(|| line.strip_prefix("- "))?;
+    let rest = rest.strip_prefix('`')?;
+    let (name, description) = rest.split_once('`')?;
+    let description = description
+        .trim_start()
+        .trim_start_matches(['-', ':'])
+        .trim();
+
+    Some((name.to_string(), description.to_string()))
+}
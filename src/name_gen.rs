@@ -0,0 +1,50 @@
+use std::collections::HashSet;
+use syn::visit::Visit;
+
+#[derive(Default)]
+pub struct NameGen {
+    reserved: HashSet<String>,
+    next: usize,
+}
+
+impl NameGen {
+    pub fn collect(file: &syn::File) -> Self {
+        let mut collector = IdentCollector::default();
+        collector.visit_file(file);
+
+        Self {
+            reserved: collector.idents,
+            next: 0,
+        }
+    }
+
+    pub fn fresh(&mut self) -> String {
+        loop {
+            self.next += 1;
+
+            let name = format!("__tmp_{}", self.next);
+            if self.reserved.insert(name.clone()) {
+                return name;
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct IdentCollector {
+    idents: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for IdentCollector {
+    fn visit_ident(&mut self, i: &'ast proc_macro2::Ident) {
+        self.idents.insert(i.to_string());
+    }
+
+    fn visit_lit_str(&mut self, i: &'ast syn::LitStr) {
+        let value = i.value();
+        let words = value.split(|c: char| !(c.is_alphanumeric() || c == '_'));
+
+        self.idents
+            .extend(words.filter(|word| !word.is_empty()).map(str::to_string));
+    }
+}
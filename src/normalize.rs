@@ -0,0 +1,185 @@
+use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
+
+/// Attributes the transpiler reads; any other is dropped before lowering.
+const KEPT_ATTRIBUTES: &[&str] = &[
+    "actor",
+    "bench",
+    "cfg",
+    "component",
+    "derive",
+    "doc",
+    "error",
+    "flamework",
+    "from",
+    "ignore",
+    "inline",
+    "kind",
+    "link_name",
+    "luau",
+    "profile",
+    "readonly",
+    "require",
+    "should_panic",
+    "test",
+];
+
+/// Strips what has no meaning in Luau from a file before it is lowered: lifetimes, in
+/// generics, bounds, references and type arguments, attributes like `#[must_use]` or
+/// `#[allow(...)]`, and `unsafe`, so an `unsafe { ... }` block is a plain block. `fn get<'a>(&'a self) -> &'a str` then reads like `fn get(&self) -> &str`,
+/// so nothing that looks at a signature's generics or a parameter's attributes trips on them.
+pub fn normalize(file: &mut syn::File) {
+    Normalize.visit_file_mut(file);
+}
+
+struct Normalize;
+
+/// Overrides a visit method to drop the node's unread attributes before visiting it.
+macro_rules! strip_attributes {
+    ($($visit:ident: $node:ty),* $(,)?) => {
+        $(
+            fn $visit(&mut self, node: &mut $node) {
+                node.attrs.retain(|attr| {
+                    KEPT_ATTRIBUTES.iter().any(|name| attr.path().is_ident(name))
+                });
+                syn::visit_mut::$visit(self, node);
+            }
+        )*
+    };
+}
+
+impl VisitMut for Normalize {
+    strip_attributes! {
+        visit_file_mut: syn::File,
+        visit_item_fn_mut: syn::ItemFn,
+        visit_item_struct_mut: syn::ItemStruct,
+        visit_item_enum_mut: syn::ItemEnum,
+        visit_item_impl_mut: syn::ItemImpl,
+        visit_item_trait_mut: syn::ItemTrait,
+        visit_item_mod_mut: syn::ItemMod,
+        visit_item_const_mut: syn::ItemConst,
+        visit_item_static_mut: syn::ItemStatic,
+        visit_item_type_mut: syn::ItemType,
+        visit_item_use_mut: syn::ItemUse,
+        visit_item_foreign_mod_mut: syn::ItemForeignMod,
+        visit_foreign_item_fn_mut: syn::ForeignItemFn,
+        visit_impl_item_fn_mut: syn::ImplItemFn,
+        visit_impl_item_const_mut: syn::ImplItemConst,
+        visit_impl_item_type_mut: syn::ImplItemType,
+        visit_trait_item_fn_mut: syn::TraitItemFn,
+        visit_trait_item_const_mut: syn::TraitItemConst,
+        visit_field_mut: syn::Field,
+        visit_variant_mut: syn::Variant,
+        visit_pat_type_mut: syn::PatType,
+        visit_local_mut: syn::Local,
+        visit_arm_mut: syn::Arm,
+        visit_field_value_mut: syn::FieldValue,
+        visit_expr_closure_mut: syn::ExprClosure,
+        visit_expr_block_mut: syn::ExprBlock,
+    }
+
+    fn visit_generics_mut(&mut self, generics: &mut syn::Generics) {
+        generics.params = std::mem::take(&mut generics.params)
+            .into_iter()
+            .filter(|param| !matches!(param, syn::GenericParam::Lifetime(_)))
+            .collect();
+        if generics.params.is_empty() {
+            generics.lt_token = None;
+            generics.gt_token = None;
+        }
+
+        if let Some(where_clause) = &mut generics.where_clause {
+            where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+                .into_iter()
+                .filter(|predicate| !matches!(predicate, syn::WherePredicate::Lifetime(_)))
+                .collect();
+            if where_clause.predicates.is_empty() {
+                generics.where_clause = None;
+            }
+        }
+
+        syn::visit_mut::visit_generics_mut(self, generics);
+    }
+
+    fn visit_type_param_bound_mut(&mut self, bound: &mut syn::TypeParamBound) {
+        if let syn::TypeParamBound::Trait(trait_bound) = bound {
+            trait_bound.lifetimes = None;
+        }
+
+        syn::visit_mut::visit_type_param_bound_mut(self, bound);
+    }
+
+    fn visit_type_mut(&mut self, ty: &mut syn::Type) {
+        match ty {
+            syn::Type::Reference(reference) => reference.lifetime = None,
+            syn::Type::BareFn(bare_fn) => bare_fn.lifetimes = None,
+            syn::Type::TraitObject(object) => strip_lifetime_bounds(&mut object.bounds),
+            syn::Type::ImplTrait(impl_trait) => strip_lifetime_bounds(&mut impl_trait.bounds),
+            _ => {}
+        }
+
+        syn::visit_mut::visit_type_mut(self, ty);
+    }
+
+    fn visit_predicate_type_mut(&mut self, predicate: &mut syn::PredicateType) {
+        predicate.lifetimes = None;
+        strip_lifetime_bounds(&mut predicate.bounds);
+        syn::visit_mut::visit_predicate_type_mut(self, predicate);
+    }
+
+    fn visit_type_param_mut(&mut self, param: &mut syn::TypeParam) {
+        strip_lifetime_bounds(&mut param.bounds);
+        if param.bounds.is_empty() {
+            param.colon_token = None;
+        }
+
+        syn::visit_mut::visit_type_param_mut(self, param);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::Unsafe(unsafe_block) = expr {
+            *expr = syn::Expr::Block(syn::ExprBlock {
+                attrs: std::mem::take(&mut unsafe_block.attrs),
+                label: None,
+                block: unsafe_block.block.clone(),
+            });
+        }
+
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_signature_mut(&mut self, sig: &mut syn::Signature) {
+        sig.unsafety = None;
+        syn::visit_mut::visit_signature_mut(self, sig);
+    }
+
+    fn visit_receiver_mut(&mut self, receiver: &mut syn::Receiver) {
+        receiver.attrs.clear();
+        if let Some((_, lifetime)) = &mut receiver.reference {
+            *lifetime = None;
+        }
+
+        syn::visit_mut::visit_receiver_mut(self, receiver);
+    }
+
+    fn visit_path_arguments_mut(&mut self, arguments: &mut syn::PathArguments) {
+        if let syn::PathArguments::AngleBracketed(angle) = arguments {
+            angle.args = std::mem::take(&mut angle.args)
+                .into_iter()
+                .filter(|arg| !matches!(arg, syn::GenericArgument::Lifetime(_)))
+                .collect();
+            if angle.args.is_empty() {
+                *arguments = syn::PathArguments::None;
+            }
+        }
+
+        syn::visit_mut::visit_path_arguments_mut(self, arguments);
+    }
+}
+
+fn strip_lifetime_bounds<P: Default>(bounds: &mut Punctuated<syn::TypeParamBound, P>) {
+    *bounds = std::mem::take(bounds)
+        .into_iter()
+        .filter(|bound| !matches!(bound, syn::TypeParamBound::Lifetime(_)))
+        .collect();
+}
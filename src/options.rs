@@ -0,0 +1,155 @@
+use crate::cfg::CfgSet;
+use crate::diagnostics::MessageFormat;
+use crate::roblox_api::RobloxApi;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum ScriptKind {
+    Script,
+    #[value(name = "localscript")]
+    LocalScript,
+    Module,
+}
+
+impl ScriptKind {
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::from_str(name, true).ok()
+    }
+
+    pub fn suffix(self) -> &'static str {
+        match self {
+            ScriptKind::Script => ".server.luau",
+            ScriptKind::LocalScript => ".client.luau",
+            ScriptKind::Module => ".luau",
+        }
+    }
+
+    pub fn runs_main(self) -> bool {
+        matches!(self, ScriptKind::Script | ScriptKind::LocalScript)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Formatter {
+    Stylua,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum TraitDispatch {
+    #[default]
+    Metatable,
+    Dict,
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Target {
+    #[default]
+    Roblox,
+    Lune,
+}
+
+/// How a `require` names its module: a string path like `"./util"`, as Lune and Roblox's
+/// require-by-string take them, or an instance path like `script.Parent.util`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum RequireStyle {
+    String,
+    Instance,
+}
+
+impl RequireStyle {
+    /// Lune has no instances, and Roblox projects mostly use instance paths.
+    pub fn default_for(target: Target) -> Self {
+        match target {
+            Target::Roblox => RequireStyle::Instance,
+            Target::Lune => RequireStyle::String,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum IntSemantics {
+    #[default]
+    Exact,
+    Wrapping,
+    Checked,
+}
+
+/// The test framework `--emit-tests` writes spec files for.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum TestFramework {
+    #[default]
+    Testez,
+    Jest,
+}
+
+/// Optimizations enabled one by one with `--opt`, independent of `--opt-level`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Optimization {
+    /// Rewrite functions that call themselves in tail position into loops
+    Tailrec,
+}
+
+/// What happens at a construct the transpiler can't lower.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Unsupported {
+    /// Stop with an error at its location
+    #[default]
+    #[value(skip)]
+    Abort,
+    /// Warn and emit an `error("roblox-rs: unsupported: ...")` stub in its place
+    Stub,
+    /// Warn and leave it out
+    Skip,
+}
+
+#[derive(Default, Clone)]
+pub struct TranspilerOptions {
+    pub cfg: CfgSet,
+    pub strip_comments: bool,
+    pub moonwave: bool,
+    pub annotate_const: bool,
+    pub profile: bool,
+    /// Start the output with an `@generated` header, stamped with this Unix time if any
+    pub header: bool,
+    pub timestamp: Option<u64>,
+    /// Wrap each item's output in `--#region` markers naming the Rust item
+    pub regions: bool,
+    pub monomorphize: bool,
+    pub script: bool,
+    pub call_main: bool,
+    pub kind: Option<ScriptKind>,
+    pub target: Target,
+    /// The style of `require` paths, or the target's [`RequireStyle::default_for`]
+    pub require_style: Option<RequireStyle>,
+    pub int_semantics: IntSemantics,
+    pub opt_level: u8,
+    pub optimizations: Vec<Optimization>,
+    pub unsupported: Unsupported,
+    pub trait_dispatch: TraitDispatch,
+    pub declarations: bool,
+    pub format: Option<Formatter>,
+    pub message_format: MessageFormat,
+    pub roblox_api: Option<Arc<RobloxApi>>,
+    pub react: Option<String>,
+    pub logger: Option<String>,
+    /// Emit the file's `#[test]` functions as a spec file for this framework instead
+    pub tests: Option<TestFramework>,
+    pub env_args: Option<String>,
+    pub roblox_ts: bool,
+    pub module_name: String,
+    pub crates: Vec<CrateModules>,
+}
+
+/// A workspace crate the file can refer to by name.
+#[derive(Clone)]
+pub struct CrateModules {
+    pub name: String,
+    /// Each module's path within the crate, empty for its root, and its `require` path in
+    /// either style, like `../shared/src/math` or `game:GetService("ReplicatedStorage").shared.math`
+    pub modules: Vec<(Vec<String>, String)>,
+    /// The names each module's `pub` items and `pub use`s export, by the module's path, for
+    /// glob imports
+    pub exports: HashMap<Vec<String>, Vec<String>>,
+}
@@ -0,0 +1,513 @@
+use crate::cache::{Cache, CachedModule};
+use crate::cli::PackageArgs;
+use crate::project::{own_modules, ModuleTree};
+use rayon::prelude::*;
+use roblox_rs::cfg::CfgSet;
+use roblox_rs::diagnostics::{self, MessageFormat};
+use roblox_rs::options::{CrateModules, ScriptKind, Target as LuauTarget, TranspilerOptions};
+use roblox_rs::roblox_api::RobloxApi;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+const WALLY_REGISTRY: &str = "https://github.com/UpliftGames/wally-index";
+
+pub fn run(args: &PackageArgs) {
+    if let Err(error) = package(args) {
+        match args.transpile.message_format {
+            MessageFormat::Human => eprintln!("error: {}", error),
+            MessageFormat::Json => eprintln!("{}", diagnostics::error_json(&error)),
+        }
+        std::process::exit(1);
+    }
+}
+
+fn package(args: &PackageArgs) -> Result<(), String> {
+    let crate_dir = Path::new(&args.crate_dir);
+    let manifest_path = crate_dir.join("Cargo.toml");
+    let manifest = fs::read_to_string(&manifest_path)
+        .map_err(|error| format!("Failed to read {}: {}", manifest_path.display(), error))?;
+    let manifest: toml::Table = manifest
+        .parse()
+        .map_err(|error| format!("Failed to parse {}: {}", manifest_path.display(), error))?;
+
+    let package = manifest
+        .get("package")
+        .and_then(|package| package.as_table())
+        .ok_or("Cargo.toml has no [package] table")?;
+
+    let name = package_str(package, "name").ok_or("Cargo.toml is missing package.name")?;
+    let version = package_str(package, "version").ok_or("Cargo.toml is missing package.version")?;
+
+    let scope = args
+        .scope
+        .clone()
+        .or_else(|| {
+            package
+                .get("metadata")
+                .and_then(|metadata| metadata.get("wally"))
+                .and_then(|wally| wally.get("scope"))
+                .and_then(|scope| scope.as_str())
+                .map(str::to_string)
+        })
+        .ok_or("A Wally scope is required; pass --scope or set package.metadata.wally.scope")?;
+
+    let src_dir = crate_dir.join("src");
+    if !src_dir.join("lib.rs").exists() {
+        return Err("`package` requires a library crate with src/lib.rs".to_string());
+    }
+    let cfg = CfgSet::from_specs(&args.transpile.cfg);
+    let tree = ModuleTree {
+        modules: module_files(&src_dir.join("lib.rs"), &cfg)?,
+        base: PathBuf::from("src"),
+    };
+    // A module's `require`s depend on which modules the crate has
+    let layout: Vec<&Vec<String>> = tree.modules.iter().map(|module| &module.path).collect();
+
+    let out_dir = Path::new(&args.out_dir);
+    fs::create_dir_all(out_dir.join("src")).expect("Failed to create output directory");
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(args.jobs.unwrap_or(0))
+        .build()
+        .expect("Failed to start worker threads");
+
+    let roblox_api = args
+        .transpile
+        .bindings
+        .as_deref()
+        .map(crate::roblox_api_for);
+    let cache = (!args.no_cache).then(|| Cache::open(crate_dir));
+
+    let sources: Vec<String> = tree
+        .modules
+        .iter()
+        .map(|module_file| fs::read_to_string(&module_file.source).expect("Failed to read file"))
+        .collect();
+    let hashes: Vec<u64> = sources
+        .iter()
+        .map(|contents| Cache::source_hash(contents))
+        .collect();
+
+    let modules: Vec<(String, CachedModule)> = pool.install(|| {
+        tree.modules
+            .par_iter()
+            .enumerate()
+            .map(|(index, module_file)| {
+                let source = &module_file.source;
+                let dependencies: Vec<u64> = tree
+                    .dependencies(index)
+                    .into_iter()
+                    .map(|dependency| hashes[dependency])
+                    .collect();
+                let key = Cache::key(
+                    source,
+                    &sources[index],
+                    &dependencies,
+                    &(&args.transpile, args.declarations, &layout),
+                );
+
+                if let Some(module) = cache.as_ref().and_then(|cache| cache.load(&key)) {
+                    return (key, module);
+                }
+
+                // Like in a project build, a Roblox `init.luau` is its folder's script
+                let target = out_dir.join("src").join(module_file.luau_path());
+                let is_init = target.file_stem().is_some_and(|stem| stem == "init");
+                let folder = target.parent().unwrap();
+                let from = match folder.parent() {
+                    Some(parent) if is_init && args.transpile.target == LuauTarget::Roblox => {
+                        parent
+                    }
+                    _ => folder,
+                };
+                let crates = own_modules(
+                    &tree,
+                    &module_file.path,
+                    is_init,
+                    out_dir,
+                    from,
+                    args.transpile.target,
+                );
+                let module = transpile_module(args, source, roblox_api.clone(), crates);
+                if let Some(cache) = &cache {
+                    cache.store(&key, &module);
+                }
+
+                (key, module)
+            })
+            .collect()
+    });
+
+    for (module_file, (_, module)) in tree.modules.iter().zip(&modules) {
+        for message in &module.messages {
+            eprintln!("{}", message);
+        }
+
+        let target = out_dir.join("src").join(module_file.luau_path());
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).expect("Failed to create output directory");
+        }
+
+        write_if_changed(&target, &module.output);
+
+        if let Some(declarations) = &module.declarations {
+            write_if_changed(&target.with_extension("d.luau"), declarations);
+        }
+    }
+
+    if let Some(cache) = &cache {
+        cache.prune(&modules.into_iter().map(|(key, _)| key).collect());
+    }
+
+    let wally_name = format!("{}/{}", wally_ident(&scope), wally_ident(&name));
+    fs::write(
+        out_dir.join("wally.toml"),
+        wally_manifest(package, &wally_name, &version, &args.realm),
+    )
+    .expect("Failed to write wally.toml");
+
+    fs::write(
+        out_dir.join("default.project.json"),
+        project_file(&name, args.declarations),
+    )
+    .expect("Failed to write default.project.json");
+
+    Ok(())
+}
+
+fn transpile_module(
+    args: &PackageArgs,
+    source: &Path,
+    roblox_api: Option<Arc<RobloxApi>>,
+    crates: Vec<CrateModules>,
+) -> CachedModule {
+    let file = source.to_string_lossy().to_string();
+    let mut options = crate::transpile_options(&args.transpile, &file);
+    options.kind = Some(ScriptKind::Module);
+    options.roblox_api = roblox_api;
+    options.crates = crates;
+
+    let declarations = args.declarations.then(|| {
+        let options = TranspilerOptions {
+            declarations: true,
+            ..options.clone()
+        };
+
+        crate::transpile_file(&file, options).output
+    });
+
+    let transpiled = crate::transpile_file(&file, options);
+    CachedModule {
+        output: transpiled.output,
+        declarations,
+        messages: transpiled.messages,
+    }
+}
+
+// Leaving unchanged files untouched keeps Rojo from re-syncing them
+pub fn write_if_changed(path: &Path, contents: &str) {
+    if fs::read_to_string(path).is_ok_and(|existing| existing == contents) {
+        return;
+    }
+
+    fs::write(path, contents).expect("Failed to write transpiled module");
+}
+
+fn package_str(package: &toml::Table, key: &str) -> Option<String> {
+    package
+        .get(key)
+        .and_then(|value| value.as_str())
+        .map(str::to_string)
+}
+
+/// A module of a crate, found by following `mod` declarations from the crate's root file.
+pub struct ModuleFile {
+    pub source: PathBuf,
+    /// Its path from the crate root, empty for the root itself
+    pub path: Vec<String>,
+    /// The modules it declares in files of their own, which make it its folder's `init.luau`
+    pub children: Vec<String>,
+    /// The names of its `pub` items and `pub use`s
+    pub exports: Vec<String>,
+    /// The crate paths its `use`s resolve to, like `["util", "add"]`, or `["util"]` for a
+    /// glob over `util`
+    pub uses: Vec<Vec<String>>,
+}
+
+impl ModuleFile {
+    /// Where it's written relative to the crate's output folder, like `util.luau` or
+    /// `geo/init.luau` for a module with modules of its own.
+    pub fn luau_path(&self) -> PathBuf {
+        let mut path: PathBuf = self.path.iter().collect();
+        if self.path.is_empty() || !self.children.is_empty() {
+            path.push("init.luau");
+        } else {
+            path.set_extension("luau");
+        }
+        path
+    }
+}
+
+/// The modules of the crate rooted at `root`, in the order they're declared, with the ones
+/// `cfg` turns off left out. A `mod` whose file doesn't exist is an error.
+pub fn module_files(root: &Path, cfg: &CfgSet) -> Result<Vec<ModuleFile>, String> {
+    let mut modules = Vec::new();
+    let dir = root.parent().unwrap_or(Path::new(".")).to_path_buf();
+    collect_modules(root, &dir, Vec::new(), cfg, &mut modules)?;
+    Ok(modules)
+}
+
+fn collect_modules(
+    source: &Path,
+    dir: &Path,
+    path: Vec<String>,
+    cfg: &CfgSet,
+    modules: &mut Vec<ModuleFile>,
+) -> Result<(), String> {
+    let contents = fs::read_to_string(source)
+        .map_err(|error| format!("Failed to read {}: {}", source.display(), error))?;
+    // A file that doesn't parse is reported when it's transpiled
+    let items = syn::parse_file(&contents)
+        .map(|file| file.items)
+        .unwrap_or_default();
+
+    let mut declared = Vec::new();
+    for item in &items {
+        let syn::Item::Mod(item_mod) = item else {
+            continue;
+        };
+        if item_mod.content.is_some() || !cfg.is_enabled(&item_mod.attrs) {
+            continue;
+        }
+
+        let name = item_mod.ident.to_string();
+        let explicit = item_mod.attrs.iter().find_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(meta) if meta.path.is_ident("path") => match &meta.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(path),
+                    ..
+                }) => Some(path.value()),
+                _ => None,
+            },
+            _ => None,
+        });
+
+        // `a.rs` declares its modules in `a/`, `a/mod.rs` and `#[path]` files next to them
+        let (file, child_dir) = match explicit {
+            Some(explicit) => {
+                let file = source.parent().unwrap_or(Path::new(".")).join(explicit);
+                let child_dir = file.parent().unwrap_or(Path::new(".")).to_path_buf();
+                (file, child_dir)
+            }
+            None if dir.join(format!("{}.rs", name)).exists() => {
+                (dir.join(format!("{}.rs", name)), dir.join(&name))
+            }
+            None if dir.join(&name).join("mod.rs").exists() => {
+                (dir.join(&name).join("mod.rs"), dir.join(&name))
+            }
+            None => {
+                return Err(format!(
+                    "{}: file not found for module `{}`; expected {} or {}",
+                    source.display(),
+                    name,
+                    dir.join(format!("{}.rs", name)).display(),
+                    dir.join(&name).join("mod.rs").display()
+                ))
+            }
+        };
+        if !file.exists() {
+            return Err(format!(
+                "{}: file not found for module `{}` at {}",
+                source.display(),
+                name,
+                file.display()
+            ));
+        }
+
+        declared.push((name, file, child_dir));
+    }
+
+    let children: Vec<String> = declared.iter().map(|(name, _, _)| name.clone()).collect();
+    modules.push(ModuleFile {
+        source: source.to_path_buf(),
+        path: path.clone(),
+        uses: used_paths(&items, &path, &children, cfg),
+        children,
+        exports: exported_names(&items, cfg),
+    });
+
+    for (name, file, child_dir) in declared {
+        let mut child = path.clone();
+        child.push(name);
+        collect_modules(&file, &child_dir, child, cfg, modules)?;
+    }
+
+    Ok(())
+}
+
+/// The names a module's `pub` items and `pub use`s add to its table.
+fn exported_names(items: &[syn::Item], cfg: &CfgSet) -> Vec<String> {
+    fn use_names(tree: &syn::UseTree, names: &mut Vec<String>) {
+        match tree {
+            syn::UseTree::Path(path) => use_names(&path.tree, names),
+            syn::UseTree::Name(name) if name.ident != "self" => names.push(name.ident.to_string()),
+            syn::UseTree::Rename(rename) => names.push(rename.rename.to_string()),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    use_names(tree, names);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let mut names = Vec::new();
+    for item in items {
+        let (attrs, vis, ident) = match item {
+            syn::Item::Const(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Enum(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Fn(item) => (&item.attrs, &item.vis, &item.sig.ident),
+            syn::Item::Mod(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Static(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Struct(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Trait(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Type(item) => (&item.attrs, &item.vis, &item.ident),
+            syn::Item::Use(item_use)
+                if matches!(item_use.vis, syn::Visibility::Public(_))
+                    && cfg.is_enabled(&item_use.attrs) =>
+            {
+                use_names(&item_use.tree, &mut names);
+                continue;
+            }
+            _ => continue,
+        };
+
+        if matches!(vis, syn::Visibility::Public(_)) && cfg.is_enabled(attrs) {
+            names.push(ident.to_string());
+        }
+    }
+
+    names
+}
+
+/// The crate paths the `use`s of the module at `module` resolve to, including those of its
+/// inline modules. Paths into other crates are left out.
+fn used_paths(
+    items: &[syn::Item],
+    module: &[String],
+    children: &[String],
+    cfg: &CfgSet,
+) -> Vec<Vec<String>> {
+    fn use_paths(tree: &syn::UseTree, mut prefix: Vec<String>, paths: &mut Vec<Vec<String>>) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                use_paths(&path.tree, prefix, paths);
+            }
+            syn::UseTree::Name(syn::UseName { ident })
+            | syn::UseTree::Rename(syn::UseRename { ident, .. }) => {
+                if ident != "self" {
+                    prefix.push(ident.to_string());
+                }
+                paths.push(prefix);
+            }
+            syn::UseTree::Glob(_) => paths.push(prefix),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    use_paths(tree, prefix.clone(), paths);
+                }
+            }
+        }
+    }
+
+    let mut paths = Vec::new();
+    for item in items {
+        match item {
+            syn::Item::Use(item_use)
+                if item_use.leading_colon.is_none() && cfg.is_enabled(&item_use.attrs) =>
+            {
+                let mut used = Vec::new();
+                use_paths(&item_use.tree, Vec::new(), &mut used);
+
+                paths.extend(used.into_iter().filter_map(|used| {
+                    let (mut resolved, rest) = match used.first()?.as_str() {
+                        "crate" => (Vec::new(), &used[1..]),
+                        "self" | "super" => (module.to_vec(), &used[..]),
+                        name if children.iter().any(|child| child == name) => {
+                            (module.to_vec(), &used[..])
+                        }
+                        _ => return None,
+                    };
+
+                    for segment in rest {
+                        match segment.as_str() {
+                            "self" => {}
+                            "super" => {
+                                resolved.pop()?;
+                            }
+                            name => resolved.push(name.to_string()),
+                        }
+                    }
+                    Some(resolved)
+                }));
+            }
+            syn::Item::Mod(item_mod) if cfg.is_enabled(&item_mod.attrs) => {
+                if let Some((_, items)) = &item_mod.content {
+                    let mut inner = module.to_vec();
+                    inner.push(item_mod.ident.to_string());
+                    paths.extend(used_paths(items, &inner, &[], cfg));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    paths
+}
+
+fn wally_ident(name: &str) -> String {
+    name.to_lowercase().replace('_', "-")
+}
+
+fn wally_manifest(package: &toml::Table, name: &str, version: &str, realm: &str) -> String {
+    let mut wally_package = toml::Table::new();
+    wally_package.insert("name".into(), name.into());
+    wally_package.insert("version".into(), version.into());
+    wally_package.insert("registry".into(), WALLY_REGISTRY.into());
+    wally_package.insert("realm".into(), realm.into());
+
+    for key in [
+        "description",
+        "license",
+        "authors",
+        "homepage",
+        "repository",
+    ] {
+        if let Some(value) = package.get(key) {
+            wally_package.insert(key.into(), value.clone());
+        }
+    }
+
+    let mut manifest = toml::Table::new();
+    manifest.insert("package".into(), wally_package.into());
+    manifest.insert("dependencies".into(), toml::Table::new().into());
+
+    toml::to_string(&manifest).expect("Failed to serialize wally.toml")
+}
+
+fn project_file(name: &str, declarations: bool) -> String {
+    let mut project = serde_json::json!({
+        "name": name,
+        "tree": {
+            "$path": "src"
+        }
+    });
+
+    if declarations {
+        project["globIgnorePaths"] = serde_json::json!(["**/*.d.luau"]);
+    }
+
+    serde_json::to_string_pretty(&project).expect("Failed to serialize default.project.json")
+}
@@ -0,0 +1,1132 @@
+use crate::cli::{ProjectArgs, TranspileArgs};
+use crate::package::{module_files, write_if_changed, ModuleFile};
+use anyhow::{anyhow, bail, Context};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, ValueEnum};
+use roblox_rs::cfg::CfgSet;
+use roblox_rs::diagnostics::{self, MessageFormat};
+use roblox_rs::options::{CrateModules, RequireStyle, ScriptKind, Target as LuauTarget};
+use roblox_rs::report::{self, FileReport};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::{Duration, SystemTime};
+
+struct Member {
+    name: String,
+    dir: PathBuf,
+    manifest: toml::Table,
+    out_dir: PathBuf,
+    metadata: toml::Table,
+    require: Option<String>,
+}
+
+pub fn build(args: &ProjectArgs, matches: &ArgMatches) {
+    if !build_project(args, matches, true) {
+        std::process::exit(1);
+    }
+}
+
+pub fn check(args: &ProjectArgs, matches: &ArgMatches) {
+    if !build_project(args, matches, false) {
+        std::process::exit(1);
+    }
+}
+
+/// Builds, then polls the manifests and sources of every member and rebuilds when one of
+/// them changes.
+pub fn watch(args: &ProjectArgs, matches: &ArgMatches) {
+    build_project(args, matches, true);
+    let mut stamps = source_stamps(args);
+
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+
+        let current = source_stamps(args);
+        if current != stamps {
+            stamps = current;
+            eprintln!("Rebuilding...");
+            build_project(args, matches, true);
+        }
+    }
+}
+
+/// Transpiles every library module and binary of the selected members, and the libraries
+/// of their `transpile-deps`, returning whether all of them transpiled.
+fn build_project(args: &ProjectArgs, matches: &ArgMatches, write: bool) -> bool {
+    let mut succeeded = true;
+    let members = match members(args) {
+        Ok(members) => members,
+        Err(error) => {
+            report_error(&format!("{:#}", error), args.transpile.message_format);
+            return false;
+        }
+    };
+    let mut reports = Vec::new();
+
+    for member in members
+        .iter()
+        .filter(|member| args.package.is_empty() || args.package.contains(&member.name))
+    {
+        let mut transpile = args.transpile.clone();
+        let packages = apply_metadata(&mut transpile, &member.metadata, matches)
+            .and_then(|()| transpiled_packages(member));
+        let packages = match packages {
+            Ok(packages) => packages,
+            Err(error) => {
+                report_error(&format!("{:#}", error), transpile.message_format);
+                succeeded = false;
+                continue;
+            }
+        };
+        for package in &packages {
+            let mut package_transpile = transpile.clone();
+            for feature in &package.features {
+                package_transpile
+                    .cfg
+                    .push(format!("feature=\"{}\"", feature));
+            }
+
+            let dependencies: Vec<(String, &Member)> = package
+                .dependencies
+                .iter()
+                .map(|(name, index)| (name.clone(), &packages[*index].member))
+                .collect();
+            let modules =
+                match targets(&package.member, &CfgSet::from_specs(&package_transpile.cfg)) {
+                    Ok(targets) => targets.into_iter().filter(|file| file.library).collect(),
+                    Err(error) => {
+                        report_error(&error, package_transpile.message_format);
+                        succeeded = false;
+                        continue;
+                    }
+                };
+
+            succeeded &= build_targets(
+                &package.member,
+                modules,
+                &dependencies,
+                &package_transpile,
+                write,
+                args,
+                args.report.is_some().then_some(&mut reports),
+            );
+        }
+
+        for feature in enabled_features(args, member) {
+            transpile.cfg.push(format!("feature=\"{}\"", feature));
+        }
+
+        let mut dependencies = workspace_dependencies(member, &members);
+        dependencies.extend(
+            packages
+                .iter()
+                .filter_map(|package| Some((package.name.clone()?, &package.member))),
+        );
+
+        let member_targets = match targets(member, &CfgSet::from_specs(&transpile.cfg)) {
+            Ok(targets) => targets,
+            Err(error) => {
+                report_error(&error, transpile.message_format);
+                succeeded = false;
+                continue;
+            }
+        };
+
+        succeeded &= build_targets(
+            member,
+            member_targets,
+            &dependencies,
+            &transpile,
+            write,
+            args,
+            args.report.is_some().then_some(&mut reports),
+        );
+    }
+
+    if let Some(path) = &args.report {
+        crate::write_report(path, &reports);
+    }
+
+    succeeded
+}
+
+fn build_targets(
+    member: &Member,
+    targets: Vec<TargetFile>,
+    dependencies: &[(String, &Member)],
+    transpile: &TranspileArgs,
+    write: bool,
+    args: &ProjectArgs,
+    mut reports: Option<&mut Vec<FileReport>>,
+) -> bool {
+    let mut succeeded = true;
+    let roblox_api = transpile.bindings.as_deref().map(crate::roblox_api_for);
+
+    for target_file in targets {
+        let source = &target_file.source;
+        let kind = target_file.kind;
+        let target = &target_file.target;
+        let file = source.to_string_lossy().to_string();
+        let contents = fs::read_to_string(source).expect("Failed to read file");
+
+        let out_dir = match target {
+            Target::Module(path) => member.out_dir.join(path).parent().unwrap().to_path_buf(),
+            Target::Script(_) => member.out_dir.clone(),
+            Target::ScriptFolder { name, .. } => member.out_dir.join(name),
+        };
+        let is_init = match target {
+            Target::Module(path) => path.file_stem().is_some_and(|stem| stem == "init"),
+            Target::Script(_) => false,
+            Target::ScriptFolder { actor, .. } => !actor,
+        };
+
+        // Require-by-string on Roblox resolves `./` from the script's parent, and a folder's
+        // `init.luau` is the folder's script, where Lune resolves it from the file's folder
+        let require_style = transpile
+            .require_style
+            .unwrap_or(RequireStyle::default_for(transpile.target));
+        let require_from = if transpile.target == LuauTarget::Roblox && is_init {
+            out_dir.parent().unwrap().to_path_buf()
+        } else {
+            out_dir.clone()
+        };
+
+        // Binaries run `main` like a Rust program, whatever kind of script they are
+        let mut options = crate::transpile_options(transpile, &file);
+        options.kind = kind.or(options.kind);
+        options.script = kind.is_none() || options.script;
+        options.roblox_api = roblox_api.clone();
+        options.crates = own_modules(
+            &target_file.tree,
+            &target_file.module,
+            is_init,
+            &member.out_dir,
+            &require_from,
+            transpile.target,
+        );
+        options
+            .crates
+            .extend(dependencies.iter().map(|(name, dependency)| {
+                crate_modules(
+                    name,
+                    dependency,
+                    &require_from,
+                    transpile.target,
+                    require_style,
+                    &CfgSet::from_specs(&transpile.cfg),
+                )
+            }));
+
+        if let Some(reports) = reports.as_deref_mut() {
+            reports.push(report::report(&file, &contents, options.clone()));
+        }
+
+        let verify_options = args.verify_deterministic.then(|| options.clone());
+        let transpiled = match roblox_rs::transpile_source(&file, &contents, options) {
+            Ok(transpiled) => transpiled,
+            Err(error) if transpile.message_format == MessageFormat::Json => {
+                eprintln!(
+                    "{}",
+                    diagnostics::error_json(&format!("{}: {}", file, error))
+                );
+                succeeded = false;
+                continue;
+            }
+            Err(error) => {
+                eprintln!("error: {}\n  --> {}", error, file);
+                succeeded = false;
+                continue;
+            }
+        };
+
+        for message in &transpiled.messages {
+            eprintln!("{}", message);
+        }
+
+        if let Some(command) = &args.typecheck {
+            let (messages, failed) =
+                crate::typecheck::typecheck(command, &file, &transpiled, transpile.message_format);
+            for message in &messages {
+                eprintln!("{}", message);
+            }
+            if failed {
+                succeeded = false;
+                continue;
+            }
+        }
+
+        if let Some(options) = verify_options {
+            if let Some(difference) = crate::nondeterminism(&file, &contents, options, &transpiled)
+            {
+                match transpile.message_format {
+                    MessageFormat::Human => eprintln!("error: {}", difference),
+                    MessageFormat::Json => eprintln!("{}", diagnostics::error_json(&difference)),
+                }
+                succeeded = false;
+                continue;
+            }
+        }
+
+        if !write {
+            continue;
+        }
+
+        // Binaries are named after their script kind so Rojo creates the right script
+        let target = match target {
+            Target::Module(path) => member.out_dir.join(path),
+            Target::Script(name) => {
+                let kind = transpiled.kind.unwrap_or(ScriptKind::Script);
+                let script = format!("{}{}", name, kind.suffix());
+                if transpiled.actor {
+                    let actor = member.out_dir.join(name);
+                    fs::create_dir_all(&actor).expect("Failed to create output directory");
+                    write_if_changed(&actor.join("init.meta.json"), roblox_rs::ACTOR_META);
+                    actor.join(script)
+                } else {
+                    member.out_dir.join(script)
+                }
+            }
+            Target::ScriptFolder { name, actor } => {
+                let kind = transpiled.kind.unwrap_or(ScriptKind::Script);
+                let folder = member.out_dir.join(name);
+                fs::create_dir_all(&folder).expect("Failed to create output directory");
+                if *actor {
+                    write_if_changed(&folder.join("init.meta.json"), roblox_rs::ACTOR_META);
+                    folder.join(format!("{}{}", name, kind.suffix()))
+                } else {
+                    folder.join(format!("init{}", kind.suffix()))
+                }
+            }
+        };
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).expect("Failed to create output directory");
+        }
+        if !args.merge {
+            write_if_changed(&target, &transpiled.output);
+            continue;
+        }
+
+        let base_dir = crate::merge::base_dir(Path::new(&args.manifest_path).parent().unwrap());
+        match crate::merge::write(&target, &base_dir, &transpiled.output) {
+            Ok(true) => eprintln!(
+                "{}",
+                diagnostics::file_note(
+                    "merge",
+                    crate::merge::MERGED,
+                    &target.to_string_lossy(),
+                    transpile.message_format
+                )
+            ),
+            Ok(false) => {}
+            Err(error) => {
+                match transpile.message_format {
+                    MessageFormat::Human => eprintln!("error: {}", error),
+                    MessageFormat::Json => eprintln!("{}", diagnostics::error_json(&error)),
+                }
+                succeeded = false;
+            }
+        }
+    }
+
+    succeeded
+}
+
+/// A dependency from crates.io (or any other source) whose library is transpiled with the
+/// member that lists it in `transpile-deps`.
+struct TranspiledPackage {
+    /// The name the member's code refers to it by, unless it is only an indirect dependency
+    name: Option<String>,
+    member: Member,
+    features: Vec<String>,
+    /// The other transpiled packages it depends on, by name and index
+    dependencies: Vec<(String, usize)>,
+}
+
+/// Finds the packages named in the member's `transpile-deps` through `cargo metadata`, which
+/// also downloads their sources. Each one is written to `deps/<package>` of the member's
+/// output root, with the features Cargo resolved for it.
+fn transpiled_packages(member: &Member) -> anyhow::Result<Vec<TranspiledPackage>> {
+    let Some(names) = member.metadata.get("transpile-deps") else {
+        return Ok(Vec::new());
+    };
+    let names: Vec<&str> = names
+        .as_array()
+        .and_then(|names| names.iter().map(|name| name.as_str()).collect())
+        .context("transpile-deps must be an array of package names")?;
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let output = std::process::Command::new("cargo")
+        .args(["metadata", "--format-version", "1", "--manifest-path"])
+        .arg(member.dir.join("Cargo.toml"))
+        .output()
+        .context("Failed to run `cargo metadata`")?;
+    if !output.status.success() {
+        bail!(
+            "`cargo metadata` failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    let metadata: serde_json::Value = serde_json::from_slice(&output.stdout)
+        .context("Failed to parse `cargo metadata` output")?;
+
+    let packages = metadata["packages"].as_array().cloned().unwrap_or_default();
+    let nodes = metadata["resolve"]["nodes"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default();
+    let node = |id: &serde_json::Value| nodes.iter().find(|node| &node["id"] == id);
+    // A node's dependencies, by the name the depending crate's code uses
+    let node_deps = |id: &serde_json::Value| -> Vec<(String, serde_json::Value)> {
+        node(id)
+            .and_then(|node| node["deps"].as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|dep| Some((dep["name"].as_str()?.to_string(), dep["pkg"].clone())))
+            .collect()
+    };
+
+    let manifest_path = fs::canonicalize(member.dir.join("Cargo.toml")).ok();
+    let member_id = packages
+        .iter()
+        .find(|package| package["manifest_path"].as_str().map(PathBuf::from) == manifest_path)
+        .map(|package| package["id"].clone())
+        .context("`cargo metadata` doesn't list the member")?;
+
+    let mut ids = Vec::new();
+    for name in &names {
+        let id = packages
+            .iter()
+            .find(|package| package["name"] == *name)
+            .map(|package| package["id"].clone())
+            .with_context(|| format!("`{}` in transpile-deps is not a dependency", name))?;
+        ids.push(id);
+    }
+
+    let direct = node_deps(&member_id);
+    ids.iter()
+        .zip(&names)
+        .map(|(id, name)| {
+            let package = packages
+                .iter()
+                .find(|package| &package["id"] == id)
+                .unwrap();
+            let manifest = PathBuf::from(package["manifest_path"].as_str().unwrap_or_default());
+            let dir = manifest.parent().unwrap().to_path_buf();
+
+            let dependencies = node_deps(id)
+                .into_iter()
+                .filter_map(|(dep_name, dep_id)| {
+                    Some((dep_name, ids.iter().position(|id| *id == dep_id)?))
+                })
+                .collect();
+
+            Ok(TranspiledPackage {
+                name: direct
+                    .iter()
+                    .find(|(_, dep_id)| dep_id == id)
+                    .map(|(dep_name, _)| dep_name.clone()),
+                member: Member {
+                    name: name.to_string(),
+                    manifest: read_manifest(&manifest)?,
+                    dir,
+                    out_dir: member.out_dir.join("deps").join(name),
+                    metadata: toml::Table::new(),
+                    require: None,
+                },
+                features: node(id)
+                    .and_then(|node| node["features"].as_array())
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|feature| feature.as_str().map(str::to_string))
+                    .collect(),
+                dependencies,
+            })
+        })
+        .collect()
+}
+
+enum Target {
+    Module(PathBuf),
+    Script(String),
+    /// A binary with modules of its own, written to a folder of its name with them: as the
+    /// folder's `init` script, or for an Actor next to them
+    ScriptFolder {
+        name: String,
+        actor: bool,
+    },
+}
+
+/// A file to transpile and where it goes.
+struct TargetFile {
+    source: PathBuf,
+    kind: Option<ScriptKind>,
+    target: Target,
+    /// Whether it's a module of the library, rather than of a binary
+    library: bool,
+    /// Its module's path in its crate
+    module: Vec<String>,
+    /// The modules of its crate
+    tree: Rc<ModuleTree>,
+}
+
+pub struct ModuleTree {
+    pub modules: Vec<ModuleFile>,
+    /// Where the crate root's folder is written, relative to the output root
+    pub base: PathBuf,
+}
+
+impl ModuleTree {
+    /// The module at `path`, relative to the output root, without its extension: a folder's
+    /// `init.luau` is the folder itself.
+    fn location(&self, path: &[String]) -> PathBuf {
+        let mut location = self.base.clone();
+        location.extend(path);
+        location
+    }
+
+    /// The indices of the modules the module at `index` `use`s, and of the ones those `use`
+    /// in turn, since a glob import also sees what a module re-exports. A path resolves to
+    /// the deepest module it passes through.
+    pub fn dependencies(&self, index: usize) -> Vec<usize> {
+        let mut found = vec![index];
+        let mut pending = vec![index];
+
+        while let Some(current) = pending.pop() {
+            for used in &self.modules[current].uses {
+                let dependency = self
+                    .modules
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, module)| used.starts_with(&module.path))
+                    .max_by_key(|(_, module)| module.path.len())
+                    .map(|(dependency, _)| dependency);
+
+                if let Some(dependency) =
+                    dependency.filter(|dependency| !found.contains(dependency))
+                {
+                    found.push(dependency);
+                    pending.push(dependency);
+                }
+            }
+        }
+
+        found.remove(0);
+        found.sort_unstable();
+        found
+    }
+}
+
+/// The library's modules, found through the `mod` declarations of `lib.rs`, under `src/` of
+/// the output root.
+fn library_tree(member: &Member, cfg: &CfgSet) -> Result<Option<ModuleTree>, String> {
+    let lib = member
+        .manifest
+        .get("lib")
+        .and_then(|lib| lib.get("path"))
+        .and_then(|path| path.as_str())
+        .unwrap_or("src/lib.rs");
+    let lib = member.dir.join(lib);
+    if !lib.exists() {
+        return Ok(None);
+    }
+
+    Ok(Some(ModuleTree {
+        modules: module_files(&lib, cfg)?,
+        base: PathBuf::from("src"),
+    }))
+}
+
+/// The library's modules, with `lib.rs` as `src/init.luau`, and each binary as a script named
+/// after it, or a folder of its name when it declares modules of its own.
+fn targets(member: &Member, cfg: &CfgSet) -> Result<Vec<TargetFile>, String> {
+    let mut targets = Vec::new();
+
+    if let Some(tree) = library_tree(member, cfg)? {
+        let tree = Rc::new(tree);
+        for module in &tree.modules {
+            targets.push(TargetFile {
+                source: module.source.clone(),
+                kind: Some(ScriptKind::Module),
+                target: Target::Module(tree.base.join(module.luau_path())),
+                library: true,
+                module: module.path.clone(),
+                tree: tree.clone(),
+            });
+        }
+    }
+
+    let mut bins: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let main = member.dir.join("src").join("main.rs");
+    if main.exists() {
+        bins.insert(member.name.clone(), main);
+    }
+
+    if let Ok(entries) = fs::read_dir(member.dir.join("src").join("bin")) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                let name = path.file_stem().unwrap().to_string_lossy().to_string();
+                bins.insert(name, path);
+            }
+        }
+    }
+
+    for bin in member
+        .manifest
+        .get("bin")
+        .and_then(|bins| bins.as_array())
+        .into_iter()
+        .flatten()
+    {
+        let Some(name) = bin.get("name").and_then(|name| name.as_str()) else {
+            continue;
+        };
+        let path = match bin.get("path").and_then(|path| path.as_str()) {
+            Some(path) => member.dir.join(path),
+            None => member
+                .dir
+                .join("src")
+                .join("bin")
+                .join(format!("{}.rs", name)),
+        };
+        bins.insert(name.to_string(), path);
+    }
+
+    for (name, path) in bins {
+        let tree = Rc::new(ModuleTree {
+            modules: module_files(&path, cfg)?,
+            base: PathBuf::from(&name),
+        });
+        let target = if tree.modules[0].children.is_empty() {
+            Target::Script(name)
+        } else {
+            Target::ScriptFolder {
+                name,
+                actor: declares_actor(&path),
+            }
+        };
+
+        targets.push(TargetFile {
+            source: path,
+            kind: None,
+            target,
+            library: false,
+            module: Vec::new(),
+            tree: tree.clone(),
+        });
+        for module in &tree.modules[1..] {
+            targets.push(TargetFile {
+                source: module.source.clone(),
+                kind: Some(ScriptKind::Module),
+                target: Target::Module(tree.base.join(module.luau_path())),
+                library: false,
+                module: module.path.clone(),
+                tree: tree.clone(),
+            });
+        }
+    }
+
+    Ok(targets)
+}
+
+/// Whether the file is marked `#![actor]`, which parents its script to an Actor.
+fn declares_actor(source: &Path) -> bool {
+    fs::read_to_string(source)
+        .ok()
+        .and_then(|contents| syn::parse_file(&contents).ok())
+        .is_some_and(|file| file.attrs.iter().any(|attr| attr.path().is_ident("actor")))
+}
+
+/// The crate's own modules as `require`d from the file of `module`, for paths starting with
+/// `crate`, `self`, `super` or a module the file declares. Each name is a [`CrateModules`]
+/// of the modules under the one it names, other than the file itself; `from` is where its
+/// relative requires start, and a Roblox `init.luau` requires its own children through `@self`.
+pub fn own_modules(
+    tree: &ModuleTree,
+    module: &[String],
+    is_init: bool,
+    out_dir: &Path,
+    from: &Path,
+    target: LuauTarget,
+) -> Vec<CrateModules> {
+    let own_folder =
+        (is_init && target == LuauTarget::Roblox).then(|| out_dir.join(tree.location(module)));
+
+    let under = |name: &str, prefix: &[String]| CrateModules {
+        name: name.to_string(),
+        exports: tree
+            .modules
+            .iter()
+            .filter(|other| other.path.starts_with(prefix) && other.path != module)
+            .map(|other| (other.path[prefix.len()..].to_vec(), other.exports.clone()))
+            .collect(),
+        modules: tree
+            .modules
+            .iter()
+            .filter(|other| other.path.starts_with(prefix) && other.path != module)
+            .map(|other| {
+                let location = out_dir.join(tree.location(&other.path));
+                let require = match own_folder
+                    .as_ref()
+                    .and_then(|folder| location.strip_prefix(folder).ok())
+                {
+                    Some(child) => format!(
+                        "@self/{}",
+                        child
+                            .to_string_lossy()
+                            .replace(std::path::MAIN_SEPARATOR, "/")
+                    ),
+                    None => relative_require(from, &location),
+                };
+                (other.path[prefix.len()..].to_vec(), require)
+            })
+            .collect(),
+    };
+
+    let mut crates = vec![under("crate", &[]), under("self", module)];
+    if let Some((_, parent)) = module.split_last() {
+        crates.push(under("super", parent));
+    }
+
+    let declared = tree
+        .modules
+        .iter()
+        .find(|other| other.path == module)
+        .map(|other| other.children.clone())
+        .unwrap_or_default();
+    for child in declared {
+        let mut path = module.to_vec();
+        path.push(child.clone());
+        crates.push(under(&child, &path));
+    }
+
+    crates.retain(|krate| !krate.modules.is_empty());
+    crates
+}
+
+/// The workspace members a member depends on, by the name its code refers to them with.
+fn workspace_dependencies<'a>(member: &Member, members: &'a [Member]) -> Vec<(String, &'a Member)> {
+    member
+        .manifest
+        .get("dependencies")
+        .and_then(|dependencies| dependencies.as_table())
+        .into_iter()
+        .flatten()
+        .filter_map(|(key, dependency)| {
+            let package = dependency
+                .get("package")
+                .and_then(|package| package.as_str())
+                .unwrap_or(key);
+            let dependency = members.iter().find(|member| member.name == package)?;
+            Some((key.replace('-', "_"), dependency))
+        })
+        .collect()
+}
+
+/// The `require` path of each library module of a dependency, as seen from a file whose
+/// string requires start from `from`. Lune requires files relative to the requiring one,
+/// as does Roblox with `--require-style string`; otherwise the library is at its `require`
+/// metadata, or `ReplicatedStorage` under the package's name.
+fn crate_modules(
+    name: &str,
+    dependency: &Member,
+    from: &Path,
+    target: LuauTarget,
+    style: RequireStyle,
+    cfg: &CfgSet,
+) -> CrateModules {
+    let root = dependency.require.clone().unwrap_or_else(|| {
+        // Package names like `math-x` aren't Luau identifiers
+        let child = if dependency.name.contains('-') {
+            format!("[\"{}\"]", dependency.name)
+        } else {
+            format!(".{}", dependency.name)
+        };
+        format!("game:GetService(\"ReplicatedStorage\"){}", child)
+    });
+
+    // Its own build reports a module it can't find
+    let tree = library_tree(dependency, cfg).ok().flatten();
+    let modules = tree
+        .iter()
+        .flat_map(|tree| tree.modules.iter().map(move |module| (tree, module)))
+        .map(|(tree, module)| {
+            let location = dependency.out_dir.join(tree.location(&module.path));
+            let module = module.path.clone();
+
+            let relative = target == LuauTarget::Lune
+                || (style == RequireStyle::String && dependency.require.is_none());
+            let require = if relative {
+                relative_require(from, &location)
+            } else {
+                std::iter::once(root.clone())
+                    .chain(module.iter().cloned())
+                    .collect::<Vec<_>>()
+                    .join(".")
+            };
+
+            (module, require)
+        })
+        .collect();
+
+    let exports = tree
+        .iter()
+        .flat_map(|tree| &tree.modules)
+        .map(|module| (module.path.clone(), module.exports.clone()))
+        .collect();
+
+    CrateModules {
+        name: name.to_string(),
+        modules,
+        exports,
+    }
+}
+
+/// A `./` or `../` path from `from` to `to`, as string `require`s take them.
+fn relative_require(from: &Path, to: &Path) -> String {
+    let from: Vec<_> = from.components().filter(|c| c.as_os_str() != ".").collect();
+    let to: Vec<_> = to.components().filter(|c| c.as_os_str() != ".").collect();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    if parts.is_empty() {
+        parts.push(".".to_string());
+    }
+    parts.extend(
+        to[common..]
+            .iter()
+            .map(|component| component.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+/// The crates of the manifest's workspace, or just its package, with their output roots.
+fn members(args: &ProjectArgs) -> anyhow::Result<Vec<Member>> {
+    let manifest_path = Path::new(&args.manifest_path);
+    let root_dir = manifest_path
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let root = read_manifest(manifest_path)?;
+
+    let workspace = root
+        .get("workspace")
+        .and_then(|workspace| workspace.as_table());
+    let workspace_metadata = workspace.and_then(roblox_metadata).unwrap_or_default();
+
+    let mut dirs = Vec::new();
+    if root.contains_key("package") {
+        dirs.push(root_dir.to_path_buf());
+    }
+
+    if let Some(workspace) = workspace {
+        let patterns = |key: &str| -> Vec<String> {
+            workspace
+                .get(key)
+                .and_then(|patterns| patterns.as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|pattern| pattern.as_str().map(str::to_string))
+                .collect()
+        };
+
+        let excluded: BTreeSet<PathBuf> = patterns("exclude")
+            .iter()
+            .map(|pattern| root_dir.join(pattern))
+            .collect();
+
+        for pattern in patterns("members") {
+            for dir in expand_member(root_dir, &pattern) {
+                if !excluded.contains(&dir) && !dirs.contains(&dir) {
+                    dirs.push(dir);
+                }
+            }
+        }
+    }
+
+    let is_workspace = workspace.is_some();
+    let out_base = match (&args.out_dir, workspace_metadata.get("out-dir")) {
+        (Some(out_dir), _) => PathBuf::from(out_dir),
+        (None, Some(out_dir)) => {
+            root_dir.join(out_dir.as_str().context("out-dir must be a string")?)
+        }
+        (None, None) => root_dir.join("out"),
+    };
+
+    let members: Vec<Member> = dirs
+        .into_iter()
+        .map(|dir| {
+            let manifest = if dir == root_dir {
+                root.clone()
+            } else {
+                read_manifest(&dir.join("Cargo.toml"))?
+            };
+
+            let package = manifest
+                .get("package")
+                .and_then(|package| package.as_table())
+                .with_context(|| format!("{} has no [package] table", dir.display()))?;
+            let name = package
+                .get("name")
+                .and_then(|name| name.as_str())
+                .with_context(|| format!("{} is missing package.name", dir.display()))?
+                .to_string();
+
+            let mut metadata = workspace_metadata.clone();
+            metadata.extend(roblox_metadata(package).unwrap_or_default());
+
+            // A member's own `out-dir` is its output root; otherwise each member of a
+            // workspace gets a folder of its own
+            let out_dir = match roblox_metadata(package)
+                .and_then(|metadata| metadata.get("out-dir").cloned())
+                .filter(|_| args.out_dir.is_none())
+            {
+                Some(out_dir) => dir.join(out_dir.as_str().context("out-dir must be a string")?),
+                None if is_workspace => out_base.join(&name),
+                None => out_base.clone(),
+            };
+
+            let require = roblox_metadata(package)
+                .and_then(|metadata| metadata.get("require").cloned())
+                .map(|require| {
+                    require
+                        .as_str()
+                        .map(str::to_string)
+                        .context("require must be a string")
+                })
+                .transpose()?;
+
+            Ok(Member {
+                name,
+                dir,
+                manifest,
+                out_dir,
+                metadata,
+                require,
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    for package in &args.package {
+        if !members.iter().any(|member| &member.name == package) {
+            bail!("No workspace member named `{}`", package);
+        }
+    }
+
+    Ok(members)
+}
+
+fn expand_member(root_dir: &Path, pattern: &str) -> Vec<PathBuf> {
+    let Some(parent) = pattern.strip_suffix("/*") else {
+        return vec![root_dir.join(pattern)];
+    };
+
+    let mut dirs: Vec<PathBuf> = fs::read_dir(root_dir.join(parent))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|path| path.join("Cargo.toml").exists())
+                .collect()
+        })
+        .unwrap_or_default();
+    dirs.sort();
+    dirs
+}
+
+fn read_manifest(path: &Path) -> anyhow::Result<toml::Table> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    contents
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn roblox_metadata(table: &toml::Table) -> Option<toml::Table> {
+    table.get("metadata")?.get("roblox-rs")?.as_table().cloned()
+}
+
+/// The requested features and the default one, with the features they enable in turn.
+fn enabled_features(args: &ProjectArgs, member: &Member) -> BTreeSet<String> {
+    let features = member
+        .manifest
+        .get("features")
+        .and_then(|features| features.as_table())
+        .cloned()
+        .unwrap_or_default();
+
+    let mut pending: Vec<String> = if args.all_features {
+        features.keys().cloned().collect()
+    } else {
+        args.features
+            .iter()
+            .filter_map(|feature| match feature.split_once('/') {
+                Some((package, feature)) => (package == member.name).then(|| feature.to_string()),
+                None => Some(feature.clone()),
+            })
+            .collect()
+    };
+    if !args.no_default_features && features.contains_key("default") {
+        pending.push("default".to_string());
+    }
+
+    let mut enabled = BTreeSet::new();
+    while let Some(feature) = pending.pop() {
+        if !enabled.insert(feature.clone()) {
+            continue;
+        }
+
+        // `dep:` and `crate/feature` entries only affect dependencies
+        for implied in features
+            .get(&feature)
+            .and_then(|implied| implied.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|implied| implied.as_str())
+            .filter(|implied| !implied.contains([':', '/']))
+        {
+            pending.push(implied.to_string());
+        }
+    }
+
+    enabled
+}
+
+/// Fills in the transpile options set in `[package.metadata.roblox-rs]` (or the workspace's
+/// metadata) that weren't given on the command line. `cfg` flags from both are combined.
+fn apply_metadata(
+    args: &mut TranspileArgs,
+    metadata: &toml::Table,
+    matches: &ArgMatches,
+) -> anyhow::Result<()> {
+    for (key, value) in metadata {
+        let id = key.replace('-', "_");
+        let invalid = || {
+            anyhow!(
+                "Invalid value for `{}` in [package.metadata.roblox-rs]",
+                key
+            )
+        };
+
+        if matches!(id.as_str(), "out_dir" | "require" | "transpile_deps") {
+            continue;
+        }
+
+        if id == "cfg" {
+            let specs = value.as_array().ok_or_else(invalid)?;
+            for spec in specs {
+                args.cfg
+                    .push(spec.as_str().ok_or_else(invalid)?.to_string());
+            }
+            continue;
+        }
+
+        let known = matches!(
+            id.as_str(),
+            "strip_comments"
+                | "moonwave"
+                | "annotate_const"
+                | "profile"
+                | "header"
+                | "no_timestamp"
+                | "regions"
+                | "monomorphize"
+                | "roblox_ts"
+                | "target"
+                | "require_style"
+                | "int_semantics"
+                | "trait_dispatch"
+                | "format"
+                | "opt_level"
+                | "opt"
+                | "lenient"
+                | "bindings"
+                | "react"
+                | "logger"
+                | "env_args"
+        );
+        if !known {
+            bail!("Unknown key `{}` in [package.metadata.roblox-rs]", key);
+        }
+        if matches.value_source(&id) == Some(ValueSource::CommandLine) {
+            continue;
+        }
+
+        let flag = || value.as_bool().ok_or_else(invalid);
+        let text = || value.as_str().map(str::to_string).ok_or_else(invalid);
+        fn value_enum<T: ValueEnum>(name: &str) -> Option<T> {
+            T::from_str(name, true).ok()
+        }
+
+        match id.as_str() {
+            "strip_comments" => args.strip_comments = flag()?,
+            "moonwave" => args.moonwave = flag()?,
+            "annotate_const" => args.annotate_const = flag()?,
+            "profile" => args.profile = flag()?,
+            "header" => args.header = flag()?,
+            "no_timestamp" => args.no_timestamp = flag()?,
+            "regions" => args.regions = flag()?,
+            "monomorphize" => args.monomorphize = flag()?,
+            "roblox_ts" => args.roblox_ts = flag()?,
+            "target" => args.target = value_enum(&text()?).ok_or_else(invalid)?,
+            "require_style" => args.require_style = Some(value_enum(&text()?).ok_or_else(invalid)?),
+            "int_semantics" => args.int_semantics = value_enum(&text()?).ok_or_else(invalid)?,
+            "trait_dispatch" => args.trait_dispatch = value_enum(&text()?).ok_or_else(invalid)?,
+            "format" => args.format = Some(value_enum(&text()?).ok_or_else(invalid)?),
+            "opt_level" => {
+                args.opt_level = value
+                    .as_integer()
+                    .filter(|level| (0..=2).contains(level))
+                    .ok_or_else(invalid)? as u8
+            }
+            "opt" => {
+                args.opt = value
+                    .as_array()
+                    .ok_or_else(invalid)?
+                    .iter()
+                    .map(|name| name.as_str().and_then(value_enum).ok_or_else(invalid))
+                    .collect::<anyhow::Result<_>>()?
+            }
+            "lenient" => args.lenient = Some(value_enum(&text()?).ok_or_else(invalid)?),
+            "bindings" => args.bindings = Some(text()?),
+            "react" => args.react = Some(text()?),
+            "logger" => args.logger = Some(text()?),
+            _ => args.env_args = Some(text()?),
+        }
+    }
+
+    Ok(())
+}
+
+fn report_error(error: &str, format: MessageFormat) {
+    match format {
+        MessageFormat::Human => eprintln!("error: {}", error),
+        MessageFormat::Json => eprintln!("{}", diagnostics::error_json(error)),
+    }
+}
+
+fn source_stamps(args: &ProjectArgs) -> BTreeMap<PathBuf, SystemTime> {
+    let mut stamps = BTreeMap::new();
+    let mut stamp = |path: &Path| {
+        if let Ok(modified) = fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            stamps.insert(path.to_path_buf(), modified);
+        }
+    };
+
+    stamp(Path::new(&args.manifest_path));
+    // Every member, since changes to a dependency change what its dependents require
+    for member in members(args).unwrap_or_default() {
+        stamp(&member.dir.join("Cargo.toml"));
+        let cfg = CfgSet::from_specs(&args.transpile.cfg);
+        for file in targets(&member, &cfg).unwrap_or_default() {
+            stamp(&file.source);
+        }
+    }
+
+    stamps
+}
@@ -0,0 +1,410 @@
+use crate::diagnostics::Diagnostic;
+use proc_macro2::{Group, TokenStream, TokenTree};
+use std::collections::{HashMap, HashSet};
+use syn::visit::Visit;
+use syn::visit_mut::VisitMut;
+
+/// Luau keywords that are plain identifiers in Rust. `do` is reserved in Rust too, so it
+/// can't name anything.
+pub const LUAU_KEYWORDS: &[&str] = &[
+    "and", "elseif", "end", "function", "local", "nil", "not", "or", "repeat", "then", "until",
+];
+
+/// Globals a local or function of the same name would shadow for the rest of the script.
+const LUAU_GLOBALS: &[&str] = &[
+    "game",
+    "script",
+    "workspace",
+    "plugin",
+    "shared",
+    "print",
+    "warn",
+    "error",
+    "assert",
+    "wait",
+    "delay",
+    "spawn",
+    "tick",
+    "time",
+    "typeof",
+    "tostring",
+    "tonumber",
+    "pairs",
+    "ipairs",
+    "next",
+    "select",
+    "unpack",
+    "require",
+    "pcall",
+    "xpcall",
+    "setmetatable",
+    "getmetatable",
+    "rawget",
+    "rawset",
+    "rawequal",
+    "rawlen",
+    "table",
+    "string",
+    "math",
+    "task",
+    "coroutine",
+    "buffer",
+    "utf8",
+    "bit32",
+    "os",
+    "debug",
+];
+
+pub struct Renames {
+    pub diagnostics: Vec<Diagnostic>,
+    /// The public functions renamed from a Luau keyword, by their new name, which are still
+    /// exported under the old one, like `["end"] = end_`
+    pub exports: HashMap<String, String>,
+}
+
+/// Renames locals, parameters and functions whose names are Luau keywords or shadow Luau
+/// globals, like `let game = ...` hiding the data model, reporting a warning for each.
+pub fn rename_reserved(file: &mut syn::File) -> Renames {
+    let mut idents = IdentCollector::default();
+    idents.visit_file(file);
+
+    let mut renamer = Renamer {
+        taken: idents.idents,
+        scopes: vec![HashMap::new()],
+        modules: 0,
+        diagnostics: Vec::new(),
+    };
+    let mut exports = HashMap::new();
+
+    // Public functions keep their names since other modules index the exports by them, but
+    // one named after a keyword can't be a local, so only its export keeps the name
+    for item in &mut file.items {
+        if let syn::Item::Fn(item_fn) = item {
+            let name = item_fn.sig.ident.to_string();
+            if !matches!(item_fn.vis, syn::Visibility::Public(_)) {
+                renamer.bind(&mut item_fn.sig.ident);
+            } else if is_luau_keyword(&name) {
+                renamer.bind(&mut item_fn.sig.ident);
+                exports.insert(item_fn.sig.ident.to_string(), name);
+            }
+        }
+    }
+
+    renamer.visit_file_mut(file);
+    Renames {
+        diagnostics: renamer.diagnostics,
+        exports,
+    }
+}
+
+/// Whether `name` is a Luau keyword that's also a valid Rust identifier, so it can't be
+/// written as a field or method name either.
+pub fn is_luau_keyword(name: &str) -> bool {
+    LUAU_KEYWORDS.contains(&name)
+}
+
+fn is_reserved(name: &str) -> bool {
+    is_luau_keyword(name) || LUAU_GLOBALS.contains(&name)
+}
+
+struct Renamer {
+    taken: HashSet<String>,
+    scopes: Vec<HashMap<String, String>>,
+    /// How many inline modules deep the items being visited are
+    modules: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Renamer {
+    fn bind(&mut self, ident: &mut syn::Ident) {
+        let name = ident.to_string();
+        if !is_reserved(&name) {
+            // A new binding of an ordinary name ends any renamed one it shadows
+            if let Some(scope) = self.scopes.last_mut() {
+                scope.remove(&name);
+            }
+            return;
+        }
+
+        let mut renamed = format!("{}_", name);
+        let mut suffix = 1;
+        while !self.taken.insert(renamed.clone()) {
+            suffix += 1;
+            renamed = format!("{}_{}", name, suffix);
+        }
+
+        let kind = if is_luau_keyword(&name) {
+            "is a Luau keyword"
+        } else {
+            "shadows the Luau global of the same name"
+        };
+
+        self.diagnostics.push(Diagnostic::warning(
+            "renamed-identifier",
+            format!(
+                "`{}` {}; renamed to `{}` in the output",
+                name, kind, renamed
+            ),
+            ident.span(),
+        ));
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name, renamed.clone());
+        }
+
+        *ident = syn::Ident::new(&renamed, ident.span());
+    }
+
+    fn lookup(&self, name: &str) -> Option<&String> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Whether `path` names an item at the top of the file, like `crate::end` or, from an
+    /// inline module, `super::end`.
+    fn is_file_item(&self, path: &syn::Path) -> bool {
+        let prefix: Vec<String> = path
+            .segments
+            .iter()
+            .rev()
+            .skip(1)
+            .map(|segment| segment.ident.to_string())
+            .collect();
+
+        match prefix.as_slice() {
+            [] => false,
+            [first] if first == "crate" || (first == "self" && self.modules == 0) => true,
+            supers => supers.len() == self.modules && supers.iter().all(|name| name == "super"),
+        }
+    }
+
+    fn scoped(&mut self, f: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        f(self);
+        self.scopes.pop();
+    }
+
+    /// Renames the variables a macro's arguments use, skipping fields, methods, paths and
+    /// named format arguments, and the variables its format string captures inline.
+    fn rename_tokens(&self, tokens: TokenStream, format_string: &mut bool) -> TokenStream {
+        let tokens: Vec<TokenTree> = tokens.into_iter().collect();
+
+        (0..tokens.len())
+            .map(|index| match &tokens[index] {
+                TokenTree::Ident(ident) => {
+                    let punct = |index: Option<usize>, ch: char| {
+                        index.and_then(|index| tokens.get(index)).is_some_and(
+                            |token| matches!(token, TokenTree::Punct(punct) if punct.as_char() == ch),
+                        )
+                    };
+                    // `x.end` and `a::end`, but not a field's value in `Point { on: end }`
+                    let member = punct(index.checked_sub(1), '.')
+                        || (punct(index.checked_sub(1), ':') && punct(index.checked_sub(2), ':'));
+                    let named = punct(Some(index + 1), '=') && !punct(Some(index + 2), '=');
+                    let path = punct(Some(index + 1), ':');
+
+                    match self.lookup(&ident.to_string()) {
+                        Some(renamed) if !member && !named && !path => {
+                            TokenTree::Ident(syn::Ident::new(renamed, ident.span()))
+                        }
+                        _ => tokens[index].clone(),
+                    }
+                }
+
+                TokenTree::Group(group) => {
+                    let stream = self.rename_tokens(group.stream(), &mut false);
+                    let mut renamed = Group::new(group.delimiter(), stream);
+                    renamed.set_span(group.span());
+                    TokenTree::Group(renamed)
+                }
+
+                TokenTree::Literal(literal) if *format_string => {
+                    match syn::Lit::new(literal.clone()) {
+                        syn::Lit::Str(lit) => {
+                            *format_string = false;
+                            let value = self.rename_captures(&lit.value());
+                            let renamed = syn::LitStr::new(&value, lit.span());
+                            TokenTree::Literal(renamed.token())
+                        }
+                        _ => tokens[index].clone(),
+                    }
+                }
+
+                token => token.clone(),
+            })
+            .collect()
+    }
+
+    /// Renames the `{name}` and `{name:?}` arguments of a format string.
+    fn rename_captures(&self, format: &str) -> String {
+        let mut renamed = String::with_capacity(format.len());
+        let mut rest = format;
+
+        while let Some(open) = rest.find(['{', '}']) {
+            renamed.push_str(&rest[..open]);
+            rest = &rest[open..];
+
+            if rest.starts_with("{{") || rest.starts_with("}}") {
+                renamed.push_str(&rest[..2]);
+                rest = &rest[2..];
+                continue;
+            }
+            if rest.starts_with('}') {
+                renamed.push('}');
+                rest = &rest[1..];
+                continue;
+            }
+
+            let end = rest.find(['}', ':']).unwrap_or(rest.len());
+            let name = &rest[1..end];
+            renamed.push('{');
+            renamed.push_str(self.lookup(name).map_or(name, String::as_str));
+            rest = &rest[end..];
+        }
+
+        renamed.push_str(rest);
+        renamed
+    }
+}
+
+impl VisitMut for Renamer {
+    fn visit_item_fn_mut(&mut self, i: &mut syn::ItemFn) {
+        self.scoped(|renamer| {
+            for input in &mut i.sig.inputs {
+                renamer.visit_fn_arg_mut(input);
+            }
+            renamer.visit_block_mut(&mut i.block);
+        });
+    }
+
+    fn visit_impl_item_fn_mut(&mut self, i: &mut syn::ImplItemFn) {
+        self.scoped(|renamer| {
+            for input in &mut i.sig.inputs {
+                renamer.visit_fn_arg_mut(input);
+            }
+            renamer.visit_block_mut(&mut i.block);
+        });
+    }
+
+    fn visit_trait_item_fn_mut(&mut self, i: &mut syn::TraitItemFn) {
+        self.scoped(|renamer| {
+            for input in &mut i.sig.inputs {
+                renamer.visit_fn_arg_mut(input);
+            }
+            if let Some(block) = &mut i.default {
+                renamer.visit_block_mut(block);
+            }
+        });
+    }
+
+    fn visit_block_mut(&mut self, i: &mut syn::Block) {
+        self.scoped(|renamer| syn::visit_mut::visit_block_mut(renamer, i));
+    }
+
+    fn visit_local_mut(&mut self, i: &mut syn::Local) {
+        if let Some(init) = &mut i.init {
+            self.visit_expr_mut(&mut init.expr);
+            if let Some((_, diverge)) = &mut init.diverge {
+                self.visit_expr_mut(diverge);
+            }
+        }
+
+        self.visit_pat_mut(&mut i.pat);
+    }
+
+    fn visit_expr_closure_mut(&mut self, i: &mut syn::ExprClosure) {
+        self.scoped(|renamer| {
+            for input in &mut i.inputs {
+                renamer.visit_pat_mut(input);
+            }
+            renamer.visit_expr_mut(&mut i.body);
+        });
+    }
+
+    fn visit_arm_mut(&mut self, i: &mut syn::Arm) {
+        self.scoped(|renamer| syn::visit_mut::visit_arm_mut(renamer, i));
+    }
+
+    fn visit_expr_for_loop_mut(&mut self, i: &mut syn::ExprForLoop) {
+        self.visit_expr_mut(&mut i.expr);
+        self.scoped(|renamer| {
+            renamer.visit_pat_mut(&mut i.pat);
+            renamer.visit_block_mut(&mut i.body);
+        });
+    }
+
+    fn visit_expr_if_mut(&mut self, i: &mut syn::ExprIf) {
+        self.scoped(|renamer| {
+            renamer.visit_expr_mut(&mut i.cond);
+            renamer.visit_block_mut(&mut i.then_branch);
+        });
+
+        if let Some((_, else_branch)) = &mut i.else_branch {
+            self.visit_expr_mut(else_branch);
+        }
+    }
+
+    fn visit_expr_while_mut(&mut self, i: &mut syn::ExprWhile) {
+        self.scoped(|renamer| {
+            renamer.visit_expr_mut(&mut i.cond);
+            renamer.visit_block_mut(&mut i.body);
+        });
+    }
+
+    fn visit_pat_ident_mut(&mut self, i: &mut syn::PatIdent) {
+        if let Some((_, subpat)) = &mut i.subpat {
+            self.visit_pat_mut(subpat);
+        }
+
+        self.bind(&mut i.ident);
+    }
+
+    fn visit_macro_mut(&mut self, i: &mut syn::Macro) {
+        if i.path.is_ident("luau") {
+            return;
+        }
+
+        let tokens = std::mem::take(&mut i.tokens);
+        i.tokens = self.rename_tokens(tokens, &mut true);
+    }
+
+    fn visit_item_mod_mut(&mut self, i: &mut syn::ItemMod) {
+        self.modules += 1;
+        syn::visit_mut::visit_item_mod_mut(self, i);
+        self.modules -= 1;
+    }
+
+    fn visit_expr_path_mut(&mut self, i: &mut syn::ExprPath) {
+        if i.qself.is_some() {
+            return;
+        }
+
+        // Functions at the top of the file are renamed in its outermost scope
+        if i.path.segments.len() > 1 && self.is_file_item(&i.path) {
+            let segment = i.path.segments.last_mut().unwrap();
+            if let Some(renamed) = self.scopes[0].get(&segment.ident.to_string()) {
+                segment.ident = syn::Ident::new(renamed, segment.ident.span());
+            }
+            return;
+        }
+
+        if i.path.segments.len() != 1 {
+            return;
+        }
+
+        let segment = &mut i.path.segments[0];
+        if let Some(renamed) = self.lookup(&segment.ident.to_string()) {
+            segment.ident = syn::Ident::new(renamed, segment.ident.span());
+        }
+    }
+}
+
+#[derive(Default)]
+struct IdentCollector {
+    idents: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for IdentCollector {
+    fn visit_ident(&mut self, i: &'ast proc_macro2::Ident) {
+        self.idents.insert(i.to_string());
+    }
+}
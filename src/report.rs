@@ -0,0 +1,425 @@
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::options::{TranspilerOptions, Unsupported};
+use std::collections::{BTreeMap, HashMap};
+use syn::spanned::Spanned;
+use syn::visit::Visit;
+
+/// Which constructs a file uses and how each of their uses lowered.
+pub struct FileReport {
+    pub file: String,
+    /// Each kind of construct with how many times it's used, how many of those uses degraded and
+    /// how many failed
+    pub constructs: Vec<(String, usize, usize, usize)>,
+    pub problems: Vec<Problem>,
+    /// Why the file couldn't be transpiled at all, such as a parse error
+    pub error: Option<String>,
+}
+
+pub struct Problem {
+    pub line: usize,
+    pub column: usize,
+    pub construct: String,
+    pub failed: bool,
+    pub message: String,
+}
+
+/// Transpiles `source` to find how each construct in it lowers. Transpilation goes on past
+/// failures so they are all reported; with `--lenient` they count as degraded, like any
+/// other stub.
+pub fn report(file: &str, source: &str, mut options: TranspilerOptions) -> FileReport {
+    let aborts = options.unsupported == Unsupported::Abort;
+    if aborts {
+        options.unsupported = Unsupported::Skip;
+    }
+
+    let mut report = FileReport {
+        file: file.to_string(),
+        constructs: Vec::new(),
+        problems: Vec::new(),
+        error: None,
+    };
+
+    let Ok(syntax_tree) = syn::parse_file(source) else {
+        report.error = crate::transpile_source(file, source, options).err();
+        return report;
+    };
+
+    let mut constructs = Constructs::default();
+    constructs.visit_file(&syntax_tree);
+
+    let diagnostics = match crate::transpile_source(file, source, options) {
+        Ok(transpiled) => transpiled.diagnostics,
+        Err(error) => {
+            report.error = Some(error);
+            Vec::new()
+        }
+    };
+
+    // Each use is lowered, degraded or failed, however many diagnostics it has
+    let mut states = vec![State::Lowered; constructs.uses.len()];
+    for diagnostic in &diagnostics {
+        let Some(failed) = outcome(diagnostic, aborts) else {
+            continue;
+        };
+
+        // When it would have aborted, what skipping it did doesn't matter
+        let message = match diagnostic.message.rsplit_once("; ") {
+            Some((message, _)) if aborts => message.to_string(),
+            _ => diagnostic.message.clone(),
+        };
+
+        // A problem outside every construct counts as a use of the item it's in
+        let index = match constructs.innermost(diagnostic) {
+            Some(index) => index,
+            None => {
+                constructs.push("item", diagnostic.bytes.clone());
+                states.resize(constructs.uses.len(), State::Lowered);
+                constructs.spans[&diagnostic.bytes]
+            }
+        };
+        let kind = constructs.uses[index].0.clone();
+        let state = if failed {
+            State::Failed
+        } else {
+            State::Degraded
+        };
+        states[index] = states[index].max(state);
+
+        report.problems.push(Problem {
+            line: diagnostic.line,
+            column: diagnostic.column,
+            construct: kind,
+            failed,
+            message,
+        });
+    }
+
+    let mut outcomes: BTreeMap<&str, (usize, usize, usize)> = BTreeMap::new();
+    for ((kind, _), state) in constructs.uses.iter().zip(&states) {
+        let counts = outcomes.entry(kind.as_str()).or_default();
+        counts.0 += 1;
+        match state {
+            State::Lowered => {}
+            State::Degraded => counts.1 += 1,
+            State::Failed => counts.2 += 1,
+        }
+    }
+
+    report.constructs = outcomes
+        .into_iter()
+        .map(|(kind, (count, degraded, failed))| (kind.to_string(), count, degraded, failed))
+        .collect();
+    report
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum State {
+    Lowered,
+    Degraded,
+    Failed,
+}
+
+/// Whether a diagnostic reports a construct that failed to lower, or that degraded.
+fn outcome(diagnostic: &Diagnostic, aborts: bool) -> Option<bool> {
+    match diagnostic.code {
+        "unsupported" => Some(aborts || diagnostic.severity == Severity::Error),
+        "any" | "format-spec" => Some(false),
+        _ => None,
+    }
+}
+
+/// The kind and location of every item and expression in a file, once per span.
+#[derive(Default)]
+struct Constructs {
+    uses: Vec<(String, std::ops::Range<usize>)>,
+    spans: HashMap<std::ops::Range<usize>, usize>,
+}
+
+impl Constructs {
+    /// Records a use, where one nested in another with the same span, like a macro item and
+    /// its macro, is the same use of the innermost kind.
+    fn push(&mut self, kind: impl Into<String>, range: std::ops::Range<usize>) {
+        match self.spans.get(&range) {
+            Some(&index) => self.uses[index].0 = kind.into(),
+            None => {
+                self.spans.insert(range.clone(), self.uses.len());
+                self.uses.push((kind.into(), range));
+            }
+        }
+    }
+
+    /// The index of the smallest construct around a diagnostic's location.
+    fn innermost(&self, diagnostic: &Diagnostic) -> Option<usize> {
+        let bytes = &diagnostic.bytes;
+
+        self.uses
+            .iter()
+            .enumerate()
+            .filter(|(_, (_, range))| range.start <= bytes.start && bytes.end <= range.end)
+            .min_by_key(|(_, (_, range))| range.len())
+            .map(|(index, _)| index)
+    }
+}
+
+impl<'ast> Visit<'ast> for Constructs {
+    fn visit_item(&mut self, i: &'ast syn::Item) {
+        let kind = match i {
+            syn::Item::Const(_) => "`const`",
+            syn::Item::Enum(_) => "`enum`",
+            syn::Item::ExternCrate(_) => "`extern crate`",
+            syn::Item::Fn(_) => "`fn`",
+            syn::Item::ForeignMod(_) => "`extern` block",
+            syn::Item::Impl(item) if item.trait_.is_some() => "trait `impl`",
+            syn::Item::Impl(_) => "`impl`",
+            syn::Item::Macro(_) => "macro item",
+            syn::Item::Mod(_) => "`mod`",
+            syn::Item::Static(_) => "`static`",
+            syn::Item::Struct(_) => "`struct`",
+            syn::Item::Trait(_) => "`trait`",
+            syn::Item::Type(_) => "`type` alias",
+            syn::Item::Union(_) => "`union`",
+            syn::Item::Use(_) => "`use`",
+            _ => "item",
+        };
+
+        self.push(kind, i.span().byte_range());
+        syn::visit::visit_item(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.push("method", i.span().byte_range());
+        syn::visit::visit_impl_item_fn(self, i);
+    }
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        let kind = match i {
+            syn::Expr::Array(_) => "array",
+            syn::Expr::Assign(_) => "assignment",
+            syn::Expr::Async(_) => "`async` block",
+            syn::Expr::Await(_) => "`.await`",
+            syn::Expr::Binary(_) => "binary operator",
+            syn::Expr::Block(_) => "block",
+            syn::Expr::Break(_) => "`break`",
+            syn::Expr::Call(_) => "call",
+            syn::Expr::Cast(_) => "`as` cast",
+            syn::Expr::Closure(_) => "closure",
+            syn::Expr::Const(_) => "`const` block",
+            syn::Expr::Continue(_) => "`continue`",
+            syn::Expr::Field(_) => "field access",
+            syn::Expr::ForLoop(_) => "`for` loop",
+            syn::Expr::If(expr_if) if matches!(&*expr_if.cond, syn::Expr::Let(_)) => "`if let`",
+            syn::Expr::If(_) => "`if`",
+            syn::Expr::Index(_) => "indexing",
+            syn::Expr::Let(_) => "`let` condition",
+            syn::Expr::Lit(_) => "literal",
+            syn::Expr::Loop(_) => "`loop`",
+            syn::Expr::Match(_) => "`match`",
+            syn::Expr::MethodCall(_) => "method call",
+            syn::Expr::Paren(_) => "parentheses",
+            syn::Expr::Path(_) => "path",
+            syn::Expr::Range(_) => "range",
+            syn::Expr::Reference(_) => "reference",
+            syn::Expr::Repeat(_) => "array repeat",
+            syn::Expr::Return(_) => "`return`",
+            syn::Expr::Struct(expr) if expr.rest.is_some() => "struct update",
+            syn::Expr::Struct(_) => "struct literal",
+            syn::Expr::Try(_) => "`?`",
+            syn::Expr::TryBlock(_) => "`try` block",
+            syn::Expr::Tuple(_) => "tuple",
+            syn::Expr::Unary(_) => "unary operator",
+            syn::Expr::Unsafe(_) => "`unsafe` block",
+            syn::Expr::While(_) => "`while` loop",
+            syn::Expr::Yield(_) => "`yield`",
+            syn::Expr::Macro(_) => {
+                syn::visit::visit_expr(self, i);
+                return;
+            }
+            _ => "expression",
+        };
+
+        self.push(kind, i.span().byte_range());
+        syn::visit::visit_expr(self, i);
+    }
+
+    fn visit_macro(&mut self, i: &'ast syn::Macro) {
+        let name = i
+            .path
+            .segments
+            .last()
+            .map_or(String::new(), |segment| segment.ident.to_string());
+        self.push(format!("`{}!`", name), i.span().byte_range());
+        syn::visit::visit_macro(self, i);
+    }
+
+    fn visit_local(&mut self, i: &'ast syn::Local) {
+        self.push("`let`", i.span().byte_range());
+        syn::visit::visit_local(self, i);
+    }
+}
+
+/// The reports as Markdown: a summary table of the files, then each file's constructs and
+/// the problems found in it.
+pub fn render_markdown(reports: &[FileReport]) -> String {
+    let mut out = String::from("# roblox-rs coverage report\n\n");
+    out += "| File | Constructs | Lowered | Degraded | Failed |\n";
+    out += "| --- | ---: | ---: | ---: | ---: |\n";
+    for report in reports {
+        let (count, degraded, failed) = totals(report);
+        let status = match &report.error {
+            Some(_) => " (did not transpile)",
+            None => "",
+        };
+        out += &format!(
+            "| `{}`{} | {} | {} | {} | {} |\n",
+            report.file,
+            status,
+            count,
+            count.saturating_sub(degraded + failed),
+            degraded,
+            failed
+        );
+    }
+
+    for report in reports {
+        out += &format!("\n## `{}`\n\n", report.file);
+        if let Some(error) = &report.error {
+            out += &format!("Did not transpile: {}\n\n", error);
+        }
+
+        out += "| Construct | Uses | Lowered | Degraded | Failed |\n";
+        out += "| --- | ---: | ---: | ---: | ---: |\n";
+        for (kind, count, degraded, failed) in &report.constructs {
+            out += &format!(
+                "| {} | {} | {} | {} | {} |\n",
+                kind,
+                count,
+                count.saturating_sub(degraded + failed),
+                degraded,
+                failed
+            );
+        }
+
+        if !report.problems.is_empty() {
+            out += "\n";
+        }
+        for problem in &report.problems {
+            out += &format!(
+                "- {}:{} {} {}: {}\n",
+                problem.line,
+                problem.column,
+                problem.construct,
+                if problem.failed { "failed" } else { "degraded" },
+                problem.message.replace('|', "\\|")
+            );
+        }
+    }
+
+    out
+}
+
+/// The same tables as [`render_markdown`], as a standalone HTML page.
+pub fn render_html(reports: &[FileReport]) -> String {
+    let row = |cells: &[String]| {
+        let cells: String = cells
+            .iter()
+            .map(|cell| format!("<td>{}</td>", cell))
+            .collect();
+        format!("<tr>{}</tr>\n", cells)
+    };
+    let header = |cells: &[&str]| {
+        let cells: String = cells
+            .iter()
+            .map(|cell| format!("<th>{}</th>", cell))
+            .collect();
+        format!("<tr>{}</tr>\n", cells)
+    };
+
+    let mut out = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>roblox-rs coverage report</title>\n</head>\n<body>\n<h1>roblox-rs coverage report</h1>\n<table>\n",
+    );
+    out += &header(&["File", "Constructs", "Lowered", "Degraded", "Failed"]);
+    for report in reports {
+        let (count, degraded, failed) = totals(report);
+        let file = match &report.error {
+            Some(_) => format!("<code>{}</code> (did not transpile)", escape(&report.file)),
+            None => format!("<code>{}</code>", escape(&report.file)),
+        };
+        out += &row(&[
+            file,
+            count.to_string(),
+            count.saturating_sub(degraded + failed).to_string(),
+            degraded.to_string(),
+            failed.to_string(),
+        ]);
+    }
+    out += "</table>\n";
+
+    for report in reports {
+        out += &format!("<h2><code>{}</code></h2>\n", escape(&report.file));
+        if let Some(error) = &report.error {
+            out += &format!("<p>Did not transpile: {}</p>\n", escape(error));
+        }
+
+        out += "<table>\n";
+        out += &header(&["Construct", "Uses", "Lowered", "Degraded", "Failed"]);
+        for (kind, count, degraded, failed) in &report.constructs {
+            out += &row(&[
+                code_spans(kind),
+                count.to_string(),
+                count.saturating_sub(degraded + failed).to_string(),
+                degraded.to_string(),
+                failed.to_string(),
+            ]);
+        }
+        out += "</table>\n";
+
+        if !report.problems.is_empty() {
+            out += "<ul>\n";
+            for problem in &report.problems {
+                out += &format!(
+                    "<li>{}:{} {} {}: {}</li>\n",
+                    problem.line,
+                    problem.column,
+                    code_spans(&problem.construct),
+                    if problem.failed { "failed" } else { "degraded" },
+                    code_spans(&problem.message)
+                );
+            }
+            out += "</ul>\n";
+        }
+    }
+
+    out += "</body>\n</html>\n";
+    out
+}
+
+fn totals(report: &FileReport) -> (usize, usize, usize) {
+    report
+        .constructs
+        .iter()
+        .fold((0, 0, 0), |(count, degraded, failed), construct| {
+            (
+                count + construct.1,
+                degraded + construct.2,
+                failed + construct.3,
+            )
+        })
+}
+
+/// Escapes text for HTML, with Markdown-style `` `code` `` spans as `<code>` elements.
+fn code_spans(text: &str) -> String {
+    escape(text)
+        .split('`')
+        .enumerate()
+        .map(|(index, part)| match index % 2 {
+            1 => format!("<code>{}</code>", part),
+            _ => part.to_string(),
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,116 @@
+use crate::options::RequireStyle;
+
+const LUAU_KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// The `require` argument for a module path as written in `#[require]` or given for a
+/// dependency: a string path like `./util`, `../shared/math` or `@self/config`, or an
+/// instance path like `script.Parent.util`, in `style`. Relative paths translate between
+/// the two, going by require-by-string resolving `./` from the script's parent like
+/// `script.Parent`; aliases like `@lune/fs` stay strings and paths from a root like
+/// `game:GetService("ReplicatedStorage")` stay instance paths, the only way to write each.
+pub fn require_arg(path: &str, style: RequireStyle) -> String {
+    let is_string = path == "." || path == ".." || path.starts_with(['.', '@']);
+
+    match (is_string, style) {
+        (true, RequireStyle::Instance) => to_instance(path).unwrap_or_else(|| quote(path)),
+        (true, RequireStyle::String) => quote(path),
+        (false, RequireStyle::String) => to_string_path(path)
+            .map(|path| quote(&path))
+            .unwrap_or_else(|| path.to_string()),
+        (false, RequireStyle::Instance) => path.to_string(),
+    }
+}
+
+fn quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `../shared/math` as `script.Parent.Parent.shared.math`.
+fn to_instance(path: &str) -> Option<String> {
+    let mut segments = path.split('/').filter(|segment| !segment.is_empty());
+
+    let mut instance = match segments.next()? {
+        "." => "script.Parent".to_string(),
+        ".." => "script.Parent.Parent".to_string(),
+        "@self" => "script".to_string(),
+        _ => return None,
+    };
+
+    for segment in segments {
+        match segment {
+            "." => {}
+            ".." => instance.push_str(".Parent"),
+            name => {
+                let name = name
+                    .strip_suffix(".luau")
+                    .or_else(|| name.strip_suffix(".lua"))
+                    .unwrap_or(name);
+                if is_identifier(name) {
+                    instance.push('.');
+                    instance.push_str(name);
+                } else {
+                    instance.push_str(&format!("[{}]", quote(name)));
+                }
+            }
+        }
+    }
+
+    Some(instance)
+}
+
+/// `script.Parent.Parent.shared.math` as `../shared/math`, or `None` if the path doesn't
+/// start from `script` or goes back up after naming a child.
+fn to_string_path(path: &str) -> Option<String> {
+    let mut rest = path.strip_prefix("script")?;
+    let mut parents = 0;
+    let mut names = Vec::new();
+
+    while !rest.is_empty() {
+        let (name, after) = if let Some(after) = rest.strip_prefix('.') {
+            let end = after.find(['.', '[', ':']).unwrap_or(after.len());
+            (after[..end].to_string(), &after[end..])
+        } else {
+            let inner = rest
+                .strip_prefix("[\"")
+                .or_else(|| rest.strip_prefix(":WaitForChild(\""))
+                .or_else(|| rest.strip_prefix(":FindFirstChild(\""))?;
+            let end = inner.find('"')?;
+            let after = inner[end + 1..]
+                .strip_prefix(']')
+                .or_else(|| inner[end + 1..].strip_prefix(')'))?;
+            (inner[..end].to_string(), after)
+        };
+
+        if name == "Parent" {
+            if !names.is_empty() {
+                return None;
+            }
+            parents += 1;
+        } else if name.is_empty() {
+            return None;
+        } else {
+            names.push(name);
+        }
+        rest = after;
+    }
+
+    if names.is_empty() {
+        return None;
+    }
+
+    let base = match parents {
+        0 => "@self".to_string(),
+        1 => ".".to_string(),
+        parents => vec![".."; parents - 1].join("/"),
+    };
+    Some(format!("{}/{}", base, names.join("/")))
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !LUAU_KEYWORDS.contains(&name)
+}
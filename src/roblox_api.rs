@@ -0,0 +1,174 @@
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum MemberKind {
+    Method,
+    Getter,
+    Setter,
+    Event,
+    Constructor,
+    Service,
+    Cast,
+    Pcall,
+    Tween,
+    Attribute,
+}
+
+impl MemberKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            MemberKind::Method => "method",
+            MemberKind::Getter => "getter",
+            MemberKind::Setter => "setter",
+            MemberKind::Event => "event",
+            MemberKind::Constructor => "constructor",
+            MemberKind::Service => "service",
+            MemberKind::Cast => "cast",
+            MemberKind::Pcall => "pcall",
+            MemberKind::Tween => "tween",
+            MemberKind::Attribute => "attribute",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        [
+            MemberKind::Method,
+            MemberKind::Getter,
+            MemberKind::Setter,
+            MemberKind::Event,
+            MemberKind::Constructor,
+            MemberKind::Service,
+            MemberKind::Cast,
+            MemberKind::Pcall,
+            MemberKind::Tween,
+            MemberKind::Attribute,
+        ]
+        .into_iter()
+        .find(|kind| kind.name() == name)
+    }
+}
+
+pub struct ApiMember {
+    pub kind: MemberKind,
+    pub name: String,
+    pub ty: Option<String>,
+}
+
+pub struct ApiFunction {
+    pub template: String,
+    pub ty: Option<String>,
+}
+
+pub struct ApiClass {
+    pub superclass: Option<String>,
+    pub members: HashMap<String, ApiMember>,
+}
+
+/// The Roblox classes, data types and enums of a crate generated by `bindgen`, keyed by
+/// their Rust names so method calls on bound types can be lowered to the Luau API.
+#[derive(Default)]
+pub struct RobloxApi {
+    pub classes: HashMap<String, ApiClass>,
+    pub enums: HashSet<String>,
+    pub globals: HashMap<String, String>,
+    pub functions: HashMap<String, ApiFunction>,
+}
+
+impl RobloxApi {
+    /// Reads the `bindings.json` written by `bindgen`.
+    pub fn from_json(contents: &str) -> Self {
+        let json: serde_json::Value =
+            serde_json::from_str(contents).expect("Failed to parse bindings.json");
+
+        let classes = json["classes"]
+            .as_object()
+            .expect("bindings.json has no classes")
+            .iter()
+            .map(|(name, class)| {
+                let members = class["members"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(rust_name, member)| {
+                        let member = ApiMember {
+                            kind: MemberKind::from_name(member["kind"].as_str()?)?,
+                            name: member["name"].as_str()?.to_string(),
+                            ty: member["type"].as_str().map(str::to_string),
+                        };
+
+                        Some((rust_name.clone(), member))
+                    })
+                    .collect();
+
+                let class = ApiClass {
+                    superclass: class["superclass"].as_str().map(str::to_string),
+                    members,
+                };
+
+                (name.clone(), class)
+            })
+            .collect();
+
+        let enums = json["enums"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|name| name.as_str().map(str::to_string))
+            .collect();
+
+        let globals = json["globals"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(name, class)| Some((name.clone(), class.as_str()?.to_string())))
+            .collect();
+
+        let functions = json["functions"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(path, function)| {
+                let function = ApiFunction {
+                    template: function["luau"].as_str()?.to_string(),
+                    ty: function["type"].as_str().map(str::to_string),
+                };
+
+                Some((path.clone(), function))
+            })
+            .collect();
+
+        Self {
+            classes,
+            enums,
+            globals,
+            functions,
+        }
+    }
+
+    pub fn is_type(&self, name: &str) -> bool {
+        self.classes.contains_key(name)
+    }
+
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.enums.contains(name)
+    }
+
+    /// The Luau a free function like `run_service::on_heartbeat` lowers to, with `{0}`,
+    /// `{1}`, ... standing for its arguments.
+    pub fn function(&self, module: &str, name: &str) -> Option<&ApiFunction> {
+        self.functions.get(&format!("{}::{}", module, name))
+    }
+
+    /// Looks a member up on the class and then on each of its superclasses.
+    pub fn member(&self, class: &str, rust_name: &str) -> Option<&ApiMember> {
+        let mut class = self.classes.get(class)?;
+
+        loop {
+            if let Some(member) = class.members.get(rust_name) {
+                return Some(member);
+            }
+
+            class = self.classes.get(class.superclass.as_ref()?)?;
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use crate::cli::RunArgs;
+use roblox_rs::options::ScriptKind;
+
+pub fn run(args: &RunArgs) {
+    let mut options = crate::transpile_options(&args.transpile, &args.file);
+    options.kind = Some(ScriptKind::Script);
+    options.roblox_api = args
+        .transpile
+        .bindings
+        .as_deref()
+        .map(crate::roblox_api_for);
+
+    let transpiled = crate::transpile_file(&args.file, options);
+    for message in &transpiled.messages {
+        eprintln!("{}", message);
+    }
+
+    if let Err(error) = execute(&args.file, &transpiled.output) {
+        eprintln!("error: {}", error);
+        std::process::exit(1);
+    }
+}
+
+/// Runs the output in a sandboxed Luau VM with the standard libraries and a `warn` that
+/// writes to stderr, without any Roblox or Lune globals.
+#[cfg(feature = "luau")]
+fn execute(file: &str, source: &str) -> Result<(), String> {
+    let lua = mlua::Lua::new();
+
+    let warn = lua
+        .create_function(|_, values: mlua::Variadic<mlua::Value>| {
+            let values: Vec<String> = values
+                .iter()
+                .map(|value| value.to_string().unwrap_or_else(|_| "?".to_string()))
+                .collect();
+            eprintln!("{}", values.join(" "));
+            Ok(())
+        })
+        .map_err(|error| error.to_string())?;
+    lua.globals()
+        .set("warn", warn)
+        .map_err(|error| error.to_string())?;
+
+    lua.load(source)
+        .set_name(format!(
+            "@{}",
+            std::path::Path::new(file).with_extension("luau").display()
+        ))
+        .exec()
+        .map_err(|error| error.to_string())
+}
+
+#[cfg(not(feature = "luau"))]
+fn execute(_file: &str, _source: &str) -> Result<(), String> {
+    Err("`run` needs the embedded Luau VM; rebuild with `--features luau`".to_string())
+}
@@ -0,0 +1,390 @@
+use std::collections::BTreeSet;
+
+/// Luau helpers shared by lowered code, emitted once at the top of the output when used.
+/// Variants are ordered so every helper comes after the ones it depends on.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Helper {
+    ResultType,
+    PcallResult,
+    CheckAttribute,
+    Signal,
+    Maid,
+    Bindable,
+    EnvVar,
+    WrappingMul32,
+    CheckedInt,
+    TruncDiv,
+    TruncateInt,
+    Dbg,
+    SomeNone,
+}
+
+impl Helper {
+    fn requires(self) -> &'static [Helper] {
+        match self {
+            Helper::ResultType => &[],
+            Helper::PcallResult => &[Helper::ResultType],
+            Helper::CheckAttribute => &[],
+            Helper::Signal => &[],
+            Helper::Maid => &[],
+            Helper::Bindable => &[],
+            Helper::EnvVar => &[Helper::ResultType],
+            Helper::WrappingMul32 => &[],
+            Helper::CheckedInt => &[],
+            Helper::TruncDiv => &[],
+            Helper::TruncateInt => &[],
+            Helper::Dbg => &[],
+            Helper::SomeNone => &[],
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Helper::ResultType => {
+                "type Result<T, E> = { ok: true, value: T } | { ok: false, err: E }"
+            }
+
+            Helper::PcallResult => {
+                "local function pcallResult(ok: boolean, ...): Result<any, any>
+    if ok then
+        return { ok = true, value = (...) }
+    end
+    return { ok = false, err = (...) }
+end"
+            }
+
+            Helper::CheckAttribute => {
+                "local function checkAttribute(value: any, expected: string, name: string): any
+    if value ~= nil and typeof(value) ~= expected then
+        error(`attribute {name} is a {typeof(value)}, expected {expected}`, 2)
+    end
+    return value
+end"
+            }
+
+            // Handlers run on a reused coroutine like GoodSignal, so firing doesn't allocate a
+            // thread per handler unless one yields
+            Helper::Signal => {
+                "type SignalConnection = { Connected: boolean, Disconnect: (self: SignalConnection) -> () }
+type Signal<T> = {
+    Connect: (self: Signal<T>, (T) -> ()) -> SignalConnection,
+    Once: (self: Signal<T>, (T) -> ()) -> SignalConnection,
+    Wait: (self: Signal<T>) -> T,
+    Fire: (self: Signal<T>, T) -> (),
+    DisconnectAll: (self: Signal<T>) -> (),
+}
+
+local freeRunnerThread: thread? = nil
+
+local function runHandler(handler, ...)
+    local runnerThread = freeRunnerThread
+    freeRunnerThread = nil
+    handler(...)
+    freeRunnerThread = runnerThread
+end
+
+local function runHandlersInFreeThread(...)
+    runHandler(...)
+    while true do
+        runHandler(coroutine.yield())
+    end
+end
+
+local SignalConnection = {}
+SignalConnection.__index = SignalConnection
+
+function SignalConnection:Disconnect()
+    self.Connected = false
+
+    local signal = self._signal
+    if signal._head == self then
+        signal._head = self._next
+        return
+    end
+
+    local previous = signal._head
+    while previous and previous._next ~= self do
+        previous = previous._next
+    end
+    if previous then
+        previous._next = self._next
+    end
+end
+
+local Signal = {}
+Signal.__index = Signal
+
+function Signal.new(): Signal<any>
+    return setmetatable({ _head = nil }, Signal) :: any
+end
+
+function Signal:Connect(handler)
+    local connection = setmetatable({
+        Connected = true,
+        _signal = self,
+        _handler = handler,
+        _next = self._head,
+    }, SignalConnection)
+    self._head = connection
+    return connection
+end
+
+function Signal:Once(handler)
+    local connection
+    connection = self:Connect(function(...)
+        if connection.Connected then
+            connection:Disconnect()
+        end
+        handler(...)
+    end)
+    return connection
+end
+
+function Signal:Wait()
+    local thread = coroutine.running()
+    self:Once(function(...)
+        task.spawn(thread, ...)
+    end)
+    return coroutine.yield()
+end
+
+function Signal:Fire(...)
+    local connection = self._head
+    while connection do
+        if connection.Connected then
+            if not freeRunnerThread then
+                freeRunnerThread = coroutine.create(runHandlersInFreeThread)
+            end
+            task.spawn(freeRunnerThread :: thread, connection._handler, ...)
+        end
+        connection = connection._next
+    end
+end
+
+function Signal:DisconnectAll()
+    self._head = nil
+end"
+            }
+
+            // Tasks are cleaned up in reverse order, so later tasks that depend on earlier
+            // ones go first
+            Helper::Maid => {
+                "type Maid = {
+    Add: <T>(self: Maid, job: T) -> T,
+    Cleanup: (self: Maid) -> (),
+    Destroy: (self: Maid) -> (),
+}
+
+local Maid = {}
+Maid.__index = Maid
+
+function Maid.new(): Maid
+    return setmetatable({ _jobs = {} }, Maid) :: any
+end
+
+function Maid:Add(job)
+    table.insert(self._jobs, job)
+    return job
+end
+
+local function cleanupJob(job: any)
+    local kind = typeof(job)
+    if kind == \"function\" then
+        job()
+    elseif kind == \"thread\" then
+        pcall(task.cancel, job)
+    elseif kind == \"RBXScriptConnection\" then
+        job:Disconnect()
+    elseif kind == \"Instance\" then
+        job:Destroy()
+    elseif job.Destroy then
+        job:Destroy()
+    elseif job.destroy then
+        job:destroy()
+    elseif job.Disconnect then
+        job:Disconnect()
+    end
+end
+
+function Maid:Cleanup()
+    local jobs = self._jobs
+    self._jobs = {}
+    for i = #jobs, 1, -1 do
+        cleanupJob(jobs[i])
+    end
+end
+
+Maid.Destroy = Maid.Cleanup"
+            }
+
+            // Bindables are kept where the other side doesn't see them, ServerStorage or
+            // PlayerScripts, so a server and a client using the same name each get their own,
+            // and payloads are encoded and checked on each side since they cross as copies
+            Helper::Bindable => {
+                "type Bindable<T> = {
+    Send: (self: Bindable<T>, T) -> (),
+    Receive: (self: Bindable<T>, (T) -> ()) -> RBXScriptConnection,
+}
+type BindableFn<A, R> = {
+    Invoke: (self: BindableFn<A, R>, A) -> R,
+    Handle: (self: BindableFn<A, R>, (A) -> R) -> (),
+}
+
+local function bindableInstance(name: string, className: string): any
+    local parent = if game:GetService(\"RunService\"):IsServer()
+        then game:GetService(\"ServerStorage\")
+        else game:GetService(\"Players\").LocalPlayer:WaitForChild(\"PlayerScripts\")
+    local folder = parent:FindFirstChild(\"RobloxRsBindables\")
+    if not folder then
+        folder = Instance.new(\"Folder\")
+        folder.Name = \"RobloxRsBindables\"
+        folder.Parent = parent
+    end
+
+    local instance = folder:FindFirstChild(name)
+    if not instance then
+        instance = Instance.new(className)
+        instance.Name = name
+        instance.Parent = folder
+    elseif not instance:IsA(className) then
+        error(`{name} is a {instance.ClassName}, expected a {className}`, 3)
+    end
+    return instance
+end
+
+local Bindable = {}
+Bindable.__index = Bindable
+
+function Bindable.new(name: string, encode: ((any) -> any)?, decode: ((any, string) -> any)?): Bindable<any>
+    local event = bindableInstance(name, \"BindableEvent\")
+    return setmetatable({ _name = name, _event = event, _encode = encode, _decode = decode }, Bindable) :: any
+end
+
+function Bindable:Send(payload)
+    self._event:Fire(if self._encode then self._encode(payload) else payload)
+end
+
+function Bindable:Receive(handler)
+    return self._event.Event:Connect(function(payload)
+        handler(if self._decode then self._decode(payload, self._name) else payload)
+    end)
+end
+
+local BindableFn = {}
+BindableFn.__index = BindableFn
+
+function BindableFn.new(
+    name: string,
+    encodeArgument: ((any) -> any)?,
+    decodeArgument: ((any, string) -> any)?,
+    encodeResult: ((any) -> any)?,
+    decodeResult: ((any, string) -> any)?
+): BindableFn<any, any>
+    return setmetatable({
+        _name = name,
+        _function = bindableInstance(name, \"BindableFunction\"),
+        _encodeArgument = encodeArgument,
+        _decodeArgument = decodeArgument,
+        _encodeResult = encodeResult,
+        _decodeResult = decodeResult,
+    }, BindableFn) :: any
+end
+
+function BindableFn:Invoke(argument)
+    local encode, decode = self._encodeArgument, self._decodeResult
+    local result = self._function:Invoke(if encode then encode(argument) else argument)
+    return if decode then decode(result, self._name) else result
+end
+
+function BindableFn:Handle(handler)
+    self._function.OnInvoke = function(argument)
+        local decode, encode = self._decodeArgument, self._encodeResult
+        local result = handler(if decode then decode(argument, self._name) else argument)
+        return if encode then encode(result) else result
+    end
+end"
+            }
+
+            Helper::EnvVar => {
+                "local function envVar(name: string): Result<string, string>
+    local value = process.env[name]
+    if value == nil then
+        return { ok = false, err = `environment variable {name} not found` }
+    end
+    return { ok = true, value = value }
+end"
+            }
+
+            // The full product of two 32-bit numbers can exceed the 53 bits a double holds
+            // exactly, so the multiplier is split into 16-bit halves
+            Helper::WrappingMul32 => {
+                "local function wrappingMul32(a: number, b: number): number
+    a %= 4294967296
+    b %= 4294967296
+    local low = b % 65536
+    local high = (b - low) / 65536
+    return (a * low + (a * high % 65536) * 65536) % 4294967296
+end"
+            }
+
+            Helper::CheckedInt => {
+                "local function checkedInt(value: number, min: number, max: number, operation: string): number
+    if value < min or value > max then
+        error(`attempt to {operation} with overflow`, 2)
+    end
+    return value
+end"
+            }
+
+            // `//` floors, but Rust's integer division truncates toward zero
+            Helper::TruncDiv => {
+                "local function truncDiv(a: number, b: number): number
+    local quotient = a // b
+    if quotient < 0 and quotient * b ~= a then
+        return quotient + 1
+    end
+    return quotient
+end"
+            }
+
+            // A float cast to an integer truncates, saturating at the type's bounds, and NaN is 0
+            Helper::TruncateInt => {
+                "local function truncateInt(value: number, min: number, max: number): number
+    if value ~= value then
+        return 0
+    end
+    return math.clamp(if value < 0 then math.ceil(value) else math.floor(value), min, max)
+end"
+            }
+
+            Helper::Dbg => {
+                "local function dbg(label: string, ...: any): ...any
+    warn(label, ...)
+    return ...
+end"
+            }
+
+            // `Some(None)`, which a bare `nil` couldn't tell apart from `None`
+            Helper::SomeNone => "local someNone: any = table.freeze({})",
+        }
+    }
+}
+
+pub fn prelude(helpers: &BTreeSet<Helper>) -> String {
+    let mut used = BTreeSet::new();
+    let mut pending: Vec<Helper> = helpers.iter().copied().collect();
+
+    while let Some(helper) = pending.pop() {
+        if used.insert(helper) {
+            pending.extend(helper.requires());
+        }
+    }
+
+    let mut prelude = String::new();
+    for helper in used {
+        prelude.push_str(helper.source());
+        prelude.push('\n');
+    }
+
+    prelude
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+pub struct Binding {
+    pub mutable: bool,
+    pub initialized: bool,
+    pub ty: Option<String>,
+    pub rust_type: Option<syn::Type>,
+}
+
+#[derive(Default)]
+pub struct ScopeStack {
+    scopes: Vec<HashMap<String, Binding>>,
+    /// The file's statics and constants, which every function sees
+    globals: HashMap<String, Binding>,
+}
+
+impl ScopeStack {
+    pub fn reset(&mut self) {
+        self.scopes.clear();
+        self.enter();
+    }
+
+    pub fn enter(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    pub fn exit(&mut self) {
+        self.scopes.pop();
+    }
+
+    pub fn depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    pub fn truncate(&mut self, depth: usize) {
+        self.scopes.truncate(depth);
+    }
+
+    pub fn declare(&mut self, name: &str, binding: Binding) {
+        if self.scopes.is_empty() {
+            self.enter();
+        }
+
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), binding);
+        }
+    }
+
+    pub fn declare_global(&mut self, name: &str, binding: Binding) {
+        self.globals.insert(name.to_string(), binding);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Binding> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .or_else(|| self.globals.get(name))
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.get_mut(name))
+            .or_else(|| self.globals.get_mut(name))
+    }
+
+    /// The function's locals in scope, innermost first, without shadowed ones or globals.
+    pub fn locals(&self) -> Vec<(&str, &Binding)> {
+        let mut locals: Vec<(&str, &Binding)> = Vec::new();
+        for scope in self.scopes.iter().rev() {
+            for (name, binding) in scope {
+                if !locals.iter().any(|(local, _)| local == name) {
+                    locals.push((name, binding));
+                }
+            }
+        }
+        locals
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+}
@@ -0,0 +1,426 @@
+use crate::diagnostics::Diagnostic;
+use crate::transpiler::UnsupportedConstruct;
+use quote::ToTokens;
+use std::collections::HashSet;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::visit_mut::VisitMut;
+
+/// Rewrites lazily initialized statics into plain ones, since a module's top level runs once,
+/// when it is first required: each `static ref` of a `lazy_static!` block becomes a `static`,
+/// and `static NAMES: LazyLock<T> = LazyLock::new(|| value)` (or `once_cell`'s `Lazy`) becomes
+/// `static NAMES: T = value`. `OnceLock` statics are left for the transpiler, as they are set
+/// by whoever calls `get_or_init` first.
+pub fn desugar_lazy_statics(file: &mut syn::File) -> Result<(), UnsupportedConstruct> {
+    let mut statics = LazyStatics::default();
+    statics.visit_file_mut(file);
+    statics.failure.map_or(Ok(()), Err)
+}
+
+#[derive(Default)]
+struct LazyStatics {
+    failure: Option<UnsupportedConstruct>,
+}
+
+impl LazyStatics {
+    /// Replaces each `lazy_static!` among `items` with the statics it declares.
+    fn expand(&mut self, items: &mut Vec<syn::Item>) {
+        *items = std::mem::take(items)
+            .into_iter()
+            .flat_map(|item| match item {
+                syn::Item::Macro(item_macro) if is_macro(&item_macro.mac, "lazy_static") => {
+                    match item_macro.mac.parse_body::<LazyStaticBlock>() {
+                        Ok(LazyStaticBlock(statics)) => {
+                            statics.into_iter().map(syn::Item::Static).collect()
+                        }
+                        Err(error) => {
+                            record_failure(&mut self.failure, "lazy_static", error);
+                            vec![syn::Item::Macro(item_macro)]
+                        }
+                    }
+                }
+                item => vec![item],
+            })
+            .collect();
+    }
+}
+
+impl VisitMut for LazyStatics {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.expand(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, item: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut item.content {
+            self.expand(items);
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, item);
+    }
+
+    fn visit_item_static_mut(&mut self, item: &mut syn::ItemStatic) {
+        let syn::Type::Path(path) = &*item.ty else {
+            return;
+        };
+        let Some(segment) = path.path.segments.last() else {
+            return;
+        };
+        if !matches!(
+            segment.ident.to_string().as_str(),
+            "LazyLock" | "LazyCell" | "Lazy"
+        ) {
+            return;
+        }
+
+        let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return;
+        };
+        let Some(syn::GenericArgument::Type(inner)) = args.args.first() else {
+            return;
+        };
+        let syn::Expr::Call(call) = &*item.expr else {
+            return;
+        };
+        let [init] = &call.args.iter().collect::<Vec<_>>()[..] else {
+            return;
+        };
+
+        let value: syn::Expr = match init {
+            syn::Expr::Closure(closure) if closure.inputs.is_empty() => (*closure.body).clone(),
+            init => syn::parse_quote!(#init()),
+        };
+        *item.ty = inner.clone();
+        *item.expr = value;
+    }
+}
+
+/// Rewrites each `thread_local!` key into a `static mut` of its value, with a `thread-local`
+/// warning, since a Luau VM runs its scripts' threads one at a time and keeps a module's
+/// locals for all of them (each Actor has its own VM, so its own copy). `Cell::new` and
+/// `RefCell::new` around the value are dropped, and the key's accessors become plain uses
+/// of it: `KEY.with(|v| body)` (or `with_borrow`/`with_borrow_mut`) is `body` with `KEY` for
+/// `v`, `get`, `borrow` and `borrow_mut` are `KEY`, `set(x)` is `KEY = x`, and `replace(x)`
+/// sets it and is the value before.
+pub fn desugar_thread_locals(
+    file: &mut syn::File,
+) -> Result<Vec<Diagnostic>, UnsupportedConstruct> {
+    let mut expander = ThreadLocals::default();
+    expander.visit_file_mut(file);
+    if let Some(failure) = expander.failure.take() {
+        return Err(failure);
+    }
+
+    let mut accessors = KeyAccessors {
+        keys: expander.keys.iter().map(|(key, _)| key.clone()).collect(),
+    };
+    accessors.visit_file_mut(file);
+
+    Ok(expander
+        .keys
+        .into_iter()
+        .map(|(key, span)| {
+            Diagnostic::warning(
+                "thread-local",
+                format!(
+                    "`{}` is thread-local, but is a module local shared by every thread of the script",
+                    key
+                ),
+                span,
+            )
+        })
+        .collect())
+}
+
+#[derive(Default)]
+struct ThreadLocals {
+    keys: Vec<(String, proc_macro2::Span)>,
+    failure: Option<UnsupportedConstruct>,
+}
+
+impl ThreadLocals {
+    fn expand(&mut self, items: &mut Vec<syn::Item>) {
+        *items = std::mem::take(items)
+            .into_iter()
+            .flat_map(|item| match item {
+                syn::Item::Macro(item_macro) if is_macro(&item_macro.mac, "thread_local") => {
+                    let statics = match item_macro.mac.parse_body::<ThreadLocalBlock>() {
+                        Ok(ThreadLocalBlock(statics)) => statics,
+                        Err(error) => {
+                            record_failure(&mut self.failure, "thread_local", error);
+                            return vec![syn::Item::Macro(item_macro)];
+                        }
+                    };
+
+                    statics
+                        .into_iter()
+                        .map(|mut item| {
+                            self.keys.push((item.ident.to_string(), item.ident.span()));
+                            item.mutability = syn::StaticMutability::Mut(Default::default());
+                            *item.expr = cell_value(&item.expr);
+                            syn::Item::Static(item)
+                        })
+                        .collect()
+                }
+                item => vec![item],
+            })
+            .collect();
+    }
+}
+
+impl VisitMut for ThreadLocals {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.expand(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, item: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut item.content {
+            self.expand(items);
+        }
+
+        syn::visit_mut::visit_item_mod_mut(self, item);
+    }
+}
+
+/// The value a thread-local is initialized with, without its `const { ... }` block or the
+/// `Cell` or `RefCell` around it.
+fn cell_value(expr: &syn::Expr) -> syn::Expr {
+    match expr {
+        syn::Expr::Const(block) => match &block.block.stmts[..] {
+            [syn::Stmt::Expr(inner, None)] => cell_value(inner),
+            _ => syn::Expr::Block(syn::ExprBlock {
+                attrs: Vec::new(),
+                label: None,
+                block: block.block.clone(),
+            }),
+        },
+        syn::Expr::Call(call) if call.args.len() == 1 => match &*call.func {
+            syn::Expr::Path(path)
+                if path.path.segments.len() >= 2
+                    && path
+                        .path
+                        .segments
+                        .last()
+                        .is_some_and(|last| last.ident == "new")
+                    && matches!(
+                        path.path.segments[path.path.segments.len() - 2]
+                            .ident
+                            .to_string()
+                            .as_str(),
+                        "Cell" | "RefCell"
+                    ) =>
+            {
+                call.args[0].clone()
+            }
+            _ => expr.clone(),
+        },
+        _ => expr.clone(),
+    }
+}
+
+/// Rewrites the uses of thread-local keys through their accessors into uses of the key.
+struct KeyAccessors {
+    keys: HashSet<String>,
+}
+
+impl KeyAccessors {
+    fn key_of(&self, expr: &syn::Expr) -> Option<syn::Ident> {
+        let syn::Expr::Path(path) = expr else {
+            return None;
+        };
+        let ident = path.path.get_ident()?;
+        self.keys
+            .contains(&ident.to_string())
+            .then(|| ident.clone())
+    }
+}
+
+impl VisitMut for KeyAccessors {
+    fn visit_expr_mut(&mut self, expr: &mut syn::Expr) {
+        if let syn::Expr::MethodCall(method_call) = expr {
+            if let Some(key) = self.key_of(&method_call.receiver) {
+                let method = method_call.method.to_string();
+                let args: Vec<syn::Expr> = method_call.args.iter().cloned().collect();
+
+                let rewritten: Option<syn::Expr> = match (method.as_str(), &args[..]) {
+                    ("with" | "with_borrow" | "with_borrow_mut", [syn::Expr::Closure(closure)]) => {
+                        match closure.inputs.first() {
+                            Some(syn::Pat::Ident(param)) if closure.inputs.len() == 1 => {
+                                let mut body = (*closure.body).clone();
+                                RenameBinding {
+                                    from: param.ident.clone(),
+                                    to: key.clone(),
+                                }
+                                .visit_expr_mut(&mut body);
+                                Some(body)
+                            }
+                            _ => None,
+                        }
+                    }
+                    ("get" | "borrow" | "borrow_mut" | "get_mut", []) => {
+                        Some(syn::parse_quote!(#key))
+                    }
+                    ("set", [value]) => Some(syn::parse_quote!(#key = #value)),
+                    ("replace", [value]) => Some(syn::parse_quote!({
+                        let previous = #key;
+                        #key = #value;
+                        previous
+                    })),
+                    _ => None,
+                };
+
+                if let Some(rewritten) = rewritten {
+                    *expr = rewritten;
+                    return self.visit_expr_mut(expr);
+                }
+            }
+        }
+
+        syn::visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_args(mac, |arg| self.visit_expr_mut(arg));
+    }
+}
+
+/// Visits the arguments of a macro taking expressions, like a format macro, putting them
+/// back into its tokens if `visit` changed any.
+fn visit_macro_args(mac: &mut syn::Macro, mut visit: impl FnMut(&mut syn::Expr)) {
+    let Ok(mut args) =
+        mac.parse_body_with(Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+    else {
+        return;
+    };
+
+    let before = args.to_token_stream().to_string();
+    for arg in args.iter_mut() {
+        visit(arg);
+    }
+    if args.to_token_stream().to_string() != before {
+        mac.tokens = args.to_token_stream();
+    }
+}
+
+/// Renames the uses of a closure's parameter, stopping where a nested closure rebinds it.
+struct RenameBinding {
+    from: syn::Ident,
+    to: syn::Ident,
+}
+
+impl VisitMut for RenameBinding {
+    fn visit_expr_path_mut(&mut self, path: &mut syn::ExprPath) {
+        if path.path.is_ident(&self.from) {
+            path.path = self.to.clone().into();
+        }
+    }
+
+    fn visit_expr_closure_mut(&mut self, closure: &mut syn::ExprClosure) {
+        let rebinds = closure
+            .inputs
+            .iter()
+            .any(|input| matches!(input, syn::Pat::Ident(param) if param.ident == self.from));
+        if !rebinds {
+            syn::visit_mut::visit_expr_closure_mut(self, closure);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        visit_macro_args(mac, |arg| self.visit_expr_mut(arg));
+    }
+}
+
+fn is_macro(mac: &syn::Macro, name: &str) -> bool {
+    mac.path
+        .segments
+        .last()
+        .is_some_and(|segment| segment.ident == name)
+}
+
+/// Keeps the first macro that failed to parse, located where parsing stopped.
+fn record_failure(failure: &mut Option<UnsupportedConstruct>, name: &str, error: syn::Error) {
+    if failure.is_none() {
+        let message = format!("Failed to parse `{}!`: {}", name, error);
+        let diagnostic = Diagnostic::error("unsupported", message, error.span());
+        *failure = Some(UnsupportedConstruct(diagnostic));
+    }
+}
+
+/// The body of a `lazy_static!`: `static ref NAME: T = value;` declarations.
+struct LazyStaticBlock(Vec<syn::ItemStatic>);
+
+impl Parse for LazyStaticBlock {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut statics = Vec::new();
+
+        while !input.is_empty() {
+            let attrs = input.call(syn::Attribute::parse_outer)?;
+            let vis: syn::Visibility = input.parse()?;
+            let static_token: syn::Token![static] = input.parse()?;
+            input.parse::<syn::Token![ref]>()?;
+            let ident: syn::Ident = input.parse()?;
+            let colon_token: syn::Token![:] = input.parse()?;
+            let ty: syn::Type = input.parse()?;
+            let eq_token: syn::Token![=] = input.parse()?;
+            let expr: syn::Expr = input.parse()?;
+            let semi_token: syn::Token![;] = input.parse()?;
+
+            statics.push(syn::ItemStatic {
+                attrs,
+                vis,
+                static_token,
+                mutability: syn::StaticMutability::None,
+                ident,
+                colon_token,
+                ty: Box::new(ty),
+                eq_token,
+                expr: Box::new(expr),
+                semi_token,
+            });
+        }
+
+        Ok(Self(statics))
+    }
+}
+
+/// The body of a `thread_local!`: `static NAME: T = value;` declarations, the last one's
+/// semicolon optional.
+struct ThreadLocalBlock(Vec<syn::ItemStatic>);
+
+impl Parse for ThreadLocalBlock {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut statics = Vec::new();
+
+        while !input.is_empty() {
+            let attrs = input.call(syn::Attribute::parse_outer)?;
+            let vis: syn::Visibility = input.parse()?;
+            let static_token: syn::Token![static] = input.parse()?;
+            let ident: syn::Ident = input.parse()?;
+            let colon_token: syn::Token![:] = input.parse()?;
+            let ty: syn::Type = input.parse()?;
+            let eq_token: syn::Token![=] = input.parse()?;
+            let expr: syn::Expr = input.parse()?;
+            let semi_token = match input.parse::<Option<syn::Token![;]>>()? {
+                Some(semi_token) => semi_token,
+                None if input.is_empty() => Default::default(),
+                None => return Err(input.error("expected `;`")),
+            };
+
+            statics.push(syn::ItemStatic {
+                attrs,
+                vis,
+                static_token,
+                mutability: syn::StaticMutability::None,
+                ident,
+                colon_token,
+                ty: Box::new(ty),
+                eq_token,
+                expr: Box::new(expr),
+                semi_token,
+            });
+        }
+
+        Ok(Self(statics))
+    }
+}
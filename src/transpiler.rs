@@ -1,436 +1,13632 @@
+use crate::comments::{extract_comments, Comment};
+use crate::crates::CrateImports;
+use crate::diagnostics::Diagnostic;
+use crate::format_string::{self, ArgRef, Piece, ValueKind};
+use crate::impl_trait::{fn_bound, fn_bounded_params};
+use crate::imports::{ItemKind, ReExport};
 use crate::indent_manager::IndentManager;
-use std::collections::HashSet;
+use crate::inline::Inlines;
+use crate::iterators::{Linear, LoopIter, LoopValue};
+use crate::logging::{self, FieldFormat};
+use crate::moonwave;
+use crate::name_gen::NameGen;
+use crate::options::{
+    self, IntSemantics, Optimization, RequireStyle, ScriptKind, Target, TestFramework,
+    TraitDispatch, TranspilerOptions,
+};
+use crate::rename;
+use crate::require_path;
+use crate::roblox_api::{MemberKind, RobloxApi};
+use crate::runtime::{self, Helper};
+use crate::scope::{Binding, ScopeStack};
+use crate::type_registry::{self, TypeRegistry};
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use syn::spanned::Spanned;
 use syn::visit::Visit;
 
+/// An [`Unsupported`] error with a formatted message, for a construct that can't be lowered.
+macro_rules! unsupported {
+    ($($arg:tt)*) => {
+        Unsupported::new(format!($($arg)*))
+    };
+}
+
+// Matches over fewer literals stay `if` chains
+const JUMP_TABLE_MIN_CASES: usize = 5;
+
+const REACT_PATH: &str = "game:GetService(\"ReplicatedStorage\").Packages.React";
+
+const JEST_GLOBALS_PATH: &str = "game:GetService(\"ReplicatedStorage\").DevPackages.JestGlobals";
+
+const RUNTIME_METHODS: &[(&str, &str, &str)] = &[
+    ("Signal", "fire", "Fire"),
+    ("Signal", "connect", "Connect"),
+    ("Signal", "once", "Once"),
+    ("Signal", "wait", "Wait"),
+    ("Signal", "disconnect_all", "DisconnectAll"),
+    ("SignalConnection", "disconnect", "Disconnect"),
+    ("Maid", "add", "Add"),
+    ("Maid", "clean", "Cleanup"),
+    ("Bindable", "send", "Send"),
+    ("Bindable", "receive", "Receive"),
+    ("BindableFn", "invoke", "Invoke"),
+    ("BindableFn", "handle", "Handle"),
+];
+
+// Fallible `std::fs` functions and the Lune `@lune/fs` functions they lower to
+const LUNE_FS: &[(&str, &str)] = &[
+    ("read_to_string", "readFile"),
+    ("write", "writeFile"),
+    ("create_dir", "writeDir"),
+    ("create_dir_all", "writeDir"),
+    ("remove_file", "removeFile"),
+    ("remove_dir_all", "removeDir"),
+    ("copy", "copy"),
+    ("rename", "move"),
+];
+
+// Float constants, written so Luau parses them to the same double
+const FLOAT_CONSTANTS: &[(&str, &str, &str)] = &[
+    ("f64", "NAN", "(0 / 0)"),
+    ("f64", "INFINITY", "math.huge"),
+    ("f64", "NEG_INFINITY", "-math.huge"),
+    ("f64", "MAX", "1.7976931348623157e308"),
+    ("f64", "MIN", "-1.7976931348623157e308"),
+    ("f64", "MIN_POSITIVE", "2.2250738585072014e-308"),
+    ("f64", "EPSILON", "2.220446049250313e-16"),
+    ("f32", "NAN", "(0 / 0)"),
+    ("f32", "INFINITY", "math.huge"),
+    ("f32", "NEG_INFINITY", "-math.huge"),
+    ("f32", "MAX", "3.4028234663852886e38"),
+    ("f32", "MIN", "-3.4028234663852886e38"),
+    ("f32", "MIN_POSITIVE", "1.1754943508222875e-38"),
+    ("f32", "EPSILON", "1.1920928955078125e-7"),
+    ("consts", "PI", "math.pi"),
+    ("consts", "TAU", "6.283185307179586"),
+    ("consts", "E", "2.718281828459045"),
+    ("consts", "SQRT_2", "1.4142135623730951"),
+    ("consts", "LN_2", "0.6931471805599453"),
+    ("consts", "LN_10", "2.302585092994046"),
+];
+
+// Flamework lifecycle methods, called on services and controllers that implement them
+const FLAMEWORK_LIFECYCLE: &[(&str, &str, &str)] = &[
+    ("on_init", "onInit", "OnInit"),
+    ("on_start", "onStart", "OnStart"),
+    ("on_tick", "onTick", "OnTick"),
+    ("on_physics", "onPhysics", "OnPhysics"),
+    ("on_render", "onRender", "OnRender"),
+];
+
+const TWEEN_INFO_FIELDS: &[(&str, &str)] = &[
+    ("time", "1"),
+    ("easing_style", "Enum.EasingStyle.Quad"),
+    ("easing_direction", "Enum.EasingDirection.Out"),
+    ("repeat_count", "0"),
+    ("reverses", "false"),
+    ("delay_time", "0"),
+];
+
 pub struct LuauTranspiler<'a> {
     indent_manager: &'a mut IndentManager,
-    local_variables: HashSet<String>,
+    scopes: ScopeStack,
     output: String,
     in_function: bool,
+    options: TranspilerOptions,
+    types: TypeRegistry,
+    names: NameGen,
+    script_main: Option<(syn::ItemFn, usize)>,
+    has_main: bool,
+    kind: Option<ScriptKind>,
+    /// Whether the file is marked `#![actor]`, to run under an Actor in Parallel Luau
+    actor: bool,
+    comments: Vec<Comment>,
+    next_comment: usize,
+    diagnostics: Vec<Diagnostic>,
+    self_type: Option<String>,
+    /// The type of the `impl` being lowered, which `Self` is written as
+    self_rust_type: Option<syn::Type>,
+    /// The table of the instantiation a generic struct's impls are being lowered for, like
+    /// `Wrapper_number`, under `--monomorphize`
+    specialized_table: Option<String>,
+    method_tables: HashSet<String>,
+    drop_scopes: Vec<DropScope>,
+    owned_droppable_params: Vec<String>,
+    /// The `Drop` locals that branches of the statement being lowered move out and return
+    returned_moves: HashSet<String>,
+    type_params: Vec<String>,
+    impl_type_params: Vec<String>,
+    /// The const generic parameters of the `impl` being lowered, with their positions among
+    /// its type's arguments
+    impl_const_params: Vec<(String, usize)>,
+    /// The type a `let` declares, while its value is lowered
+    expected_type: Option<syn::Type>,
+    type_substitutions: HashMap<String, String>,
+    struct_instantiations: RefCell<Vec<(String, Vec<String>)>>,
+    /// How many of `struct_instantiations` have had their types and impls emitted
+    emitted_structs: usize,
+    fn_instantiations: Vec<(String, Vec<String>)>,
+    pending_fn_instantiations: Vec<(String, Vec<String>)>,
+    extra_params: Vec<(String, String)>,
+    trait_dicts: Vec<(String, String, String)>,
+    fn_output: Option<syn::Type>,
+    in_script_main: bool,
+    /// Whether the function being emitted is profiled, so its returns end its
+    /// `debug.profilebegin` label first
+    profiled: bool,
+    string_buffers: HashSet<String>,
+    concat_parts: HashMap<String, String>,
+    runtime: RefCell<BTreeSet<Helper>>,
+    lune_libraries: RefCell<BTreeSet<&'static str>>,
+    module_path: Vec<String>,
+    module_fns: Vec<HashSet<String>>,
+    crate_imports: CrateImports,
+    re_exports: Vec<ReExport>,
+    /// Public functions renamed from a Luau keyword, exported under their Rust names
+    renamed_exports: HashMap<String, String>,
+    tail_call: Option<TailCall>,
+    /// The loops being emitted, innermost last, which `break` and `continue` leave
+    loops: Vec<LoopFrame>,
+    inlines: Inlines,
+    /// The functions being inlined, which the bodies of don't inline again
+    inlining: Vec<(Option<String>, String)>,
+    /// Set for a call made for its effects, which stays a call
+    statement_call: bool,
+    /// How many closures the code being lowered is nested in
+    closure_depth: usize,
+    /// The jump tables of the item being lowered, emitted ahead of it once it's done
+    jump_tables: Vec<String>,
+    /// The indentation of the item being lowered, where its jump tables go, or `None` for
+    /// code no item is lowered around
+    hoist_level: Option<usize>,
+    /// The Rust types written as `any`, found while mapping types
+    any_fallbacks: RefCell<Vec<(String, proc_macro2::Span)>>,
+    /// Where in `output` the lowering of each Rust line starts, for the source map
+    origins: Vec<(usize, usize)>,
 }
 
 impl<'a> LuauTranspiler<'a> {
-    pub fn new(indent_manager: &'a mut IndentManager) -> Self {
+    pub fn new(
+        indent_manager: &'a mut IndentManager,
+        source: &str,
+        options: TranspilerOptions,
+    ) -> Self {
+        let comments = if options.strip_comments {
+            Vec::new()
+        } else {
+            extract_comments(source)
+        };
+
         Self {
             indent_manager,
-            local_variables: HashSet::new(),
+            scopes: ScopeStack::default(),
             output: String::new(),
             in_function: false,
+            options,
+            types: TypeRegistry::default(),
+            names: NameGen::default(),
+            script_main: None,
+            has_main: false,
+            kind: None,
+            actor: false,
+            comments,
+            next_comment: 0,
+            diagnostics: Vec::new(),
+            self_type: None,
+            self_rust_type: None,
+            specialized_table: None,
+            method_tables: HashSet::new(),
+            drop_scopes: Vec::new(),
+            owned_droppable_params: Vec::new(),
+            returned_moves: HashSet::new(),
+            type_params: Vec::new(),
+            impl_type_params: Vec::new(),
+            impl_const_params: Vec::new(),
+            expected_type: None,
+            type_substitutions: HashMap::new(),
+            struct_instantiations: RefCell::new(Vec::new()),
+            emitted_structs: 0,
+            fn_instantiations: Vec::new(),
+            pending_fn_instantiations: Vec::new(),
+            extra_params: Vec::new(),
+            trait_dicts: Vec::new(),
+            fn_output: None,
+            in_script_main: false,
+            profiled: false,
+            string_buffers: HashSet::new(),
+            concat_parts: HashMap::new(),
+            lune_libraries: RefCell::default(),
+            module_path: Vec::new(),
+            module_fns: Vec::new(),
+            runtime: RefCell::new(BTreeSet::new()),
+            crate_imports: CrateImports::default(),
+            re_exports: Vec::new(),
+            renamed_exports: HashMap::new(),
+            tail_call: None,
+            loops: Vec::new(),
+            inlines: Inlines::default(),
+            inlining: Vec::new(),
+            statement_call: false,
+            closure_depth: 0,
+            jump_tables: Vec::new(),
+            hoist_level: None,
+            any_fallbacks: RefCell::default(),
+            origins: Vec::new(),
         }
     }
 
-    pub fn render(self) -> String {
-        self.output
+    pub fn import_crates(&mut self, imports: CrateImports) {
+        self.crate_imports = imports;
     }
 
-    fn add_line(&mut self, line: &str) {
-        self.output
-            .push_str(&format!("{}{}\n", self.indent_manager.get_indent(), line));
+    pub fn re_export(&mut self, re_exports: Vec<ReExport>) {
+        self.re_exports = re_exports;
     }
 
-    fn map_type(&self, rust_type: &syn::Type) -> &str {
-        if let syn::Type::Path(type_path) = rust_type {
-            if let Some(segment) = type_path.path.segments.last() {
-                return match segment.ident.to_string().as_str() {
-                    "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64"
-                    | "usize" | "f32" | "f64" => "number",
-                    "bool" => "boolean",
-                    "String" | "str" => "string",
-        
-                    _ => "any",
-                };
-            }
-        }
-
-        "any"
+    pub fn export_renamed(&mut self, exports: HashMap<String, String>) {
+        self.renamed_exports = exports;
     }
 
-    fn transpile_expr(&mut self, expr: &syn::Expr) -> String {
-        match expr {
-            syn::Expr::Lit(lit) => match &lit.lit {
-                syn::Lit::Str(s) => format!("\"{}\"", s.value()),
-                syn::Lit::Int(i) => i.base10_digits().to_string(),
-                syn::Lit::Float(f) => f.base10_digits().to_string(),
-                syn::Lit::Bool(b) => b.value.to_string(),
-                _ => "nil".to_string(),
-            },
-            
-            syn::Expr::Path(path) => path
-                .path
-                .get_ident()
-                .map_or("nil".to_string(), |ident| ident.to_string()),
-            
-            syn::Expr::Binary(bin_expr) => {
-                let left = self.transpile_expr(&bin_expr.left);
-                let right = self.transpile_expr(&bin_expr.right);
-                let op = match bin_expr.op {
-                    syn::BinOp::Add(_) => "+",
-                    syn::BinOp::Sub(_) => "-",
-                    syn::BinOp::Mul(_) => "*",
-                    syn::BinOp::Div(_) => "/",
-                    syn::BinOp::Eq(_) => "==",
-                    syn::BinOp::Ne(_) => "~=",
-                    syn::BinOp::Lt(_) => "<",
-                    syn::BinOp::Le(_) => "<=",
-                    syn::BinOp::Gt(_) => ">",
-                    syn::BinOp::Ge(_) => ">=",
-                    syn::BinOp::And(_) => "and",
-                    syn::BinOp::Or(_) => "or",
-                
-                    _ => panic!("unimplemented binary op!"),
-                };
+    /// The output and its source map: the Rust line each of its lines was lowered from, or
+    /// `None` for the prelude and lines outside any item.
+    pub fn render(self) -> (String, Vec<Option<usize>>) {
+        let mut prelude: String = self
+            .lune_libraries
+            .into_inner()
+            .into_iter()
+            .map(|library| format!("local {} = require(\"@lune/{}\")\n", library, library))
+            .collect();
 
-                format!("{} {} {}", left, op, right)
-            }
+        let runtime = self.runtime.into_inner();
+        if !runtime.is_empty() {
+            prelude += &runtime::prelude(&runtime);
+        }
 
-            syn::Expr::Match(match_expr) => {
-                self.visit_expr_match(match_expr);
-                String::new()
+        let mut source_map = vec![None; prelude.lines().count()];
+        let mut origins = self.origins.iter().peekable();
+        let mut origin = None;
+        let mut start = 0;
+        for line in self.output.split_inclusive('\n') {
+            while let Some((_, line)) = origins.next_if(|(offset, _)| *offset <= start) {
+                origin = Some(*line).filter(|line| *line > 0);
             }
+            source_map.push(origin);
+            start += line.len();
+        }
+
+        (prelude + &self.output, source_map)
+    }
 
-            _ => "nil".to_string(),
+    /// Attributes the output from here on to a Rust line, returning the line it was
+    /// attributed to before. A closure's body is lowered into a string of its own, so it
+    /// stays with the statement the closure is in.
+    fn mark_origin(&mut self, line: usize) -> usize {
+        let previous = self.origins.last().map_or(0, |(_, line)| *line);
+        if self.closure_depth == 0 {
+            self.origins.push((self.output.len(), line));
         }
+        previous
     }
 
-    fn add_local_variable(&mut self, name: &str) {
-        self.local_variables.insert(name.to_string());
+    fn use_helper(&self, helper: Helper) {
+        self.runtime.borrow_mut().insert(helper);
     }
 
-    fn is_local_variable(&self, name: &str) -> bool {
-        self.local_variables.contains(name)
+    fn is_lune(&self) -> bool {
+        self.options.target == Target::Lune
     }
 
-    fn clear_local_variables(&mut self) {
-        self.local_variables.clear();
+    /// A `require` of the module at `path`, in the chosen [`RequireStyle`].
+    fn require(&self, path: &str) -> String {
+        let style = self
+            .options
+            .require_style
+            .unwrap_or(RequireStyle::default_for(self.options.target));
+        format!("require({})", require_path::require_arg(path, style))
     }
 
-    fn transpile_pat(&mut self, pat: &syn::Pat) -> String {
-        match pat {
-            syn::Pat::Lit(pat_lit) => self.transpile_expr(&syn::Expr::Lit(pat_lit.clone())),
-            syn::Pat::Range(pat_range) => {
-                let start = self.transpile_expr(pat_range.start.as_ref().unwrap());
-                let end = self.transpile_expr(pat_range.end.as_ref().unwrap());
-                match &pat_range.limits {
-                    syn::RangeLimits::HalfOpen(_) => format!("{} <= x and x < {}", start, end),
-                    syn::RangeLimits::Closed(_) => format!("{} <= x and x <= {}", start, end),
-                }
-            }
+    fn use_lune_library(&self, library: &'static str) {
+        self.lune_libraries.borrow_mut().insert(library);
+    }
 
-            syn::Pat::Ident(pat_ident) => pat_ident.ident.to_string(),
-            syn::Pat::Wild(_) => "_".to_string(),
-            syn::Pat::Or(pat_or) => pat_or
-                .cases
-                .iter()
-                .map(|pat| self.transpile_pat(pat))
-                .collect::<Vec<_>>()
-                .join(" or "),
+    pub fn kind(&self) -> Option<ScriptKind> {
+        self.kind
+    }
 
-            _ => panic!("Unsupported pattern type"),
-        }
+    pub fn is_actor(&self) -> bool {
+        self.actor
     }
-}
 
-fn parse_range(expr: &syn::Expr) -> Option<(Option<String>, Option<String>, bool)> {
-    if let syn::Expr::Range(syn::ExprRange {
-        start, end, limits, ..
-    }) = expr
-    {
-        let start = start.as_ref().and_then(|expr| extract_literal_value(expr));
-        let end = end.as_ref().and_then(|expr| extract_literal_value(expr));
-        let inclusive = matches!(limits, syn::RangeLimits::Closed(_));
+    fn is_module(&self) -> bool {
+        self.kind == Some(ScriptKind::Module)
+    }
 
-        Some((start, end, inclusive))
-    } else {
-        None
+    fn type_keyword(&self, vis: &syn::Visibility) -> &'static str {
+        if self.is_module() && matches!(vis, syn::Visibility::Public(_)) {
+            "export type"
+        } else {
+            "type"
+        }
     }
-}
 
-fn extract_literal_value(expr: &syn::Expr) -> Option<String> {
-    if let syn::Expr::Lit(syn::ExprLit {
-        lit: syn::Lit::Int(lit_int),
-        ..
-    }) = expr
-    {
-        Some(lit_int.base10_digits().to_string())
-    } else {
-        None
+    /// The name and value of each item a `pub use` in `module` re-exports, leaving out
+    /// traits, type aliases and types without a table, which have no value at runtime.
+    fn re_exported(&self, module: &[String]) -> Vec<(String, String)> {
+        self.re_exports
+            .iter()
+            .filter(|re_export| re_export.module == module)
+            .filter_map(|re_export| {
+                let name = re_export.path.last()?;
+                let value = match re_export.kind {
+                    // Functions and modules are fields of their module's table
+                    ItemKind::Function | ItemKind::Module | ItemKind::Required => {
+                        re_export.path.join(".")
+                    }
+                    ItemKind::Value => name.clone(),
+                    ItemKind::Type | ItemKind::Enum
+                        if self.types.has_methods(name)
+                            || self.types.is_unit_struct(name)
+                            || self.types.is_enum(name) =>
+                    {
+                        name.clone()
+                    }
+                    ItemKind::Variant => {
+                        let enum_name = &re_export.path[re_export.path.len() - 2];
+                        if !self.types.is_enum(enum_name) {
+                            return None;
+                        }
+                        format!("{}.{}", enum_name, name)
+                    }
+                    ItemKind::Type | ItemKind::Enum => return None,
+                };
+
+                Some((re_export.name.clone(), value))
+            })
+            .collect()
     }
-}
 
-impl<'ast, 'a> Visit<'ast> for LuauTranspiler<'a> {
-    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
-        self.in_function = true;
-        self.clear_local_variables();
+    fn module_items(&self, file: &syn::File) -> (Vec<String>, Vec<String>) {
+        let mut locals = Vec::new();
+        let mut exports = Vec::new();
 
-        let fn_name = i.sig.ident.to_string();
-        let params: Vec<(String, &str)> = i
-            .sig
-            .inputs
-            .iter()
-            .filter_map(|arg| {
-                if let syn::FnArg::Typed(pat_type) = arg {
-                    if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
-                        Some((pat_ident.ident.to_string(), self.map_type(&pat_type.ty)))
-                    } else {
-                        None
+        for item in &file.items {
+            match item {
+                syn::Item::Fn(item_fn) if self.options.cfg.is_enabled(&item_fn.attrs) => {
+                    let is_generic =
+                        !type_registry::type_param_names(&item_fn.sig.generics).is_empty();
+                    if self.options.monomorphize && is_generic {
+                        continue;
+                    }
+
+                    let name = item_fn.sig.ident.to_string();
+                    if matches!(item_fn.vis, syn::Visibility::Public(_)) {
+                        exports.push(name.clone());
+                    }
+
+                    if !self.inlines.is_removed(&name) {
+                        locals.push(name);
                     }
-                } else {
-                    None
                 }
-            })
-            .collect();
 
-        let return_type = if let syn::ReturnType::Type(_, ty) = &i.sig.output {
-            Some(self.map_type(ty))
-        } else {
-            None
-        };
+                syn::Item::Struct(item_struct)
+                    if self.options.cfg.is_enabled(&item_struct.attrs)
+                        && matches!(item_struct.vis, syn::Visibility::Public(_))
+                        && self.has_struct_table(&item_struct.ident.to_string()) =>
+                {
+                    exports.push(item_struct.ident.to_string());
+                }
 
-        let params_str = params
-            .iter()
-            .map(|(name, ty)| format!("{}: {}", name, ty))
-            .collect::<Vec<_>>()
-            .join(", ");
+                syn::Item::Enum(item_enum)
+                    if self.options.cfg.is_enabled(&item_enum.attrs)
+                        && matches!(item_enum.vis, syn::Visibility::Public(_))
+                        && self.types.is_enum(&item_enum.ident.to_string()) =>
+                {
+                    exports.push(item_enum.ident.to_string());
+                }
 
-        let ret_type_str = return_type.map_or(String::new(), |ty| format!(": {}", ty));
+                syn::Item::Mod(item_mod)
+                    if self.options.cfg.is_enabled(&item_mod.attrs)
+                        && matches!(item_mod.vis, syn::Visibility::Public(_))
+                        && item_mod.content.is_some() =>
+                {
+                    exports.push(item_mod.ident.to_string());
+                }
 
-        self.add_line(&format!(
-            "function {}({}){}",
-            fn_name, params_str, ret_type_str
-        ));
+                syn::Item::Const(item_const)
+                    if self.options.cfg.is_enabled(&item_const.attrs)
+                        && matches!(item_const.vis, syn::Visibility::Public(_)) =>
+                {
+                    exports.push(item_const.ident.to_string());
+                }
 
-        self.indent_manager.increase();
+                // A `static mut` would be exported as its value at the time of the require
+                syn::Item::Static(item_static)
+                    if self.options.cfg.is_enabled(&item_static.attrs)
+                        && matches!(item_static.vis, syn::Visibility::Public(_))
+                        && matches!(item_static.mutability, syn::StaticMutability::None) =>
+                {
+                    exports.push(item_static.ident.to_string());
+                }
 
-        for stmt in &i.block.stmts {
-            self.visit_stmt(stmt);
-        }
+                syn::Item::Impl(item_impl)
+                    if !self.types.dict_dispatch
+                        && self.options.cfg.is_enabled(&item_impl.attrs) =>
+                {
+                    let Some(type_name) = type_registry::type_name(&item_impl.self_ty)
+                        .filter(|type_name| self.types.is_extended(type_name))
+                    else {
+                        continue;
+                    };
 
-        self.indent_manager.decrease();
-        self.add_line("end");
+                    let is_public = match &item_impl.trait_ {
+                        Some((_, path, _)) => self
+                            .types
+                            .traits
+                            .get(&path.segments.last().unwrap().ident.to_string())
+                            .is_some_and(|item| matches!(item.vis, syn::Visibility::Public(_))),
+                        None => item_impl.items.iter().any(|item| {
+                            matches!(item, syn::ImplItem::Fn(method)
+                                if matches!(method.vis, syn::Visibility::Public(_)))
+                        }),
+                    };
 
-        self.in_function = false;
-    }
+                    let table_name = extension_table(&type_name);
+                    if is_public && !exports.contains(&table_name) {
+                        exports.push(table_name);
+                    }
+                }
 
-    fn visit_local(&mut self, i: &'ast syn::Local) {
-        if let syn::Pat::Type(pat_type) = &i.pat {
-            if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
-                let var_name = pat_ident.ident.to_string();
-                let var_value = i.init.as_ref().map(|init| self.transpile_expr(&init.expr));
-                let var_type = self.map_type(&pat_type.ty);
-                let var_type_str = var_type.to_string();
-
-                self.add_local_variable(&var_name);
-                self.add_line(&format!(
-                    "local {}: {}{}",
-                    var_name,
-                    var_type_str,
-                    var_value.map_or(String::new(), |v| format!(" = {}", v))
-                ));
+                syn::Item::Impl(item_impl)
+                    if self.types.dict_dispatch
+                        && self.options.cfg.is_enabled(&item_impl.attrs) =>
+                {
+                    let trait_name = item_impl
+                        .trait_
+                        .as_ref()
+                        .and_then(|(_, path, _)| path.segments.last())
+                        .map(|segment| segment.ident.to_string())
+                        .filter(|trait_name| self.types.traits.contains_key(trait_name));
+                    let type_name = type_registry::type_name(&item_impl.self_ty);
+
+                    let is_public = type_name.as_ref().is_some_and(|type_name| {
+                        self.types
+                            .get(type_name)
+                            .is_some_and(|info| matches!(info.item.vis, syn::Visibility::Public(_)))
+                    });
+
+                    if let (Some(trait_name), Some(type_name), true) =
+                        (trait_name, type_name, is_public)
+                    {
+                        exports.push(format!("{}_{}", type_name, trait_name));
+                    }
+                }
+
+                _ => {}
             }
-        } else if let syn::Pat::Ident(pat_ident) = &i.pat {
-            let var_name = pat_ident.ident.to_string();
+        }
 
-            if let Some(init) = &i.init {
-                if let syn::Expr::Match(expr_match) = &*init.expr {
-                    let match_expr_str = self.transpile_expr(&expr_match.expr);
+        (locals, exports)
+    }
 
-                    self.add_line(&format!("local {} = nil", var_name));
+    fn trait_bounds(&self, generics: &syn::Generics) -> Vec<(String, String, String)> {
+        let mut bounds = Vec::new();
 
-                    let mut is_first = true;
-                    for arm in &expr_match.arms {
-                        if arm.guard.is_some() {
-                            panic!("Guard clauses are not yet supported");
-                        }
-                        
-                        let condition = match &arm.pat {
-                            syn::Pat::Lit(_) | syn::Pat::Ident(_) => {
-                                format!("{} == {}", match_expr_str, self.transpile_pat(&arm.pat))
-                            }
-                        
-                            syn::Pat::Range(_) => self.transpile_pat(&arm.pat),
-                            syn::Pat::Or(pat_or) => pat_or
-                                .cases
-                                .iter()
-                                .map(|pat| {
-                                    format!("{} == {}", match_expr_str, self.transpile_pat(pat))
-                                })
-                                .collect::<Vec<_>>()
-                                .join(" or "),
-                        
-                            syn::Pat::Wild(_) => "_".to_string(),
-                        
-                            _ => panic!("Unsupported pattern type in match arm"),
-                        };
+        let inline = generics
+            .type_params()
+            .flat_map(|param| param.bounds.iter().map(move |bound| (&param.ident, bound)));
+        let predicates = generics
+            .where_clause
+            .iter()
+            .flat_map(|where_clause| where_clause.predicates.iter())
+            .filter_map(|predicate| match predicate {
+                syn::WherePredicate::Type(predicate) => match &predicate.bounded_ty {
+                    syn::Type::Path(type_path) => type_path
+                        .path
+                        .get_ident()
+                        .map(|ident| predicate.bounds.iter().map(move |bound| (ident, bound))),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .flatten();
 
-                        if condition == "_" {
-                            self.add_line("else");
-                        } else if is_first {
-                            self.add_line(&format!("if {} then", condition));
-                            is_first = false;
-                        } else {
-                            self.add_line(&format!("elseif {} then", condition));
-                        }
-                        
-                        self.indent_manager.increase();
-                        
-                        let transpiled_body = self.transpile_expr(&arm.body);
-                        
-                        self.add_line(&format!("{} = {}", var_name, transpiled_body));
-                        self.indent_manager.decrease();
-                    }
+        for (param, bound) in inline.chain(predicates) {
+            let syn::TypeParamBound::Trait(trait_bound) = bound else {
+                continue;
+            };
 
-                    self.add_line("end");
-                }
-            } else {
-                let var_value = i.init.as_ref().map(|init| self.transpile_expr(&init.expr));
+            let Some(segment) = trait_bound.path.segments.last() else {
+                continue;
+            };
 
-                self.add_local_variable(&var_name);
-                self.add_line(&format!(
-                    "local {}{}",
-                    var_name,
-                    var_value.map_or(String::new(), |v| format!(" = {}", v))
-                ));
+            let trait_name = segment.ident.to_string();
+            if !self.types.traits.contains_key(&trait_name) {
+                continue;
             }
+
+            let mut type_args = vec![param.to_string()];
+            type_args.extend(
+                generic_type_args(&segment.arguments)
+                    .into_iter()
+                    .map(|ty| self.map_type(ty)),
+            );
+
+            let dict_type = format!("{}<{}>", trait_name, type_args.join(", "));
+            bounds.push((param.to_string(), trait_name, dict_type));
         }
+
+        bounds
     }
 
-    fn visit_expr_assign(&mut self, i: &'ast syn::ExprAssign) {
-        if let syn::Expr::Path(path) = &*i.left {
-            if let Some(ident) = path.path.get_ident() {
-                let var_name = ident.to_string();
-                let value = self.transpile_expr(&i.right);
+    /// Whether the receiver's type declares `method` itself, inherently or through a trait.
+    fn defines_method(&self, receiver: &syn::Expr, method: &syn::Ident) -> bool {
+        self.receiver_type_name(receiver).is_some_and(|ty| {
+            self.types
+                .inherent_methods
+                .contains(&(ty.clone(), method.to_string()))
+                || self
+                    .types
+                    .implemented_trait(&ty, &method.to_string())
+                    .is_some()
+        })
+    }
 
-                if self.in_function && !self.is_local_variable(&var_name) {
-                    self.add_line(&format!("local {} = {}", var_name, value));
-                    self.add_local_variable(&var_name);
-                } else {
-                    self.add_line(&format!("{} = {}", var_name, value));
+    /// The type `is::<T>()` or `downcast_ref::<T>()` checks for, which must have a method
+    /// table to compare against.
+    fn downcast_type(&self, method_call: &syn::ExprMethodCall) -> Lowered<String> {
+        let type_name = method_call
+            .turbofish
+            .iter()
+            .flat_map(|turbofish| &turbofish.args)
+            .find_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => type_registry::type_name(ty),
+                _ => None,
+            });
+
+        match type_name {
+            Some(name) if self.types.has_methods(&name) && !self.types.is_newtype(&name) => {
+                Ok(name)
+            }
+            _ => Err(unsupported!(
+                "`{}` needs a struct with methods to check against",
+                method_call.method
+            )),
+        }
+    }
+
+    /// The unit enum an expression is a value of, like `Color` for `Color::Red`.
+    fn enum_type_of(&self, expr: &syn::Expr) -> Option<String> {
+        let name = match expr {
+            syn::Expr::Path(path) if path.path.segments.len() >= 2 => {
+                let segments = &path.path.segments;
+                match (&segments[segments.len() - 2].ident, &self.self_type) {
+                    (ident, Some(self_type)) if ident == "Self" => self_type.clone(),
+                    (ident, _) => ident.to_string(),
                 }
             }
+            _ => self.receiver_type_name(expr)?,
+        };
+
+        (self.types.is_enum(&name) && !self.types.is_error_enum(&name)).then_some(name)
+    }
+
+    fn receiver_type_name(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Reference(reference) => return self.receiver_type_name(&reference.expr),
+            syn::Expr::Paren(paren) => return self.receiver_type_name(&paren.expr),
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Deref(_),
+                expr,
+                ..
+            }) => return self.receiver_type_name(expr),
+            _ => {}
+        }
+
+        if let Some(type_name) = self.expr_type_name(expr) {
+            return Some(type_name);
+        }
+
+        if let syn::Expr::Path(_) | syn::Expr::Struct(_) = expr {
+            if let Some(type_name) = self.expr_type_of(expr) {
+                return Some(type_name);
+            }
+        }
+
+        if let Some(type_name) = self.api_type_of(expr) {
+            return Some(type_name);
+        }
+
+        if let Some(type_name) = self.runtime_type_of(expr) {
+            return Some(type_name);
+        }
+
+        if let syn::Expr::Field(expr_field) = expr {
+            let syn::Member::Named(field) = &expr_field.member else {
+                return None;
+            };
+
+            let base = self.receiver_type_name(&expr_field.base)?;
+            let field = self
+                .types
+                .get(&base)?
+                .item
+                .fields
+                .iter()
+                .find(|f| f.ident.as_ref() == Some(field))?;
+            return type_registry::type_name(&field.ty);
+        }
+
+        let mut rust_type = self.expr_rust_type(expr)?;
+        while let syn::Type::Reference(reference) = rust_type {
+            rust_type = &reference.elem;
+        }
+
+        type_registry::type_name(rust_type)
+    }
+
+    /// The bundled runtime class behind a type like `Signal<T>` or `Maid`, unless the
+    /// file defines a type of that name itself.
+    fn runtime_class(&self, name: &str) -> Option<Helper> {
+        if self.types.is_struct(name) {
+            return None;
+        }
+
+        match name {
+            "Signal" | "SignalConnection" => Some(Helper::Signal),
+            "Maid" => Some(Helper::Maid),
+            "Bindable" | "BindableFn" => Some(Helper::Bindable),
+            _ => None,
         }
     }
 
-    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
-        let condition = self.transpile_expr(&i.cond);
+    fn runtime_method(&self, method_call: &syn::ExprMethodCall) -> Option<&'static str> {
+        let receiver = self.receiver_type_name(&method_call.receiver)?;
+        self.runtime_class(&receiver)?;
 
-        self.add_line(&format!("if {} then", condition));
-        self.indent_manager.increase();
+        RUNTIME_METHODS
+            .iter()
+            .find(|(ty, method, _)| *ty == receiver && method_call.method == method)
+            .map(|(_, _, name)| *name)
+    }
 
-        for stmt in &i.then_branch.stmts {
-            self.visit_stmt(stmt);
+    fn runtime_type_of(&self, expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::MethodCall(method_call) = expr else {
+            return None;
+        };
+
+        match self.runtime_method(method_call)? {
+            "Connect" | "Once" | "Receive" => Some("SignalConnection".to_string()),
+            _ => None,
         }
+    }
 
-        self.indent_manager.decrease();
+    fn api(&self) -> Option<&RobloxApi> {
+        self.options.roblox_api.as_deref()
+    }
 
-        if let Some((_, else_branch)) = &i.else_branch {
-            match &**else_branch {
-                syn::Expr::If(else_if) => {
-                    let elseif_condition = self.transpile_expr(&else_if.cond);
-                    
-                    self.add_line(&format!("elseif {} then", elseif_condition));
-                    self.indent_manager.increase();
-                    
-                    for stmt in &else_if.then_branch.stmts {
-                        self.visit_stmt(stmt);
-                    }
-                    
-                    self.indent_manager.decrease();
-                    
-                    if let Some((_, nested_else_branch)) = &else_if.else_branch {
-                        match &**nested_else_branch {
-                            syn::Expr::If(nested_else_if) => self.visit_expr_if(nested_else_if),
-                    
-                            _ => {
-                                self.add_line("else");
-                                self.indent_manager.increase();
-                                self.visit_expr(nested_else_branch);
-                                self.indent_manager.decrease();
-                                self.add_line("end");
-                            }
-                        }
-                    } else {
-                        self.add_line("end");
-                    }
+    /// The bound Roblox type a call evaluates to, so chains like
+    /// `Players::service().local_player()` keep resolving members.
+    fn api_type_of(&self, expr: &syn::Expr) -> Option<String> {
+        let api = self.api()?;
+
+        match expr {
+            syn::Expr::MethodCall(method_call) => {
+                let receiver = self.receiver_type_name(&method_call.receiver)?;
+                let method = method_call.method.to_string();
+
+                // A bound class comes back as an `Option`, whose unwrap is the class again
+                if UNWRAP_METHODS.contains(&method.as_str()) && self.is_api_class(&receiver) {
+                    return Some(receiver);
                 }
-                
-                _ => {
-                    self.add_line("else");
-                    self.indent_manager.increase();
 
-                    if let syn::Expr::Block(block) = &**else_branch {
-                        for stmt in &block.block.stmts {
-                            self.visit_stmt(stmt);
-                        }
-                    } else {
-                        self.visit_expr(else_branch);
-                    }
-                    
-                    self.indent_manager.decrease();
-                    self.add_line("end");
+                api.member(&receiver, &method)?.ty.clone()
+            }
+
+            syn::Expr::Call(call) => {
+                let syn::Expr::Path(path) = &*call.func else {
+                    return None;
+                };
+
+                if let Some(ident) = path.path.get_ident() {
+                    return api.globals.get(&ident.to_string()).cloned();
+                }
+
+                let segments = &path.path.segments;
+                if segments.len() < 2 {
+                    return None;
+                }
+
+                let class = segments[segments.len() - 2].ident.to_string();
+                let function = segments[segments.len() - 1].ident.to_string();
+
+                if let Some(api_function) = api.function(&class, &function) {
+                    return api_function.ty.clone();
                 }
+
+                api.member(&class, &function)?.ty.clone()
             }
-        } else {
-            self.add_line("end");
+
+            _ => None,
         }
     }
 
-    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
-        let loop_var = if let syn::Pat::Ident(pat_ident) = &*i.pat {
-            pat_ident.ident.to_string()
-        } else {
-            panic!("Unsupported loop variable pattern");
+    fn is_api_class(&self, name: &str) -> bool {
+        !self.types.is_struct(name)
+            && !self.types.is_enum(name)
+            && self.api().is_some_and(|api| api.is_type(name))
+    }
+
+    /// Types the name a `Some(name)` pattern binds from a bound Roblox value, so calls on
+    /// it keep lowering to the Luau API.
+    fn type_api_binding(&mut self, pat: &syn::Pat, scrutinee: &syn::Expr) {
+        let Some(name) = some_binding(pat) else {
+            return;
         };
-        
-        if let Some((start, end, inclusive)) = parse_range(&i.expr) {
-            let end_val = if inclusive {
-                end
-            } else {
-                end.map(|v| format!("{} - 1", v))
-            };
-        
-            self.add_line(&format!(
-                "for {} = {}, {} do",
-                loop_var,
-                start.unwrap_or_else(|| "0".to_string()),
-                end_val.unwrap_or_else(|| "math.huge".to_string())
-            ));
-        } else {
-            panic!("Unsupported iterator expression");
-        }
-        
-        self.indent_manager.increase();
-        
-        for stmt in &i.body.stmts {
-            self.visit_stmt(stmt);
+
+        if let Some(ty) = self
+            .receiver_type_name(scrutinee)
+            .filter(|ty| self.is_api_class(ty))
+        {
+            self.set_local_type(&name, Some(ty));
         }
-        
-        self.indent_manager.decrease();
-        self.add_line("end");
     }
 
-    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
-        let condition = self.transpile_expr(&i.cond);
-        
-        self.add_line(&format!("while {} do", condition));
-        self.indent_manager.increase();
-        
-        for stmt in &i.body.stmts {
-            self.visit_stmt(stmt);
+    fn api_method(&self, method_call: &syn::ExprMethodCall) -> Option<(MemberKind, String)> {
+        let receiver = self.receiver_type_name(&method_call.receiver)?;
+        if self.types.is_struct(&receiver) || self.types.is_enum(&receiver) {
+            return None;
         }
-        
-        self.indent_manager.decrease();
-        self.add_line("end");
+
+        let member = self
+            .api()?
+            .member(&receiver, &method_call.method.to_string())?;
+
+        Some((member.kind, member.name.clone()))
     }
 
-    fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
-        self.add_line("while true do");
+    fn lower_api_method(&mut self, method_call: &syn::ExprMethodCall) -> Lowered<String> {
+        let (kind, name) = self.api_method(method_call).unwrap();
+        let receiver = self.transpile_expr(&method_call.receiver)?;
+        let receiver = method_receiver(&method_call.receiver, receiver);
+
+        Ok(match kind {
+            MemberKind::Getter | MemberKind::Event => format!("{}.{}", receiver, name),
+            MemberKind::Setter => {
+                let value = self.transpile_args(&method_call.args)?;
+                format!("{}.{} = {}", receiver, name, value)
+            }
+            MemberKind::Pcall => {
+                self.use_helper(Helper::PcallResult);
+                let args = self.transpile_args(&method_call.args)?;
+
+                let call = if is_simple_place(&receiver) {
+                    let args = [receiver.clone(), args]
+                        .into_iter()
+                        .filter(|arg| !arg.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    format!("pcall({}.{}, {})", receiver, name, args)
+                } else {
+                    format!(
+                        "pcall(function() return {}:{}({}) end)",
+                        receiver, name, args
+                    )
+                };
+
+                format!("pcallResult({})", call)
+            }
+            MemberKind::Attribute => {
+                let args = self.transpile_args(&method_call.args)?;
+                let get = format!("{}:{}({})", receiver, name, args);
+
+                let expected = match method_call
+                    .turbofish
+                    .as_ref()
+                    .and_then(|turbofish| turbofish.args.first())
+                {
+                    Some(syn::GenericArgument::Type(ty)) => self.typeof_name(ty),
+                    _ => None,
+                };
+
+                match expected.filter(|_| self.options.cfg.is_set("debug_assertions")) {
+                    Some(expected) => {
+                        self.use_helper(Helper::CheckAttribute);
+                        format!("checkAttribute({}, \"{}\", {})", get, expected, args)
+                    }
+                    None => get,
+                }
+            }
+            MemberKind::Cast => match method_call
+                .turbofish
+                .as_ref()
+                .and_then(|turbofish| turbofish.args.first())
+            {
+                Some(syn::GenericArgument::Type(ty)) => {
+                    format!("({} :: {})", receiver, self.map_type(ty))
+                }
+                _ => receiver,
+            },
+            _ => {
+                let args = self.transpile_args(&method_call.args)?;
+                format!("{}:{}({})", receiver, name, args)
+            }
+        })
+    }
+
+    /// What Luau's `typeof` reports for values of a Rust type, where it is known.
+    fn typeof_name(&self, ty: &syn::Type) -> Option<String> {
+        let mut ty = ty;
+        while let syn::Type::Reference(reference) = ty {
+            ty = &reference.elem;
+        }
+
+        let name = type_registry::type_name(ty)?;
+        let api = self.api();
+
+        match name.as_str() {
+            "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64" | "usize"
+            | "f32" | "f64" => Some("number".to_string()),
+            "bool" => Some("boolean".to_string()),
+            "String" | "str" => Some("string".to_string()),
+            "Vec" | "VecDeque" | "HashMap" | "BTreeMap" | "HashSet" | "BTreeSet" => {
+                Some("table".to_string())
+            }
+            _ if api.is_some_and(|api| api.is_enum(&name)) => Some("EnumItem".to_string()),
+            _ if api.is_some_and(|api| api.is_type(&name)) => Some(name),
+            _ => None,
+        }
+    }
+
+    fn lower_api_call(&mut self, call: &syn::ExprCall) -> Lowered<Option<String>> {
+        let Some(api) = self.api() else {
+            return Ok(None);
+        };
+        let syn::Expr::Path(path) = &*call.func else {
+            return Ok(None);
+        };
+
+        if let Some(ident) = path.path.get_ident() {
+            let name = ident.to_string();
+            let is_global = api.globals.contains_key(&name) && !self.is_local_variable(&name);
+            return Ok(is_global.then_some(name));
+        }
+
+        let segments = &path.path.segments;
+        if segments.len() < 2 {
+            return Ok(None);
+        }
+
+        let class = segments[segments.len() - 2].ident.to_string();
+        if self.types.is_struct(&class) || self.types.is_enum(&class) {
+            return Ok(None);
+        }
+
+        let function = segments[segments.len() - 1].ident.to_string();
+        if let Some(api_function) = api.function(&class, &function) {
+            let template = api_function.template.clone();
+            let args: Vec<String> = call
+                .args
+                .iter()
+                .map(|arg| self.transpile_expr(arg))
+                .collect::<Lowered<_>>()?;
+
+            return Ok(Some(
+                args.iter()
+                    .enumerate()
+                    .fold(template, |lowered, (index, arg)| {
+                        lowered.replace(&format!("{{{}}}", index), arg)
+                    }),
+            ));
+        }
+
+        if class == "TweenInfo" && function == "default" && api.is_type(&class) {
+            return Ok(Some("TweenInfo.new()".to_string()));
+        }
+
+        let Some(member) = api.member(&class, &function) else {
+            return Ok(None);
+        };
+        Ok(match member.kind {
+            MemberKind::Constructor => Some(format!("Instance.new(\"{}\")", member.name)),
+            MemberKind::Service => Some(format!("game:GetService(\"{}\")", member.name)),
+            MemberKind::Tween => Some(self.lower_tween(call)?),
+            _ => None,
+        })
+    }
+
+    fn lower_tween(&mut self, call: &syn::ExprCall) -> Lowered<String> {
+        let [Some(instance), Some(info), Some(properties)] =
+            [0, 1, 2].map(|index| call.args.get(index))
+        else {
+            return Err(unsupported!(
+                "Tween::new expects an instance, a TweenInfo and props!"
+            ));
+        };
+
+        let mut instance_expr = instance;
+        while let syn::Expr::Reference(reference) = instance_expr {
+            instance_expr = &reference.expr;
+        }
+        let class = self.receiver_type_name(instance_expr);
+
+        let properties = match properties {
+            syn::Expr::Macro(expr_macro) => self.lower_props(&expr_macro.mac, class.as_deref())?,
+            properties => self.transpile_expr(properties)?,
+        };
+
+        Ok(format!(
+            "game:GetService(\"TweenService\"):Create({}, {}, {})",
+            self.transpile_expr(instance)?,
+            self.transpile_expr(info)?,
+            properties
+        ))
+    }
+
+    /// `props! { transparency: 1.0 }` as a property table, keyed by the Luau names the
+    /// bindings give the instance's getters.
+    fn lower_props(&mut self, mac: &syn::Macro, class: Option<&str>) -> Lowered<String> {
+        let fields = mac
+            .parse_body_with(
+                syn::punctuated::Punctuated::<syn::FieldValue, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|_| unsupported!("props! expects `name: value` pairs"))?;
+
+        let fields = fields
+            .iter()
+            .map(|field| {
+                let syn::Member::Named(ident) = &field.member else {
+                    return Err(unsupported!("props! expects named properties"));
+                };
+
+                let rust_name = ident.to_string();
+                let name = class
+                    .zip(self.api())
+                    .and_then(|(class, api)| api.member(class, &rust_name))
+                    .filter(|member| member.kind == MemberKind::Getter)
+                    .map_or_else(|| pascal_case(&rust_name), |member| member.name.clone());
+
+                Ok(format!("{} = {}", name, self.transpile_expr(&field.expr)?))
+            })
+            .collect::<Lowered<Vec<_>>>()?;
+
+        if fields.is_empty() {
+            return Ok("{}".to_string());
+        }
+
+        Ok(format!("{{ {} }}", fields.join(", ")))
+    }
+
+    fn is_tween_info(&self, expr_struct: &syn::ExprStruct) -> bool {
+        expr_struct.path.is_ident("TweenInfo")
+            && !self.types.is_struct("TweenInfo")
+            && self.api().is_some_and(|api| api.is_type("TweenInfo"))
+    }
+
+    // `TweenInfo.new` takes its fields positionally, so everything up to the last field
+    // given is passed, with the Roblox defaults filling the gaps
+    fn lower_tween_info(&mut self, expr_struct: &syn::ExprStruct) -> Lowered<String> {
+        if let Some(rest) = &expr_struct.rest {
+            let is_default = match &**rest {
+                syn::Expr::Call(call) => matches!(
+                    &*call.func,
+                    syn::Expr::Path(path)
+                        if path.path.segments.last().is_some_and(|segment| segment.ident == "default")
+                ),
+                _ => false,
+            };
+
+            if !is_default {
+                return Err(unsupported!(
+                    "TweenInfo only supports `..Default::default()` as its base"
+                ));
+            }
+        }
+
+        let mut args: Vec<String> = TWEEN_INFO_FIELDS
+            .iter()
+            .map(|(_, default)| default.to_string())
+            .collect();
+        let mut given = 0;
+
+        for field in &expr_struct.fields {
+            let syn::Member::Named(ident) = &field.member else {
+                return Err(unsupported!("TweenInfo has no unnamed fields"));
+            };
+
+            let index = TWEEN_INFO_FIELDS
+                .iter()
+                .position(|(name, _)| ident == name)
+                .ok_or_else(|| unsupported!("TweenInfo has no field `{}`", ident))?;
+
+            args[index] = self.transpile_expr(&field.expr)?;
+            given = given.max(index + 1);
+        }
+
+        args.truncate(given);
+        Ok(format!("TweenInfo.new({})", args.join(", ")))
+    }
+
+    fn dict_for(&self, type_name: &str, trait_name: &str) -> String {
+        self.trait_dicts
+            .iter()
+            .rev()
+            .find(|(param, bound, _)| param == type_name && bound == trait_name)
+            .map_or_else(
+                || format!("{}_{}", type_name, trait_name),
+                |(_, _, dict)| dict.clone(),
+            )
+    }
+
+    fn method_dict(&self, receiver: &syn::Expr, method: &str) -> Option<String> {
+        if !self.types.dict_dispatch {
+            return None;
+        }
+
+        let type_name = self.receiver_type_name(receiver)?;
+
+        let bound = self
+            .trait_dicts
+            .iter()
+            .rev()
+            .find(|(param, trait_name, _)| {
+                *param == type_name && self.types.trait_declares(trait_name, method)
+            });
+        if let Some((_, _, dict)) = bound {
+            return Some(dict.clone());
+        }
+
+        let trait_name = self.types.implemented_trait(&type_name, method)?;
+        Some(self.dict_for(&type_name, trait_name))
+    }
+
+    // `Trait::method(&value, ...)` dispatches on the first argument like a method call
+    fn trait_call_dict(&self, call: &syn::ExprCall) -> Option<String> {
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        let [trait_segment, method] = path.path.segments.iter().collect::<Vec<_>>()[..] else {
+            return None;
+        };
+
+        let trait_name = trait_segment.ident.to_string();
+        if !self.types.dict_dispatch || !self.types.traits.contains_key(&trait_name) {
+            return None;
+        }
+
+        let type_name = self.receiver_type_name(call.args.first()?)?;
+        self.types
+            .trait_declares(&trait_name, &method.ident.to_string())
+            .then(|| self.dict_for(&type_name, &trait_name))
+    }
+
+    fn call_dicts(&self, call: &syn::ExprCall) -> Lowered<Vec<String>> {
+        if !self.types.dict_dispatch {
+            return Ok(Vec::new());
+        }
+
+        let syn::Expr::Path(path) = &*call.func else {
+            return Ok(Vec::new());
+        };
+
+        let Some(segment) = path
+            .path
+            .segments
+            .last()
+            .filter(|_| path.path.segments.len() == 1)
+        else {
+            return Ok(Vec::new());
+        };
+
+        let name = segment.ident.to_string();
+        let Some(item) = self.types.generic_fns.get(&name) else {
+            return Ok(Vec::new());
+        };
+
+        let params = type_registry::type_param_names(&item.sig.generics);
+        let explicit = generic_type_args(&segment.arguments);
+
+        self.trait_bounds(&item.sig.generics)
+            .into_iter()
+            .map(|(param, trait_name, _)| {
+                let explicit = params
+                    .iter()
+                    .position(|name| *name == param)
+                    .and_then(|index| explicit.get(index))
+                    .and_then(|ty| type_registry::type_name(ty));
+
+                let inputs = item.sig.inputs.iter().filter_map(|input| match input {
+                    syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+                    syn::FnArg::Receiver(_) => None,
+                });
+
+                let inferred = inputs.zip(call.args.iter()).find_map(|(input, arg)| {
+                    if is_type_param(input, &param) {
+                        return self.receiver_type_name(arg);
+                    }
+
+                    self.expr_rust_type(arg)
+                        .and_then(|concrete| unify_type_param(input, concrete, &param))
+                        .and_then(type_registry::type_name)
+                });
+
+                let type_name = explicit.or(inferred).ok_or_else(|| {
+                    unsupported!(
+                        "Could not infer which `{}` implementation to pass to `{}`",
+                        trait_name,
+                        name
+                    )
+                })?;
+
+                Ok(self.dict_for(&type_name, &trait_name))
+            })
+            .collect()
+    }
+
+    fn emit_trait_type(&mut self, item: &syn::ItemTrait) {
+        let trait_name = item.ident.to_string();
+        let mut generics = vec!["Self".to_string()];
+        generics.extend(type_registry::type_param_names(&item.generics));
+
+        let previous_self_type = self.self_type.replace("Self".to_string());
+        let previous_len = self.type_params.len();
+        self.type_params.extend(generics.iter().cloned());
+
+        self.emit_doc_comments(doc_lines(&item.attrs));
+        let keyword = self.type_keyword(&item.vis);
+        self.add_line(&format!(
+            "{} {} = {{",
+            keyword,
+            generic_alias(&trait_name, &generics)
+        ));
+        self.indent_manager.increase();
+
+        for trait_item in &item.items {
+            let syn::TraitItem::Fn(method) = trait_item else {
+                continue;
+            };
+
+            if self.options.cfg.is_enabled(&method.attrs) {
+                let method_name = method.sig.ident.to_string();
+                self.declare_method(&method_name, &method.attrs, &method.sig, None, "Self");
+            }
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("}");
+
+        self.type_params.truncate(previous_len);
+        self.self_type = previous_self_type;
+    }
+
+    /// The type of a trait's objects, for a trait used as `dyn Trait`: a table of its methods
+    /// that the implementations' method tables provide.
+    fn emit_interface_type(&mut self, item: &syn::ItemTrait) {
+        let trait_name = item.ident.to_string();
+        let generics = type_registry::type_param_names(&item.generics);
+        let interface = generic_alias(&trait_name, &generics);
+
+        let previous_self_type = self.self_type.replace(interface.clone());
+        let previous_len = self.type_params.len();
+        self.type_params.extend(generics);
+
+        self.emit_doc_comments(doc_lines(&item.attrs));
+        let keyword = self.type_keyword(&item.vis);
+        self.add_line(&format!("{} {} = {{", keyword, interface));
         self.indent_manager.increase();
-        
-        for stmt in &i.body.stmts {
-            self.visit_stmt(stmt);
+
+        for trait_item in &item.items {
+            let syn::TraitItem::Fn(method) = trait_item else {
+                continue;
+            };
+
+            if method.sig.receiver().is_some() && self.options.cfg.is_enabled(&method.attrs) {
+                let method_name = method.sig.ident.to_string();
+                self.declare_method(&method_name, &method.attrs, &method.sig, None, &interface);
+            }
         }
-        
+
         self.indent_manager.decrease();
-        self.add_line("end");
+        self.add_line("}");
+
+        self.type_params.truncate(previous_len);
+        self.self_type = previous_self_type;
+    }
+
+    fn emit_dict_trait(&mut self, item: &syn::ItemTrait) -> Lowered {
+        self.emit_trait_type(item);
+
+        let trait_name = item.ident.to_string();
+        if !self.types.has_default_methods(&trait_name) {
+            return Ok(());
+        }
+
+        self.add_line(&format!("local {} = {{}}", trait_name));
+
+        let mut generics = vec!["Self".to_string()];
+        generics.extend(type_registry::type_param_names(&item.generics));
+        let dict_type = generic_alias(&trait_name, &generics);
+
+        let previous_self_type = self.self_type.replace("Self".to_string());
+
+        for method in type_registry::default_methods(item) {
+            if !self.options.cfg.is_enabled(&method.attrs) {
+                continue;
+            }
+
+            self.flush_comments_before(method.span().start().line);
+
+            self.impl_type_params = generics.clone();
+            self.extra_params = vec![("dict".to_string(), dict_type.clone())];
+            if method.sig.receiver().is_some() {
+                self.extra_params
+                    .push(("self".to_string(), "Self".to_string()));
+            }
+
+            self.trait_dicts
+                .push(("Self".to_string(), trait_name.clone(), "dict".to_string()));
+
+            let qualified_name = format!("{}.{}", trait_name, method.sig.ident);
+            self.transpile_function(
+                &method.attrs,
+                &method.sig,
+                method.default.as_ref().unwrap(),
+                &qualified_name,
+                &trait_name,
+                &[],
+            )?;
+
+            self.trait_dicts.pop();
+            self.skip_comments_through(method.span().end().line);
+        }
+
+        self.self_type = previous_self_type;
+        self.impl_type_params.clear();
+
+        Ok(())
+    }
+
+    fn emit_dict_impl(
+        &mut self,
+        item: &syn::ItemImpl,
+        type_name: &str,
+        trait_name: &str,
+    ) -> Lowered {
+        let dict = format!("{}_{}", type_name, trait_name);
+        self.add_line(&format!("local {} = {{}}", dict));
+
+        let previous_self_type = self.self_type.replace(type_name.to_string());
+        let impl_generics = type_registry::type_param_names(&item.generics);
+        let self_param = generic_alias(type_name, &impl_generics);
+
+        for impl_item in &item.items {
+            let syn::ImplItem::Fn(method) = impl_item else {
+                continue;
+            };
+
+            if !self.options.cfg.is_enabled(&method.attrs) {
+                continue;
+            }
+
+            self.flush_comments_before(method.span().start().line);
+
+            self.impl_type_params = impl_generics.clone();
+            if method.sig.receiver().is_some() {
+                self.extra_params = vec![("self".to_string(), self_param.clone())];
+            }
+
+            let qualified_name = format!("{}.{}", dict, method.sig.ident);
+            self.transpile_function(
+                &method.attrs,
+                &method.sig,
+                &method.block,
+                &qualified_name,
+                type_name,
+                &[],
+            )?;
+
+            self.skip_comments_through(method.span().end().line);
+        }
+
+        let inherited: Vec<syn::TraitItemFn> = self
+            .types
+            .traits
+            .get(trait_name)
+            .map(|trait_item| {
+                type_registry::default_methods(trait_item)
+                    .filter(|method| {
+                        !item.items.iter().any(|impl_item| {
+                            matches!(impl_item, syn::ImplItem::Fn(overridden) if overridden.sig.ident == method.sig.ident)
+                        })
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        for method in inherited {
+            if !self.options.cfg.is_enabled(&method.attrs) {
+                continue;
+            }
+
+            // Inherited defaults forward to the trait table with this implementation's dictionary
+            self.impl_type_params = impl_generics.clone();
+            let (generics, params, return_type) =
+                self.declared_signature(&method.sig, method.default.as_ref(), Some(&self_param));
+
+            let mut args = vec![dict.clone()];
+            if method.sig.receiver().is_some() {
+                args.push("self".to_string());
+            }
+            args.extend(method.sig.inputs.iter().filter_map(|input| match input {
+                syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                    _ => None,
+                },
+                syn::FnArg::Receiver(_) => None,
+            }));
+
+            let method_name = method.sig.ident.to_string();
+            let call = format!(
+                "{}({})",
+                member_access(trait_name, &method_name),
+                args.join(", ")
+            );
+            self.add_line(&function_header(
+                &format!("{}.{}", dict, method_name),
+                &generics,
+                &params,
+                &return_type
+                    .as_ref()
+                    .map_or(String::new(), |ty| format!(": {}", ty)),
+            ));
+            self.indent_manager.increase();
+            match return_type {
+                Some(_) => self.add_line(&format!("return {}", call)),
+                None => self.add_line(&call),
+            }
+            self.indent_manager.decrease();
+            self.add_line("end");
+        }
+
+        self.self_type = previous_self_type;
+        self.impl_type_params.clear();
+
+        Ok(())
+    }
+
+    fn default_method_traits(&self, file: &syn::File) -> Vec<(String, Vec<String>)> {
+        let mut implementors: Vec<(String, Vec<String>)> = Vec::new();
+
+        for item in &file.items {
+            let syn::Item::Impl(item_impl) = item else {
+                continue;
+            };
+
+            let Some((_, path, _)) = &item_impl.trait_ else {
+                continue;
+            };
+
+            let Some(type_name) = type_registry::type_name(&item_impl.self_ty) else {
+                continue;
+            };
+
+            let trait_name = path.segments.last().unwrap().ident.to_string();
+            let is_enabled = self.options.cfg.is_enabled(&item_impl.attrs)
+                && self
+                    .types
+                    .traits
+                    .get(&trait_name)
+                    .is_some_and(|item| self.options.cfg.is_enabled(&item.attrs));
+
+            if !is_enabled || !self.types.has_default_methods(&trait_name) {
+                continue;
+            }
+
+            match implementors.iter_mut().find(|(name, _)| *name == type_name) {
+                Some((_, traits)) => traits.push(trait_name),
+                None => implementors.push((type_name, vec![trait_name])),
+            }
+        }
+
+        implementors
+    }
+
+    // Emitted after every item so trait tables declared later in the file are already defined
+    fn emit_trait_inheritance(&mut self, file: &syn::File) {
+        if self.types.dict_dispatch {
+            return;
+        }
+
+        for (type_name, traits) in self.default_method_traits(file) {
+            let type_name = if self.types.is_extended(&type_name) {
+                extension_table(&type_name)
+            } else {
+                type_name
+            };
+
+            if let [trait_name] = traits.as_slice() {
+                self.add_line(&format!(
+                    "setmetatable({}, {{ __index = {} }})",
+                    type_name, trait_name
+                ));
+                continue;
+            }
+
+            let lookups: Vec<String> = traits
+                .iter()
+                .map(|trait_name| format!("{}[key]", trait_name))
+                .collect();
+
+            self.add_line(&format!("setmetatable({}, {{", type_name));
+            self.indent_manager.increase();
+            self.add_line("__index = function(_, key)");
+            self.indent_manager.increase();
+            self.add_line(&format!("return {}", lookups.join(" or ")));
+            self.indent_manager.decrease();
+            self.add_line("end,");
+            self.indent_manager.decrease();
+            self.add_line("})");
+        }
+    }
+
+    /// Structs marked `#[flamework(service)]` or `#[flamework(controller)]`, with the
+    /// Flamework decorator they are registered under. Only used with `--roblox-ts`.
+    fn flamework_classes(&self, file: &syn::File) -> Lowered<Vec<(String, &'static str)>> {
+        if !self.options.roblox_ts {
+            return Ok(Vec::new());
+        }
+
+        let mut classes = Vec::new();
+        for item in &file.items {
+            let syn::Item::Struct(item_struct) = item else {
+                continue;
+            };
+
+            let Some(attr) = item_struct
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("flamework"))
+            else {
+                continue;
+            };
+            let decorator = match attr.parse_args::<syn::Ident>() {
+                Ok(kind) if kind == "service" => "Service",
+                Ok(kind) if kind == "controller" => "Controller",
+                _ => {
+                    return Err(unsupported!(
+                        "Expected `#[flamework(service)]` or `#[flamework(controller)]`"
+                    ))
+                }
+            };
+
+            classes.push((item_struct.ident.to_string(), decorator));
+        }
+
+        Ok(classes)
+    }
+
+    // Flamework constructs singletons itself through `constructor` and calls lifecycle
+    // methods by their TypeScript names, so both are bridged onto the method table
+    fn emit_flamework_metadata(&mut self, type_name: &str, decorator: &str) {
+        self.ensure_method_table(type_name);
+
+        let methods: HashSet<String> = self
+            .types
+            .inherent_methods
+            .iter()
+            .filter(|(ty, _)| ty == type_name)
+            .map(|(_, method)| method.clone())
+            .collect();
+        let has_method = |method: &str| methods.contains(method);
+
+        if !has_method("constructor") {
+            self.add_line(&format!("function {}:constructor()", type_name));
+            if has_method("new") {
+                self.indent_manager.increase();
+                self.add_line(&format!("for key, value in {}.new() do", type_name));
+                self.indent_manager.increase();
+                self.add_line("self[key] = value");
+                self.indent_manager.decrease();
+                self.add_line("end");
+                self.indent_manager.decrease();
+            }
+            self.add_line("end");
+        }
+
+        let mut implements = Vec::new();
+        for (method, ts_name, interface) in FLAMEWORK_LIFECYCLE {
+            if has_method(method) {
+                self.add_line(&format!(
+                    "{}.{} = {}.{}",
+                    type_name, ts_name, type_name, method
+                ));
+                implements.push(format!("\"$:flamework@{}\"", interface));
+            }
+        }
+
+        self.add_line(&format!(
+            "FlameworkCore.Reflect.defineMetadata({}, \"identifier\", \"{}@{}\")",
+            type_name, self.options.module_name, type_name
+        ));
+
+        if !implements.is_empty() {
+            self.add_line(&format!(
+                "FlameworkCore.Reflect.defineMetadata({}, \"flamework:implements\", {{ {} }})",
+                type_name,
+                implements.join(", ")
+            ));
+        }
+
+        self.add_line(&format!(
+            "FlameworkCore.Reflect.decorate({}, \"$:flamework@{}\", FlameworkCore.{}, {{ {{}} }})",
+            type_name, decorator, decorator
+        ));
+    }
+
+    // Inline `mod` blocks become nested tables, all created up front so functions anywhere
+    // in the file can reach into them
+    fn emit_module_tables(&mut self, items: &[syn::Item], parent: &[String]) {
+        for item in items {
+            let syn::Item::Mod(item_mod) = item else {
+                continue;
+            };
+
+            let Some((_, content)) = &item_mod.content else {
+                continue;
+            };
+
+            if !self.options.cfg.is_enabled(&item_mod.attrs) {
+                continue;
+            }
+
+            let mut path = parent.to_vec();
+            path.push(item_mod.ident.to_string());
+
+            if parent.is_empty() {
+                self.add_line(&format!("local {} = {{}}", path[0]));
+            } else {
+                self.add_line(&format!("{} = {{}}", path.join(".")));
+            }
+
+            self.emit_module_tables(content, &path);
+        }
+    }
+
+    /// Requires the modules of other workspace crates the file uses, and binds the names it
+    /// imports from them. Imported type names also get a type alias for annotations.
+    fn emit_crate_requires(&mut self) {
+        let imports = self.crate_imports.clone();
+
+        for (module, path) in &imports.requires {
+            let require = if self.options.roblox_ts {
+                ts_import(path)
+            } else {
+                self.require(path)
+            };
+            self.add_line(&format!("local {} = {}", module, require));
+        }
+
+        for (name, module, item) in &imports.imports {
+            if imports.imports_type(name) {
+                self.add_line(&format!("type {} = {}.{}", name, module, item));
+            }
+            self.add_line(&format!("local {} = {}.{}", name, module, item));
+        }
+    }
+
+    // Required before any item so functions defined earlier in the file capture the local
+    fn emit_extern_requires(&mut self, file: &syn::File) -> Lowered {
+        for item in &file.items {
+            let syn::Item::ForeignMod(item_foreign) = item else {
+                continue;
+            };
+
+            if !self.options.cfg.is_enabled(&item_foreign.attrs) {
+                continue;
+            }
+
+            let extern_module = self
+                .types
+                .extern_module(item_foreign)
+                .map_err(|message| Unsupported::new(message).within(item_foreign.span()))?;
+            let Some((module, path)) = extern_module else {
+                continue;
+            };
+
+            let require = if self.options.roblox_ts {
+                ts_import(&path)
+            } else {
+                self.require(&path)
+            };
+            self.add_line(&format!("local {} = {} :: {{", module, require));
+            self.indent_manager.increase();
+
+            for foreign_item in &item_foreign.items {
+                let syn::ForeignItem::Fn(item_fn) = foreign_item else {
+                    return Err(unsupported!(
+                        "Only functions are supported in extern \"luau\" blocks"
+                    ));
+                };
+
+                if !self.options.cfg.is_enabled(&item_fn.attrs) {
+                    continue;
+                }
+
+                let (params, return_type) = self.signature_types(&item_fn.sig, None);
+                let params = params
+                    .iter()
+                    .map(|(name, ty)| format!("{}: {}", name, ty))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                let luau_name = &self.types.extern_fns[&item_fn.sig.ident.to_string()].luau_name;
+
+                self.add_line(&format!(
+                    "{}: ({}) -> {},",
+                    luau_name,
+                    params,
+                    return_type.as_deref().unwrap_or("()")
+                ));
+            }
+
+            self.indent_manager.decrease();
+            self.add_line("}");
+        }
+
+        Ok(())
+    }
+
+    fn emit_declarations(&mut self, file: &syn::File) {
+        let items: Vec<&syn::Item> = file
+            .items
+            .iter()
+            .filter(|item| match item {
+                syn::Item::Fn(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Impl(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Struct(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Enum(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Trait(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Const(item) => self.options.cfg.is_enabled(&item.attrs),
+                syn::Item::Static(item) => self.options.cfg.is_enabled(&item.attrs),
+                _ => false,
+            })
+            .collect();
+
+        for item in &items {
+            match item {
+                syn::Item::Struct(item_struct) => {
+                    let generics = type_registry::type_param_names(&item_struct.generics);
+                    if self.options.monomorphize && !generics.is_empty() {
+                        continue;
+                    }
+
+                    let previous_len = self.type_params.len();
+                    self.type_params.extend(generics.iter().cloned());
+                    let alias = generic_alias(&item_struct.ident.to_string(), &generics);
+                    self.emit_struct_type(item_struct, &alias);
+                    self.type_params.truncate(previous_len);
+                }
+
+                syn::Item::Enum(item_enum)
+                    if self.types.is_error_enum(&item_enum.ident.to_string()) =>
+                {
+                    let keyword = self.type_keyword(&item_enum.vis);
+                    self.add_line(&format!("{} {} = string", keyword, item_enum.ident));
+                }
+
+                syn::Item::Enum(item_enum) => {
+                    let Some(variants) = unit_variants(item_enum) else {
+                        continue;
+                    };
+
+                    let tags: Vec<String> = variants
+                        .iter()
+                        .map(|variant| format!("\"{}\"", variant))
+                        .collect();
+                    let ty = if self.types.has_discriminants(&item_enum.ident.to_string()) {
+                        "number".to_string()
+                    } else {
+                        tags.join(" | ")
+                    };
+                    let keyword = self.type_keyword(&item_enum.vis);
+                    self.add_line(&format!("{} {} = {}", keyword, item_enum.ident, ty));
+                }
+
+                syn::Item::Trait(item_trait) if self.types.dict_dispatch => {
+                    self.emit_trait_type(item_trait);
+                }
+
+                syn::Item::Trait(item_trait)
+                    if self.types.is_dyn_trait(&item_trait.ident.to_string()) =>
+                {
+                    self.emit_interface_type(item_trait);
+                }
+
+                _ => {}
+            }
+        }
+
+        for item in &items {
+            match item {
+                syn::Item::Fn(item_fn) if matches!(item_fn.vis, syn::Visibility::Public(_)) => {
+                    let is_generic =
+                        !type_registry::type_param_names(&item_fn.sig.generics).is_empty();
+                    if self.options.monomorphize && is_generic {
+                        continue;
+                    }
+
+                    let (generics, params, return_type) =
+                        self.declared_signature(&item_fn.sig, Some(&item_fn.block), None);
+                    let return_type = return_type.map_or(String::new(), |ty| format!(": {}", ty));
+
+                    self.add_line("");
+                    self.emit_doc_comments(doc_lines(&item_fn.attrs));
+                    self.add_line(&format!(
+                        "declare function {}{}({}){}",
+                        item_fn.sig.ident, generics, params, return_type
+                    ));
+                }
+
+                syn::Item::Const(item_const)
+                    if matches!(item_const.vis, syn::Visibility::Public(_)) =>
+                {
+                    let ty = self.map_type(&item_const.ty);
+                    self.add_line("");
+                    self.emit_doc_comments(doc_lines(&item_const.attrs));
+                    self.add_line(&format!("declare {}: {}", item_const.ident, ty));
+                }
+
+                syn::Item::Static(item_static)
+                    if matches!(item_static.vis, syn::Visibility::Public(_))
+                        && matches!(item_static.mutability, syn::StaticMutability::None) =>
+                {
+                    let ty = self.map_type(&item_static.ty);
+                    self.add_line("");
+                    self.emit_doc_comments(doc_lines(&item_static.attrs));
+                    self.add_line(&format!("declare {}: {}", item_static.ident, ty));
+                }
+
+                syn::Item::Struct(item_struct)
+                    if matches!(item_struct.vis, syn::Visibility::Public(_))
+                        && self.types.is_unit_struct(&item_struct.ident.to_string()) =>
+                {
+                    let name = item_struct.ident.to_string();
+                    if self.types.has_methods(&name) {
+                        self.declare_method_table(&name, &items);
+                    } else {
+                        self.add_line("");
+                        self.add_line(&format!("declare {}: {{}}", name));
+                    }
+
+                    self.add_line(&format!("export type {} = typeof({})", name, name));
+                }
+
+                syn::Item::Struct(item_struct)
+                    if matches!(item_struct.vis, syn::Visibility::Public(_))
+                        && self.types.has_methods(&item_struct.ident.to_string()) =>
+                {
+                    self.declare_method_table(&item_struct.ident.to_string(), &items);
+                }
+
+                syn::Item::Impl(item_impl) if self.types.dict_dispatch => {
+                    let Some((_, path, _)) = &item_impl.trait_ else {
+                        continue;
+                    };
+
+                    let segment = path.segments.last().unwrap();
+                    let trait_name = segment.ident.to_string();
+                    let Some(type_name) = type_registry::type_name(&item_impl.self_ty) else {
+                        continue;
+                    };
+
+                    if self.types.traits.contains_key(&trait_name) {
+                        let mut type_args = vec![type_name.clone()];
+                        type_args.extend(
+                            generic_type_args(&segment.arguments)
+                                .into_iter()
+                                .map(|ty| self.map_type(ty)),
+                        );
+
+                        self.add_line("");
+                        self.add_line(&format!(
+                            "declare {}_{}: {}<{}>",
+                            type_name,
+                            trait_name,
+                            trait_name,
+                            type_args.join(", ")
+                        ));
+                    }
+                }
+
+                syn::Item::Enum(item_enum)
+                    if matches!(item_enum.vis, syn::Visibility::Public(_))
+                        && self.types.is_error_enum(&item_enum.ident.to_string()) =>
+                {
+                    self.add_line("");
+                    self.add_line(&format!("declare {}: {{", item_enum.ident));
+                    self.indent_manager.increase();
+                    for variant in &item_enum.variants {
+                        let params: Vec<String> = variant
+                            .fields
+                            .iter()
+                            .map(|field| self.map_type(&field.ty))
+                            .collect();
+                        let ty = if matches!(variant.fields, syn::Fields::Unit) {
+                            "string".to_string()
+                        } else {
+                            format!("({}) -> string", params.join(", "))
+                        };
+                        self.add_line(&format!("read {}: {},", variant.ident, ty));
+                    }
+                    self.indent_manager.decrease();
+                    self.add_line("}");
+                }
+
+                syn::Item::Enum(item_enum)
+                    if matches!(item_enum.vis, syn::Visibility::Public(_)) =>
+                {
+                    let Some(variants) = unit_variants(item_enum) else {
+                        continue;
+                    };
+
+                    self.add_line("");
+                    let numeric = self.types.has_discriminants(&item_enum.ident.to_string());
+                    self.add_line(&format!("declare {}: {{", item_enum.ident));
+                    self.indent_manager.increase();
+                    for variant in variants {
+                        let ty = if numeric {
+                            "number".to_string()
+                        } else {
+                            format!("\"{}\"", variant)
+                        };
+                        self.add_line(&format!("read {}: {},", variant, ty));
+                    }
+                    self.indent_manager.decrease();
+                    self.add_line("}");
+                }
+
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_method_table(&mut self, type_name: &str, items: &[&syn::Item]) {
+        self.add_line("");
+        self.add_line(&format!("declare {}: {{", type_name));
+        self.indent_manager.increase();
+
+        let previous_self_type = self.self_type.replace(type_name.to_string());
+        let mut declares_destroy = false;
+
+        for item in items {
+            let syn::Item::Impl(item_impl) = item else {
+                continue;
+            };
+
+            if type_registry::type_name(&item_impl.self_ty).as_deref() != Some(type_name) {
+                continue;
+            }
+
+            let trait_name = item_impl
+                .trait_
+                .as_ref()
+                .and_then(|(_, path, _)| path.segments.last())
+                .map(|segment| segment.ident.to_string());
+
+            let is_dict_impl = trait_name
+                .as_ref()
+                .is_some_and(|trait_name| self.types.traits.contains_key(trait_name));
+            if is_dict_impl && self.types.dict_dispatch {
+                continue;
+            }
+
+            self.impl_type_params = type_registry::type_param_names(&item_impl.generics);
+            self.impl_const_params = type_registry::impl_const_params(item_impl);
+            let self_param = generic_alias(type_name, &self.impl_type_params);
+
+            for impl_item in &item_impl.items {
+                let syn::ImplItem::Fn(method) = impl_item else {
+                    continue;
+                };
+
+                let is_public =
+                    trait_name.is_some() || matches!(method.vis, syn::Visibility::Public(_));
+                let is_newindex =
+                    trait_name.as_deref() == Some("IndexMut") && method.sig.ident == "index_mut";
+
+                if !is_public || is_newindex || !self.options.cfg.is_enabled(&method.attrs) {
+                    continue;
+                }
+
+                let method_name =
+                    if trait_name.as_deref() == Some("Drop") && method.sig.ident == "drop" {
+                        "destroy".to_string()
+                    } else {
+                        method.sig.ident.to_string()
+                    };
+                declares_destroy |= method_name == "destroy";
+
+                self.declare_method(
+                    &method_name,
+                    &method.attrs,
+                    &method.sig,
+                    Some(&method.block),
+                    &self_param,
+                );
+            }
+
+            let inherited: Vec<syn::TraitItemFn> = trait_name
+                .as_ref()
+                .and_then(|trait_name| self.types.traits.get(trait_name))
+                .map(|item| {
+                    type_registry::default_methods(item)
+                        .filter(|method| {
+                            !item_impl.items.iter().any(|impl_item| {
+                                matches!(impl_item, syn::ImplItem::Fn(overridden) if overridden.sig.ident == method.sig.ident)
+                            })
+                        })
+                        .cloned()
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            for method in inherited {
+                if self.options.cfg.is_enabled(&method.attrs) {
+                    let method_name = method.sig.ident.to_string();
+                    self.declare_method(
+                        &method_name,
+                        &method.attrs,
+                        &method.sig,
+                        method.default.as_ref(),
+                        &self_param,
+                    );
+                }
+            }
+
+            self.impl_type_params.clear();
+            self.impl_const_params.clear();
+        }
+
+        let needs_destroy = self
+            .types
+            .get(type_name)
+            .is_some_and(|info| !info.droppable_fields.is_empty());
+        if needs_destroy && !declares_destroy {
+            self.add_line(&format!("destroy: (self: {}) -> (),", type_name));
+        }
+
+        if self.types.is_buffered(type_name) {
+            for line in [
+                "SIZE: number,".to_string(),
+                format!(
+                    "write_buffer: (self: {}, buf: buffer, offset: number) -> (),",
+                    type_name
+                ),
+                format!(
+                    "read_buffer: (buf: buffer, offset: number) -> {},",
+                    type_name
+                ),
+                format!("to_buffer: (self: {}) -> buffer,", type_name),
+                format!("from_buffer: (buf: buffer) -> {},", type_name),
+            ] {
+                self.add_line(&line);
+            }
+        }
+
+        if self.types.is_serializable(type_name) {
+            self.add_line(&format!(
+                "serialize: (self: {}) -> {{ [string]: any }},",
+                type_name
+            ));
+            self.add_line(&format!(
+                "deserialize: (data: {{ [string]: any }}) -> {},",
+                type_name
+            ));
+        }
+
+        self.self_type = previous_self_type;
+        self.indent_manager.decrease();
+        self.add_line("}");
+    }
+
+    fn declare_method(
+        &mut self,
+        name: &str,
+        attrs: &[syn::Attribute],
+        sig: &syn::Signature,
+        body: Option<&syn::Block>,
+        self_param: &str,
+    ) {
+        let (generics, params, return_type) = self.declared_signature(sig, body, Some(self_param));
+
+        self.emit_doc_comments(doc_lines(attrs));
+        self.add_line(&format!(
+            "{}: {}({}) -> {},",
+            name,
+            generics,
+            params,
+            return_type.unwrap_or_else(|| "()".to_string())
+        ));
+    }
+
+    fn declared_signature(
+        &mut self,
+        sig: &syn::Signature,
+        body: Option<&syn::Block>,
+        self_param: Option<&str>,
+    ) -> (String, String, Option<String>) {
+        let mut generics = self.impl_type_params.clone();
+        generics.extend(type_registry::type_param_names(&sig.generics));
+
+        let previous_len = self.type_params.len();
+        self.type_params.extend(generics.iter().cloned());
+        let substitutions = self.substitute_function_params(&sig.generics);
+        let (params, return_type) = self.signature_types(sig, body);
+        self.restore_substitutions(substitutions);
+        self.type_params.truncate(previous_len);
+
+        let mut params: Vec<String> = params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect();
+
+        if let (Some(self_param), Some(_)) = (self_param, sig.receiver()) {
+            params.insert(0, format!("self: {}", self_param));
+        }
+
+        let generics = if generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", generics.join(", "))
+        };
+
+        (generics, params.join(", "), return_type)
+    }
+
+    fn lower_file(&mut self, i: &syn::File) -> Lowered {
+        self.types = TypeRegistry::collect(i);
+        self.types.dict_dispatch = self.options.trait_dispatch == TraitDispatch::Dict;
+        self.names = NameGen::collect(i);
+        if self.options.opt_level >= 2 {
+            self.inlines = Inlines::collect(i, &self.types);
+        }
+        self.kind = if self.options.declarations || self.options.tests.is_some() {
+            Some(ScriptKind::Module)
+        } else {
+            match self.options.kind {
+                Some(kind) => Some(kind),
+                None => file_kind(&i.attrs)?,
+            }
+        };
+        if self.is_lune() && self.options.require_style == Some(RequireStyle::Instance) {
+            return Err(unsupported!(
+                "`--require-style instance` needs `--target roblox`; Lune requires modules by path"
+            ));
+        }
+        self.actor = i.attrs.iter().any(|attr| attr.path().is_ident("actor"));
+        if self.actor
+            && self.is_module()
+            && !self.options.declarations
+            && self.options.tests.is_none()
+        {
+            return Err(unsupported!(
+                "An `#![actor]` file must be a script, which is parented to an Actor"
+            ));
+        }
+
+        let file_docs = doc_lines(&i.attrs);
+        if self.options.moonwave {
+            let class_docs = moonwave::class_docs(&self.options.module_name, file_docs);
+            self.emit_doc_comments(class_docs);
+            self.add_line("");
+        } else if !file_docs.is_empty() {
+            let level = long_bracket_level(&file_docs.join("\n"));
+            self.add_line(&format!("--[{}[", level));
+            for line in file_docs {
+                self.add_line(&line);
+            }
+            self.add_line(&format!("]{}]", level));
+        }
+
+        if self.options.declarations {
+            self.emit_declarations(i);
+            return Ok(());
+        }
+
+        let (module_locals, module_exports) = self.module_items(i);
+        if self.is_module() && !module_locals.is_empty() {
+            self.add_line(&format!("local {}", module_locals.join(", ")));
+        }
+
+        self.emit_module_tables(&i.items, &[]);
+
+        let flamework = self.flamework_classes(i)?;
+        let imports_extern = i.items.iter().any(|item| {
+            matches!(item, syn::Item::ForeignMod(item_foreign)
+                if matches!(self.types.extern_module(item_foreign), Ok(Some(_))))
+        });
+
+        if self.options.roblox_ts && (imports_extern || !flamework.is_empty()) {
+            self.add_line("local TS = _G[script]");
+        }
+
+        if !flamework.is_empty() {
+            self.add_line(
+                "local FlameworkCore = TS.import(script, TS.getModule(script, \"@flamework\", \"core\").out)",
+            );
+        }
+
+        self.emit_extern_requires(i)?;
+        self.emit_crate_requires();
+
+        if self.types.uses_react() {
+            let path = self.options.react.as_deref().unwrap_or(REACT_PATH);
+            self.add_line(&format!("local React = {}", self.require(path)));
+        }
+
+        if let Some(path) = self
+            .options
+            .logger
+            .as_deref()
+            .filter(|_| uses_log_macros(i))
+        {
+            self.add_line(&format!("local Logger = {}", self.require(path)));
+        }
+
+        if self.options.tests == Some(TestFramework::Jest) {
+            self.add_line(&format!(
+                "local JestGlobals = {}",
+                self.require(JEST_GLOBALS_PATH)
+            ));
+            self.add_line(
+                "local describe, expect, it = JestGlobals.describe, JestGlobals.expect, JestGlobals.it",
+            );
+        }
+
+        self.visit_items(&i.items)?;
+        self.bind_actor_handlers(i)?;
+
+        if self.options.monomorphize {
+            self.emit_monomorphized_items()?;
+        }
+
+        self.emit_trait_inheritance(i);
+
+        for (type_name, decorator) in flamework {
+            self.emit_flamework_metadata(&type_name, decorator);
+        }
+
+        self.flush_comments_before(usize::MAX);
+
+        // A spec runs the tests instead of `main`, and returns the suite rather than exports
+        if let Some(framework) = self.options.tests {
+            self.emit_test_suite(i, framework)?;
+            return Ok(());
+        }
+
+        if let Some((main, first_comment)) = self.script_main.take() {
+            let resume_comment = self.next_comment;
+            self.next_comment = first_comment;
+
+            let start = self.output.len();
+            let region = self.begin_region(&syn::Item::Fn(main.clone()));
+            self.in_function = true;
+            self.in_script_main = true;
+            self.hoist_level = Some(self.indent_manager.level());
+            self.scopes.reset();
+            self.fn_output = match &main.sig.output {
+                syn::ReturnType::Type(_, ty) => Some((**ty).clone()),
+                syn::ReturnType::Default => None,
+            };
+            self.visit_block_body(&main.block)?;
+            self.in_script_main = false;
+            self.in_function = false;
+            self.hoist_level = None;
+            self.end_region(region);
+            self.emit_jump_tables_before(start);
+
+            if self.options.monomorphize {
+                self.emit_monomorphized_before(start)?;
+            }
+
+            self.next_comment = resume_comment;
+        } else if self.options.call_main && self.has_main {
+            self.add_line("main()");
+        }
+
+        if self.is_module() {
+            let re_exports = self.re_exported(&[]);
+            if module_exports.is_empty() && re_exports.is_empty() {
+                self.add_line("return {}");
+            } else {
+                self.add_line("return {");
+                self.indent_manager.increase();
+                for name in &module_exports {
+                    let key = self.renamed_exports.get(name).unwrap_or(name);
+                    self.add_line(&format!("{} = {},", table_key(key), name));
+                }
+                for (name, value) in re_exports {
+                    self.add_line(&format!("{} = {},", name, value));
+                }
+                self.indent_manager.decrease();
+                self.add_line("}");
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        for (ty, span) in self.any_fallbacks.take() {
+            self.warn(
+                "any",
+                format!("`{}` has no Luau type and is typed as `any`", ty),
+                span,
+            );
+        }
+
+        std::mem::take(&mut self.diagnostics)
+    }
+
+    fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            output: self.output.len(),
+            indent: self.indent_manager.level(),
+            scopes: self.scopes.depth(),
+            drop_scopes: self.drop_scopes.clone(),
+            diagnostics: self.diagnostics.len(),
+            next_comment: self.next_comment,
+            inlining: self.inlining.len(),
+            type_params: self.type_params.len(),
+            trait_dicts: self.trait_dicts.len(),
+            module_path: self.module_path.len(),
+            module_fns: self.module_fns.len(),
+            self_type: self.self_type.clone(),
+            fn_output: self.fn_output.clone(),
+            in_function: self.in_function,
+            in_script_main: self.in_script_main,
+            profiled: self.profiled,
+            tail_call: self.tail_call.clone(),
+            loops: self.loops.len(),
+            jump_tables: self.jump_tables.len(),
+            hoist_level: self.hoist_level,
+        }
+    }
+
+    fn restore(&mut self, checkpoint: Checkpoint) {
+        self.output.truncate(checkpoint.output);
+        self.origins
+            .retain(|(offset, _)| *offset <= checkpoint.output);
+        self.indent_manager.set_level(checkpoint.indent);
+        self.scopes.truncate(checkpoint.scopes);
+        self.drop_scopes = checkpoint.drop_scopes;
+        self.diagnostics.truncate(checkpoint.diagnostics);
+        self.next_comment = checkpoint.next_comment;
+        self.inlining.truncate(checkpoint.inlining);
+        self.type_params.truncate(checkpoint.type_params);
+        self.trait_dicts.truncate(checkpoint.trait_dicts);
+        self.module_path.truncate(checkpoint.module_path);
+        self.module_fns.truncate(checkpoint.module_fns);
+        self.self_type = checkpoint.self_type;
+        self.fn_output = checkpoint.fn_output;
+        self.in_function = checkpoint.in_function;
+        self.in_script_main = checkpoint.in_script_main;
+        self.profiled = checkpoint.profiled;
+        self.tail_call = checkpoint.tail_call;
+        self.loops.truncate(checkpoint.loops);
+        self.jump_tables.truncate(checkpoint.jump_tables);
+        self.hoist_level = checkpoint.hoist_level;
+        self.statement_call = false;
+    }
+
+    /// Emits a statement or item with `emit`. A construct in it that can't be lowered
+    /// fails transpilation with its location, or with `--lenient` is warned about, and
+    /// what it emitted is taken back for what `stub` emits or, with `--lenient skip`, nothing.
+    fn recover(
+        &mut self,
+        span: proc_macro2::Span,
+        emit: impl FnOnce(&mut Self) -> Lowered,
+        stub: impl FnOnce(&mut Self, &str),
+    ) -> Lowered {
+        if self.options.unsupported == options::Unsupported::Abort {
+            return emit(self).map_err(|error| error.within(span));
+        }
+
+        let checkpoint = self.checkpoint();
+        let Err(error) = emit(self) else {
+            return Ok(());
+        };
+        let error = error.within(span);
+        self.restore(checkpoint);
+
+        let stubbed = self.options.unsupported == options::Unsupported::Stub;
+        let outcome = if stubbed {
+            "replaced with an `error` stub"
+        } else {
+            "left out"
+        };
+        let span = error.span.unwrap_or(span);
+        self.warn(
+            "unsupported",
+            format!("{}; {}", error.message, outcome),
+            span,
+        );
+
+        if stubbed {
+            stub(self, &error.message);
+        }
+
+        Ok(())
+    }
+
+    /// A function whose stub raises the error when it's called.
+    fn recover_function(
+        &mut self,
+        span: proc_macro2::Span,
+        name: &str,
+        emit: impl FnOnce(&mut Self) -> Lowered,
+    ) -> Lowered {
+        self.recover(span, emit, |this, message| {
+            this.add_line(&function_header(name, "", "...", ""));
+            this.indent_manager.increase();
+            this.add_line(&unsupported_stub(message));
+            this.indent_manager.decrease();
+            this.add_line("end");
+        })
+    }
+
+    fn warn(&mut self, code: &'static str, message: String, span: proc_macro2::Span) {
+        self.diagnostics
+            .push(Diagnostic::warning(code, message, span));
+    }
+
+    fn note(&mut self, code: &'static str, message: String, span: proc_macro2::Span) {
+        self.diagnostics.push(Diagnostic::note(code, message, span));
+    }
+
+    fn add_line(&mut self, line: &str) {
+        self.output
+            .push_str(&format!("{}{}\n", self.indent_manager.get_indent(), line));
+    }
+
+    /// Under `--regions`, opens a `--#region` naming the item, like `fn net::send (line 12)`,
+    /// returning where the output was before it for [`Self::end_region`].
+    fn begin_region(&mut self, item: &syn::Item) -> Option<(usize, usize)> {
+        if !self.options.regions {
+            return None;
+        }
+
+        let label = match item {
+            syn::Item::Impl(item_impl) => {
+                let self_ty = type_registry::type_name(&item_impl.self_ty).unwrap_or_else(|| {
+                    quote::ToTokens::to_token_stream(&item_impl.self_ty).to_string()
+                });
+                match &item_impl.trait_ {
+                    Some((_, path, _)) => format!(
+                        "impl {} for {}",
+                        path.segments.last().unwrap().ident,
+                        self_ty
+                    ),
+                    None => format!("impl {}", self_ty),
+                }
+            }
+            syn::Item::ForeignMod(_) => "extern block".to_string(),
+            _ => {
+                let (keyword, ident) = match item {
+                    syn::Item::Const(item) => ("const", &item.ident),
+                    syn::Item::Enum(item) => ("enum", &item.ident),
+                    syn::Item::Fn(item) => ("fn", &item.sig.ident),
+                    syn::Item::Mod(item) => ("mod", &item.ident),
+                    syn::Item::Static(item) => ("static", &item.ident),
+                    syn::Item::Struct(item) => ("struct", &item.ident),
+                    syn::Item::Trait(item) => ("trait", &item.ident),
+                    syn::Item::Type(item) => ("type", &item.ident),
+                    _ => return None,
+                };
+                let path = self
+                    .module_path
+                    .iter()
+                    .map(String::as_str)
+                    .chain([ident.to_string().as_str()])
+                    .collect::<Vec<_>>()
+                    .join("::");
+                format!("{} {}", keyword, path)
+            }
+        };
+
+        let start = self.output.len();
+        self.add_line(&format!(
+            "--#region {} (line {})",
+            label,
+            item.span().start().line
+        ));
+        Some((start, self.output.len()))
+    }
+
+    /// Closes a region, or removes it if the item left no output, like a `use` or `main`.
+    fn end_region(&mut self, region: Option<(usize, usize)>) {
+        let Some((start, opened)) = region else {
+            return;
+        };
+
+        if self.output.len() == opened {
+            self.output.truncate(start);
+            self.origins.retain(|(offset, _)| *offset <= start);
+        } else {
+            self.add_line("--#endregion");
+        }
+    }
+
+    fn flush_comments_before(&mut self, line: usize) {
+        while let Some(comment) = self.comments.get(self.next_comment) {
+            if comment.line >= line {
+                break;
+            }
+
+            let lines = if comment.block {
+                let level = long_bracket_level(&comment.text);
+                let mut lines = vec![format!("--[{}[", level)];
+                lines.extend(comment.text.lines().map(|line| line.trim().to_string()));
+                lines.push(format!("]{}]", level));
+                lines
+            } else if comment.text.is_empty() {
+                vec!["--".to_string()]
+            } else {
+                vec![format!("-- {}", comment.text)]
+            };
+
+            for line in lines {
+                self.add_line(&line);
+            }
+
+            self.next_comment += 1;
+        }
+    }
+
+    fn skip_comments_through(&mut self, line: usize) {
+        while self
+            .comments
+            .get(self.next_comment)
+            .is_some_and(|comment| comment.line <= line)
+        {
+            self.next_comment += 1;
+        }
+    }
+
+    fn emit_doc_comments(&mut self, lines: Vec<String>) {
+        for line in lines {
+            if line.is_empty() {
+                self.add_line("---");
+            } else {
+                self.add_line(&format!("--- {}", line));
+            }
+        }
+    }
+
+    fn visit_block_body(&mut self, block: &syn::Block) -> Lowered {
+        self.visit_block_stmts(block, BlockTail::Discard)?;
+
+        Ok(())
+    }
+
+    fn visit_block_stmts(&mut self, block: &syn::Block, tail_target: BlockTail) -> Lowered {
+        self.scopes.enter();
+
+        let owned_params = std::mem::take(&mut self.owned_droppable_params);
+        let enclosing_moved: Vec<HashSet<String>> = self
+            .drop_scopes
+            .iter()
+            .map(|scope| scope.moved.clone())
+            .collect();
+        self.drop_scopes.push(DropScope::new(owned_params));
+
+        let tail = match (block.stmts.last(), &tail_target) {
+            (Some(syn::Stmt::Expr(_, None)), BlockTail::Discard) => None,
+            (Some(syn::Stmt::Expr(expr, None)), BlockTail::Return)
+                if !is_value_expr(expr)
+                    && !matches!(
+                        expr,
+                        syn::Expr::If(_)
+                            | syn::Expr::Match(_)
+                            | syn::Expr::Loop(_)
+                            | syn::Expr::Block(_)
+                    ) =>
+            {
+                None
+            }
+            (Some(syn::Stmt::Expr(expr, None)), _) => Some(expr),
+            _ => None,
+        };
+
+        let body = if tail.is_some() {
+            &block.stmts[..block.stmts.len() - 1]
+        } else {
+            &block.stmts[..]
+        };
+
+        for stmt in body {
+            let tracked = self.track_moves(stmt);
+            match stmt {
+                syn::Stmt::Expr(expr, _) if self.is_tail_call(expr) => self.emit_tail_call(expr)?,
+                _ => self.visit_stmt(stmt)?,
+            }
+            self.check_moves(stmt, tracked)?;
+        }
+
+        let tail_moves = tail
+            .and(block.stmts.last())
+            .map(|stmt| (stmt, self.track_moves(stmt)));
+        let droppable = self.drop_scopes.pop().unwrap_or_default();
+
+        if let Some(tail) = tail {
+            self.flush_comments_before(tail.span().start().line + 1);
+
+            let emit = |this: &mut Self| {
+                match tail_target {
+                    BlockTail::Assign(target) => {
+                        this.emit_value_into(tail, target)?;
+                        this.emit_scope_destroys(&droppable);
+                    }
+                    _ if !is_value_expr(tail) && droppable.locals.is_empty() => {
+                        this.emit_return_value(tail)?
+                    }
+                    _ if !is_value_expr(tail) => {
+                        // The scope's locals are destroyed before returning the branches' value
+                        let result = this.names.fresh();
+                        this.add_line(&format!("local {}", result));
+                        this.emit_value_into(tail, &result)?;
+                        this.emit_scope_destroys(&droppable);
+                        this.emit_return(&result);
+                    }
+                    _ if this.is_tail_call(tail) => {
+                        this.emit_scope_destroys(&droppable);
+                        this.emit_tail_call(tail)?;
+                    }
+                    _ => {
+                        let value = this.transpile_expr(tail)?;
+                        this.emit_scope_destroys(&droppable);
+                        this.emit_return(&value);
+                    }
+                }
+
+                Ok(())
+            };
+            let enclosing = self.mark_origin(tail.span().start().line);
+            self.recover(tail.span(), emit, |this, message| {
+                this.add_line(&unsupported_stub(message))
+            })?;
+            self.mark_origin(enclosing);
+        } else {
+            self.emit_scope_destroys(&droppable);
+        }
+
+        if let Some((stmt, tracked)) = tail_moves {
+            self.check_moves(stmt, tracked)?;
+        }
+
+        let returns = matches!(tail_target, BlockTail::Return) || block_returns(block);
+        for (scope, moved) in self.drop_scopes.iter_mut().zip(enclosing_moved) {
+            if scope.moved == moved {
+                continue;
+            }
+
+            // A branch that returns leaves the locals it moves owned on the paths that fall
+            // through it, while one that falls through would need a drop flag
+            if !returns {
+                let changed = scope.moved.symmetric_difference(&moved).next().unwrap();
+                return Err(unsupported!(
+                    "`{}` is moved or reassigned on only some paths, so where it's dropped can't be preserved",
+                    changed
+                ));
+            }
+
+            self.returned_moves
+                .extend(scope.moved.difference(&moved).cloned());
+            scope.moved = moved;
+        }
+
+        self.flush_comments_before(block.brace_token.span.close().start().line);
+        self.scopes.exit();
+
+        Ok(())
+    }
+
+    /// Marks the live `Drop` locals `stmt` always moves out as moved before it runs, so
+    /// neither its exits nor the scope's end destroy them, and returns the ones left live
+    /// with the moves the enclosing statement's returning branches made so far.
+    fn track_moves(&mut self, stmt: &syn::Stmt) -> (Vec<String>, HashSet<String>) {
+        let mut live = Vec::new();
+        for scope in &mut self.drop_scopes {
+            let moved: Vec<String> = scope
+                .live()
+                .filter(|name| moves_local(stmt, name, false))
+                .cloned()
+                .collect();
+            scope.moved.extend(moved);
+            live.extend(scope.live().cloned());
+        }
+
+        (live, std::mem::take(&mut self.returned_moves))
+    }
+
+    /// Reports the locals `stmt` moves out on only some paths, other than through branches
+    /// that return, which would need a drop flag to destroy on the others.
+    fn check_moves(
+        &mut self,
+        stmt: &syn::Stmt,
+        (live, returned): (Vec<String>, HashSet<String>),
+    ) -> Lowered {
+        for name in live {
+            if !self.returned_moves.contains(&name) && moves_local(stmt, &name, true) {
+                return Err(unsupported!(
+                    "`{}` is moved on only some paths, so where it's dropped can't be preserved",
+                    name
+                )
+                .within(stmt.span()));
+            }
+        }
+
+        self.returned_moves.extend(returned);
+        Ok(())
+    }
+
+    /// A block statement as a `do` block, so its locals don't shadow the enclosing ones after it.
+    fn emit_block_stmt(&mut self, expr_block: &syn::ExprBlock) -> Lowered {
+        if expr_block.label.is_some() {
+            return Err(unsupported!("Labeled blocks are not supported"));
+        }
+
+        self.add_line("do");
+        self.indent_manager.increase();
+        self.visit_block_stmts(&expr_block.block, BlockTail::Discard)?;
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    fn emit_scope_destroys(&mut self, droppable: &DropScope) {
+        for line in self.scope_destroys(droppable) {
+            self.add_line(&line);
+        }
+    }
+
+    /// Destroys the locals of a scope that haven't been moved out of it, last declared first.
+    fn scope_destroys(&self, droppable: &DropScope) -> Vec<String> {
+        let live: Vec<&String> = droppable.live().collect();
+        live.into_iter()
+            .rev()
+            .map(|name| self.local_destroy(name, droppable))
+            .collect()
+    }
+
+    /// Destroys the live locals of the scopes from `depth` in, innermost first, for a
+    /// `return`, `break` or `continue` that leaves them.
+    fn exit_destroys(&self, depth: usize) -> Vec<String> {
+        self.drop_scopes
+            .iter()
+            .skip(depth)
+            .rev()
+            .flat_map(|scope| self.scope_destroys(scope))
+            .collect()
+    }
+
+    fn local_destroy(&self, name: &str, scope: &DropScope) -> String {
+        if let Some(ty) = scope.elements.get(name) {
+            return format!(
+                "for _, element in {} do element:{}() end",
+                name,
+                self.types.destroy_method(Some(ty))
+            );
+        }
+
+        let ty = self
+            .scopes
+            .get(name)
+            .and_then(|binding| binding.ty.as_deref());
+        format!("{}:{}()", name, self.types.destroy_method(ty))
+    }
+
+    fn field_destroy(&self, type_name: &str, field: &str) -> String {
+        let ty = self.types.get(type_name).and_then(|info| {
+            info.item
+                .fields
+                .iter()
+                .find(|f| f.ident.as_ref().is_some_and(|ident| ident == field))
+                .and_then(|f| type_registry::type_name(&f.ty))
+        });
+
+        format!(
+            "{}:{}()",
+            member_access("self", field),
+            self.types.destroy_method(ty.as_deref())
+        )
+    }
+
+    /// The element type of a `Vec` local whose elements need destroying, from its annotation,
+    /// its `vec![...]` or the local it's moved from.
+    fn droppable_elements(&self, local: &syn::Local) -> Option<String> {
+        let ty = match &local.pat {
+            syn::Pat::Type(pat_type) => match &*pat_type.ty {
+                syn::Type::Path(type_path) if type_registry::type_name(&pat_type.ty)? == "Vec" => {
+                    let syn::PathArguments::AngleBracketed(args) =
+                        &type_path.path.segments.last()?.arguments
+                    else {
+                        return None;
+                    };
+                    match args.args.first()? {
+                        syn::GenericArgument::Type(ty) => type_registry::type_name(ty),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            },
+            _ => match &*local.init.as_ref()?.expr {
+                syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                    let elems = expr_macro
+                        .mac
+                        .parse_body_with(
+                            syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+                        )
+                        .ok()?;
+                    self.expr_type_of(elems.first()?)
+                }
+                syn::Expr::Path(path) => {
+                    let name = path.path.get_ident()?.to_string();
+                    return self
+                        .drop_scopes
+                        .iter()
+                        .rev()
+                        .find_map(|scope| scope.elements.get(&name))
+                        .cloned();
+                }
+                _ => None,
+            },
+        }?;
+
+        self.types.needs_destroy(&ty).then_some(ty)
+    }
+
+    fn local_type_of(&self, local: &syn::Local) -> Option<String> {
+        match &local.pat {
+            syn::Pat::Type(pat_type) => {
+                let type_name = type_registry::type_name(&pat_type.ty)?;
+                if type_name == "Self" {
+                    self.self_type.clone()
+                } else {
+                    Some(type_name)
+                }
+            }
+            _ => self.expr_type_of(&local.init.as_ref()?.expr),
+        }
+    }
+
+    /// The type name a `let` infers from its initializer.
+    fn expr_type_of(&self, init: &syn::Expr) -> Option<String> {
+        let type_name = match init {
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Neg(_)) => {
+                return self.expr_type_of(&unary.expr);
+            }
+            syn::Expr::Paren(paren) => return self.expr_type_of(&paren.expr),
+
+            // Arithmetic keeps its operands' type
+            syn::Expr::Binary(binary)
+                if matches!(
+                    binary.op,
+                    syn::BinOp::Add(_)
+                        | syn::BinOp::Sub(_)
+                        | syn::BinOp::Mul(_)
+                        | syn::BinOp::Div(_)
+                        | syn::BinOp::Rem(_)
+                        | syn::BinOp::BitAnd(_)
+                        | syn::BinOp::BitOr(_)
+                        | syn::BinOp::BitXor(_)
+                        | syn::BinOp::Shl(_)
+                        | syn::BinOp::Shr(_)
+                ) =>
+            {
+                return self
+                    .expr_type_of(&binary.left)
+                    .or_else(|| self.expr_type_of(&binary.right));
+            }
+            syn::Expr::Binary(_) => Some("bool".to_string()),
+
+            syn::Expr::Path(path) => {
+                let name = path.path.get_ident()?.to_string();
+                match self.scopes.get(&name) {
+                    Some(binding) => binding.ty.clone(),
+                    // A unit struct's name is also its value
+                    None => self.types.is_struct(&name).then_some(name),
+                }
+            }
+
+            syn::Expr::Struct(expr_struct) => expr_struct
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string()),
+
+            syn::Expr::Lit(lit) => match &lit.lit {
+                syn::Lit::Int(_) => Some("i64".to_string()),
+                syn::Lit::Float(_) => Some("f64".to_string()),
+                syn::Lit::Str(_) => Some("String".to_string()),
+                syn::Lit::Bool(_) => Some("bool".to_string()),
+                syn::Lit::Char(_) => Some("char".to_string()),
+                _ => None,
+            },
+
+            syn::Expr::Call(call) => match &*call.func {
+                syn::Expr::Path(path) if path.path.segments.len() == 1 => {
+                    let name = path.path.segments[0].ident.to_string();
+                    if name == "Self" || self.types.is_struct(&name) {
+                        Some(name)
+                    } else {
+                        self.types
+                            .fn_outputs
+                            .get(&name)
+                            .cloned()
+                            .or_else(|| self.api_type_of(init))
+                    }
+                }
+
+                syn::Expr::Path(path) if spawns_thread(&path.path) => Some("Thread".to_string()),
+
+                syn::Expr::Path(path) if path.path.segments.len() >= 2 => {
+                    let segments = &path.path.segments;
+                    self.api_type_of(init)
+                        .or_else(|| Some(segments[segments.len() - 2].ident.to_string()))
+                }
+
+                _ => None,
+            },
+
+            init => self
+                .api_type_of(init)
+                .or_else(|| self.runtime_type_of(init)),
+        }?;
+
+        if type_name == "Self" {
+            self.self_type.clone()
+        } else {
+            Some(type_name)
+        }
+    }
+
+    fn emit_struct_type(&mut self, item: &syn::ItemStruct, alias: &str) {
+        let name = item.ident.to_string();
+
+        if let syn::Fields::Unnamed(fields) = &item.fields {
+            let mut fields = fields.unnamed.iter().map(|field| self.map_type(&field.ty));
+            let ty = if self.types.is_newtype(&name) {
+                fields.next().unwrap()
+            } else {
+                tuple_type(fields)
+            };
+
+            self.emit_doc_comments(doc_lines(&item.attrs));
+            let keyword = self.type_keyword(&item.vis);
+            self.add_line(&format!("{} {} = {}", keyword, alias, ty));
+            return;
+        }
+
+        let syn::Fields::Named(fields) = &item.fields else {
+            return;
+        };
+
+        let readonly = self.types.is_readonly(&name);
+        let fields: Vec<(String, String)> = fields
+            .named
+            .iter()
+            .filter(|field| self.options.cfg.is_enabled(&field.attrs))
+            .map(|field| {
+                let field_name = field.ident.as_ref().unwrap().to_string();
+                (field_name, self.map_type(&field.ty))
+            })
+            .collect();
+
+        let docs = doc_lines(&item.attrs);
+        if self.options.moonwave {
+            let interface = moonwave::InterfaceDocs {
+                within: &self.options.module_name,
+                name: alias,
+                fields: &fields,
+            };
+
+            self.emit_doc_comments(moonwave::interface_docs(&interface, docs));
+        } else {
+            self.emit_doc_comments(docs);
+        }
+
+        let keyword = self.type_keyword(&item.vis);
+        self.add_line(&format!("{} {} = {{", keyword, alias));
+        self.indent_manager.increase();
+
+        for (field_name, field_type) in &fields {
+            let modifier = if readonly { "read " } else { "" };
+            self.add_line(&format!(
+                "{}{}: {},",
+                modifier,
+                table_key(field_name),
+                field_type
+            ));
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("}");
+    }
+
+    /// Whether a struct has a table of its own by its name, which the instantiations of a
+    /// generic one don't under `--monomorphize`.
+    fn has_struct_table(&self, name: &str) -> bool {
+        (self.types.has_methods(name) || self.types.is_unit_struct(name))
+            && !(self.options.monomorphize && self.types.is_generic_struct(name))
+    }
+
+    fn ensure_method_table(&mut self, type_name: &str) {
+        if !self.method_tables.insert(type_name.to_string()) {
+            return;
+        }
+
+        self.add_line(&format!("local {} = {{}}", type_name));
+
+        if self.types.implements_index(type_name) {
+            self.add_line(&format!("{}.__index = function(self, key)", type_name));
+            self.indent_manager.increase();
+            self.add_line(&format!("local method = {}[key]", type_name));
+            self.add_line("if method ~= nil then");
+            self.indent_manager.increase();
+            self.add_line("return method");
+            self.indent_manager.decrease();
+            self.add_line("end");
+            self.add_line(&format!("return {}.index(self, key)", type_name));
+            self.indent_manager.decrease();
+            self.add_line("end");
+        } else {
+            self.add_line(&format!("{}.__index = {}", type_name, type_name));
+        }
+
+        let Some(info) = self.types.get(type_name) else {
+            return;
+        };
+
+        if info.implements_drop || info.droppable_fields.is_empty() {
+            return;
+        }
+
+        let fields = info.droppable_fields.clone();
+        self.add_line(&format!("function {}:destroy()", type_name));
+        self.indent_manager.increase();
+
+        for field in fields {
+            let line = self.field_destroy(type_name, &field);
+            self.add_line(&line);
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+    }
+
+    fn transpile_newindex(&mut self, type_name: &str, method: &syn::ImplItemFn) -> Lowered {
+        let key = method
+            .sig
+            .inputs
+            .iter()
+            .find_map(|arg| match arg {
+                syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                    _ => None,
+                },
+                _ => None,
+            })
+            .ok_or_else(|| unsupported!("IndexMut::index_mut must take an index parameter"))?;
+
+        let place = match method.block.stmts.last() {
+            Some(syn::Stmt::Expr(syn::Expr::Reference(reference), None)) => &*reference.expr,
+            _ => {
+                return Err(unsupported!(
+                "IndexMut::index_mut must end in a place expression like `&mut self.items[index]`"
+            ))
+            }
+        };
+
+        let previous_self_type = self.self_type.replace(type_name.to_string());
+        self.scopes.reset();
+        self.add_local_variable("self", true, true);
+        self.add_local_variable(&key, false, true);
+        let place = self.transpile_expr(place)?;
+        self.self_type = previous_self_type;
+
+        self.add_line(&format!(
+            "{}.__newindex = function(self, {}, value)",
+            type_name, key
+        ));
+        self.indent_manager.increase();
+        self.add_line(&format!("{} = value", place));
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    fn transpile_function(
+        &mut self,
+        attrs: &[syn::Attribute],
+        sig: &syn::Signature,
+        block: &syn::Block,
+        name: &str,
+        within: &str,
+        epilogue: &[String],
+    ) -> Lowered {
+        self.in_function = true;
+        let in_script_main = std::mem::replace(&mut self.in_script_main, false);
+        let profiled = (self.options.profile
+            || attrs.iter().any(|attr| attr.path().is_ident("profile")))
+            && luau_attribute(attrs)?.is_none();
+        let enclosing_profiled = std::mem::replace(&mut self.profiled, profiled);
+        let enclosing_drops = std::mem::take(&mut self.drop_scopes);
+        let string_buffers = std::mem::replace(&mut self.string_buffers, string_buffers(block));
+        self.scopes.reset();
+        self.fn_output = match &sig.output {
+            syn::ReturnType::Type(_, ty) => Some((**ty).clone()),
+            syn::ReturnType::Default => None,
+        };
+
+        let mut generics = self.impl_type_params.clone();
+        generics.extend(type_registry::type_param_names(&sig.generics));
+
+        let generics_str = if self.options.monomorphize {
+            for param in &generics {
+                self.type_substitutions
+                    .entry(param.clone())
+                    .or_insert_with(|| "any".to_string());
+            }
+
+            String::new()
+        } else if generics.is_empty() {
+            String::new()
+        } else {
+            format!("<{}>", generics.join(", "))
+        };
+
+        let previous_type_params = self.type_params.len();
+        self.type_params.extend(generics);
+        let substitutions = self.substitute_function_params(&sig.generics);
+
+        let mut params = std::mem::take(&mut self.extra_params);
+        let previous_dicts = self.trait_dicts.len();
+
+        if self.types.dict_dispatch {
+            for (param, trait_name, dict_type) in self.trait_bounds(&sig.generics) {
+                let dict = format!("{}_{}", trait_name, param);
+                params.push((dict.clone(), dict_type));
+                self.trait_dicts.push((param, trait_name, dict));
+            }
+        }
+
+        let (sig_params, return_type) = self.signature_types(sig, Some(block));
+        params.extend(sig_params);
+
+        let params_str = params
+            .iter()
+            .map(|(name, ty)| format!("{}: {}", name, ty))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        let ret_type_str = return_type
+            .as_ref()
+            .map_or(String::new(), |ty| format!(": {}", ty));
+
+        let docs = doc_lines(attrs);
+        if self.options.moonwave {
+            let short_name = name.rsplit([':', '.']).next().unwrap_or(name);
+            let function = moonwave::FunctionDocs {
+                within,
+                name: short_name,
+                params: &params,
+                return_type: return_type.as_deref(),
+            };
+
+            self.emit_doc_comments(moonwave::function_docs(&function, docs));
+        } else {
+            self.emit_doc_comments(docs);
+        }
+
+        self.add_line(&function_header(
+            name,
+            &generics_str,
+            &params_str,
+            &ret_type_str,
+        ));
+
+        for arg in &sig.inputs {
+            match arg {
+                syn::FnArg::Receiver(receiver) => {
+                    self.add_local_variable("self", receiver.mutability.is_some(), true);
+
+                    let owns_self = receiver.reference.is_none()
+                        && self
+                            .self_type
+                            .as_ref()
+                            .is_some_and(|ty| self.types.needs_destroy(ty))
+                        && !name.ends_with(":destroy");
+
+                    if owns_self {
+                        self.owned_droppable_params.push("self".to_string());
+                    }
+                }
+
+                syn::FnArg::Typed(pat_type) => {
+                    if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                        let name = pat_ident.ident.to_string();
+                        self.add_local_variable(&name, pat_ident.mutability.is_some(), true);
+
+                        let owned_type = type_registry::type_name(&pat_type.ty).map(|ty| {
+                            match (ty.as_str(), &self.self_type) {
+                                ("Self", Some(self_type)) => self_type.clone(),
+                                _ => ty,
+                            }
+                        });
+
+                        if owned_type
+                            .as_ref()
+                            .is_some_and(|ty| self.types.needs_destroy(ty))
+                        {
+                            self.owned_droppable_params.push(name.clone());
+                        }
+
+                        self.set_local_type(&name, owned_type);
+                        self.set_local_rust_type(&name, &pat_type.ty);
+                    }
+                }
+            }
+        }
+
+        self.indent_manager.increase();
+        if profiled {
+            let label = self.profile_label(name);
+            self.add_line(&format!("debug.profilebegin(\"{}\")", label));
+        }
+        self.emit_const_params(sig, block)?;
+
+        // A `Copy` value passed as `mut self` is the caller's copy in Rust, but the same
+        // table in Luau, so mutations must not leak back through the shared reference
+        let copies_self = sig
+            .receiver()
+            .is_some_and(|receiver| receiver.reference.is_none() && receiver.mutability.is_some());
+        if let Some(self_type) = self
+            .self_type
+            .clone()
+            .filter(|ty| self.types.is_copy(ty) && !self.types.is_newtype(ty))
+        {
+            if copies_self {
+                self.add_line(&format!(
+                    "self = setmetatable(table.clone(self), {})",
+                    self_type
+                ));
+            }
+        }
+
+        if let Some(raw) = luau_attribute(attrs)? {
+            for line in raw_luau_lines(&raw) {
+                self.add_line(&line);
+            }
+        } else {
+            let tail_target = if return_type.is_some() {
+                BlockTail::Return
+            } else {
+                BlockTail::Discard
+            };
+
+            match self.tail_call_loop(sig, block) {
+                Some(tail_call) => self.emit_tail_call_loop(block, tail_target, tail_call)?,
+                None => self.visit_block_stmts(block, tail_target)?,
+            }
+        }
+
+        self.owned_droppable_params.clear();
+
+        for line in epilogue {
+            self.add_line(line);
+        }
+
+        // A body that can run off its end ends the label there
+        let returns_last = matches!(
+            block.stmts.last(),
+            Some(syn::Stmt::Expr(syn::Expr::Return(_), _))
+        );
+        if profiled && return_type.is_none() && !returns_last {
+            self.add_line("debug.profileend()");
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        self.type_params.truncate(previous_type_params);
+        self.restore_substitutions(substitutions);
+        self.trait_dicts.truncate(previous_dicts);
+        self.in_function = false;
+        self.in_script_main = in_script_main;
+        self.profiled = enclosing_profiled;
+        self.drop_scopes = enclosing_drops;
+        self.string_buffers = string_buffers;
+
+        Ok(())
+    }
+
+    /// The microprofiler label of a function, like `Inventory::Item::new` for `Item.new` in
+    /// the `Inventory` module.
+    fn profile_label(&self, name: &str) -> String {
+        let path = name.replace([':', '.'], "::");
+        let module = &self.options.module_name;
+        if module.is_empty() || path.starts_with(&format!("{}::", module)) {
+            path
+        } else {
+            format!("{}::{}", module, path)
+        }
+    }
+
+    /// Emits `return value`, or a bare `return` for an empty `value`. The function's live
+    /// `Drop` locals are destroyed and a profiled function ends its label first, after
+    /// computing a value that does any work.
+    fn emit_return(&mut self, value: &str) {
+        let mut epilogue = self.exit_destroys(0);
+        if self.profiled {
+            epilogue.push("debug.profileend()".to_string());
+        }
+
+        if epilogue.is_empty() {
+            match value {
+                "" => self.add_line("return"),
+                _ => self.add_line(&format!("return {}", value)),
+            }
+            return;
+        }
+
+        let plain = value
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.');
+        let value = if plain {
+            value.to_string()
+        } else {
+            let result = self.names.fresh();
+            self.add_line(&format!("local {} = {}", result, value));
+            result
+        };
+        for line in epilogue {
+            self.add_line(&line);
+        }
+        self.add_line(format!("return {}", value).trim_end());
+    }
+
+    /// The `return` of a one-line early exit like `if x == nil then return nil end`, which
+    /// destroys the live `Drop` locals and ends a profiled function's label first.
+    fn return_keyword(&self) -> String {
+        let mut exit = self.exit_destroys(0);
+        if self.profiled {
+            exit.push("debug.profileend()".to_string());
+        }
+        exit.push("return".to_string());
+        exit.join(" ")
+    }
+
+    /// With `--opt tailrec`, the loop a function that calls itself in tail position becomes,
+    /// warning about calls to itself that stay recursive.
+    fn tail_call_loop(&mut self, sig: &syn::Signature, block: &syn::Block) -> Option<TailCall> {
+        if !self.options.optimizations.contains(&Optimization::Tailrec)
+            || sig.asyncness.is_some()
+            || !self.owned_droppable_params.is_empty()
+        {
+            return None;
+        }
+
+        let params: Option<Vec<String>> = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Receiver(_) => None,
+                syn::FnArg::Typed(pat_type) => Some(match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident)
+                        if pat_ident.subpat.is_none() && pat_type.attrs.is_empty() =>
+                    {
+                        Some(pat_ident.ident.to_string())
+                    }
+                    _ => None,
+                }),
+            })
+            .collect();
+        let params = params?;
+
+        let calls = self_calls(block, sig, self.self_type.is_some());
+        if let Some(span) = calls.others.first() {
+            self.warn(
+                "deep-recursion",
+                format!(
+                    "`{}` calls itself outside tail position, which can't become a loop; deep recursion can exhaust the Luau stack",
+                    sig.ident
+                ),
+                *span,
+            );
+        }
+
+        if calls.tail.is_empty() || params.iter().any(|param| calls.bound.contains(param)) {
+            return None;
+        }
+        let tail_calls = calls.tail;
+
+        self.note(
+            "tail-recursion",
+            format!(
+                "`{}` calls itself in tail position; rewritten into a loop",
+                sig.ident
+            ),
+            sig.ident.span(),
+        );
+
+        let last = match block.stmts.last() {
+            Some(syn::Stmt::Expr(syn::Expr::Return(expr_return), _)) => expr_return.expr.as_deref(),
+            Some(syn::Stmt::Expr(expr, _)) => Some(expr),
+            _ => None,
+        };
+        let last = last
+            .map(|expr| span_key(expr.span()))
+            .filter(|key| tail_calls.contains(key));
+
+        Some(TailCall {
+            calls: tail_calls,
+            params,
+            last,
+        })
+    }
+
+    fn emit_tail_call_loop(
+        &mut self,
+        block: &syn::Block,
+        tail: BlockTail,
+        tail_call: TailCall,
+    ) -> Lowered {
+        let ends_in_call = tail_call.last.is_some();
+        self.tail_call = Some(tail_call);
+
+        self.add_line("while true do");
+        self.indent_manager.increase();
+        self.visit_block_stmts(block, tail)?;
+
+        // A function without a value leaves the loop at the end of its body
+        if matches!(tail, BlockTail::Discard) && !ends_in_call {
+            self.add_line("break");
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+        self.tail_call = None;
+
+        Ok(())
+    }
+
+    fn is_tail_call(&self, expr: &syn::Expr) -> bool {
+        self.tail_call
+            .as_ref()
+            .is_some_and(|tail_call| tail_call.calls.contains(&span_key(expr.span())))
+    }
+
+    /// Rebinds the parameters to a tail call's arguments, all at once since each can read
+    /// any of them, and starts the loop over.
+    fn emit_tail_call(&mut self, expr: &syn::Expr) -> Lowered {
+        let args: Vec<&syn::Expr> = match expr {
+            syn::Expr::Call(call) => call.args.iter().collect(),
+            syn::Expr::MethodCall(method_call) => method_call.args.iter().collect(),
+            _ => unreachable!("tail calls are calls"),
+        };
+        let tail_call = self.tail_call.as_ref().expect("Expected a tail call loop");
+        let params = tail_call.params.clone();
+        let is_last = tail_call.last == Some(span_key(expr.span()));
+
+        let (names, values): (Vec<String>, Vec<String>) = params
+            .into_iter()
+            .zip(args)
+            .map(|(param, arg)| Ok((param, self.transpile_expr(arg)?)))
+            .collect::<Lowered<Vec<_>>>()?
+            .into_iter()
+            .filter(|(param, value)| param != value)
+            .unzip();
+
+        if !names.is_empty() {
+            self.add_line(&format!("{} = {}", names.join(", "), values.join(", ")));
+        }
+
+        if !is_last {
+            self.add_line("continue");
+        }
+
+        Ok(())
+    }
+
+    /// At `--opt-level 2`, the body a call to a small function expands to, with the function
+    /// it calls.
+    #[allow(clippy::type_complexity)]
+    fn inline_call(&self, expr: &syn::Expr) -> Option<((Option<String>, String), syn::Expr)> {
+        let (owner, name, receiver, args): (Option<String>, _, _, Vec<&syn::Expr>) = match expr {
+            syn::Expr::Call(call) => {
+                let syn::Expr::Path(path) = &*call.func else {
+                    return None;
+                };
+                if path.qself.is_some() {
+                    return None;
+                }
+
+                let segments: Vec<&syn::PathSegment> = path.path.segments.iter().collect();
+                match segments[..] {
+                    [name] if !self.is_local_variable(&name.ident.to_string()) => {
+                        (None, &name.ident, None, call.args.iter().collect())
+                    }
+                    [owner, name] => {
+                        let owner = match owner.ident.to_string().as_str() {
+                            "Self" => self.self_type.clone()?,
+                            owner => owner.to_string(),
+                        };
+                        (Some(owner), &name.ident, None, call.args.iter().collect())
+                    }
+                    _ => return None,
+                }
+            }
+
+            syn::Expr::MethodCall(method_call) if method_call.turbofish.is_none() => {
+                let owner = self.receiver_type_name(&method_call.receiver)?;
+                (
+                    Some(owner),
+                    &method_call.method,
+                    Some(&*method_call.receiver),
+                    method_call.args.iter().collect(),
+                )
+            }
+
+            _ => return None,
+        };
+
+        let key = (owner, name.to_string());
+        let inline_fn = self.inlines.get(key.0.as_deref(), &key.1)?;
+        if self.inlining.contains(&key)
+            || inline_fn.has_receiver() != receiver.is_some()
+            || !inline_fn.accepts(receiver, args.iter().copied())
+            || inline_fn
+                .free_names()
+                .iter()
+                .any(|free| self.is_local_variable(free))
+        {
+            return None;
+        }
+
+        let inlined = inline_fn.expand(key.0.as_deref(), receiver, args.into_iter());
+        Some((key, inlined))
+    }
+
+    /// The Luau types of a signature's parameters and return type; an `impl Trait` return type
+    /// is the type `body` returns, where that is known.
+    fn signature_types(
+        &self,
+        sig: &syn::Signature,
+        body: Option<&syn::Block>,
+    ) -> (Vec<(String, String)>, Option<String>) {
+        let const_params = self
+            .const_param_sources(sig)
+            .into_iter()
+            .filter(|(_, source)| source.is_none())
+            .map(|(name, _)| (name, "number".to_string()));
+
+        let params = const_params
+            .chain(sig.inputs.iter().filter_map(|arg| {
+                if let syn::FnArg::Typed(pat_type) = arg {
+                    if !self.options.cfg.is_enabled(&pat_type.attrs) {
+                        return None;
+                    }
+
+                    if let syn::Pat::Ident(pat_ident) = &*pat_type.pat {
+                        Some((pat_ident.ident.to_string(), self.map_type(&pat_type.ty)))
+                    } else {
+                        None
+                    }
+                } else {
+                    None
+                }
+            }))
+            .collect();
+
+        let return_type = match &sig.output {
+            syn::ReturnType::Type(_, ty) if matches!(**ty, syn::Type::ImplTrait(_)) => body
+                .and_then(|body| self.returned_type(sig, body))
+                .or_else(|| Some(self.map_type(ty))),
+            syn::ReturnType::Type(_, ty) => Some(self.map_type(ty)),
+            syn::ReturnType::Default => None,
+        };
+
+        (params, return_type)
+    }
+
+    /// Where each const generic parameter of a function being lowered is read from, like
+    /// `type_registry::const_param_sources`.
+    fn const_param_sources(&self, sig: &syn::Signature) -> Vec<(String, Option<String>)> {
+        let self_struct = self
+            .self_type
+            .as_ref()
+            .and_then(|name| self.types.get(name))
+            .map(|info| &info.item);
+        type_registry::const_param_sources(&self.impl_const_params, sig, self_struct)
+    }
+
+    /// Declares the const generic parameters a function body reads as locals, each the length
+    /// of the array it comes from or the parameter passing it.
+    fn emit_const_params(&mut self, sig: &syn::Signature, block: &syn::Block) -> Lowered {
+        for (name, source) in self.const_param_sources(sig) {
+            let from_impl = self
+                .impl_const_params
+                .iter()
+                .any(|(param, _)| *param == name);
+            if source.is_none() && from_impl && sig.receiver().is_some() {
+                return Err(unsupported!(
+                    "`{}` can't be read from `self`; give `{}` a field of type `[T; {}]`",
+                    name,
+                    self.self_type.as_deref().unwrap_or("the type"),
+                    name
+                ));
+            }
+
+            self.add_local_variable(&name, false, true);
+            if let Some(source) = source.filter(|_| mentions_ident(block, &name)) {
+                self.add_line(&format!("local {} = {}", name, source));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The const generic arguments a call passes ahead of its arguments, from its turbofish,
+    /// the type the `let` it initializes declares, or a parameter of the same name in scope.
+    fn call_const_args(&mut self, call: &syn::ExprCall) -> Lowered<Vec<String>> {
+        let syn::Expr::Path(path) = &*call.func else {
+            return Ok(Vec::new());
+        };
+        let segments: Vec<&syn::PathSegment> = path.path.segments.iter().collect();
+        let (type_segment, fn_segment) = match segments.as_slice() {
+            [fn_segment] => (None, *fn_segment),
+            [.., type_segment, fn_segment] => (Some(*type_segment), *fn_segment),
+            [] => return Ok(Vec::new()),
+        };
+
+        let key = match type_segment {
+            Some(segment) if segment.ident == "Self" => match &self.self_type {
+                Some(self_type) => format!("{}::{}", self_type, fn_segment.ident),
+                None => return Ok(Vec::new()),
+            },
+            Some(segment) => format!("{}::{}", segment.ident, fn_segment.ident),
+            None => fn_segment.ident.to_string(),
+        };
+        let Some(const_fn) = self.types.const_fns.get(&key) else {
+            return Ok(Vec::new());
+        };
+
+        let explicit = |segment: Option<&syn::PathSegment>, index: usize| {
+            let syn::PathArguments::AngleBracketed(args) = &segment?.arguments else {
+                return None;
+            };
+            args.args.iter().nth(index).cloned()
+        };
+        let expected = self.expected_type.clone();
+        let params = const_fn.params.clone();
+        let output = const_fn.output.clone();
+
+        params
+            .into_iter()
+            .map(|(name, position)| {
+                let arg = match position {
+                    type_registry::ConstPosition::Type(index) => explicit(type_segment, index),
+                    type_registry::ConstPosition::Fn(index) => explicit(Some(fn_segment), index),
+                };
+                let inferred = || {
+                    output
+                        .as_ref()
+                        .zip(expected.as_ref())
+                        .and_then(|(output, expected)| unify_const_param(output, expected, &name))
+                };
+
+                match (arg, inferred()) {
+                    (Some(syn::GenericArgument::Const(expr)), _) => self.transpile_expr(&expr),
+                    (Some(syn::GenericArgument::Type(ty)), _) => {
+                        self.transpile_expr(&syn::parse_quote!(#ty))
+                    }
+                    (_, Some(expr)) => self.transpile_expr(&expr),
+                    _ if self.is_local_variable(&name) => Ok(name),
+                    _ => {
+                        let example = match (position, type_segment) {
+                            (type_registry::ConstPosition::Type(_), Some(segment)) => {
+                                format!("{}::<3>::{}()", segment.ident, fn_segment.ident)
+                            }
+                            _ => format!("{}::<3>()", fn_segment.ident),
+                        };
+                        Err(unsupported!(
+                            "Could not infer the const parameter `{}` of `{}`; pass it like `{}`",
+                            name,
+                            key,
+                            example
+                        ))
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// The Luau type of the value a function body ends with, for an `impl Trait` return type.
+    fn returned_type(&self, sig: &syn::Signature, body: &syn::Block) -> Option<String> {
+        let tail = match body.stmts.last()? {
+            syn::Stmt::Expr(expr, None) => expr,
+            syn::Stmt::Expr(syn::Expr::Return(ret), Some(_)) => ret.expr.as_deref()?,
+            _ => return None,
+        };
+
+        match tail {
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("format") => {
+                Some("string".to_string())
+            }
+
+            syn::Expr::Path(path) => sig.inputs.iter().find_map(|input| match input {
+                syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                    syn::Pat::Ident(pat_ident) if path.path.is_ident(&pat_ident.ident) => {
+                        Some(self.map_type(&pat_type.ty))
+                    }
+                    _ => None,
+                },
+                syn::FnArg::Receiver(_) => None,
+            }),
+
+            _ => self.infer_luau_type(tail),
+        }
+    }
+
+    fn map_type(&self, rust_type: &syn::Type) -> String {
+        match rust_type {
+            syn::Type::Path(type_path) => {
+                let Some(segment) = type_path.path.segments.last() else {
+                    return self.any_fallback(rust_type);
+                };
+
+                let name = segment.ident.to_string();
+                let args = generic_type_args(&segment.arguments);
+
+                if type_path.path.segments.len() == 1 {
+                    if let Some(substitution) = self.type_substitutions.get(&name) {
+                        return substitution.clone();
+                    }
+
+                    if self.type_params.contains(&name) || self.crate_imports.imports_type(&name) {
+                        return name;
+                    }
+                }
+
+                // A type of another workspace crate's module, exported from its module table
+                if let [module, _] = &type_path.path.segments.iter().collect::<Vec<_>>()[..] {
+                    if self
+                        .crate_imports
+                        .requires_module(&module.ident.to_string())
+                    {
+                        let args: Vec<String> = args.iter().map(|arg| self.map_type(arg)).collect();
+                        return if args.is_empty() {
+                            format!("{}.{}", module.ident, name)
+                        } else {
+                            format!("{}.{}<{}>", module.ident, name, args.join(", "))
+                        };
+                    }
+                }
+
+                match name.as_str() {
+                    "i8" | "i16" | "i32" | "i64" | "isize" | "u8" | "u16" | "u32" | "u64"
+                    | "usize" | "f32" | "f64" => "number".to_string(),
+                    "bool" => "boolean".to_string(),
+                    "String" | "str" | "char" => "string".to_string(),
+
+                    "Vec" | "VecDeque" => format!("{{{}}}", self.map_type_arg(&args, 0)),
+                    "HashMap" | "BTreeMap" => format!(
+                        "{{ [{}]: {} }}",
+                        self.map_type_arg(&args, 0),
+                        self.map_type_arg(&args, 1)
+                    ),
+                    "HashSet" | "BTreeSet" => {
+                        format!("{{ [{}]: boolean }}", self.map_type_arg(&args, 0))
+                    }
+                    // `Some(())` is `true`
+                    "Option" => match self.map_type_arg(&args, 0) {
+                        unit if unit == "()" => "boolean?".to_string(),
+                        value => optional_type(value),
+                    },
+                    "Signal" if self.runtime_class(&name).is_some() => {
+                        self.use_helper(Helper::Signal);
+                        let value = match self.map_type_arg(&args, 0) {
+                            unit if unit == "()" => "nil".to_string(),
+                            value => value,
+                        };
+                        format!("Signal<{}>", value)
+                    }
+                    "Bindable" if self.runtime_class(&name).is_some() => {
+                        self.use_helper(Helper::Bindable);
+                        let payload = match self.map_type_arg(&args, 0) {
+                            unit if unit == "()" => "nil".to_string(),
+                            payload => payload,
+                        };
+                        format!("Bindable<{}>", payload)
+                    }
+                    "BindableFn" if self.runtime_class(&name).is_some() => {
+                        self.use_helper(Helper::Bindable);
+                        let [argument, result] =
+                            [0, 1].map(|index| match self.map_type_arg(&args, index) {
+                                unit if unit == "()" => "nil".to_string(),
+                                payload => payload,
+                            });
+                        format!("BindableFn<{}, {}>", argument, result)
+                    }
+                    "Thread" if !self.types.is_struct(&name) => "thread".to_string(),
+                    "SignalConnection" | "Maid" => match self.runtime_class(&name) {
+                        Some(helper) => {
+                            self.use_helper(helper);
+                            name
+                        }
+                        None => self.map_struct_type(&name, &args),
+                    },
+                    "Element" if self.types.uses_react() && !self.types.is_struct(&name) => {
+                        "React.ReactElement".to_string()
+                    }
+                    "Result" => {
+                        self.use_helper(Helper::ResultType);
+                        let value = match self.map_type_arg(&args, 0) {
+                            unit if unit == "()" => "nil".to_string(),
+                            value => value,
+                        };
+                        // anyhow's `Result<T>` errors with a message
+                        let error = match args.get(1) {
+                            Some(error) => self.map_type(error),
+                            None => "string".to_string(),
+                        };
+                        format!("Result<{}, {}>", value, error)
+                    }
+                    "Error"
+                        if type_path
+                            .path
+                            .segments
+                            .first()
+                            .is_some_and(|segment| segment.ident == "anyhow") =>
+                    {
+                        "string".to_string()
+                    }
+                    "Arc" if self.actor && type_registry::is_shared_table_type(rust_type) => {
+                        "SharedTable".to_string()
+                    }
+                    "Box" | "Rc" | "Arc" | "RefCell" | "Cell" | "Mutex" | "RwLock" => {
+                        self.map_type_arg(&args, 0)
+                    }
+                    "OnceLock" | "OnceCell" if !self.types.is_struct(&name) => {
+                        optional_type(self.map_type_arg(&args, 0))
+                    }
+
+                    "Self" => match (&self.self_rust_type, &self.self_type) {
+                        (Some(self_rust_type), Some(self_type))
+                            if self.types.is_generic_struct(self_type) =>
+                        {
+                            self.map_type(self_rust_type)
+                        }
+                        (_, Some(self_type)) => self_type.clone(),
+                        (_, None) => self.any_fallback(rust_type),
+                    },
+
+                    _ if self.types.is_struct(&name) => self.map_struct_type(&name, &args),
+                    _ if self.types.is_enum(&name) => name,
+                    _ if self.api().is_some_and(|api| api.is_enum(&name)) => {
+                        format!("Enum.{}", name)
+                    }
+                    "Variant" => "any".to_string(),
+                    _ if self.api().is_some_and(|api| api.is_type(&name)) => name,
+                    _ => self.any_fallback(rust_type),
+                }
+            }
+
+            syn::Type::Reference(reference) => self.map_type(&reference.elem),
+            syn::Type::Paren(paren) => self.map_type(&paren.elem),
+            syn::Type::Slice(slice) => format!("{{{}}}", self.map_type(&slice.elem)),
+            syn::Type::Array(array) => format!("{{{}}}", self.map_type(&array.elem)),
+            syn::Type::Tuple(tuple) if tuple.elems.is_empty() => "()".to_string(),
+            syn::Type::Tuple(tuple) => tuple_type(tuple.elems.iter().map(|ty| self.map_type(ty))),
+
+            syn::Type::BareFn(bare_fn) => {
+                self.function_type(bare_fn.inputs.iter().map(|arg| &arg.ty), &bare_fn.output)
+            }
+            syn::Type::ImplTrait(impl_trait) if fn_bound(&impl_trait.bounds).is_some() => {
+                let args = fn_bound(&impl_trait.bounds).unwrap();
+                self.function_type(args.inputs.iter(), &args.output)
+            }
+            syn::Type::TraitObject(object) => self.trait_object_type(object),
+
+            _ => self.any_fallback(rust_type),
+        }
+    }
+
+    /// The type of a `dyn Trait`: a function type for a closure trait, `any` for `dyn Any`, and
+    /// otherwise the trait's interface type. Objects dispatch through their method tables,
+    /// which `--trait-dispatch dict` doesn't emit, so there they are `any`.
+    fn trait_object_type(&self, object: &syn::TypeTraitObject) -> String {
+        if let Some(args) = fn_bound(&object.bounds) {
+            return self.function_type(args.inputs.iter(), &args.output);
+        }
+
+        let segment = object.bounds.iter().find_map(|bound| match bound {
+            syn::TypeParamBound::Trait(trait_bound) => trait_bound.path.segments.last(),
+            _ => None,
+        });
+        let rust_type = syn::Type::TraitObject(object.clone());
+        let Some(segment) = segment else {
+            return self.any_fallback(&rust_type);
+        };
+
+        let name = segment.ident.to_string();
+        if name == "Any" {
+            return "any".to_string();
+        }
+        if self.types.dict_dispatch || !self.types.is_dyn_trait(&name) {
+            return self.any_fallback(&rust_type);
+        }
+
+        let args: Vec<String> = generic_type_args(&segment.arguments)
+            .into_iter()
+            .map(|arg| self.map_type(arg))
+            .collect();
+        generic_alias(&name, &args)
+    }
+
+    /// A Luau function type, like `(number) -> string` for `Fn(i32) -> String`.
+    fn function_type<'t>(
+        &self,
+        inputs: impl Iterator<Item = &'t syn::Type>,
+        output: &syn::ReturnType,
+    ) -> String {
+        let params: Vec<String> = inputs.map(|ty| self.map_type(ty)).collect();
+        let output = match output {
+            syn::ReturnType::Type(_, ty) => self.map_type(ty),
+            syn::ReturnType::Default => "()".to_string(),
+        };
+
+        format!("({}) -> {}", params.join(", "), output)
+    }
+
+    /// Types the parameters `generics` bounds by a closure trait as their function types for
+    /// the function being lowered, returning what they were before.
+    fn substitute_function_params(
+        &mut self,
+        generics: &syn::Generics,
+    ) -> Vec<(String, Option<String>)> {
+        fn_bounded_params(generics)
+            .into_iter()
+            .map(|(name, args)| {
+                let ty = self.function_type(args.inputs.iter(), &args.output);
+                let previous = self.type_substitutions.insert(name.clone(), ty);
+                (name, previous)
+            })
+            .collect()
+    }
+
+    fn restore_substitutions(&mut self, previous: Vec<(String, Option<String>)>) {
+        for (name, substitution) in previous {
+            match substitution {
+                Some(substitution) => self.type_substitutions.insert(name, substitution),
+                None => self.type_substitutions.remove(&name),
+            };
+        }
+    }
+
+    /// `any`, for a type with no Luau equivalent. Types parsed from strings rather than the
+    /// source have no location and aren't reported.
+    fn any_fallback(&self, rust_type: &syn::Type) -> String {
+        let span = rust_type.span();
+        let ty = rust_type_name(rust_type);
+        let mut fallbacks = self.any_fallbacks.borrow_mut();
+        let seen = fallbacks
+            .iter()
+            .any(|(_, seen)| seen.byte_range() == span.byte_range());
+
+        if span.start().line > 0 && !seen {
+            fallbacks.push((ty, span));
+        }
+
+        "any".to_string()
+    }
+
+    fn map_type_arg(&self, args: &[&syn::Type], index: usize) -> String {
+        args.get(index)
+            .map_or("any".to_string(), |ty| self.map_type(ty))
+    }
+
+    fn map_struct_type(&self, name: &str, args: &[&syn::Type]) -> String {
+        let generics = self
+            .types
+            .get(name)
+            .map(|info| info.generics.len())
+            .unwrap_or(0);
+
+        if generics == 0 {
+            return name.to_string();
+        }
+
+        let args: Vec<String> = (0..generics)
+            .map(|index| self.map_type_arg(args, index))
+            .collect();
+
+        if self.options.monomorphize {
+            self.instantiate_struct(name, args)
+        } else {
+            format!("{}<{}>", name, args.join(", "))
+        }
+    }
+
+    /// Records an instantiation of a generic struct to emit, returning its name.
+    fn instantiate_struct(&self, name: &str, args: Vec<String>) -> String {
+        let instantiation = (name.to_string(), args);
+        let specialized = mangle_instantiation(&instantiation.0, &instantiation.1);
+        let mut pending = self.struct_instantiations.borrow_mut();
+
+        if !pending.contains(&instantiation) {
+            pending.push(instantiation);
+        }
+
+        specialized
+    }
+
+    fn infer_luau_type(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Lit(lit) => match &lit.lit {
+                syn::Lit::Int(_) | syn::Lit::Float(_) => Some("number".to_string()),
+                syn::Lit::Str(_) => Some("string".to_string()),
+                syn::Lit::Bool(_) => Some("boolean".to_string()),
+                _ => None,
+            },
+
+            syn::Expr::Path(_) => {
+                if let Some(ty) = self.expr_rust_type(expr) {
+                    return Some(self.map_type(ty));
+                }
+
+                let ty = self.expr_type_name(expr)?;
+                let ty = syn::parse_str::<syn::Type>(&ty).ok()?;
+                Some(self.map_type(&ty))
+            }
+
+            syn::Expr::Struct(expr_struct) => match self.value_rust_type(expr) {
+                Some(ty) => Some(self.map_type(&ty)),
+                None => expr_struct
+                    .path
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident.to_string())
+                    .filter(|name| self.types.is_struct(name)),
+            },
+
+            syn::Expr::Reference(reference) => self.infer_luau_type(&reference.expr),
+            syn::Expr::Paren(paren) => self.infer_luau_type(&paren.expr),
+            syn::Expr::Unary(unary) => self.infer_luau_type(&unary.expr),
+            syn::Expr::Binary(binary) => self.infer_luau_type(&binary.left),
+
+            _ => None,
+        }
+    }
+
+    fn resolve_generic_call(&mut self, call: &syn::ExprCall) -> Option<String> {
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        if path.path.segments.len() == 2 {
+            return self.resolve_generic_associated_call(call, &path.path);
+        }
+
+        if path.path.segments.len() != 1 {
+            return None;
+        }
+
+        let segment = &path.path.segments[0];
+        let name = segment.ident.to_string();
+        let item = self.types.generic_fns.get(&name)?;
+        let params = type_registry::type_param_names(&item.sig.generics);
+        let explicit = generic_type_args(&segment.arguments);
+
+        let args: Vec<String> = params
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                if let Some(ty) = explicit.get(index) {
+                    return self.map_type(ty);
+                }
+
+                let inputs = item.sig.inputs.iter().filter_map(|input| match input {
+                    syn::FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+                    syn::FnArg::Receiver(_) => None,
+                });
+
+                for (input, arg) in inputs.zip(call.args.iter()) {
+                    if is_type_param(input, param) {
+                        if let Some(ty) = self.infer_luau_type(arg) {
+                            return ty;
+                        }
+                    }
+
+                    let unified = self
+                        .expr_rust_type(arg)
+                        .and_then(|concrete| unify_type_param(input, concrete, param));
+
+                    if let Some(ty) = unified {
+                        return self.map_type(ty);
+                    }
+                }
+
+                "any".to_string()
+            })
+            .collect();
+
+        let specialized = mangle_instantiation(&name, &args);
+        let instantiation = (name, args);
+
+        if !self.fn_instantiations.contains(&instantiation) {
+            self.fn_instantiations.push(instantiation.clone());
+            self.pending_fn_instantiations.push(instantiation);
+        }
+
+        Some(specialized)
+    }
+
+    /// The table a call like `Wrapper::new(3)` finds the function on for the instantiation
+    /// it's made with, like `Wrapper_number.new`, from the type's explicit arguments or the
+    /// function's.
+    fn resolve_generic_associated_call(
+        &mut self,
+        call: &syn::ExprCall,
+        path: &syn::Path,
+    ) -> Option<String> {
+        let type_segment = &path.segments[0];
+        let fn_segment = &path.segments[1];
+        if type_segment.ident == "Self" {
+            return None;
+        }
+
+        let name = type_segment.ident.to_string();
+        let impls = self.types.generic_impls.get(&name)?;
+        let (item_impl, method) = impls.iter().find_map(|item_impl| {
+            item_impl.items.iter().find_map(|item| match item {
+                syn::ImplItem::Fn(method) if method.sig.ident == fn_segment.ident => {
+                    Some((item_impl, method))
+                }
+                _ => None,
+            })
+        })?;
+
+        let params = type_registry::type_param_names(&item_impl.generics);
+        let syn::Type::Path(self_ty) = &*item_impl.self_ty else {
+            return None;
+        };
+        let self_args = generic_type_args(&self_ty.path.segments.last()?.arguments);
+        let mut explicit = generic_type_args(&type_segment.arguments);
+
+        // `let t: Stack<String> = Stack::new()` instantiates the type the `let` declares
+        let expected = self.expected_type.as_ref().and_then(|ty| match ty {
+            syn::Type::Path(expected) => expected
+                .path
+                .segments
+                .last()
+                .filter(|segment| segment.ident == name),
+            _ => None,
+        });
+        if let (true, Some(expected)) = (explicit.is_empty(), expected) {
+            explicit = generic_type_args(&expected.arguments);
+        }
+
+        // A method called like `Wrapper::get(&w)` takes its receiver first
+        let inputs: Vec<&syn::Type> = method
+            .sig
+            .inputs
+            .iter()
+            .map(|input| match input {
+                syn::FnArg::Typed(pat_type) => &*pat_type.ty,
+                syn::FnArg::Receiver(receiver) => &*receiver.ty,
+            })
+            .collect();
+
+        let args: Vec<String> = self_args
+            .iter()
+            .enumerate()
+            .map(|(index, self_arg)| {
+                if let Some(ty) = explicit.get(index) {
+                    return self.map_type(ty);
+                }
+
+                let Some(param) = params.iter().find(|param| is_type_param(self_arg, param)) else {
+                    return self.map_type(self_arg);
+                };
+
+                for (input, arg) in inputs.iter().zip(call.args.iter()) {
+                    if is_type_param(input, param) {
+                        if let Some(ty) = self.infer_luau_type(arg) {
+                            return ty;
+                        }
+                    }
+
+                    let unified = self
+                        .value_rust_type(arg)
+                        .and_then(|concrete| unify_type_param(input, &concrete, param).cloned());
+
+                    if let Some(ty) = unified {
+                        return self.map_type(&ty);
+                    }
+                }
+
+                "any".to_string()
+            })
+            .collect();
+
+        let table = self.instantiate_struct(&name, args);
+        Some(format!("{}.{}", table, fn_segment.ident))
+    }
+
+    fn emit_monomorphized_items(&mut self) -> Lowered {
+        loop {
+            while let Some((name, args)) = self.pending_fn_instantiations.pop() {
+                let item = self.types.generic_fns[&name].clone();
+                let params = type_registry::type_param_names(&item.sig.generics);
+                let specialized = mangle_instantiation(&name, &args);
+                let within = self.options.module_name.clone();
+
+                self.type_substitutions = params.into_iter().zip(args).collect();
+                self.transpile_function(
+                    &item.attrs,
+                    &item.sig,
+                    &item.block,
+                    &specialized,
+                    &within,
+                    &[],
+                )?;
+                self.type_substitutions.clear();
+            }
+
+            if self.emitted_structs == self.struct_instantiations.borrow().len() {
+                return Ok(());
+            }
+
+            let (name, args) = self.struct_instantiations.borrow()[self.emitted_structs].clone();
+            self.emitted_structs += 1;
+
+            let Some(info) = self.types.get(&name) else {
+                continue;
+            };
+
+            let item = info.item.clone();
+            let specialized = mangle_instantiation(&name, &args);
+
+            self.type_substitutions = info.generics.iter().cloned().zip(args.clone()).collect();
+            self.emit_struct_type(&item, &specialized);
+            self.type_substitutions.clear();
+
+            // Each instantiation gets a table of its own, with its impls' methods specialized
+            let impls = self
+                .types
+                .generic_impls
+                .get(&name)
+                .cloned()
+                .unwrap_or_default();
+            if !self.types.has_methods(&name) {
+                continue;
+            }
+
+            self.ensure_method_table(&specialized);
+            for item_impl in impls {
+                let Some(substitutions) = self.impl_substitutions(&item_impl, &args) else {
+                    continue;
+                };
+
+                self.type_substitutions = substitutions;
+                self.specialized_table = Some(specialized.clone());
+                let result = self.visit_item_impl(&item_impl);
+                self.specialized_table = None;
+                result?;
+            }
+        }
+    }
+
+    /// What an impl of a generic struct substitutes for its parameters to lower it for the
+    /// instantiation with `args`, or `None` when it's for another one, like `impl Pair<i32>`
+    /// for `Pair<string>`.
+    fn impl_substitutions(
+        &self,
+        item_impl: &syn::ItemImpl,
+        args: &[String],
+    ) -> Option<HashMap<String, String>> {
+        let params = type_registry::type_param_names(&item_impl.generics);
+        let syn::Type::Path(self_ty) = &*item_impl.self_ty else {
+            return None;
+        };
+        let self_args = generic_type_args(&self_ty.path.segments.last()?.arguments);
+
+        let mut substitutions = HashMap::new();
+        for (self_arg, arg) in self_args.into_iter().zip(args) {
+            let param = params.iter().find(|param| is_type_param(self_arg, param));
+            match param {
+                Some(param) => match substitutions.insert(param.clone(), arg.clone()) {
+                    Some(previous) if previous != *arg => return None,
+                    _ => {}
+                },
+                None if self.map_type(self_arg) != *arg => return None,
+                None => {}
+            }
+        }
+
+        for param in params {
+            substitutions
+                .entry(param)
+                .or_insert_with(|| "any".to_string());
+        }
+
+        Some(substitutions)
+    }
+
+    /// Emits the specializations that the output from `start` on instantiated ahead of it,
+    /// so they're defined before top-level code, like a script's `main`, calls them.
+    fn emit_monomorphized_before(&mut self, start: usize) -> Lowered {
+        if self.pending_fn_instantiations.is_empty()
+            && self.emitted_structs == self.struct_instantiations.borrow().len()
+        {
+            return Ok(());
+        }
+
+        let tail = self.output.split_off(start);
+        let split = self.origins.partition_point(|(offset, _)| *offset < start);
+        let tail_origins = self.origins.split_off(split);
+        let attributed = self.origins.last().map_or(0, |(_, line)| *line);
+        let first_fn = self.fn_instantiations.len() - self.pending_fn_instantiations.len();
+
+        self.emit_monomorphized_items()?;
+
+        // A module's functions are locals, declared before any of them is defined
+        if self.is_module() && self.fn_instantiations.len() > first_fn {
+            let names: Vec<String> = self.fn_instantiations[first_fn..]
+                .iter()
+                .map(|(name, args)| mangle_instantiation(name, args))
+                .collect();
+            let declaration = format!(
+                "{}local {}\n",
+                self.indent_manager.get_indent(),
+                names.join(", ")
+            );
+            self.output.insert_str(start, &declaration);
+            for (offset, _) in &mut self.origins[split..] {
+                *offset += declaration.len();
+            }
+        }
+
+        let shift = self.output.len() - start;
+        self.origins.push((self.output.len(), attributed));
+        self.origins.extend(
+            tail_origins
+                .into_iter()
+                .map(|(offset, line)| (offset + shift, line)),
+        );
+        self.output.push_str(&tail);
+
+        Ok(())
+    }
+
+    /// Emits the jump tables the output from `start` on dispatches through ahead of it, so
+    /// they're built once when the file loads instead of on every call.
+    fn emit_jump_tables_before(&mut self, start: usize) {
+        if self.jump_tables.is_empty() {
+            return;
+        }
+
+        let tables = std::mem::take(&mut self.jump_tables).concat();
+        self.output.insert_str(start, &tables);
+        let split = self.origins.partition_point(|(offset, _)| *offset < start);
+        for (offset, _) in &mut self.origins[split..] {
+            *offset += tables.len();
+        }
+    }
+
+    fn transpile_expr(&mut self, expr: &syn::Expr) -> Lowered<String> {
+        self.lower_expr(expr)
+            .map_err(|error| error.within(expr.span()))
+    }
+
+    fn lower_expr(&mut self, expr: &syn::Expr) -> Lowered<String> {
+        if !std::mem::take(&mut self.statement_call) {
+            if let Some((key, inlined)) = self.inline_call(expr) {
+                self.note(
+                    "inline",
+                    format!("call to `{}` inlined", key.1),
+                    expr.span(),
+                );
+                self.inlining.push(key);
+                let value = self.transpile_expr(&inlined)?;
+                self.inlining.pop();
+                return Ok(value);
+            }
+        }
+
+        Ok(match expr {
+            syn::Expr::Lit(lit) => match &lit.lit {
+                syn::Lit::Str(s) => format_string::quoted(&s.value()),
+                syn::Lit::Int(i) => i.base10_digits().to_string(),
+                syn::Lit::Float(f) => float_literal(f.base10_digits()),
+                syn::Lit::Bool(b) => b.value.to_string(),
+                syn::Lit::Char(c) => format_string::quoted(&c.value().to_string()),
+                syn::Lit::Byte(b) => b.value().to_string(),
+                syn::Lit::ByteStr(bytes) => {
+                    let elems: Vec<String> =
+                        bytes.value().iter().map(|byte| byte.to_string()).collect();
+                    format!("{{{}}}", elems.join(", "))
+                }
+                _ => return Err(unsupported!("Unsupported literal")),
+            },
+
+            syn::Expr::Path(path) => self.transpile_path(&path.path)?,
+
+            syn::Expr::Call(call) => {
+                if let Some(lowered) = self.lower_api_call(call)? {
+                    return Ok(lowered);
+                }
+
+                if let Some(lowered) = self.lower_bindable_new(call)? {
+                    return Ok(lowered);
+                }
+
+                if let syn::Expr::Path(path) = &*call.func {
+                    let segments = &path.path.segments;
+                    let class = segments.first().map(|segment| segment.ident.to_string());
+                    let helper = class.as_deref().and_then(|class| self.runtime_class(class));
+
+                    if let Some(helper) = helper.filter(|_| {
+                        segments.len() == 2 && segments[1].ident == "new" && call.args.is_empty()
+                    }) {
+                        self.use_helper(helper);
+                        return Ok(format!("{}.new()", class.unwrap()));
+                    }
+                }
+
+                if let Some(lowered) = self.lower_lune_call(call)? {
+                    return Ok(lowered);
+                }
+
+                if is_std_path(&call.func, "env", "args") {
+                    return Ok(self.env_args());
+                }
+
+                if let Some(body) = self.catch_unwind_body(call) {
+                    self.use_helper(Helper::PcallResult);
+                    let body = self.transpile_expr(body)?;
+                    return Ok(format!("pcallResult(pcall({}))", body));
+                }
+
+                if is_new_string(expr) {
+                    return Ok("\"\"".to_string());
+                }
+
+                if is_std_path(&call.func, "String", "from") && call.args.len() == 1 {
+                    return self.transpile_expr(&call.args[0]);
+                }
+
+                if is_std_path(&call.func, "task", "desynchronize") && !self.actor {
+                    self.warn(
+                        "desynchronize",
+                        "`task::desynchronize` errors outside a script parented to an Actor; mark the file `#![actor]`"
+                            .to_string(),
+                        call.span(),
+                    );
+                }
+
+                if self.actor && is_shared_table_new(call) {
+                    return Ok("SharedTable.new()".to_string());
+                }
+
+                if ["Rc", "Arc"]
+                    .iter()
+                    .any(|ty| is_std_path(&call.func, ty, "clone") && !self.types.is_struct(ty))
+                    && call.args.len() == 1
+                {
+                    return self.transpile_expr(&call.args[0]);
+                }
+
+                // Smart pointers are the value they point to, closures included
+                if ["Box", "Rc", "Arc"]
+                    .iter()
+                    .any(|ty| is_std_path(&call.func, ty, "new") && !self.types.is_struct(ty))
+                    && call.args.len() == 1
+                {
+                    return self.transpile_expr(&call.args[0]);
+                }
+
+                // The capacity pre-sizes the table's array part so pushes don't regrow it
+                if ["Vec", "VecDeque"]
+                    .iter()
+                    .any(|ty| is_std_path(&call.func, ty, "with_capacity") && call.args.len() == 1)
+                {
+                    let capacity = self.transpile_expr(&call.args[0])?;
+                    return Ok(format!("table.create({})", capacity));
+                }
+
+                if ["Vec", "VecDeque"]
+                    .iter()
+                    .any(|ty| is_std_path(&call.func, ty, "new"))
+                {
+                    return Ok("{}".to_string());
+                }
+
+                // A cell is `nil` until it's set
+                if ["OnceLock", "OnceCell"]
+                    .iter()
+                    .any(|ty| is_std_path(&call.func, ty, "new") && !self.types.is_struct(ty))
+                {
+                    return Ok("nil".to_string());
+                }
+
+                if let Some(hook) = self.react_hook(call) {
+                    let args = self.transpile_args(&call.args)?;
+                    return Ok(format!("React.{}({})", hook, args));
+                }
+
+                if let Some(element) = self.lower_element(expr)? {
+                    return Ok(element);
+                }
+
+                if let Some(variant) = self.variant_constructor(call) {
+                    if variant == "Some" {
+                        return self.lower_some(call, true);
+                    }
+
+                    let value = call
+                        .args
+                        .first()
+                        .filter(
+                            |arg| !matches!(arg, syn::Expr::Tuple(unit) if unit.elems.is_empty()),
+                        )
+                        .map(|arg| self.transpile_expr(arg))
+                        .transpose()?;
+                    return Ok(match (variant, value) {
+                        ("Ok", Some(value)) if value != "nil" => {
+                            format!("{{ ok = true, value = {} }}", value)
+                        }
+                        ("Ok", _) => "{ ok = true }".to_string(),
+                        (_, value) => format!(
+                            "{{ ok = false, err = {} }}",
+                            value.unwrap_or_else(|| "nil".to_string())
+                        ),
+                    });
+                }
+
+                if let Some(dict) = self.trait_call_dict(call) {
+                    let method = match &*call.func {
+                        syn::Expr::Path(path) => path.path.segments.last().unwrap().ident.clone(),
+                        _ => unreachable!(),
+                    };
+
+                    return Ok(format!(
+                        "{}.{}({})",
+                        dict,
+                        method,
+                        self.transpile_args(&call.args)?
+                    ));
+                }
+
+                if let Some(struct_name) = self.tuple_struct_constructor(&call.func) {
+                    if self.types.is_newtype(&struct_name) {
+                        return self.transpile_expr(&call.args[0]);
+                    }
+
+                    let values: Vec<(syn::Member, &syn::Expr)> = call
+                        .args
+                        .iter()
+                        .enumerate()
+                        .map(|(index, arg)| (syn::Member::from(index), arg))
+                        .collect();
+                    let class = match &*call.func {
+                        syn::Expr::Path(func) => {
+                            self.struct_class(&struct_name, &func.path, &values)
+                        }
+                        _ => struct_name.clone(),
+                    };
+                    let fields = self.transpile_args(&call.args)?;
+                    return Ok(self.struct_table(&struct_name, &class, &fields));
+                }
+
+                let mut dicts = self.call_dicts(call)?;
+                dicts.extend(self.call_const_args(call)?);
+                let func = match self.options.monomorphize {
+                    true => self.resolve_generic_call(call),
+                    false => None,
+                };
+                // `(button.on_click)(x)` calls the field's function, like `button.on_click(x)`
+                let callee = match &*call.func {
+                    syn::Expr::Paren(paren)
+                        if matches!(&*paren.expr, syn::Expr::Field(_) | syn::Expr::Path(_)) =>
+                    {
+                        &*paren.expr
+                    }
+                    callee => callee,
+                };
+                let func = match func {
+                    Some(func) => func,
+                    None => self.transpile_expr(callee)?,
+                };
+                let args = self.transpile_args(&call.args)?;
+                let args = dicts
+                    .into_iter()
+                    .chain((!args.is_empty()).then_some(args))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                format!("{}({})", func, args)
+            }
+
+            syn::Expr::MethodCall(_) if self.is_element(expr) => self.lower_element(expr)?.unwrap(),
+
+            syn::Expr::MethodCall(method_call)
+                if self.shared_table_of(&method_call.receiver).is_some() =>
+            {
+                self.lower_shared_table_method(method_call)?
+            }
+
+            syn::Expr::MethodCall(method_call) if self.runtime_method(method_call).is_some() => {
+                let method = self.runtime_method(method_call).unwrap();
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+                let receiver = method_receiver(&method_call.receiver, receiver);
+                let args = match method_call.args.first() {
+                    Some(syn::Expr::Tuple(unit)) if unit.elems.is_empty() => String::new(),
+                    _ => self.transpile_args(&method_call.args)?,
+                };
+                format!("{}:{}({})", receiver, method, args)
+            }
+
+            syn::Expr::MethodCall(method_call) if self.api_method(method_call).is_some() => {
+                self.lower_api_method(method_call)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "as_slice" | "as_mut_slice"
+                ) =>
+            {
+                self.transpile_expr(&method_call.receiver)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "cancel"
+                    && method_call.args.is_empty()
+                    && !self.types.is_struct("Thread")
+                    && self.receiver_type_name(&method_call.receiver).as_deref()
+                        == Some("Thread") =>
+            {
+                format!(
+                    "task.cancel({})",
+                    self.transpile_expr(&method_call.receiver)?
+                )
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "get" | "get_or_init"
+                ) && self.is_once_cell(&method_call.receiver) =>
+            {
+                self.lower_once_cell(method_call)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "set" && self.is_once_cell(&method_call.receiver) =>
+            {
+                return Err(unsupported!(
+                    "`OnceLock::set` is only supported as a statement, not for its `Result`"
+                ))
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "collect"
+                    && matches!(&*method_call.receiver, syn::Expr::Call(call)
+                        if is_std_path(&call.func, "env", "args")) =>
+            {
+                self.env_args()
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "collect"
+                    && method_call.args.is_empty()
+                    && collected_range(&method_call.receiver).is_some() =>
+            {
+                self.lower_range_collect(&method_call.receiver)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "push_str" | "push")
+                    && self.concat_parts_of(&method_call.receiver).is_some() =>
+            {
+                let parts = self.concat_parts_of(&method_call.receiver).unwrap();
+                let part = self.transpile_args(&method_call.args)?;
+                format!("table.insert({}, {})", parts, part)
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "push_str" | "push")
+                    && self.is_string_expr(&method_call.receiver)
+                    && !self.is_string_buffer(&method_call.receiver) =>
+            {
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+                let part = self.transpile_args(&method_call.args)?;
+                format!("{} ..= {}", receiver, part)
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if self.is_string_buffer(&method_call.receiver)
+                    && matches!(
+                        method_call.method.to_string().as_str(),
+                        "push_str" | "push" | "clear"
+                    ) =>
+            {
+                let buffer = self.transpile_buffer(&method_call.receiver);
+                if method_call.method == "clear" {
+                    format!("table.clear({})", buffer)
+                } else {
+                    let part = self.transpile_args(&method_call.args)?;
+                    format!("table.insert({}, {})", buffer, part)
+                }
+            }
+
+            // A `Vec` is the array part of its table, so it grows and shrinks at the end
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "push" | "pop")
+                    && self.is_vec(&method_call.receiver)
+                    && !self.defines_method(&method_call.receiver, &method_call.method) =>
+            {
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+
+                if method_call.method == "push" {
+                    if let Some(value) = method_call.args.first() {
+                        self.check_element(value)?;
+                    }
+                    let value = self.transpile_args(&method_call.args)?;
+                    format!("table.insert({}, {})", receiver, value)
+                } else {
+                    format!("table.remove({})", receiver)
+                }
+            }
+
+            // NaN is the only value not equal to itself
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "is_nan" | "is_infinite" | "is_finite"
+                ) && method_call.args.is_empty() =>
+            {
+                let value = self.match_operand(&method_call.receiver)?;
+
+                match method_call.method.to_string().as_str() {
+                    "is_nan" => format!("{} ~= {}", value, value),
+                    "is_infinite" => format!("math.abs({}) == math.huge", value),
+                    _ => format!(
+                        "{} == {} and math.abs({}) ~= math.huge",
+                        value, value, value
+                    ),
+                }
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if INT_METHODS.contains(&method_call.method.to_string().as_str())
+                    && method_call.args.len() == 1
+                    && !self.defines_method(&method_call.receiver, &method_call.method) =>
+            {
+                // Wider integers can only be checked, for `usize` going below zero and the like
+                let wide = self
+                    .expr_type_name(&method_call.receiver)
+                    .and_then(|ty| match ty.as_str() {
+                        "u64" | "usize" | "u128" => Some((64, false)),
+                        "i64" | "isize" | "i128" => Some((64, true)),
+                        _ => None,
+                    })
+                    .filter(|_| method_call.method.to_string().starts_with("checked_"));
+
+                match self.int_type_of(&method_call.receiver).or(wide) {
+                    Some(int_type) => self.lower_int_method(method_call, int_type)?,
+                    None => {
+                        return Err(unsupported!(
+                            "`{}` needs an 8-, 16- or 32-bit integer receiver",
+                            method_call.method
+                        ))
+                    }
+                }
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "len"
+                    && method_call.args.is_empty()
+                    && !self.defines_method(&method_call.receiver, &method_call.method) =>
+            {
+                format!("#{}", self.transpile_expr(&method_call.receiver)?)
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "clone" && method_call.args.is_empty() =>
+            {
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+
+                match self.expr_type_name(&method_call.receiver) {
+                    Some(ty) if self.types.is_newtype(&ty) => {
+                        let inner = self.types.get(&ty).unwrap().item.fields.iter().next();
+                        let inner = self.map_type(&inner.unwrap().ty);
+
+                        if inner.starts_with('{') {
+                            format!("table.clone({})", receiver)
+                        } else {
+                            receiver
+                        }
+                    }
+                    // `table.clone` keeps the metatable
+                    Some(ty) if self.types.is_struct(&ty) => format!("table.clone({})", receiver),
+                    // Strings can't be changed in place, so sharing one is a copy
+                    _ if self.is_string_expr(&method_call.receiver) => receiver,
+                    _ if self.is_collection(&method_call.receiver) => {
+                        format!("table.clone({})", receiver)
+                    }
+                    _ => format!(
+                        "{}:clone()",
+                        method_receiver(&method_call.receiver, receiver)
+                    ),
+                }
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "is_ok" | "is_err")
+                    && method_call.args.is_empty() =>
+            {
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+                let receiver = method_receiver(&method_call.receiver, receiver);
+
+                if method_call.method == "is_ok" {
+                    format!("{}.ok", receiver)
+                } else {
+                    format!("not {}.ok", receiver)
+                }
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "is_some" | "is_none"
+                ) && method_call.args.is_empty() =>
+            {
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+
+                if method_call.method == "is_some" {
+                    format!("{} ~= nil", receiver)
+                } else {
+                    format!("{} == nil", receiver)
+                }
+            }
+
+            // A trait object is the implementation's value, its `Any` the same table
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "as_ref" | "as_mut")
+                    && method_call.args.is_empty()
+                    && !self.defines_method(&method_call.receiver, &method_call.method) =>
+            {
+                self.transpile_expr(&method_call.receiver)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "context" | "with_context"
+                ) && method_call.args.len() == 1
+                    && !self.defines_method(&method_call.receiver, &method_call.method) =>
+            {
+                self.lower_context(method_call)?
+            }
+
+            // A value's concrete type is its method table
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "is" | "downcast_ref" | "downcast_mut"
+                ) && method_call.args.is_empty()
+                    && method_call.turbofish.is_some() =>
+            {
+                let type_name = self.downcast_type(method_call)?;
+                if method_call.method == "is" {
+                    let value = self.transpile_expr(&method_call.receiver)?;
+                    format!("getmetatable({}) == {}", value, type_name)
+                } else {
+                    let value = self.match_operand(&method_call.receiver)?;
+                    format!(
+                        "(if getmetatable({}) == {} then {} else nil)",
+                        value, type_name, value
+                    )
+                }
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if UNWRAP_METHODS.contains(&method_call.method.to_string().as_str())
+                    && !self.defines_method(&method_call.receiver, &method_call.method)
+                    && self.unwrap_source(&method_call.receiver).is_some() =>
+            {
+                let source = self.unwrap_source(&method_call.receiver).unwrap();
+                self.lower_unwrap(method_call, source)?
+            }
+
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "flatten"
+                    && method_call.args.is_empty()
+                    && self.is_nested_option(&method_call.receiver) =>
+            {
+                self.use_helper(Helper::SomeNone);
+                let value = self.match_operand(&method_call.receiver)?;
+                format!("(if {} == someNone then nil else {})", value, value)
+            }
+
+            syn::Expr::MethodCall(method_call) => {
+                let method = method_call.method.to_string();
+                let receiver = self.transpile_expr(&method_call.receiver)?;
+                let args = self.transpile_args(&method_call.args)?;
+
+                if let Some(dict) = self.method_dict(&method_call.receiver, &method) {
+                    let args = [receiver, args]
+                        .into_iter()
+                        .filter(|arg| !arg.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    return Ok(format!("{}({})", member_access(&dict, &method), args));
+                }
+
+                let static_table = self
+                    .receiver_type_name(&method_call.receiver)
+                    .and_then(|ty| {
+                        self.types
+                            .is_extension_method(&ty, &method)
+                            .then(|| extension_table(&ty))
+                    });
+
+                if let Some(table) = static_table {
+                    let args = [receiver, args]
+                        .into_iter()
+                        .filter(|arg| !arg.is_empty())
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    return Ok(format!("{}({})", member_access(&table, &method), args));
+                }
+
+                let receiver = method_receiver(&method_call.receiver, receiver);
+                method_call_expr(&receiver, &method, &args)
+            }
+
+            // Integer `as` casts truncate even in debug builds
+            syn::Expr::Cast(cast) => {
+                // An enum with discriminants is its numbers already
+                if let Some(name) = self.enum_type_of(&cast.expr) {
+                    if !self.types.has_discriminants(&name) {
+                        return Err(unsupported!(
+                            "Casting `{}` with `as` needs explicit discriminants, like `{} = 0`",
+                            name,
+                            self.types.enum_variants(&name).unwrap_or_default()[0]
+                        ));
+                    }
+                }
+
+                let value = self.transpile_expr(&cast.expr)?;
+                let target_name = type_registry::type_name(&cast.ty).unwrap_or_default();
+                let numeric = int_width(&target_name).is_some()
+                    || matches!(target_name.as_str(), "f32" | "f64");
+
+                // Bools and chars aren't numbers in Luau, so they're converted first
+                if numeric && self.is_bool_expr(&cast.expr) {
+                    return Ok(format!("(if {} then 1 else 0)", value));
+                }
+                if target_name == "char" && !self.is_char_expr(&cast.expr) {
+                    return Ok(format!("utf8.char({})", value));
+                }
+                let from_char = numeric && self.is_char_expr(&cast.expr);
+                let value = if from_char {
+                    format!("utf8.codepoint({})", value)
+                } else {
+                    value
+                };
+                let source = if from_char {
+                    Some((32, false))
+                } else {
+                    self.int_type_of(&cast.expr)
+                };
+
+                // A float always truncates, while narrowing an integer wraps like overflow
+                let target = int_width(&target_name);
+                match target {
+                    Some((bits, signed)) if self.is_float_expr(&cast.expr) => {
+                        self.use_helper(Helper::TruncateInt);
+                        let (min, max) = int_range(bits, signed);
+                        format!("truncateInt({}, {}, {})", value, min, max)
+                    }
+                    None if is_wide_int(&target_name) && self.is_float_expr(&cast.expr) => {
+                        self.use_helper(Helper::TruncateInt);
+                        let min = match target_name.starts_with('u') {
+                            true => "0",
+                            false => "-math.huge",
+                        };
+                        format!("truncateInt({}, {}, math.huge)", value, min)
+                    }
+                    Some(_) if self.options.int_semantics == IntSemantics::Exact => value,
+                    Some((bits, signed))
+                        if !source.is_some_and(|source| int_fits(source, (bits, signed))) =>
+                    {
+                        wrap_int(&value, bits, signed)
+                    }
+                    _ => value,
+                }
+            }
+
+            syn::Expr::Try(expr_try) => self.lower_try(expr_try)?,
+
+            syn::Expr::Closure(closure) => self.lower_closure(closure)?,
+
+            syn::Expr::Reference(reference) => self.transpile_expr(&reference.expr)?,
+
+            syn::Expr::Unary(unary) => {
+                let operand = self.transpile_expr(&unary.expr)?;
+
+                match unary.op {
+                    syn::UnOp::Deref(_) => operand,
+                    syn::UnOp::Not(_)
+                        if lowers_to_comparison(&unary.expr) && !is_parenthesized(&operand) =>
+                    {
+                        format!("not ({})", operand)
+                    }
+                    syn::UnOp::Not(_) => format!("not {}", operand),
+                    syn::UnOp::Neg(_) => format!("-{}", operand),
+                    _ => return Err(unsupported!("unimplemented unary op!")),
+                }
+            }
+
+            syn::Expr::Index(index) => {
+                let base = self.transpile_expr(&index.expr)?;
+                let key = if self.is_keyed_container(&index.expr) {
+                    self.transpile_expr(&index.index)?
+                } else {
+                    self.transpile_array_index(&index.index)?
+                };
+
+                format!("{}[{}]", base, key)
+            }
+
+            syn::Expr::Binary(bin_expr) => {
+                let left = self.transpile_expr(&bin_expr.left)?;
+                let right = self.transpile_expr(&bin_expr.right)?;
+                if matches!(bin_expr.op, syn::BinOp::Add(_)) && self.is_string_expr(&bin_expr.left)
+                {
+                    return Ok(format!("{} .. {}", left, right));
+                }
+
+                let int_type = self.int_type_of(expr);
+                let semantics = self.options.int_semantics;
+                if let Some(value) =
+                    self.lower_int_op(&bin_expr.op, &left, &right, int_type, semantics)
+                {
+                    return Ok(value);
+                }
+                if let Some(value) = self.lower_int_division(
+                    &bin_expr.op,
+                    &left,
+                    &right,
+                    [&bin_expr.left, &bin_expr.right],
+                ) {
+                    return Ok(value);
+                }
+
+                let op = match bin_expr.op {
+                    syn::BinOp::Add(_) => "+",
+                    syn::BinOp::Sub(_) => "-",
+                    syn::BinOp::Mul(_) => "*",
+                    syn::BinOp::Div(_) => "/",
+                    syn::BinOp::Rem(_) => "%",
+                    syn::BinOp::Eq(_) => "==",
+                    syn::BinOp::Ne(_) => "~=",
+                    syn::BinOp::Lt(_) => "<",
+                    syn::BinOp::Le(_) => "<=",
+                    syn::BinOp::Gt(_) => ">",
+                    syn::BinOp::Ge(_) => ">=",
+                    syn::BinOp::And(_) => "and",
+                    syn::BinOp::Or(_) => "or",
+
+                    _ => return Err(unsupported!("unimplemented binary op!")),
+                };
+
+                format!("{} {} {}", left, op, right)
+            }
+
+            syn::Expr::Match(match_expr) => {
+                let result = self.names.fresh();
+                self.add_line(&format!("local {}", result));
+                self.lower_match(match_expr, BlockTail::Assign(&result))?;
+                result
+            }
+
+            syn::Expr::Loop(expr_loop) => {
+                let result = self.names.fresh();
+                self.add_line(&format!("local {}", result));
+                self.lower_loop(expr_loop, LoopResult::Assign(result.clone()))?;
+                result
+            }
+
+            syn::Expr::If(_) | syn::Expr::Block(_) => {
+                let result = self.names.fresh();
+                self.add_line(&format!("local {}", result));
+                self.emit_value_into(expr, &result)?;
+                result
+            }
+
+            syn::Expr::Struct(expr_struct) if self.is_tween_info(expr_struct) => {
+                self.lower_tween_info(expr_struct)?
+            }
+
+            syn::Expr::Struct(expr_struct) if self.error_variant(&expr_struct.path).is_some() => {
+                let (name, variant) = self.error_variant(&expr_struct.path).unwrap();
+                let args: Vec<String> = variant
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let value = expr_struct
+                            .fields
+                            .iter()
+                            .find(|value| {
+                                matches!(&value.member, syn::Member::Named(ident)
+                                    if field.ident.as_ref() == Some(ident))
+                            })
+                            .ok_or_else(|| {
+                                unsupported!("Error variants can't be built with `..` from another")
+                            })?;
+                        self.transpile_expr(&value.expr)
+                    })
+                    .collect::<Lowered<_>>()?;
+                format!("{}.{}({})", name, variant.ident, args.join(", "))
+            }
+
+            syn::Expr::Struct(expr_struct) => {
+                if expr_struct.rest.is_some() {
+                    return Err(unsupported!("Struct update syntax is not yet supported"));
+                }
+
+                let fields = expr_struct
+                    .fields
+                    .iter()
+                    .map(|field| {
+                        let name = match &field.member {
+                            syn::Member::Named(ident) => table_key(&ident.to_string()),
+                            syn::Member::Unnamed(index) => format!("[{}]", index.index + 1),
+                        };
+
+                        Ok(format!("{} = {}", name, self.transpile_expr(&field.expr)?))
+                    })
+                    .collect::<Lowered<Vec<_>>>()?
+                    .join(", ");
+
+                if let Some((enum_name, variant)) = self.data_variant(&expr_struct.path) {
+                    let fields = if fields.is_empty() {
+                        format!("tag = \"{}\"", variant)
+                    } else {
+                        format!("tag = \"{}\", {}", variant, fields)
+                    };
+                    return Ok(self.variant_table(&enum_name, &fields));
+                }
+
+                let struct_name = expr_struct
+                    .path
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident.to_string())
+                    .unwrap_or_default();
+
+                let struct_name = match (struct_name.as_str(), &self.self_type) {
+                    ("Self", Some(self_type)) => self_type.clone(),
+                    _ => struct_name,
+                };
+
+                let values: Vec<(syn::Member, &syn::Expr)> = expr_struct
+                    .fields
+                    .iter()
+                    .map(|field| (field.member.clone(), &field.expr))
+                    .collect();
+                let class = self.struct_class(&struct_name, &expr_struct.path, &values);
+                self.struct_table(&struct_name, &class, &fields)
+            }
+
+            syn::Expr::Field(expr_field) => {
+                let base = self.transpile_expr(&expr_field.base)?;
+
+                match &expr_field.member {
+                    syn::Member::Named(ident) => member_access(&base, &ident.to_string()),
+                    syn::Member::Unnamed(_)
+                        if self
+                            .receiver_type_name(&expr_field.base)
+                            .is_some_and(|ty| self.types.is_newtype(&ty)) =>
+                    {
+                        base
+                    }
+                    syn::Member::Unnamed(_) => field_element(&base, &expr_field.member),
+                }
+            }
+
+            syn::Expr::Paren(paren) => format!("({})", self.transpile_expr(&paren.expr)?),
+
+            syn::Expr::Tuple(tuple) => {
+                let elems: Vec<String> = tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.transpile_expr(elem))
+                    .collect::<Lowered<Vec<_>>>()?;
+                format!("{{{}}}", elems.join(", "))
+            }
+
+            syn::Expr::Array(array) => {
+                let elems = self.transpile_elements(&array.elems)?;
+                format!("{{{}}}", elems.join(", "))
+            }
+
+            syn::Expr::Repeat(repeat) => self.lower_repeat(&repeat.expr, &repeat.len)?,
+
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("cfg") => {
+                self.options.cfg.eval_macro(&expr_macro.mac).to_string()
+            }
+
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("format") => {
+                self.lower_format(&expr_macro.mac)?
+            }
+
+            syn::Expr::Macro(expr_macro)
+                if is_write_macro(&expr_macro.mac) && self.lower_write(&expr_macro.mac)? =>
+            {
+                "{ ok = true }".to_string()
+            }
+
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("matches") => {
+                self.lower_matches(&expr_macro.mac)?
+            }
+
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                self.lower_vec(&expr_macro.mac)?
+            }
+
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("dbg") => {
+                self.lower_dbg(&expr_macro.mac)?
+            }
+
+            syn::Expr::Macro(expr_macro) if is_luau_macro(&expr_macro.mac) => {
+                raw_luau_lines(&parse_luau_macro(&expr_macro.mac)?).join(" ")
+            }
+
+            syn::Expr::Macro(expr_macro) => {
+                self.lower_panic(&expr_macro.mac)?.ok_or_else(|| {
+                    unsupported!("Unsupported macro `{}!`", macro_name(&expr_macro.mac))
+                })?
+            }
+
+            syn::Expr::Let(_) => {
+                return Err(unsupported!(
+                    "`let` is only supported as the whole condition of an `if` or `while`"
+                ))
+            }
+
+            _ => return Err(unsupported!("Unsupported expression")),
+        })
+    }
+
+    /// A `thiserror::Error` enum is lowered to the messages its `#[error(...)]` attributes
+    /// format, so errors print and propagate as strings: unit variants are their message and
+    /// the others functions from their fields to it.
+    fn emit_error_enum(&mut self, item: &syn::ItemEnum) -> Lowered {
+        let name = item.ident.to_string();
+        let keyword = self.type_keyword(&item.vis);
+        self.add_line(&format!("{} {} = string", keyword, name));
+        self.add_line(&format!("local {} = {{}}", name));
+
+        for variant in &item.variants {
+            let params = error_params(variant);
+            let message = self.error_message(&name, variant, &params)?;
+
+            if matches!(variant.fields, syn::Fields::Unit) {
+                self.add_line(&format!("{}.{} = {}", name, variant.ident, message));
+                continue;
+            }
+
+            let params: Vec<String> = variant
+                .fields
+                .iter()
+                .zip(&params)
+                .map(|(field, param)| format!("{}: {}", param, self.map_type(&field.ty)))
+                .collect();
+            self.add_line(&format!(
+                "function {}.{}({}): string",
+                name,
+                variant.ident,
+                params.join(", ")
+            ));
+            self.indent_manager.increase();
+            self.add_line(&format!("return {}", message));
+            self.indent_manager.decrease();
+            self.add_line("end");
+        }
+
+        self.add_line(&format!("table.freeze({})", name));
+
+        Ok(())
+    }
+
+    /// The message of an error variant, formatting its fields, which are bound to `params`.
+    fn error_message(
+        &mut self,
+        name: &str,
+        variant: &syn::Variant,
+        params: &[String],
+    ) -> Lowered<String> {
+        let attr = variant
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("error"))
+            .ok_or_else(|| {
+                unsupported!(
+                    "`{}::{}` needs an `#[error(\"...\")]` message",
+                    name,
+                    variant.ident
+                )
+            })?;
+        let mut args = attr
+            .parse_args_with(
+                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|_| unsupported!("Failed to parse `#[error]` arguments"))?;
+
+        if let [syn::Expr::Path(path)] = &args.iter().collect::<Vec<_>>()[..] {
+            if path.path.is_ident("transparent") {
+                let source = params
+                    .first()
+                    .ok_or_else(|| unsupported!("`#[error(transparent)]` needs a field"))?;
+                return Ok(format!("tostring({})", source));
+            }
+        }
+        if args.len() > 1 {
+            return Err(unsupported!(
+                "Only a format string is supported in `#[error]`, with fields named in it"
+            ));
+        }
+
+        // Tuple fields are the positional arguments `{0}` and `{1}` refer to
+        if matches!(variant.fields, syn::Fields::Unnamed(_)) {
+            for param in params {
+                let param = syn::Ident::new(param, attr.span());
+                args.push(syn::parse_quote!(#param));
+            }
+        }
+        self.lower_format(&syn::parse_quote!(format!(#args)))
+    }
+
+    /// Binds each `#[actor]` function to the messages of its name sent to the script's Actor,
+    /// which it handles in parallel.
+    fn bind_actor_handlers(&mut self, file: &syn::File) -> Lowered {
+        // A spec isn't parented to the Actor
+        if self.options.tests.is_some() {
+            return Ok(());
+        }
+
+        for item in &file.items {
+            let syn::Item::Fn(item_fn) = item else {
+                continue;
+            };
+            if !item_fn
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("actor"))
+                || !self.options.cfg.is_enabled(&item_fn.attrs)
+            {
+                continue;
+            }
+
+            let name = &item_fn.sig.ident;
+            self.recover(
+                name.span(),
+                |this| {
+                    if !this.actor {
+                        return Err(unsupported!(
+                            "`#[actor]` function `{}` needs its file marked `#![actor]`",
+                            name
+                        ));
+                    }
+                    this.add_line(&format!(
+                        "script:GetActor():BindToMessageParallel(\"{}\", {})",
+                        name, name
+                    ));
+                    Ok(())
+                },
+                |_, _| {},
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Visits `items` with their statics and constants first, so the functions among them
+    /// see their locals wherever they are declared.
+    fn visit_items(&mut self, items: &[syn::Item]) -> Lowered {
+        let (globals, rest): (Vec<_>, Vec<_>) = items
+            .iter()
+            .partition(|item| matches!(item, syn::Item::Const(_) | syn::Item::Static(_)));
+
+        for item in globals.into_iter().chain(rest) {
+            let start = self.output.len();
+            let hoist_level = self.hoist_level.replace(self.indent_manager.level());
+            self.visit_item(item)?;
+            self.hoist_level = hoist_level;
+            self.emit_jump_tables_before(start);
+
+            if self.options.monomorphize && self.module_path.is_empty() {
+                self.emit_monomorphized_before(start)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A `static` or `const` as a local of the file, initialized when it is first required.
+    fn emit_global(
+        &mut self,
+        ident: &syn::Ident,
+        ty: &syn::Type,
+        expr: &syn::Expr,
+        mutable: bool,
+    ) -> Lowered {
+        let binding: syn::Stmt = if mutable {
+            syn::parse_quote!(let mut #ident: #ty = #expr;)
+        } else {
+            syn::parse_quote!(let #ident: #ty = #expr;)
+        };
+
+        // Every actor gets the same table, by the static's name
+        let shared = self.actor
+            && type_registry::is_shared_table_type(ty)
+            && matches!(expr, syn::Expr::Call(call) if is_shared_table_new(call));
+        if shared {
+            self.add_line(&format!(
+                "local {}: SharedTable = game:GetService(\"SharedTableRegistry\"):GetSharedTable(\"{}\")",
+                ident, ident
+            ));
+        } else if let syn::Stmt::Local(local) = binding {
+            self.visit_local_binding(&local)?;
+        }
+
+        self.scopes.declare_global(
+            &ident.to_string(),
+            Binding {
+                mutable,
+                initialized: true,
+                ty: type_registry::type_name(ty),
+                rust_type: Some(ty.clone()),
+            },
+        );
+
+        Ok(())
+    }
+
+    /// The value of each variant of an enum with discriminants, counting up from the one
+    /// before like Rust does for those without: `{ Red = 1, Green, Blue = 4 }` is 1, 2 and 4.
+    fn discriminants(&mut self, item: &syn::ItemEnum) -> Lowered<Vec<(String, String)>> {
+        let mut previous: Option<String> = None;
+        let mut values = Vec::new();
+
+        for variant in &item.variants {
+            let value = match (&variant.discriminant, previous) {
+                (Some((_, expr)), _) => self.transpile_expr(expr)?,
+                (None, None) => "0".to_string(),
+                (None, Some(previous)) => match previous.parse::<i128>() {
+                    Ok(previous) => (previous + 1).to_string(),
+                    Err(_) => format!("({}) + 1", previous),
+                },
+            };
+
+            values.push((variant.ident.to_string(), value.clone()));
+            previous = Some(value);
+        }
+
+        Ok(values)
+    }
+
+    /// The error enum and variant a path like `ConfigError::Missing` constructs.
+    fn error_variant(&self, path: &syn::Path) -> Option<(String, syn::Variant)> {
+        let [enum_segment, variant_segment] = &path.segments.iter().collect::<Vec<_>>()[..] else {
+            return None;
+        };
+
+        let name = match (enum_segment.ident.to_string().as_str(), &self.self_type) {
+            ("Self", Some(self_type)) => self_type.clone(),
+            (name, _) => name.to_string(),
+        };
+        let variant = self
+            .types
+            .error_enums
+            .get(&name)?
+            .variants
+            .iter()
+            .find(|variant| variant.ident == variant_segment.ident)?;
+
+        Some((name, variant.clone()))
+    }
+
+    // `#[derive(LuauSerialize)]` round-trips a struct through a plain table, so payloads
+    // sent over remotes or stored in data stores are checked field by field on the way in
+    fn emit_serialize(&mut self, item: &syn::ItemStruct) -> Lowered {
+        let name = item.ident.to_string();
+        if !item.generics.params.is_empty() {
+            return Err(unsupported!(
+                "LuauSerialize does not support generic structs like `{}`",
+                name
+            ));
+        }
+
+        let syn::Fields::Named(fields) = &item.fields else {
+            return Err(unsupported!(
+                "LuauSerialize requires named fields on `{}`",
+                name
+            ));
+        };
+
+        let fields: Vec<(String, &syn::Type)> = fields
+            .named
+            .iter()
+            .filter(|field| self.options.cfg.is_enabled(&field.attrs))
+            .map(|field| (field.ident.as_ref().unwrap().to_string(), &field.ty))
+            .collect();
+
+        self.add_line(&format!(
+            "function {}.serialize(self: {}): {{ [string]: any }}",
+            name, name
+        ));
+        self.indent_manager.increase();
+        self.add_line("local data = {}");
+
+        for (field, ty) in &fields {
+            let value = member_access("self", field);
+            let key = member_access("data", field);
+
+            match self.serializable_elements(ty) {
+                Some(element) => {
+                    self.add_line(&format!("{} = {{}}", key));
+                    self.add_line(&format!("for index, item in ipairs({}) do", value));
+                    self.indent_manager.increase();
+                    self.add_line(&format!("{}[index] = {}.serialize(item)", key, element));
+                    self.indent_manager.decrease();
+                    self.add_line("end");
+                }
+                None => {
+                    let value = self.serialize_value(ty, &value, "serialize");
+                    self.add_line(&format!("{} = {}", key, value));
+                }
+            }
+        }
+
+        self.add_line("return data");
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        self.add_line(&format!(
+            "function {}.deserialize(data: {{ [string]: any }}): {}",
+            name, name
+        ));
+        self.indent_manager.increase();
+        self.add_line(&format!(
+            "assert(type(data) == \"table\", \"{}.deserialize expects a table\")",
+            name
+        ));
+
+        let mut values = Vec::new();
+        for (field, ty) in &fields {
+            let value = member_access("data", field);
+
+            if let Some((condition, expected)) = self.field_check(ty, &value) {
+                self.add_line(&format!(
+                    "assert({}, \"{}.{} must be {}\")",
+                    condition, name, field, expected
+                ));
+            }
+
+            let value = match self.serializable_elements(ty) {
+                Some(element) => {
+                    let local = if field == "data" || !is_luau_identifier(field) {
+                        self.names.fresh()
+                    } else {
+                        field.clone()
+                    };
+
+                    self.add_line(&format!("local {} = {{}}", local));
+                    self.add_line(&format!("for index, item in ipairs({}) do", value));
+                    self.indent_manager.increase();
+                    self.add_line(&format!("{}[index] = {}.deserialize(item)", local, element));
+                    self.indent_manager.decrease();
+                    self.add_line("end");
+                    local
+                }
+                None => self.serialize_value(ty, &value, "deserialize"),
+            };
+
+            values.push(format!("{} = {}", table_key(field), value));
+        }
+
+        let table = self.struct_table(&name, &name, &values.join(", "));
+        self.add_line(&format!("return {}", table));
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    // `#[derive(LuauBuffer)]` packs a struct into a fixed-size `buffer`, with each field
+    // at an offset computed from the field types
+    fn emit_buffer_layout(&mut self, item: &syn::ItemStruct) -> Lowered {
+        let name = item.ident.to_string();
+        let size = self.buffer_size(&name)?;
+
+        let syn::Fields::Named(fields) = &item.fields else {
+            return Err(unsupported!(
+                "LuauBuffer requires named fields on `{}`",
+                name
+            ));
+        };
+
+        let mut writes = Vec::new();
+        let mut reads = Vec::new();
+        let mut offset = 0;
+
+        for field in &fields.named {
+            if !self.options.cfg.is_enabled(&field.attrs) {
+                continue;
+            }
+
+            let field_name = field.ident.as_ref().unwrap().to_string();
+            let at = if offset == 0 {
+                "offset".to_string()
+            } else {
+                format!("offset + {}", offset)
+            };
+            let value = member_access("self", &field_name);
+            let key = table_key(&field_name);
+            let layout = self.buffer_field(&field.ty)?.ok_or_else(|| {
+                unsupported!(
+                    "LuauBuffer field `{}.{}` has no fixed-size layout",
+                    name,
+                    field_name
+                )
+            })?;
+
+            match layout {
+                BufferField::Number(kind, _) => {
+                    writes.push(format!("buffer.write{}(buf, {}, {})", kind, at, value));
+                    reads.push(format!("{} = buffer.read{}(buf, {})", key, kind, at));
+                }
+                BufferField::Bool => {
+                    writes.push(format!(
+                        "buffer.writeu8(buf, {}, if {} then 1 else 0)",
+                        at, value
+                    ));
+                    reads.push(format!("{} = buffer.readu8(buf, {}) == 1", key, at));
+                }
+                BufferField::Enum(variants) => {
+                    let last = variants.len() - 1;
+                    let index = variants
+                        .iter()
+                        .enumerate()
+                        .map(|(index, variant)| match index {
+                            _ if index == last && index > 0 => format!("else {}", index),
+                            _ if index == last => index.to_string(),
+                            0 => format!("if {} == \"{}\" then 0", value, variant),
+                            _ => format!("elseif {} == \"{}\" then {}", value, variant, index),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    let tags = variants
+                        .iter()
+                        .map(|variant| format!("\"{}\"", variant))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+
+                    writes.push(format!("buffer.writeu8(buf, {}, {})", at, index));
+                    reads.push(format!(
+                        "{} = ({{ {} }})[buffer.readu8(buf, {}) + 1]",
+                        key, tags, at
+                    ));
+                }
+                BufferField::Struct(inner) => {
+                    writes.push(format!("{}.write_buffer({}, buf, {})", inner, value, at));
+                    reads.push(format!("{} = {}.read_buffer(buf, {})", key, inner, at));
+                }
+            }
+
+            offset += self.buffer_field_size(&field.ty)?;
+        }
+
+        self.add_line(&format!("{}.SIZE = {}", name, size));
+
+        self.add_line(&format!(
+            "function {}.write_buffer(self: {}, buf: buffer, offset: number)",
+            name, name
+        ));
+        self.indent_manager.increase();
+        for write in writes {
+            self.add_line(&write);
+        }
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        self.add_line(&format!(
+            "function {}.read_buffer(buf: buffer, offset: number): {}",
+            name, name
+        ));
+        self.indent_manager.increase();
+        let table = self.struct_table(&name, &name, &reads.join(", "));
+        self.add_line(&format!("return {}", table));
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        self.add_line(&format!(
+            "function {}.to_buffer(self: {}): buffer",
+            name, name
+        ));
+        self.indent_manager.increase();
+        self.add_line(&format!("local buf = buffer.create({})", size));
+        self.add_line(&format!("{}.write_buffer(self, buf, 0)", name));
+        self.add_line("return buf");
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        self.add_line(&format!(
+            "function {}.from_buffer(buf: buffer): {}",
+            name, name
+        ));
+        self.indent_manager.increase();
+        self.add_line(&format!("return {}.read_buffer(buf, 0)", name));
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    fn buffer_field(&self, ty: &syn::Type) -> Lowered<Option<BufferField>> {
+        let Some(name) = type_registry::type_name(ty) else {
+            return Ok(None);
+        };
+
+        let number = |kind, size| Some(BufferField::Number(kind, size));
+        Ok(match name.as_str() {
+            "u8" => number("u8", 1),
+            "i8" => number("i8", 1),
+            "u16" => number("u16", 2),
+            "i16" => number("i16", 2),
+            "u32" => number("u32", 4),
+            "i32" => number("i32", 4),
+            "f32" => number("f32", 4),
+            // Luau numbers are doubles, so 64-bit integers round-trip through `f64`
+            "f64" | "u64" | "i64" | "usize" | "isize" => number("f64", 8),
+            "bool" => Some(BufferField::Bool),
+            _ if self.types.is_buffered(&name) => Some(BufferField::Struct(name)),
+            // Discriminants aren't indices, so they're stored as they are
+            _ if self.types.has_discriminants(&name) => number("i32", 4),
+            _ => match self.types.enums.get(&name) {
+                Some(variants) if variants.len() > 256 => {
+                    return Err(unsupported!(
+                        "LuauBuffer stores `{}` in a u8, so it can have at most 256 variants",
+                        name
+                    ));
+                }
+                variants => variants.map(|variants| BufferField::Enum(variants.clone())),
+            },
+        })
+    }
+
+    fn buffer_field_size(&self, ty: &syn::Type) -> Lowered<usize> {
+        Ok(match self.buffer_field(ty)? {
+            Some(BufferField::Number(_, size)) => size,
+            Some(BufferField::Bool | BufferField::Enum(_)) => 1,
+            Some(BufferField::Struct(inner)) => self.buffer_size(&inner)?,
+            None => 0,
+        })
+    }
+
+    fn buffer_size(&self, name: &str) -> Lowered<usize> {
+        let Some(info) = self.types.get(name) else {
+            return Ok(0);
+        };
+
+        info.item
+            .fields
+            .iter()
+            .filter(|field| self.options.cfg.is_enabled(&field.attrs))
+            .map(|field| self.buffer_field_size(&field.ty))
+            .sum()
+    }
+
+    fn serializable_struct(&self, ty: &syn::Type) -> Option<String> {
+        type_registry::type_name(ty).filter(|name| self.types.is_serializable(name))
+    }
+
+    fn serializable_elements(&self, ty: &syn::Type) -> Option<String> {
+        let syn::Type::Path(type_path) = ty else {
+            return None;
+        };
+
+        let segment = type_path.path.segments.last()?;
+        if segment.ident != "Vec" {
+            return None;
+        }
+
+        self.serializable_struct(generic_type_args(&segment.arguments).first()?)
+    }
+
+    /// `Bindable::new("Damage")` or `BindableFn::new("Lookup")`, passing the functions that
+    /// encode and check its payloads, whose types come from its type arguments or the type
+    /// it's bound to.
+    fn lower_bindable_new(&mut self, call: &syn::ExprCall) -> Lowered<Option<String>> {
+        let syn::Expr::Path(path) = &*call.func else {
+            return Ok(None);
+        };
+        let [class, new] = &path.path.segments.iter().collect::<Vec<_>>()[..] else {
+            return Ok(None);
+        };
+        let class_name = class.ident.to_string();
+        let roles: &[&str] = match class_name.as_str() {
+            "Bindable" => &["payload"],
+            "BindableFn" => &["argument", "result"],
+            _ => return Ok(None),
+        };
+        if new.ident != "new" || self.runtime_class(&class_name).is_none() {
+            return Ok(None);
+        }
+
+        if self.is_lune() {
+            return Err(unsupported!(
+                "`{}` needs `--target roblox`; Lune has no BindableEvents or BindableFunctions",
+                class_name
+            ));
+        }
+
+        let [name] = &call.args.iter().collect::<Vec<_>>()[..] else {
+            return Err(unsupported!(
+                "`{}::new` takes the name the scripts share it by, like `{}::new(\"Damage\")`",
+                class_name,
+                class_name
+            ));
+        };
+
+        let mut payloads: Vec<syn::Type> = generic_type_args(&class.arguments)
+            .into_iter()
+            .cloned()
+            .collect();
+        if payloads.is_empty() {
+            if let Some(syn::Type::Path(expected)) = &self.expected_type {
+                let segment = expected.path.segments.last();
+                if let Some(segment) = segment.filter(|segment| segment.ident == class_name) {
+                    payloads = generic_type_args(&segment.arguments)
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                }
+            }
+        }
+        if payloads.len() != roles.len() {
+            return Err(unsupported!(
+                "`{}::new` needs its payload types, from the type it's bound to like `let event: Bindable<Damage> = ...` or written like `Bindable::<Damage>::new(...)`",
+                class_name
+            ));
+        }
+
+        self.use_helper(Helper::Bindable);
+        let mut args = vec![self.transpile_expr(name)?];
+        for (ty, role) in payloads.iter().zip(roles) {
+            let (encode, decode) = self.payload_codec(ty, role)?;
+            args.push(encode);
+            args.push(decode);
+        }
+        while args.last().is_some_and(|arg| arg == "nil") {
+            args.pop();
+        }
+
+        Ok(Some(format!("{}.new({})", class_name, args.join(", "))))
+    }
+
+    /// The functions that encode a bindable payload of type `ty` and decode and check it on
+    /// the other side, each `nil` if the payload crosses as it is. Structs are copied without
+    /// their metatable, so they must derive LuauSerialize, which rebuilds them field by field;
+    /// other payloads get a check of their type, which `role` names when it fails.
+    fn payload_codec(&mut self, ty: &syn::Type, role: &str) -> Lowered<(String, String)> {
+        if matches!(ty, syn::Type::Tuple(unit) if unit.elems.is_empty()) {
+            return Ok(("nil".to_string(), "nil".to_string()));
+        }
+
+        if let Some(name) = self.serializable_struct(ty) {
+            return Ok((
+                format!("{}.serialize", name),
+                format!("{}.deserialize", name),
+            ));
+        }
+
+        let element = type_registry::type_name(ty)
+            .filter(|name| name == "Vec" || name == "Option")
+            .and_then(|_| match ty {
+                syn::Type::Path(type_path) => type_path.path.segments.last(),
+                _ => None,
+            })
+            .and_then(|segment| generic_type_args(&segment.arguments).first().copied())
+            .unwrap_or(ty);
+        if let Some(name) = type_registry::type_name(element)
+            .filter(|name| self.types.is_struct(name) && !self.types.is_serializable(name))
+        {
+            return Err(unsupported!(
+                "`{}` is sent between scripts as a copy without its metatable; derive LuauSerialize on it, which rebuilds it and checks its fields on the way in",
+                name
+            ));
+        }
+
+        let luau_type = self.map_type(ty);
+        if let Some(element) = self.serializable_elements(ty) {
+            let encode = self.inline_function(
+                "payload: any",
+                "any",
+                &[
+                    "local items = {}".to_string(),
+                    "for index, item in ipairs(payload) do".to_string(),
+                    format!("    items[index] = {}.serialize(item)", element),
+                    "end".to_string(),
+                    "return items".to_string(),
+                ],
+            );
+            let decode = self.inline_function(
+                "payload: any, name: string",
+                &luau_type,
+                &[
+                    format!(
+                        "assert(typeof(payload) == \"table\", `{{name}} {} must be a table`)",
+                        role
+                    ),
+                    format!("local items: {} = {{}}", luau_type),
+                    "for index, item in ipairs(payload) do".to_string(),
+                    format!("    items[index] = {}.deserialize(item)", element),
+                    "end".to_string(),
+                    "return items".to_string(),
+                ],
+            );
+            return Ok((encode, decode));
+        }
+
+        if option_inner(ty).is_some_and(|inner| self.serializable_struct(inner).is_some()) {
+            let encode = self.inline_function(
+                "payload: any",
+                "any",
+                &[format!(
+                    "return {}",
+                    self.serialize_value(ty, "payload", "serialize")
+                )],
+            );
+            let decode = self.inline_function(
+                "payload: any, name: string",
+                &luau_type,
+                &[format!(
+                    "return {}",
+                    self.serialize_value(ty, "payload", "deserialize")
+                )],
+            );
+            return Ok((encode, decode));
+        }
+
+        let Some((condition, expected)) = self.field_check(ty, "payload") else {
+            return Ok(("nil".to_string(), "nil".to_string()));
+        };
+        let decode = self.inline_function(
+            "payload: any, name: string",
+            &luau_type,
+            &[
+                format!(
+                    "assert({}, `{{name}} {} must be {}`)",
+                    condition, role, expected
+                ),
+                "return payload".to_string(),
+            ],
+        );
+        Ok(("nil".to_string(), decode))
+    }
+
+    /// A function expression with `lines` as its body, indented to sit inline in a line.
+    fn inline_function(&mut self, params: &str, return_type: &str, lines: &[String]) -> String {
+        let outer = self.indent_manager.get_indent();
+        self.indent_manager.increase();
+        let inner = self.indent_manager.get_indent();
+        self.indent_manager.decrease();
+
+        let body: String = lines
+            .iter()
+            .map(|line| format!("{}{}\n", inner, line))
+            .collect();
+        format!(
+            "function({}): {}\n{}{}end",
+            params, return_type, body, outer
+        )
+    }
+
+    fn serialize_value(&self, ty: &syn::Type, value: &str, direction: &str) -> String {
+        if let Some(name) = self.serializable_struct(ty) {
+            return format!("{}.{}({})", name, direction, value);
+        }
+
+        let inner = option_inner(ty).and_then(|inner| self.serializable_struct(inner));
+        match inner {
+            Some(name) => format!(
+                "if {} ~= nil then {}.{}({}) else nil",
+                value, name, direction, value
+            ),
+            None => value.to_string(),
+        }
+    }
+
+    fn field_check(&self, ty: &syn::Type, value: &str) -> Option<(String, String)> {
+        if let Some(inner) = option_inner(ty) {
+            let (condition, expected) = self.field_check(inner, value)?;
+            return Some((
+                format!("{} == nil or {}", value, condition),
+                format!("{} or nil", expected),
+            ));
+        }
+
+        let name = type_registry::type_name(ty)?;
+        if self.types.has_discriminants(&name) {
+            let values: Vec<String> = self
+                .types
+                .enum_variants(&name)
+                .unwrap_or_default()
+                .iter()
+                .map(|variant| format!("{}.{}", name, variant))
+                .collect();
+            return Some((
+                format!("table.find({{ {} }}, {}) ~= nil", values.join(", "), value),
+                format!("a {}", name),
+            ));
+        }
+
+        if self.types.is_data_enum(&name) {
+            return Some((
+                format!("typeof({}) == \"table\" and {}.tag ~= nil", value, value),
+                format!("a {}", name),
+            ));
+        }
+
+        if self.types.is_enum(&name) {
+            return Some((
+                format!(
+                    "typeof({}) == \"string\" and {}[{}] ~= nil",
+                    value, name, value
+                ),
+                format!("a {}", name),
+            ));
+        }
+
+        if self.types.is_struct(&name) {
+            return Some((
+                format!("typeof({}) == \"table\"", value),
+                "a table".to_string(),
+            ));
+        }
+
+        let expected = self.typeof_name(ty)?;
+        let article = if expected.starts_with(['A', 'E', 'I', 'O', 'U']) {
+            "an"
+        } else {
+            "a"
+        };
+
+        Some((
+            format!("typeof({}) == \"{}\"", value, expected),
+            format!("{} {}", article, expected),
+        ))
+    }
+
+    fn visit_local_binding(&mut self, i: &syn::Local) -> Lowered {
+        if let Some(name) = self.string_buffer_binding(i) {
+            self.add_local_variable(&name, true, true);
+            self.add_line(&format!("local {}: {{string}} = {{}}", name));
+            return Ok(());
+        }
+
+        if let (syn::Pat::Wild(_), Some(init)) = (&i.pat, &i.init) {
+            if self.emit_write(&init.expr)?
+                || self.emit_once_cell_set(&init.expr)?
+                || self.emit_shared_table_write(&init.expr)?
+            {
+                return Ok(());
+            }
+        }
+
+        let typed_ident = match &i.pat {
+            syn::Pat::Type(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(pat_ident) => Some((pat_type, pat_ident)),
+                _ => None,
+            },
+            _ => None,
+        };
+
+        if let Some((pat_type, pat_ident)) = typed_ident {
+            let var_name = pat_ident.ident.to_string();
+            let previous_expected = self.expected_type.replace((*pat_type.ty).clone());
+
+            if let Some(init) = i.init.as_ref().filter(|init| !is_value_expr(&init.expr)) {
+                let var_type_str = self.map_type(&pat_type.ty);
+
+                self.add_local_variable(&var_name, pat_ident.mutability.is_some(), true);
+                self.add_line(&format!("local {}: {}", var_name, var_type_str));
+                self.emit_value_into(&init.expr, &var_name)?;
+                self.expected_type = previous_expected;
+                return Ok(());
+            }
+
+            let mutable = pat_ident.mutability.is_some();
+            let var_value = i
+                .init
+                .as_ref()
+                .map(|init| {
+                    let value = self.transpile_expr(&init.expr)?;
+                    Ok(self.copied_into(&init.expr, value, mutable))
+                })
+                .transpose()?;
+            self.expected_type = previous_expected;
+            let var_type_str = self.map_type(&pat_type.ty);
+            let suffix = self.local_suffix(mutable, var_value.is_some());
+
+            self.add_local_variable(&var_name, mutable, var_value.is_some());
+            self.add_line(&format!(
+                "local {}: {}{}{}",
+                var_name,
+                var_type_str,
+                var_value.map_or(String::new(), |v| format!(" = {}", v)),
+                suffix
+            ));
+        } else if let syn::Pat::Ident(pat_ident) = &i.pat {
+            let var_name = pat_ident.ident.to_string();
+            let mutable = pat_ident.mutability.is_some();
+
+            if let Some(init) = i.init.as_ref().filter(|init| !is_value_expr(&init.expr)) {
+                self.add_local_variable(&var_name, mutable, true);
+                self.add_line(&format!("local {} = nil", var_name));
+                self.emit_value_into(&init.expr, &var_name)?;
+            } else {
+                let var_value = i
+                    .init
+                    .as_ref()
+                    .map(|init| {
+                        let value = self.transpile_expr(&init.expr)?;
+                        Ok(self.copied_into(&init.expr, value, mutable))
+                    })
+                    .transpose()?;
+                let suffix = self.local_suffix(mutable, var_value.is_some());
+
+                self.add_local_variable(&var_name, mutable, var_value.is_some());
+                self.add_line(&format!(
+                    "local {}{}{}",
+                    var_name,
+                    var_value.map_or(String::new(), |v| format!(" = {}", v)),
+                    suffix
+                ));
+            }
+        } else if let Some((names, hook)) = self.multiple_return_binding(i)? {
+            for name in &names {
+                self.add_local_variable(name, false, true);
+            }
+
+            self.add_line(&format!("local {} = {}", names.join(", "), hook));
+        } else {
+            let (pat, ty) = match &i.pat {
+                syn::Pat::Type(pat_type) => (&*pat_type.pat, Some(&*pat_type.ty)),
+                pat => (pat, None),
+            };
+
+            let Some(init) = &i.init else {
+                if matches!(pat, syn::Pat::Wild(_)) {
+                    return Ok(());
+                }
+                return Err(unsupported!(
+                    "Destructuring patterns in `let` require an initializer"
+                ));
+            };
+
+            let previous_expected = match ty {
+                Some(ty) => self.expected_type.replace(ty.clone()),
+                None => self.expected_type.clone(),
+            };
+            let value = if !is_value_expr(&init.expr) {
+                let temp = self.names.fresh();
+                self.add_line(&format!("local {} = nil", temp));
+                self.emit_value_into(&init.expr, &temp).map(|()| temp)
+            } else {
+                self.transpile_expr(&init.expr)
+            };
+            self.expected_type = previous_expected;
+            let mut scrutinee = value?;
+
+            // `let _ = side();` still evaluates its initializer
+            if matches!(pat, syn::Pat::Wild(_)) {
+                if !is_simple_place(&scrutinee) {
+                    self.add_line(&format!("local _ = {}", scrutinee));
+                }
+                return Ok(());
+            }
+
+            if !is_simple_place(&scrutinee) {
+                let temp = self.names.fresh();
+                self.add_line(&format!("local {} = {}", temp, scrutinee));
+                scrutinee = temp;
+            }
+
+            if let Some((_, diverge)) = &init.diverge {
+                if let Some(condition) = self.pattern_condition(pat, &scrutinee, None)? {
+                    self.add_line(&format!("if not ({}) then", condition));
+                    self.indent_manager.increase();
+                    match &**diverge {
+                        syn::Expr::Block(expr_block) => self.visit_block_body(&expr_block.block)?,
+                        diverge => self.visit_expr(diverge)?,
+                    }
+                    self.indent_manager.decrease();
+                    self.add_line("end");
+                }
+            }
+
+            let mut bindings = Vec::new();
+            collect_pattern_bindings(pat, &scrutinee, None, &self.types, &mut bindings)?;
+
+            for (name, value) in bindings {
+                self.add_local_variable(&name, false, true);
+                self.add_line(&format!("local {} = {}", name, value));
+            }
+            self.type_api_binding(pat, &init.expr);
+        }
+
+        Ok(())
+    }
+
+    // React hooks like `useState` return several values rather than a table, so
+    // `let (count, set_count) = use_state(0)` binds them directly
+    fn multiple_return_binding(
+        &mut self,
+        local: &syn::Local,
+    ) -> Lowered<Option<(Vec<String>, String)>> {
+        let syn::Pat::Tuple(pat_tuple) = &local.pat else {
+            return Ok(None);
+        };
+        let Some(init) = &local.init else {
+            return Ok(None);
+        };
+        let syn::Expr::Call(call) = &*init.expr else {
+            return Ok(None);
+        };
+
+        if !matches!(self.react_hook(call), Some("useState" | "useReducer")) {
+            return Ok(None);
+        }
+
+        let names = pat_tuple
+            .elems
+            .iter()
+            .map(|elem| match elem {
+                syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                syn::Pat::Wild(_) => Some("_".to_string()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(names) = names else {
+            return Ok(None);
+        };
+
+        let value = self.transpile_expr(&init.expr)?;
+        Ok(Some((names, value)))
+    }
+
+    fn tuple_struct_constructor(&self, func: &syn::Expr) -> Option<String> {
+        let syn::Expr::Path(path) = func else {
+            return None;
+        };
+
+        let ident = path.path.get_ident()?.to_string();
+        let name = match (ident.as_str(), &self.self_type) {
+            ("Self", Some(self_type)) => self_type.clone(),
+            _ => ident,
+        };
+
+        let is_tuple_struct = self
+            .types
+            .get(&name)
+            .is_some_and(|info| matches!(info.item.fields, syn::Fields::Unnamed(_)));
+
+        is_tuple_struct.then_some(name)
+    }
+
+    /// The table a struct literal's methods are on: the instantiation's, like
+    /// `Pair_number`, for a generic struct under `--monomorphize`.
+    fn struct_class(
+        &self,
+        struct_name: &str,
+        path: &syn::Path,
+        values: &[(syn::Member, &syn::Expr)],
+    ) -> String {
+        if !(self.options.monomorphize && self.types.is_generic_struct(struct_name)) {
+            return struct_name.to_string();
+        }
+
+        // One that can't be inferred in its own impl, like `Stack { items: Vec::new() }`, is
+        // taken to be `Self`
+        let own = self
+            .self_rust_type
+            .clone()
+            .filter(|_| self.self_type.as_deref() == Some(struct_name));
+        match self.struct_literal_type(path, values).or(own) {
+            Some(ty) => self.map_type(&ty),
+            None => self.map_struct_type(struct_name, &[]),
+        }
+    }
+
+    /// The instantiation a literal of a generic struct builds, like `Pair<i32>` for
+    /// `Pair { a: 1, b: 2 }`, from its explicit type arguments or its field values' types.
+    fn struct_literal_type(
+        &self,
+        path: &syn::Path,
+        values: &[(syn::Member, &syn::Expr)],
+    ) -> Option<syn::Type> {
+        let segment = path.segments.last()?;
+        if segment.ident == "Self" {
+            return self.self_rust_type.clone().filter(|_| {
+                self.self_type
+                    .as_ref()
+                    .is_some_and(|ty| self.types.is_generic_struct(ty))
+            });
+        }
+
+        let info = self.types.get(&segment.ident.to_string())?;
+        if info.generics.is_empty() {
+            return None;
+        }
+
+        let explicit = generic_type_args(&segment.arguments);
+        let args = info
+            .generics
+            .iter()
+            .enumerate()
+            .map(|(index, param)| {
+                if let Some(ty) = explicit.get(index) {
+                    return Some((*ty).clone());
+                }
+
+                info.item
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .find_map(|(index, field)| {
+                        let member = match &field.ident {
+                            Some(ident) => syn::Member::Named(ident.clone()),
+                            None => syn::Member::from(index),
+                        };
+                        let (_, value) = values.iter().find(|(field, _)| *field == member)?;
+                        let concrete = self.value_rust_type(value)?;
+                        unify_type_param(&field.ty, &concrete, param).cloned()
+                    })
+            })
+            .collect::<Option<Vec<syn::Type>>>()?;
+
+        let ident = &segment.ident;
+        Some(syn::parse_quote!(#ident<#(#args),*>))
+    }
+
+    /// The Rust type of a value a generic is instantiated with, as far as it's known here.
+    fn value_rust_type(&self, expr: &syn::Expr) -> Option<syn::Type> {
+        match expr {
+            syn::Expr::Lit(lit) => match &lit.lit {
+                syn::Lit::Int(int) if !int.suffix().is_empty() => syn::parse_str(int.suffix()).ok(),
+                syn::Lit::Int(_) => Some(syn::parse_quote!(i32)),
+                syn::Lit::Float(float) if !float.suffix().is_empty() => {
+                    syn::parse_str(float.suffix()).ok()
+                }
+                syn::Lit::Float(_) => Some(syn::parse_quote!(f64)),
+                syn::Lit::Str(_) => Some(syn::parse_quote!(&str)),
+                syn::Lit::Bool(_) => Some(syn::parse_quote!(bool)),
+                syn::Lit::Char(_) => Some(syn::parse_quote!(char)),
+                _ => None,
+            },
+
+            syn::Expr::Struct(expr_struct) => {
+                let values: Vec<(syn::Member, &syn::Expr)> = expr_struct
+                    .fields
+                    .iter()
+                    .map(|field| (field.member.clone(), &field.expr))
+                    .collect();
+                self.struct_literal_type(&expr_struct.path, &values)
+            }
+
+            syn::Expr::Reference(reference) => self.value_rust_type(&reference.expr),
+            syn::Expr::Paren(paren) => self.value_rust_type(&paren.expr),
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Neg(_)) => {
+                self.value_rust_type(&unary.expr)
+            }
+
+            syn::Expr::Call(call)
+                if matches!(&*call.func, syn::Expr::Path(path)
+                    if path.path.segments.len() == 2 && path.path.segments[0].ident == "String") =>
+            {
+                Some(syn::parse_quote!(String))
+            }
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "to_string" && method_call.args.is_empty() =>
+            {
+                Some(syn::parse_quote!(String))
+            }
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("format") => {
+                Some(syn::parse_quote!(String))
+            }
+            syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                Some(syn::parse_quote!(Vec<_>))
+            }
+            syn::Expr::MethodCall(method_call)
+                if method_call.method == "clone" && method_call.args.is_empty() =>
+            {
+                self.value_rust_type(&method_call.receiver)
+            }
+
+            syn::Expr::Array(array) => {
+                let elem = array
+                    .elems
+                    .first()
+                    .and_then(|elem| self.value_rust_type(elem))
+                    .unwrap_or(syn::parse_quote!(_));
+                let len = array.elems.len();
+                Some(syn::parse_quote!([#elem; #len]))
+            }
+            syn::Expr::Repeat(repeat) => {
+                let elem = self
+                    .value_rust_type(&repeat.expr)
+                    .unwrap_or(syn::parse_quote!(_));
+                let len = &repeat.len;
+                Some(syn::parse_quote!([#elem; #len]))
+            }
+            syn::Expr::Tuple(tuple) if !tuple.elems.is_empty() => {
+                let elems = tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.value_rust_type(elem).unwrap_or(syn::parse_quote!(_)));
+                Some(syn::parse_quote!((#(#elems,)*)))
+            }
+
+            // A field typed by one of its struct's parameters has the base's argument for it
+            syn::Expr::Field(expr_field) => {
+                let mut base = self.value_rust_type(&expr_field.base)?;
+                while let syn::Type::Reference(reference) = base {
+                    base = *reference.elem;
+                }
+                let syn::Type::Path(base) = base else {
+                    return None;
+                };
+                let segment = base.path.segments.last()?;
+                let info = self.types.get(&segment.ident.to_string())?;
+                let field = info
+                    .item
+                    .fields
+                    .iter()
+                    .enumerate()
+                    .find(|(index, field)| match (&expr_field.member, &field.ident) {
+                        (syn::Member::Named(member), Some(ident)) => member == ident,
+                        (syn::Member::Unnamed(member), None) => member.index as usize == *index,
+                        _ => false,
+                    })
+                    .map(|(_, field)| &field.ty)?;
+
+                match info
+                    .generics
+                    .iter()
+                    .position(|param| is_type_param(field, param))
+                {
+                    Some(index) => generic_type_args(&segment.arguments)
+                        .get(index)
+                        .map(|ty| (*ty).clone()),
+                    None if info
+                        .generics
+                        .iter()
+                        .any(|param| mentions_type_param(field, param)) =>
+                    {
+                        None
+                    }
+                    None => Some(field.clone()),
+                }
+            }
+
+            expr => self.expr_rust_type(expr).cloned(),
+        }
+    }
+
+    // A data enum's values are tables with the variant's name in `tag`, and tuple fields
+    // at their position like a tuple struct
+    fn emit_data_enum(&mut self, item: &syn::ItemEnum, keyword: &str) {
+        let name = item.ident.to_string();
+        let variants: Vec<&syn::Variant> = item
+            .variants
+            .iter()
+            .filter(|variant| self.options.cfg.is_enabled(&variant.attrs))
+            .collect();
+
+        let shapes: Vec<String> = variants
+            .iter()
+            .map(|variant| {
+                let tag = format!("tag: \"{}\"", variant.ident);
+                match &variant.fields {
+                    syn::Fields::Unit => format!("{{ {} }}", tag),
+                    syn::Fields::Named(fields) => {
+                        let fields: Vec<String> = fields
+                            .named
+                            .iter()
+                            .map(|field| {
+                                format!(
+                                    "{}: {}",
+                                    table_key(&field.ident.as_ref().unwrap().to_string()),
+                                    self.map_type(&field.ty)
+                                )
+                            })
+                            .collect();
+                        format!("{{ {}, {} }}", tag, fields.join(", "))
+                    }
+                    syn::Fields::Unnamed(fields) => {
+                        let elements =
+                            tuple_type(fields.unnamed.iter().map(|field| self.map_type(&field.ty)));
+                        format!(
+                            "{{ {}, [number]: {} }}",
+                            tag,
+                            &elements[1..elements.len() - 1]
+                        )
+                    }
+                }
+            })
+            .collect();
+        self.add_line(&format!("{} {} = {}", keyword, name, shapes.join(" | ")));
+        self.ensure_method_table(&name);
+
+        for variant in variants {
+            let tag = format!("tag = \"{}\"", variant.ident);
+            match &variant.fields {
+                syn::Fields::Unit => {
+                    let value = self.variant_table(&name, &tag);
+                    self.add_line(&format!(
+                        "{}.{} = table.freeze({})",
+                        name, variant.ident, value
+                    ));
+                }
+                syn::Fields::Unnamed(fields) => {
+                    let params: Vec<String> = (1..=fields.unnamed.len())
+                        .map(|index| format!("_{}", index))
+                        .collect();
+                    let typed: Vec<String> = params
+                        .iter()
+                        .zip(&fields.unnamed)
+                        .map(|(param, field)| format!("{}: {}", param, self.map_type(&field.ty)))
+                        .collect();
+                    self.add_line(&format!(
+                        "function {}.{}({}): {}",
+                        name,
+                        variant.ident,
+                        typed.join(", "),
+                        name
+                    ));
+                    self.indent_manager.increase();
+                    let value =
+                        self.variant_table(&name, &format!("{}, {}", tag, params.join(", ")));
+                    self.add_line(&format!("return {}", value));
+                    self.indent_manager.decrease();
+                    self.add_line("end");
+                }
+                // Built by a struct literal, which tags the table itself
+                syn::Fields::Named(_) => {}
+            }
+        }
+    }
+
+    fn variant_table(&self, enum_name: &str, fields: &str) -> String {
+        if self.types.data_enum_has_methods(enum_name) {
+            format!("setmetatable({{ {} }}, {})", fields, enum_name)
+        } else {
+            format!("{{ {} }}", fields)
+        }
+    }
+
+    /// The data enum and variant a path like `Op::Add` or `Self::Move` names.
+    fn data_variant(&self, path: &syn::Path) -> Option<(String, String)> {
+        let segments: Vec<&syn::PathSegment> = path.segments.iter().collect();
+        let [.., enum_segment, variant_segment] = &segments[..] else {
+            return None;
+        };
+
+        let name = match (enum_segment.ident.to_string().as_str(), &self.self_type) {
+            ("Self", Some(self_type)) => self_type.clone(),
+            (name, _) => name.to_string(),
+        };
+        let variant = variant_segment.ident.to_string();
+        self.types
+            .enum_variants(&name)
+            .filter(|variants| self.types.is_data_enum(&name) && variants.contains(&variant))?;
+
+        Some((name, variant))
+    }
+
+    /// A struct's value, with the table of its methods as its metatable; `class` is the
+    /// struct's name, or the instantiation's under `--monomorphize`.
+    fn struct_table(&self, struct_name: &str, class: &str, fields: &str) -> String {
+        let table = if self.types.has_methods(struct_name) {
+            format!("setmetatable({{ {} }}, {})", fields, class)
+        } else {
+            format!("{{ {} }}", fields)
+        };
+
+        if self.types.is_readonly(struct_name) {
+            format!("table.freeze({})", table)
+        } else {
+            table
+        }
+    }
+
+    /// The React-lua hook a `use_*` call stands for, like `use_state` → `useState`.
+    fn react_hook(&self, call: &syn::ExprCall) -> Option<&'static str> {
+        if !self.types.uses_react() {
+            return None;
+        }
+
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        let name = path.path.get_ident()?.to_string();
+        if self.is_local_variable(&name) {
+            return None;
+        }
+
+        match name.as_str() {
+            "use_state" => Some("useState"),
+            "use_effect" => Some("useEffect"),
+            "use_layout_effect" => Some("useLayoutEffect"),
+            "use_memo" => Some("useMemo"),
+            "use_callback" => Some("useCallback"),
+            "use_ref" => Some("useRef"),
+            "use_context" => Some("useContext"),
+            "use_reducer" => Some("useReducer"),
+            _ => None,
+        }
+    }
+
+    fn element_root<'e>(
+        &self,
+        expr: &'e syn::Expr,
+    ) -> Option<(&'e syn::ExprCall, Vec<&'e syn::ExprMethodCall>)> {
+        if !self.types.uses_react() {
+            return None;
+        }
+
+        let mut chain = Vec::new();
+        let mut current = expr;
+        while let syn::Expr::MethodCall(method_call) = current {
+            chain.push(method_call);
+            current = &method_call.receiver;
+        }
+
+        let syn::Expr::Call(call) = current else {
+            return None;
+        };
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        let segments = &path.path.segments;
+        let is_element = segments.len() == 2
+            && segments[0].ident == "Element"
+            && matches!(segments[1].ident.to_string().as_str(), "new" | "component")
+            && !self.types.is_struct("Element");
+
+        chain.reverse();
+        is_element.then_some((call, chain))
+    }
+
+    fn is_element(&self, expr: &syn::Expr) -> bool {
+        self.element_root(expr).is_some()
+    }
+
+    // `Element::new("Frame").prop("Size", size).child("Label", label)` builds a host
+    // element, `Element::component(Counter, props)` one of another component
+    fn lower_element(&mut self, expr: &syn::Expr) -> Lowered<Option<String>> {
+        let Some((root, chain)) = self.element_root(expr) else {
+            return Ok(None);
+        };
+        let syn::Expr::Path(path) = &*root.func else {
+            unreachable!()
+        };
+
+        let constructor = path.path.segments[1].ident.to_string();
+        let element_type = root
+            .args
+            .first()
+            .map(|arg| self.transpile_expr(arg))
+            .transpose()?
+            .ok_or_else(|| unsupported!("Element::{} expects the element type", constructor))?;
+        let component_props = root
+            .args
+            .iter()
+            .nth(1)
+            .map(|arg| self.transpile_expr(arg))
+            .transpose()?;
+
+        let mut props = Vec::new();
+        let mut children = Vec::new();
+        let mut children_list = None;
+
+        for method_call in chain {
+            let method = method_call.method.to_string();
+            let args: Vec<&syn::Expr> = method_call.args.iter().collect();
+
+            match (method.as_str(), args.as_slice()) {
+                ("prop", [name, value]) => {
+                    let value = self.transpile_expr(value)?;
+                    props.push(format!("{} = {}", self.element_key(name)?, value));
+                }
+                ("event" | "change", [name, handler]) => {
+                    let kind = if method == "event" { "Event" } else { "Change" };
+                    let name = match name {
+                        syn::Expr::Lit(syn::ExprLit {
+                            lit: syn::Lit::Str(name),
+                            ..
+                        }) => name.value(),
+                        _ => {
+                            return Err(unsupported!(
+                                "Element::{} expects a string literal name",
+                                method
+                            ))
+                        }
+                    };
+
+                    let handler = self.transpile_expr(handler)?;
+                    props.push(format!("[React.{}.{}] = {}", kind, name, handler));
+                }
+                ("child", [key, child]) => {
+                    let child = self.transpile_expr(child)?;
+                    children.push(format!("{} = {}", self.element_key(key)?, child));
+                }
+                ("children", [list]) => children_list = Some(self.transpile_expr(list)?),
+                _ => {
+                    return Err(unsupported!(
+                        "Unsupported element builder method `{}`",
+                        method
+                    ))
+                }
+            }
+        }
+
+        let props = match component_props {
+            Some(_) if !props.is_empty() => {
+                return Err(unsupported!(
+                    "Props of a component element are passed to `Element::component`"
+                ))
+            }
+            Some(component_props) => component_props,
+            None if props.is_empty() => "{}".to_string(),
+            None => format!("{{ {} }}", props.join(", ")),
+        };
+
+        let children = match children_list {
+            Some(_) if !children.is_empty() => {
+                return Err(unsupported!(
+                    "An element takes either `.child` calls or one `.children` list"
+                ))
+            }
+            Some(list) => Some(list),
+            None if children.is_empty() => None,
+            None => Some(format!("{{ {} }}", children.join(", "))),
+        };
+
+        Ok(Some(match children {
+            Some(children) => format!(
+                "React.createElement({}, {}, {})",
+                element_type, props, children
+            ),
+            None if props == "{}" => format!("React.createElement({})", element_type),
+            None => format!("React.createElement({}, {})", element_type, props),
+        }))
+    }
+
+    fn element_key(&mut self, key: &syn::Expr) -> Lowered<String> {
+        Ok(match key {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(key),
+                ..
+            }) => table_key(&key.value()),
+            key => format!("[{}]", self.transpile_expr(key)?),
+        })
+    }
+
+    fn emit_macro_stmt(&mut self, mac: &syn::Macro) -> Lowered {
+        if is_luau_macro(mac) {
+            for line in raw_luau_lines(&parse_luau_macro(mac)?) {
+                self.add_line(&line);
+            }
+            return Ok(());
+        }
+
+        if self.lower_write(mac)? {
+            return Ok(());
+        }
+
+        // Outside debug builds `dbg!` only evaluates its arguments
+        if mac.path.is_ident("dbg") {
+            if self.options.cfg.is_set("debug_assertions") {
+                let call = self.lower_dbg(mac)?;
+                self.add_line(&call);
+            } else {
+                for arg in dbg_args(mac)? {
+                    if matches!(arg, syn::Expr::Call(_) | syn::Expr::MethodCall(_)) {
+                        let value = self.transpile_expr(&arg)?;
+                        self.add_line(&format!("local _ = {}", value));
+                    }
+                }
+            }
+            return Ok(());
+        }
+
+        if self.options.tests.is_some() && self.emit_expectation(mac)? {
+            return Ok(());
+        }
+
+        if let Some(level) = logging::log_level(mac) {
+            self.emit_log(mac, level)?;
+            return Ok(());
+        }
+
+        if let Some(call) = self.lower_panic(mac)? {
+            self.add_line(&call);
+            return Ok(());
+        }
+
+        let printer = match mac.path.get_ident().map(|ident| ident.to_string()) {
+            Some(name) if name == "println" || name == "print" => "print",
+            Some(name) if name == "eprintln" || name == "eprint" => "warn",
+            _ => return Err(unsupported!("Unsupported macro `{}!`", macro_name(mac))),
+        };
+
+        let message = if mac.tokens.is_empty() {
+            "\"\"".to_string()
+        } else {
+            self.lower_format(mac)?
+        };
+        self.add_line(&format!("{}({})", printer, message));
+
+        Ok(())
+    }
+
+    /// Emits `assert!`, `assert_eq!` or `assert_ne!` (or their `debug_` forms) in a spec as an
+    /// `expect` call of its test framework, dropping the message, which neither one takes.
+    fn emit_expectation(&mut self, mac: &syn::Macro) -> Lowered<bool> {
+        let Some(name) = mac.path.get_ident().map(|ident| ident.to_string()) else {
+            return Ok(false);
+        };
+        let name = name.strip_prefix("debug_").unwrap_or(&name);
+        if !matches!(name, "assert" | "assert_eq" | "assert_ne") {
+            return Ok(false);
+        }
+
+        let args: Vec<syn::Expr> = mac
+            .parse_body_with(
+                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|_| unsupported!("Failed to parse `{}!` arguments", name))?
+            .into_iter()
+            .collect();
+
+        let jest = self.options.tests == Some(TestFramework::Jest);
+        let (value, negated, expected) = match (name, &args[..]) {
+            ("assert", [condition, ..]) => (condition, false, "true".to_string()),
+            ("assert_eq", [left, right, ..]) => (left, false, self.transpile_expr(right)?),
+            ("assert_ne", [left, right, ..]) => (left, true, self.transpile_expr(right)?),
+            _ => return Err(unsupported!("`{}!` is missing its operands", name)),
+        };
+        let value = self.transpile_expr(value)?;
+
+        let matcher = match (jest, name) {
+            (true, "assert") => "toBe",
+            (true, _) => "toEqual",
+            (false, _) => "to.equal",
+        };
+        let never = if negated { "never." } else { "" };
+        self.add_line(&format!(
+            "expect({}).{}{}({})",
+            value, never, matcher, expected
+        ));
+
+        Ok(true)
+    }
+
+    /// Emits a `describe` block for the file with an `it` for each of its `#[test]` functions,
+    /// which for TestEZ is returned in the function a spec module returns.
+    fn emit_test_suite(&mut self, file: &syn::File, framework: TestFramework) -> Lowered {
+        let mut tests = Vec::new();
+        self.collect_tests(&file.items, &mut Vec::new(), &mut tests)?;
+
+        let testez = framework == TestFramework::Testez;
+        if testez {
+            self.add_line("return function()");
+            self.indent_manager.increase();
+        }
+
+        let module = &self.options.module_name;
+        let name = if module.is_empty() { "tests" } else { module };
+        self.add_line(&format!(
+            "describe({}, function()",
+            format_string::quoted(name)
+        ));
+        self.indent_manager.increase();
+
+        for (path, item_fn) in tests {
+            let ignored = item_fn
+                .attrs
+                .iter()
+                .any(|attr| attr.path().is_ident("ignore"));
+            let it = match (ignored, testez) {
+                (true, true) => "itSKIP",
+                (true, false) => "it.skip",
+                (false, _) => "it",
+            };
+            // Named like cargo names it, and called through the tables of its modules
+            let mut path = path;
+            path.push(item_fn.sig.ident.to_string());
+            let label = format_string::quoted(&path.join("::"));
+            let callee = path.join(".");
+
+            self.add_line(&format!("{}({}, function()", it, label));
+            self.indent_manager.increase();
+
+            let should_panic = item_fn
+                .attrs
+                .iter()
+                .find(|attr| attr.path().is_ident("should_panic"));
+            if let Some(attr) = should_panic {
+                // `expected` is a substring of the message, as both frameworks match it
+                let expected = match &attr.meta {
+                    syn::Meta::List(_) => {
+                        let value: syn::MetaNameValue = attr.parse_args().map_err(|_| {
+                            unsupported!("Expected `#[should_panic(expected = \"...\")]`")
+                        })?;
+                        self.transpile_expr(&value.value)?
+                    }
+                    _ => String::new(),
+                };
+                let matcher = if testez { "to.throw" } else { "toThrow" };
+                self.add_line(&format!("expect({}).{}({})", callee, matcher, expected));
+            } else if matches!(
+                &item_fn.sig.output,
+                syn::ReturnType::Type(_, ty) if type_registry::type_name(ty).as_deref() == Some("Result")
+            ) {
+                let result = self.names.fresh();
+                self.add_line(&format!("local {} = {}()", result, callee));
+                self.add_line(&format!(
+                    "if not {}.ok then error({}.err, 0) end",
+                    result, result
+                ));
+            } else {
+                self.add_line(&format!("{}()", callee));
+            }
+
+            self.indent_manager.decrease();
+            self.add_line("end)");
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("end)");
+
+        if testez {
+            self.indent_manager.decrease();
+            self.add_line("end");
+        }
+
+        Ok(())
+    }
+
+    /// The enabled `#[test]` functions among `items`, with the path of modules they are in.
+    fn collect_tests(
+        &self,
+        items: &[syn::Item],
+        path: &mut Vec<String>,
+        tests: &mut Vec<(Vec<String>, syn::ItemFn)>,
+    ) -> Lowered {
+        for item in items {
+            match item {
+                syn::Item::Fn(item_fn)
+                    if item_fn
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("test"))
+                        && self.options.cfg.is_enabled(&item_fn.attrs) =>
+                {
+                    if !item_fn.sig.inputs.is_empty() {
+                        return Err(unsupported!(
+                            "`#[test]` function `{}` must take no arguments",
+                            item_fn.sig.ident
+                        ));
+                    }
+                    tests.push((path.clone(), item_fn.clone()));
+                }
+                syn::Item::Mod(item_mod) if self.options.cfg.is_enabled(&item_mod.attrs) => {
+                    if let Some((_, items)) = &item_mod.content {
+                        path.push(item_mod.ident.to_string());
+                        self.collect_tests(items, path, tests)?;
+                        path.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Emits a `log` or `tracing` event as a `print`, or a `warn` for warnings and errors, of
+    /// its level, message and `key=value` fields, or with `--logger` as a call to the
+    /// logger module's function for the level with the message and a table of the fields.
+    fn emit_log(&mut self, mac: &syn::Macro, level: &str) -> Lowered {
+        let args = logging::parse_log_args(mac);
+        let format_macro =
+            |message: &[syn::Expr]| -> syn::Macro { syn::parse_quote!(format!(#(#message),*)) };
+
+        if self.options.logger.is_some() {
+            let message = match args.message.is_empty() {
+                true => "\"\"".to_string(),
+                false => self.lower_format(&format_macro(&args.message))?,
+            };
+            let fields: Vec<String> = args
+                .fields
+                .iter()
+                .map(|field| {
+                    let value = self.transpile_expr(&field.value)?;
+                    Ok(format!("{} = {}", table_key(&field.name), value))
+                })
+                .collect::<Lowered<_>>()?;
+
+            let call = match fields.is_empty() {
+                true => format!("Logger.{}({})", level, message),
+                false => format!("Logger.{}({}, {{ {} }})", level, message, fields.join(", ")),
+            };
+            self.add_line(&call);
+            return Ok(());
+        }
+
+        // The fields follow the message as extra arguments, by index past the message's own
+        let mut message = args.message.into_iter();
+        let (text, mut format_args) = match message.next() {
+            Some(syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(text),
+                ..
+            })) => (format!(" {}", text.value()), message.collect::<Vec<_>>()),
+            Some(_) => {
+                return Err(unsupported!(
+                    "`{}!` expects a string literal message",
+                    level
+                ))
+            }
+            None => (String::new(), Vec::new()),
+        };
+        let positional = format_args
+            .iter()
+            .filter(|arg| !matches!(arg, syn::Expr::Assign(_)))
+            .count();
+
+        let mut format = format!("[{}]{}", level.to_uppercase(), text);
+        for (index, field) in args.fields.into_iter().enumerate() {
+            let spec = match field.format {
+                FieldFormat::Debug => ":?",
+                FieldFormat::Display => "",
+            };
+            format.push_str(&format!(
+                " {}={{{}{}}}",
+                field.name,
+                positional + index,
+                spec
+            ));
+            format_args.push(field.value);
+        }
+
+        let format = syn::LitStr::new(&format, mac.path.span());
+        let mut message = vec![syn::Expr::Lit(syn::ExprLit {
+            attrs: Vec::new(),
+            lit: syn::Lit::Str(format),
+        })];
+        message.extend(format_args);
+        let message = self.lower_format(&format_macro(&message))?;
+
+        let printer = match level {
+            "warn" | "error" => "warn",
+            _ => "print",
+        };
+        self.add_line(&format!("{}({})", printer, message));
+
+        Ok(())
+    }
+
+    /// `let mut s = String::new()` for a string that is built up with `write!`, which is
+    /// bound to a table of parts instead and joined with `table.concat` wherever it's read.
+    fn string_buffer_binding(&self, local: &syn::Local) -> Option<String> {
+        let pat = match &local.pat {
+            syn::Pat::Type(pat_type) => &*pat_type.pat,
+            pat => pat,
+        };
+        let syn::Pat::Ident(pat_ident) = pat else {
+            return None;
+        };
+
+        let name = pat_ident.ident.to_string();
+        let is_new_string = local
+            .init
+            .as_ref()
+            .is_some_and(|init| is_new_string(&init.expr));
+        (is_new_string && self.string_buffers.contains(&name)).then_some(name)
+    }
+
+    fn is_string_buffer(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Reference(reference) => self.is_string_buffer(&reference.expr),
+            syn::Expr::Paren(paren) => self.is_string_buffer(&paren.expr),
+            syn::Expr::Path(path) => path.path.get_ident().is_some_and(|ident| {
+                let name = ident.to_string();
+                self.string_buffers.contains(&name) && self.is_local_variable(&name)
+            }),
+            _ => false,
+        }
+    }
+
+    /// The parts table of a string buffer, rather than its joined value.
+    fn transpile_buffer(&self, expr: &syn::Expr) -> String {
+        match expr {
+            syn::Expr::Reference(reference) => self.transpile_buffer(&reference.expr),
+            syn::Expr::Paren(paren) => self.transpile_buffer(&paren.expr),
+            syn::Expr::Path(path) => path.path.get_ident().unwrap().to_string(),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Emits a `write!` statement, seeing through the `?`, `.unwrap()` or `.expect(..)`
+    /// that handles its `fmt::Result`, which can't fail for a string buffer.
+    fn emit_write(&mut self, expr: &syn::Expr) -> Lowered<bool> {
+        Ok(match expr {
+            syn::Expr::Macro(expr_macro) => self.lower_write(&expr_macro.mac)?,
+            syn::Expr::Try(expr_try) => self.emit_write(&expr_try.expr)?,
+            syn::Expr::MethodCall(method_call)
+                if matches!(method_call.method.to_string().as_str(), "unwrap" | "expect") =>
+            {
+                matches!(&*method_call.receiver, syn::Expr::Macro(expr_macro)
+                    if self.lower_write(&expr_macro.mac)?)
+            }
+            _ => false,
+        })
+    }
+
+    /// The `SharedTable` an expression is or locks, like `STATE` for `STATE.write().unwrap()`.
+    fn shared_table_of<'e>(&self, expr: &'e syn::Expr) -> Option<&'e syn::Expr> {
+        match expr {
+            syn::Expr::Reference(reference) => self.shared_table_of(&reference.expr),
+            syn::Expr::Paren(paren) => self.shared_table_of(&paren.expr),
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Deref(_),
+                expr,
+                ..
+            }) => self.shared_table_of(expr),
+            syn::Expr::MethodCall(method_call)
+                if matches!(
+                    method_call.method.to_string().as_str(),
+                    "read" | "write" | "lock" | "unwrap" | "expect"
+                ) =>
+            {
+                self.shared_table_of(&method_call.receiver)
+            }
+            _ => (self.actor
+                && self
+                    .expr_rust_type(expr)
+                    .is_some_and(type_registry::is_shared_table_type))
+            .then_some(expr),
+        }
+    }
+
+    /// Lowers a method of a `SharedTable`'s map, or of the lock around it, which is the
+    /// table itself.
+    fn lower_shared_table_method(&mut self, method_call: &syn::ExprMethodCall) -> Lowered<String> {
+        let table = self.shared_table_of(&method_call.receiver).unwrap();
+        let table = self.transpile_expr(table)?;
+        let method = method_call.method.to_string();
+        let key = || {
+            method_call
+                .args
+                .first()
+                .ok_or_else(|| unsupported!("`.{}()` expects a key", method_call.method))
+        };
+
+        Ok(match method.as_str() {
+            "read" | "write" | "lock" | "unwrap" | "expect" | "clone" => table,
+            "get" => format!("{}[{}]", table, self.transpile_expr(key()?)?),
+            "contains_key" => format!("{}[{}] ~= nil", table, self.transpile_expr(key()?)?),
+            "len" => format!("SharedTable.size({})", table),
+            "is_empty" => format!("SharedTable.size({}) == 0", table),
+            "insert" | "remove" | "clear" => {
+                return Err(unsupported!(
+                    "`{}` on a `SharedTable` is only supported as a statement, not for its value",
+                    method
+                ))
+            }
+            _ => {
+                return Err(unsupported!(
+                    "`{}` is not supported on a `SharedTable`",
+                    method
+                ))
+            }
+        })
+    }
+
+    /// Emits `insert`, `remove` or `clear` on a `SharedTable` as a statement, returning false
+    /// for other expressions.
+    fn emit_shared_table_write(&mut self, expr: &syn::Expr) -> Lowered<bool> {
+        let syn::Expr::MethodCall(method_call) = expr else {
+            return Ok(false);
+        };
+        let Some(table) = self.shared_table_of(&method_call.receiver) else {
+            return Ok(false);
+        };
+
+        let table = self.transpile_expr(table)?;
+        let args: Vec<String> = method_call
+            .args
+            .iter()
+            .map(|arg| self.transpile_expr(arg))
+            .collect::<Lowered<Vec<_>>>()?;
+        let line = match (method_call.method.to_string().as_str(), &args[..]) {
+            ("insert", [key, value]) => format!("{}[{}] = {}", table, key, value),
+            ("remove", [key]) => format!("{}[{}] = nil", table, key),
+            ("clear", []) => format!("SharedTable.clear({})", table),
+            _ => return Ok(false),
+        };
+        self.add_line(&line);
+
+        Ok(true)
+    }
+
+    fn is_once_cell(&self, expr: &syn::Expr) -> bool {
+        self.receiver_type_name(expr).is_some_and(|ty| {
+            matches!(ty.as_str(), "OnceLock" | "OnceCell") && !self.types.is_struct(&ty)
+        })
+    }
+
+    /// Lowers `cell.get_or_init(f)` to setting the cell if it's still `nil`, and the cell;
+    /// `cell.get()` is the cell itself.
+    fn lower_once_cell(&mut self, method_call: &syn::ExprMethodCall) -> Lowered<String> {
+        let cell = self.transpile_expr(&method_call.receiver)?;
+        if method_call.method == "get" {
+            return Ok(cell);
+        }
+
+        let init = method_call
+            .args
+            .first()
+            .ok_or_else(|| unsupported!("`get_or_init` expects an initializer"))?;
+        self.add_line(&format!("if {} == nil then", cell));
+        self.indent_manager.increase();
+        match init {
+            syn::Expr::Closure(closure) if closure.inputs.is_empty() => match &*closure.body {
+                syn::Expr::Block(body) => {
+                    self.visit_block_stmts(&body.block, BlockTail::Assign(&cell))?
+                }
+                body => self.emit_value_into(body, &cell)?,
+            },
+            init => {
+                let init = self.transpile_expr(init)?;
+                self.add_line(&format!("{} = {}()", cell, init));
+            }
+        }
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(cell)
+    }
+
+    /// Emits `cell.set(value)` (or its `.ok()`) or `cell.get_or_init(f)` as a statement,
+    /// returning false for other expressions.
+    fn emit_once_cell_set(&mut self, expr: &syn::Expr) -> Lowered<bool> {
+        let syn::Expr::MethodCall(method_call) = expr else {
+            return Ok(false);
+        };
+        if method_call.method == "ok" && method_call.args.is_empty() {
+            return self.emit_once_cell_set(&method_call.receiver);
+        }
+        if !self.is_once_cell(&method_call.receiver) {
+            return Ok(false);
+        }
+
+        match method_call.method.to_string().as_str() {
+            "get_or_init" => {
+                self.lower_once_cell(method_call)?;
+            }
+            "set" => {
+                let cell = self.transpile_expr(&method_call.receiver)?;
+                let value = self.transpile_args(&method_call.args)?;
+                self.add_line(&format!("if {} == nil then", cell));
+                self.indent_manager.increase();
+                self.add_line(&format!("{} = {}", cell, value));
+                self.indent_manager.decrease();
+                self.add_line("end");
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Lowers `write!(s, ...)` and `writeln!(s, ...)` on a string buffer to a
+    /// `table.insert` of the formatted part, returning false for other writers.
+    fn lower_write(&mut self, mac: &syn::Macro) -> Lowered<bool> {
+        if !is_write_macro(mac) {
+            return Ok(false);
+        }
+
+        let mut args = mac
+            .parse_body_with(
+                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|_| unsupported!("Failed to parse `write!` arguments"))?
+            .into_iter();
+        let Some(target) = args.next() else {
+            return Ok(false);
+        };
+        if !self.is_string_buffer(&target) {
+            return Ok(false);
+        }
+
+        let mut format_args: Vec<syn::Expr> = args.collect();
+        if mac.path.is_ident("writeln") {
+            match format_args.first_mut() {
+                Some(syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(format),
+                    ..
+                })) => *format = syn::LitStr::new(&(format.value() + "\n"), format.span()),
+                _ => format_args.insert(0, syn::parse_quote!("\n")),
+            }
+        }
+
+        let format_mac = syn::Macro {
+            path: syn::parse_quote!(format),
+            tokens: quote::quote!(#(#format_args),*),
+            ..mac.clone()
+        };
+
+        let buffer = self.transpile_buffer(&target);
+        let part = self.lower_format(&format_mac)?;
+        self.add_line(&format!("table.insert({}, {})", buffer, part));
+        Ok(true)
+    }
+
+    /// Lowers `matches!(x, Pat)` to the pattern's condition. A guard needs the pattern's
+    /// bindings, so it's evaluated into a temporary inside an `if` on the pattern.
+    fn lower_matches(&mut self, mac: &syn::Macro) -> Lowered<String> {
+        let (expr, pat, guard) = mac
+            .parse_body_with(|input: syn::parse::ParseStream| {
+                let expr: syn::Expr = input.parse()?;
+                input.parse::<syn::Token![,]>()?;
+                let pat = syn::Pat::parse_multi_with_leading_vert(input)?;
+                let guard = if input.parse::<Option<syn::Token![if]>>()?.is_some() {
+                    Some(input.parse::<syn::Expr>()?)
+                } else {
+                    None
+                };
+                input.parse::<Option<syn::Token![,]>>()?;
+                Ok((expr, pat, guard))
+            })
+            .map_err(|_| unsupported!("Failed to parse `matches!` arguments"))?;
+
+        let elements = match &expr {
+            syn::Expr::Tuple(tuple) => Some(
+                tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.match_operand(elem))
+                    .collect::<Lowered<Vec<_>>>()?,
+            ),
+            _ => None,
+        };
+        let scrutinee = match &elements {
+            Some(elements) => format!("{{{}}}", elements.join(", ")),
+            None => self.match_operand(&expr)?,
+        };
+
+        let condition = self.pattern_condition(&pat, &scrutinee, elements.as_deref())?;
+        let Some(guard) = guard else {
+            return Ok(match condition {
+                Some(condition) if condition.contains(" or ") => format!("({})", condition),
+                Some(condition) => condition,
+                None => "true".to_string(),
+            });
+        };
+
+        let result = self.names.fresh();
+        self.add_line(&format!("local {} = false", result));
+        match &condition {
+            Some(condition) => self.add_line(&format!("if {} then", condition)),
+            None => self.add_line("do"),
+        }
+        self.indent_manager.increase();
+        self.scopes.enter();
+
+        let mut bindings = Vec::new();
+        collect_pattern_bindings(
+            &pat,
+            &scrutinee,
+            elements.as_deref(),
+            &self.types,
+            &mut bindings,
+        )?;
+        for (name, value) in bindings {
+            self.add_local_variable(&name, false, true);
+            self.add_line(&format!("local {} = {}", name, value));
+        }
+
+        let guard = self.transpile_expr(&guard)?;
+        self.add_line(&format!("{} = {}", result, guard));
+
+        self.scopes.exit();
+        self.indent_manager.decrease();
+        self.add_line("end");
+        Ok(result)
+    }
+
+    /// Lowers `vec![a, b]` to a table constructor. `vec![x; n]` fills the table in a loop,
+    /// evaluating `x` for each element the way `vec!` clones it.
+    fn lower_vec(&mut self, mac: &syn::Macro) -> Lowered<String> {
+        let repeat = mac.parse_body_with(|input: syn::parse::ParseStream| {
+            let value: syn::Expr = input.parse()?;
+            input.parse::<syn::Token![;]>()?;
+            let len: syn::Expr = input.parse()?;
+            Ok((value, len))
+        });
+
+        let Ok((value, len)) = repeat else {
+            let elements = mac
+                .parse_body_with(
+                    syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+                )
+                .map_err(|_| unsupported!("Failed to parse `vec!` elements"))?;
+            let elements = self.transpile_elements(&elements)?;
+            return Ok(format!("{{{}}}", elements.join(", ")));
+        };
+
+        self.lower_repeat(&value, &len)
+    }
+
+    /// Lowers the elements of a `vec!` or array literal.
+    fn transpile_elements<'e>(
+        &mut self,
+        elements: impl IntoIterator<Item = &'e syn::Expr>,
+    ) -> Lowered<Vec<String>> {
+        elements
+            .into_iter()
+            .map(|element| {
+                self.check_element(element)?;
+                self.transpile_expr(element)
+            })
+            .collect()
+    }
+
+    /// An `Option` stored in a `Vec` or array could be a `nil`, leaving a hole in the table
+    /// that `#` and `ipairs` stop at.
+    fn check_element(&self, element: &syn::Expr) -> Lowered {
+        let is_some = matches!(element, syn::Expr::Call(call)
+            if matches!(&*call.func, syn::Expr::Path(path) if path.path.is_ident("Some")));
+
+        if is_none_expr(element) || (self.is_optional(element) && !is_some) {
+            return Err(unsupported!(
+                "A `None` in a `Vec` or array is a `nil` that leaves a hole in its table, which `#` and `ipairs` stop at; store a value standing for it instead"
+            )
+            .within(element.span()));
+        }
+
+        Ok(())
+    }
+
+    /// Lowers `vec![value; len]` and `[value; len]` to a table of `len` copies of the value.
+    fn lower_repeat(&mut self, value: &syn::Expr, len: &syn::Expr) -> Lowered<String> {
+        let len = self.transpile_expr(len)?;
+        if is_none_expr(value) {
+            return Ok(format!("table.create({})", len));
+        }
+        if is_immutable_value(value) {
+            let value = self.transpile_expr(value)?;
+            return Ok(format!("table.create({}, {})", len, value));
+        }
+
+        let table = self.names.fresh();
+        let index = self.names.fresh();
+        self.add_line(&format!("local {} = {{}}", table));
+        self.add_line(&format!("for {} = 1, {} do", index, len));
+        self.indent_manager.increase();
+        let value = self.transpile_expr(value)?;
+        self.add_line(&format!("{}[{}] = {}", table, index, value));
+        self.indent_manager.decrease();
+        self.add_line("end");
+        Ok(table)
+    }
+
+    /// Lowers `dbg!(x)` to a call that warns `[line:column] x = <value>` and returns the
+    /// value in debug builds (`--cfg debug_assertions`), and to just the value otherwise.
+    /// `panic!`, `unreachable!`, `todo!` and `unimplemented!` as an `error` call with the
+    /// message Rust would panic with.
+    fn lower_panic(&mut self, mac: &syn::Macro) -> Lowered<Option<String>> {
+        let Some(prefix) = panic_prefix(mac) else {
+            return Ok(None);
+        };
+        let message = match (prefix, mac.tokens.is_empty()) {
+            ("", true) => "\"explicit panic\"".to_string(),
+            (prefix, true) => format_string::quoted(prefix),
+            ("", false) => self.lower_format(mac)?,
+            (prefix, false) => format!(
+                "{} .. {}",
+                format_string::quoted(&format!("{}: ", prefix)),
+                self.lower_format(mac)?
+            ),
+        };
+        Ok(Some(format!("error({})", message)))
+    }
+
+    fn lower_dbg(&mut self, mac: &syn::Macro) -> Lowered<String> {
+        let args = dbg_args(mac)?;
+        let debug = self.options.cfg.is_set("debug_assertions");
+        if debug {
+            self.use_helper(Helper::Dbg);
+        }
+
+        let location = |span: proc_macro2::Span| {
+            let start = span.start();
+            format!("[{}:{}]", start.line, start.column + 1)
+        };
+
+        let values: Vec<String> = args
+            .iter()
+            .map(|arg| {
+                let value = self.transpile_expr(arg)?;
+                if !debug {
+                    return Ok(value);
+                }
+
+                let source = arg.span().source_text().unwrap_or_default();
+                let label = format!("{} {} =", location(mac.path.span()), source);
+                Ok(format!("dbg({}, {})", format_string::quoted(&label), value))
+            })
+            .collect::<Lowered<_>>()?;
+
+        Ok(match values.as_slice() {
+            [] if debug => format!("dbg({})", format_string::quoted(&location(mac.path.span()))),
+            [] => "nil".to_string(),
+            [value] => value.clone(),
+            values => format!("{{{}}}", values.join(", ")),
+        })
+    }
+
+    /// Lowers the arguments of `format!`, `println!` and friends to a Luau string,
+    /// interpolated when the format string has placeholders.
+    fn lower_format(&mut self, mac: &syn::Macro) -> Lowered<String> {
+        let args = mac
+            .parse_body_with(
+                syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated,
+            )
+            .map_err(|_| {
+                unsupported!(
+                    "Failed to parse `{}!` arguments",
+                    mac.path.segments[0].ident
+                )
+            })?;
+        let mut args = args.into_iter();
+
+        let Some(syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(format),
+            ..
+        })) = args.next()
+        else {
+            return Err(unsupported!(
+                "Format macros expect a string literal as their first argument"
+            ));
+        };
+
+        let mut positional = Vec::new();
+        let mut named = HashMap::new();
+        for arg in args {
+            match arg {
+                syn::Expr::Assign(assign) => {
+                    let syn::Expr::Path(name) = &*assign.left else {
+                        return Err(unsupported!("Named format arguments must be identifiers"));
+                    };
+                    named.insert(name.path.segments[0].ident.to_string(), *assign.right);
+                }
+                arg => positional.push(arg),
+            }
+        }
+
+        let pieces = format_string::parse(&format.value()).map_err(Unsupported::new)?;
+        if let [] | [Piece::Literal(_)] = pieces.as_slice() {
+            let text = match pieces.first() {
+                Some(Piece::Literal(text)) => text.as_str(),
+                _ => "",
+            };
+            return Ok(format_string::quoted(text));
+        }
+
+        let mut next = 0;
+        let mut lowered = String::from("`");
+        for piece in pieces {
+            match piece {
+                Piece::Literal(text) => lowered.push_str(&format_string::interpolated(&text)),
+                Piece::Placeholder { arg, spec } => {
+                    // `{:.*}` takes its precision from the next argument, ahead of the value
+                    let precision = match spec.contains(".*") {
+                        true => {
+                            next += 1;
+                            let precision = positional.get(next - 1).cloned().ok_or_else(|| {
+                                unsupported!("Format string has more placeholders than arguments")
+                            })?;
+                            Some(self.transpile_expr(&precision)?)
+                        }
+                        false => None,
+                    };
+                    let spec = spec.replacen(".*", ".0", 1);
+
+                    // An inline `{name}` is a variable in scope
+                    let (arg, inline) = match arg {
+                        ArgRef::Next => {
+                            next += 1;
+                            (positional.get(next - 1).cloned(), None)
+                        }
+                        ArgRef::Index(index) => (positional.get(index).cloned(), None),
+                        ArgRef::Name(name) => match named.get(&name) {
+                            Some(arg) => (Some(arg.clone()), None),
+                            None => {
+                                let ident = syn::Ident::new(&name, format.span());
+                                (Some(syn::parse_quote!(#ident)), Some(name))
+                            }
+                        },
+                    };
+                    let arg = arg.ok_or_else(|| {
+                        unsupported!("Format string has more placeholders than arguments")
+                    })?;
+                    let mut value = match inline {
+                        Some(name) => name,
+                        None => self.transpile_expr(&arg)?,
+                    };
+
+                    match format_string::printf_pattern(&spec, self.value_kind(&arg)) {
+                        Ok(Some(pattern)) => {
+                            let pattern = match (&precision, pattern.split_once(".0")) {
+                                (Some(precision), Some((before, after))) => format!(
+                                    "{} .. {} .. {}",
+                                    format_string::quoted(&format!("{}.", before)),
+                                    precision,
+                                    format_string::quoted(after)
+                                ),
+                                _ => format_string::quoted(&pattern),
+                            };
+                            value = format!("string.format({}, {})", pattern, value);
+                        }
+                        Ok(None) => {}
+                        Err(reason) => self.warn(
+                            "format-spec",
+                            format!("`{{:{}}}` is formatted like `{{}}`: {}", spec, reason),
+                            format.span(),
+                        ),
+                    }
+
+                    lowered.push('{');
+                    lowered.push_str(&value);
+                    lowered.push('}');
+                }
+            }
+        }
+
+        lowered.push('`');
+        Ok(lowered)
+    }
+
+    fn env_args(&self) -> String {
+        if let Some(env_args) = &self.options.env_args {
+            return env_args.clone();
+        }
+
+        if self.is_lune() {
+            // Lune's `process.args` leaves out the program name Rust puts first
+            self.use_lune_library("process");
+            return "{ \"lune\", table.unpack(process.args) }".to_string();
+        }
+
+        "{}".to_string()
+    }
+
+    /// `std::fs`, `std::env`, `std::process` and `net` calls lowered to the Lune builtin
+    /// libraries; fallible ones return the `Result` representation.
+    fn lower_lune_call(&mut self, call: &syn::ExprCall) -> Lowered<Option<String>> {
+        if !self.is_lune() {
+            return Ok(None);
+        }
+
+        let fs_function = LUNE_FS
+            .iter()
+            .find(|(name, _)| is_std_path(&call.func, "fs", name));
+        if let Some((_, lune_name)) = fs_function {
+            self.use_lune_library("fs");
+            self.use_helper(Helper::PcallResult);
+
+            let args = self.transpile_args(&call.args)?;
+            return Ok(Some(format!(
+                "pcallResult(pcall(fs.{}, {}))",
+                lune_name, args
+            )));
+        }
+
+        if is_std_path(&call.func, "env", "var") && call.args.len() == 1 {
+            self.use_lune_library("process");
+            self.use_helper(Helper::EnvVar);
+            return Ok(Some(format!(
+                "envVar({})",
+                self.transpile_expr(&call.args[0])?
+            )));
+        }
+
+        if is_std_path(&call.func, "env", "current_dir") {
+            self.use_lune_library("process");
+            self.use_helper(Helper::ResultType);
+            return Ok(Some("{ ok = true, value = process.cwd }".to_string()));
+        }
+
+        if let Some(code) = process_exit_code(call) {
+            self.use_lune_library("process");
+            return Ok(Some(format!(
+                "process.exit({})",
+                self.transpile_expr(code)?
+            )));
+        }
+
+        let syn::Expr::Path(path) = &*call.func else {
+            return Ok(None);
+        };
+
+        let segments = &path.path.segments;
+        if segments.len() == 2 && segments[0].ident == "net" && !self.is_local_variable("net") {
+            self.use_lune_library("net");
+
+            let function = camel_case(&segments[1].ident.to_string());
+            let args = self.transpile_args(&call.args)?;
+            return Ok(Some(format!("net.{}({})", function, args)));
+        }
+
+        Ok(None)
+    }
+
+    /// The `T` of the `Result<T, E>` that a `std::fs` or `std::env` call lowered for Lune
+    /// makes, so its `unwrap` and `?` read the table's fields.
+    fn lune_result_value(&self, call: &syn::ExprCall) -> Option<syn::Type> {
+        if !self.is_lune() {
+            return None;
+        }
+
+        if is_std_path(&call.func, "fs", "read_to_string")
+            || (is_std_path(&call.func, "env", "var") && call.args.len() == 1)
+        {
+            return Some(syn::parse_quote!(String));
+        }
+        if is_std_path(&call.func, "env", "current_dir") {
+            return Some(syn::parse_quote!(PathBuf));
+        }
+
+        LUNE_FS
+            .iter()
+            .any(|(name, _)| is_std_path(&call.func, "fs", name))
+            .then(|| syn::parse_quote!(()))
+    }
+
+    /// The closure of a `catch_unwind(|| ...)` call, seeing through `AssertUnwindSafe`, which
+    /// lowers to a `pcall` so errors raised inside become an `Err`.
+    fn catch_unwind_body<'e>(&self, call: &'e syn::ExprCall) -> Option<&'e syn::Expr> {
+        let is_path = |func: &syn::Expr, name: &str| match func {
+            syn::Expr::Path(path) => {
+                let segments: Vec<String> = path
+                    .path
+                    .segments
+                    .iter()
+                    .map(|segment| segment.ident.to_string())
+                    .collect();
+
+                let prefix = &segments[..segments.len() - 1];
+                segments.last().is_some_and(|last| last == name)
+                    && matches!(
+                        prefix
+                            .iter()
+                            .map(String::as_str)
+                            .collect::<Vec<_>>()
+                            .as_slice(),
+                        [] | ["panic"] | ["std", "panic"]
+                    )
+                    && !(prefix.is_empty() && self.is_local_variable(name))
+            }
+            _ => false,
+        };
+
+        if !is_path(&call.func, "catch_unwind") || call.args.len() != 1 {
+            return None;
+        }
+
+        match &call.args[0] {
+            syn::Expr::Call(wrapper)
+                if is_path(&wrapper.func, "AssertUnwindSafe") && wrapper.args.len() == 1 =>
+            {
+                Some(&wrapper.args[0])
+            }
+            body => Some(body),
+        }
+    }
+
+    fn variant_constructor(&self, call: &syn::ExprCall) -> Option<&'static str> {
+        let syn::Expr::Path(path) = &*call.func else {
+            return None;
+        };
+
+        let ident = path.path.get_ident()?;
+        if self.is_local_variable(&ident.to_string()) {
+            return None;
+        }
+
+        match ident.to_string().as_str() {
+            "Some" => Some("Some"),
+            "Ok" => Some("Ok"),
+            "Err" => Some("Err"),
+            _ => None,
+        }
+    }
+
+    // `?` hoists its operand into a local and returns early from the enclosing function;
+    // an `Option` function propagates `nil`, a `Result` function the failed result table
+    fn lower_try(&mut self, expr_try: &syn::ExprTry) -> Lowered<String> {
+        let value = self.transpile_expr(&expr_try.expr)?;
+        let temp = if is_simple_place(&value) {
+            value
+        } else {
+            let temp = self.names.fresh();
+            self.add_line(&format!("local {} = {}", temp, value));
+            temp
+        };
+
+        let returns_option = self
+            .fn_output
+            .as_ref()
+            .and_then(type_registry::type_name)
+            .is_some_and(|ty| ty == "Option");
+
+        if returns_option {
+            let exit = self.return_keyword();
+            self.add_line(&format!("if {} == nil then {} nil end", temp, exit));
+            return Ok(temp);
+        }
+
+        // `#[from]` wraps the error in the variant for its type, like `?` calls `From::from`
+        let wrapped = self
+            .fn_output
+            .as_ref()
+            .and_then(type_registry::result_error)
+            .zip(self.try_error_type(&expr_try.expr))
+            .and_then(|(error, source)| {
+                let variant = self.types.conversion_variant(&error, &source)?;
+                Some(format!("{}.{}({}.err)", error, variant, temp))
+            });
+
+        if self.in_script_main {
+            let error = wrapped.unwrap_or_else(|| format!("{}.err", temp));
+            self.add_line(&format!("if not {}.ok then error({}, 0) end", temp, error));
+        } else if let Some(error) = wrapped {
+            self.add_line(&format!(
+                "if not {}.ok then {} {{ ok = false, err = {} }} end",
+                temp,
+                self.return_keyword(),
+                error
+            ));
+        } else {
+            let exit = self.return_keyword();
+            self.add_line(&format!("if not {}.ok then {} {} end", temp, exit, temp));
+        }
+        Ok(format!("{}.value", temp))
+    }
+
+    /// Lowers anyhow's `.context(msg)` and `.with_context(|| msg)`: a `None` becomes an error
+    /// with the message, and an error gets it in front, as `{:#}` prints the chain.
+    fn lower_context(&mut self, method_call: &syn::ExprMethodCall) -> Lowered<String> {
+        let receiver = &method_call.receiver;
+        let is_option = match self.expr_rust_type(receiver) {
+            Some(ty) => type_registry::type_name(ty).as_deref() == Some("Option"),
+            None => {
+                self.try_error_type(receiver).is_none()
+                    && matches!(&**receiver, syn::Expr::MethodCall(call)
+                        if OPTION_METHODS.contains(&call.method.to_string().as_str()))
+            }
+        };
+        let value = self.match_operand(receiver)?;
+
+        // The message is spliced into the interpolated error
+        let message = match &method_call.args[0] {
+            syn::Expr::Closure(closure) if method_call.method == "with_context" => {
+                self.transpile_expr(&closure.body)?
+            }
+            message if method_call.method == "with_context" => {
+                format!("{}()", self.transpile_expr(message)?)
+            }
+            message => self.transpile_expr(message)?,
+        };
+        let message = match message.strip_prefix('`').and_then(|m| m.strip_suffix('`')) {
+            Some(text) => text.to_string(),
+            None => match message.strip_prefix('"').and_then(|m| m.strip_suffix('"')) {
+                Some(text) if !text.contains('\\') => format_string::interpolated(text),
+                _ => format!("{{{}}}", message),
+            },
+        };
+
+        Ok(if is_option {
+            format!(
+                "(if {} ~= nil then {{ ok = true, value = {} }} else {{ ok = false, err = `{}` }})",
+                value, value, message
+            )
+        } else {
+            format!(
+                "(if {}.ok then {} else {{ ok = false, err = `{}: {{{}.err}}` }})",
+                value, value, message, value
+            )
+        })
+    }
+
+    /// The error type name of a `Result` that `?` is applied to, where it's known.
+    fn try_error_type(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::Call(call) => match &*call.func {
+                syn::Expr::Path(path) => {
+                    let name = path.path.get_ident()?.to_string();
+                    self.types.result_errors.get(&name).cloned()
+                }
+                _ => None,
+            },
+            syn::Expr::Paren(paren) => self.try_error_type(&paren.expr),
+            _ => self
+                .expr_rust_type(expr)
+                .and_then(type_registry::result_error),
+        }
+    }
+
+    /// Resolves `crate::`, `self::` and `super::` prefixes against the inline module the
+    /// path appears in.
+    fn resolve_module_path(&self, path: &syn::Path) -> Lowered<Option<String>> {
+        let Some(first) = path.segments.first().map(|segment| &segment.ident) else {
+            return Ok(None);
+        };
+        if first != "crate" && first != "self" && first != "super" {
+            return Ok(None);
+        }
+
+        let mut module = self.module_path.clone();
+        let mut segments = path.segments.iter().peekable();
+
+        match segments.peek() {
+            Some(segment) if segment.ident == "crate" => {
+                module.clear();
+                segments.next();
+            }
+            Some(segment) if segment.ident == "self" => {
+                segments.next();
+            }
+            _ => {}
+        }
+
+        while segments
+            .peek()
+            .is_some_and(|segment| segment.ident == "super")
+        {
+            if module.pop().is_none() {
+                return Err(unsupported!("`super` used outside of an inline module"));
+            }
+            segments.next();
+        }
+
+        let rest: Vec<String> = segments.map(|segment| segment.ident.to_string()).collect();
+        let Some(first) = rest.first() else {
+            return Ok(None);
+        };
+
+        // Types keep their file-level local wherever they are declared
+        if module.is_empty() || self.types.is_struct(first) || self.types.is_enum(first) {
+            return Ok(Some(member_path(rest)));
+        }
+
+        Ok(Some(member_path(module.into_iter().chain(rest))))
+    }
+
+    fn transpile_path(&self, path: &syn::Path) -> Lowered<String> {
+        let segments = &path.segments;
+        if segments.len() >= 2 {
+            let enum_name = segments[segments.len() - 2].ident.to_string();
+            let is_api_enum = self.api().is_some_and(|api| api.is_enum(&enum_name));
+
+            if is_api_enum && !self.types.is_enum(&enum_name) {
+                return Ok(format!(
+                    "Enum.{}.{}",
+                    enum_name,
+                    segments[segments.len() - 1].ident
+                ));
+            }
+        }
+
+        if let Some(constant) = float_constant(path) {
+            return Ok(constant.to_string());
+        }
+
+        if self.is_lune() && segments.len() == 2 && segments[0].ident == "task" {
+            self.use_lune_library("task");
+        }
+
+        if segments.len() >= 2 {
+            if let Some(resolved) = self.resolve_module_path(path)? {
+                return Ok(resolved);
+            }
+        }
+
+        if let Some(ident) = path.get_ident() {
+            let name = ident.to_string();
+
+            if self.string_buffers.contains(&name) && self.is_local_variable(&name) {
+                return Ok(format!("table.concat({})", name));
+            }
+
+            let in_module = self
+                .module_fns
+                .last()
+                .is_some_and(|fns| fns.contains(&name));
+            if in_module && !self.is_local_variable(&name) {
+                return Ok(member_access(&self.module_path.join("."), &name));
+            }
+
+            if let Some(extern_fn) = self.types.extern_fns.get(&name) {
+                if !self.is_local_variable(&name) {
+                    return Ok(format!("{}.{}", extern_fn.module, extern_fn.luau_name));
+                }
+            }
+
+            if name == "None" && !self.is_local_variable(&name) {
+                return Ok("nil".to_string());
+            }
+        }
+
+        Ok(member_path(path.segments.iter().map(|segment| {
+            if segment.ident == "Self" {
+                self.specialized_table
+                    .clone()
+                    .or_else(|| self.self_type.clone())
+                    .unwrap_or_else(|| segment.ident.to_string())
+            } else {
+                segment.ident.to_string()
+            }
+        })))
+    }
+
+    // The body is rendered into its own buffer one level deeper, so the closure can sit
+    // inline in whatever expression it is passed to
+    fn lower_closure(&mut self, closure: &syn::ExprClosure) -> Lowered<String> {
+        let mut names = Vec::new();
+        let params = closure
+            .inputs
+            .iter()
+            .map(|input| {
+                let (pat, ty) = match input {
+                    syn::Pat::Type(pat_type) => (&*pat_type.pat, Some(&*pat_type.ty)),
+                    pat => (pat, None),
+                };
+
+                let name = match pat {
+                    syn::Pat::Ident(pat_ident) => {
+                        names.push((
+                            pat_ident.ident.to_string(),
+                            pat_ident.mutability.is_some(),
+                            ty,
+                        ));
+                        pat_ident.ident.to_string()
+                    }
+                    syn::Pat::Wild(_) => "_".to_string(),
+                    _ => return Err(unsupported!("Closure parameters must be identifiers")),
+                };
+
+                match ty {
+                    Some(ty) => Ok(format!("{}: {}", name, self.map_type(ty))),
+                    None => Ok(name),
+                }
+            })
+            .collect::<Lowered<Vec<_>>>()?
+            .join(", ");
+
+        let (output, return_type) = match &closure.output {
+            syn::ReturnType::Type(_, ty) => {
+                (Some((**ty).clone()), format!(": {}", self.map_type(ty)))
+            }
+            syn::ReturnType::Default => (None, String::new()),
+        };
+
+        // A `move` closure owns what it captures, so assigning to a captured local mustn't
+        // change the enclosing one; those locals are passed into a function wrapping it
+        let copies: Vec<String> = match closure.capture {
+            Some(_) => assigned_names(&closure.body)
+                .into_iter()
+                .filter(|name| {
+                    self.is_local_variable(name) && !names.iter().any(|(param, ..)| param == name)
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let enclosing_output = std::mem::replace(&mut self.fn_output, output);
+        let enclosing_lines = std::mem::take(&mut self.output);
+        let in_script_main = std::mem::replace(&mut self.in_script_main, false);
+        let enclosing_profiled = std::mem::replace(&mut self.profiled, false);
+        let enclosing_drops = std::mem::take(&mut self.drop_scopes);
+
+        self.closure_depth += 1;
+        self.scopes.enter();
+        for (name, mutable, ty) in names {
+            self.add_local_variable(&name, mutable, true);
+
+            if let Some(ty) = ty {
+                self.set_local_type(&name, type_registry::type_name(ty));
+                self.set_local_rust_type(&name, ty);
+            }
+        }
+
+        let body = match &*closure.body {
+            syn::Expr::Block(expr_block) => expr_block.block.clone(),
+            expr => syn::parse_quote!({ #expr }),
+        };
+
+        if !copies.is_empty() {
+            self.indent_manager.increase();
+        }
+        self.indent_manager.increase();
+        self.visit_block_stmts(&body, BlockTail::Return)?;
+        self.indent_manager.decrease();
+        self.scopes.exit();
+        self.closure_depth -= 1;
+
+        let body = std::mem::replace(&mut self.output, enclosing_lines);
+        self.fn_output = enclosing_output;
+        self.in_script_main = in_script_main;
+        self.profiled = enclosing_profiled;
+        self.drop_scopes = enclosing_drops;
+
+        if copies.is_empty() {
+            return Ok(format!(
+                "function({}){}\n{}{}end",
+                params,
+                return_type,
+                body,
+                self.indent_manager.get_indent()
+            ));
+        }
+
+        let inner_indent = self.indent_manager.get_indent();
+        self.indent_manager.decrease();
+        Ok(format!(
+            "(function({copies})\n{inner}return function({}){}\n{}{inner}end\n{outer}end)({copies})",
+            params,
+            return_type,
+            body,
+            copies = copies.join(", "),
+            inner = inner_indent,
+            outer = self.indent_manager.get_indent(),
+        ))
+    }
+
+    // Arguments are passed by value, so a `Copy` aggregate gets a copy of its own
+    fn transpile_args(
+        &mut self,
+        args: &syn::punctuated::Punctuated<syn::Expr, syn::Token![,]>,
+    ) -> Lowered<String> {
+        Ok(args
+            .iter()
+            .map(|arg| {
+                let value = self.transpile_expr(arg)?;
+                Ok(self.copied(arg, value))
+            })
+            .collect::<Lowered<Vec<_>>>()?
+            .join(", "))
+    }
+
+    /// A `Copy` struct, array or tuple read from a place is a copy in Rust, but the same
+    /// table in Luau, so it's cloned where one could be changed without the other.
+    fn copied(&self, expr: &syn::Expr, value: String) -> String {
+        if self.is_copy_place(expr) {
+            format!("table.clone({})", value)
+        } else {
+            value
+        }
+    }
+
+    /// Whether a value is a `Vec`, map, set, array or tuple, which are all tables.
+    fn is_vec(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Macro(expr_macro) => expr_macro.mac.path.is_ident("vec"),
+            syn::Expr::Reference(reference) => self.is_vec(&reference.expr),
+            syn::Expr::Paren(paren) => self.is_vec(&paren.expr),
+            _ => self.receiver_type_name(expr).as_deref() == Some("Vec"),
+        }
+    }
+
+    fn is_collection(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Macro(expr_macro) => return expr_macro.mac.path.is_ident("vec"),
+            syn::Expr::Array(_) | syn::Expr::Repeat(_) => return true,
+            syn::Expr::Reference(reference) => return self.is_collection(&reference.expr),
+            syn::Expr::Paren(paren) => return self.is_collection(&paren.expr),
+            _ => {}
+        }
+
+        let rust_type = self.expr_rust_type(expr).map(|mut ty| {
+            while let syn::Type::Reference(reference) = ty {
+                ty = &reference.elem;
+            }
+            ty
+        });
+        match rust_type {
+            Some(syn::Type::Array(_) | syn::Type::Slice(_) | syn::Type::Tuple(_)) => return true,
+            Some(syn::Type::Path(path)) => {
+                let name = path
+                    .path
+                    .segments
+                    .last()
+                    .map(|segment| segment.ident.to_string());
+                if matches!(
+                    name.as_deref(),
+                    Some("Vec" | "VecDeque" | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet")
+                ) {
+                    return true;
+                }
+            }
+            _ => {}
+        }
+
+        self.receiver_type_name(expr).is_some_and(|ty| {
+            matches!(
+                ty.as_str(),
+                "Vec" | "VecDeque" | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet"
+            )
+        })
+    }
+
+    /// A `Copy` aggregate bound to a new local, which only needs a copy if either side can
+    /// be changed afterwards.
+    fn copied_into(&self, expr: &syn::Expr, value: String, mutable: bool) -> String {
+        let immutable_local = match expr {
+            syn::Expr::Path(path) => path
+                .path
+                .get_ident()
+                .and_then(|ident| self.scopes.get(&ident.to_string()))
+                .is_some_and(|binding| !binding.mutable),
+            _ => false,
+        };
+
+        if mutable || !immutable_local {
+            self.copied(expr, value)
+        } else {
+            value
+        }
+    }
+
+    fn is_copy_place(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Paren(paren) => return self.is_copy_place(&paren.expr),
+            syn::Expr::Unary(unary) if matches!(unary.op, syn::UnOp::Deref(_)) => {
+                return self.is_copy_place(&unary.expr)
+            }
+            syn::Expr::Path(_) | syn::Expr::Field(_) | syn::Expr::Index(_) => {}
+            _ => return false,
+        }
+
+        let copy_struct = self.receiver_type_name(expr).is_some_and(|ty| {
+            self.types.is_copy(&ty) && !self.types.is_newtype(&ty) && !self.types.is_readonly(&ty)
+        });
+        let copy_table = self.expr_rust_type(expr).is_some_and(|ty| match ty {
+            syn::Type::Array(_) => true,
+            syn::Type::Tuple(tuple) => !tuple.elems.is_empty(),
+            _ => false,
+        });
+
+        copy_struct || copy_table
+    }
+
+    fn add_local_variable(&mut self, name: &str, mutable: bool, initialized: bool) {
+        self.scopes.declare(
+            name,
+            Binding {
+                mutable,
+                initialized,
+                ty: None,
+                rust_type: None,
+            },
+        );
+    }
+
+    fn is_keyed_container(&self, expr: &syn::Expr) -> bool {
+        self.expr_type_name(expr).is_some_and(|ty| {
+            self.types.implements_index(&ty) || matches!(ty.as_str(), "HashMap" | "BTreeMap")
+        })
+    }
+
+    fn transpile_array_index(&mut self, index: &syn::Expr) -> Lowered<String> {
+        Ok(self
+            .linear_expr(index)?
+            .add(&Linear::constant(1))
+            .render(""))
+    }
+
+    fn set_local_type(&mut self, name: &str, ty: Option<String>) {
+        if let Some(binding) = self.scopes.get_mut(name) {
+            binding.ty = ty;
+        }
+    }
+
+    fn set_local_rust_type(&mut self, name: &str, rust_type: &syn::Type) {
+        if let Some(binding) = self.scopes.get_mut(name) {
+            binding.rust_type = Some(rust_type.clone());
+        }
+    }
+
+    fn expr_rust_type(&self, expr: &syn::Expr) -> Option<&syn::Type> {
+        match expr {
+            syn::Expr::Path(path) => {
+                let ident = path.path.get_ident()?;
+                self.scopes.get(&ident.to_string())?.rust_type.as_ref()
+            }
+
+            syn::Expr::Reference(reference) => self.expr_rust_type(&reference.expr),
+            syn::Expr::Paren(paren) => self.expr_rust_type(&paren.expr),
+
+            _ => None,
+        }
+    }
+
+    /// The type of the items a `for` loop walks, so members on the loop variable resolve.
+    fn loop_item_type(&self, expr: &syn::Expr) -> Option<String> {
+        match expr {
+            syn::Expr::MethodCall(method_call)
+                if method_call.args.is_empty()
+                    && matches!(
+                        method_call.method.to_string().as_str(),
+                        "iter" | "iter_mut" | "into_iter"
+                    ) =>
+            {
+                return self.loop_item_type(&method_call.receiver);
+            }
+
+            syn::Expr::Reference(reference) => return self.loop_item_type(&reference.expr),
+            _ => {}
+        }
+
+        if let Some(mut rust_type) = self.expr_rust_type(expr) {
+            while let syn::Type::Reference(reference) = rust_type {
+                rust_type = &reference.elem;
+            }
+
+            return match rust_type {
+                syn::Type::Slice(slice) => type_registry::type_name(&slice.elem),
+                syn::Type::Array(array) => type_registry::type_name(&array.elem),
+                syn::Type::Path(type_path) => {
+                    let segment = type_path.path.segments.last()?;
+                    match segment.ident.to_string().as_str() {
+                        "Vec" | "VecDeque" => generic_type_args(&segment.arguments)
+                            .first()
+                            .and_then(|elem| type_registry::type_name(elem)),
+                        _ => None,
+                    }
+                }
+                _ => None,
+            };
+        }
+
+        let ty = self
+            .expr_type_name(expr)
+            .or_else(|| self.api_type_of(expr))?;
+        ty.strip_prefix("Vec<")?
+            .strip_suffix('>')
+            .map(str::to_string)
+    }
+
+    fn expr_type_name(&self, expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::Path(path) = expr else {
+            return None;
+        };
+
+        let ident = path.path.get_ident()?;
+        if ident == "self" {
+            return self.self_type.clone();
+        }
+
+        self.scopes.get(&ident.to_string())?.ty.clone()
+    }
+
+    fn is_local_variable(&self, name: &str) -> bool {
+        self.scopes.contains(name)
+    }
+
+    fn check_assignment(&mut self, name: &str, span: proc_macro2::Span) {
+        let Some(binding) = self.scopes.get_mut(name) else {
+            return;
+        };
+
+        if binding.mutable {
+            return;
+        }
+
+        if !binding.initialized {
+            binding.initialized = true;
+            return;
+        }
+
+        self.warn(
+            "immutable-assignment",
+            format!("assignment to `{}`, which was not declared `mut`", name),
+            span,
+        );
+    }
+
+    fn local_suffix(&self, mutable: bool, initialized: bool) -> &'static str {
+        if self.options.annotate_const && !mutable && initialized {
+            " -- const"
+        } else {
+            ""
+        }
+    }
+
+    fn emit_value_into(&mut self, expr: &syn::Expr, target: &str) -> Lowered {
+        match expr {
+            syn::Expr::Match(expr_match) => {
+                self.lower_match(expr_match, BlockTail::Assign(target))?
+            }
+            syn::Expr::Loop(expr_loop) => {
+                self.lower_loop(expr_loop, LoopResult::Assign(target.to_string()))?
+            }
+            syn::Expr::If(expr_if) => self.lower_if_value(expr_if, target)?,
+            syn::Expr::Block(expr_block) => {
+                self.add_line("do");
+                self.indent_manager.increase();
+                self.visit_block_stmts(&expr_block.block, BlockTail::Assign(target))?;
+                self.indent_manager.decrease();
+                self.add_line("end");
+            }
+            syn::Expr::Paren(paren) => self.emit_value_into(&paren.expr, target)?,
+
+            _ if is_value_expr(expr) => {
+                let value = self.transpile_expr(expr)?;
+                self.add_line(&format!("{} = {}", target, value));
+            }
+
+            _ => self.visit_expr(expr)?,
+        }
+
+        Ok(())
+    }
+
+    fn emit_branch_value(&mut self, expr: &syn::Expr, tail: BlockTail) -> Lowered {
+        match (expr, tail) {
+            (syn::Expr::Block(expr_block), _) => self.visit_block_stmts(&expr_block.block, tail)?,
+            (_, BlockTail::Assign(target)) => self.emit_value_into(expr, target)?,
+            (_, BlockTail::Return) => self.emit_return_value(expr)?,
+            (_, BlockTail::Discard) => self.visit_expr(expr)?,
+        }
+
+        Ok(())
+    }
+
+    /// Emits the value a function returns, returning from each branch of an `if` or `match`
+    /// directly instead of through a temporary.
+    fn emit_return_value(&mut self, expr: &syn::Expr) -> Lowered {
+        match expr {
+            syn::Expr::If(expr_if) => self.emit_if_chain(expr_if, BlockTail::Return)?,
+            syn::Expr::Match(expr_match) => self.lower_match(expr_match, BlockTail::Return)?,
+            syn::Expr::Loop(expr_loop) => self.lower_loop(expr_loop, LoopResult::Return)?,
+            syn::Expr::Block(expr_block) => {
+                self.add_line("do");
+                self.indent_manager.increase();
+                self.visit_block_stmts(&expr_block.block, BlockTail::Return)?;
+                self.indent_manager.decrease();
+                self.add_line("end");
+            }
+            syn::Expr::Paren(paren) => self.emit_return_value(&paren.expr)?,
+
+            _ if self.is_tail_call(expr) => self.emit_tail_call(expr)?,
+            _ if is_value_expr(expr) => {
+                let value = self.transpile_expr(expr)?;
+                self.emit_return(&value);
+            }
+
+            _ => self.visit_expr(expr)?,
+        }
+
+        Ok(())
+    }
+
+    /// Lowers `Some(value)` to the value itself, or where that would be `nil` to what stands
+    /// for it: `Some(())` is `true`, and `Some(None)` the `someNone` sentinel.
+    fn lower_some(&mut self, call: &syn::ExprCall, outermost: bool) -> Lowered<String> {
+        let Some(arg) = call.args.first() else {
+            return Ok("nil".to_string());
+        };
+
+        Ok(match arg {
+            syn::Expr::Tuple(unit) if unit.elems.is_empty() => "true".to_string(),
+            _ if lowers_to_nil(arg) => {
+                self.use_helper(Helper::SomeNone);
+                "someNone".to_string()
+            }
+
+            // `Some(Some(x))` is `x`, which can't be `nil` itself
+            syn::Expr::Call(inner) if self.variant_constructor(inner) == Some("Some") => {
+                self.lower_some(inner, false)?
+            }
+
+            _ if self.is_optional(arg) => {
+                self.use_helper(Helper::SomeNone);
+                let value = self.match_operand(arg)?;
+                format!("(if {} == nil then someNone else {})", value, value)
+            }
+
+            _ => {
+                let nested_output = self
+                    .fn_output
+                    .as_ref()
+                    .is_some_and(|output| type_registry::option_depth(output) > 1);
+                if outermost && nested_output {
+                    self.warn(
+                        "option-truthiness",
+                        "this `Some` can wrap a `None`, which is `nil` like the `None` around it; give the value an `Option` type so it's told apart"
+                            .to_string(),
+                        call.span(),
+                    );
+                }
+
+                self.transpile_expr(arg)?
+            }
+        })
+    }
+
+    /// Whether `expr` is known to be an `Option`, from its type or the method making it.
+    fn is_optional(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::MethodCall(method_call) => {
+                OPTION_METHODS.contains(&method_call.method.to_string().as_str())
+            }
+            _ => self.option_depth(expr) > 0,
+        }
+    }
+
+    /// Whether `expr` is known to be an `Option` or a `Result`, from its type or the function
+    /// or method making it.
+    fn unwrap_source(&self, expr: &syn::Expr) -> Option<UnwrapSource> {
+        let lune_result =
+            matches!(expr, syn::Expr::Call(call) if self.lune_result_value(call).is_some());
+        if lune_result || self.try_error_type(expr).is_some() {
+            return Some(UnwrapSource::Result);
+        }
+        if self.is_optional(expr) {
+            return Some(UnwrapSource::Option);
+        }
+
+        if let syn::Expr::MethodCall(method_call) = expr {
+            match self.api_method(method_call) {
+                Some((MemberKind::Pcall, _)) => return Some(UnwrapSource::Result),
+                Some((MemberKind::Attribute, _)) => return Some(UnwrapSource::Option),
+                _ => {}
+            }
+        }
+
+        match self.receiver_type_name(expr)?.as_str() {
+            "Option" => Some(UnwrapSource::Option),
+            "Result" => Some(UnwrapSource::Result),
+            // Members returning a bound class give an `Option` of it
+            ty if self.is_api_class(ty) => Some(UnwrapSource::Option),
+            _ => None,
+        }
+    }
+
+    /// The `T` an `Option<T>` or `Result<T, E>` expression wraps.
+    fn wrapped_type(&self, expr: &syn::Expr) -> Option<syn::Type> {
+        match expr {
+            syn::Expr::Call(call) => {
+                if let Some(value) = self.lune_result_value(call) {
+                    return Some(value);
+                }
+
+                let syn::Expr::Path(path) = &*call.func else {
+                    return None;
+                };
+                let name = path.path.get_ident()?.to_string();
+                self.types.wrapped_outputs.get(&name).cloned()
+            }
+
+            syn::Expr::Field(expr_field) => {
+                let syn::Member::Named(field) = &expr_field.member else {
+                    return None;
+                };
+                let base = self.receiver_type_name(&expr_field.base)?;
+                let field = self
+                    .types
+                    .get(&base)?
+                    .item
+                    .fields
+                    .iter()
+                    .find(|f| f.ident.as_ref() == Some(field))?;
+                type_registry::wrapped_value(&field.ty).cloned()
+            }
+
+            _ => type_registry::wrapped_value(self.expr_rust_type(expr)?).cloned(),
+        }
+    }
+
+    /// Lowers `unwrap`, `expect` and the `unwrap_or*` methods of an `Option`, which is `nil`
+    /// for `None`, or of a `Result` through its `ok` and `value`/`err` fields.
+    fn lower_unwrap(
+        &mut self,
+        method_call: &syn::ExprMethodCall,
+        source: UnwrapSource,
+    ) -> Lowered<String> {
+        let method = method_call.method.to_string();
+        let value = self.match_operand(&method_call.receiver)?;
+        let (is_some, is_none, inner, failure) = match source {
+            UnwrapSource::Option => (
+                format!("{} ~= nil", value),
+                format!("{} == nil", value),
+                value.clone(),
+                "\"called `Option::unwrap()` on a `None` value\"".to_string(),
+            ),
+            UnwrapSource::Result => (
+                format!("{}.ok", value),
+                format!("not {}.ok", value),
+                format!("{}.value", value),
+                format!(
+                    "`called \\`Result::unwrap()\\` on an \\`Err\\` value: {{{}.err}}`",
+                    value
+                ),
+            ),
+        };
+
+        let argument = || {
+            method_call
+                .args
+                .first()
+                .ok_or_else(|| unsupported!("`.{}()` expects an argument", method))
+        };
+
+        Ok(match method.as_str() {
+            "unwrap" | "expect" => {
+                let message = match (method.as_str(), source) {
+                    ("unwrap", _) => failure,
+                    (_, UnwrapSource::Option) => self.transpile_expr(argument()?)?,
+                    (_, UnwrapSource::Result) => {
+                        let message = match argument()? {
+                            syn::Expr::Lit(syn::ExprLit {
+                                lit: syn::Lit::Str(message),
+                                ..
+                            }) => format_string::interpolated(&message.value()),
+                            message => format!("{{{}}}", self.transpile_expr(message)?),
+                        };
+                        format!("`{}: {{{}.err}}`", message, value)
+                    }
+                };
+                self.add_line(&format!("if {} then error({}) end", is_none, message));
+                inner
+            }
+
+            // `value or default` would also pick the default for `Some(false)`
+            "unwrap_or" => {
+                let default = self.transpile_expr(argument()?)?;
+                format!("(if {} then {} else {})", is_some, inner, default)
+            }
+
+            "unwrap_or_else" => {
+                let function = self.transpile_expr(argument()?)?;
+                let function = if is_simple_place(&function) {
+                    function
+                } else {
+                    format!("({})", function)
+                };
+                let error = match source {
+                    UnwrapSource::Option => String::new(),
+                    UnwrapSource::Result => format!("{}.err", value),
+                };
+                format!(
+                    "(if {} then {} else {}({}))",
+                    is_some, inner, function, error
+                )
+            }
+
+            _ => {
+                let default = self
+                    .wrapped_type(&method_call.receiver)
+                    .or_else(|| self.expected_type.clone())
+                    .and_then(|ty| default_value(&ty))
+                    .ok_or_else(|| {
+                        unsupported!("`unwrap_or_default` needs a value type with a known default")
+                    })?;
+                format!("(if {} then {} else {})", is_some, inner, default)
+            }
+        })
+    }
+
+    /// Whether `expr` is an `Option<Option<T>>`, whose `Some(None)` is the `someNone` sentinel.
+    fn is_nested_option(&self, expr: &syn::Expr) -> bool {
+        self.option_depth(expr) > 1
+    }
+
+    fn option_depth(&self, expr: &syn::Expr) -> usize {
+        if let syn::Expr::Call(call) = expr {
+            let syn::Expr::Path(path) = &*call.func else {
+                return 0;
+            };
+            return path
+                .path
+                .get_ident()
+                .and_then(|name| self.types.option_depths.get(&name.to_string()))
+                .copied()
+                .unwrap_or(0);
+        }
+
+        self.expr_rust_type(expr)
+            .map_or(0, type_registry::option_depth)
+    }
+
+    /// Maps the `someNone` that a `Some(inner)` pattern binds out of a nested `Option` back
+    /// to `None`.
+    fn unwrap_nested_binding(
+        &self,
+        pat: &syn::Pat,
+        scrutinee: &str,
+        bindings: &mut [(String, String)],
+    ) {
+        let Some(name) = some_binding(pat) else {
+            return;
+        };
+
+        self.use_helper(Helper::SomeNone);
+        for (bound, value) in bindings.iter_mut() {
+            if *bound == name {
+                *value = format!("(if {} == someNone then nil else {})", scrutinee, scrutinee);
+            }
+        }
+    }
+
+    fn lower_if_value(&mut self, expr_if: &syn::ExprIf, target: &str) -> Lowered {
+        self.emit_if_chain(expr_if, BlockTail::Assign(target))?;
+
+        Ok(())
+    }
+
+    /// Emits an `if`/`elseif` chain, with each branch's value assigned or returned by `tail`.
+    fn emit_if_chain(&mut self, expr_if: &syn::ExprIf, tail: BlockTail) -> Lowered {
+        let mut current = expr_if;
+        let mut keyword = "if";
+        let mut nested = 0;
+
+        loop {
+            let operands = self.condition_operands(&current.cond)?;
+
+            // An `else if let` whose operand needs a temporary can't stay an `elseif`, since
+            // the temporary has to be declared between the branches
+            if keyword == "elseif" && operands.iter().any(|operand| !is_simple_place(operand)) {
+                self.add_line("else");
+                self.indent_manager.increase();
+                keyword = "if";
+                nested += 1;
+            }
+
+            self.scopes.enter();
+            let (condition, bindings) = self.lower_condition(&current.cond, operands)?;
+            self.add_line(&format!("{} {} then", keyword, condition));
+            self.indent_manager.increase();
+
+            for (name, value) in bindings {
+                self.add_local_variable(&name, false, true);
+                self.add_line(&format!("local {} = {}", name, value));
+            }
+            if let syn::Expr::Let(expr_let) = &*current.cond {
+                self.type_api_binding(&expr_let.pat, &expr_let.expr);
+            }
+
+            self.visit_block_stmts(&current.then_branch, tail)?;
+
+            self.indent_manager.decrease();
+            self.scopes.exit();
+
+            match current.else_branch.as_ref().map(|(_, branch)| &**branch) {
+                Some(syn::Expr::If(else_if)) => {
+                    keyword = "elseif";
+                    current = else_if;
+                }
+
+                Some(else_branch) => {
+                    self.add_line("else");
+                    self.indent_manager.increase();
+                    self.emit_branch_value(else_branch, tail)?;
+                    self.indent_manager.decrease();
+                    break;
+                }
+
+                None => break,
+            }
+        }
+
+        self.add_line("end");
+        for _ in 0..nested {
+            self.indent_manager.decrease();
+            self.add_line("end");
+        }
+
+        Ok(())
+    }
+
+    /// The scrutinee values an `if let`/`while let` condition tests, before any are
+    /// stored in temporaries.
+    fn condition_operands(&mut self, cond: &syn::Expr) -> Lowered<Vec<String>> {
+        let syn::Expr::Let(expr_let) = cond else {
+            return Ok(Vec::new());
+        };
+
+        match &*expr_let.expr {
+            syn::Expr::Tuple(tuple) => tuple
+                .elems
+                .iter()
+                .map(|elem| self.transpile_expr(elem))
+                .collect(),
+            expr => Ok(vec![self.transpile_expr(expr)?]),
+        }
+    }
+
+    /// Lowers a condition to Luau, along with the names an `if let` pattern binds. Patterns
+    /// are tested with explicit `~= nil` checks rather than truthiness, since `Some(false)`
+    /// is stored as a plain `false`.
+    fn lower_condition(
+        &mut self,
+        cond: &syn::Expr,
+        operands: Vec<String>,
+    ) -> Lowered<(String, Vec<(String, String)>)> {
+        let syn::Expr::Let(expr_let) = cond else {
+            return Ok((self.transpile_expr(cond)?, Vec::new()));
+        };
+
+        let mut operands: Vec<String> = operands
+            .into_iter()
+            .map(|operand| {
+                if is_simple_place(&operand) {
+                    return operand;
+                }
+
+                let temp = self.names.fresh();
+                self.add_line(&format!("local {} = {}", temp, operand));
+                temp
+            })
+            .collect();
+
+        let (scrutinee, elements) = match &*expr_let.expr {
+            syn::Expr::Tuple(_) => (format!("{{{}}}", operands.join(", ")), Some(operands)),
+            _ => (operands.remove(0), None),
+        };
+
+        let condition = self
+            .pattern_condition(&expr_let.pat, &scrutinee, elements.as_deref())?
+            .unwrap_or_else(|| "true".to_string());
+
+        let mut bindings = Vec::new();
+        collect_pattern_bindings(
+            &expr_let.pat,
+            &scrutinee,
+            elements.as_deref(),
+            &self.types,
+            &mut bindings,
+        )?;
+        if self.is_nested_option(&expr_let.expr) {
+            self.unwrap_nested_binding(&expr_let.pat, &scrutinee, &mut bindings);
+        }
+
+        Ok((condition, bindings))
+    }
+
+    fn loop_iter(&mut self, expr: &syn::Expr) -> Lowered<LoopIter> {
+        Ok(match expr {
+            syn::Expr::Range(range) => {
+                let start = match &range.start {
+                    Some(start) => self.linear_expr(start)?,
+                    None => Linear::constant(0),
+                };
+
+                let is_float = [&range.start, &range.end]
+                    .into_iter()
+                    .flatten()
+                    .any(|bound| self.is_float_expr(bound));
+
+                let bound = match &range.end {
+                    Some(end) => {
+                        let end = self.linear_expr(end)?;
+
+                        match range.limits {
+                            syn::RangeLimits::Closed(_) => end,
+                            syn::RangeLimits::HalfOpen(_) if is_float => {
+                                return Ok(LoopIter::float_range(start, end));
+                            }
+                            syn::RangeLimits::HalfOpen(_) => end.sub(&Linear::constant(1)),
+                        }
+                    }
+                    None => Linear::atom("math.huge"),
+                };
+
+                LoopIter::range(start, bound)
+            }
+
+            syn::Expr::Paren(paren) => self.loop_iter(&paren.expr)?,
+            syn::Expr::Reference(reference) => self.loop_iter(&reference.expr)?,
+
+            syn::Expr::MethodCall(method_call) => {
+                match (
+                    method_call.method.to_string().as_str(),
+                    method_call.args.len(),
+                ) {
+                    ("iter" | "iter_mut" | "into_iter" | "cloned" | "copied", 0) => {
+                        self.loop_iter(&method_call.receiver)?
+                    }
+                    ("rev", 0) => self.indexed_loop_iter(&method_call.receiver)?.rev(),
+                    ("enumerate", 0) => self.indexed_loop_iter(&method_call.receiver)?.enumerate(),
+
+                    ("step_by", 1) => {
+                        let step = self.linear_expr(&method_call.args[0])?;
+                        self.indexed_loop_iter(&method_call.receiver)?
+                            .step_by(step)
+                            .map_err(Unsupported::new)?
+                    }
+
+                    ("zip", 1) => {
+                        let iter = self.indexed_loop_iter(&method_call.receiver)?;
+                        let other = self.indexed_loop_iter(&method_call.args[0])?;
+                        iter.zip(other)
+                    }
+
+                    (method, _) if ITERATOR_ADAPTERS.contains(&method) => {
+                        return Err(unsupported!(
+                            "`.{}()` in a `for` loop is not supported",
+                            method
+                        ))
+                    }
+
+                    _ => LoopIter::sequence(&self.transpile_expr(expr)?),
+                }
+            }
+
+            _ => LoopIter::sequence(&self.transpile_expr(expr)?),
+        })
+    }
+
+    /// The iterator an adapter walks by index, with a table that isn't a plain place hoisted
+    /// into a local, since the loop reads it for both its length and its items.
+    fn indexed_loop_iter(&mut self, expr: &syn::Expr) -> Lowered<LoopIter> {
+        let iter = self.loop_iter(expr)?;
+        match &iter.sequence {
+            Some(base) if !is_simple_place(base) => {
+                let sequence = self.names.fresh();
+                self.add_line(&format!("local {} = {}", sequence, base));
+                Ok(LoopIter::sequence(&sequence))
+            }
+            _ => Ok(iter),
+        }
+    }
+
+    /// Collects a bounded range, optionally `.map`ped, into a table created with its
+    /// final length.
+    fn lower_range_collect(&mut self, receiver: &syn::Expr) -> Lowered<String> {
+        let (range, map) = collected_range(receiver).expect("Expected a collected range");
+        let iter = self.loop_iter(range)?.close();
+        // An empty range can count below zero, which `table.create` rejects
+        let count = match iter.count().as_constant() {
+            Some(count) => count.max(0).to_string(),
+            None => format!("math.max({}, 0)", iter.count().render("")),
+        };
+
+        let table = self.names.fresh();
+        self.add_line(&format!("local {} = table.create({})", table, count));
+
+        self.scopes.enter();
+        let next = format!("{}[#{} + 1]", table, table);
+        match map {
+            Some(closure) => {
+                let pat = closure
+                    .inputs
+                    .first()
+                    .ok_or_else(|| unsupported!("Expected a closure parameter"))?;
+                self.emit_loop_header(pat, iter)?;
+                if let Some(item) = simple_binding(pat) {
+                    self.add_local_variable(&item, false, true);
+                }
+                self.indent_manager.increase();
+                self.emit_branch_value(&closure.body, BlockTail::Assign(&next))?;
+            }
+            None => {
+                let item = self.names.fresh();
+                let ident = syn::Ident::new(&item, proc_macro2::Span::call_site());
+                self.emit_loop_header(&syn::parse_quote!(#ident), iter)?;
+                self.indent_manager.increase();
+                self.add_line(&format!("{} = {}", next, item));
+            }
+        }
+        self.indent_manager.decrease();
+        self.add_line("end");
+        self.scopes.exit();
+
+        Ok(table)
+    }
+
+    fn linear_expr(&mut self, expr: &syn::Expr) -> Lowered<Linear> {
+        Ok(match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => match int.base10_parse() {
+                Ok(value) => Linear::constant(value),
+                Err(_) => Linear::atom(int.base10_digits()),
+            },
+
+            syn::Expr::Binary(binary) => match binary.op {
+                syn::BinOp::Add(_) => self
+                    .linear_expr(&binary.left)?
+                    .add(&self.linear_expr(&binary.right)?),
+                syn::BinOp::Sub(_) => self
+                    .linear_expr(&binary.left)?
+                    .sub(&self.linear_expr(&binary.right)?),
+
+                syn::BinOp::Mul(_) => {
+                    let left = self.linear_expr(&binary.left)?;
+                    let right = self.linear_expr(&binary.right)?;
+
+                    match (left.as_constant(), right.as_constant()) {
+                        (Some(factor), _) => right.scale(factor),
+                        (_, Some(factor)) => left.scale(factor),
+                        _ => Linear::atom(&self.transpile_expr(expr)?),
+                    }
+                }
+
+                _ => Linear::atom(&self.transpile_expr(expr)?),
+            },
+
+            syn::Expr::Unary(syn::ExprUnary {
+                op: syn::UnOp::Neg(_),
+                expr,
+                ..
+            }) => self.linear_expr(expr)?.scale(-1),
+
+            syn::Expr::Paren(paren) => self.linear_expr(&paren.expr)?,
+            syn::Expr::Cast(cast) => self.linear_expr(&cast.expr)?,
+
+            _ => Linear::atom(&self.transpile_expr(expr)?),
+        })
+    }
+
+    /// At `--opt-level 1`, strings a loop only appends to collect their parts in a table
+    /// that's joined once the loop ends, since `..` in a loop copies the string every time.
+    fn begin_concat_parts(
+        &mut self,
+        body: &syn::Block,
+        span: proc_macro2::Span,
+    ) -> Lowered<Vec<(String, String)>> {
+        if self.options.opt_level < 1 {
+            return Ok(Vec::new());
+        }
+
+        let mut parts = Vec::new();
+        for name in loop_appends(body) {
+            let is_string = !self.string_buffers.contains(&name)
+                && !self.concat_parts.contains_key(&name)
+                && self
+                    .scopes
+                    .get(&name)
+                    .and_then(|binding| binding.ty.as_deref())
+                    .is_some_and(|ty| ty == "String");
+            if !is_string {
+                continue;
+            }
+
+            self.note(
+                "concat-in-loop",
+                format!(
+                    "`{}` is appended to in this loop; collecting the parts with `table.concat` instead of `..`",
+                    name
+                ),
+                span,
+            );
+
+            let table = self.names.fresh();
+            self.add_line(&format!("local {} = {{ {} }}", table, name));
+            self.concat_parts.insert(name.clone(), table.clone());
+            parts.push((name, table));
+        }
+
+        Ok(parts)
+    }
+
+    fn end_concat_parts(&mut self, parts: Vec<(String, String)>) {
+        for (name, table) in parts {
+            self.concat_parts.remove(&name);
+            self.add_line(&format!("{} = table.concat({})", name, table));
+        }
+    }
+
+    fn concat_parts_of(&self, expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::Path(path) = expr else {
+            return None;
+        };
+
+        self.concat_parts
+            .get(&path.path.get_ident()?.to_string())
+            .cloned()
+    }
+
+    fn is_string_expr(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(_),
+                ..
+            }) => true,
+
+            syn::Expr::Macro(expr_macro) => expr_macro.mac.path.is_ident("format"),
+            syn::Expr::Reference(reference) => self.is_string_expr(&reference.expr),
+            syn::Expr::Paren(paren) => self.is_string_expr(&paren.expr),
+            syn::Expr::Binary(binary) => {
+                matches!(binary.op, syn::BinOp::Add(_)) && self.is_string_expr(&binary.left)
+            }
+            syn::Expr::Call(call) => {
+                is_new_string(expr) || is_std_path(&call.func, "String", "from")
+            }
+
+            _ => self
+                .receiver_type_name(expr)
+                .is_some_and(|ty| ty == "String" || ty == "str"),
+        }
+    }
+
+    /// The width and signedness of an 8-, 16- or 32-bit integer expression. Wider integers
+    /// can't be emulated on doubles, so `--int-semantics` leaves them as plain numbers.
+    fn int_type_of(&self, expr: &syn::Expr) -> Option<(u32, bool)> {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(int),
+                ..
+            }) => int_width(int.suffix()),
+
+            syn::Expr::Binary(binary) => match binary.op {
+                syn::BinOp::Shl(_) | syn::BinOp::Shr(_) => self.int_type_of(&binary.left),
+                syn::BinOp::Add(_)
+                | syn::BinOp::Sub(_)
+                | syn::BinOp::Mul(_)
+                | syn::BinOp::Div(_)
+                | syn::BinOp::Rem(_)
+                | syn::BinOp::BitAnd(_)
+                | syn::BinOp::BitOr(_)
+                | syn::BinOp::BitXor(_) => self
+                    .int_type_of(&binary.left)
+                    .or_else(|| self.int_type_of(&binary.right)),
+                _ => None,
+            },
+
+            syn::Expr::Unary(unary) => self.int_type_of(&unary.expr),
+            syn::Expr::Paren(paren) => self.int_type_of(&paren.expr),
+            syn::Expr::Reference(reference) => self.int_type_of(&reference.expr),
+            syn::Expr::Cast(cast) => int_width(&type_registry::type_name(&cast.ty)?),
+
+            _ => int_width(&self.receiver_type_name(expr)?),
+        }
+    }
+
+    /// Integer arithmetic under `--int-semantics`: wrapped into the type's range with `%`
+    /// or checked against it, plus bitwise operators through `bit32`.
+    fn lower_int_op(
+        &mut self,
+        op: &syn::BinOp,
+        left: &str,
+        right: &str,
+        int_type: Option<(u32, bool)>,
+        semantics: IntSemantics,
+    ) -> Option<String> {
+        let signed = int_type.is_some_and(|(_, signed)| signed);
+
+        let bit_op = match op {
+            syn::BinOp::BitAnd(_) => Some("band"),
+            syn::BinOp::BitOr(_) => Some("bor"),
+            syn::BinOp::BitXor(_) => Some("bxor"),
+            syn::BinOp::Shl(_) => Some("lshift"),
+            syn::BinOp::Shr(_) if signed => Some("arshift"),
+            syn::BinOp::Shr(_) => Some("rshift"),
+            _ => None,
+        };
+
+        if let Some(bit_op) = bit_op {
+            let value = format!("bit32.{}({}, {})", bit_op, left, right);
+
+            // `bit32` works on unsigned 32-bit values, so signed results are reinterpreted
+            // and shifted-out bits of narrower types dropped
+            return Some(match int_type {
+                Some((bits, signed))
+                    if semantics != IntSemantics::Exact
+                        && (signed || (bits < 32 && matches!(op, syn::BinOp::Shl(_)))) =>
+                {
+                    wrap_int(&value, bits, signed)
+                }
+                _ => value,
+            });
+        }
+
+        let (bits, signed) = int_type?;
+        if semantics == IntSemantics::Exact {
+            return None;
+        }
+
+        let (value, operation) = self.int_arithmetic(op, left, right, signed)?;
+        match semantics {
+            IntSemantics::Exact => None,
+
+            // Only `i32::MIN / -1` overflows a division, and no remainder does
+            _ if !signed && matches!(op, syn::BinOp::Div(_) | syn::BinOp::Rem(_)) => Some(value),
+            _ if matches!(op, syn::BinOp::Rem(_)) => Some(value),
+
+            IntSemantics::Wrapping if bits == 32 && operation == "multiply" => {
+                self.use_helper(Helper::WrappingMul32);
+                let product = format!("wrappingMul32({}, {})", left, right);
+                Some(if signed {
+                    wrap_int(&product, bits, signed)
+                } else {
+                    product
+                })
+            }
+
+            IntSemantics::Wrapping => Some(wrap_int(&value, bits, signed)),
+
+            IntSemantics::Checked => {
+                self.use_helper(Helper::CheckedInt);
+                let (min, max) = int_range(bits, signed);
+                Some(format!(
+                    "checkedInt({}, {}, {}, \"{}\")",
+                    value, min, max, operation
+                ))
+            }
+        }
+    }
+
+    /// `/` and `%` on integers of any width, which truncate toward zero and take the sign of
+    /// the dividend under every `--int-semantics`, left as they are for floats.
+    fn lower_int_division(
+        &self,
+        op: &syn::BinOp,
+        left: &str,
+        right: &str,
+        operands: [&syn::Expr; 2],
+    ) -> Option<String> {
+        if !matches!(op, syn::BinOp::Div(_) | syn::BinOp::Rem(_))
+            || operands.iter().any(|operand| self.is_float_expr(operand))
+            || !operands.iter().any(|operand| self.is_int_expr(operand))
+        {
+            return None;
+        }
+
+        let signed = operands
+            .iter()
+            .find_map(|operand| self.int_type_of(operand))
+            .is_none_or(|(_, signed)| signed);
+        self.int_arithmetic(op, left, right, signed)
+            .map(|(value, _)| value)
+    }
+
+    /// Whether `expr` is an integer of any width. An operation with an integer operand is
+    /// one too, as Rust doesn't mix integers and floats.
+    fn is_int_expr(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(_),
+                ..
+            }) => true,
+
+            syn::Expr::Binary(binary) => {
+                matches!(
+                    binary.op,
+                    syn::BinOp::Add(_)
+                        | syn::BinOp::Sub(_)
+                        | syn::BinOp::Mul(_)
+                        | syn::BinOp::Div(_)
+                        | syn::BinOp::Rem(_)
+                ) && (self.is_int_expr(&binary.left) || self.is_int_expr(&binary.right))
+            }
+            syn::Expr::Unary(unary) => self.is_int_expr(&unary.expr),
+            syn::Expr::Paren(paren) => self.is_int_expr(&paren.expr),
+            syn::Expr::Reference(reference) => self.is_int_expr(&reference.expr),
+            syn::Expr::Cast(cast) => type_registry::type_name(&cast.ty)
+                .is_some_and(|ty| int_width(&ty).is_some() || is_wide_int(&ty)),
+
+            _ => {
+                self.int_type_of(expr).is_some()
+                    || self
+                        .receiver_type_name(expr)
+                        .is_some_and(|ty| is_wide_int(&ty))
+            }
+        }
+    }
+
+    /// An integer operation's exact result, before it's wrapped or checked, with the verb of
+    /// its overflow message: division truncates toward zero, and a remainder takes the sign
+    /// of the dividend.
+    fn int_arithmetic(
+        &self,
+        op: &syn::BinOp,
+        left: &str,
+        right: &str,
+        signed: bool,
+    ) -> Option<(String, &'static str)> {
+        Some(match op {
+            syn::BinOp::Add(_) => (format!("{} + {}", left, right), "add"),
+            syn::BinOp::Sub(_) => (format!("{} - {}", left, right), "subtract"),
+            syn::BinOp::Mul(_) => (format!("{} * {}", left, right), "multiply"),
+            syn::BinOp::Div(_) if signed => {
+                self.use_helper(Helper::TruncDiv);
+                (format!("truncDiv({}, {})", left, right), "divide")
+            }
+            syn::BinOp::Div(_) => (format!("{} // {}", left, right), "divide"),
+            syn::BinOp::Rem(_) if signed => (
+                format!("math.fmod({}, {})", left, right),
+                "calculate the remainder",
+            ),
+            syn::BinOp::Rem(_) => (format!("{} % {}", left, right), "calculate the remainder"),
+            _ => return None,
+        })
+    }
+
+    /// `wrapping_add` and the like, and the `checked_` ones returning `None` instead of
+    /// overflowing, on an 8- to 32-bit integer.
+    fn lower_int_method(
+        &mut self,
+        method_call: &syn::ExprMethodCall,
+        int_type: (u32, bool),
+    ) -> Lowered<String> {
+        let method = method_call.method.to_string();
+        let Some((mode, operation)) = method.split_once('_') else {
+            return Err(unsupported!("Unsupported integer method `{}`", method));
+        };
+        let op: syn::BinOp = match operation {
+            "add" => syn::parse_quote!(+),
+            "sub" => syn::parse_quote!(-),
+            "mul" => syn::parse_quote!(*),
+            "div" => syn::parse_quote!(/),
+            "rem" => syn::parse_quote!(%),
+            _ => return Err(unsupported!("Unsupported integer method `{}`", method)),
+        };
+
+        let operand = |value: String| match is_simple_place(&value) {
+            true => value,
+            false => format!("({})", value),
+        };
+        let left = operand(self.transpile_expr(&method_call.receiver)?);
+        let right = operand(self.transpile_expr(&method_call.args[0])?);
+
+        if mode == "wrapping" {
+            let value =
+                self.lower_int_op(&op, &left, &right, Some(int_type), IntSemantics::Wrapping);
+            return Ok(value.unwrap());
+        }
+
+        let (bits, signed) = int_type;
+        let (value, _) = self.int_arithmetic(&op, &left, &right, signed).unwrap();
+        let result = self.names.fresh();
+        self.add_line(&format!("local {} = {}", result, value));
+
+        // A division by zero is infinite or NaN, neither of which is in range
+        let in_range = match (bits, signed) {
+            (64, false) => format!("{} >= 0 and {} ~= math.huge", result, result),
+            (64, true) => format!(
+                "{} == {} and math.abs({}) ~= math.huge",
+                result, result, result
+            ),
+            _ => {
+                let (min, max) = int_range(bits, signed);
+                format!("{} >= {} and {} <= {}", result, min, result, max)
+            }
+        };
+        Ok(format!("(if {} then {} else nil)", in_range, result))
+    }
+
+    /// What a formatted value is known to be, for the conversion of its format spec.
+    fn value_kind(&self, expr: &syn::Expr) -> ValueKind {
+        let is_int = matches!(
+            expr,
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Int(_),
+                ..
+            })
+        ) || self.int_type_of(expr).is_some()
+            || self.expr_type_name(expr).is_some_and(|ty| {
+                matches!(
+                    ty.as_str(),
+                    "i64" | "u64" | "isize" | "usize" | "i128" | "u128"
+                )
+            });
+
+        if self.is_string_expr(expr) {
+            ValueKind::Str
+        } else if self.is_float_expr(expr) {
+            ValueKind::Float
+        } else if is_int {
+            ValueKind::Int
+        } else {
+            ValueKind::Unknown
+        }
+    }
+
+    fn is_bool_expr(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Bool(_),
+                ..
+            }) => true,
+
+            syn::Expr::Binary(binary) => matches!(
+                binary.op,
+                syn::BinOp::Eq(_)
+                    | syn::BinOp::Ne(_)
+                    | syn::BinOp::Lt(_)
+                    | syn::BinOp::Le(_)
+                    | syn::BinOp::Gt(_)
+                    | syn::BinOp::Ge(_)
+                    | syn::BinOp::And(_)
+                    | syn::BinOp::Or(_)
+            ),
+            syn::Expr::Unary(unary) => {
+                matches!(unary.op, syn::UnOp::Not(_)) && self.is_bool_expr(&unary.expr)
+            }
+            syn::Expr::Paren(paren) => self.is_bool_expr(&paren.expr),
+
+            _ => self.expr_type_name(expr).is_some_and(|ty| ty == "bool"),
+        }
+    }
+
+    fn is_char_expr(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Char(_),
+                ..
+            }) => true,
+
+            syn::Expr::Paren(paren) => self.is_char_expr(&paren.expr),
+
+            _ => self.expr_type_name(expr).is_some_and(|ty| ty == "char"),
+        }
+    }
+
+    fn is_float_expr(&self, expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Float(_),
+                ..
+            }) => true,
+
+            syn::Expr::Binary(binary) => {
+                self.is_float_expr(&binary.left) || self.is_float_expr(&binary.right)
+            }
+            syn::Expr::Unary(unary) => self.is_float_expr(&unary.expr),
+            syn::Expr::Paren(paren) => self.is_float_expr(&paren.expr),
+            syn::Expr::Cast(cast) => matches!(
+                type_registry::type_name(&cast.ty).as_deref(),
+                Some("f32" | "f64")
+            ),
+
+            _ => self
+                .expr_type_name(expr)
+                .is_some_and(|ty| matches!(ty.as_str(), "f32" | "f64")),
+        }
+    }
+
+    fn emit_loop_header(&mut self, pat: &syn::Pat, iter: LoopIter) -> Lowered {
+        let iter = iter.close();
+
+        if let Some(base) = &iter.sequence {
+            let item = simple_binding(pat).unwrap_or_else(|| self.names.fresh());
+            self.add_line(&format!("for _, {} in ipairs({}) do", item, base));
+
+            let value = LoopValue::Number(Linear::atom(&item));
+            self.emit_loop_bindings(pat, &value, &item, Some(&item))?;
+            return Ok(());
+        }
+
+        let mut bindings = Vec::new();
+        bind_loop_pattern(pat, &iter.value, &mut bindings)?;
+
+        let rebased = bindings.iter().find_map(|(name, value)| match value {
+            LoopValue::Number(linear) => iter
+                .rebase_onto(linear)
+                .map(|rebased| (name.clone(), rebased)),
+            _ => None,
+        });
+
+        let (counter, iter) = match rebased {
+            Some((name, rebased)) => (name, rebased),
+            None => (self.names.fresh(), iter),
+        };
+
+        let step = match iter.step.as_constant() {
+            Some(1) => String::new(),
+            _ => format!(", {}", iter.step.render(&counter)),
+        };
+
+        self.add_line(&format!(
+            "for {} = {}, {}{} do",
+            counter,
+            iter.start.render(&counter),
+            iter.bound.render(&counter),
+            step
+        ));
+
+        self.emit_loop_bindings(pat, &iter.value, &counter, None)?;
+
+        Ok(())
+    }
+
+    fn emit_loop_bindings(
+        &mut self,
+        pat: &syn::Pat,
+        value: &LoopValue,
+        counter: &str,
+        emitted: Option<&str>,
+    ) -> Lowered {
+        let mut bindings = Vec::new();
+        bind_loop_pattern(pat, value, &mut bindings)?;
+
+        self.indent_manager.increase();
+
+        for (name, value) in bindings {
+            self.add_local_variable(&name, false, true);
+
+            let rendered = value.render(counter);
+            if name == counter && (rendered == counter || emitted == Some(counter)) {
+                continue;
+            }
+
+            self.add_line(&format!("local {} = {}", name, rendered));
+        }
+
+        self.indent_manager.decrease();
+
+        Ok(())
+    }
+
+    fn keyed_loop_source(&self, expr: &syn::Expr) -> Lowered<Option<(String, KeyedLoop)>> {
+        let (receiver, kind) = match expr {
+            syn::Expr::Reference(reference) => (&*reference.expr, KeyedLoop::Entries),
+            syn::Expr::MethodCall(method_call) if method_call.args.is_empty() => {
+                let kind = match method_call.method.to_string().as_str() {
+                    "iter" | "iter_mut" | "into_iter" => KeyedLoop::Entries,
+                    "keys" => KeyedLoop::Keys,
+                    "values" | "values_mut" => KeyedLoop::Values,
+                    _ => return Ok(None),
+                };
+
+                (&*method_call.receiver, kind)
+            }
+            _ => (expr, KeyedLoop::Entries),
+        };
+
+        let Some(ty) = self.expr_type_name(receiver) else {
+            return Ok(None);
+        };
+        let base = match receiver {
+            syn::Expr::Path(path) => self.transpile_path(&path.path)?,
+            _ => return Ok(None),
+        };
+
+        Ok(match (ty.as_str(), kind) {
+            ("HashMap" | "BTreeMap", kind) => Some((base, kind)),
+            ("HashSet" | "BTreeSet", KeyedLoop::Entries) => Some((base, KeyedLoop::Keys)),
+            _ => None,
+        })
+    }
+
+    fn emit_keyed_loop_header(
+        &mut self,
+        pat: &syn::Pat,
+        (base, kind): (String, KeyedLoop),
+    ) -> Lowered {
+        let binding = |pat: &syn::Pat| simple_binding(pat).unwrap_or_else(|| "_".to_string());
+
+        let names = match (kind, pat) {
+            (KeyedLoop::Entries, syn::Pat::Tuple(tuple)) if tuple.elems.len() == 2 => {
+                vec![binding(&tuple.elems[0]), binding(&tuple.elems[1])]
+            }
+            (KeyedLoop::Entries, _) => {
+                return Err(unsupported!(
+                    "Map iteration requires a `(key, value)` pattern"
+                ))
+            }
+            (KeyedLoop::Keys, pat) => vec![binding(pat)],
+            (KeyedLoop::Values, pat) => vec!["_".to_string(), binding(pat)],
+        };
+
+        for name in names.iter().filter(|name| *name != "_") {
+            self.add_local_variable(name, false, true);
+        }
+
+        self.add_line(&format!("for {} in pairs({}) do", names.join(", "), base));
+
+        Ok(())
+    }
+
+    fn match_operand(&mut self, expr: &syn::Expr) -> Lowered<String> {
+        let value = self.transpile_expr(expr)?;
+
+        if is_simple_place(&value) || matches!(expr, syn::Expr::Lit(_)) {
+            return Ok(value);
+        }
+
+        let temp = self.names.fresh();
+        self.add_line(&format!("local {} = {}", temp, value));
+        Ok(temp)
+    }
+
+    fn lower_match(&mut self, expr_match: &syn::ExprMatch, tail: BlockTail) -> Lowered {
+        // Arms become functions, which a tail call's `continue` can't leave
+        if self.options.opt_level >= 1 && self.tail_call.is_none() {
+            if let Some((cases, default)) = jump_table_cases(expr_match, &self.options.cfg) {
+                if let Some(captures) = self.jump_table_captures(&cases) {
+                    self.lower_jump_table(expr_match, &cases, default, &captures, tail)?;
+                    return Ok(());
+                }
+            }
+        }
+
+        let elements = match &*expr_match.expr {
+            syn::Expr::Tuple(tuple) => Some(
+                tuple
+                    .elems
+                    .iter()
+                    .map(|elem| self.match_operand(elem))
+                    .collect::<Lowered<Vec<_>>>()?,
+            ),
+            _ => None,
+        };
+        let scrutinee = match &elements {
+            Some(elements) => format!("{{{}}}", elements.join(", ")),
+            None => self.match_operand(&expr_match.expr)?,
+        };
+        let arms: Vec<(&syn::Pat, &syn::Arm)> = expr_match
+            .arms
+            .iter()
+            .filter(|arm| self.options.cfg.is_enabled(&arm.attrs))
+            .flat_map(|arm| match &arm.pat {
+                syn::Pat::Or(pat_or) if pat_or.cases.iter().any(binds_names) => {
+                    pat_or.cases.iter().map(|case| (case, arm)).collect()
+                }
+                pat => vec![(pat, arm)],
+            })
+            .collect();
+        // Another arm's `Some(None)` or `Some(Some(..))` shows the `Option` nests too
+        let nested = self.is_nested_option(&expr_match.expr)
+            || arms.iter().any(|(pat, _)| nests_option(pat));
+
+        let mut coverage = MatchCoverage::default();
+        let mut is_first = true;
+        // An arm without a condition matches whatever reaches it, so it ends the chain
+        let mut unconditional = false;
+
+        for (pat, arm) in arms {
+            if coverage.exhaustive || unconditional {
+                self.warn(
+                    "unreachable-pattern",
+                    "unreachable match arm; an earlier arm already matches every value".to_string(),
+                    pat.span(),
+                );
+                continue;
+            }
+
+            if arm.guard.is_some() {
+                return Err(unsupported!("Guard clauses are not yet supported"));
+            }
+
+            if !coverage.add(pat, &self.types) {
+                self.warn(
+                    "unreachable-pattern",
+                    "unreachable match arm; its pattern is already covered by earlier arms"
+                        .to_string(),
+                    pat.span(),
+                );
+                continue;
+            }
+
+            let condition = self.pattern_condition(pat, &scrutinee, elements.as_deref())?;
+
+            match (&condition, is_first) {
+                (None, true) => self.add_line("do"),
+                (None, false) => self.add_line("else"),
+                (Some(condition), true) => self.add_line(&format!("if {} then", condition)),
+                (Some(condition), false) => self.add_line(&format!("elseif {} then", condition)),
+            }
+
+            is_first = false;
+            unconditional = condition.is_none();
+            self.indent_manager.increase();
+            self.scopes.enter();
+
+            let mut bindings = Vec::new();
+            collect_pattern_bindings(
+                pat,
+                &scrutinee,
+                elements.as_deref(),
+                &self.types,
+                &mut bindings,
+            )?;
+            if nested {
+                self.unwrap_nested_binding(pat, &scrutinee, &mut bindings);
+            }
+
+            for (name, value) in bindings {
+                self.add_local_variable(&name, false, true);
+                self.add_line(&format!("local {} = {}", name, value));
+            }
+            self.type_api_binding(pat, &expr_match.expr);
+
+            self.emit_branch_value(&arm.body, tail)?;
+            self.scopes.exit();
+            self.indent_manager.decrease();
+        }
+
+        if is_first {
+            return Ok(());
+        }
+
+        if !coverage.exhaustive && !unconditional {
+            self.add_line("else");
+            self.indent_manager.increase();
+            self.add_line("error(\"unreachable match\")");
+            self.indent_manager.decrease();
+        }
+
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    /// The locals the arms of a jump table read, which its handlers take as arguments, as
+    /// the table is built once ahead of the item. `None` where it can't be: outside an
+    /// item, in a function taking trait dictionaries, or when an arm assigns to a local or
+    /// reads a `mut` one, which it could change through a method.
+    fn jump_table_captures(&self, cases: &[(Vec<&syn::Lit>, &syn::Arm)]) -> Option<Vec<String>> {
+        if self.hoist_level.is_none() || !self.trait_dicts.is_empty() {
+            return None;
+        }
+
+        let locals = self.scopes.locals();
+        let mut captures = Vec::new();
+
+        for (_, arm) in cases {
+            let body = &arm.body;
+            if assigned_names(body)
+                .iter()
+                .any(|name| self.is_local_variable(name))
+            {
+                return None;
+            }
+
+            let block: syn::Block = syn::parse_quote!({ #body });
+            for (name, binding) in &locals {
+                if captures.iter().any(|capture| capture == name) || !mentions_ident(&block, name) {
+                    continue;
+                }
+                if binding.mutable && *name != "self" {
+                    return None;
+                }
+                captures.push(name.to_string());
+            }
+        }
+
+        captures.sort();
+        Some(captures)
+    }
+
+    /// Dispatches a match over many literals through a table of one function per arm,
+    /// indexed by the scrutinee, instead of comparing it against each literal in turn. The
+    /// table goes ahead of the item, and its handlers take the `captures` they read.
+    fn lower_jump_table(
+        &mut self,
+        expr_match: &syn::ExprMatch,
+        cases: &[(Vec<&syn::Lit>, &syn::Arm)],
+        default: Option<&syn::Arm>,
+        captures: &[String],
+        tail: BlockTail,
+    ) -> Lowered {
+        self.note(
+            "jump-table",
+            format!(
+                "match over {} literals dispatched through a lookup table",
+                cases.iter().map(|(lits, _)| lits.len()).sum::<usize>()
+            ),
+            expr_match.match_token.span,
+        );
+
+        let scrutinee = self.match_operand(&expr_match.expr)?;
+        let handlers = self.names.fresh();
+        let params = captures.join(", ");
+
+        // A handler returns the value of its arm, which the call assigns where it goes
+        let handler_tail = match tail {
+            BlockTail::Discard => BlockTail::Discard,
+            _ => BlockTail::Return,
+        };
+
+        let level = self.indent_manager.level();
+        let enclosing_lines = std::mem::take(&mut self.output);
+        let enclosing_drops = std::mem::take(&mut self.drop_scopes);
+        let in_script_main = std::mem::replace(&mut self.in_script_main, false);
+        let profiled = std::mem::replace(&mut self.profiled, false);
+        self.indent_manager.set_level(self.hoist_level.unwrap_or(0));
+        self.closure_depth += 1;
+
+        let lowered = self.emit_jump_table(&handlers, cases, &params, handler_tail);
+
+        self.closure_depth -= 1;
+        self.indent_manager.set_level(level);
+        self.profiled = profiled;
+        self.in_script_main = in_script_main;
+        self.drop_scopes = enclosing_drops;
+        let table = std::mem::replace(&mut self.output, enclosing_lines);
+        lowered?;
+        self.jump_tables.push(table);
+
+        let handler = self.names.fresh();
+        let call = format!("{}({})", handler, params);
+        self.add_line(&format!("local {} = {}[{}]", handler, handlers, scrutinee));
+        self.add_line(&format!("if {} then", handler));
+        self.indent_manager.increase();
+        match tail {
+            BlockTail::Return => self.emit_return(&call),
+            BlockTail::Assign(target) => self.add_line(&format!("{} = {}", target, call)),
+            BlockTail::Discard => self.add_line(&call),
+        }
+        self.indent_manager.decrease();
+        self.add_line("else");
+        self.indent_manager.increase();
+        match default {
+            Some(arm) => {
+                self.scopes.enter();
+                self.emit_branch_value(&arm.body, tail)?;
+                self.scopes.exit();
+            }
+            None => self.add_line("error(\"unreachable match\")"),
+        }
+        self.indent_manager.decrease();
+        self.add_line("end");
+
+        Ok(())
+    }
+
+    fn emit_jump_table(
+        &mut self,
+        handlers: &str,
+        cases: &[(Vec<&syn::Lit>, &syn::Arm)],
+        params: &str,
+        tail: BlockTail,
+    ) -> Lowered {
+        let mut aliases = Vec::new();
+
+        self.add_line(&format!("local {} = {{", handlers));
+        self.indent_manager.increase();
+
+        for (lits, arm) in cases {
+            let keys: Vec<String> = lits
+                .iter()
+                .map(|lit| {
+                    self.transpile_expr(&syn::Expr::Lit(syn::ExprLit {
+                        attrs: Vec::new(),
+                        lit: (*lit).clone(),
+                    }))
+                })
+                .collect::<Lowered<_>>()?;
+
+            self.add_line(&format!("[{}] = function({})", keys[0], params));
+            self.indent_manager.increase();
+            self.scopes.enter();
+            self.emit_branch_value(&arm.body, tail)?;
+            self.scopes.exit();
+            self.indent_manager.decrease();
+            self.add_line("end,");
+
+            // The other values of an `|` pattern share the arm's function
+            for key in &keys[1..] {
+                aliases.push((key.clone(), keys[0].clone()));
+            }
+        }
+
+        self.indent_manager.decrease();
+        self.add_line("}");
+
+        for (key, first) in aliases {
+            self.add_line(&format!("{}[{}] = {}[{}]", handlers, key, handlers, first));
+        }
+
+        Ok(())
+    }
+
+    fn pattern_condition(
+        &mut self,
+        pat: &syn::Pat,
+        scrutinee: &str,
+        elements: Option<&[String]>,
+    ) -> Lowered<Option<String>> {
+        if let syn::Pat::Or(pat_or) = pat {
+            let mut conditions = Vec::new();
+
+            for case in &pat_or.cases {
+                // A case that always matches makes the whole pattern unconditional
+                let Some(condition) = self.pattern_condition(case, scrutinee, elements)? else {
+                    return Ok(None);
+                };
+                conditions.push(condition);
+            }
+
+            return Ok(Some(conditions.join(" or ")));
+        }
+
+        let mut conditions = Vec::new();
+        self.collect_pattern_conditions(pat, scrutinee, elements, &mut conditions)?;
+
+        if conditions.len() > 1 {
+            for condition in &mut conditions {
+                if condition.contains(" or ") {
+                    *condition = format!("({})", condition);
+                }
+            }
+        }
+
+        Ok((!conditions.is_empty()).then(|| conditions.join(" and ")))
+    }
+
+    fn collect_pattern_conditions(
+        &mut self,
+        pat: &syn::Pat,
+        scrutinee: &str,
+        elements: Option<&[String]>,
+        conditions: &mut Vec<String>,
+    ) -> Lowered {
+        match pat {
+            syn::Pat::Lit(pat_lit) => {
+                let value = self.transpile_expr(&syn::Expr::Lit(pat_lit.clone()))?;
+                conditions.push(format!("{} == {}", scrutinee, value));
+            }
+
+            syn::Pat::Range(pat_range) => {
+                if let Some(start) = &pat_range.start {
+                    conditions.push(format!("{} <= {}", self.transpile_expr(start)?, scrutinee));
+                }
+
+                if let Some(end) = &pat_range.end {
+                    let end = self.transpile_expr(end)?;
+                    conditions.push(match &pat_range.limits {
+                        syn::RangeLimits::HalfOpen(_) => format!("{} < {}", scrutinee, end),
+                        syn::RangeLimits::Closed(_) => format!("{} <= {}", scrutinee, end),
+                    });
+                }
+            }
+
+            syn::Pat::Ident(pat_ident) if is_binding_ident(&pat_ident.ident) => {
+                if let Some((_, subpat)) = &pat_ident.subpat {
+                    self.collect_pattern_conditions(subpat, scrutinee, elements, conditions)?;
+                }
+            }
+
+            syn::Pat::Ident(pat_ident) if pat_ident.ident == "None" => {
+                conditions.push(format!("{} == nil", scrutinee));
+            }
+
+            syn::Pat::Ident(pat_ident)
+                if self.types.is_unit_struct(&pat_ident.ident.to_string()) => {}
+
+            syn::Pat::Ident(pat_ident) => {
+                conditions.push(format!("{} == {}", scrutinee, pat_ident.ident));
+            }
+
+            syn::Pat::Path(pat_path) if pat_path.path.is_ident("None") => {
+                conditions.push(format!("{} == nil", scrutinee));
+            }
+
+            syn::Pat::Path(pat_path) if self.data_variant(&pat_path.path).is_some() => {
+                let (_, variant) = self.data_variant(&pat_path.path).unwrap();
+                conditions.push(format!("{}.tag == \"{}\"", scrutinee, variant));
+            }
+
+            syn::Pat::Path(pat_path) => {
+                let value = self.transpile_path(&pat_path.path)?;
+                conditions.push(format!("{} == {}", scrutinee, value));
+            }
+
+            syn::Pat::Wild(_) | syn::Pat::Rest(_) => {}
+
+            syn::Pat::Or(_) => {
+                if let Some(condition) = self.pattern_condition(pat, scrutinee, elements)? {
+                    conditions.push(condition);
+                }
+            }
+
+            syn::Pat::Reference(pat_ref) => {
+                self.collect_pattern_conditions(&pat_ref.pat, scrutinee, elements, conditions)?
+            }
+
+            syn::Pat::Paren(pat_paren) => {
+                self.collect_pattern_conditions(&pat_paren.pat, scrutinee, elements, conditions)?
+            }
+
+            syn::Pat::Type(pat_type) => {
+                self.collect_pattern_conditions(&pat_type.pat, scrutinee, elements, conditions)?
+            }
+
+            syn::Pat::Tuple(pat_tuple) => {
+                for (index, elem) in pat_tuple.elems.iter().enumerate() {
+                    let element = tuple_element(scrutinee, elements, index);
+                    self.collect_pattern_conditions(elem, &element, None, conditions)?;
+                }
+            }
+
+            // `Some(None)` is the `someNone` sentinel, and `Some(Some(x))` just `x`
+            syn::Pat::TupleStruct(pat_tuple_struct) if pat_tuple_struct.path.is_ident("Some") => {
+                let inner = pat_tuple_struct.elems.first();
+                if inner.is_some_and(is_none_pattern) {
+                    self.use_helper(Helper::SomeNone);
+                    conditions.push(format!("{} == someNone", scrutinee));
+                    return Ok(());
+                }
+
+                // The inner `Some` tests for `nil` itself
+                if inner.is_some_and(is_some_pattern) {
+                    self.use_helper(Helper::SomeNone);
+                    conditions.push(format!("{} ~= someNone", scrutinee));
+                } else {
+                    conditions.push(format!("{} ~= nil", scrutinee));
+                }
+
+                if let Some(inner) = inner {
+                    self.collect_pattern_conditions(inner, scrutinee, None, conditions)?;
+                }
+            }
+
+            syn::Pat::TupleStruct(pat_tuple_struct)
+                if result_variant(&pat_tuple_struct.path).is_some() =>
+            {
+                let (ok, field) = result_variant(&pat_tuple_struct.path).unwrap();
+                let tag = field_element(scrutinee, &syn::parse_quote!(ok));
+                conditions.push(if ok { tag } else { format!("not {}", tag) });
+
+                if let Some(inner) = pat_tuple_struct.elems.first() {
+                    let element = field_element(scrutinee, &field);
+                    self.collect_pattern_conditions(inner, &element, None, conditions)?;
+                }
+            }
+
+            syn::Pat::TupleStruct(syn::PatTupleStruct { path, .. })
+            | syn::Pat::Struct(syn::PatStruct { path, .. })
+                if self.error_variant(path).is_some() =>
+            {
+                return Err(unsupported!(
+                    "An error enum's values are its messages, so `{}` can't be matched; bind the error instead",
+                    quote::quote!(#path).to_string().replace(' ', "")
+                ))
+            }
+
+            syn::Pat::TupleStruct(pat_tuple_struct) => {
+                if let Some((_, variant)) = self.data_variant(&pat_tuple_struct.path) {
+                    conditions.push(format!("{}.tag == \"{}\"", scrutinee, variant));
+                }
+
+                for (index, elem) in pat_tuple_struct.elems.iter().enumerate() {
+                    let element =
+                        tuple_struct_element(&self.types, &pat_tuple_struct.path, scrutinee, index);
+                    self.collect_pattern_conditions(elem, &element, None, conditions)?;
+                }
+            }
+
+            syn::Pat::Struct(pat_struct) => {
+                if let Some((_, variant)) = self.data_variant(&pat_struct.path) {
+                    conditions.push(format!("{}.tag == \"{}\"", scrutinee, variant));
+                }
+
+                for field in &pat_struct.fields {
+                    let element = field_element(scrutinee, &field.member);
+                    self.collect_pattern_conditions(&field.pat, &element, None, conditions)?;
+                }
+            }
+
+            syn::Pat::Slice(pat_slice) => {
+                let shape = SliceShape::of(pat_slice)?;
+                let len = slice_len(scrutinee);
+
+                match (shape.rest, shape.fixed_len()) {
+                    (Some(_), 0) => {}
+                    (Some(_), fixed) => conditions.push(format!("{} >= {}", len, fixed)),
+                    (None, fixed) => conditions.push(format!("{} == {}", len, fixed)),
+                }
+
+                for (elem, element) in shape.elements(scrutinee) {
+                    self.collect_pattern_conditions(elem, &element, None, conditions)?;
+                }
+            }
+
+            _ => return Err(unsupported!("Unsupported pattern type in match arm")),
+        }
+
+        Ok(())
+    }
+
+    fn emit_for_loop(&mut self, i: &syn::ExprForLoop) -> Lowered {
+        self.enter_loop(i.label.as_ref(), &i.body, LoopResult::Discard)?;
+        self.scopes.enter();
+
+        match self.keyed_loop_source(&i.expr)? {
+            Some(source) => self.emit_keyed_loop_header(&i.pat, source)?,
+            None => {
+                let iter = self.loop_iter(&i.expr)?;
+                self.emit_loop_header(&i.pat, iter)?;
+
+                if let Some(item) = simple_binding(&i.pat) {
+                    // Looping over a `Vec` of `Drop` values hands each one to the body to destroy
+                    let elements = match &*i.expr {
+                        syn::Expr::Path(path) => path.path.get_ident().and_then(|ident| {
+                            let name = ident.to_string();
+                            self.drop_scopes
+                                .iter()
+                                .rev()
+                                .find_map(|scope| scope.elements.get(&name))
+                                .cloned()
+                        }),
+                        _ => None,
+                    };
+
+                    if elements.is_some() {
+                        self.owned_droppable_params.push(item.clone());
+                    }
+
+                    let item_type = elements.or_else(|| self.loop_item_type(&i.expr));
+                    self.set_local_type(&item, item_type);
+                }
+            }
+        }
+
+        self.indent_manager.increase();
+
+        self.visit_block_body(&i.body)?;
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+        self.scopes.exit();
+        self.exit_loop(&i.body)?;
+
+        Ok(())
+    }
+
+    fn emit_while(&mut self, i: &syn::ExprWhile) -> Lowered {
+        self.enter_loop(i.label.as_ref(), &i.body, LoopResult::Discard)?;
+
+        // The operand of a `while let` is evaluated again on every iteration, so the test
+        // moves inside the loop
+        if let syn::Expr::Let(_) = &*i.cond {
+            self.add_line("while true do");
+            self.indent_manager.increase();
+            self.scopes.enter();
+
+            let operands = self.condition_operands(&i.cond)?;
+            let (condition, bindings) = self.lower_condition(&i.cond, operands)?;
+            if condition != "true" {
+                self.add_line(&format!("if not ({}) then", condition));
+                self.indent_manager.increase();
+                self.add_line("break");
+                self.indent_manager.decrease();
+                self.add_line("end");
+            }
+
+            for (name, value) in bindings {
+                self.add_local_variable(&name, false, true);
+                self.add_line(&format!("local {} = {}", name, value));
+            }
+            if let syn::Expr::Let(expr_let) = &*i.cond {
+                self.type_api_binding(&expr_let.pat, &expr_let.expr);
+            }
+
+            self.visit_block_body(&i.body)?;
+
+            self.scopes.exit();
+            self.indent_manager.decrease();
+            self.add_line("end");
+            self.exit_loop(&i.body)?;
+            return Ok(());
+        }
+
+        let condition = self.transpile_expr(&i.cond)?;
+
+        self.add_line(&format!("while {} do", condition));
+        self.indent_manager.increase();
+
+        self.visit_block_body(&i.body)?;
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+        self.exit_loop(&i.body)?;
+
+        Ok(())
+    }
+
+    /// Emits a `loop`, with the values its `break`s carry put where `result` says.
+    fn lower_loop(&mut self, i: &syn::ExprLoop, result: LoopResult) -> Lowered {
+        let parts = self.begin_concat_parts(&i.body, i.span())?;
+        self.emit_loop(i, result)?;
+        self.end_concat_parts(parts);
+
+        Ok(())
+    }
+
+    fn emit_loop(&mut self, i: &syn::ExprLoop, result: LoopResult) -> Lowered {
+        self.enter_loop(i.label.as_ref(), &i.body, result)?;
+        self.add_line("while true do");
+        self.indent_manager.increase();
+
+        self.visit_block_body(&i.body)?;
+
+        self.indent_manager.decrease();
+        self.add_line("end");
+        self.exit_loop(&i.body)?;
+
+        Ok(())
+    }
+
+    /// Starts a loop. Luau's `break` and `continue` only leave the innermost loop, so a
+    /// labeled loop that a nested loop leaves gets a local the nested loop sets to `"break"`
+    /// or `"continue"` before breaking out, which is checked after each loop in between.
+    fn enter_loop(
+        &mut self,
+        label: Option<&syn::Label>,
+        body: &syn::Block,
+        result: LoopResult,
+    ) -> Lowered {
+        let label = label.map(|label| label.name.ident.to_string());
+        let exit = label
+            .as_ref()
+            .filter(|label| {
+                labeled_exits(body)
+                    .iter()
+                    .any(|exit| exit.nested && exit.label == **label)
+            })
+            .map(|_| {
+                let exit = self.names.fresh();
+                self.add_line(&format!("local {}: string? = nil", exit));
+                exit
+            });
+
+        self.loops.push(LoopFrame {
+            label,
+            exit,
+            result,
+            drop_depth: self.drop_scopes.len(),
+        });
+
+        Ok(())
+    }
+
+    /// Ends the innermost loop, passing on a `break` or `continue` out of it to an outer
+    /// label.
+    fn exit_loop(&mut self, body: &syn::Block) -> Lowered {
+        self.loops.pop();
+
+        let exits = labeled_exits(body);
+        let innermost = self.loops.len().saturating_sub(1);
+        let targets: Vec<(usize, String, Vec<&str>)> = self
+            .loops
+            .iter()
+            .enumerate()
+            .filter_map(|(index, frame)| {
+                let label = frame.label.as_ref()?;
+                let exit = frame.exit.clone()?;
+                let mut keywords: Vec<&str> = exits
+                    .iter()
+                    .filter(|exit| exit.label == *label)
+                    .map(|exit| exit.keyword)
+                    .collect();
+                keywords.sort_unstable();
+                keywords.dedup();
+
+                (!keywords.is_empty()).then_some((index, exit, keywords))
+            })
+            .collect();
+
+        for (index, exit, keywords) in targets {
+            if index < innermost {
+                self.add_line(&format!("if {} ~= nil then break end", exit));
+                continue;
+            }
+
+            for (position, keyword) in keywords.into_iter().enumerate() {
+                let branch = if position == 0 { "if" } else { "elseif" };
+                self.add_line(&format!("{} {} == \"{}\" then", branch, exit, keyword));
+                self.indent_manager.increase();
+                if keyword == "continue" {
+                    self.add_line(&format!("{} = nil", exit));
+                }
+                self.add_line(keyword);
+                self.indent_manager.decrease();
+            }
+            self.add_line("end");
+        }
+
+        Ok(())
+    }
+
+    /// The loop a `break` or `continue` with `label` leaves, or `None` outside any.
+    fn loop_index(&self, label: Option<&syn::Lifetime>, keyword: &str) -> Lowered<Option<usize>> {
+        let Some(label) = label else {
+            return Ok(self.loops.len().checked_sub(1));
+        };
+
+        let index = self
+            .loops
+            .iter()
+            .rposition(|frame| frame.label.as_deref() == Some(&label.ident.to_string()));
+        if index.is_none() {
+            return Err(unsupported!(
+                "`{} '{}` leaves a labeled block, which isn't supported",
+                keyword,
+                label.ident
+            ));
+        }
+        Ok(index)
+    }
+
+    /// Leaves the loop at `index` with `keyword`, through its exit local when it is outside
+    /// the innermost loop, destroying the live `Drop` locals of the scopes it leaves.
+    fn emit_loop_exit(&mut self, index: Option<usize>, keyword: &str) -> Lowered {
+        if let Some(index) = index {
+            for line in self.exit_destroys(self.loops[index].drop_depth) {
+                self.add_line(&line);
+            }
+        }
+
+        match index {
+            Some(index) if index + 1 < self.loops.len() => {
+                let exit = self.loops[index]
+                    .exit
+                    .clone()
+                    .expect("A loop left from a nested loop has an exit local");
+                self.add_line(&format!("{} = \"{}\"", exit, keyword));
+                self.add_line("break");
+            }
+            _ => self.add_line(keyword),
+        }
+
+        Ok(())
+    }
+}
+
+fn collect_pattern_bindings(
+    pat: &syn::Pat,
+    scrutinee: &str,
+    elements: Option<&[String]>,
+    types: &TypeRegistry,
+    bindings: &mut Vec<(String, String)>,
+) -> Lowered {
+    match pat {
+        syn::Pat::Ident(pat_ident) if is_binding_ident(&pat_ident.ident) => {
+            bindings.push((pat_ident.ident.to_string(), scrutinee.to_string()));
+
+            if let Some((_, subpat)) = &pat_ident.subpat {
+                collect_pattern_bindings(subpat, scrutinee, elements, types, bindings)?;
+            }
+        }
+
+        syn::Pat::Or(pat_or) if pat_or.cases.iter().any(binds_names) => {
+            return Err(unsupported!(
+                "Bindings inside nested or-patterns are not yet supported"
+            ));
+        }
+
+        syn::Pat::Reference(pat_ref) => {
+            collect_pattern_bindings(&pat_ref.pat, scrutinee, elements, types, bindings)?
+        }
+        syn::Pat::Paren(pat_paren) => {
+            collect_pattern_bindings(&pat_paren.pat, scrutinee, elements, types, bindings)?
+        }
+        syn::Pat::Type(pat_type) => {
+            collect_pattern_bindings(&pat_type.pat, scrutinee, elements, types, bindings)?
+        }
+
+        syn::Pat::Tuple(pat_tuple) => {
+            for (index, elem) in pat_tuple.elems.iter().enumerate() {
+                let element = tuple_element(scrutinee, elements, index);
+                collect_pattern_bindings(elem, &element, None, types, bindings)?;
+            }
+        }
+
+        syn::Pat::TupleStruct(pat_tuple_struct) if pat_tuple_struct.path.is_ident("Some") => {
+            if let Some(inner) = pat_tuple_struct.elems.first() {
+                collect_pattern_bindings(inner, scrutinee, None, types, bindings)?;
+            }
+        }
+
+        syn::Pat::TupleStruct(pat_tuple_struct)
+            if result_variant(&pat_tuple_struct.path).is_some() =>
+        {
+            let (_, field) = result_variant(&pat_tuple_struct.path).unwrap();
+
+            if let Some(inner) = pat_tuple_struct.elems.first() {
+                let element = field_element(scrutinee, &field);
+                collect_pattern_bindings(inner, &element, None, types, bindings)?;
+            }
+        }
+
+        syn::Pat::TupleStruct(pat_tuple_struct) => {
+            for (index, elem) in pat_tuple_struct.elems.iter().enumerate() {
+                let element = tuple_struct_element(types, &pat_tuple_struct.path, scrutinee, index);
+                collect_pattern_bindings(elem, &element, None, types, bindings)?;
+            }
+        }
+
+        syn::Pat::Struct(pat_struct) => {
+            for field in &pat_struct.fields {
+                let element = field_element(scrutinee, &field.member);
+                collect_pattern_bindings(&field.pat, &element, None, types, bindings)?;
+            }
+        }
+
+        syn::Pat::Slice(pat_slice) => {
+            let shape = SliceShape::of(pat_slice)?;
+
+            for (elem, element) in shape.elements(scrutinee) {
+                collect_pattern_bindings(elem, &element, None, types, bindings)?;
+            }
+
+            if let Some(syn::Pat::Ident(pat_ident)) = shape.rest {
+                let end = match shape.suffix.len() {
+                    0 => slice_len(scrutinee),
+                    suffix => format!("{} - {}", slice_len(scrutinee), suffix),
+                };
+
+                bindings.push((
+                    pat_ident.ident.to_string(),
+                    format!(
+                        "table.move({}, {}, {}, 1, {{}})",
+                        scrutinee,
+                        shape.prefix.len() + 1,
+                        end
+                    ),
+                ));
+            }
+        }
+
+        syn::Pat::Macro(_) | syn::Pat::Verbatim(_) => {
+            return Err(unsupported!("Unsupported pattern"));
+        }
+
+        _ => {}
+    }
+
+    Ok(())
+}
+
+struct SliceShape<'a> {
+    prefix: Vec<&'a syn::Pat>,
+    rest: Option<&'a syn::Pat>,
+    suffix: Vec<&'a syn::Pat>,
+}
+
+impl<'a> SliceShape<'a> {
+    fn of(pat_slice: &'a syn::PatSlice) -> Lowered<Self> {
+        let mut shape = SliceShape {
+            prefix: Vec::new(),
+            rest: None,
+            suffix: Vec::new(),
+        };
+
+        for elem in &pat_slice.elems {
+            if is_rest_pattern(elem) {
+                if shape.rest.is_some() {
+                    return Err(unsupported!("Slice patterns may contain at most one `..`"));
+                }
+
+                shape.rest = Some(elem);
+            } else if shape.rest.is_some() {
+                shape.suffix.push(elem);
+            } else {
+                shape.prefix.push(elem);
+            }
+        }
+
+        Ok(shape)
+    }
+
+    fn fixed_len(&self) -> usize {
+        self.prefix.len() + self.suffix.len()
+    }
+
+    fn elements(&self, scrutinee: &str) -> Vec<(&'a syn::Pat, String)> {
+        let mut elements: Vec<(&'a syn::Pat, String)> = self
+            .prefix
+            .iter()
+            .enumerate()
+            .map(|(index, elem)| (*elem, tuple_element(scrutinee, None, index)))
+            .collect();
+
+        let base = if is_simple_place(scrutinee) {
+            scrutinee.to_string()
+        } else {
+            format!("({})", scrutinee)
+        };
+        let len = slice_len(scrutinee);
+
+        for (index, elem) in self.suffix.iter().enumerate() {
+            let offset = self.suffix.len() - 1 - index;
+            let element = match offset {
+                0 => format!("{}[{}]", base, len),
+                offset => format!("{}[{} - {}]", base, len, offset),
+            };
+
+            elements.push((*elem, element));
+        }
+
+        elements
+    }
+}
+
+fn is_rest_pattern(pat: &syn::Pat) -> bool {
+    match pat {
+        syn::Pat::Rest(_) => true,
+        syn::Pat::Ident(pat_ident) => pat_ident
+            .subpat
+            .as_ref()
+            .is_some_and(|(_, subpat)| matches!(&**subpat, syn::Pat::Rest(_))),
+        _ => false,
+    }
+}
+
+fn slice_len(scrutinee: &str) -> String {
+    if is_simple_place(scrutinee) {
+        format!("#{}", scrutinee)
+    } else {
+        format!("#({})", scrutinee)
+    }
+}
+
+fn binds_names(pat: &syn::Pat) -> bool {
+    // Nested or-patterns are only rejected when they bind names
+    let mut bindings = Vec::new();
+    collect_pattern_bindings(pat, "", None, &TypeRegistry::default(), &mut bindings).is_err()
+        || !bindings.is_empty()
+}
+
+fn result_variant(path: &syn::Path) -> Option<(bool, syn::Member)> {
+    if path.is_ident("Ok") {
+        Some((true, syn::parse_quote!(value)))
+    } else if path.is_ident("Err") {
+        Some((false, syn::parse_quote!(err)))
+    } else {
+        None
+    }
+}
+
+fn tuple_struct_element(
+    types: &TypeRegistry,
+    path: &syn::Path,
+    scrutinee: &str,
+    index: usize,
+) -> String {
+    let is_newtype = path
+        .segments
+        .last()
+        .is_some_and(|segment| types.is_newtype(&segment.ident.to_string()));
+
+    if is_newtype {
+        scrutinee.to_string()
+    } else {
+        tuple_element(scrutinee, None, index)
+    }
+}
+
+fn tuple_element(scrutinee: &str, elements: Option<&[String]>, index: usize) -> String {
+    if let Some(element) = elements.and_then(|elements| elements.get(index)) {
+        return element.clone();
+    }
+
+    if is_simple_place(scrutinee) {
+        format!("{}[{}]", scrutinee, index + 1)
+    } else {
+        format!("({})[{}]", scrutinee, index + 1)
+    }
+}
+
+fn field_element(scrutinee: &str, member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) if is_simple_place(scrutinee) => {
+            member_access(scrutinee, &ident.to_string())
+        }
+        syn::Member::Named(ident) => member_access(&format!("({})", scrutinee), &ident.to_string()),
+        syn::Member::Unnamed(index) => tuple_element(scrutinee, None, index.index as usize),
+    }
+}
+
+fn float_constant(path: &syn::Path) -> Option<&'static str> {
+    let segments: Vec<String> = path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+    let [.., ty, name] = segments.as_slice() else {
+        return None;
+    };
+
+    // `consts::PI` only names the float constants under `f32::consts` or `f64::consts`
+    if ty == "consts"
+        && segments.len() >= 3
+        && !matches!(segments[segments.len() - 3].as_str(), "f32" | "f64")
+    {
+        return None;
+    }
+
+    FLOAT_CONSTANTS
+        .iter()
+        .find(|(constant_ty, constant, _)| constant_ty == ty && constant == name)
+        .map(|(_, _, luau)| *luau)
+}
+
+/// Float literal digits as Luau source, with a trailing `1.` completed to `1.0`.
+fn float_literal(digits: &str) -> String {
+    if digits.ends_with('.') {
+        format!("{}0", digits)
+    } else {
+        digits.to_string()
+    }
+}
+
+fn int_width(name: &str) -> Option<(u32, bool)> {
+    match name {
+        "u8" => Some((8, false)),
+        "u16" => Some((16, false)),
+        "u32" => Some((32, false)),
+        "i8" => Some((8, true)),
+        "i16" => Some((16, true)),
+        "i32" => Some((32, true)),
+        _ => None,
+    }
+}
+
+/// The `=`s of the lowest level long bracket, like `--[==[ ... ]==]`, that `text` doesn't
+/// close early.
+fn long_bracket_level(text: &str) -> String {
+    let mut level = String::new();
+    while text.contains(&format!("]{}]", level)) {
+        level.push('=');
+    }
+    level
+}
+
+/// Whether `name` is an integer type wider than 32 bits, which is left a plain number.
+fn is_wide_int(name: &str) -> bool {
+    matches!(name, "i64" | "u64" | "isize" | "usize" | "i128" | "u128")
+}
+
+/// Whether every value of one integer type is also a value of another.
+fn int_fits((bits, signed): (u32, bool), (target_bits, target_signed): (u32, bool)) -> bool {
+    match (signed, target_signed) {
+        (false, true) => bits < target_bits,
+        (true, false) => false,
+        _ => bits <= target_bits,
+    }
+}
+
+fn int_range(bits: u32, signed: bool) -> (i64, i64) {
+    if signed {
+        (-(1 << (bits - 1)), (1 << (bits - 1)) - 1)
+    } else {
+        (0, (1 << bits) - 1)
+    }
+}
+
+/// The integer methods that wrap or check their arithmetic, whatever `--int-semantics` is.
+const INT_METHODS: &[&str] = &[
+    "wrapping_add",
+    "wrapping_sub",
+    "wrapping_mul",
+    "wrapping_div",
+    "wrapping_rem",
+    "checked_add",
+    "checked_sub",
+    "checked_mul",
+    "checked_div",
+    "checked_rem",
+];
+
+/// Wraps a value into a `bits`-wide integer's range the way two's complement overflow does.
+fn wrap_int(value: &str, bits: u32, signed: bool) -> String {
+    let modulus = 1i64 << bits;
+    if signed {
+        let half = modulus / 2;
+        format!("(({} + {}) % {} - {})", value, half, modulus, half)
+    } else {
+        format!("({}) % {}", value, modulus)
+    }
+}
+
+fn is_write_macro(mac: &syn::Macro) -> bool {
+    mac.path.is_ident("write") || mac.path.is_ident("writeln")
+}
+
+/// The bounded range a `collect()` receiver iterates, through `rev`/`step_by`, and the
+/// closure of a single `.map` applied to it. Only these have a length known up front.
+fn collected_range(expr: &syn::Expr) -> Option<(&syn::Expr, Option<&syn::ExprClosure>)> {
+    fn is_bounded_range(expr: &syn::Expr) -> bool {
+        match expr {
+            syn::Expr::Range(range) => range.end.is_some(),
+            syn::Expr::Paren(paren) => is_bounded_range(&paren.expr),
+            syn::Expr::MethodCall(method_call) => {
+                matches!(
+                    (
+                        method_call.method.to_string().as_str(),
+                        method_call.args.len()
+                    ),
+                    ("rev" | "into_iter", 0) | ("step_by", 1)
+                ) && is_bounded_range(&method_call.receiver)
+            }
+            _ => false,
+        }
+    }
+
+    if let syn::Expr::MethodCall(method_call) = expr {
+        if method_call.method == "map" && method_call.args.len() == 1 {
+            let syn::Expr::Closure(closure) = &method_call.args[0] else {
+                return None;
+            };
+            return (closure.inputs.len() == 1 && is_bounded_range(&method_call.receiver))
+                .then_some((&*method_call.receiver, Some(closure)));
+        }
+    }
+
+    is_bounded_range(expr).then_some((expr, None))
+}
+
+fn is_none_expr(expr: &syn::Expr) -> bool {
+    matches!(expr, syn::Expr::Path(path) if path.path.is_ident("None"))
+}
+
+/// Values a `vec![value; n]` can share between elements, so `table.create` can fill them.
+fn is_immutable_value(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Lit(_) => true,
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => matches!(&**expr, syn::Expr::Lit(_)),
+        syn::Expr::Call(call) => is_new_string(expr) || is_std_path(&call.func, "String", "from"),
+        _ => false,
+    }
+}
+
+fn is_new_string(expr: &syn::Expr) -> bool {
+    let syn::Expr::Call(call) = expr else {
+        return false;
+    };
+    let syn::Expr::Path(path) = &*call.func else {
+        return false;
+    };
+
+    let segments = &path.path.segments;
+    segments.len() == 2
+        && segments[0].ident == "String"
+        && (segments[1].ident == "new" || segments[1].ident == "with_capacity")
+}
+
+/// The `String::new()` locals of a function body that `write!`/`writeln!` append to,
+/// unless they're also reassigned as a whole.
+fn string_buffers(block: &syn::Block) -> HashSet<String> {
+    #[derive(Default)]
+    struct Finder {
+        strings: HashSet<String>,
+        written: HashSet<String>,
+        assigned: HashSet<String>,
+    }
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_local(&mut self, i: &'ast syn::Local) {
+            let pat = match &i.pat {
+                syn::Pat::Type(pat_type) => &*pat_type.pat,
+                pat => pat,
+            };
+
+            if let (syn::Pat::Ident(pat_ident), Some(init)) = (pat, &i.init) {
+                if is_new_string(&init.expr) {
+                    self.strings.insert(pat_ident.ident.to_string());
+                }
+            }
+
+            syn::visit::visit_local(self, i);
+        }
+
+        fn visit_macro(&mut self, i: &'ast syn::Macro) {
+            if !is_write_macro(i) {
+                return;
+            }
+
+            let target = i.parse_body_with(|input: syn::parse::ParseStream| {
+                let target: syn::Expr = input.parse()?;
+                input.parse::<proc_macro2::TokenStream>()?;
+                Ok(target)
+            });
+
+            let mut target = target.ok();
+            while let Some(syn::Expr::Reference(reference)) = target {
+                target = Some(*reference.expr);
+            }
+
+            if let Some(syn::Expr::Path(path)) = target {
+                if let Some(ident) = path.path.get_ident() {
+                    self.written.insert(ident.to_string());
+                }
+            }
+        }
+
+        fn visit_expr_assign(&mut self, i: &'ast syn::ExprAssign) {
+            if let syn::Expr::Path(path) = &*i.left {
+                if let Some(ident) = path.path.get_ident() {
+                    self.assigned.insert(ident.to_string());
+                }
+            }
+
+            syn::visit::visit_expr_assign(self, i);
+        }
+    }
+
+    let mut finder = Finder::default();
+    finder.visit_block(block);
+
+    finder
+        .strings
+        .into_iter()
+        .filter(|name| finder.written.contains(name) && !finder.assigned.contains(name))
+        .collect()
+}
+
+/// The arms of a match worth dispatching through a table: at least `JUMP_TABLE_MIN_CASES`
+/// distinct integer or string literals, dense if integers, in arms without guards whose
+/// bodies can run inside a function, with at most a trailing `_` arm as the default.
+#[allow(clippy::type_complexity)]
+fn jump_table_cases<'a>(
+    expr_match: &'a syn::ExprMatch,
+    cfg: &crate::cfg::CfgSet,
+) -> Option<(Vec<(Vec<&'a syn::Lit>, &'a syn::Arm)>, Option<&'a syn::Arm>)> {
+    let arms: Vec<&syn::Arm> = expr_match
+        .arms
+        .iter()
+        .filter(|arm| cfg.is_enabled(&arm.attrs))
+        .collect();
+
+    let mut cases = Vec::new();
+    let mut default = None;
+
+    for (index, arm) in arms.iter().enumerate() {
+        if arm.guard.is_some() || escapes_function(&arm.body) {
+            return None;
+        }
+
+        let lits = match &arm.pat {
+            syn::Pat::Wild(_) if index == arms.len() - 1 => {
+                default = Some(*arm);
+                continue;
+            }
+            syn::Pat::Lit(pat_lit) => vec![&pat_lit.lit],
+            syn::Pat::Or(pat_or) => pat_or
+                .cases
+                .iter()
+                .map(|case| match case {
+                    syn::Pat::Lit(pat_lit) => Some(&pat_lit.lit),
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>()?,
+            _ => return None,
+        };
+
+        cases.push((lits, *arm));
+    }
+
+    let lits: Vec<&syn::Lit> = cases.iter().flat_map(|(lits, _)| lits.clone()).collect();
+    if lits.len() < JUMP_TABLE_MIN_CASES {
+        return None;
+    }
+
+    // Duplicates keep the `if` chain, which reports them as unreachable
+    if lits.iter().all(|lit| matches!(lit, syn::Lit::Str(_))) {
+        let values: HashSet<String> = lits
+            .iter()
+            .filter_map(|lit| match lit {
+                syn::Lit::Str(lit) => Some(lit.value()),
+                _ => None,
+            })
+            .collect();
+        return (values.len() == lits.len()).then_some((cases, default));
+    }
+
+    let values: Option<BTreeSet<i64>> = lits
+        .iter()
+        .map(|lit| match lit {
+            syn::Lit::Int(lit) => lit.base10_parse().ok(),
+            _ => None,
+        })
+        .collect();
+    let values = values?;
+    let (min, max) = (values.first()?, values.last()?);
+    let dense = max.abs_diff(*min) < values.len() as u64 * 2;
+
+    (values.len() == lits.len() && dense).then_some((cases, default))
+}
+
+/// Whether `return`, `?`, `.await` or a `break` or `continue` out of the expression itself
+/// would change meaning inside a function wrapped around it.
+fn escapes_function(expr: &syn::Expr) -> bool {
+    #[derive(Default)]
+    struct Finder {
+        loops: usize,
+        escapes: bool,
+    }
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_return(&mut self, _: &'ast syn::ExprReturn) {
+            self.escapes = true;
+        }
+
+        fn visit_expr_try(&mut self, _: &'ast syn::ExprTry) {
+            self.escapes = true;
+        }
+
+        fn visit_expr_await(&mut self, _: &'ast syn::ExprAwait) {
+            self.escapes = true;
+        }
+
+        fn visit_expr_break(&mut self, i: &'ast syn::ExprBreak) {
+            self.escapes |= self.loops == 0 || i.label.is_some();
+            syn::visit::visit_expr_break(self, i);
+        }
+
+        fn visit_expr_continue(&mut self, i: &'ast syn::ExprContinue) {
+            self.escapes |= self.loops == 0 || i.label.is_some();
+        }
+
+        fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+            self.visit_expr(&i.expr);
+            self.loops += 1;
+            self.visit_block(&i.body);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+            self.loops += 1;
+            syn::visit::visit_expr_while(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
+            self.loops += 1;
+            syn::visit::visit_expr_loop(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+    }
+
+    let mut finder = Finder::default();
+    finder.visit_expr(expr);
+    finder.escapes
+}
+
+/// The locals a loop body appends to with `+=`, `push_str` or `push` and uses in no other
+/// way, so their value is only needed again after the loop.
+fn loop_appends(body: &syn::Block) -> Vec<String> {
+    #[derive(Default)]
+    struct Finder {
+        appended: Vec<String>,
+        used: HashSet<String>,
+    }
+
+    fn append_target(expr: &syn::Expr) -> Option<String> {
+        let syn::Expr::Path(path) = expr else {
+            return None;
+        };
+        path.path.get_ident().map(|ident| ident.to_string())
+    }
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+            if let (syn::BinOp::AddAssign(_), Some(name)) = (i.op, append_target(&i.left)) {
+                if !self.appended.contains(&name) {
+                    self.appended.push(name);
+                }
+                self.visit_expr(&i.right);
+                return;
+            }
+
+            syn::visit::visit_expr_binary(self, i);
+        }
+
+        fn visit_expr_method_call(&mut self, i: &'ast syn::ExprMethodCall) {
+            let is_append = i.method == "push_str" || i.method == "push";
+            if let (true, Some(name)) = (is_append, append_target(&i.receiver)) {
+                if !self.appended.contains(&name) {
+                    self.appended.push(name);
+                }
+                for arg in &i.args {
+                    self.visit_expr(arg);
+                }
+                return;
+            }
+
+            syn::visit::visit_expr_method_call(self, i);
+        }
+
+        fn visit_expr_path(&mut self, i: &'ast syn::ExprPath) {
+            if let Some(ident) = i.path.get_ident() {
+                self.used.insert(ident.to_string());
+            }
+        }
+
+        // A closure could read the string before the loop ends
+        fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+            let mut inner = Finder::default();
+            inner.visit_expr(&i.body);
+            self.used.extend(inner.appended);
+            self.used.extend(inner.used);
+        }
+
+        // Macro arguments aren't parsed, so any mention of a name counts as a use,
+        // including inline `{name}` captures in format strings
+        fn visit_macro(&mut self, i: &'ast syn::Macro) {
+            let mut tokens: Vec<proc_macro2::TokenTree> = i.tokens.clone().into_iter().collect();
+            while let Some(token) = tokens.pop() {
+                match token {
+                    proc_macro2::TokenTree::Ident(ident) => {
+                        self.used.insert(ident.to_string());
+                    }
+                    proc_macro2::TokenTree::Literal(literal) => {
+                        let text = literal.to_string();
+                        for piece in text.split('{').skip(1) {
+                            let name = piece.split(['}', ':']).next().unwrap_or_default();
+                            self.used.insert(name.trim().to_string());
+                        }
+                    }
+                    proc_macro2::TokenTree::Group(group) => tokens.extend(group.stream()),
+                    proc_macro2::TokenTree::Punct(_) => {}
+                }
+            }
+        }
+    }
+
+    let mut finder = Finder::default();
+    finder.visit_block(body);
+
+    finder
+        .appended
+        .into_iter()
+        .filter(|name| !finder.used.contains(name))
+        .collect()
+}
+
+fn is_parenthesized(expr: &str) -> bool {
+    let mut depth = 0;
+    for (index, c) in expr.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth == 0 => return false,
+            _ => {}
+        }
+
+        if depth == 0 {
+            return index == expr.len() - 1;
+        }
+    }
+
+    false
+}
+
+fn dbg_args(mac: &syn::Macro) -> Lowered<Vec<syn::Expr>> {
+    let args = mac
+        .parse_body_with(syn::punctuated::Punctuated::<syn::Expr, syn::Token![,]>::parse_terminated)
+        .map_err(|_| unsupported!("Failed to parse `dbg!` arguments"))?;
+    Ok(args.into_iter().collect())
+}
+
+/// Whether an expression lowers to a comparison like `x ~= nil`, which `not` has to wrap.
+fn lowers_to_comparison(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::MethodCall(method_call) => {
+            matches!(
+                method_call.method.to_string().as_str(),
+                "is_some" | "is_none" | "is_nan" | "is_infinite" | "is_finite"
+            ) && method_call.args.is_empty()
+        }
+        syn::Expr::Macro(expr_macro) => expr_macro.mac.path.is_ident("matches"),
+        _ => false,
+    }
+}
+
+fn is_none_pattern(pat: &syn::Pat) -> bool {
+    match pat {
+        syn::Pat::Ident(pat_ident) => pat_ident.ident == "None" && pat_ident.subpat.is_none(),
+        syn::Pat::Path(pat_path) => pat_path.path.is_ident("None"),
+        syn::Pat::Paren(pat_paren) => is_none_pattern(&pat_paren.pat),
+        _ => false,
+    }
+}
+
+fn is_some_pattern(pat: &syn::Pat) -> bool {
+    match pat {
+        syn::Pat::TupleStruct(pat_tuple_struct) => pat_tuple_struct.path.is_ident("Some"),
+        syn::Pat::Paren(pat_paren) => is_some_pattern(&pat_paren.pat),
+        _ => false,
+    }
+}
+
+/// Whether `pat` matches inside a nested `Option`, like `Some(None)` or `Some(Some(x))`.
+fn nests_option(pat: &syn::Pat) -> bool {
+    match pat {
+        syn::Pat::TupleStruct(pat_tuple_struct) if pat_tuple_struct.path.is_ident("Some") => {
+            pat_tuple_struct
+                .elems
+                .first()
+                .is_some_and(|inner| is_none_pattern(inner) || is_some_pattern(inner))
+        }
+        syn::Pat::Or(pat_or) => pat_or.cases.iter().any(nests_option),
+        syn::Pat::Paren(pat_paren) => nests_option(&pat_paren.pat),
+        syn::Pat::Reference(pat_ref) => nests_option(&pat_ref.pat),
+        _ => false,
+    }
+}
+
+/// The name a `Some(name)` pattern binds its value to.
+fn some_binding(pat: &syn::Pat) -> Option<String> {
+    match pat {
+        syn::Pat::TupleStruct(pat_tuple_struct) if pat_tuple_struct.path.is_ident("Some") => {
+            match pat_tuple_struct.elems.first()? {
+                syn::Pat::Ident(pat_ident)
+                    if pat_ident.subpat.is_none() && is_binding_ident(&pat_ident.ident) =>
+                {
+                    Some(pat_ident.ident.to_string())
+                }
+                _ => None,
+            }
+        }
+        syn::Pat::Paren(pat_paren) => some_binding(&pat_paren.pat),
+        syn::Pat::Reference(pat_ref) => some_binding(&pat_ref.pat),
+        _ => None,
+    }
+}
+
+fn lowers_to_nil(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Tuple(unit) => unit.elems.is_empty(),
+        syn::Expr::Path(path) => path.path.is_ident("None"),
+        syn::Expr::Paren(paren) => lowers_to_nil(&paren.expr),
+        _ => false,
+    }
+}
+
+fn is_simple_place(expr: &str) -> bool {
+    expr.chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'))
+}
+
+fn is_irrefutable(pat: &syn::Pat) -> bool {
+    match pat {
+        syn::Pat::Wild(_) | syn::Pat::Rest(_) => true,
+        syn::Pat::Ident(pat_ident) => {
+            is_binding_ident(&pat_ident.ident)
+                && pat_ident
+                    .subpat
+                    .as_ref()
+                    .is_none_or(|(_, subpat)| is_irrefutable(subpat))
+        }
+        syn::Pat::Reference(pat_ref) => is_irrefutable(&pat_ref.pat),
+        syn::Pat::Paren(pat_paren) => is_irrefutable(&pat_paren.pat),
+        syn::Pat::Type(pat_type) => is_irrefutable(&pat_type.pat),
+        syn::Pat::Tuple(pat_tuple) => pat_tuple.elems.iter().all(is_irrefutable),
+        syn::Pat::Struct(pat_struct) => {
+            !is_variant_path(&pat_struct.path)
+                && pat_struct
+                    .fields
+                    .iter()
+                    .all(|field| is_irrefutable(&field.pat))
+        }
+        syn::Pat::Or(pat_or) => pat_or.cases.iter().any(is_irrefutable),
+        syn::Pat::Slice(pat_slice) => {
+            pat_slice.elems.len() == 1 && is_rest_pattern(&pat_slice.elems[0])
+        }
+        _ => false,
+    }
+}
+
+/// A construct the transpiler can't lower, with its location.
+pub struct UnsupportedConstruct(pub Diagnostic);
+
+/// Why lowering a construct failed, and where: the innermost expression, statement or item
+/// being lowered that has a location, as the failure itself usually doesn't know its own.
+pub struct Unsupported {
+    message: String,
+    span: Option<proc_macro2::Span>,
+}
+
+impl Unsupported {
+    fn new(message: String) -> Self {
+        Self {
+            message,
+            span: None,
+        }
+    }
+
+    /// Locates the failure at `span` unless something inside it already did. Expressions
+    /// made up by the transpiler have no location and are seen through.
+    fn within(mut self, span: proc_macro2::Span) -> Self {
+        if self.span.is_none() && span.start().line > 0 {
+            self.span = Some(span);
+        }
+
+        self
+    }
+
+    pub(crate) fn into_construct(self, fallback: proc_macro2::Span) -> UnsupportedConstruct {
+        let span = self.span.unwrap_or(fallback);
+        UnsupportedConstruct(Diagnostic::error("unsupported", self.message, span))
+    }
+}
+
+type Lowered<T = ()> = Result<T, Unsupported>;
+
+/// The state a statement or item can leave changed when it fails partway, for `--lenient`.
+struct Checkpoint {
+    output: usize,
+    indent: usize,
+    scopes: usize,
+    drop_scopes: Vec<DropScope>,
+    diagnostics: usize,
+    next_comment: usize,
+    inlining: usize,
+    type_params: usize,
+    trait_dicts: usize,
+    module_path: usize,
+    module_fns: usize,
+    self_type: Option<String>,
+    fn_output: Option<syn::Type>,
+    in_function: bool,
+    in_script_main: bool,
+    profiled: bool,
+    tail_call: Option<TailCall>,
+    loops: usize,
+    jump_tables: usize,
+    hoist_level: Option<usize>,
+}
+
+fn unsupported_stub(message: &str) -> String {
+    format!(
+        "error({})",
+        format_string::quoted(&format!("roblox-rs: unsupported: {}", message))
+    )
+}
+
+/// A function emitted as a loop: its calls to itself at these spans rebind the parameters
+/// and start the loop over.
+#[derive(Clone)]
+struct TailCall {
+    calls: HashSet<SpanKey>,
+    params: Vec<String>,
+    /// The call that ends the loop body, after which the loop starts over without `continue`
+    last: Option<SpanKey>,
+}
+
+type SpanKey = (usize, usize, usize, usize);
+
+fn span_key(span: proc_macro2::Span) -> SpanKey {
+    let (start, end) = (span.start(), span.end());
+    (start.line, start.column, end.line, end.column)
+}
+
+/// The calls a function makes to itself: those in tail position, whose value is the
+/// function's, by span, and the spans of the others.
+struct SelfCalls {
+    tail: HashSet<SpanKey>,
+    others: Vec<proc_macro2::Span>,
+    /// Names the body binds, which would shadow a parameter the loop has to rebind
+    bound: HashSet<String>,
+}
+
+fn self_calls(block: &syn::Block, sig: &syn::Signature, in_impl: bool) -> SelfCalls {
+    struct Finder<'a> {
+        sig: &'a syn::Signature,
+        in_impl: bool,
+        loops: usize,
+        tail: HashSet<SpanKey>,
+        all: Vec<proc_macro2::Span>,
+        bound: HashSet<String>,
+    }
+
+    impl Finder<'_> {
+        fn is_self_call(&self, expr: &syn::Expr) -> bool {
+            let arity = self.sig.inputs.len() - usize::from(self.sig.receiver().is_some());
+            match expr {
+                syn::Expr::Call(call) if call.args.len() == arity => {
+                    let syn::Expr::Path(path) = &*call.func else {
+                        return false;
+                    };
+                    let segments: Vec<&syn::Ident> = path
+                        .path
+                        .segments
+                        .iter()
+                        .map(|segment| &segment.ident)
+                        .collect();
+                    match segments[..] {
+                        [name] => !self.in_impl && *name == self.sig.ident,
+                        [owner, name] => {
+                            self.in_impl
+                                && self.sig.receiver().is_none()
+                                && owner == "Self"
+                                && *name == self.sig.ident
+                        }
+                        _ => false,
+                    }
+                }
+                syn::Expr::MethodCall(method_call) => {
+                    self.sig.receiver().is_some()
+                        && method_call.method == self.sig.ident
+                        && method_call.args.len() == arity
+                        && matches!(&*method_call.receiver, syn::Expr::Path(path) if path.path.is_ident("self"))
+                }
+                _ => false,
+            }
+        }
+
+        fn tail_position(&mut self, expr: &syn::Expr) {
+            match expr {
+                _ if self.is_self_call(expr) => {
+                    self.tail.insert(span_key(expr.span()));
+                }
+                syn::Expr::If(expr_if) => {
+                    self.tail_block(&expr_if.then_branch);
+                    if let Some((_, else_branch)) = &expr_if.else_branch {
+                        self.tail_position(else_branch);
+                    }
+                }
+                syn::Expr::Match(expr_match) => {
+                    for arm in &expr_match.arms {
+                        self.tail_position(&arm.body);
+                    }
+                }
+                syn::Expr::Block(expr_block) => self.tail_block(&expr_block.block),
+                syn::Expr::Paren(paren) => self.tail_position(&paren.expr),
+                syn::Expr::Return(expr_return) => {
+                    if let Some(expr) = &expr_return.expr {
+                        self.tail_position(expr);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        fn tail_block(&mut self, block: &syn::Block) {
+            if let Some(syn::Stmt::Expr(expr, _)) = block.stmts.last() {
+                self.tail_position(expr);
+            }
+        }
+    }
+
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_expr(&mut self, i: &'ast syn::Expr) {
+            if self.is_self_call(i) {
+                self.all.push(i.span());
+            }
+            syn::visit::visit_expr(self, i);
+        }
+
+        // A `return` in a loop leaves the loop too, unlike the `continue` that replaces it
+        fn visit_expr_return(&mut self, i: &'ast syn::ExprReturn) {
+            if let (0, Some(expr)) = (self.loops, &i.expr) {
+                self.tail_position(expr);
+            }
+            syn::visit::visit_expr_return(self, i);
+        }
+
+        fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+            self.visit_expr(&i.expr);
+            self.loops += 1;
+            self.visit_block(&i.body);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+            self.loops += 1;
+            syn::visit::visit_expr_while(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
+            self.loops += 1;
+            syn::visit::visit_expr_loop(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_pat_ident(&mut self, i: &'ast syn::PatIdent) {
+            self.bound.insert(i.ident.to_string());
+            syn::visit::visit_pat_ident(self, i);
+        }
+
+        fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+    }
+
+    let mut finder = Finder {
+        sig,
+        in_impl,
+        loops: 0,
+        tail: HashSet::new(),
+        all: Vec::new(),
+        bound: HashSet::new(),
+    };
+    finder.tail_block(block);
+    finder.visit_block(block);
+
+    let others = finder
+        .all
+        .into_iter()
+        .filter(|span| !finder.tail.contains(&span_key(*span)))
+        .collect();
+    SelfCalls {
+        tail: finder.tail,
+        others,
+        bound: finder.bound,
+    }
+}
+
+#[derive(Default)]
+struct MatchCoverage {
+    exhaustive: bool,
+    literals: HashSet<String>,
+    variants: HashMap<String, HashSet<String>>,
+    slice_lengths: HashSet<usize>,
+}
+
+impl MatchCoverage {
+    fn add(&mut self, pat: &syn::Pat, types: &TypeRegistry) -> bool {
+        if is_irrefutable(pat) {
+            self.exhaustive = true;
+            return true;
+        }
+
+        match pat {
+            syn::Pat::Lit(syn::ExprLit {
+                lit: syn::Lit::Bool(value),
+                ..
+            }) => {
+                let is_new = self.literals.insert(value.value.to_string());
+                if self.literals.contains("true") && self.literals.contains("false") {
+                    self.exhaustive = true;
+                }
+
+                is_new
+            }
+
+            syn::Pat::Lit(lit) => self.literals.insert(quote::quote!(#lit).to_string()),
+
+            syn::Pat::Ident(pat_ident) if pat_ident.ident == "None" => {
+                self.add_variant("Option", "None", &["Some", "None"])
+            }
+
+            syn::Pat::Path(pat_path) if pat_path.path.is_ident("None") => {
+                self.add_variant("Option", "None", &["Some", "None"])
+            }
+
+            syn::Pat::TupleStruct(pat_tuple_struct)
+                if pat_tuple_struct.path.is_ident("Some")
+                    && pat_tuple_struct.elems.iter().all(is_irrefutable) =>
+            {
+                self.add_variant("Option", "Some", &["Some", "None"])
+            }
+
+            syn::Pat::TupleStruct(pat_tuple_struct)
+                if result_variant(&pat_tuple_struct.path).is_some()
+                    && pat_tuple_struct.elems.iter().all(is_irrefutable) =>
+            {
+                let variant = pat_tuple_struct.path.get_ident().unwrap().to_string();
+                self.add_variant("Result", &variant, &["Ok", "Err"])
+            }
+
+            syn::Pat::Ident(pat_ident) if types.is_unit_struct(&pat_ident.ident.to_string()) => {
+                self.exhaustive = true;
+                true
+            }
+
+            syn::Pat::TupleStruct(pat_tuple_struct)
+                if pat_tuple_struct.elems.iter().all(is_irrefutable)
+                    && pat_tuple_struct
+                        .path
+                        .get_ident()
+                        .is_some_and(|ident| types.is_newtype(&ident.to_string())) =>
+            {
+                self.exhaustive = true;
+                true
+            }
+
+            syn::Pat::Path(pat_path) if pat_path.path.segments.len() >= 2 => {
+                self.add_enum_variant(&pat_path.path, types)
+            }
+
+            syn::Pat::TupleStruct(syn::PatTupleStruct { path, elems, .. })
+                if is_variant_path(path) && elems.iter().all(is_irrefutable) =>
+            {
+                self.add_enum_variant(path, types)
+            }
+
+            syn::Pat::Struct(syn::PatStruct { path, fields, .. })
+                if is_variant_path(path)
+                    && fields.iter().all(|field| is_irrefutable(&field.pat)) =>
+            {
+                self.add_enum_variant(path, types)
+            }
+
+            syn::Pat::Or(pat_or) => {
+                let mut any_new = false;
+                for case in &pat_or.cases {
+                    any_new |= self.add(case, types);
+                }
+
+                any_new
+            }
+
+            syn::Pat::Ident(pat_ident) => match &pat_ident.subpat {
+                Some((_, subpat)) => self.add(subpat, types),
+                None => true,
+            },
+
+            syn::Pat::Reference(pat_ref) => self.add(&pat_ref.pat, types),
+            syn::Pat::Paren(pat_paren) => self.add(&pat_paren.pat, types),
+
+            syn::Pat::Slice(pat_slice) if pat_slice.elems.iter().all(is_irrefutable) => {
+                // Lowering the arm reports a malformed pattern
+                let Ok(shape) = SliceShape::of(pat_slice) else {
+                    return true;
+                };
+                let fixed = shape.fixed_len();
+
+                if shape.rest.is_some() {
+                    if (0..fixed).all(|len| self.slice_lengths.contains(&len)) {
+                        self.exhaustive = true;
+                    }
+
+                    true
+                } else {
+                    self.slice_lengths.insert(fixed)
+                }
+            }
+
+            _ => true,
+        }
+    }
+
+    fn add_enum_variant(&mut self, path: &syn::Path, types: &TypeRegistry) -> bool {
+        let segments = &path.segments;
+        let enum_name = segments[segments.len() - 2].ident.to_string();
+        let variant = segments[segments.len() - 1].ident.to_string();
+        let all_variants = types.enum_variants(&enum_name).unwrap_or_default().to_vec();
+        let all_variants: Vec<&str> = all_variants.iter().map(String::as_str).collect();
+
+        self.add_variant(&enum_name, &variant, &all_variants)
+    }
+
+    fn add_variant(&mut self, enum_name: &str, variant: &str, all_variants: &[&str]) -> bool {
+        let covered = self.variants.entry(enum_name.to_string()).or_default();
+        let is_new = covered.insert(variant.to_string());
+
+        if !all_variants.is_empty()
+            && all_variants
+                .iter()
+                .all(|variant| covered.contains(*variant))
+        {
+            self.exhaustive = true;
+        }
+
+        is_new
+    }
+}
+
+/// Whether a struct pattern's path names an enum variant, like `Op::Move` or `Self::Move`,
+/// rather than a struct, going by the enum's name being capitalized.
+fn is_variant_path(path: &syn::Path) -> bool {
+    let segments = &path.segments;
+    segments.len() >= 2
+        && segments[segments.len() - 2]
+            .ident
+            .to_string()
+            .starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+/// What the message of a panicking macro starts with, empty for `panic!` itself.
+fn panic_prefix(mac: &syn::Macro) -> Option<&'static str> {
+    match mac.path.get_ident()?.to_string().as_str() {
+        "panic" => Some(""),
+        "unreachable" => Some("internal error: entered unreachable code"),
+        "todo" => Some("not yet implemented"),
+        "unimplemented" => Some("not implemented"),
+        _ => None,
+    }
+}
+
+fn macro_name(mac: &syn::Macro) -> String {
+    mac.path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+fn is_binding_ident(ident: &syn::Ident) -> bool {
+    !ident
+        .to_string()
+        .starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+fn generic_type_args(arguments: &syn::PathArguments) -> Vec<&syn::Type> {
+    match arguments {
+        syn::PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            })
+            .collect(),
+
+        _ => Vec::new(),
+    }
+}
+
+// Only names, calls, indexing and parenthesized expressions can be called with `:` in Luau
+fn method_receiver(expr: &syn::Expr, receiver: String) -> String {
+    match expr {
+        syn::Expr::Path(_)
+        | syn::Expr::Call(_)
+        | syn::Expr::MethodCall(_)
+        | syn::Expr::Field(_)
+        | syn::Expr::Index(_)
+        | syn::Expr::Paren(_) => receiver,
+
+        syn::Expr::Struct(_) if !receiver.starts_with('{') => receiver,
+        syn::Expr::Reference(reference) => method_receiver(&reference.expr, receiver),
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) => method_receiver(expr, receiver),
+
+        _ => format!("({})", receiver),
+    }
+}
+
+fn extension_table(type_name: &str) -> String {
+    format!("{}Extensions", type_name)
+}
+
+fn generic_alias(name: &str, generics: &[String]) -> String {
+    if generics.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}<{}>", name, generics.join(", "))
+    }
+}
+
+/// The parameter names of an error variant's fields: `_0`, `_1` for a tuple variant.
+fn error_params(variant: &syn::Variant) -> Vec<String> {
+    variant
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => ident.to_string(),
+            None => format!("_{}", index),
+        })
+        .collect()
+}
+
+/// Methods returning an `Option`, so anyhow's `.context` on them turns `None` into an error.
+/// Iterator adapters a `for` loop can't lower to a numeric loop over a table.
+const ITERATOR_ADAPTERS: &[&str] = &[
+    "map",
+    "filter",
+    "filter_map",
+    "flat_map",
+    "skip",
+    "take",
+    "skip_while",
+    "take_while",
+    "chain",
+    "cycle",
+    "peekable",
+    "inspect",
+    "scan",
+    "flatten",
+    "chars",
+    "windows",
+    "chunks",
+    "map_while",
+    "fuse",
+];
+
+const UNWRAP_METHODS: &[&str] = &[
+    "unwrap",
+    "expect",
+    "unwrap_or",
+    "unwrap_or_else",
+    "unwrap_or_default",
+];
+
+#[derive(Clone, Copy)]
+enum UnwrapSource {
+    Option,
+    Result,
+}
+
+/// The Luau value of a type's `Default`, for the types whose default is a literal.
+fn default_value(ty: &syn::Type) -> Option<&'static str> {
+    match type_registry::type_name(ty)?.as_str() {
+        "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64" | "u128"
+        | "usize" | "f32" | "f64" => Some("0"),
+        "bool" => Some("false"),
+        "String" | "str" | "char" => Some("\"\""),
+        "Vec" | "VecDeque" | "HashMap" | "HashSet" | "BTreeMap" | "BTreeSet" => Some("{}"),
+        "Option" => Some("nil"),
+        _ => None,
+    }
+}
+
+const OPTION_METHODS: &[&str] = &[
+    "get", "get_mut", "first", "last", "pop", "find", "position", "next", "max", "min",
+];
+
+fn unit_variants(item: &syn::ItemEnum) -> Option<Vec<String>> {
+    item.variants
+        .iter()
+        .map(|variant| {
+            matches!(variant.fields, syn::Fields::Unit).then(|| variant.ident.to_string())
+        })
+        .collect()
+}
+
+// Luau has no tuple types, so tuples are array tables of the union of their element types
+/// A type as written in Rust, without the spaces between its tokens.
+fn rust_type_name(ty: &syn::Type) -> String {
+    quote::quote!(#ty)
+        .to_string()
+        .replace(" :: ", "::")
+        .replace(" <", "<")
+        .replace("< ", "<")
+        .replace(" >", ">")
+        .replace(" ,", ",")
+        .replace("& ", "&")
+        .replace("* ", "*")
+}
+
+fn tuple_type(elems: impl Iterator<Item = String>) -> String {
+    let mut types: Vec<String> = Vec::new();
+    for ty in elems {
+        if !types.contains(&ty) {
+            types.push(ty);
+        }
+    }
+
+    format!("{{{}}}", types.join(" | "))
+}
+
+fn optional_type(ty: String) -> String {
+    if ty == "any" || ty.ends_with('?') {
+        ty
+    } else {
+        format!("{}?", ty)
+    }
+}
+
+fn is_type_param(ty: &syn::Type, param: &str) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path.qself.is_none() && type_path.path.is_ident(param),
+        syn::Type::Reference(reference) => is_type_param(&reference.elem, param),
+        _ => false,
+    }
+}
+
+/// Whether a type is or has a parameter named `param` anywhere, like `Vec<T>`.
+fn mentions_type_param(ty: &syn::Type, param: &str) -> bool {
+    quote::ToTokens::to_token_stream(ty)
+        .to_string()
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .any(|part| part == param)
+}
+
+fn unify_type_param<'t>(
+    pattern: &syn::Type,
+    concrete: &'t syn::Type,
+    param: &str,
+) -> Option<&'t syn::Type> {
+    if is_type_param(pattern, param) {
+        return Some(concrete);
+    }
+
+    match (pattern, concrete) {
+        (syn::Type::Reference(pattern), syn::Type::Reference(concrete)) => {
+            unify_type_param(&pattern.elem, &concrete.elem, param)
+        }
+
+        (syn::Type::Reference(pattern), _) => unify_type_param(&pattern.elem, concrete, param),
+        (_, syn::Type::Reference(concrete)) => unify_type_param(pattern, &concrete.elem, param),
+
+        (syn::Type::Path(pattern), syn::Type::Path(concrete)) => {
+            let pattern = pattern.path.segments.last()?;
+            let concrete = concrete.path.segments.last()?;
+
+            if pattern.ident != concrete.ident {
+                return None;
+            }
+
+            generic_type_args(&pattern.arguments)
+                .into_iter()
+                .zip(generic_type_args(&concrete.arguments))
+                .find_map(|(pattern, concrete)| unify_type_param(pattern, concrete, param))
+        }
+
+        (syn::Type::Slice(pattern), syn::Type::Slice(concrete)) => {
+            unify_type_param(&pattern.elem, &concrete.elem, param)
+        }
+
+        _ => None,
+    }
+}
+
+/// The value `concrete` gives the const generic parameter `param` that `pattern` is written
+/// with, like `4` for `N` in `Grid<N>` against `Grid<4>`.
+fn unify_const_param(pattern: &syn::Type, concrete: &syn::Type, param: &str) -> Option<syn::Expr> {
+    match (pattern, concrete) {
+        (syn::Type::Array(pattern), syn::Type::Array(concrete)) => match &pattern.len {
+            syn::Expr::Path(path) if path.path.is_ident(param) => Some(concrete.len.clone()),
+            _ => None,
+        }
+        .or_else(|| unify_const_param(&pattern.elem, &concrete.elem, param)),
+
+        (syn::Type::Reference(pattern), _) => unify_const_param(&pattern.elem, concrete, param),
+        (_, syn::Type::Reference(concrete)) => unify_const_param(pattern, &concrete.elem, param),
+
+        (syn::Type::Path(pattern), syn::Type::Path(concrete)) => {
+            let pattern = pattern.path.segments.last()?;
+            let concrete = concrete.path.segments.last()?;
+            if pattern.ident != concrete.ident {
+                return None;
+            }
+
+            let (
+                syn::PathArguments::AngleBracketed(pattern),
+                syn::PathArguments::AngleBracketed(concrete),
+            ) = (&pattern.arguments, &concrete.arguments)
+            else {
+                return None;
+            };
+            pattern
+                .args
+                .iter()
+                .zip(&concrete.args)
+                .find_map(|(pattern, concrete)| match (pattern, concrete) {
+                    (syn::GenericArgument::Type(syn::Type::Path(path)), concrete)
+                        if path.path.is_ident(param) =>
+                    {
+                        match concrete {
+                            syn::GenericArgument::Const(expr) => Some(expr.clone()),
+                            syn::GenericArgument::Type(ty) => Some(syn::parse_quote!(#ty)),
+                            _ => None,
+                        }
+                    }
+                    (syn::GenericArgument::Type(pattern), syn::GenericArgument::Type(concrete)) => {
+                        unify_const_param(pattern, concrete, param)
+                    }
+                    _ => None,
+                })
+        }
+
+        _ => None,
+    }
+}
+
+/// Whether `block` names `ident` anywhere, outside nested items.
+fn mentions_ident(block: &syn::Block, ident: &str) -> bool {
+    struct Finder<'a> {
+        ident: &'a str,
+        found: bool,
+    }
+
+    impl<'ast> Visit<'ast> for Finder<'_> {
+        fn visit_ident(&mut self, i: &'ast proc_macro2::Ident) {
+            self.found |= i == self.ident;
+        }
+
+        // Including the inline arguments of a format string, like `{N}`
+        fn visit_macro(&mut self, i: &'ast syn::Macro) {
+            self.found |= i
+                .tokens
+                .to_string()
+                .split(|c: char| !c.is_alphanumeric() && c != '_')
+                .any(|word| word == self.ident);
+        }
+
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+    }
+
+    let mut finder = Finder {
+        ident,
+        found: false,
+    };
+    finder.visit_block(block);
+    finder.found
+}
+
+fn mangle_instantiation(name: &str, args: &[String]) -> String {
+    let mut mangled = name.to_string();
+
+    for arg in args {
+        let arg = arg
+            .replace('{', " Array ")
+            .replace('?', " Optional ")
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("_");
+
+        mangled.push('_');
+        mangled.push_str(&arg);
+    }
+
+    mangled
+}
+
+/// The `Drop` locals a block owns, destroyed when it ends or an exit leaves it.
+#[derive(Clone, Default)]
+struct DropScope {
+    locals: Vec<String>,
+    /// The locals moved out so far, whose new owner destroys them instead
+    moved: HashSet<String>,
+    /// The `Vec` locals that destroy each of their elements, by the elements' type
+    elements: HashMap<String, String>,
+}
+
+impl DropScope {
+    fn new(params: Vec<String>) -> Self {
+        Self {
+            locals: params,
+            ..Self::default()
+        }
+    }
+
+    fn live(&self) -> impl Iterator<Item = &String> {
+        self.locals
+            .iter()
+            .filter(|name| !self.moved.contains(*name))
+    }
+}
+
+/// Whether `stmt` moves the local `name` out. With `branches` unset, only the moves it makes
+/// whenever it runs count, not those in its branches and loop bodies.
+fn moves_local(stmt: &syn::Stmt, name: &str, branches: bool) -> bool {
+    let mut finder = MoveFinder {
+        name,
+        branches,
+        moved: false,
+    };
+    finder.visit_stmt(stmt);
+    finder.moved
+}
+
+/// Whether a block always leaves its function, so the locals its statements move out are
+/// still owned after it on every path that falls through.
+fn block_returns(block: &syn::Block) -> bool {
+    match block.stmts.last() {
+        Some(syn::Stmt::Expr(syn::Expr::Return(_), _)) => true,
+        Some(syn::Stmt::Expr(syn::Expr::Macro(expr_macro), _)) => {
+            panic_prefix(&expr_macro.mac).is_some()
+        }
+        Some(syn::Stmt::Macro(stmt_macro)) => panic_prefix(&stmt_macro.mac).is_some(),
+        _ => false,
+    }
+}
+
+struct MoveFinder<'n> {
+    name: &'n str,
+    branches: bool,
+    moved: bool,
+}
+
+impl MoveFinder<'_> {
+    fn is_binding(&self, expr: &syn::Expr) -> bool {
+        matches!(expr, syn::Expr::Path(path) if path.path.is_ident(self.name))
+    }
+}
+
+impl<'ast> Visit<'ast> for MoveFinder<'_> {
+    fn visit_local(&mut self, i: &'ast syn::Local) {
+        // `let _ = name;` binds nothing, so it doesn't move
+        match &i.init {
+            Some(init) if matches!(i.pat, syn::Pat::Wild(_)) && self.is_binding(&init.expr) => {}
+            Some(init) if !self.branches => self.visit_expr(&init.expr),
+            _ => syn::visit::visit_local(self, i),
+        }
+    }
+
+    fn visit_expr(&mut self, i: &'ast syn::Expr) {
+        match i {
+            syn::Expr::Path(path) if path.path.is_ident(self.name) => self.moved = true,
+
+            syn::Expr::MethodCall(method_call) if self.is_binding(&method_call.receiver) => {
+                for arg in &method_call.args {
+                    self.visit_expr(arg);
+                }
+            }
+
+            syn::Expr::Field(field) if self.is_binding(&field.base) => {}
+            syn::Expr::Reference(reference) if self.is_binding(&reference.expr) => {}
+            syn::Expr::Assign(assign) if self.is_binding(&assign.left) => {
+                self.visit_expr(&assign.right);
+            }
+
+            syn::Expr::Binary(binary)
+                if matches!(
+                    binary.op,
+                    syn::BinOp::Eq(_)
+                        | syn::BinOp::Ne(_)
+                        | syn::BinOp::Lt(_)
+                        | syn::BinOp::Le(_)
+                        | syn::BinOp::Gt(_)
+                        | syn::BinOp::Ge(_)
+                ) =>
+            {
+                for operand in [&binary.left, &binary.right] {
+                    if !self.is_binding(operand) {
+                        self.visit_expr(operand);
+                    }
+                }
+            }
+
+            _ if self.branches => syn::visit::visit_expr(self, i),
+
+            syn::Expr::If(expr_if) => self.visit_expr(&expr_if.cond),
+            syn::Expr::Match(expr_match) => self.visit_expr(&expr_match.expr),
+            syn::Expr::While(expr_while) => self.visit_expr(&expr_while.cond),
+            syn::Expr::ForLoop(expr_for) => self.visit_expr(&expr_for.expr),
+            syn::Expr::Loop(_) => {}
+            syn::Expr::Binary(binary)
+                if matches!(binary.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) =>
+            {
+                self.visit_expr(&binary.left)
+            }
+
+            _ => syn::visit::visit_expr(self, i),
+        }
+    }
+}
+
+enum KeyedLoop {
+    Entries,
+    Keys,
+    Values,
+}
+
+fn file_kind(attrs: &[syn::Attribute]) -> Lowered<Option<ScriptKind>> {
+    let Some(attr) = attrs.iter().find(|attr| attr.path().is_ident("kind")) else {
+        return Ok(None);
+    };
+
+    match &attr.meta {
+        syn::Meta::NameValue(syn::MetaNameValue {
+            value:
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(kind),
+                    ..
+                }),
+            ..
+        }) => ScriptKind::from_name(&kind.value())
+            .map(Some)
+            .ok_or_else(|| unsupported!("Unknown script kind `{}`", kind.value())),
+        _ => Err(unsupported!(
+            "Expected `#![kind = \"script\" | \"localscript\" | \"module\"]`"
+        )),
+    }
+}
+
+fn assignable_place(expr: &syn::Expr) -> Option<&syn::Expr> {
+    match expr {
+        syn::Expr::Field(_) | syn::Expr::Index(_) => Some(expr),
+        syn::Expr::Paren(paren) => assignable_place(&paren.expr),
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Deref(_),
+            expr,
+            ..
+        }) => assignable_place(expr),
+        _ => None,
+    }
+}
+
+fn simple_binding(pat: &syn::Pat) -> Option<String> {
+    match pat {
+        syn::Pat::Ident(pat_ident) if pat_ident.subpat.is_none() => {
+            Some(pat_ident.ident.to_string())
+        }
+        syn::Pat::Reference(pat_ref) => simple_binding(&pat_ref.pat),
+        syn::Pat::Paren(pat_paren) => simple_binding(&pat_paren.pat),
+        syn::Pat::Type(pat_type) => simple_binding(&pat_type.pat),
+        _ => None,
+    }
+}
+
+fn bind_loop_pattern(
+    pat: &syn::Pat,
+    value: &LoopValue,
+    bindings: &mut Vec<(String, LoopValue)>,
+) -> Lowered {
+    match (pat, value) {
+        (syn::Pat::Ident(pat_ident), _) if pat_ident.subpat.is_none() => {
+            bindings.push((pat_ident.ident.to_string(), value.clone()));
+        }
+
+        (syn::Pat::Reference(pat_ref), _) => bind_loop_pattern(&pat_ref.pat, value, bindings)?,
+        (syn::Pat::Paren(pat_paren), _) => bind_loop_pattern(&pat_paren.pat, value, bindings)?,
+        (syn::Pat::Type(pat_type), _) => bind_loop_pattern(&pat_type.pat, value, bindings)?,
+        (syn::Pat::Wild(_), _) => {}
+
+        (syn::Pat::Tuple(tuple), LoopValue::Tuple(values)) if tuple.elems.len() == values.len() => {
+            for (elem, value) in tuple.elems.iter().zip(values) {
+                bind_loop_pattern(elem, value, bindings)?;
+            }
+        }
+
+        (syn::Pat::Tuple(tuple), _) => {
+            for (index, elem) in tuple.elems.iter().enumerate() {
+                let element = LoopValue::Element(Box::new(value.clone()), index);
+                bind_loop_pattern(elem, &element, bindings)?;
+            }
+        }
+
+        _ => return Err(unsupported!("Unsupported loop variable pattern")),
+    }
+
+    Ok(())
+}
+
+enum BufferField {
+    Number(&'static str, usize),
+    Bool,
+    Enum(Vec<String>),
+    Struct(String),
+}
+
+#[derive(Clone, Copy)]
+enum BlockTail<'a> {
+    Discard,
+    Return,
+    Assign(&'a str),
+}
+
+struct LoopFrame {
+    label: Option<String>,
+    /// The local a nested loop leaving this one through its label sets
+    exit: Option<String>,
+    result: LoopResult,
+    /// How many drop scopes were open outside the loop, which its body's exits leave open
+    drop_depth: usize,
+}
+
+/// Where the value of a `break` out of a loop goes.
+#[derive(Clone)]
+enum LoopResult {
+    Discard,
+    Return,
+    Assign(String),
+}
+
+/// A `break 'label` or `continue 'label`.
+struct LabeledExit {
+    label: String,
+    keyword: &'static str,
+    /// Whether it is inside a loop nested in the block searched
+    nested: bool,
+}
+
+/// The `break`s and `continue`s in `block` that leave a labeled loop.
+fn labeled_exits(block: &syn::Block) -> Vec<LabeledExit> {
+    #[derive(Default)]
+    struct Finder {
+        loops: usize,
+        exits: Vec<LabeledExit>,
+    }
+
+    impl Finder {
+        fn push(&mut self, label: &Option<syn::Lifetime>, keyword: &'static str) {
+            if let Some(label) = label {
+                self.exits.push(LabeledExit {
+                    label: label.ident.to_string(),
+                    keyword,
+                    nested: self.loops > 0,
+                });
+            }
+        }
+    }
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_expr_break(&mut self, i: &'ast syn::ExprBreak) {
+            self.push(&i.label, "break");
+            syn::visit::visit_expr_break(self, i);
+        }
+
+        fn visit_expr_continue(&mut self, i: &'ast syn::ExprContinue) {
+            self.push(&i.label, "continue");
+        }
+
+        fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+            self.visit_expr(&i.expr);
+            self.loops += 1;
+            self.visit_block(&i.body);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+            self.loops += 1;
+            syn::visit::visit_expr_while(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_loop(&mut self, i: &'ast syn::ExprLoop) {
+            self.loops += 1;
+            syn::visit::visit_expr_loop(self, i);
+            self.loops -= 1;
+        }
+
+        fn visit_expr_closure(&mut self, _: &'ast syn::ExprClosure) {}
+
+        fn visit_item(&mut self, _: &'ast syn::Item) {}
+    }
+
+    let mut finder = Finder::default();
+    finder.visit_block(block);
+    finder.exits
+}
+
+fn is_value_expr(expr: &syn::Expr) -> bool {
+    match expr {
+        syn::Expr::Binary(binary) => !is_compound_assign(&binary.op),
+        syn::Expr::Macro(expr_macro) => {
+            !["println", "print", "eprintln", "eprint"]
+                .iter()
+                .any(|name| expr_macro.mac.path.is_ident(name))
+                && logging::log_level(&expr_macro.mac).is_none()
+                // A panic has no value to assign or return
+                && panic_prefix(&expr_macro.mac).is_none()
+        }
+        _ => !matches!(
+            expr,
+            syn::Expr::If(_)
+                | syn::Expr::Match(_)
+                | syn::Expr::Block(_)
+                | syn::Expr::ForLoop(_)
+                | syn::Expr::While(_)
+                | syn::Expr::Loop(_)
+                | syn::Expr::Return(_)
+                | syn::Expr::Break(_)
+                | syn::Expr::Continue(_)
+                | syn::Expr::Assign(_)
+        ),
+    }
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::AddAssign(_)
+            | syn::BinOp::SubAssign(_)
+            | syn::BinOp::MulAssign(_)
+            | syn::BinOp::DivAssign(_)
+            | syn::BinOp::RemAssign(_)
+            | syn::BinOp::BitAndAssign(_)
+            | syn::BinOp::BitOrAssign(_)
+            | syn::BinOp::BitXorAssign(_)
+            | syn::BinOp::ShlAssign(_)
+            | syn::BinOp::ShrAssign(_)
+    )
+}
+
+/// The names `expr` assigns to that it doesn't bind itself, in the order they're assigned.
+fn assigned_names(expr: &syn::Expr) -> Vec<String> {
+    #[derive(Default)]
+    struct Assigned {
+        assigned: Vec<String>,
+        bound: HashSet<String>,
+    }
+
+    impl Assigned {
+        fn assign(&mut self, place: &syn::Expr) {
+            if let syn::Expr::Path(path) = place {
+                if let Some(ident) = path.path.get_ident() {
+                    let name = ident.to_string();
+                    if !self.assigned.contains(&name) {
+                        self.assigned.push(name);
+                    }
+                }
+            }
+        }
+    }
+
+    impl<'ast> Visit<'ast> for Assigned {
+        fn visit_expr_assign(&mut self, i: &'ast syn::ExprAssign) {
+            self.assign(&i.left);
+            syn::visit::visit_expr_assign(self, i);
+        }
+
+        fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+            if is_compound_assign(&i.op) {
+                self.assign(&i.left);
+            }
+            syn::visit::visit_expr_binary(self, i);
+        }
+
+        fn visit_pat_ident(&mut self, i: &'ast syn::PatIdent) {
+            self.bound.insert(i.ident.to_string());
+            syn::visit::visit_pat_ident(self, i);
+        }
+    }
+
+    let mut names = Assigned::default();
+    names.visit_expr(expr);
+    names
+        .assigned
+        .into_iter()
+        .filter(|name| !names.bound.contains(name))
+        .collect()
+}
+
+fn stmt_attrs(stmt: &syn::Stmt) -> &[syn::Attribute] {
+    match stmt {
+        syn::Stmt::Local(local) => &local.attrs,
+        syn::Stmt::Macro(stmt_macro) => &stmt_macro.attrs,
+        syn::Stmt::Expr(expr, _) => expr_attrs(expr),
+        syn::Stmt::Item(_) => &[],
+    }
+}
+
+fn expr_attrs(expr: &syn::Expr) -> &[syn::Attribute] {
+    match expr {
+        syn::Expr::Assign(e) => &e.attrs,
+        syn::Expr::Binary(e) => &e.attrs,
+        syn::Expr::Block(e) => &e.attrs,
+        syn::Expr::Break(e) => &e.attrs,
+        syn::Expr::Call(e) => &e.attrs,
+        syn::Expr::Continue(e) => &e.attrs,
+        syn::Expr::ForLoop(e) => &e.attrs,
+        syn::Expr::If(e) => &e.attrs,
+        syn::Expr::Loop(e) => &e.attrs,
+        syn::Expr::Macro(e) => &e.attrs,
+        syn::Expr::Match(e) => &e.attrs,
+        syn::Expr::MethodCall(e) => &e.attrs,
+        syn::Expr::Return(e) => &e.attrs,
+        syn::Expr::Unsafe(e) => &e.attrs,
+        syn::Expr::While(e) => &e.attrs,
+
+        _ => &[],
+    }
+}
+
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    let text = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(doc),
+                        ..
+                    }),
+                ..
+            }) => Some(doc.value()),
+
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    raw_luau_lines(&text)
+}
+
+/// `task::spawn`, `task::defer` and `task::delay` return the thread they schedule, which
+/// `task::cancel` or `.cancel()` stops.
+fn spawns_thread(path: &syn::Path) -> bool {
+    let segments = &path.segments;
+    segments.len() == 2
+        && segments[0].ident == "task"
+        && matches!(
+            segments[1].ident.to_string().as_str(),
+            "spawn" | "defer" | "delay"
+        )
+}
+
+/// The roblox-ts `TS.import` equivalent of a `require` path like `script.Parent.Util` or
+/// `game:GetService("ReplicatedStorage").Shared.Util`: the leading instance expression
+/// becomes the parent and each segment after it a child name.
+fn ts_import(path: &str) -> String {
+    let mut segments = Vec::new();
+    let mut segment = String::new();
+    let mut depth = 0;
+
+    for c in path.chars() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            '.' if depth == 0 => {
+                segments.push(std::mem::take(&mut segment));
+                continue;
+            }
+            _ => {}
+        }
+        segment.push(c);
+    }
+    segments.push(segment);
+
+    let mut segments = segments.into_iter();
+    let mut parent = segments.next().unwrap_or_default();
+    let mut names: Vec<String> = segments.collect();
+
+    if parent == "game" && !names.is_empty() {
+        parent = format!("game:GetService(\"{}\")", names.remove(0));
+    }
+
+    while names.first().is_some_and(|name| name == "Parent") {
+        parent.push_str(".Parent");
+        names.remove(0);
+    }
+
+    let names: Vec<String> = names.iter().map(|name| format!("\"{}\"", name)).collect();
+    format!("TS.import(script, {}, {})", parent, names.join(", "))
+}
+
+/// Whether `func` is `module::name` or `std::module::name`.
+fn is_std_path(func: &syn::Expr, module: &str, name: &str) -> bool {
+    let syn::Expr::Path(path) = func else {
+        return false;
+    };
+
+    let segments: Vec<String> = path
+        .path
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect();
+
+    match segments.as_slice() {
+        [m, n] => m == module && n == name,
+        [std, m, n] => std == "std" && m == module && n == name,
+        _ => false,
+    }
+}
+
+/// Whether `call` creates empty shared map state, like `Arc::new(RwLock::new(HashMap::new()))`.
+fn is_shared_table_new(call: &syn::ExprCall) -> bool {
+    let inner = |call: &syn::ExprCall| match &call.args.iter().collect::<Vec<_>>()[..] {
+        [syn::Expr::Call(inner)] => Some(inner.clone()),
+        _ => None,
+    };
+
+    is_std_path(&call.func, "Arc", "new")
+        && inner(call).is_some_and(|lock| {
+            ["RwLock", "Mutex"]
+                .iter()
+                .any(|ty| is_std_path(&lock.func, ty, "new"))
+                && inner(&lock).is_some_and(|map| {
+                    map.args.is_empty()
+                        && ["HashMap", "BTreeMap"].iter().any(|ty| {
+                            is_std_path(&map.func, ty, "new")
+                                || is_std_path(&map.func, ty, "default")
+                        })
+                })
+        })
+}
+
+fn process_exit_code(call: &syn::ExprCall) -> Option<&syn::Expr> {
+    (is_std_path(&call.func, "process", "exit") && call.args.len() == 1).then(|| &call.args[0])
+}
+
+fn table_key(name: &str) -> String {
+    if is_luau_identifier(name) {
+        name.to_string()
+    } else {
+        format!("[\"{}\"]", name)
+    }
+}
+
+/// `base.name`, or `base["name"]` for a name that can't be written as a field, like `end`.
+fn member_access(base: &str, name: &str) -> String {
+    if is_luau_identifier(name) {
+        format!("{}.{}", base, name)
+    } else {
+        format!("{}[\"{}\"]", base, name)
+    }
+}
+
+/// Segments joined into a field path like `util.inner.then`, indexing by key past any
+/// segment that can't be a field, like `util.inner["then"]`.
+fn member_path(segments: impl IntoIterator<Item = String>) -> String {
+    let mut segments = segments.into_iter();
+    let first = segments.next().unwrap_or_default();
+    segments.fold(first, |path, segment| member_access(&path, &segment))
+}
+
+/// `receiver:method(args)`. A method named after a Luau keyword is looked up by its key and
+/// passed the receiver, through a function when the receiver must only be evaluated once.
+fn method_call_expr(receiver: &str, method: &str, args: &str) -> String {
+    if is_luau_identifier(method) {
+        return format!("{}:{}({})", receiver, method, args);
+    }
+
+    let separator = if args.is_empty() { "" } else { ", " };
+    if is_simple_place(receiver) {
+        format!(
+            "{}({}{}{})",
+            member_access(receiver, method),
+            receiver,
+            separator,
+            args
+        )
+    } else {
+        format!(
+            "(function(self, ...) return {}(self, ...) end)({}{}{})",
+            member_access("self", method),
+            receiver,
+            separator,
+            args
+        )
+    }
+}
+
+fn is_luau_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !rename::is_luau_keyword(name)
+}
+
+/// The opening line of a function declared as `name`, like `function Range:len(): number`.
+/// A method or module function named after a Luau keyword is assigned to its table's key
+/// instead, as `Range["end"] = function(self): number`.
+fn function_header(name: &str, generics: &str, params: &str, return_type: &str) -> String {
+    match name.rfind([':', '.']) {
+        Some(at) if !is_luau_identifier(&name[at + 1..]) => {
+            let params = match (&name[at..at + 1], params) {
+                (":", "") => "self".to_string(),
+                (":", params) => format!("self, {}", params),
+                (_, params) => params.to_string(),
+            };
+            format!(
+                "{} = function{}({}){}",
+                member_access(&name[..at], &name[at + 1..]),
+                generics,
+                params,
+                return_type
+            )
+        }
+        _ => format!("function {}{}({}){}", name, generics, params, return_type),
+    }
+}
+
+fn option_inner(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+
+    generic_type_args(&segment.arguments).first().copied()
+}
+
+fn camel_case(name: &str) -> String {
+    let pascal = pascal_case(name);
+    let mut chars = pascal.chars();
+
+    match chars.next() {
+        Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+        None => pascal,
+    }
+}
+
+fn pascal_case(name: &str) -> String {
+    name.split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first.to_ascii_uppercase().to_string() + chars.as_str()
+            })
+        })
+        .collect()
+}
+
+fn uses_log_macros(file: &syn::File) -> bool {
+    struct Finder(bool);
+
+    impl<'ast> Visit<'ast> for Finder {
+        fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+            self.0 |= logging::log_level(mac).is_some();
+        }
+    }
+
+    let mut finder = Finder(false);
+    finder.visit_file(file);
+    finder.0
+}
+
+fn is_luau_macro(mac: &syn::Macro) -> bool {
+    mac.path.is_ident("luau")
+}
+
+fn parse_luau_macro(mac: &syn::Macro) -> Lowered<String> {
+    mac.parse_body::<syn::LitStr>()
+        .map(|lit| lit.value())
+        .map_err(|_| unsupported!("luau! expects a single string literal"))
+}
+
+fn luau_attribute(attrs: &[syn::Attribute]) -> Lowered<Option<String>> {
+    attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("luau"))
+        .map(|attr| {
+            attr.parse_args::<syn::LitStr>()
+                .map(|lit| lit.value())
+                .map_err(|_| unsupported!("#[luau] expects a single string literal"))
+        })
+        .transpose()
+}
+
+fn raw_luau_lines(source: &str) -> Vec<String> {
+    let lines: Vec<&str> = source
+        .lines()
+        .skip_while(|line| line.trim().is_empty())
+        .collect();
+
+    let common_indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    let mut lines: Vec<String> = lines
+        .iter()
+        .map(|line| {
+            line.get(common_indent..)
+                .unwrap_or("")
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+
+    lines
+}
+
+impl<'a> LuauTranspiler<'a> {
+    pub fn visit_file(&mut self, i: &syn::File) -> Result<(), UnsupportedConstruct> {
+        // A failure outside any item or statement, like options that conflict, fails the file
+        // even with `--lenient`
+        self.lower_file(i)
+            .map_err(|error| error.into_construct(i.span()))
+    }
+
+    fn visit_item(&mut self, i: &syn::Item) -> Lowered {
+        let attrs = match i {
+            syn::Item::Const(item) => &item.attrs,
+            syn::Item::Enum(item) => &item.attrs,
+            syn::Item::Fn(item) => &item.attrs,
+            syn::Item::ForeignMod(item) => &item.attrs,
+            syn::Item::Impl(item) => &item.attrs,
+            syn::Item::Mod(item) => &item.attrs,
+            syn::Item::Static(item) => &item.attrs,
+            syn::Item::Struct(item) => &item.attrs,
+            syn::Item::Trait(item) => &item.attrs,
+            syn::Item::Type(item) => &item.attrs,
+            syn::Item::Use(item) => &item.attrs,
+
+            _ => return Ok(()),
+        };
+
+        if self.options.cfg.is_enabled(attrs) {
+            self.flush_comments_before(i.span().start().line);
+            let region = self.begin_region(i);
+            let enclosing = self.mark_origin(i.span().start().line);
+            // Functions leave a stub of themselves instead
+            self.recover(
+                i.span(),
+                |this| {
+                    this.check_dict_trait_objects(i)?;
+                    match i {
+                        syn::Item::Const(item) => this.visit_item_const(item),
+                        syn::Item::Enum(item) => this.visit_item_enum(item),
+                        syn::Item::Fn(item) => this.visit_item_fn(item),
+                        syn::Item::Impl(item) => this.visit_item_impl(item),
+                        syn::Item::Mod(item) => this.visit_item_mod(item),
+                        syn::Item::Static(item) => this.visit_item_static(item),
+                        syn::Item::Struct(item) => this.visit_item_struct(item),
+                        syn::Item::Trait(item) => this.visit_item_trait(item),
+                        _ => Ok(()),
+                    }
+                },
+                |this, message| {
+                    if !matches!(i, syn::Item::Fn(_)) {
+                        this.add_line(&format!("-- roblox-rs: unsupported: {}", message));
+                    }
+                },
+            )?;
+            self.mark_origin(enclosing);
+            self.end_region(region);
+        }
+
+        self.skip_comments_through(i.span().end().line);
+
+        Ok(())
+    }
+
+    /// Under `--trait-dispatch dict` values have no methods to call through a `dyn` of a
+    /// user trait, so one in the item, outside its nested items, can't be lowered.
+    fn check_dict_trait_objects(&self, item: &syn::Item) -> Lowered {
+        #[derive(Default)]
+        struct Finder {
+            objects: Vec<(String, proc_macro2::Span)>,
+        }
+
+        impl<'ast> Visit<'ast> for Finder {
+            fn visit_type_trait_object(&mut self, i: &'ast syn::TypeTraitObject) {
+                for bound in &i.bounds {
+                    if let syn::TypeParamBound::Trait(trait_bound) = bound {
+                        if let Some(segment) = trait_bound.path.segments.last() {
+                            self.objects.push((segment.ident.to_string(), i.span()));
+                        }
+                    }
+                }
+                syn::visit::visit_type_trait_object(self, i);
+            }
+
+            fn visit_item(&mut self, _: &'ast syn::Item) {}
+        }
+
+        if !self.types.dict_dispatch {
+            return Ok(());
+        }
+
+        let mut finder = Finder::default();
+        syn::visit::visit_item(&mut finder, item);
+        match finder
+            .objects
+            .into_iter()
+            .find(|(name, _)| self.types.traits.contains_key(name))
+        {
+            Some((name, span)) => Err(unsupported!(
+                "`dyn {}` isn't supported with `--trait-dispatch dict`, which gives values no methods; take a generic `T: {}` instead",
+                name,
+                name
+            )
+            .within(span)),
+            None => Ok(()),
+        }
+    }
+
+    fn visit_stmt(&mut self, i: &syn::Stmt) -> Lowered {
+        if self.options.cfg.is_enabled(stmt_attrs(i)) {
+            self.flush_comments_before(i.span().start().line + 1);
+            let enclosing = self.mark_origin(i.span().start().line);
+            self.recover(
+                i.span(),
+                |this| match i {
+                    syn::Stmt::Expr(syn::Expr::Block(expr_block), _) => {
+                        this.emit_block_stmt(expr_block)
+                    }
+                    syn::Stmt::Expr(expr, _) => this.visit_expr(expr),
+                    syn::Stmt::Local(local) => this.visit_local(local),
+                    syn::Stmt::Item(item) => this.visit_item(item),
+                    syn::Stmt::Macro(stmt_macro) => this.visit_stmt_macro(stmt_macro),
+                },
+                |this, message| this.add_line(&unsupported_stub(message)),
+            )?;
+            self.mark_origin(enclosing);
+        }
+
+        self.skip_comments_through(i.span().end().line);
+
+        Ok(())
+    }
+
+    /// Lowers an expression whose value is discarded, as the statements it runs.
+    fn visit_expr(&mut self, i: &syn::Expr) -> Lowered {
+        match i {
+            syn::Expr::Assign(expr) => self.visit_expr_assign(expr),
+            syn::Expr::Binary(expr) => self.visit_expr_binary(expr),
+            syn::Expr::Break(expr) => self.visit_expr_break(expr),
+            syn::Expr::Call(expr) => self.visit_expr_call(expr),
+            syn::Expr::Continue(expr) => self.visit_expr_continue(expr),
+            syn::Expr::ForLoop(expr) => self.visit_expr_for_loop(expr),
+            syn::Expr::If(expr) => self.visit_expr_if(expr),
+            syn::Expr::Loop(expr) => self.visit_expr_loop(expr),
+            syn::Expr::Macro(expr) => self.visit_expr_macro(expr),
+            syn::Expr::Match(expr) => self.visit_expr_match(expr),
+            syn::Expr::MethodCall(expr) => self.visit_expr_method_call(expr),
+            syn::Expr::Return(expr) => self.visit_expr_return(expr),
+            syn::Expr::Try(expr) => self.visit_expr_try(expr),
+            syn::Expr::While(expr) => self.visit_expr_while(expr),
+
+            syn::Expr::Block(syn::ExprBlock { block, .. })
+            | syn::Expr::Unsafe(syn::ExprUnsafe { block, .. }) => {
+                for stmt in &block.stmts {
+                    self.visit_stmt(stmt)?;
+                }
+
+                Ok(())
+            }
+
+            // Only the calls and assignments inside anything else have effects
+            syn::Expr::Paren(syn::ExprParen { expr, .. })
+            | syn::Expr::Group(syn::ExprGroup { expr, .. })
+            | syn::Expr::Reference(syn::ExprReference { expr, .. })
+            | syn::Expr::Unary(syn::ExprUnary { expr, .. })
+            | syn::Expr::Cast(syn::ExprCast { expr, .. })
+            | syn::Expr::Field(syn::ExprField { base: expr, .. }) => self.visit_expr(expr),
+            syn::Expr::Index(expr) => {
+                self.visit_expr(&expr.expr)?;
+                self.visit_expr(&expr.index)
+            }
+            syn::Expr::Tuple(syn::ExprTuple { elems, .. })
+            | syn::Expr::Array(syn::ExprArray { elems, .. }) => {
+                elems.iter().try_for_each(|elem| self.visit_expr(elem))
+            }
+            syn::Expr::Struct(expr) => expr
+                .fields
+                .iter()
+                .try_for_each(|field| self.visit_expr(&field.expr)),
+
+            _ => Ok(()),
+        }
+    }
+
+    fn visit_item_fn(&mut self, i: &syn::ItemFn) -> Lowered {
+        if self.options.monomorphize && !type_registry::type_param_names(&i.sig.generics).is_empty()
+        {
+            return Ok(());
+        }
+
+        let fn_name = i.sig.ident.to_string();
+        let within = self.options.module_name.clone();
+
+        if !self.module_path.is_empty() {
+            let fn_name = format!("{}.{}", self.module_path.join("."), fn_name);
+            self.recover_function(i.span(), &fn_name, |this| {
+                this.transpile_function(&i.attrs, &i.sig, &i.block, &fn_name, &within, &[])
+            })?;
+            return Ok(());
+        }
+
+        if self.inlines.is_removed(&fn_name) {
+            return Ok(());
+        }
+
+        if fn_name == "main" && i.sig.inputs.is_empty() {
+            self.has_main = true;
+
+            // Lune runs a file's top-level code like a Roblox script
+            let lune_script = self.is_lune() && !self.is_module();
+            if self.options.script || self.kind.is_some_and(ScriptKind::runs_main) || lune_script {
+                self.script_main = Some((i.clone(), self.next_comment));
+                self.skip_comments_through(i.span().end().line);
+                return Ok(());
+            }
+        }
+
+        self.recover_function(i.span(), &fn_name, |this| {
+            this.transpile_function(&i.attrs, &i.sig, &i.block, &fn_name, &within, &[])
+        })?;
+
+        Ok(())
+    }
+
+    fn visit_item_mod(&mut self, i: &syn::ItemMod) -> Lowered {
+        let Some((_, items)) = &i.content else {
+            return Ok(());
+        };
+
+        let fns = items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Fn(item_fn) => Some(item_fn.sig.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+
+        self.module_path.push(i.ident.to_string());
+        self.module_fns.push(fns);
+
+        self.visit_items(items)?;
+
+        // Types keep their file-level local, and the module table gets a field for the pub ones
+        let table = self.module_path.join(".");
+        for item in items {
+            let (name, vis) = match item {
+                syn::Item::Struct(item_struct) => (item_struct.ident.to_string(), &item_struct.vis),
+                syn::Item::Enum(item_enum) => (item_enum.ident.to_string(), &item_enum.vis),
+                syn::Item::Const(item_const) => (item_const.ident.to_string(), &item_const.vis),
+                syn::Item::Static(item_static) => (item_static.ident.to_string(), &item_static.vis),
+                _ => continue,
+            };
+
+            let has_table = self.has_struct_table(&name)
+                || self.types.is_enum(&name)
+                || matches!(item, syn::Item::Const(_) | syn::Item::Static(_));
+            if has_table && matches!(vis, syn::Visibility::Public(_)) {
+                self.add_line(&format!("{}.{} = {}", table, name, name));
+            }
+        }
+
+        for (name, value) in self.re_exported(&self.module_path) {
+            self.add_line(&format!("{}.{} = {}", table, name, value));
+        }
+
+        self.module_fns.pop();
+        self.module_path.pop();
+
+        Ok(())
+    }
+
+    fn visit_item_impl(&mut self, i: &syn::ItemImpl) -> Lowered {
+        let Some(type_name) = type_registry::type_name(&i.self_ty) else {
+            return Ok(());
+        };
+
+        // Lowered with each of the struct's instantiations instead
+        if self.options.monomorphize
+            && self.specialized_table.is_none()
+            && self.types.is_generic_struct(&type_name)
+        {
+            return Ok(());
+        }
+
+        let user_trait = i
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string())
+            .filter(|trait_name| self.types.traits.contains_key(trait_name));
+
+        if let Some(trait_name) = user_trait.filter(|_| self.types.dict_dispatch) {
+            self.emit_dict_impl(i, &type_name, &trait_name)?;
+            return Ok(());
+        }
+
+        let is_drop = i
+            .trait_
+            .as_ref()
+            .is_some_and(|(_, path, _)| path.is_ident("Drop"));
+        let is_index_mut = i
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .is_some_and(|segment| segment.ident == "IndexMut");
+
+        let is_extension = self.types.is_extended(&type_name);
+        let table_name = if is_extension {
+            let table_name = extension_table(&type_name);
+            if self.method_tables.insert(table_name.clone()) {
+                self.add_line(&format!("local {} = {{}}", table_name));
+            }
+
+            table_name
+        } else if let Some(table_name) = &self.specialized_table {
+            table_name.clone()
+        } else {
+            self.ensure_method_table(&type_name);
+            type_name.clone()
+        };
+
+        let previous_self_type = self.self_type.replace(type_name.clone());
+        let previous_self_rust_type = self.self_rust_type.replace((*i.self_ty).clone());
+        let impl_generics = type_registry::type_param_names(&i.generics);
+        self.impl_const_params = type_registry::impl_const_params(i);
+        let self_alias = if is_extension {
+            self.map_type(&i.self_ty)
+        } else {
+            generic_alias(&type_name, &impl_generics)
+        };
+
+        if self.options.monomorphize {
+            for param in &impl_generics {
+                self.type_substitutions
+                    .entry(param.clone())
+                    .or_insert_with(|| "any".to_string());
+            }
+        } else {
+            self.impl_type_params = impl_generics;
+        }
+
+        for item in &i.items {
+            let syn::ImplItem::Fn(method) = item else {
+                continue;
+            };
+
+            if !self.options.cfg.is_enabled(&method.attrs) {
+                continue;
+            }
+
+            self.flush_comments_before(method.span().start().line);
+
+            if is_index_mut && method.sig.ident == "index_mut" {
+                self.transpile_newindex(&type_name, method)?;
+                self.skip_comments_through(method.span().end().line);
+                continue;
+            }
+
+            let is_drop_method = is_drop && method.sig.ident == "drop";
+            let method_name = if is_drop_method {
+                "destroy".to_string()
+            } else {
+                method.sig.ident.to_string()
+            };
+
+            let epilogue: Vec<String> = if is_drop_method {
+                self.types
+                    .get(&type_name)
+                    .map(|info| info.droppable_fields.clone())
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|field| self.field_destroy(&type_name, field))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let separator = match method.sig.receiver() {
+                Some(_) if is_extension => {
+                    self.extra_params = vec![("self".to_string(), self_alias.clone())];
+                    "."
+                }
+                Some(_) => ":",
+                None => ".",
+            };
+
+            let qualified_name = format!("{}{}{}", table_name, separator, method_name);
+            self.recover_function(method.span(), &qualified_name, |this| {
+                this.transpile_function(
+                    &method.attrs,
+                    &method.sig,
+                    &method.block,
+                    &qualified_name,
+                    &table_name,
+                    &epilogue,
+                )
+            })?;
+
+            self.skip_comments_through(method.span().end().line);
+        }
+
+        self.self_type = previous_self_type;
+        self.self_rust_type = previous_self_rust_type;
+        self.impl_type_params.clear();
+        self.impl_const_params.clear();
+        self.type_substitutions.clear();
+
+        Ok(())
+    }
+
+    fn visit_item_trait(&mut self, i: &syn::ItemTrait) -> Lowered {
+        if self.types.dict_dispatch {
+            self.emit_dict_trait(i)?;
+            return Ok(());
+        }
+
+        let trait_name = i.ident.to_string();
+        if self.types.is_dyn_trait(&trait_name) {
+            self.emit_interface_type(i);
+        }
+
+        if !self.types.has_default_methods(&trait_name) {
+            return Ok(());
+        }
+
+        self.add_line(&format!("local {} = {{}}", trait_name));
+
+        let previous_self_type = self.self_type.take();
+        self.impl_type_params = type_registry::type_param_names(&i.generics);
+
+        for method in type_registry::default_methods(i) {
+            if !self.options.cfg.is_enabled(&method.attrs) {
+                continue;
+            }
+
+            self.flush_comments_before(method.span().start().line);
+
+            let separator = if method.sig.receiver().is_some() {
+                ":"
+            } else {
+                "."
+            };
+
+            let qualified_name = format!("{}{}{}", trait_name, separator, method.sig.ident);
+            self.transpile_function(
+                &method.attrs,
+                &method.sig,
+                method.default.as_ref().unwrap(),
+                &qualified_name,
+                &trait_name,
+                &[],
+            )?;
+
+            self.skip_comments_through(method.span().end().line);
+        }
+
+        self.self_type = previous_self_type;
+        self.impl_type_params.clear();
+
+        Ok(())
+    }
+
+    fn visit_item_struct(&mut self, i: &syn::ItemStruct) -> Lowered {
+        let name = i.ident.to_string();
+        let is_generic = i.generics.type_params().next().is_some();
+
+        if !(self.options.monomorphize && is_generic) {
+            let generics = type_registry::type_param_names(&i.generics);
+            let previous_len = self.type_params.len();
+            self.type_params.extend(generics.iter().cloned());
+
+            let alias = generic_alias(&name, &generics);
+            self.emit_struct_type(i, &alias);
+            self.type_params.truncate(previous_len);
+        }
+
+        if self.types.is_unit_struct(&name) {
+            self.emit_doc_comments(doc_lines(&i.attrs));
+
+            if self.types.has_methods(&name) {
+                self.ensure_method_table(&name);
+            } else {
+                self.add_line(&format!("local {} = table.freeze({{}})", name));
+            }
+
+            let keyword = self.type_keyword(&i.vis);
+            self.add_line(&format!("{} {} = typeof({})", keyword, name, name));
+            return Ok(());
+        }
+
+        // Each instantiation gets its own table
+        if self.types.has_methods(&name) && !(self.options.monomorphize && is_generic) {
+            self.ensure_method_table(&name);
+        }
+
+        if self.types.is_serializable(&name) {
+            self.emit_serialize(i)?;
+        }
+
+        if self.types.is_buffered(&name) {
+            self.emit_buffer_layout(i)?;
+        }
+
+        Ok(())
+    }
+
+    fn visit_item_enum(&mut self, i: &syn::ItemEnum) -> Lowered {
+        if self.types.is_error_enum(&i.ident.to_string()) {
+            self.emit_error_enum(i)?;
+            return Ok(());
+        }
+
+        let name = i.ident.to_string();
+        let keyword = self.type_keyword(&i.vis);
+
+        let Some(variants) = unit_variants(i) else {
+            self.emit_data_enum(i, keyword);
+            return Ok(());
+        };
+
+        if self.types.has_discriminants(&name) {
+            let fields: Vec<String> = self
+                .discriminants(i)?
+                .into_iter()
+                .map(|(variant, value)| format!("{} = {}", variant, value))
+                .collect();
+            self.add_line(&format!("{} {} = number", keyword, name));
+            self.add_line(&format!(
+                "local {} = table.freeze({{ {} }})",
+                name,
+                fields.join(", ")
+            ));
+            return Ok(());
+        }
+
+        let tags: Vec<String> = variants
+            .iter()
+            .map(|variant| format!("\"{}\"", variant))
+            .collect();
+        self.add_line(&format!("{} {} = {}", keyword, name, tags.join(" | ")));
+
+        let fields: Vec<String> = variants
+            .iter()
+            .map(|variant| format!("{} = \"{}\"", variant, variant))
+            .collect();
+        self.add_line(&format!(
+            "local {} = table.freeze({{ {} }})",
+            name,
+            fields.join(", ")
+        ));
+
+        Ok(())
+    }
+
+    fn visit_item_const(&mut self, i: &syn::ItemConst) -> Lowered {
+        self.emit_global(&i.ident, &i.ty, &i.expr, false)?;
+
+        Ok(())
+    }
+
+    fn visit_item_static(&mut self, i: &syn::ItemStatic) -> Lowered {
+        let mutable = matches!(i.mutability, syn::StaticMutability::Mut(_));
+        self.emit_global(&i.ident, &i.ty, &i.expr, mutable)?;
+
+        Ok(())
+    }
+
+    fn visit_local(&mut self, i: &syn::Local) -> Lowered {
+        let local_name = match &i.pat {
+            syn::Pat::Type(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+                _ => None,
+            },
+            syn::Pat::Ident(pat_ident) => Some(pat_ident.ident.to_string()),
+            _ => None,
+        };
+        let local_type = self.local_type_of(i);
+        let needs_destroy = local_type
+            .as_ref()
+            .is_some_and(|ty| self.types.needs_destroy(ty));
+        let elements = self.droppable_elements(i);
+
+        if needs_destroy || elements.is_some() {
+            if let (Some(name), Some(scope)) = (&local_name, self.drop_scopes.last_mut()) {
+                scope.locals.push(name.clone());
+                if let Some(ty) = elements {
+                    scope.elements.insert(name.clone(), ty);
+                }
+            }
+        }
+
+        self.visit_local_binding(i)?;
+
+        // `let _ = value;` drops a value it doesn't take from a place right away
+        let temporary = i.init.as_ref().is_some_and(|init| {
+            !matches!(
+                &*init.expr,
+                syn::Expr::Path(_) | syn::Expr::Field(_) | syn::Expr::Index(_)
+            )
+        });
+        if needs_destroy && temporary && matches!(i.pat, syn::Pat::Wild(_)) {
+            let method = self.types.destroy_method(local_type.as_deref());
+            self.add_line(&format!("_:{}()", method));
+        }
+
+        if let Some(name) = local_name {
+            self.set_local_type(&name, local_type);
+
+            if let syn::Pat::Type(pat_type) = &i.pat {
+                self.set_local_rust_type(&name, &pat_type.ty);
+            }
+
+            // A generic struct's literal types the local with the instantiation it builds, and
+            // an array, tuple or `vec!` with the collection, so copies and clones can copy it
+            let literal_type = i.init.as_ref().and_then(|init| match &*init.expr {
+                syn::Expr::Struct(_)
+                | syn::Expr::Array(_)
+                | syn::Expr::Repeat(_)
+                | syn::Expr::Tuple(_) => self.value_rust_type(&init.expr),
+                syn::Expr::Macro(expr_macro) if expr_macro.mac.path.is_ident("vec") => {
+                    self.value_rust_type(&init.expr)
+                }
+                syn::Expr::MethodCall(method_call) if method_call.method == "clone" => {
+                    self.value_rust_type(&init.expr)
+                }
+                _ => None,
+            });
+            if let Some(literal_type) = literal_type.filter(|_| !matches!(i.pat, syn::Pat::Type(_)))
+            {
+                self.set_local_rust_type(&name, &literal_type);
+            }
+
+            // A lock guard on a `SharedTable` is the table
+            let table_type = i
+                .init
+                .as_ref()
+                .and_then(|init| self.shared_table_of(&init.expr))
+                .and_then(|table| self.expr_rust_type(table))
+                .cloned();
+            if let Some(table_type) = table_type {
+                self.set_local_rust_type(&name, &table_type);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn visit_stmt_macro(&mut self, i: &syn::StmtMacro) -> Lowered {
+        self.emit_macro_stmt(&i.mac)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_macro(&mut self, i: &syn::ExprMacro) -> Lowered {
+        self.emit_macro_stmt(&i.mac)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_assign(&mut self, i: &syn::ExprAssign) -> Lowered {
+        if let Some(place) = assignable_place(&i.left) {
+            let target = self.transpile_expr(place)?;
+            let value = self.transpile_expr(&i.right)?;
+            let value = self.copied(&i.right, value);
+            self.add_line(&format!("{} = {}", target, value));
+            return Ok(());
+        }
+
+        if let syn::Expr::Path(path) = &*i.left {
+            if let Some(ident) = path.path.get_ident() {
+                let var_name = ident.to_string();
+                let value = self.transpile_expr(&i.right)?;
+                let value = self.copied(&i.right, value);
+
+                // In a closure, a name it doesn't know is the enclosing function's
+                if self.in_function && self.closure_depth == 0 && !self.is_local_variable(&var_name)
+                {
+                    self.add_line(&format!("local {} = {}", var_name, value));
+                    self.add_local_variable(&var_name, true, true);
+                } else {
+                    self.check_assignment(&var_name, i.span());
+                    self.reassign_local(&var_name, value);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Assigns a local, destroying the `Drop` value it held once the new one is built. A
+    /// local that was moved out of holds nothing, and owns its new value again.
+    fn reassign_local(&mut self, name: &str, mut value: String) {
+        let index = self
+            .drop_scopes
+            .iter()
+            .rposition(|scope| scope.locals.iter().any(|local| local == name));
+
+        if let Some(index) = index {
+            if !self.drop_scopes[index].moved.remove(name) {
+                if !is_simple_place(&value) {
+                    let temp = self.names.fresh();
+                    self.add_line(&format!("local {} = {}", temp, value));
+                    value = temp;
+                }
+
+                let line = self.local_destroy(name, &self.drop_scopes[index]);
+                self.add_line(&line);
+            }
+        }
+
+        self.add_line(&format!("{} = {}", name, value));
+    }
+
+    fn visit_expr_call(&mut self, i: &syn::ExprCall) -> Lowered {
+        if self.is_lune() {
+            if let Some(lowered) = self.lower_lune_call(i)? {
+                self.add_line(&lowered);
+                return Ok(());
+            }
+
+            if is_std_path(&i.func, "env", "set_var") && i.args.len() == 2 {
+                self.use_lune_library("process");
+
+                let name = self.transpile_expr(&i.args[0])?;
+                let value = self.transpile_expr(&i.args[1])?;
+                self.add_line(&format!("process.env[{}] = {}", name, value));
+                return Ok(());
+            }
+        }
+
+        // `Ok(())` ending a `main() -> Result` that runs at the top level has no one to
+        // return to, and `Err(e)` fails the script
+        if self.in_script_main {
+            match self.variant_constructor(i) {
+                Some("Err") => {
+                    let err = self.transpile_expr(&i.args[0])?;
+                    self.add_line(&format!("error({}, 0)", err));
+                    return Ok(());
+                }
+                Some(_) => return Ok(()),
+                None => {}
+            }
+        }
+
+        if let Some(code) = process_exit_code(i) {
+            let code = self.transpile_expr(code)?;
+            if self.in_script_main && code == "0" {
+                self.add_line("return");
+            } else if code.parse::<i64>().is_ok() {
+                self.add_line(&format!("error(\"process exited with code {}\", 0)", code));
+            } else {
+                self.add_line(&format!(
+                    "error(`process exited with code {{{}}}`, 0)",
+                    code
+                ));
+            }
+
+            return Ok(());
+        }
+
+        if let syn::Expr::Path(path) = &*i.func {
+            if path.path.is_ident("drop") && i.args.len() == 1 {
+                if let Some(syn::Expr::Path(arg)) = i.args.first() {
+                    if let Some(ident) = arg.path.get_ident() {
+                        let name = ident.to_string();
+                        let scope = self
+                            .drop_scopes
+                            .iter()
+                            .rev()
+                            .find(|scope| scope.locals.contains(&name));
+                        if let Some(scope) = scope {
+                            let line = self.local_destroy(&name, scope);
+                            self.add_line(&line);
+                        }
+                    }
+                }
+
+                return Ok(());
+            }
+        }
+
+        self.statement_call = true;
+        let call = self.transpile_expr(&syn::Expr::Call(i.clone()))?;
+        self.add_line(&call);
+
+        Ok(())
+    }
+
+    fn visit_expr_try(&mut self, i: &syn::ExprTry) -> Lowered {
+        if self.emit_write(&i.expr)? {
+            return Ok(());
+        }
+
+        self.lower_try(i)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_method_call(&mut self, i: &syn::ExprMethodCall) -> Lowered {
+        let expr = syn::Expr::MethodCall(i.clone());
+        if self.emit_write(&expr)?
+            || self.emit_once_cell_set(&expr)?
+            || self.emit_shared_table_write(&expr)?
+        {
+            return Ok(());
+        }
+
+        self.statement_call = true;
+        let call = self.transpile_expr(&expr)?;
+
+        // An `unwrap` left as a statement only keeps its check, its value being a name or a
+        // field, and an `unwrap_or*` an `if` expression, which isn't a statement in Luau
+        if call.starts_with("(if ") {
+            self.add_line(&format!("local _ = {}", call));
+        } else if !is_simple_place(&call) {
+            self.add_line(&call);
+        }
+
+        Ok(())
+    }
+
+    fn visit_expr_return(&mut self, i: &syn::ExprReturn) -> Lowered {
+        if self.in_script_main {
+            // The script's top level has no caller to hand a value to, so only a failed
+            // `main() -> Result` return is kept, as an error
+            if let Some(syn::Expr::Call(call)) = i.expr.as_deref() {
+                if self.variant_constructor(call) == Some("Err") {
+                    let err = self.transpile_expr(&call.args[0])?;
+                    self.add_line(&format!("error({}, 0)", err));
+                    return Ok(());
+                }
+            }
+
+            self.add_line("return");
+            return Ok(());
+        }
+
+        match &i.expr {
+            Some(expr) if self.is_tail_call(expr) => self.emit_tail_call(expr)?,
+            Some(expr) => {
+                let value = self.transpile_expr(expr)?;
+                self.emit_return(&value);
+            }
+
+            None => self.emit_return(""),
+        }
+
+        Ok(())
+    }
+
+    fn visit_expr_binary(&mut self, i: &syn::ExprBinary) -> Lowered {
+        let compound = match i.op {
+            syn::BinOp::AddAssign(_) => "+=",
+            syn::BinOp::SubAssign(_) => "-=",
+            syn::BinOp::MulAssign(_) => "*=",
+            syn::BinOp::DivAssign(_) => "/=",
+            syn::BinOp::RemAssign(_) => "%=",
+            syn::BinOp::BitAndAssign(_) => "band",
+            syn::BinOp::BitOrAssign(_) => "bor",
+            syn::BinOp::BitXorAssign(_) => "bxor",
+            syn::BinOp::ShlAssign(_) => "lshift",
+            syn::BinOp::ShrAssign(_) => "rshift",
+
+            _ => return Ok(()),
+        };
+
+        if let syn::Expr::Path(path) = &*i.left {
+            if let Some(ident) = path.path.get_ident() {
+                self.check_assignment(&ident.to_string(), i.span());
+            }
+        }
+
+        if matches!(i.op, syn::BinOp::AddAssign(_)) && self.is_string_buffer(&i.left) {
+            let buffer = self.transpile_buffer(&i.left);
+            let part = self.transpile_expr(&i.right)?;
+            self.add_line(&format!("table.insert({}, {})", buffer, part));
+            return Ok(());
+        }
+
+        if matches!(i.op, syn::BinOp::AddAssign(_)) {
+            if let Some(parts) = self.concat_parts_of(&i.left) {
+                let part = self.transpile_expr(&i.right)?;
+                self.add_line(&format!("table.insert({}, {})", parts, part));
+                return Ok(());
+            }
+        }
+
+        let place = assignable_place(&i.left).unwrap_or(&i.left);
+        let target = self.transpile_expr(place)?;
+        let value = self.transpile_expr(&i.right)?;
+
+        if matches!(i.op, syn::BinOp::AddAssign(_)) && self.is_string_expr(&i.left) {
+            self.add_line(&format!("{} ..= {}", target, value));
+            return Ok(());
+        }
+
+        let op = match i.op {
+            syn::BinOp::AddAssign(_) => syn::BinOp::Add(Default::default()),
+            syn::BinOp::SubAssign(_) => syn::BinOp::Sub(Default::default()),
+            syn::BinOp::MulAssign(_) => syn::BinOp::Mul(Default::default()),
+            syn::BinOp::DivAssign(_) => syn::BinOp::Div(Default::default()),
+            syn::BinOp::RemAssign(_) => syn::BinOp::Rem(Default::default()),
+            syn::BinOp::BitAndAssign(_) => syn::BinOp::BitAnd(Default::default()),
+            syn::BinOp::BitOrAssign(_) => syn::BinOp::BitOr(Default::default()),
+            syn::BinOp::BitXorAssign(_) => syn::BinOp::BitXor(Default::default()),
+            syn::BinOp::ShlAssign(_) => syn::BinOp::Shl(Default::default()),
+            syn::BinOp::ShrAssign(_) => syn::BinOp::Shr(Default::default()),
+            op => op,
+        };
+
+        let int_type = self.int_type_of(&i.left);
+        let semantics = self.options.int_semantics;
+        let result = self
+            .lower_int_op(&op, &target, &value, int_type, semantics)
+            .or_else(|| self.lower_int_division(&op, &target, &value, [&i.left, &i.right]));
+        if let Some(result) = result {
+            self.add_line(&format!("{} = {}", target, result));
+            return Ok(());
+        }
+
+        if compound.ends_with('=') {
+            self.add_line(&format!("{} {} {}", target, compound, value));
+        } else {
+            self.add_line(&format!(
+                "{} = bit32.{}({}, {})",
+                target, compound, target, value
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn visit_expr_if(&mut self, i: &syn::ExprIf) -> Lowered {
+        self.emit_if_chain(i, BlockTail::Discard)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_match(&mut self, i: &syn::ExprMatch) -> Lowered {
+        self.lower_match(i, BlockTail::Discard)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_for_loop(&mut self, i: &syn::ExprForLoop) -> Lowered {
+        let parts = self.begin_concat_parts(&i.body, i.span())?;
+        self.emit_for_loop(i)?;
+        self.end_concat_parts(parts);
+
+        Ok(())
+    }
+
+    fn visit_expr_while(&mut self, i: &syn::ExprWhile) -> Lowered {
+        let parts = self.begin_concat_parts(&i.body, i.span())?;
+        self.emit_while(i)?;
+        self.end_concat_parts(parts);
+
+        Ok(())
+    }
+
+    fn visit_expr_loop(&mut self, i: &syn::ExprLoop) -> Lowered {
+        self.lower_loop(i, LoopResult::Discard)?;
+
+        Ok(())
+    }
+
+    fn visit_expr_break(&mut self, i: &syn::ExprBreak) -> Lowered {
+        let index = self.loop_index(i.label.as_ref(), "break")?;
+
+        if let Some(value) = &i.expr {
+            let result = index.map(|index| self.loops[index].result.clone());
+            match result {
+                Some(LoopResult::Assign(target)) => self.emit_value_into(value, &target)?,
+                // The loop's value is the function's, so the `break` returns it
+                Some(LoopResult::Return) => return self.emit_return_value(value),
+                _ => self.emit_branch_value(value, BlockTail::Discard)?,
+            }
+        }
+
+        self.emit_loop_exit(index, "break")?;
+
+        Ok(())
+    }
+
+    fn visit_expr_continue(&mut self, i: &syn::ExprContinue) -> Lowered {
+        let index = self.loop_index(i.label.as_ref(), "continue")?;
+        self.emit_loop_exit(index, "continue")?;
+
+        Ok(())
     }
 }
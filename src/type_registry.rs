@@ -0,0 +1,1061 @@
+use crate::impl_trait::fn_bounded_params;
+use std::collections::{HashMap, HashSet};
+use syn::visit::Visit;
+
+pub struct StructInfo {
+    pub readonly: bool,
+    pub copy: bool,
+    pub has_methods: bool,
+    pub has_table_methods: bool,
+    pub implements_drop: bool,
+    pub needs_destroy: bool,
+    pub droppable_fields: Vec<String>,
+    pub index: bool,
+    pub serializable: bool,
+    pub buffered: bool,
+    pub generics: Vec<String>,
+    pub item: syn::ItemStruct,
+}
+
+/// A function passed some of its const generic parameters ahead of its arguments, since no
+/// `[T; N]` parameter or field of `self` has them as its length.
+pub struct ConstFn {
+    pub params: Vec<(String, ConstPosition)>,
+    /// The return type, with `Self` written out, which a `let`'s annotation can give the
+    /// parameters from
+    pub output: Option<syn::Type>,
+}
+
+/// Where a call names a const generic argument: among the type's generic arguments for an
+/// `impl`'s parameter, like `Grid::<4>::new()`, or among the function's.
+#[derive(Clone, Copy)]
+pub enum ConstPosition {
+    Type(usize),
+    Fn(usize),
+}
+
+#[derive(Default)]
+pub struct TypeRegistry {
+    pub structs: HashMap<String, StructInfo>,
+    pub generic_fns: HashMap<String, syn::ItemFn>,
+    /// The impls of each generic struct, which `--monomorphize` lowers once per instantiation
+    pub generic_impls: HashMap<String, Vec<syn::ItemImpl>>,
+    pub enums: HashMap<String, Vec<String>>,
+    /// Enums deriving `thiserror::Error`, whose values are their messages
+    pub error_enums: HashMap<String, syn::ItemEnum>,
+    /// Unit enums with explicit discriminants, whose values are numbers
+    pub discriminant_enums: HashSet<String>,
+    /// Enums with a variant that has fields, whose values are tables tagged with the
+    /// variant's name
+    pub data_enums: HashSet<String>,
+    /// The error type names of top-level functions returning a `Result`
+    pub result_errors: HashMap<String, String>,
+    /// How many `Option`s the return types of top-level functions nest, 2 for
+    /// `Option<Option<T>>`
+    pub option_depths: HashMap<String, usize>,
+    /// The names of the types top-level functions return
+    pub fn_outputs: HashMap<String, String>,
+    /// The `T` that top-level functions returning an `Option<T>` or `Result<T, E>` wrap
+    pub wrapped_outputs: HashMap<String, syn::Type>,
+    /// Functions taking const generic parameters, keyed by `name` or `Type::name`
+    pub const_fns: HashMap<String, ConstFn>,
+    pub traits: HashMap<String, syn::ItemTrait>,
+    /// The traits used as trait objects, which get an interface type
+    pub dyn_traits: HashSet<String>,
+    pub trait_impls: Vec<(String, String)>,
+    pub inherent_methods: HashSet<(String, String)>,
+    pub extern_fns: HashMap<String, ExternFn>,
+    pub components: HashSet<String>,
+    pub dict_dispatch: bool,
+}
+
+/// A function declared in an `extern "luau"` block, called through the required module.
+pub struct ExternFn {
+    pub module: String,
+    pub luau_name: String,
+}
+
+impl TypeRegistry {
+    pub fn collect(file: &syn::File) -> Self {
+        let mut collector = Collector::default();
+        collector.visit_file(file);
+        let mut mutations = Mutations::default();
+        mutations.visit_file(file);
+
+        for (type_name, trait_name) in &collector.trait_impls {
+            let mutated_by_default = collector
+                .traits
+                .get(trait_name)
+                .is_some_and(|item| default_methods(item).any(takes_mut_self));
+
+            if mutated_by_default {
+                collector.mutated_by_methods.insert(type_name.clone());
+            }
+        }
+
+        let mut structs: HashMap<String, StructInfo> = collector
+            .structs
+            .iter()
+            .map(|item| {
+                let name = item.ident.to_string();
+                let has_pub_field = item
+                    .fields
+                    .iter()
+                    .any(|field| matches!(field.vis, syn::Visibility::Public(_)));
+                let marked_readonly = item
+                    .attrs
+                    .iter()
+                    .any(|attr| attr.path().is_ident("readonly"));
+                // Private fields can still be written by functions in the same module
+                let mutated = collector.mutated_by_methods.contains(&name)
+                    || mutations.borrowed_mut.contains(&name)
+                    || item.fields.iter().any(|field| {
+                        field.ident.as_ref().is_some_and(|ident| {
+                            mutations.assigned_fields.contains(&ident.to_string())
+                        })
+                    });
+                let readonly = marked_readonly || (!has_pub_field && !mutated);
+                let implements_drop = collector.drop_impls.contains(&name);
+                let serializable = derives(&item.attrs, "LuauSerialize");
+                let buffered = derives(&item.attrs, "LuauBuffer");
+                let generated_methods = serializable || buffered;
+
+                let info = StructInfo {
+                    readonly,
+                    copy: derives(&item.attrs, "Copy"),
+                    has_methods: generated_methods || collector.impl_types.contains(&name),
+                    has_table_methods: generated_methods
+                        || collector.inherent_impl_types.contains(&name)
+                        || collector.trait_impls.iter().any(|(ty, trait_name)| {
+                            *ty == name && !collector.traits.contains_key(trait_name)
+                        }),
+                    implements_drop,
+                    needs_destroy: implements_drop,
+                    droppable_fields: Vec::new(),
+                    index: collector.index_impls.contains(&name),
+                    serializable,
+                    buffered,
+                    generics: type_param_names(&item.generics),
+                    item: item.clone(),
+                };
+
+                (name, info)
+            })
+            .collect();
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+
+            for item in &collector.structs {
+                let name = item.ident.to_string();
+                let droppable_fields: Vec<String> = item
+                    .fields
+                    .iter()
+                    .filter(|field| {
+                        type_name(&field.ty).is_some_and(|ty| match structs.get(&ty) {
+                            Some(info) => info.needs_destroy,
+                            None => is_builtin_droppable(&ty),
+                        })
+                    })
+                    .filter_map(|field| field.ident.as_ref().map(|ident| ident.to_string()))
+                    .collect();
+
+                let info = structs.get_mut(&name).unwrap();
+                if !droppable_fields.is_empty() && !info.needs_destroy {
+                    info.needs_destroy = true;
+                    changed = true;
+                }
+
+                info.droppable_fields = droppable_fields;
+            }
+        }
+
+        let const_fns = collector
+            .const_fns
+            .iter()
+            .filter_map(|(key, impl_params, sig, self_ty)| {
+                let self_struct = self_ty
+                    .as_ref()
+                    .and_then(type_name)
+                    .and_then(|name| collector.structs.iter().find(|item| item.ident == name));
+                let sources = const_param_sources(impl_params, sig, self_struct);
+                let fn_params: Vec<String> = sig
+                    .generics
+                    .params
+                    .iter()
+                    .map(|param| match param {
+                        syn::GenericParam::Const(param) => param.ident.to_string(),
+                        _ => String::new(),
+                    })
+                    .collect();
+
+                let params: Vec<(String, ConstPosition)> = sources
+                    .into_iter()
+                    .filter(|(_, source)| source.is_none())
+                    .map(|(name, _)| {
+                        let position = match impl_params.iter().find(|(param, _)| *param == name) {
+                            Some((_, index)) => ConstPosition::Type(*index),
+                            None => ConstPosition::Fn(
+                                fn_params.iter().position(|param| *param == name).unwrap(),
+                            ),
+                        };
+                        (name, position)
+                    })
+                    .collect();
+                if params.is_empty() {
+                    return None;
+                }
+
+                let output = match &sig.output {
+                    syn::ReturnType::Type(_, ty) => match (&**ty, self_ty) {
+                        (syn::Type::Path(path), Some(self_ty)) if path.path.is_ident("Self") => {
+                            Some(self_ty.clone())
+                        }
+                        (ty, _) => Some(ty.clone()),
+                    },
+                    syn::ReturnType::Default => None,
+                };
+
+                Some((key.clone(), ConstFn { params, output }))
+            })
+            .collect();
+
+        let mut dyn_traits = DynTraits::default();
+        dyn_traits.visit_file(file);
+
+        let mut generic_impls: HashMap<String, Vec<syn::ItemImpl>> = HashMap::new();
+        for (name, item) in collector.impls {
+            if structs
+                .get(&name)
+                .is_some_and(|info| !info.generics.is_empty())
+            {
+                generic_impls.entry(name).or_default().push(item);
+            }
+        }
+
+        let mut registry = Self {
+            structs,
+            generic_fns: collector.generic_fns,
+            generic_impls,
+            enums: collector.enums,
+            error_enums: collector.error_enums,
+            discriminant_enums: collector.discriminant_enums,
+            data_enums: collector.data_enums,
+            result_errors: collector.result_errors,
+            option_depths: collector.option_depths,
+            fn_outputs: collector.fn_outputs,
+            wrapped_outputs: collector.wrapped_outputs,
+            const_fns,
+            dyn_traits: dyn_traits
+                .names
+                .into_iter()
+                .filter(|name| collector.traits.contains_key(name))
+                .collect(),
+            traits: collector.traits,
+            trait_impls: collector.trait_impls,
+            inherent_methods: collector.inherent_methods,
+            extern_fns: HashMap::new(),
+            components: collector.components,
+            dict_dispatch: false,
+        };
+
+        for item in &collector.foreign_mods {
+            // A malformed block is reported when the transpiler requires its module
+            let Ok(Some((module, _))) = registry.extern_module(item) else {
+                continue;
+            };
+
+            for item in &item.items {
+                if let syn::ForeignItem::Fn(item_fn) = item {
+                    let name = item_fn.sig.ident.to_string();
+                    let luau_name = link_name(&item_fn.attrs).unwrap_or_else(|| name.clone());
+
+                    registry.extern_fns.insert(
+                        name,
+                        ExternFn {
+                            module: module.clone(),
+                            luau_name,
+                        },
+                    );
+                }
+            }
+        }
+
+        registry
+    }
+
+    /// The local name and `require` path of an `extern "luau"` block marked
+    /// `#[require("script.Parent.PlayerData")]`. The local is named after the last path
+    /// segment, with a `Module` suffix if a type of the same name is declared in the file.
+    pub fn extern_module(
+        &self,
+        item: &syn::ItemForeignMod,
+    ) -> Result<Option<(String, String)>, String> {
+        let Some((module, path)) = luau_module(item)? else {
+            return Ok(None);
+        };
+        let is_taken = self.structs.contains_key(&module)
+            || self.enums.contains_key(&module)
+            || self.traits.contains_key(&module);
+
+        if is_taken {
+            Ok(Some((format!("{}Module", module), path)))
+        } else {
+            Ok(Some((module, path)))
+        }
+    }
+
+    pub fn is_struct(&self, name: &str) -> bool {
+        self.structs.contains_key(name)
+    }
+
+    pub fn is_generic_struct(&self, name: &str) -> bool {
+        self.structs
+            .get(name)
+            .is_some_and(|info| !info.generics.is_empty())
+    }
+
+    pub fn is_readonly(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| info.readonly)
+    }
+
+    pub fn is_copy(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| info.copy)
+    }
+
+    /// A single-field tuple struct without methods is erased to its inner value, so
+    /// `Meters(x)` is just `x`. One with methods stays a table, which they're called on.
+    pub fn is_newtype(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| {
+            !info.has_methods
+                && !info.needs_destroy
+                && matches!(&info.item.fields, syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1)
+        })
+    }
+
+    pub fn is_unit_struct(&self, name: &str) -> bool {
+        self.structs
+            .get(name)
+            .is_some_and(|info| matches!(info.item.fields, syn::Fields::Unit))
+    }
+
+    /// With dictionary dispatch, methods of user-defined traits live on their own
+    /// dictionary tables, so only inherent and built-in trait methods need a method table.
+    /// Files with a `#[component]` function are lowered against React-lua.
+    pub fn uses_react(&self) -> bool {
+        !self.components.is_empty()
+    }
+
+    pub fn is_serializable(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| info.serializable)
+    }
+
+    pub fn is_buffered(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| info.buffered)
+    }
+
+    pub fn has_methods(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| {
+            let has_methods = if self.dict_dispatch {
+                info.has_table_methods
+            } else {
+                info.has_methods
+            };
+
+            has_methods || info.needs_destroy
+        })
+    }
+
+    pub fn implemented_trait(&self, type_name: &str, method: &str) -> Option<&str> {
+        if self
+            .inherent_methods
+            .contains(&(type_name.to_string(), method.to_string()))
+        {
+            return None;
+        }
+
+        self.trait_impls
+            .iter()
+            .filter(|(ty, _)| ty == type_name)
+            .map(|(_, trait_name)| trait_name.as_str())
+            .find(|trait_name| self.trait_declares(trait_name, method))
+    }
+
+    /// Whether the values of a data enum get its methods through a metatable.
+    pub fn data_enum_has_methods(&self, name: &str) -> bool {
+        self.is_data_enum(name)
+            && (self.trait_impls.iter().any(|(ty, _)| ty == name)
+                || self.inherent_methods.iter().any(|(ty, _)| ty == name))
+    }
+
+    /// Types declared outside this file (Roblox or std types) that still have `impl` blocks;
+    /// their methods live on a separate extension table instead of the foreign type itself.
+    pub fn is_extended(&self, name: &str) -> bool {
+        !self.structs.contains_key(name)
+            && !self.enums.contains_key(name)
+            && (self.trait_impls.iter().any(|(ty, _)| ty == name)
+                || self.inherent_methods.iter().any(|(ty, _)| ty == name))
+    }
+
+    pub fn is_extension_method(&self, type_name: &str, method: &str) -> bool {
+        self.is_extended(type_name)
+            && (self
+                .inherent_methods
+                .contains(&(type_name.to_string(), method.to_string()))
+                || self.implemented_trait(type_name, method).is_some())
+    }
+
+    pub fn trait_declares(&self, trait_name: &str, method: &str) -> bool {
+        self.traits.get(trait_name).is_some_and(|item| {
+            item.items.iter().any(
+                |item| matches!(item, syn::TraitItem::Fn(trait_fn) if trait_fn.sig.ident == method),
+            )
+        })
+    }
+
+    pub fn needs_destroy(&self, name: &str) -> bool {
+        match self.structs.get(name) {
+            Some(info) => info.needs_destroy,
+            None => is_builtin_droppable(name),
+        }
+    }
+
+    /// The cleanup call for a value of this type: user types get a generated `:destroy()`,
+    /// the bundled `Maid` its Janitor-style `:Destroy()`.
+    pub fn destroy_method(&self, name: Option<&str>) -> &'static str {
+        match name {
+            Some(name) if !self.structs.contains_key(name) && is_builtin_droppable(name) => {
+                "Destroy"
+            }
+            _ => "destroy",
+        }
+    }
+
+    pub fn implements_index(&self, name: &str) -> bool {
+        self.structs.get(name).is_some_and(|info| info.index)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&StructInfo> {
+        self.structs.get(name)
+    }
+
+    pub fn is_enum(&self, name: &str) -> bool {
+        self.enums.contains_key(name)
+    }
+
+    pub fn is_error_enum(&self, name: &str) -> bool {
+        self.error_enums.contains_key(name)
+    }
+
+    pub fn has_discriminants(&self, name: &str) -> bool {
+        self.discriminant_enums.contains(name)
+    }
+
+    pub fn is_data_enum(&self, name: &str) -> bool {
+        self.data_enums.contains(name)
+    }
+
+    /// The variant of an error enum that `#[from]` converts `source` into, for `?`.
+    pub fn conversion_variant(&self, name: &str, source: &str) -> Option<String> {
+        self.error_enums
+            .get(name)?
+            .variants
+            .iter()
+            .find_map(|variant| {
+                let field = variant.fields.iter().next()?;
+                let is_from = field.attrs.iter().any(|attr| attr.path().is_ident("from"));
+                (is_from && type_name(&field.ty).as_deref() == Some(source))
+                    .then(|| variant.ident.to_string())
+            })
+    }
+
+    pub fn is_dyn_trait(&self, trait_name: &str) -> bool {
+        self.dyn_traits.contains(trait_name)
+    }
+
+    pub fn has_default_methods(&self, trait_name: &str) -> bool {
+        self.traits
+            .get(trait_name)
+            .is_some_and(|item| default_methods(item).next().is_some())
+    }
+
+    pub fn enum_variants(&self, name: &str) -> Option<&[String]> {
+        self.enums.get(name).map(|variants| variants.as_slice())
+    }
+}
+
+pub fn default_methods(item: &syn::ItemTrait) -> impl Iterator<Item = &syn::TraitItemFn> {
+    item.items.iter().filter_map(|item| match item {
+        syn::TraitItem::Fn(method) if method.default.is_some() => Some(method),
+        _ => None,
+    })
+}
+
+fn takes_mut_self(method: &syn::TraitItemFn) -> bool {
+    method
+        .sig
+        .receiver()
+        .is_some_and(|receiver| receiver.mutability.is_some())
+}
+
+/// The name of the error type of a `Result<T, E>`.
+/// The `T` of an `Option<T>` or a `Result<T, E>`.
+pub fn wrapped_value(ty: &syn::Type) -> Option<&syn::Type> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" && segment.ident != "Result" {
+        return None;
+    }
+
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args.iter().find_map(|arg| match arg {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    })
+}
+
+pub fn result_error(ty: &syn::Type) -> Option<String> {
+    let syn::Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Result" {
+        return None;
+    }
+
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+    args.args
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => type_name(ty),
+            _ => None,
+        })
+        .nth(1)
+}
+
+/// The const generic parameters of an `impl`, with their positions among the arguments of its
+/// type, like `N` at 0 in `impl<const N: usize> Grid<N>`.
+pub fn impl_const_params(item: &syn::ItemImpl) -> Vec<(String, usize)> {
+    item.generics
+        .const_params()
+        .filter_map(|param| {
+            let index = type_args(&item.self_ty)
+                .iter()
+                .position(|arg| generic_arg_is(arg, &param.ident))?;
+            Some((param.ident.to_string(), index))
+        })
+        .collect()
+}
+
+/// Where each const generic parameter of a function, its `impl`'s first, is read from inside
+/// it: the length of a `[T; N]` parameter or of a field of `self`, or `None` for one passed
+/// ahead of its arguments.
+pub fn const_param_sources(
+    impl_params: &[(String, usize)],
+    sig: &syn::Signature,
+    self_struct: Option<&syn::ItemStruct>,
+) -> Vec<(String, Option<String>)> {
+    let params = impl_params
+        .iter()
+        .map(|(name, index)| (name.clone(), Some(*index)))
+        .chain(
+            sig.generics
+                .const_params()
+                .map(|param| (param.ident.to_string(), None)),
+        );
+
+    params
+        .map(|(name, impl_index)| {
+            let from_param = sig.inputs.iter().find_map(|input| {
+                let syn::FnArg::Typed(pat_type) = input else {
+                    return None;
+                };
+                let syn::Pat::Ident(pat_ident) = &*pat_type.pat else {
+                    return None;
+                };
+                (array_length(&pat_type.ty)? == name).then(|| format!("#{}", pat_ident.ident))
+            });
+
+            // The struct may call the parameter something else than the `impl` does
+            let struct_param = impl_index.zip(self_struct).and_then(|(index, item)| {
+                item.generics
+                    .params
+                    .iter()
+                    .nth(index)
+                    .and_then(|param| match param {
+                        syn::GenericParam::Const(param) => Some(param.ident.to_string()),
+                        _ => None,
+                    })
+                    .map(|param| (param, item))
+            });
+            let from_self =
+                struct_param
+                    .filter(|_| sig.receiver().is_some())
+                    .and_then(|(param, item)| {
+                        item.fields.iter().find_map(|field| {
+                            let ident = field.ident.as_ref()?;
+                            (array_length(&field.ty)? == param).then(|| format!("#self.{}", ident))
+                        })
+                    });
+
+            (name, from_param.or(from_self))
+        })
+        .collect()
+}
+
+/// The const generic parameter an array type `[T; N]`, or a reference to one, is as long as.
+fn array_length(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Array(array) => match &array.len {
+            syn::Expr::Path(path) => path.path.get_ident().map(|ident| ident.to_string()),
+            _ => None,
+        },
+        syn::Type::Reference(reference) => array_length(&reference.elem),
+        syn::Type::Paren(paren) => array_length(&paren.elem),
+        _ => None,
+    }
+}
+
+fn type_args(ty: &syn::Type) -> Vec<&syn::GenericArgument> {
+    let syn::Type::Path(type_path) = ty else {
+        return Vec::new();
+    };
+    match type_path
+        .path
+        .segments
+        .last()
+        .map(|segment| &segment.arguments)
+    {
+        Some(syn::PathArguments::AngleBracketed(args)) => args.args.iter().collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Whether a generic argument is just `ident`, which parses as a type unless braced.
+fn generic_arg_is(arg: &syn::GenericArgument, ident: &syn::Ident) -> bool {
+    match arg {
+        syn::GenericArgument::Type(syn::Type::Path(path)) => path.path.is_ident(ident),
+        syn::GenericArgument::Const(syn::Expr::Path(path)) => path.path.is_ident(ident),
+        syn::GenericArgument::Const(syn::Expr::Block(block)) => matches!(
+            block.block.stmts.as_slice(),
+            [syn::Stmt::Expr(syn::Expr::Path(path), None)] if path.path.is_ident(ident)
+        ),
+        _ => false,
+    }
+}
+
+/// Whether `ty` is locked map state like `Arc<RwLock<HashMap<K, V>>>`, which actor scripts
+/// keep in a `SharedTable`.
+pub fn is_shared_table_type(ty: &syn::Type) -> bool {
+    let ty = match ty {
+        syn::Type::Reference(reference) => &reference.elem,
+        ty => ty,
+    };
+    let is = |ty: &syn::Type, names: &[&str]| {
+        type_name(ty).is_some_and(|name| names.contains(&name.as_str()))
+    };
+    let inner = |ty: &syn::Type| match type_args(ty).first() {
+        Some(syn::GenericArgument::Type(inner)) => Some(inner.clone()),
+        _ => None,
+    };
+
+    is(ty, &["Arc"])
+        && inner(ty).is_some_and(|lock| {
+            is(&lock, &["RwLock", "Mutex"])
+                && inner(&lock).is_some_and(|map| is(&map, &["HashMap", "BTreeMap"]))
+        })
+}
+
+/// How many `Option`s `ty` nests, 0 for a type that isn't one.
+pub fn option_depth(ty: &syn::Type) -> usize {
+    let syn::Type::Path(type_path) = ty else {
+        return 0;
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return 0;
+    };
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return 0;
+    };
+    if segment.ident != "Option" {
+        return 0;
+    }
+
+    match args.args.first() {
+        Some(syn::GenericArgument::Type(inner)) => 1 + option_depth(inner),
+        _ => 1,
+    }
+}
+
+pub fn derives(attrs: &[syn::Attribute], derive: &str) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("derive"))
+        .any(|attr| {
+            let mut found = false;
+            let _ = attr.parse_nested_meta(|meta| {
+                found |= meta
+                    .path
+                    .segments
+                    .last()
+                    .is_some_and(|segment| segment.ident == derive);
+                Ok(())
+            });
+
+            found
+        })
+}
+
+fn luau_module(item: &syn::ItemForeignMod) -> Result<Option<(String, String)>, String> {
+    if item
+        .abi
+        .name
+        .as_ref()
+        .is_none_or(|name| name.value() != "luau")
+    {
+        return Ok(None);
+    }
+
+    let attr = item
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("require"))
+        .ok_or_else(|| {
+            "extern \"luau\" blocks need a #[require(\"...\")] attribute naming the module"
+                .to_string()
+        })?;
+    let path = attr
+        .parse_args::<syn::LitStr>()
+        .map_err(|_| "#[require] expects a single string literal".to_string())?
+        .value();
+
+    let module: String = path
+        .rsplit(['.', ':', '/'])
+        .next()
+        .unwrap()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_')
+        .collect();
+
+    if module.is_empty() || module.starts_with(|c: char| c.is_ascii_digit()) {
+        return Err(format!(
+            "Cannot name a local for the Luau module `{}`",
+            path
+        ));
+    }
+
+    Ok(Some((module, path)))
+}
+
+fn link_name(attrs: &[syn::Attribute]) -> Option<String> {
+    attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(meta) if meta.path.is_ident("link_name") => match &meta.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(name),
+                ..
+            }) => Some(name.value()),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+/// The names of the type parameters that are Luau generics, which those bounded by a closure
+/// trait aren't: they are typed as their function type.
+pub fn type_param_names(generics: &syn::Generics) -> Vec<String> {
+    let function_params = fn_bounded_params(generics);
+    generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .filter(|name| !function_params.iter().any(|(param, _)| param == name))
+        .collect()
+}
+
+fn is_builtin_droppable(name: &str) -> bool {
+    name == "Maid"
+}
+
+pub fn type_name(ty: &syn::Type) -> Option<String> {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+
+        _ => None,
+    }
+}
+
+/// The names of the traits `dyn` types anywhere in a file are objects of.
+#[derive(Default)]
+struct DynTraits {
+    names: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for DynTraits {
+    fn visit_type_trait_object(&mut self, i: &'ast syn::TypeTraitObject) {
+        for bound in &i.bounds {
+            if let syn::TypeParamBound::Trait(trait_bound) = bound {
+                if let Some(segment) = trait_bound.path.segments.last() {
+                    self.names.insert(segment.ident.to_string());
+                }
+            }
+        }
+
+        syn::visit::visit_type_trait_object(self, i);
+    }
+}
+
+/// The types borrowed as `&mut T` and the names of fields assigned or mutably borrowed
+/// anywhere in a file, which keep a struct with private fields from being frozen.
+#[derive(Default)]
+struct Mutations {
+    borrowed_mut: HashSet<String>,
+    assigned_fields: HashSet<String>,
+}
+
+impl Mutations {
+    fn record_place(&mut self, place: &syn::Expr) {
+        match place {
+            syn::Expr::Field(field) => {
+                if let syn::Member::Named(ident) = &field.member {
+                    self.assigned_fields.insert(ident.to_string());
+                }
+                self.record_place(&field.base);
+            }
+            syn::Expr::Index(index) => self.record_place(&index.expr),
+            syn::Expr::Paren(paren) => self.record_place(&paren.expr),
+            _ => {}
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Mutations {
+    fn visit_type_reference(&mut self, i: &'ast syn::TypeReference) {
+        if i.mutability.is_some() {
+            if let Some(name) = type_name(&i.elem) {
+                self.borrowed_mut.insert(name);
+            }
+        }
+
+        syn::visit::visit_type_reference(self, i);
+    }
+
+    fn visit_expr_assign(&mut self, i: &'ast syn::ExprAssign) {
+        self.record_place(&i.left);
+        syn::visit::visit_expr_assign(self, i);
+    }
+
+    fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+        if is_compound_assign(&i.op) {
+            self.record_place(&i.left);
+        }
+
+        syn::visit::visit_expr_binary(self, i);
+    }
+
+    fn visit_expr_reference(&mut self, i: &'ast syn::ExprReference) {
+        if i.mutability.is_some() {
+            self.record_place(&i.expr);
+        }
+
+        syn::visit::visit_expr_reference(self, i);
+    }
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    matches!(
+        op,
+        syn::BinOp::AddAssign(_)
+            | syn::BinOp::SubAssign(_)
+            | syn::BinOp::MulAssign(_)
+            | syn::BinOp::DivAssign(_)
+            | syn::BinOp::RemAssign(_)
+            | syn::BinOp::BitXorAssign(_)
+            | syn::BinOp::BitAndAssign(_)
+            | syn::BinOp::BitOrAssign(_)
+            | syn::BinOp::ShlAssign(_)
+            | syn::BinOp::ShrAssign(_)
+    )
+}
+
+#[derive(Default)]
+struct Collector {
+    structs: Vec<syn::ItemStruct>,
+    impl_types: HashSet<String>,
+    drop_impls: HashSet<String>,
+    index_impls: HashSet<String>,
+    mutated_by_methods: HashSet<String>,
+    generic_fns: HashMap<String, syn::ItemFn>,
+    impls: Vec<(String, syn::ItemImpl)>,
+    enums: HashMap<String, Vec<String>>,
+    error_enums: HashMap<String, syn::ItemEnum>,
+    discriminant_enums: HashSet<String>,
+    data_enums: HashSet<String>,
+    result_errors: HashMap<String, String>,
+    option_depths: HashMap<String, usize>,
+    fn_outputs: HashMap<String, String>,
+    wrapped_outputs: HashMap<String, syn::Type>,
+    /// Each function with const generic parameters, keyed like `TypeRegistry::const_fns`,
+    /// with its `impl`'s parameters and type
+    #[allow(clippy::type_complexity)]
+    const_fns: Vec<(
+        String,
+        Vec<(String, usize)>,
+        syn::Signature,
+        Option<syn::Type>,
+    )>,
+    traits: HashMap<String, syn::ItemTrait>,
+    trait_impls: Vec<(String, String)>,
+    inherent_impl_types: HashSet<String>,
+    inherent_methods: HashSet<(String, String)>,
+    foreign_mods: Vec<syn::ItemForeignMod>,
+    components: HashSet<String>,
+}
+
+impl<'ast> Visit<'ast> for Collector {
+    fn visit_item_struct(&mut self, i: &'ast syn::ItemStruct) {
+        self.structs.push(i.clone());
+    }
+
+    fn visit_item_enum(&mut self, i: &'ast syn::ItemEnum) {
+        let variants = i
+            .variants
+            .iter()
+            .map(|variant| variant.ident.to_string())
+            .collect();
+
+        self.enums.insert(i.ident.to_string(), variants);
+        if derives(&i.attrs, "Error") {
+            self.error_enums.insert(i.ident.to_string(), i.clone());
+        } else if i
+            .variants
+            .iter()
+            .any(|variant| variant.discriminant.is_some())
+        {
+            self.discriminant_enums.insert(i.ident.to_string());
+        } else if i
+            .variants
+            .iter()
+            .any(|variant| !matches!(variant.fields, syn::Fields::Unit))
+        {
+            self.data_enums.insert(i.ident.to_string());
+        }
+    }
+
+    fn visit_item_trait(&mut self, i: &'ast syn::ItemTrait) {
+        self.traits.insert(i.ident.to_string(), i.clone());
+    }
+
+    fn visit_item_foreign_mod(&mut self, i: &'ast syn::ItemForeignMod) {
+        self.foreign_mods.push(i.clone());
+    }
+
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if !type_param_names(&i.sig.generics).is_empty() {
+            self.generic_fns.insert(i.sig.ident.to_string(), i.clone());
+        }
+
+        if i.attrs.iter().any(|attr| attr.path().is_ident("component")) {
+            self.components.insert(i.sig.ident.to_string());
+        }
+
+        if let syn::ReturnType::Type(_, output) = &i.sig.output {
+            if let Some(error) = result_error(output) {
+                self.result_errors.insert(i.sig.ident.to_string(), error);
+            }
+
+            let depth = option_depth(output);
+            if depth > 0 {
+                self.option_depths.insert(i.sig.ident.to_string(), depth);
+            }
+
+            if let Some(name) = type_name(output) {
+                self.fn_outputs.insert(i.sig.ident.to_string(), name);
+            }
+
+            if let Some(value) = wrapped_value(output) {
+                self.wrapped_outputs
+                    .insert(i.sig.ident.to_string(), value.clone());
+            }
+        }
+
+        if i.sig.generics.const_params().next().is_some() {
+            self.const_fns
+                .push((i.sig.ident.to_string(), Vec::new(), i.sig.clone(), None));
+        }
+    }
+
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        let Some(name) = type_name(&i.self_ty) else {
+            return;
+        };
+
+        let trait_name = i
+            .trait_
+            .as_ref()
+            .and_then(|(_, path, _)| path.segments.last())
+            .map(|segment| segment.ident.to_string());
+        let is_drop = trait_name.as_deref() == Some("Drop");
+
+        let takes_mut_self = i.items.iter().any(|item| {
+            matches!(
+                item,
+                syn::ImplItem::Fn(method)
+                    if method.sig.receiver().is_some_and(|receiver| receiver.mutability.is_some())
+            )
+        });
+
+        if is_drop {
+            self.drop_impls.insert(name.clone());
+        }
+
+        match &trait_name {
+            Some(trait_name) => self.trait_impls.push((name.clone(), trait_name.clone())),
+
+            None => {
+                self.inherent_impl_types.insert(name.clone());
+
+                for item in &i.items {
+                    if let syn::ImplItem::Fn(method) = item {
+                        self.inherent_methods
+                            .insert((name.clone(), method.sig.ident.to_string()));
+                    }
+                }
+            }
+        }
+
+        if matches!(trait_name.as_deref(), Some("Index") | Some("IndexMut")) {
+            self.index_impls.insert(name.clone());
+        }
+
+        let impl_params = impl_const_params(i);
+        for item in &i.items {
+            if let syn::ImplItem::Fn(method) = item {
+                if !impl_params.is_empty() || method.sig.generics.const_params().next().is_some() {
+                    self.const_fns.push((
+                        format!("{}::{}", name, method.sig.ident),
+                        impl_params.clone(),
+                        method.sig.clone(),
+                        Some((*i.self_ty).clone()),
+                    ));
+                }
+            }
+        }
+
+        if takes_mut_self {
+            self.mutated_by_methods.insert(name.clone());
+        }
+
+        self.impls.push((name.clone(), i.clone()));
+        self.impl_types.insert(name);
+    }
+}
@@ -0,0 +1,119 @@
+use roblox_rs::diagnostics::{self, Diagnostic, MessageFormat, Severity};
+use roblox_rs::Transpiled;
+use std::path::Path;
+use std::process::Command;
+
+/// The checker `--typecheck` runs when it isn't given one.
+const LUAU_ANALYZE: &str = "luau-analyze";
+
+/// Runs `command`, `luau-analyze` or a command line like `luau-lsp analyze --defs=...`, on
+/// the output in strict mode. Returns its errors and warnings, at the Rust line each came
+/// from through the source map, and whether any was an error.
+pub fn typecheck(
+    command: &str,
+    file: &str,
+    transpiled: &Transpiled,
+    format: MessageFormat,
+) -> (Vec<String>, bool) {
+    let stem = Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let path = std::env::temp_dir().join(format!(
+        "roblox-rs-typecheck-{}-{}.luau",
+        std::process::id(),
+        stem
+    ));
+    std::fs::write(&path, format!("--!strict\n{}", transpiled.output))
+        .expect("Failed to write the output to typecheck");
+
+    let mut words = command.split_whitespace();
+    let program = words.next().unwrap_or(LUAU_ANALYZE);
+    let result = Command::new(program).args(words).arg(&path).output();
+    let _ = std::fs::remove_file(&path);
+
+    let checked = result.unwrap_or_else(|error| {
+        panic!(
+            "Failed to run `{}` for `--typecheck`: {}; install luau-analyze from https://github.com/luau-lang/luau/releases or pass the checker to run with `--typecheck=COMMAND`",
+            program, error
+        )
+    });
+
+    // Each problem is reported as `path(line,column): Kind: message`
+    let path = path.to_string_lossy();
+    let reports = String::from_utf8_lossy(&checked.stdout).to_string()
+        + &String::from_utf8_lossy(&checked.stderr);
+    let mut failed = false;
+    let mut messages = Vec::new();
+
+    for report in reports.lines() {
+        let Some((line, column, kind, message)) =
+            report.strip_prefix(path.as_ref()).and_then(parse_report)
+        else {
+            continue;
+        };
+
+        let severity = if kind.ends_with("Error") {
+            failed = true;
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+
+        // The `--!strict` line comes first
+        let luau_line = line.saturating_sub(1);
+        let message = format!(
+            "{}: {}, at {}:{} of the output",
+            kind, message, luau_line, column
+        );
+        let rust_line = transpiled
+            .source_map
+            .get(luau_line.wrapping_sub(1))
+            .copied()
+            .flatten();
+
+        messages.push(match rust_line {
+            Some(rust_line) => {
+                let diagnostic = Diagnostic {
+                    severity,
+                    code: "typecheck",
+                    message,
+                    line: rust_line,
+                    column: 1,
+                    end_line: rust_line,
+                    end_column: 1,
+                    bytes: 0..0,
+                };
+                match format {
+                    MessageFormat::Human => diagnostic.render(file),
+                    MessageFormat::Json => diagnostic.to_json(file),
+                }
+            }
+            None => match format {
+                MessageFormat::Human => {
+                    format!("{}[typecheck]: {}\n  --> {}", severity, message, file)
+                }
+                MessageFormat::Json => diagnostics::error_json(&format!("{}: {}", file, message)),
+            },
+        });
+    }
+
+    if !checked.status.success() && !failed {
+        failed = true;
+        messages.push(format!(
+            "error[typecheck]: `{}` failed without reporting a problem\n  --> {}",
+            command, file
+        ));
+    }
+
+    (messages, failed)
+}
+
+/// Parses `(12,5): TypeError: Type 'string' could not be converted into 'number'`.
+fn parse_report(report: &str) -> Option<(usize, usize, &str, &str)> {
+    let (location, rest) = report.strip_prefix('(')?.split_once("): ")?;
+    let (line, column) = location.split_once(',')?;
+    let (kind, message) = rest.split_once(": ")?;
+
+    Some((line.parse().ok()?, column.parse().ok()?, kind, message))
+}
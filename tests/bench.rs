@@ -0,0 +1,45 @@
+//! Runs the `bench` subcommand without Lune installed and checks it falls back to the
+//! embedded Luau VM, or reports that it can't, rather than panicking.
+
+use std::process::Command;
+
+#[test]
+fn missing_lune() {
+    let dir = std::env::temp_dir().join(format!("roblox-rs-bench-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("sums.rs");
+    std::fs::write(
+        &file,
+        "#[bench]\nfn sum() {\n    let mut total = 0;\n    for i in 0..100 {\n        total += i;\n    }\n}\n",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_roblox-rs"))
+        .arg("bench")
+        .arg(&file)
+        .args(["--opt-levels", "0", "--time", "0.01"])
+        .args(["--lune", "roblox-rs-missing-lune"])
+        .output()
+        .expect("failed to run roblox-rs");
+    std::fs::remove_dir_all(&dir).ok();
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+
+    if cfg!(feature = "luau") {
+        assert!(output.status.success(), "{}", stderr);
+        assert!(
+            stdout.lines().any(|line| line.starts_with("sum ")),
+            "{}",
+            stdout
+        );
+    } else {
+        assert_eq!(output.status.code(), Some(1), "{}", stderr);
+        assert!(
+            stderr.starts_with("error: `roblox-rs-missing-lune` wasn't found"),
+            "{}",
+            stderr
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Transpiles programs against a small `bindings.json` and checks calls on bound Roblox
+//! values lower to the Luau API however the value's type is reached.
+
+use roblox_rs::options::TranspilerOptions;
+use roblox_rs::roblox_api::RobloxApi;
+use std::sync::Arc;
+
+const BINDINGS: &str = r#"{
+    "classes": {
+        "Instance": {
+            "superclass": null,
+            "members": {
+                "destroy": { "kind": "method", "name": "Destroy" },
+                "find_first_child": { "kind": "method", "name": "FindFirstChild", "type": "Instance" },
+                "name": { "kind": "getter", "name": "Name" },
+                "parent": { "kind": "getter", "name": "Parent", "type": "Instance" }
+            }
+        },
+        "DataModel": { "superclass": "Instance", "members": {} }
+    },
+    "enums": [],
+    "globals": { "game": "DataModel" },
+    "functions": {}
+}"#;
+
+fn transpile(source: &str) -> String {
+    let options = TranspilerOptions {
+        roblox_api: Some(Arc::new(RobloxApi::from_json(BINDINGS))),
+        ..Default::default()
+    };
+
+    roblox_rs::transpile_source("test.rs", source, options)
+        .unwrap_or_else(|error| panic!("failed to transpile: {}", error))
+        .output
+}
+
+#[test]
+fn inferred_let_keeps_bound_type() {
+    let output = transpile(
+        r#"
+        fn main() {
+            let g = game();
+            g.destroy();
+        }
+        "#,
+    );
+
+    assert!(output.contains("g:Destroy()"), "{}", output);
+}
+
+#[test]
+fn unwrap_of_bound_class() {
+    let output = transpile(
+        r#"
+        fn main() {
+            let part = game().find_first_child("Part").unwrap();
+            part.destroy();
+            let other = game().find_first_child("Other").expect("missing");
+            println!("{}", other.name());
+        }
+        "#,
+    );
+
+    assert!(!output.contains("):unwrap("), "{}", output);
+    assert!(!output.contains("):expect("), "{}", output);
+    assert!(output.contains("error(\"missing\")"), "{}", output);
+    assert!(output.contains("part:Destroy()"), "{}", output);
+    assert!(output.contains("other.Name"), "{}", output);
+}
+
+#[test]
+fn pattern_bindings_keep_bound_type() {
+    let output = transpile(
+        r#"
+        fn main() {
+            let p = game().find_first_child("Part");
+            if let Some(part) = p {
+                part.destroy();
+            }
+            match game().parent() {
+                Some(parent) => parent.destroy(),
+                None => {}
+            }
+            let Some(child) = game().find_first_child("Child") else {
+                return;
+            };
+            child.destroy();
+        }
+        "#,
+    );
+
+    assert!(output.contains("part:Destroy()"), "{}", output);
+    assert!(output.contains("parent:Destroy()"), "{}", output);
+    assert!(output.contains("child:Destroy()"), "{}", output);
+}
@@ -0,0 +1,77 @@
+//! Runs programs building and reading collections in the embedded Luau VM and checks they
+//! print what `rustc` builds of them do.
+
+mod common;
+
+use common::{run_luau, run_rustc};
+
+fn assert_matches_rustc(source: &str) {
+    assert_eq!(run_luau(source, Default::default()), run_rustc(source));
+}
+
+#[test]
+fn vec_push_and_pop() {
+    assert_matches_rustc(
+        r#"
+        fn fill(items: &mut Vec<i32>, count: i32) {
+            for i in 0..count {
+                items.push(i * 10);
+            }
+        }
+
+        fn main() {
+            let mut sized = Vec::with_capacity(4);
+            fill(&mut sized, 3);
+            let mut literal = vec![1, 2];
+            literal.push(3);
+            let mut empty: Vec<i32> = Vec::new();
+
+            println!("{} {}", sized.len(), literal.len());
+            match sized.pop() {
+                Some(last) => println!("popped {}", last),
+                None => println!("empty"),
+            }
+            literal.pop();
+            println!("{} {} {}", sized.len(), literal.len(), empty.pop().is_none());
+        }
+        "#,
+    );
+}
+
+#[test]
+fn options_without_none_in_vec() {
+    assert_matches_rustc(
+        r#"
+        fn main() {
+            let flags = vec![Some(false), Some(true)];
+            for flag in &flags {
+                match flag {
+                    Some(set) => println!("{}", set),
+                    None => println!("none"),
+                }
+            }
+            println!("{}", flags.len());
+        }
+        "#,
+    );
+}
+
+#[test]
+fn none_in_vec_is_unsupported() {
+    for source in [
+        "fn main() { let v = vec![Some(false), None, Some(true)]; }",
+        "fn main() { let a = [Some(1), None]; }",
+        "fn main() { let x: Option<i32> = None; let v = vec![x]; }",
+        "fn main() { let mut v = vec![Some(1)]; v.push(None); }",
+    ] {
+        let error = roblox_rs::transpile_source("test.rs", source, Default::default())
+            .err()
+            .unwrap_or_else(|| panic!("transpiled: {}", source));
+
+        assert!(
+            error.to_string().contains("leaves a hole in its table"),
+            "{}",
+            error
+        );
+    }
+}
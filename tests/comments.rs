@@ -0,0 +1,22 @@
+//! Checks comments carried into the output can't end their Luau comment early.
+
+mod common;
+
+use roblox_rs::options::TranspilerOptions;
+
+#[test]
+fn comments_containing_closing_brackets_stay_comments() {
+    let source = r#"
+        //! Indexes like a[b[0]] and ]=] in the file docs
+        /* a block comment about a[b[0]] */
+        fn main() {
+            /* and another about m[i[j]] */
+            println!("done");
+        }
+    "#;
+
+    assert_eq!(
+        common::run_luau(source, TranspilerOptions::default()),
+        ["done"]
+    );
+}
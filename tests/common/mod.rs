@@ -0,0 +1,86 @@
+//! Runs a program both as Luau in the embedded VM and as Rust through `rustc`, so tests can
+//! check the transpiled output prints what the original does, and writes crates for the
+//! subcommands to run on.
+
+// Each test crate uses only some of these
+#![allow(dead_code)]
+
+use roblox_rs::options::{ScriptKind, TranspilerOptions};
+use std::process::Command;
+
+/// The lines `source` prints when transpiled with `options` as a script and run.
+pub fn run_luau(source: &str, options: TranspilerOptions) -> Vec<String> {
+    let options = TranspilerOptions {
+        kind: Some(ScriptKind::Script),
+        ..options
+    };
+    let transpiled = roblox_rs::transpile_source("test.rs", source, options)
+        .unwrap_or_else(|error| panic!("failed to transpile: {}", error));
+
+    let lua = mlua::Lua::new();
+    lua.load(
+        "output = {}
+        print = function(...)
+            local values = table.pack(...)
+            for index = 1, values.n do
+                values[index] = tostring(values[index])
+            end
+            table.insert(output, table.concat(values, '\\t'))
+        end",
+    )
+    .exec()
+    .unwrap();
+    lua.load(&transpiled.output)
+        .exec()
+        .unwrap_or_else(|error| panic!("{}\n\n{}", error, transpiled.output));
+
+    lua.globals().get("output").unwrap()
+}
+
+/// The lines `source` prints when compiled with `rustc` and run.
+pub fn run_rustc(source: &str) -> Vec<String> {
+    let dir = std::env::temp_dir().join(format!(
+        "roblox-rs-test-{}-{:?}",
+        std::process::id(),
+        std::thread::current().id()
+    ));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("main.rs");
+    let binary = dir.join("main");
+    std::fs::write(&file, source).unwrap();
+
+    let compiled = Command::new("rustc")
+        .args(["--edition", "2021", "-A", "warnings", "-o"])
+        .arg(&binary)
+        .arg(&file)
+        .output()
+        .expect("failed to start rustc");
+    assert!(
+        compiled.status.success(),
+        "rustc failed:\n{}",
+        String::from_utf8_lossy(&compiled.stderr)
+    );
+
+    let ran = Command::new(&binary)
+        .output()
+        .expect("failed to run the binary");
+    std::fs::remove_dir_all(&dir).ok();
+    String::from_utf8_lossy(&ran.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+/// Writes `files` into a fresh crate named `name`, returning its directory.
+pub fn write_crate(name: &str, files: &[(&str, &str)]) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("roblox-rs-{}-{}", name, std::process::id()));
+    std::fs::remove_dir_all(&dir).ok();
+
+    for (path, contents) in files {
+        let path = dir.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+
+    dir
+}
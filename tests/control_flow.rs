@@ -0,0 +1,171 @@
+//! Runs programs that leave loops from inside `match` arms in the embedded Luau VM and
+//! checks they print what they do in Rust.
+
+use roblox_rs::options::{ScriptKind, TranspilerOptions};
+
+fn run(source: &str) -> Vec<String> {
+    let options = TranspilerOptions {
+        kind: Some(ScriptKind::Script),
+        ..Default::default()
+    };
+    let transpiled = roblox_rs::transpile_source("test.rs", source, options)
+        .unwrap_or_else(|error| panic!("failed to transpile: {}", error));
+
+    let lua = mlua::Lua::new();
+    lua.load(
+        "output = {}
+        print = function(...)
+            local values = table.pack(...)
+            for index = 1, values.n do
+                values[index] = tostring(values[index])
+            end
+            table.insert(output, table.concat(values, '\\t'))
+        end",
+    )
+    .exec()
+    .unwrap();
+    lua.load(&transpiled.output)
+        .exec()
+        .unwrap_or_else(|error| panic!("{}\n\n{}", error, transpiled.output));
+
+    lua.globals().get("output").unwrap()
+}
+
+#[test]
+fn return_from_match_in_loop() {
+    let output = run(r#"
+        fn find(target: i32) -> i32 {
+            let mut i = 0;
+            loop {
+                match (i * 3 == target, i * 3 > target) {
+                    (true, _) => return i,
+                    (_, true) => return -1,
+                    _ => {}
+                }
+                i += 1;
+            }
+        }
+
+        fn first_even(limit: i32) -> Option<i32> {
+            for n in 1..limit {
+                match n % 2 {
+                    0 => return Some(n * 10),
+                    _ => continue,
+                }
+            }
+            None
+        }
+
+        fn countdown(mut n: i32) -> &'static str {
+            while n > 0 {
+                match n {
+                    3 => return "three",
+                    _ => n -= 1,
+                }
+            }
+            "none"
+        }
+
+        fn main() {
+            println!("{} {} {}", find(9), find(10), find(0));
+            println!("{} {}", first_even(5).unwrap_or(-1), first_even(2).unwrap_or(-1));
+            println!("{} {}", countdown(5), countdown(2));
+        }
+    "#);
+
+    assert_eq!(output, ["3 -1 0", "20 -1", "three none"]);
+}
+
+#[test]
+fn return_from_nested_match_in_loop() {
+    let output = run(r#"
+        fn classify(values: [i32; 4]) -> i32 {
+            for v in values {
+                match v {
+                    0 => continue,
+                    1..=9 => match v % 2 {
+                        0 => return v,
+                        _ => {}
+                    },
+                    _ => return -v,
+                }
+            }
+            0
+        }
+
+        fn main() {
+            println!("{}", classify([0, 3, 4, 50]));
+            println!("{}", classify([1, 3, 20, 2]));
+            println!("{}", classify([0, 1, 3, 5]));
+        }
+    "#);
+
+    assert_eq!(output, ["4", "-20", "0"]);
+}
+
+#[test]
+fn labeled_break_and_continue_from_match() {
+    let output = run(r#"
+        fn main() {
+            let mut total = 0;
+            'outer: for i in 0..5 {
+                for j in 0..5 {
+                    match (i, j) {
+                        (3, _) => break 'outer,
+                        (_, 2) => continue 'outer,
+                        _ => total += 10 * i + j,
+                    }
+                }
+            }
+            println!("{}", total);
+
+            let mut hits = 0;
+            let mut i = 0;
+            'rows: while i < 4 {
+                i += 1;
+                let mut j = 0;
+                loop {
+                    j += 1;
+                    match (i, j) {
+                        (2, 1) => continue 'rows,
+                        (_, 3) => continue 'rows,
+                        _ => hits += 1,
+                    }
+                }
+            }
+            println!("{}", hits);
+        }
+    "#);
+
+    assert_eq!(output, ["63", "6"]);
+}
+
+#[test]
+fn labeled_break_with_value_from_match() {
+    let output = run(r#"
+        fn main() {
+            let mut n = 1;
+            let found = 'search: loop {
+                loop {
+                    match n % 7 {
+                        0 => break 'search n * 2,
+                        _ => n += 1,
+                    }
+                }
+            };
+            println!("{}", found);
+
+            let mut steps = 0;
+            let last = loop {
+                steps += 1;
+                match steps {
+                    4 => break steps * 100,
+                    _ => continue,
+                }
+            };
+            println!("{}", last);
+        }
+    "#);
+
+    assert_eq!(output, ["14", "400"]);
+}
@@ -0,0 +1,22 @@
+//! Runs `format!`-style macros and checks they print what they do when compiled with `rustc`.
+
+mod common;
+
+use roblox_rs::options::TranspilerOptions;
+
+#[test]
+fn precision_from_an_argument_consumes_it() {
+    let source = r#"
+        fn main() {
+            println!("{:.*} then {}", 2, 1.5, "next");
+            let p = 3;
+            println!("{:>8.*}|{}|{:.*}", p, 3.14159, 7, 1, "abc");
+            println!("{:.*} {}", 0, 42, 'x');
+        }
+    "#;
+
+    assert_eq!(
+        common::run_luau(source, TranspilerOptions::default()),
+        common::run_rustc(source)
+    );
+}
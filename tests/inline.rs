@@ -0,0 +1,35 @@
+//! Runs programs whose calls `--opt-level 2` inlines and checks they print what they do
+//! when compiled with `rustc`.
+
+mod common;
+
+use roblox_rs::options::TranspilerOptions;
+
+fn assert_inlined_like_rustc(source: &str) {
+    let options = TranspilerOptions {
+        opt_level: 2,
+        ..Default::default()
+    };
+    assert_eq!(common::run_luau(source, options), common::run_rustc(source));
+}
+
+#[test]
+fn inlined_body_keeps_its_precedence() {
+    assert_inlined_like_rustc(
+        r#"
+        fn twice(x: i32) -> i32 {
+            x + x
+        }
+
+        fn neg(x: i32) -> i32 {
+            -x
+        }
+
+        fn main() {
+            println!("{}", twice(3) * 2);
+            println!("{}", 10 - twice(1));
+            println!("{}", 3 * neg(2));
+        }
+        "#,
+    );
+}
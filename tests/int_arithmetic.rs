@@ -0,0 +1,34 @@
+//! Runs integer arithmetic under the default `--int-semantics exact` and checks it prints
+//! what it does when compiled with `rustc`.
+
+mod common;
+
+use roblox_rs::options::TranspilerOptions;
+
+#[test]
+fn division_remainder_and_float_casts_match_rust() {
+    let source = r#"
+        fn main() {
+            println!("{}", 7 / 2);
+            println!("{}", -7 / 2);
+            println!("{}", -7 % 3);
+            let f = 300.7;
+            println!("{}", f as u8);
+            println!("{}", -2.9 as i32);
+            let n: usize = 17;
+            println!("{}", n / 5);
+            let mut x = 9;
+            x /= 2;
+            println!("{}", x);
+            let mut y: i64 = -9;
+            y %= 4;
+            println!("{}", y);
+            println!("{}", 2.5 / 2.0);
+        }
+    "#;
+
+    assert_eq!(
+        common::run_luau(source, TranspilerOptions::default()),
+        common::run_rustc(source)
+    );
+}
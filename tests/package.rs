@@ -0,0 +1,99 @@
+//! Runs the `package` subcommand on small crates and checks the Wally package it writes.
+
+mod common;
+
+use common::write_crate;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn package(crate_dir: &Path, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_roblox-rs"))
+        .arg("package")
+        .arg(crate_dir)
+        .arg("--out-dir")
+        .arg(crate_dir.join("wally-package"))
+        .arg("--no-cache")
+        .args(args)
+        .output()
+        .expect("failed to run roblox-rs")
+}
+
+const MANIFEST: &str = "[package]\nname = \"geometry\"\nversion = \"0.1.0\"\n";
+
+#[test]
+fn exports_file_modules() {
+    let dir = write_crate(
+        "package-exports",
+        &[
+            ("Cargo.toml", MANIFEST),
+            (
+                "src/lib.rs",
+                "pub mod util;\npub mod shapes;\nmod private;\npub mod inline {\n    pub fn one() -> i32 { 1 }\n}\n",
+            ),
+            ("src/util.rs", "pub fn add(a: i32, b: i32) -> i32 { a + b }\n"),
+            ("src/shapes/mod.rs", "pub mod circle;\n"),
+            ("src/shapes/circle.rs", "pub fn area(r: f64) -> f64 { r * r * 3.14 }\n"),
+            ("src/private.rs", "pub fn hidden() -> i32 { 2 }\n"),
+        ],
+    );
+
+    let output = package(&dir, &["--scope", "test"]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let root = fs::read_to_string(dir.join("wally-package/src/init.luau")).unwrap();
+    let shapes = fs::read_to_string(dir.join("wally-package/src/shapes/init.luau")).unwrap();
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(root.contains("inline = inline,"), "{}", root);
+    assert!(root.contains("util = Util,"), "{}", root);
+    assert!(root.contains("shapes = Shapes,"), "{}", root);
+    assert!(!root.contains("private ="), "{}", root);
+    assert!(shapes.contains("circle = Circle,"), "{}", shapes);
+}
+
+#[test]
+fn missing_scope_is_an_error() {
+    let dir = write_crate(
+        "package-scope",
+        &[
+            ("Cargo.toml", MANIFEST),
+            ("src/lib.rs", "pub fn one() -> i32 { 1 }\n"),
+        ],
+    );
+
+    let output = package(&dir, &[]);
+    fs::remove_dir_all(&dir).ok();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.starts_with("error: A Wally scope is required"),
+        "{}",
+        stderr
+    );
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn binary_crate_is_an_error() {
+    let dir = write_crate(
+        "package-binary",
+        &[("Cargo.toml", MANIFEST), ("src/main.rs", "fn main() {}\n")],
+    );
+
+    let output = package(&dir, &["--scope", "test"]);
+    fs::remove_dir_all(&dir).ok();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(1), "{}", stderr);
+    assert!(
+        stderr.starts_with("error: `package` requires a library crate"),
+        "{}",
+        stderr
+    );
+}
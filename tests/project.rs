@@ -0,0 +1,58 @@
+//! Runs the `check` subcommand on projects with configuration mistakes and checks they're
+//! reported as errors rather than panics.
+
+mod common;
+
+use common::write_crate;
+use std::process::Command;
+
+const MANIFEST: &str = "[package]\nname = \"game\"\nversion = \"0.1.0\"\n";
+
+/// The exit code and stderr of `check` on the crate `files` make up, with `args`.
+fn check(name: &str, files: &[(&str, &str)], args: &[&str]) -> (Option<i32>, String) {
+    let dir = write_crate(name, files);
+    let output = Command::new(env!("CARGO_BIN_EXE_roblox-rs"))
+        .arg("check")
+        .arg("--manifest-path")
+        .arg(dir.join("Cargo.toml"))
+        .args(args)
+        .output()
+        .expect("failed to run roblox-rs");
+    std::fs::remove_dir_all(&dir).ok();
+
+    (
+        output.status.code(),
+        String::from_utf8_lossy(&output.stderr).to_string(),
+    )
+}
+
+#[test]
+fn unknown_member_is_an_error() {
+    let (code, stderr) = check(
+        "project-member",
+        &[("Cargo.toml", MANIFEST), ("src/main.rs", "fn main() {}\n")],
+        &["--package", "missing"],
+    );
+
+    assert_eq!(code, Some(1), "{}", stderr);
+    assert_eq!(stderr, "error: No workspace member named `missing`\n");
+}
+
+#[test]
+fn unknown_metadata_key_is_an_error() {
+    let manifest = format!(
+        "{}\n[package.metadata.roblox-rs]\nopt-levle = 2\n",
+        MANIFEST
+    );
+    let (code, stderr) = check(
+        "project-metadata",
+        &[("Cargo.toml", &manifest), ("src/main.rs", "fn main() {}\n")],
+        &[],
+    );
+
+    assert_eq!(code, Some(1), "{}", stderr);
+    assert_eq!(
+        stderr,
+        "error: Unknown key `opt-levle` in [package.metadata.roblox-rs]\n"
+    );
+}